@@ -1,5 +1,5 @@
 use clap::{App, Arg};
-use easy_fs::{BlockDevice, EasyFileSystem};
+use easy_fs::{block_cache_sync_all, BlockDevice, EasyFileSystem};
 use std::fs::{read_dir, File, OpenOptions};
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::sync::Arc;
@@ -23,6 +23,180 @@ impl BlockDevice for BlockFile {
             .expect("Error when seeking!");
         assert_eq!(file.write(buf).unwrap(), BLOCK_SZ, "Not a complete block!");
     }
+
+    fn flush(&self) -> bool {
+        self.0.lock().unwrap().sync_data().is_ok()
+    }
+}
+
+/// A block file recording whether it has been flushed
+#[cfg(test)]
+struct FlushRecorder {
+    file: BlockFile,
+    flushed: std::sync::atomic::AtomicBool,
+}
+
+#[cfg(test)]
+impl BlockDevice for FlushRecorder {
+    fn read_block(&self, block_id: usize, buf: &mut [u8]) {
+        self.file.read_block(block_id, buf);
+    }
+
+    fn write_block(&self, block_id: usize, buf: &[u8]) {
+        self.file.write_block(block_id, buf);
+    }
+
+    fn flush(&self) -> bool {
+        self.flushed
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        self.file.flush()
+    }
+}
+
+/// A memory block device which cannot flush while `failing` is set
+#[cfg(test)]
+struct FlushFailer {
+    device: easy_fs::MemBlockDevice,
+    failing: std::sync::atomic::AtomicBool,
+}
+
+#[cfg(test)]
+impl BlockDevice for FlushFailer {
+    fn read_block(&self, block_id: usize, buf: &mut [u8]) {
+        self.device.read_block(block_id, buf);
+    }
+
+    fn write_block(&self, block_id: usize, buf: &[u8]) {
+        self.device.write_block(block_id, buf);
+    }
+
+    fn flush(&self) -> bool {
+        !self.failing.load(std::sync::atomic::Ordering::SeqCst)
+    }
+}
+
+/// A memory block device logging the ids of the blocks read from it
+#[cfg(test)]
+struct ReadLog {
+    device: easy_fs::MemBlockDevice,
+    reads: Mutex<Vec<usize>>,
+}
+
+#[cfg(test)]
+impl ReadLog {
+    fn new(blocks: usize) -> Self {
+        Self {
+            device: easy_fs::MemBlockDevice::new(blocks),
+            reads: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Take the ids logged so far
+    fn take(&self) -> Vec<usize> {
+        std::mem::take(&mut self.reads.lock().unwrap())
+    }
+}
+
+#[cfg(test)]
+impl BlockDevice for ReadLog {
+    fn read_block(&self, block_id: usize, buf: &mut [u8]) {
+        self.reads.lock().unwrap().push(block_id);
+        self.device.read_block(block_id, buf);
+    }
+
+    fn write_block(&self, block_id: usize, buf: &[u8]) {
+        self.device.write_block(block_id, buf);
+    }
+}
+
+/// A memory block device logging the ids of the blocks written to it
+#[cfg(test)]
+struct WriteLog {
+    device: easy_fs::MemBlockDevice,
+    writes: Mutex<Vec<usize>>,
+}
+
+#[cfg(test)]
+impl WriteLog {
+    fn new(blocks: usize) -> Self {
+        Self {
+            device: easy_fs::MemBlockDevice::new(blocks),
+            writes: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Take the ids logged so far
+    fn take(&self) -> Vec<usize> {
+        std::mem::take(&mut self.writes.lock().unwrap())
+    }
+}
+
+#[cfg(test)]
+impl BlockDevice for WriteLog {
+    fn read_block(&self, block_id: usize, buf: &mut [u8]) {
+        self.device.read_block(block_id, buf);
+    }
+
+    fn write_block(&self, block_id: usize, buf: &[u8]) {
+        self.writes.lock().unwrap().push(block_id);
+        self.device.write_block(block_id, buf);
+    }
+}
+
+/// A memory block device whose content can be copied as it would be after a crash
+#[cfg(test)]
+struct CrashDisk {
+    data: Mutex<Vec<u8>>,
+    /// number of writes to go before the power is lost, unlimited if `None`
+    writes_left: Mutex<Option<usize>>,
+}
+
+#[cfg(test)]
+impl CrashDisk {
+    fn new(blocks: usize) -> Self {
+        Self::with_data(vec![0u8; blocks * BLOCK_SZ])
+    }
+
+    fn with_data(data: Vec<u8>) -> Self {
+        Self {
+            data: Mutex::new(data),
+            writes_left: Mutex::new(None),
+        }
+    }
+
+    /// Drop every write after the next `writes` ones
+    fn lose_power_after(&self, writes: usize) {
+        *self.writes_left.lock().unwrap() = Some(writes);
+    }
+
+    /// Whether the power has been lost
+    fn power_lost(&self) -> bool {
+        *self.writes_left.lock().unwrap() == Some(0)
+    }
+
+    /// Copy what has reached the device so far, losing whatever is only in the block cache
+    fn crash(&self) -> Self {
+        Self::with_data(self.data.lock().unwrap().clone())
+    }
+}
+
+#[cfg(test)]
+impl BlockDevice for CrashDisk {
+    fn read_block(&self, block_id: usize, buf: &mut [u8]) {
+        let start = block_id * BLOCK_SZ;
+        buf.copy_from_slice(&self.data.lock().unwrap()[start..start + BLOCK_SZ]);
+    }
+
+    fn write_block(&self, block_id: usize, buf: &[u8]) {
+        let mut writes_left = self.writes_left.lock().unwrap();
+        match writes_left.as_mut() {
+            Some(0) => return,
+            Some(writes) => *writes -= 1,
+            None => {}
+        }
+        let start = block_id * BLOCK_SZ;
+        self.data.lock().unwrap()[start..start + BLOCK_SZ].copy_from_slice(buf);
+    }
 }
 
 fn main() {
@@ -45,9 +219,22 @@ fn easy_fs_pack() -> std::io::Result<()> {
                 .takes_value(true)
                 .help("Executable target dir(with backslash)"),
         )
+        .arg(
+            Arg::with_name("inodes")
+                .short("i")
+                .long("inodes")
+                .takes_value(true)
+                .default_value("4096")
+                .help("Number of inodes in the image"),
+        )
         .get_matches();
     let src_path = matches.value_of("source").unwrap();
     let target_path = matches.value_of("target").unwrap();
+    let inodes: u32 = matches
+        .value_of("inodes")
+        .unwrap()
+        .parse()
+        .expect("Invalid number of inodes!");
     println!("src_path = {}\ntarget_path = {}", src_path, target_path);
     let block_file = Arc::new(BlockFile(Mutex::new({
         let f = OpenOptions::new()
@@ -58,8 +245,9 @@ fn easy_fs_pack() -> std::io::Result<()> {
         f.set_len(16 * 2048 * 512).unwrap();
         f
     })));
-    // 16MiB, at most 4095 files
-    let efs = EasyFileSystem::create(block_file, 16 * 2048, 1);
+    // 16MiB, at most inodes - 1 files besides the root directory
+    let efs = EasyFileSystem::create_with_inodes(block_file, 16 * 2048, inodes)
+        .expect("Too many inodes for the image!");
     let root_inode = Arc::new(EasyFileSystem::root_inode(&efs));
     let apps: Vec<_> = read_dir(src_path)
         .unwrap()
@@ -80,6 +268,8 @@ fn easy_fs_pack() -> std::io::Result<()> {
         // write data to easy-fs
         inode.write_at(0, all_data.as_slice());
     }
+    // 文件内容只写入了块缓存，退出前全部写回镜像文件
+    block_cache_sync_all();
     // list apps
     // for app in root_inode.ls() {
     //     println!("{}", app);
@@ -87,18 +277,31 @@ fn easy_fs_pack() -> std::io::Result<()> {
     Ok(())
 }
 
+/// The block cache is shared by the tests, which must not evict blocks of each other
+#[cfg(test)]
+static FS_TEST_LOCK: Mutex<()> = Mutex::new(());
+
 #[test]
 fn efs_test() -> std::io::Result<()> {
-    let block_file = Arc::new(BlockFile(Mutex::new({
-        let f = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .open("target/fs.img")?;
-        f.set_len(8192 * 512).unwrap();
-        f
-    })));
+    use std::sync::atomic::Ordering;
+    let _guard = FS_TEST_LOCK.lock().unwrap();
+    let block_file = Arc::new(FlushRecorder {
+        file: BlockFile(Mutex::new({
+            let f = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .open("target/fs.img")?;
+            f.set_len(8192 * 512).unwrap();
+            f
+        })),
+        flushed: false.into(),
+    });
     EasyFileSystem::create(block_file.clone(), 4096, 1);
+    assert!(block_file.flushed.swap(false, Ordering::SeqCst));
+    // the image file reports that its data reached the disk
+    assert!(block_file.flush());
+    block_file.flushed.store(false, Ordering::SeqCst);
     let efs = EasyFileSystem::open(block_file.clone());
     let root_inode = EasyFileSystem::root_inode(&efs);
     root_inode.create("filea");
@@ -107,6 +310,9 @@ fn efs_test() -> std::io::Result<()> {
         println!("{}", name);
     }
     let filea = root_inode.find("filea").unwrap();
+    assert_eq!(root_inode.inode_id(), 0);
+    assert_eq!(filea.inode_id(), 1);
+    assert_eq!(root_inode.find("fileb").unwrap().inode_id(), 2);
     let greet_str = "Hello, world!";
     filea.write_at(0, greet_str.as_bytes());
     //let mut buffer = [0u8; 512];
@@ -147,5 +353,835 @@ fn efs_test() -> std::io::Result<()> {
     random_str_test(1000 * BLOCK_SZ);
     random_str_test(2000 * BLOCK_SZ);
 
+    // shrinking keeps the prefix and growing again reads back zeros
+    let content: Vec<u8> = (0..2000 * BLOCK_SZ).map(|i| (i % 251) as u8 + 1).collect();
+    filea.clear();
+    filea.write_at(0, &content);
+    for &size in [
+        1500 * BLOCK_SZ + 17,
+        300 * BLOCK_SZ,
+        100 * BLOCK_SZ + 3,
+        20 * BLOCK_SZ + 5,
+        7,
+        0,
+    ]
+    .iter()
+    {
+        assert!(filea.resize(size as u32));
+        let grown = size + 3 * BLOCK_SZ + 11;
+        assert!(filea.resize(grown as u32));
+        let mut read_back = vec![0u8; grown + BLOCK_SZ];
+        assert_eq!(filea.read_at(0, &mut read_back), grown);
+        assert_eq!(&read_back[..size], &content[..size]);
+        assert!(read_back[size..grown].iter().all(|b| *b == 0));
+        assert!(filea.resize(size as u32));
+    }
+    assert!(!filea.resize(u32::MAX));
+    // freed blocks can be allocated again
+    random_str_test(2000 * BLOCK_SZ);
+
+    // written data stays in the block cache until the range is synced
+    let marker = b"#sync-range-marker#";
+    let image_contains = |pattern: &[u8]| {
+        std::fs::read("target/fs.img")
+            .unwrap()
+            .windows(pattern.len())
+            .any(|window| window == pattern)
+    };
+    block_file.flushed.store(false, Ordering::SeqCst);
+    filea.write_at(1000 * BLOCK_SZ + 100, marker);
+    assert!(!image_contains(marker));
+    assert!(!block_file.flushed.load(Ordering::SeqCst));
+    filea.sync_range(1000 * BLOCK_SZ + 100, marker.len());
+    assert!(image_contains(marker));
+    // the device is asked to persist its own cache as well
+    assert!(block_file.flushed.load(Ordering::SeqCst));
+
+    // allocating data blocks is reflected in the usage
+    let stat = efs.lock().stat();
+    // super block, inode bitmap, 4 inodes per block, journal and data bitmap
+    assert_eq!(stat.total_blocks, 4096 - 1 - 1 - 4096 / 4 - 32 - 1);
+    assert_eq!(stat.total_inodes, 4096);
+    assert_eq!(stat.free_inodes, stat.total_inodes - 3);
+    let blocks: Vec<u32> = (0..10).map(|_| efs.lock().alloc_data()).collect();
+    assert_eq!(efs.lock().stat().free_blocks, stat.free_blocks - 10);
+    for block_id in blocks {
+        efs.lock().dealloc_data(block_id);
+    }
+    assert_eq!(efs.lock().stat(), stat);
+
+    // the running counter of the data bitmap agrees with a full rescan
+    let counter_matches_rescan = || {
+        let efs = efs.lock();
+        efs.data_bitmap.allocated() == efs.data_bitmap.allocated_bits(&efs.block_device).len()
+    };
+    assert!(counter_matches_rescan());
+    let blocks: Vec<u32> = (0..300).map(|_| efs.lock().alloc_data()).collect();
+    assert!(counter_matches_rescan());
+    for block_id in blocks.iter().step_by(2) {
+        efs.lock().dealloc_data(*block_id);
+    }
+    assert!(counter_matches_rescan());
+    for block_id in blocks.iter().skip(1).step_by(2) {
+        efs.lock().dealloc_data(*block_id);
+    }
+    assert!(counter_matches_rescan());
+    assert_eq!(efs.lock().stat(), stat);
+
+    // the checker flags a data block allocated in the bitmap but referred by no inode
+    assert_eq!(efs.lock().check(), Vec::new());
+    let leaked = efs.lock().alloc_data();
+    assert_eq!(
+        efs.lock().check(),
+        vec![easy_fs::FsckProblem::BlockLeaked(leaked)]
+    );
+    efs.lock().dealloc_data(leaked);
+    assert_eq!(efs.lock().check(), Vec::new());
+
     Ok(())
 }
+
+#[test]
+fn efs_mem_test() {
+    let _guard = FS_TEST_LOCK.lock().unwrap();
+    let block_device = Arc::new(easy_fs::MemBlockDevice::new(4096));
+    let efs = EasyFileSystem::create(block_device.clone(), 4096, 1);
+    let root_inode = EasyFileSystem::root_inode(&efs);
+    let file = root_inode.create("mem_file").unwrap();
+    assert_eq!(file.mode(), 0o644);
+    assert_eq!(root_inode.mode(), 0o755);
+    file.set_mode(0o100600);
+    // large enough to go through the indirect blocks and to evict cached blocks
+    let content: Vec<u8> = (0..300 * BLOCK_SZ).map(|i| (i % 253) as u8).collect();
+    assert_eq!(file.write_at(0, &content), content.len());
+    block_cache_sync_all();
+    // a freshly opened filesystem reads everything back through the cache
+    let efs = EasyFileSystem::open(block_device);
+    let file = EasyFileSystem::root_inode(&efs).find("mem_file").unwrap();
+    let mut read_back = vec![0u8; content.len() + BLOCK_SZ];
+    assert_eq!(file.read_at(0, &mut read_back), content.len());
+    assert_eq!(&read_back[..content.len()], &content[..]);
+    // only the permission bits are kept
+    assert_eq!(file.mode(), 0o600);
+    assert_eq!(file.size() as usize, content.len());
+    assert!(!file.is_dir());
+    assert_eq!(efs.lock().check(), Vec::new());
+}
+
+#[test]
+fn efs_defrag_test() {
+    let _guard = FS_TEST_LOCK.lock().unwrap();
+    let block_device = Arc::new(ReadLog::new(4096));
+    let efs = EasyFileSystem::create(block_device.clone(), 4096, 1);
+    let root_inode = EasyFileSystem::root_inode(&efs);
+    // growing two files in turns interleaves their blocks
+    let a = root_inode.create("a").unwrap();
+    let b = root_inode.create("b").unwrap();
+    let content: Vec<u8> = (0..200 * BLOCK_SZ).map(|i| (i % 251) as u8).collect();
+    for (i, chunk) in content.chunks(BLOCK_SZ).enumerate() {
+        a.write_at(i * BLOCK_SZ, chunk);
+        b.write_at(i * BLOCK_SZ, chunk);
+    }
+    let read_seeks = |file: &easy_fs::Inode| {
+        block_cache_sync_all();
+        block_device.take();
+        let mut read_back = vec![0u8; content.len()];
+        assert_eq!(file.read_at(0, &mut read_back), content.len());
+        assert_eq!(read_back, content);
+        // count the reads not following the previous one
+        let reads = block_device.take();
+        reads
+            .windows(2)
+            .filter(|pair| pair[1] != pair[0] + 1)
+            .count()
+    };
+    let fragmented = read_seeks(&a);
+    // refuse to move blocks under open inodes
+    assert_eq!(EasyFileSystem::defragment(&efs), None);
+    drop((root_inode, a, b));
+    assert_eq!(EasyFileSystem::defragment(&efs), Some(2));
+    assert_eq!(efs.lock().check(), Vec::new());
+    let root_inode = EasyFileSystem::root_inode(&efs);
+    let a = root_inode.find("a").unwrap();
+    let b = root_inode.find("b").unwrap();
+    let contiguous = read_seeks(&a);
+    assert!(contiguous * 4 < fragmented);
+    read_seeks(&b);
+    // nothing left to move
+    drop((root_inode, a, b));
+    assert_eq!(EasyFileSystem::defragment(&efs), Some(0));
+}
+
+#[test]
+fn efs_prefetch_test() {
+    let _guard = FS_TEST_LOCK.lock().unwrap();
+    let block_device = Arc::new(ReadLog::new(4096));
+    let efs = EasyFileSystem::create(block_device.clone(), 4096, 1);
+    let root_inode = EasyFileSystem::root_inode(&efs);
+    let file = root_inode.create("stream").unwrap();
+    let content: Vec<u8> = (0..6 * BLOCK_SZ - 100).map(|i| (i % 251) as u8).collect();
+    file.write_at(0, &content);
+    // push the blocks of the file out of the cache
+    let filler = root_inode.create("filler").unwrap();
+    filler.write_at(0, &[0u8; 40 * BLOCK_SZ]);
+    filler.read_at(0, &mut [0u8; 40 * BLOCK_SZ]);
+    let mut buf = [0u8; BLOCK_SZ];
+    file.read_at(0, &mut buf);
+    // the blocks fetched ahead are not read again when they are used
+    block_device.take();
+    assert_eq!(file.prefetch(BLOCK_SZ, 2), 2);
+    let prefetched = block_device.take();
+    assert_eq!(prefetched.len(), 2);
+    file.read_at(BLOCK_SZ, &mut buf);
+    file.read_at(2 * BLOCK_SZ, &mut buf);
+    assert!(block_device
+        .take()
+        .iter()
+        .all(|block_id| !prefetched.contains(block_id)));
+    // an unaligned offset starts from the next block, and nothing past EOF is read
+    assert_eq!(file.prefetch(3 * BLOCK_SZ + 1, 4), 2);
+    assert_eq!(block_device.take().len(), 2);
+    assert_eq!(file.prefetch(6 * BLOCK_SZ, 2), 0);
+    assert!(block_device.take().is_empty());
+    let mut read_back = vec![0u8; content.len()];
+    assert_eq!(file.read_at(0, &mut read_back), content.len());
+    assert_eq!(read_back, content);
+    // evicted blocks have to be read from the device again
+    file.evict_range(BLOCK_SZ, 2 * BLOCK_SZ);
+    block_device.take();
+    assert_eq!(file.read_at(0, &mut read_back), content.len());
+    let reads = block_device.take();
+    assert!(prefetched.iter().all(|block_id| reads.contains(block_id)));
+}
+
+#[test]
+fn efs_dirty_threshold_test() {
+    let _guard = FS_TEST_LOCK.lock().unwrap();
+    let block_device = Arc::new(WriteLog::new(4096));
+    let efs = EasyFileSystem::create(block_device.clone(), 4096, 1);
+    let root_inode = EasyFileSystem::root_inode(&efs);
+    let file = root_inode.create("file").unwrap();
+    file.write_at(0, &[0u8; 12 * BLOCK_SZ]);
+    block_cache_sync_all();
+    block_device.take();
+    // overwriting the file touches only its inode and its 12 blocks, which all fit in the
+    // cache, yet once more than 8 blocks are dirty they are written back without any eviction
+    let content = [0x5au8; 12 * BLOCK_SZ];
+    assert_eq!(file.write_at(0, &content), content.len());
+    let written = block_device.take();
+    assert!(written.len() > 8);
+    // the rest are still only in the cache, and each of the 12 blocks and the inode is
+    // written back once
+    block_cache_sync_all();
+    let mut all_written = written.clone();
+    all_written.extend(block_device.take());
+    assert_eq!(all_written.len(), 13);
+    all_written.sort();
+    all_written.dedup();
+    assert_eq!(all_written.len(), 13);
+    let mut buf = vec![0u8; content.len()];
+    assert_eq!(file.read_at(0, &mut buf), content.len());
+    assert_eq!(buf, content);
+}
+
+#[test]
+fn efs_cache_policy_test() {
+    let _guard = FS_TEST_LOCK.lock().unwrap();
+    let block_device = Arc::new(ReadLog::new(4096));
+    let efs = EasyFileSystem::create(block_device.clone(), 4096, 1);
+    let root_inode = EasyFileSystem::root_inode(&efs);
+    let hot = root_inode.create("hot").unwrap();
+    hot.write_at(0, &[0x11u8; BLOCK_SZ]);
+    let scan = root_inode.create("scan").unwrap();
+    scan.write_at(0, &[0x22u8; 24 * BLOCK_SZ]);
+    // read a hot block a few times, scan more blocks than the cache holds once each, then
+    // see whether the hot block has to be read from the device again
+    let hot_reread_after_scan = |policy| {
+        easy_fs::block_cache_set_policy(policy);
+        block_cache_sync_all();
+        easy_fs::block_cache_discard_all();
+        let mut buf = [0u8; BLOCK_SZ];
+        for _ in 0..3 {
+            assert_eq!(hot.read_at(0, &mut buf), BLOCK_SZ);
+        }
+        for i in 0..24 {
+            assert_eq!(scan.read_at(i * BLOCK_SZ, &mut buf), BLOCK_SZ);
+            assert_eq!(buf, [0x22u8; BLOCK_SZ]);
+        }
+        block_device.take();
+        assert_eq!(hot.read_at(0, &mut buf), BLOCK_SZ);
+        assert_eq!(buf, [0x11u8; BLOCK_SZ]);
+        !block_device.take().is_empty()
+    };
+    assert!(hot_reread_after_scan(easy_fs::CachePolicy::Fifo));
+    assert!(hot_reread_after_scan(easy_fs::CachePolicy::Lru));
+    assert!(!hot_reread_after_scan(easy_fs::CachePolicy::Lru2));
+    easy_fs::block_cache_set_policy(easy_fs::CachePolicy::Fifo);
+}
+
+#[test]
+fn efs_sync_test() {
+    let _guard = FS_TEST_LOCK.lock().unwrap();
+    let first = Arc::new(WriteLog::new(4096));
+    let second = Arc::new(WriteLog::new(4096));
+    let first_efs = EasyFileSystem::create(first.clone(), 4096, 1);
+    let second_efs = EasyFileSystem::create(second.clone(), 4096, 1);
+    let first_file = EasyFileSystem::root_inode(&first_efs)
+        .create("file")
+        .unwrap();
+    let second_file = EasyFileSystem::root_inode(&second_efs)
+        .create("file")
+        .unwrap();
+    first_file.write_at(0, &[0u8; 2 * BLOCK_SZ]);
+    second_file.write_at(0, &[0u8; 2 * BLOCK_SZ]);
+    block_cache_sync_all();
+    first.take();
+    second.take();
+
+    // the data blocks stay in the cache until synced, syncing a filesystem leaves the other alone
+    first_file.write_at(0, &[1u8; 2 * BLOCK_SZ]);
+    second_file.write_at(0, &[2u8; 2 * BLOCK_SZ]);
+    let first_inode = first.take();
+    let second_inode = second.take();
+    assert_eq!(first_efs.lock().sync(), Some(0));
+    assert_eq!(first.take().len(), 2);
+    assert!(second.take().is_empty());
+    // syncing everything writes the rest, and nothing when there is nothing left
+    assert_eq!(easy_fs::block_cache_try_sync(None), Some(0));
+    assert!(first.take().is_empty());
+    assert_eq!(second.take().len(), 2);
+    assert_eq!(easy_fs::block_cache_try_sync(None), Some(0));
+    assert!(first.take().is_empty() && second.take().is_empty());
+    // only the inodes were written back at once
+    assert_eq!(first_inode.len(), 1);
+    assert_eq!(second_inode.len(), 1);
+}
+
+#[test]
+fn efs_flush_failure_test() {
+    use std::sync::atomic::Ordering;
+    let _guard = FS_TEST_LOCK.lock().unwrap();
+    let block_device = Arc::new(FlushFailer {
+        device: easy_fs::MemBlockDevice::new(4096),
+        failing: false.into(),
+    });
+    let efs = EasyFileSystem::create(block_device.clone(), 4096, 1);
+    let root_inode = EasyFileSystem::root_inode(&efs);
+    let file = root_inode.create("file").unwrap();
+    assert!(block_cache_sync_all());
+    assert_eq!(efs.lock().sync(), Some(0));
+
+    // a device which cannot flush fails every sync
+    block_device.failing.store(true, Ordering::SeqCst);
+    assert!(!block_cache_sync_all());
+    file.write_at(0, b"data");
+    assert!(!file.sync_range(0, 4));
+    assert_eq!(efs.lock().sync(), None);
+    // a failed flush inside an operation is reported by the next sync, only once
+    root_inode.create("other").unwrap();
+    block_device.failing.store(false, Ordering::SeqCst);
+    assert_eq!(efs.lock().sync(), None);
+    assert_eq!(efs.lock().sync(), Some(0));
+    assert!(file.sync_range(0, 4));
+    assert_eq!(easy_fs::block_cache_try_sync(None), Some(0));
+}
+
+#[test]
+fn efs_inodes_test() {
+    let _guard = FS_TEST_LOCK.lock().unwrap();
+    // the inodes must leave room for data blocks
+    let block_device = Arc::new(easy_fs::MemBlockDevice::new(8192));
+    assert!(EasyFileSystem::create_with_inodes(block_device.clone(), 8192, 0).is_none());
+    assert!(EasyFileSystem::create_with_inodes(block_device.clone(), 8192, 8192 * 4).is_none());
+    // a few inodes only: the root directory and 7 files
+    let efs = EasyFileSystem::create_with_inodes(block_device.clone(), 8192, 8).unwrap();
+    assert_eq!(efs.lock().stat().total_inodes, 8);
+    let root_inode = EasyFileSystem::root_inode(&efs);
+    for i in 0..7 {
+        assert!(root_inode.create(&format!("f{}", i)).is_some());
+    }
+    assert!(root_inode.create("full").is_none());
+    drop(root_inode);
+    // more files than the 4096 inodes brought by one block of inode bitmap
+    let efs = EasyFileSystem::create_with_inodes(block_device.clone(), 8192, 5000).unwrap();
+    let root_inode = EasyFileSystem::root_inode(&efs);
+    for i in 0..4200 {
+        assert!(root_inode.create(&format!("f{}", i)).is_some());
+    }
+    // a directory cursor lists every entry exactly once across many small reads
+    let mut names = std::collections::BTreeSet::new();
+    let mut pos = 0;
+    loop {
+        let (entries, next) = root_inode.read_dir(pos, 100);
+        if entries.is_empty() {
+            break;
+        }
+        for (name, _, type_) in entries {
+            assert_eq!(type_, Some(easy_fs::DiskInodeType::File));
+            assert!(names.insert(name));
+        }
+        pos = next;
+    }
+    assert_eq!(names.len(), 4200);
+    let stat = efs.lock().stat();
+    assert_eq!(stat.total_inodes, 5000);
+    assert_eq!(stat.free_inodes, 5000 - 4201);
+    // the inode count is kept on the device
+    let efs = EasyFileSystem::open(block_device);
+    assert_eq!(efs.lock().stat().total_inodes, 5000);
+    assert_eq!(efs.lock().check(), Vec::new());
+}
+
+#[test]
+fn efs_rename_test() {
+    let _guard = FS_TEST_LOCK.lock().unwrap();
+    let block_device = Arc::new(easy_fs::MemBlockDevice::new(4096));
+    let efs = EasyFileSystem::create(block_device, 4096, 1);
+    let root_inode = EasyFileSystem::root_inode(&efs);
+    let read_all = |file: &easy_fs::Inode| {
+        let mut buf = vec![0u8; 64];
+        let len = file.read_at(0, &mut buf);
+        String::from_utf8(buf[..len].to_vec()).unwrap()
+    };
+    let target = root_inode.create("target").unwrap();
+    target.write_at(0, b"old content");
+    let target_id = target.inode_id();
+    let tmp = root_inode.create("tmp").unwrap();
+    tmp.write_at(0, b"new content");
+    drop(tmp);
+    let free_inodes = efs.lock().stat().free_inodes;
+
+    // write to a temporary file then rename it over the target
+    assert!(root_inode.rename("tmp", "target"));
+    assert!(root_inode.find("tmp").is_none());
+    assert_eq!(read_all(&root_inode.find("target").unwrap()), "new content");
+    assert_eq!(root_inode.ls(), vec![String::from("target")]);
+    // the open target keeps its content and its inode until dropped
+    assert_eq!(read_all(&target), "old content");
+    assert_eq!(efs.lock().stat().free_inodes, free_inodes);
+    // while open, the orphan is no longer reachable from the root directory
+    assert!(efs
+        .lock()
+        .check()
+        .contains(&easy_fs::FsckProblem::InodeLeaked(target_id)));
+    drop(target);
+    assert_eq!(efs.lock().stat().free_inodes, free_inodes + 1);
+    assert_eq!(efs.lock().check(), Vec::new());
+
+    // a replaced target which is not open is freed at once
+    root_inode.create("other").unwrap();
+    assert!(root_inode.rename("target", "other"));
+    assert_eq!(efs.lock().stat().free_inodes, free_inodes + 1);
+    assert_eq!(read_all(&root_inode.find("other").unwrap()), "new content");
+    // plain rename, rename to itself and rename of a missing file
+    assert!(root_inode.rename("other", "renamed"));
+    assert!(root_inode.rename("renamed", "renamed"));
+    assert!(!root_inode.rename("missing", "renamed"));
+    assert_eq!(root_inode.ls(), vec![String::from("renamed")]);
+    assert_eq!(efs.lock().check(), Vec::new());
+}
+
+#[test]
+fn efs_sparse_test() {
+    let _guard = FS_TEST_LOCK.lock().unwrap();
+    let block_device = Arc::new(easy_fs::MemBlockDevice::new(4096));
+    let efs = EasyFileSystem::create(block_device, 4096, 1);
+    let root_inode = EasyFileSystem::root_inode(&efs);
+    let free_blocks = || efs.lock().stat().free_blocks;
+    let file = root_inode.create("sparse").unwrap();
+    let before = free_blocks();
+    // only the data block and the index blocks leading to it are allocated
+    let offset = 1 << 20;
+    let content: Vec<u8> = (0..BLOCK_SZ + 100).map(|i| (i % 251) as u8).collect();
+    assert_eq!(file.write_at(offset, &content), content.len());
+    assert_eq!(file.size() as usize, offset + content.len());
+    assert!(before - free_blocks() <= 4);
+    assert_eq!(file.allocated_blocks() as usize, before - free_blocks());
+    // the hole reads back as zeros
+    let mut read_back = vec![0xffu8; offset + content.len()];
+    assert_eq!(file.read_at(0, &mut read_back), read_back.len());
+    assert!(read_back[..offset].iter().all(|byte| *byte == 0));
+    assert_eq!(&read_back[offset..], &content[..]);
+    assert_eq!(efs.lock().check(), Vec::new());
+
+    // writing into the hole allocates its blocks only
+    let inside = 100 * BLOCK_SZ;
+    let used = before - free_blocks();
+    file.write_at(inside + 10, b"inside");
+    assert_eq!(before - free_blocks(), used + 2);
+    assert_eq!(file.allocated_blocks() as usize, used + 2);
+    let mut buf = [0xffu8; 16];
+    file.read_at(inside, &mut buf);
+    assert_eq!(&buf[..16], b"\0\0\0\0\0\0\0\0\0\0inside");
+    // writes ending beyond the max file size are rejected as a whole
+    let max = easy_fs::MAX_FILE_SIZE as usize;
+    assert_eq!(file.write_at(max - 1, b"xy"), 0);
+    assert_eq!(file.write_at(16 << 20, b"x"), 0);
+    assert_eq!(file.write_at(1 << 32, b"x"), 0);
+    assert_eq!(file.write_at(usize::MAX, b"x"), 0);
+    assert_eq!(before - free_blocks(), used + 2);
+    // shrinking into the hole and growing again leaves only zeros behind
+    assert!(file.resize((offset - BLOCK_SZ) as u32));
+    assert!(file.resize((offset + content.len()) as u32));
+    assert_eq!(file.read_at(0, &mut read_back), read_back.len());
+    assert!(read_back[offset..].iter().all(|byte| *byte == 0));
+    assert_eq!(&read_back[inside + 10..inside + 16], b"inside");
+    assert_eq!(efs.lock().check(), Vec::new());
+    // defragmenting keeps the holes
+    drop((root_inode, file));
+    EasyFileSystem::defragment(&efs).unwrap();
+    assert_eq!(efs.lock().check(), Vec::new());
+    let file = EasyFileSystem::root_inode(&efs).find("sparse").unwrap();
+    assert_eq!(before - free_blocks(), 2);
+    assert_eq!(file.read_at(0, &mut read_back), read_back.len());
+    assert_eq!(&read_back[inside + 10..inside + 16], b"inside");
+    file.clear();
+    assert_eq!(free_blocks(), before);
+    assert_eq!(efs.lock().check(), Vec::new());
+}
+
+#[test]
+fn efs_mknod_test() {
+    let _guard = FS_TEST_LOCK.lock().unwrap();
+    let block_device = Arc::new(easy_fs::MemBlockDevice::new(4096));
+    let efs = EasyFileSystem::create(block_device.clone(), 4096, 1);
+    let root_inode = EasyFileSystem::root_inode(&efs);
+    let null = root_inode.mknod("null", 0x103).unwrap();
+    assert_eq!(null.device(), Some(0x103));
+    assert!(root_inode.mknod("null", 0x105).is_none());
+    assert_eq!(root_inode.create("file").unwrap().device(), None);
+    // directory entries carry the type of their inodes
+    let (entries, _) = root_inode.read_dir(0, 10);
+    let types: Vec<_> = entries
+        .into_iter()
+        .map(|(name, _, type_)| (name, type_))
+        .collect();
+    assert_eq!(
+        types,
+        vec![
+            ("null".to_string(), Some(easy_fs::DiskInodeType::Device)),
+            ("file".to_string(), Some(easy_fs::DiskInodeType::File)),
+        ]
+    );
+    // the device number is not a data block
+    assert!(!null.resize(BLOCK_SZ as u32));
+    null.clear();
+    assert_eq!(null.size(), 0);
+    assert_eq!(efs.lock().check(), Vec::new());
+    // the device number persists on disk
+    let efs = EasyFileSystem::open(block_device);
+    let null = EasyFileSystem::root_inode(&efs).find("null").unwrap();
+    assert_eq!(null.device(), Some(0x103));
+    assert_eq!(null.mode(), 0o644);
+}
+
+#[test]
+fn efs_mkdir_test() {
+    let _guard = FS_TEST_LOCK.lock().unwrap();
+    let block_device = Arc::new(easy_fs::MemBlockDevice::new(4096));
+    let efs = EasyFileSystem::create(block_device.clone(), 4096, 1);
+    let root_inode = EasyFileSystem::root_inode(&efs);
+    let a = root_inode.mkdir("a").unwrap();
+    assert!(a.is_dir());
+    assert_eq!(a.mode(), 0o755);
+    assert!(root_inode.mkdir("a").is_none());
+    // nested directories hold their own entries
+    let b = a.mkdir("b").unwrap();
+    let file = b.create("file").unwrap();
+    assert_eq!(file.write_at(0, b"nested"), 6);
+    assert!(root_inode.find("b").is_none());
+    let (entries, _) = a.read_dir(0, 10);
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].0, "b");
+    assert_eq!(entries[0].2, Some(easy_fs::DiskInodeType::Directory));
+    assert_eq!(efs.lock().check(), Vec::new());
+    drop((root_inode, a, b, file));
+    // the tree persists on disk
+    let efs = EasyFileSystem::open(block_device);
+    let file = EasyFileSystem::root_inode(&efs)
+        .find("a")
+        .and_then(|a| a.find("b"))
+        .and_then(|b| b.find("file"))
+        .unwrap();
+    let mut buf = [0u8; 6];
+    assert_eq!(file.read_at(0, &mut buf), 6);
+    assert_eq!(&buf, b"nested");
+}
+
+#[test]
+fn efs_remove_test() {
+    let _guard = FS_TEST_LOCK.lock().unwrap();
+    let block_device = Arc::new(easy_fs::MemBlockDevice::new(4096));
+    let efs = EasyFileSystem::create(block_device, 4096, 1);
+    let root_inode = EasyFileSystem::root_inode(&efs);
+    root_inode.create("keep").unwrap();
+    let before = efs.lock().stat();
+    let a = root_inode.mkdir("a").unwrap();
+    let b = a.mkdir("b").unwrap();
+    let data = vec![0x5au8; 3 * BLOCK_SZ];
+    a.create("f1").unwrap().write_at(0, &data);
+    let f2 = b.create("f2").unwrap();
+    f2.write_at(0, &data);
+    // only empty directories can be removed with rmdir, and only files with unlink
+    assert!(!root_inode.rmdir("a"));
+    assert!(!a.rmdir("f1"));
+    assert!(!root_inode.unlink("a"));
+    assert!(!root_inode.unlink("missing"));
+    assert!(b.unlink("f2"));
+    assert!(b.find("f2").is_none());
+    // an open file keeps its content until dropped
+    let mut buf = vec![0u8; data.len()];
+    assert_eq!(f2.read_at(0, &mut buf), data.len());
+    assert_eq!(buf, data);
+    drop(f2);
+    assert!(a.unlink("f1"));
+    assert!(a.rmdir("b"));
+    drop(b);
+    assert!(root_inode.rmdir("a"));
+    drop(a);
+    assert_eq!(root_inode.ls(), vec![String::from("keep")]);
+    // the removed entries at the end of a directory no longer take up space, leaving the
+    // single 32-byte entry of "keep"
+    let after = efs.lock().stat();
+    assert_eq!(after.free_blocks, before.free_blocks);
+    assert_eq!(after.free_inodes, before.free_inodes);
+    assert_eq!(root_inode.size(), 32);
+    assert_eq!(efs.lock().check(), Vec::new());
+}
+
+#[test]
+fn efs_write_through_test() {
+    let _guard = FS_TEST_LOCK.lock().unwrap();
+    let disk = Arc::new(CrashDisk::new(4096));
+    let efs = EasyFileSystem::create(disk.clone(), 4096, 1);
+    let root_inode = EasyFileSystem::root_inode(&efs);
+    let file = root_inode.create("file").unwrap();
+    // growing the file allocates blocks and changes its inode, but syncs nothing
+    let content = vec![0x5au8; 3 * BLOCK_SZ];
+    assert_eq!(file.write_at(0, &content), content.len());
+    let free_blocks = efs.lock().stat().free_blocks;
+
+    // crash before anything is synced: the metadata has reached the disk already
+    let efs = EasyFileSystem::open(Arc::new(disk.crash()));
+    assert_eq!(efs.lock().stat().free_blocks, free_blocks);
+    assert_eq!(efs.lock().check(), Vec::new());
+    let crashed = EasyFileSystem::root_inode(&efs).find("file").unwrap();
+    assert_eq!(crashed.size() as usize, content.len());
+    // while the data blocks are still only in the block cache
+    let mut buf = vec![0xffu8; content.len()];
+    assert_eq!(crashed.read_at(0, &mut buf), content.len());
+    assert!(buf.iter().all(|byte| *byte == 0));
+}
+
+#[test]
+fn efs_sync_range_test() {
+    let _guard = FS_TEST_LOCK.lock().unwrap();
+    let block_device = Arc::new(easy_fs::MemBlockDevice::new(4096));
+    let efs = EasyFileSystem::create(block_device.clone(), 4096, 1);
+    let root_inode = EasyFileSystem::root_inode(&efs);
+    let synced = root_inode.create("synced").unwrap();
+    let lazy = root_inode.create("lazy").unwrap();
+    // the way an O_SYNC file is written: each write is followed by syncing its range
+    let content = vec![0xa5u8; 3 * BLOCK_SZ];
+    assert_eq!(synced.write_at(0, &content), content.len());
+    synced.sync_range(0, content.len());
+    assert_eq!(lazy.write_at(0, &[0x3cu8; BLOCK_SZ]), BLOCK_SZ);
+    drop((synced, lazy, root_inode, efs));
+
+    // drop the cache without writing anything back, then look at the raw device
+    easy_fs::block_cache_discard_all();
+    let blocks_filled_with = |byte: u8| {
+        let mut block = [0u8; BLOCK_SZ];
+        (0..4096)
+            .filter(|block_id| {
+                block_device.read_block(*block_id, &mut block);
+                block.iter().all(|b| *b == byte)
+            })
+            .count()
+    };
+    assert_eq!(blocks_filled_with(0xa5), 3);
+    assert_eq!(blocks_filled_with(0x3c), 0);
+    // the synced file can be found through the metadata on the device
+    let efs = EasyFileSystem::open(block_device);
+    assert_eq!(efs.lock().check(), Vec::new());
+    let mut buf = vec![0u8; content.len()];
+    let synced = EasyFileSystem::root_inode(&efs).find("synced").unwrap();
+    assert_eq!(synced.read_at(0, &mut buf), content.len());
+    assert_eq!(buf, content);
+}
+
+#[test]
+fn efs_discard_test() {
+    let _guard = FS_TEST_LOCK.lock().unwrap();
+    let block_device = Arc::new(easy_fs::MemBlockDevice::new(4096));
+    let efs = EasyFileSystem::create(block_device.clone(), 4096, 1);
+    let root_inode = EasyFileSystem::root_inode(&efs);
+    let synced = root_inode.create("synced").unwrap();
+    let lost = root_inode.create("lost").unwrap();
+    synced.write_at(0, &[1u8; BLOCK_SZ]);
+    block_cache_sync_all();
+    synced.write_at(0, &[2u8; BLOCK_SZ]);
+    lost.write_at(0, &[3u8; BLOCK_SZ]);
+    drop((synced, lost, root_inode, efs));
+
+    // the data written after the sync is lost while the metadata has reached the device
+    easy_fs::block_cache_discard_all();
+    let efs = EasyFileSystem::open(block_device);
+    assert_eq!(efs.lock().check(), Vec::new());
+    let root_inode = EasyFileSystem::root_inode(&efs);
+    let mut buf = [0u8; BLOCK_SZ];
+    assert_eq!(
+        root_inode.find("synced").unwrap().read_at(0, &mut buf),
+        BLOCK_SZ
+    );
+    assert!(buf.iter().all(|byte| *byte == 1));
+    assert_eq!(
+        root_inode.find("lost").unwrap().read_at(0, &mut buf),
+        BLOCK_SZ
+    );
+    assert!(buf.iter().all(|byte| *byte == 0));
+}
+
+#[test]
+fn efs_journal_test() {
+    let _guard = FS_TEST_LOCK.lock().unwrap();
+    let (mut before, mut after) = (false, false);
+    // lose the power after each number of writes in turn, until the creation completes
+    for writes in 0.. {
+        let disk = Arc::new(CrashDisk::new(4096));
+        let efs = EasyFileSystem::create(disk.clone(), 4096, 1);
+        let root_inode = EasyFileSystem::root_inode(&efs);
+        root_inode.create("old").unwrap().write_at(0, b"old");
+        block_cache_sync_all();
+        let free_inodes = efs.lock().stat().free_inodes;
+        let free_blocks = efs.lock().stat().free_blocks;
+        disk.lose_power_after(writes);
+        root_inode.mkdir("new").unwrap();
+        let power_lost = disk.power_lost();
+
+        // the creation either happened as a whole or not at all
+        let efs = EasyFileSystem::open(Arc::new(disk.crash()));
+        assert_eq!(efs.lock().check(), Vec::new());
+        let root_inode = EasyFileSystem::root_inode(&efs);
+        let mut buf = [0u8; 3];
+        assert_eq!(root_inode.find("old").unwrap().read_at(0, &mut buf), 3);
+        assert_eq!(&buf, b"old");
+        let stat = efs.lock().stat();
+        match root_inode.find("new") {
+            Some(new) => {
+                assert!(new.is_dir());
+                assert_eq!(root_inode.ls(), vec!["old", "new"]);
+                assert_eq!(stat.free_inodes, free_inodes - 1);
+                after = true;
+            }
+            None => {
+                assert_eq!(root_inode.ls(), vec!["old"]);
+                assert_eq!(stat.free_inodes, free_inodes);
+                assert_eq!(stat.free_blocks, free_blocks);
+                before = true;
+            }
+        }
+        if !power_lost {
+            break;
+        }
+    }
+    assert!(before && after);
+}
+
+#[test]
+fn efs_large_transaction_test() {
+    let _guard = FS_TEST_LOCK.lock().unwrap();
+    // more full data bitmap blocks than the block cache holds, each covering 4096 blocks
+    const FULL_BITMAP_BLOCKS: usize = 17;
+    const MAX_FILE_BLOCKS: usize = 16384;
+    let total_blocks = (FULL_BITMAP_BLOCKS + 1) * 4096;
+    let block_device = Arc::new(easy_fs::MemBlockDevice::new(total_blocks));
+    let efs =
+        EasyFileSystem::create_with_inodes(block_device.clone(), total_blocks as u32, 64).unwrap();
+    let root_inode = EasyFileSystem::root_inode(&efs);
+    let data = vec![0x5au8; MAX_FILE_BLOCKS * BLOCK_SZ];
+    let mut files = 0;
+    loop {
+        let stat = efs.lock().stat();
+        let used = stat.total_blocks - stat.free_blocks;
+        if used >= FULL_BITMAP_BLOCKS * 4096 {
+            break;
+        }
+        let blocks = (FULL_BITMAP_BLOCKS * 4096 - used).min(MAX_FILE_BLOCKS);
+        let file = root_inode.create(&format!("f{}", files)).unwrap();
+        assert_eq!(
+            file.write_at(0, &data[..blocks * BLOCK_SZ]),
+            blocks * BLOCK_SZ
+        );
+        files += 1;
+    }
+    // fill the first block of the root directory, which holds 16 entries
+    while root_inode.ls().len() < BLOCK_SZ / 32 {
+        root_inode.create(&format!("f{}", files)).unwrap();
+        files += 1;
+    }
+    // the new entry needs a block, found after scanning every full bitmap block in the transaction
+    root_inode.create("last").unwrap().write_at(0, b"last");
+    block_cache_sync_all();
+    assert_eq!(efs.lock().check(), Vec::new());
+    drop(root_inode);
+    drop(efs);
+    easy_fs::block_cache_discard_all();
+
+    let efs = EasyFileSystem::open(block_device);
+    assert_eq!(efs.lock().check(), Vec::new());
+    let root_inode = EasyFileSystem::root_inode(&efs);
+    assert_eq!(root_inode.ls().len(), files + 1);
+    let mut buf = [0u8; 4];
+    assert_eq!(root_inode.find("last").unwrap().read_at(0, &mut buf), 4);
+    assert_eq!(&buf, b"last");
+}
+
+#[test]
+fn efs_large_truncate_crash_test() {
+    let _guard = FS_TEST_LOCK.lock().unwrap();
+    // a directory whose index blocks alone are more than a transaction can hold
+    const DIR_BLOCKS: usize = 28 + 128 + 40 * 128;
+    let (mut during, mut after) = (false, false);
+    // lose the power at points spread over the removal, until it completes
+    for writes in (0..).step_by(997) {
+        let disk = Arc::new(CrashDisk::new(8192));
+        let efs = EasyFileSystem::create(disk.clone(), 8192, 1);
+        let root_inode = EasyFileSystem::root_inode(&efs);
+        let dir = root_inode.mkdir("d").unwrap();
+        block_cache_sync_all();
+        let free_blocks = efs.lock().stat().free_blocks;
+        let data = vec![0u8; DIR_BLOCKS * BLOCK_SZ];
+        assert_eq!(dir.write_at(0, &data), data.len());
+        let last_id = dir.create("last").unwrap().inode_id();
+        block_cache_sync_all();
+        // removing the last entry cuts off every empty entry before it
+        disk.lose_power_after(writes);
+        assert!(dir.unlink("last"));
+        let power_lost = disk.power_lost();
+
+        // the entry is removed as a whole, then the directory shrinks a transaction at a time
+        let efs = EasyFileSystem::open(Arc::new(disk.crash()));
+        let problems = efs.lock().check();
+        let root_inode = EasyFileSystem::root_inode(&efs);
+        let dir = root_inode.find("d").unwrap();
+        if dir.find("last").is_some() || !power_lost {
+            assert_eq!(problems, Vec::new());
+        } else if !problems.is_empty() {
+            // like any unlink, losing the power before the inode is freed leaks it
+            assert_eq!(problems, vec![easy_fs::FsckProblem::InodeLeaked(last_id)]);
+        }
+        assert_eq!(dir.size() as usize % 32, 0);
+        if !power_lost {
+            assert!(dir.ls().is_empty());
+            assert_eq!(dir.size(), 0);
+            assert_eq!(efs.lock().stat().free_blocks, free_blocks);
+            after = true;
+            break;
+        }
+        if dir.ls().is_empty() && dir.size() > 0 {
+            during = true;
+        }
+    }
+    assert!(during && after);
+}
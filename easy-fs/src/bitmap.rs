@@ -1,5 +1,7 @@
-use super::{get_block_cache, BlockDevice, BLOCK_SZ};
+use super::{get_metadata_block_cache, BlockDevice, BLOCK_SZ};
 use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::ops::Range;
 // 磁盘块上位图区域的数据是要以磁盘数据结构 BitmapBlock 的格式进行操作
 // BitmapBlock 是一个磁盘数据结构，它将位图区域中的一个磁盘块解释为长度为 64 的一个 u64 数组，每个 u64 打包了一组 64 bits，于是整个数组包含 64×64=4096 bits，且可以以组为单位进行操作
 /// A bitmap block
@@ -13,6 +15,10 @@ const BLOCK_BITS: usize = BLOCK_SZ * 8;
 pub struct Bitmap {
     start_block_id: usize,
     blocks: usize,
+    // 可以分配的 bit 数，不超过区域中 bit 的总数。索引节点区域能够容纳的索引节点数可能少于索引节点位图中 bit 的总数
+    maximum: usize,
+    // 已经分配出去的 bit 数，挂载时扫描一遍位图得到，之后随着 alloc/dealloc 更新，查询空闲数量时不必再扫描位图
+    allocated: usize,
 }
 
 /// Decompose bits into (block_pos, bits64_pos, inner_pos)
@@ -25,56 +31,133 @@ fn decomposition(mut bit: usize) -> (usize, usize, usize) {
 impl Bitmap {
     /// A new bitmap from start block id and number of blocks
     pub fn new(start_block_id: usize, blocks: usize) -> Self {
+        Self::with_maximum(start_block_id, blocks, blocks * BLOCK_BITS)
+    }
+    /// A new bitmap of which only the first `maximum` bits are allocatable
+    pub fn with_maximum(start_block_id: usize, blocks: usize, maximum: usize) -> Self {
+        assert!(maximum <= blocks * BLOCK_BITS);
         Self {
             start_block_id,
             blocks,
+            maximum,
+            allocated: 0,
         }
     }
+    /// Recount the allocated bits by scanning the whole bitmap
+    pub fn recount(&mut self, block_device: &Arc<dyn BlockDevice>) {
+        self.allocated = self.allocated_bits(block_device).len();
+    }
     /// Allocate a new block from a block device
-    pub fn alloc(&self, block_device: &Arc<dyn BlockDevice>) -> Option<usize> {
-        // 遍历区域中的每个块
+    pub fn alloc(&mut self, block_device: &Arc<dyn BlockDevice>) -> Option<usize> {
+        // 位图已满时不必再扫描。分配总是选择编号最小的空闲 bit ，因此未满时找到的 bit 一定小于 maximum
+        if self.allocated == self.maximum() {
+            return None;
+        }
+        // 遍历区域中的每个块。只修改找到了空闲 bit 的那个块，被修改的块都要写回，在事务中还要写入日志
         for block_id in 0..self.blocks {
-            let pos = get_block_cache(
+            let block_cache = get_metadata_block_cache(
                 block_id + self.start_block_id as usize,
                 Arc::clone(block_device),
-            )
-            .lock()
-            .modify(0, |bitmap_block: &mut BitmapBlock| {
-                // 再在每个块中以bit组（每组 64 bits）为单位进行遍历
-                // 如果它并没有达到 u64::MAX （即 2^64-1），则通过 u64::trailing_ones 找到最低的一个 0 并置为 1
-                // 如果能够找到的话，bit组的编号将保存在变量 bits64_pos 中，而分配的bit在组内的位置将保存在变量 inner_pos 中
-                if let Some((bits64_pos, inner_pos)) = bitmap_block
+            );
+            let mut block_cache = block_cache.lock();
+            // 再在每个块中以bit组（每组 64 bits）为单位进行遍历
+            // 如果它并没有达到 u64::MAX （即 2^64-1），则通过 u64::trailing_ones 找到最低的一个 0
+            // 如果能够找到的话，bit组的编号将保存在变量 bits64_pos 中，而分配的bit在组内的位置将保存在变量 inner_pos 中
+            let free = block_cache.read(0, |bitmap_block: &BitmapBlock| {
+                bitmap_block
                     .iter()
                     .enumerate()
                     .find(|(_, bits64)| **bits64 != u64::MAX)
                     .map(|(bits64_pos, bits64)| (bits64_pos, bits64.trailing_ones() as usize))
-                {
-                    // modify cache
-                    bitmap_block[bits64_pos] |= 1u64 << inner_pos;
-                    Some(block_id * BLOCK_BITS + bits64_pos * 64 + inner_pos as usize)
-                } else {
-                    None
-                }
             });
-            if pos.is_some() {
+            if let Some((bits64_pos, inner_pos)) = free {
+                // modify cache
+                block_cache.modify(0, |bitmap_block: &mut BitmapBlock| {
+                    bitmap_block[bits64_pos] |= 1u64 << inner_pos;
+                });
                 // 一旦在某个块中找到一个空闲的bit并成功分配，就不再考虑后续的块，提前返回
-                return pos;
+                self.allocated += 1;
+                return Some(block_id * BLOCK_BITS + bits64_pos * 64 + inner_pos);
             }
         }
         None
     }
+    // 在前 limit 个 bit 中找到第一段长度为 count 的连续空闲 bit 并全部分配，返回这段 bit 的起始编号
+    /// Allocate `count` contiguous bits among the first `limit` ones
+    pub fn alloc_contiguous(
+        &mut self,
+        block_device: &Arc<dyn BlockDevice>,
+        count: usize,
+        limit: usize,
+    ) -> Option<usize> {
+        let mut start = 0;
+        for bit in self
+            .allocated_bits(block_device)
+            .into_iter()
+            .take_while(|bit| *bit < limit)
+        {
+            if bit >= start + count {
+                break;
+            }
+            start = bit + 1;
+        }
+        if start + count > limit {
+            return None;
+        }
+        for bit in start..start + count {
+            let (block_pos, bits64_pos, inner_pos) = decomposition(bit);
+            get_metadata_block_cache(block_pos + self.start_block_id, Arc::clone(block_device))
+                .lock()
+                .modify(0, |bitmap_block: &mut BitmapBlock| {
+                    bitmap_block[bits64_pos] |= 1u64 << inner_pos;
+                });
+        }
+        self.allocated += count;
+        Some(start)
+    }
     /// Deallocate a block
-    pub fn dealloc(&self, block_device: &Arc<dyn BlockDevice>, bit: usize) {
+    pub fn dealloc(&mut self, block_device: &Arc<dyn BlockDevice>, bit: usize) {
         let (block_pos, bits64_pos, inner_pos) = decomposition(bit);
-        get_block_cache(block_pos + self.start_block_id, Arc::clone(block_device))
+        get_metadata_block_cache(block_pos + self.start_block_id, Arc::clone(block_device))
             .lock()
             .modify(0, |bitmap_block: &mut BitmapBlock| {
                 assert!(bitmap_block[bits64_pos] & (1u64 << inner_pos) > 0);
                 bitmap_block[bits64_pos] -= 1u64 << inner_pos;
             });
+        self.allocated -= 1;
     }
     /// Get the max number of allocatable blocks
     pub fn maximum(&self) -> usize {
-        self.blocks * BLOCK_BITS
+        self.maximum
+    }
+    /// Get the number of allocated bits
+    pub fn allocated(&self) -> usize {
+        self.allocated
+    }
+    /// Get the number of free bits
+    pub fn free(&self) -> usize {
+        self.maximum() - self.allocated
+    }
+    /// Get all the allocated bits in ascending order
+    pub fn allocated_bits(&self, block_device: &Arc<dyn BlockDevice>) -> Vec<usize> {
+        let mut bits: Vec<usize> = Vec::new();
+        for block_id in 0..self.blocks {
+            get_metadata_block_cache(block_id + self.start_block_id, Arc::clone(block_device))
+                .lock()
+                .read(0, |bitmap_block: &BitmapBlock| {
+                    for (bits64_pos, bits64) in bitmap_block.iter().enumerate() {
+                        for inner_pos in 0..64 {
+                            if bits64 & (1u64 << inner_pos) != 0 {
+                                bits.push(block_id * BLOCK_BITS + bits64_pos * 64 + inner_pos);
+                            }
+                        }
+                    }
+                });
+        }
+        bits
+    }
+    /// Get the ids of the blocks holding the bitmap
+    pub fn block_ids(&self) -> Range<usize> {
+        self.start_block_id..self.start_block_id + self.blocks
     }
 }
@@ -1,6 +1,8 @@
 use super::{BlockDevice, BLOCK_SZ};
 use alloc::collections::VecDeque;
 use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicUsize, Ordering};
 use lazy_static::*;
 use spin::Mutex;
 /// Cached block inside memory
@@ -17,13 +19,27 @@ pub struct BlockCache {
     // modified 记录这个块从磁盘载入内存缓存之后，它有没有被修改过
     /// whether the block is dirty
     modified: bool,
+    // 保存元数据（超级块、位图、索引节点和索引块）的块一旦损坏，整个文件系统都会出错，
+    // 因此它们每次被修改之后都立即写回磁盘，数据块则仍然等到被替换出去或者被显式同步时才写回
+    /// whether every modification is written back at once
+    write_through: bool,
+    // 在事务中被修改过的元数据块要先写入日志，事务提交之后才能写回原位。在此之前它不会被写回，也不会被替换出去
+    /// whether the block is modified by an uncommitted transaction
+    journaled: bool,
+    // 与块缓存管理器共享的脏块计数，本块变脏和被写回时分别加一和减一
+    /// number of dirty blocks in the cache
+    dirty_blocks: Arc<AtomicUsize>,
 }
 
 // 一旦磁盘块已经存在于内存缓存中，CPU 就可以直接访问磁盘块数据了
 impl BlockCache {
     // 当我们创建一个 BlockCache 的时候，这将触发一次 read_block 将一个块上的数据从磁盘读到缓冲区 cache
     /// Load a new BlockCache from disk.
-    pub fn new(block_id: usize, block_device: Arc<dyn BlockDevice>) -> Self {
+    pub fn new(
+        block_id: usize,
+        block_device: Arc<dyn BlockDevice>,
+        dirty_blocks: Arc<AtomicUsize>,
+    ) -> Self {
         let mut cache = [0u8; BLOCK_SZ];
         block_device.read_block(block_id, &mut cache);
         Self {
@@ -31,6 +47,9 @@ impl BlockCache {
             block_id,
             block_device,
             modified: false,
+            write_through: false,
+            journaled: false,
+            dirty_blocks,
         }
     }
     /// Get the address of an offset inside the cached block data
@@ -59,6 +78,9 @@ impl BlockCache {
         let type_size = core::mem::size_of::<T>();
         assert!(offset + type_size <= BLOCK_SZ);
         // 将 BlockCache 的 modified 标记为 true 表示该缓冲区已经被修改，之后需要将数据写回磁盘块才能真正将修改同步到磁盘
+        if !self.modified {
+            self.dirty_blocks.fetch_add(1, Ordering::Relaxed);
+        }
         self.modified = true;
         let addr = self.addr_of_offset(offset);
         unsafe { &mut *(addr as *mut T) }
@@ -74,18 +96,35 @@ impl BlockCache {
     }
 
     pub fn modify<T, V>(&mut self, offset: usize, f: impl FnOnce(&mut T) -> V) -> V {
-        f(self.get_mut(offset))
+        let ret = f(self.get_mut(offset));
+        if self.write_through {
+            if transaction_record(&self.block_device, self.block_id) {
+                self.journaled = true;
+            } else {
+                self.sync();
+            }
+        }
+        ret
     }
 
     // 在 Linux 中，sync 并不是只有在 drop 的时候才会被调用。通常有一个后台进程负责定期将内存中缓冲区的内容写回磁盘。另外有一个 sys_fsync 系统调用可以让应用主动通知内核将一个文件的修改同步回磁盘。
     // 由于我们的实现比较简单， sync 仅会在 BlockCache 被 drop 时才会被调用
     pub fn sync(&mut self) {
-        // modified 标记将会决定数据是否需要写回磁盘
-        if self.modified {
+        // modified 标记将会决定数据是否需要写回磁盘，事务还没有提交的块暂时不能写回
+        if self.modified && !self.journaled {
             self.modified = false;
+            self.dirty_blocks.fetch_sub(1, Ordering::Relaxed);
             self.block_device.write_block(self.block_id, &self.cache);
         }
     }
+    /// Forget the modification of the block without writing it back
+    fn discard(&mut self) {
+        if self.modified {
+            self.modified = false;
+            self.dirty_blocks.fetch_sub(1, Ordering::Relaxed);
+        }
+        self.journaled = false;
+    }
 }
 
 // BlockCache 的设计也体现了 RAII 思想， 它管理着一个缓冲区的生命周期。当 BlockCache 的生命周期结束之后缓冲区也会被从内存中回收
@@ -97,23 +136,100 @@ impl Drop for BlockCache {
 // 为了避免在块缓存上浪费过多内存，我们希望内存中同时只能驻留有限个磁盘块的缓冲区
 /// Use a block cache of 16 blocks
 const BLOCK_CACHE_SIZE: usize = 16;
+// 脏块只在被替换出去或者被显式同步时才写回，缓存中积累的脏块越多，替换时越可能要先等待写回，崩溃时丢失的修改也越多。
+// 因此脏块的数量超过这个阈值时，在下一次请求块缓存时主动写回所有没有被使用的脏块
+/// Write back the dirty blocks once more than half of the cache is dirty
+const DIRTY_THRESHOLD: usize = BLOCK_CACHE_SIZE / 2;
+
+// 只用过一次的块（比如顺序扫描一个大文件时读到的块）很可能不会再被用到，LRU 却会为了它们替换掉扫描之前反复使用的块。
+// LRU-2 按照倒数第二次使用的时间选择被替换的块，只用过一次的块视为倒数第二次使用无穷早，因此总是先于用过多次的块被替换
+/// Replacement policy of the block cache
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CachePolicy {
+    /// Evict the block loaded the earliest
+    Fifo,
+    /// Evict the block used the least recently
+    Lru,
+    /// Evict the least recently used block among those used only once, or the block whose
+    /// last but one use is the earliest if every block has been used more than once
+    Lru2,
+}
+
+/// Logical times of the last two uses of a cached block
+#[derive(Clone, Copy)]
+struct Uses {
+    last: u64,
+    // 只用过一次时为 None
+    previous: Option<u64>,
+}
 
 // 块缓存全局管理器的功能是：当我们要对一个磁盘块进行读写时，首先看它是否已经被载入到内存缓存中了，如果已经被载入的话则直接返回，否则需要先读取磁盘块的数据到内存缓存中
 // 如果内存中驻留的磁盘块缓冲区的数量已满，则需要遵循某种缓存替换算法将某个块的缓存从内存中移除，再将刚刚读到的块数据加入到内存缓存中。
-// 我们这里使用一种类 FIFO 的简单缓存替换算法，因此在管理器中只需维护一个队列：
+// 默认使用一种类 FIFO 的简单缓存替换算法，也可以选择 LRU 或 LRU-2 。无论哪种算法，管理器中都只需维护一个队列：
 pub struct BlockCacheManager {
-    // 队列 queue 中管理的是块编号和块缓存的二元组。块编号的类型为 usize ，而块缓存的类型则是一个 Arc<Mutex<BlockCache>>
+    // 队列 queue 中管理的是块编号、块设备、块缓存和最近两次使用时间的四元组。块编号的类型为 usize ，而块缓存的类型则是一个 Arc<Mutex<BlockCache>>
     // Arc和Mutex组合可以同时提供共享引用和互斥访问
     // 共享引用意义在于块缓存既需要在管理器 BlockCacheManager 保留一个引用，还需要以引用的形式返回给块缓存的请求者让它可以对块缓存进行访问
-    queue: VecDeque<(usize, Arc<Mutex<BlockCache>>)>,
+    // 同时存在多个块设备（比如测试中使用的内存块设备）时，编号相同的块可能来自不同的设备，因此还需要记录块所在的设备
+    queue: VecDeque<CacheEntry>,
+    // 缓存中脏块的数量，由每个 BlockCache 在变脏和被写回时更新
+    dirty_blocks: Arc<AtomicUsize>,
+    policy: CachePolicy,
+    // 逻辑时钟，每请求一次块缓存加一
+    clock: u64,
 }
 
+/// Block id, block device, the cached block and its uses
+type CacheEntry = (usize, Arc<dyn BlockDevice>, Arc<Mutex<BlockCache>>, Uses);
+
 impl BlockCacheManager {
     pub fn new() -> Self {
         Self {
             queue: VecDeque::new(),
+            dirty_blocks: Arc::new(AtomicUsize::new(0)),
+            policy: CachePolicy::Fifo,
+            clock: 0,
         }
     }
+    /// Replace cached blocks by `policy` from now on
+    pub fn set_policy(&mut self, policy: CachePolicy) {
+        self.policy = policy;
+    }
+    /// Number of dirty blocks in the cache
+    pub fn dirty_blocks(&self) -> usize {
+        self.dirty_blocks.load(Ordering::Relaxed)
+    }
+    // 只写回强引用计数恰好为 1 的块缓存：其他块缓存可能正被持有者锁住，而且很快还会被继续修改。事务还没有提交的块由 sync 跳过
+    /// Write back the dirty blocks not in use if there are more than `DIRTY_THRESHOLD` of them
+    fn write_back_dirty(&self) {
+        if self.dirty_blocks() <= DIRTY_THRESHOLD {
+            return;
+        }
+        for (_, _, cache, _) in self.queue.iter() {
+            if Arc::strong_count(cache) == 1 {
+                cache.lock().sync();
+            }
+        }
+    }
+    // 只有没有被使用（强引用计数恰好为 1）并且不属于还没有提交的事务的块缓存才能被替换出去。
+    // FIFO 和 LRU 的队列分别按照载入和最近一次使用的先后排列，从队头找到的第一个就是要替换的块。
+    // LRU-2 的队列也按照最近一次使用的先后排列，只用过一次的块的 previous 为 None ，比任何时间都早，
+    // 因此它们当中最近一次使用最早的最先被替换，其次才是倒数第二次使用最早的块
+    /// Position of the block to evict in the queue, `None` if all blocks are in use
+    fn victim(&self) -> Option<usize> {
+        let mut evictable = self
+            .queue
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, _, cache, _))| {
+                Arc::strong_count(cache) == 1 && !cache.lock().journaled
+            });
+        match self.policy {
+            CachePolicy::Fifo | CachePolicy::Lru => evictable.next(),
+            CachePolicy::Lru2 => evictable.min_by_key(|(_, (_, _, _, uses))| uses.previous),
+        }
+        .map(|(idx, _)| idx)
+    }
 
     // 从块缓存管理器中获取一个编号为 block_id 的块的块缓存，如果找不到，会从磁盘读取到内存中，还有可能会发生缓存替换
     pub fn get_block_cache(
@@ -121,22 +237,37 @@ impl BlockCacheManager {
         block_id: usize,
         block_device: Arc<dyn BlockDevice>,
     ) -> Arc<Mutex<BlockCache>> {
+        self.write_back_dirty();
+        self.clock += 1;
+        let now = self.clock;
         // 遍历整个队列试图找到一个编号相同的块缓存，如果找到了，会将块缓存管理器中保存的块缓存的引用复制一份并返回
-        if let Some(pair) = self.queue.iter().find(|pair: &&(usize, Arc<Mutex<BlockCache>>)| pair.0 == block_id) {
-            Arc::clone(&pair.1)
+        if let Some(mut idx) = self
+            .queue
+            .iter()
+            .position(|(id, device, _, _)| *id == block_id && Arc::ptr_eq(device, &block_device))
+        {
+            // LRU 和 LRU-2 把刚用过的块移到队尾，FIFO 则保持载入的顺序
+            if self.policy != CachePolicy::Fifo {
+                let entry = self.queue.remove(idx).unwrap();
+                self.queue.push_back(entry);
+                idx = self.queue.len() - 1;
+            }
+            let (_, _, cache, uses) = &mut self.queue[idx];
+            *uses = Uses {
+                last: now,
+                previous: Some(uses.last),
+            };
+            Arc::clone(cache)
         } else {
             // 找不到时，必须将块从磁盘读入内存中的缓冲区。在实际读取之前，需要判断管理器保存的块缓存数量是否已经达到了上限
             // substitute
-            if self.queue.len() == BLOCK_CACHE_SIZE {
-                // 如果达到了上限需要执行缓存替换算法，丢掉某个块缓存并空出一个空位。从队头遍历到队尾找到第一个强引用计数恰好为 1 的块缓存并将其替换出去
-                // from front to tail
-                if let Some((idx, _)) = self
-                    .queue
-                    .iter()
-                    .enumerate()
-                    .find(|(_, pair)| Arc::strong_count(&pair.1) == 1)
-                {
+            // 事务扣留的块不能被替换出去，它们占满缓存时缓存暂时超出上限，事务提交之后再逐渐替换回上限以内
+            while self.queue.len() >= BLOCK_CACHE_SIZE {
+                // 如果达到了上限需要执行缓存替换算法，丢掉某个块缓存并空出一个空位
+                if let Some(idx) = self.victim() {
                     self.queue.drain(idx..=idx);
+                } else if transaction_holding() {
+                    break;
                 } else {
                     // 队列已满且其中所有的块缓存都正在使用的情形，内核将 panic （基于简单内核设计的思路）
                     panic!("Run out of BlockCache!");
@@ -147,8 +278,14 @@ impl BlockCacheManager {
             let block_cache = Arc::new(Mutex::new(BlockCache::new(
                 block_id,
                 Arc::clone(&block_device),
+                Arc::clone(&self.dirty_blocks),
             )));
-            self.queue.push_back((block_id, Arc::clone(&block_cache)));
+            let uses = Uses {
+                last: now,
+                previous: None,
+            };
+            self.queue
+                .push_back((block_id, block_device, Arc::clone(&block_cache), uses));
             block_cache
         }
     }
@@ -160,6 +297,83 @@ lazy_static! {
     pub static ref BLOCK_CACHE_MANAGER: Mutex<BlockCacheManager> =
         Mutex::new(BlockCacheManager::new());
 }
+/// Select the replacement policy of the block cache, FIFO by default
+pub fn block_cache_set_policy(policy: CachePolicy) {
+    BLOCK_CACHE_MANAGER.lock().set_policy(policy);
+}
+/// Block device, the metadata blocks held back by the transaction on it and the max number
+/// of blocks the transaction may hold back
+type Transaction = (Arc<dyn BlockDevice>, Vec<usize>, usize);
+
+// 每个块设备上至多有一个正在进行的事务，记录下事务中被修改过的元数据块，按照修改完成的先后顺序排列
+lazy_static! {
+    static ref TRANSACTIONS: Mutex<Vec<Transaction>> = Mutex::new(Vec::new());
+}
+/// Whether any transaction is holding back blocks
+fn transaction_holding() -> bool {
+    TRANSACTIONS
+        .lock()
+        .iter()
+        .any(|(_, block_ids, _)| !block_ids.is_empty())
+}
+// 事务修改过的块全部要写入日志才能提交，既不能丢掉原子性直接写回原位，也无法在持有块缓存锁的时候提前提交。
+// 因此文件系统的每个操作都要把自己拆分成不超过日志容量的事务，超出容量说明存在错误
+/// Record a modified metadata block in the transaction on its device, if any. Return false
+/// if there is no transaction and the block should be written back at once
+fn transaction_record(block_device: &Arc<dyn BlockDevice>, block_id: usize) -> bool {
+    let mut transactions = TRANSACTIONS.lock();
+    match transactions
+        .iter_mut()
+        .find(|(device, _, _)| Arc::ptr_eq(device, block_device))
+    {
+        Some((_, block_ids, capacity)) => {
+            if !block_ids.contains(&block_id) {
+                assert!(
+                    block_ids.len() < *capacity,
+                    "transaction exceeds the journal"
+                );
+                block_ids.push(block_id);
+            }
+            true
+        }
+        None => false,
+    }
+}
+/// Start a transaction on `block_device` holding back at most `capacity` blocks: modified
+/// metadata blocks are held back from then on
+pub fn block_cache_begin(block_device: &Arc<dyn BlockDevice>, capacity: usize) {
+    let mut transactions = TRANSACTIONS.lock();
+    assert!(!transactions
+        .iter()
+        .any(|(device, _, _)| Arc::ptr_eq(device, block_device)));
+    transactions.push((Arc::clone(block_device), Vec::new(), capacity));
+}
+/// End the transaction on `block_device`, return the metadata blocks it modified in order.
+/// They stay held back until installed by `block_cache_install`
+pub fn block_cache_end(block_device: &Arc<dyn BlockDevice>) -> Vec<usize> {
+    let mut transactions = TRANSACTIONS.lock();
+    let pos = transactions
+        .iter()
+        .position(|(device, _, _)| Arc::ptr_eq(device, block_device))
+        .unwrap();
+    transactions.remove(pos).1
+}
+// 事务提交之后，按照 block_ids 的顺序将这些块写回原位，它们之后就可以被正常替换出去了
+/// Write the blocks of an ended transaction back to `block_device` in order
+pub fn block_cache_install(block_ids: &[usize], block_device: &Arc<dyn BlockDevice>) {
+    let manager = BLOCK_CACHE_MANAGER.lock();
+    for block_id in block_ids {
+        if let Some((_, _, cache, _)) = manager
+            .queue
+            .iter()
+            .find(|(id, device, _, _)| id == block_id && Arc::ptr_eq(device, block_device))
+        {
+            let mut cache = cache.lock();
+            cache.journaled = false;
+            cache.sync();
+        }
+    }
+}
 // 对于其他模块而言，就可以直接通过 get_block_cache 方法来请求块缓存了。
 // 它返回的是一个 Arc<Mutex<BlockCache>> ，调用者需要通过 .lock() 获取里层互斥锁 Mutex 才能对最里面的 BlockCache 进行操作
 /// Get the block cache corresponding to the given block id and block device
@@ -171,10 +385,123 @@ pub fn get_block_cache(
         .lock()
         .get_block_cache(block_id, block_device)
 }
-/// Sync all block cache to block device
-pub fn block_cache_sync_all() {
+// 获取保存元数据的块的块缓存，并将它标记为写穿：之后对它的每次修改都会立即写回磁盘。
+// 嵌套的修改由内向外依次写回，例如扩大文件时先写回位图和索引块，最后才写回索引节点，
+// 中途崩溃最多泄漏几个已分配的块，而不会让索引节点指向未分配的块
+/// Get the block cache of a metadata block, which is written back on every modification
+pub fn get_metadata_block_cache(
+    block_id: usize,
+    block_device: Arc<dyn BlockDevice>,
+) -> Arc<Mutex<BlockCache>> {
+    let block_cache = get_block_cache(block_id, block_device);
+    block_cache.lock().write_through = true;
+    block_cache
+}
+// 只同步给定的若干个块：不在缓存中的块要么从未被修改过，要么已经在被替换出去时写回了磁盘，因此不需要处理。
+// BlockCache::sync 在块缓存的锁内检查并清除 modified 标记，同一个块被多处同时同步时也只会写回一次
+/// Sync the cached blocks among `block_ids` of `block_device` to it
+pub fn block_cache_sync(block_ids: &[usize], block_device: &Arc<dyn BlockDevice>) {
+    let manager = BLOCK_CACHE_MANAGER.lock();
+    for (block_id, device, cache, _) in manager.queue.iter() {
+        if block_ids.contains(block_id) && Arc::ptr_eq(device, block_device) {
+            cache.lock().sync();
+        }
+    }
+}
+// 将给定的若干个块从缓存中移除，被修改过的块在 BlockCache 被回收时写回磁盘；仍在被使用（强引用计数不为 1）的块继续留在缓存中
+/// Evict the cached blocks among `block_ids` of `block_device` which are not in use
+pub fn block_cache_evict(block_ids: &[usize], block_device: &Arc<dyn BlockDevice>) {
+    BLOCK_CACHE_MANAGER
+        .lock()
+        .queue
+        .retain(|(block_id, device, cache, _)| {
+            !(block_ids.contains(block_id)
+                && Arc::ptr_eq(device, block_device)
+                && Arc::strong_count(cache) == 1
+                && !cache.lock().journaled)
+        });
+}
+// 事务提交、创建文件之后的写回等操作内部的 flush 无法把失败交给调用者，因此记下 flush 失败过的设备，
+// 由下一次 sync/syncfs 报告给用户，就像 Linux 在下一次 fsync 时报告之前的回写错误一样
+lazy_static! {
+    static ref FLUSH_FAILED: Mutex<Vec<Arc<dyn BlockDevice>>> = Mutex::new(Vec::new());
+}
+/// Flush `block_device`, remembering the failure for the next `block_cache_try_sync` to
+/// report if it cannot flush. Return false in that case
+pub fn block_cache_flush(block_device: &Arc<dyn BlockDevice>) -> bool {
+    if block_device.flush() {
+        return true;
+    }
+    let mut failed = FLUSH_FAILED.lock();
+    if !failed
+        .iter()
+        .any(|device| Arc::ptr_eq(device, block_device))
+    {
+        failed.push(Arc::clone(block_device));
+    }
+    false
+}
+// 将块写回之后，再要求这些块所在的设备将自身缓存的数据也写入存储介质
+/// Sync all block cache to block device and flush the devices. Return false if a device
+/// cannot flush
+pub fn block_cache_sync_all() -> bool {
     let manager = BLOCK_CACHE_MANAGER.lock();
-    for (_, cache) in manager.queue.iter() {
+    let mut devices: Vec<Arc<dyn BlockDevice>> = Vec::new();
+    for (_, device, cache, _) in manager.queue.iter() {
         cache.lock().sync();
+        if !devices.iter().any(|synced| Arc::ptr_eq(synced, device)) {
+            devices.push(Arc::clone(device));
+        }
+    }
+    let mut flushed = true;
+    for device in devices.iter() {
+        flushed &= block_cache_flush(device);
+    }
+    flushed
+}
+// 供 sync/syncfs 使用：正被其他持有者锁住的块可能还在修改当中，跳过它们而不是等待，它们会在下一次同步或者被替换出去时写回。
+// 之前记下的 flush 失败在这里报告一次之后就被忘掉
+/// Sync the cached blocks not locked by others to their devices and flush the devices, only
+/// the blocks of `block_device` if given. Return the number of blocks skipped, or None if a
+/// device cannot flush or has failed to flush since the last call
+pub fn block_cache_try_sync(block_device: Option<&Arc<dyn BlockDevice>>) -> Option<usize> {
+    let manager = BLOCK_CACHE_MANAGER.lock();
+    let mut devices: Vec<Arc<dyn BlockDevice>> = Vec::new();
+    let mut skipped = 0;
+    for (_, device, cache, _) in manager.queue.iter() {
+        if block_device.is_some_and(|block_device| !Arc::ptr_eq(device, block_device)) {
+            continue;
+        }
+        match cache.try_lock() {
+            Some(mut cache) => cache.sync(),
+            None => skipped += 1,
+        }
+        if !devices.iter().any(|synced| Arc::ptr_eq(synced, device)) {
+            devices.push(Arc::clone(device));
+        }
+    }
+    let mut flushed = true;
+    for device in devices.iter() {
+        flushed &= block_cache_flush(device);
+    }
+    let mut failed = FLUSH_FAILED.lock();
+    failed.retain(|device| {
+        let reported = match block_device {
+            Some(block_device) => Arc::ptr_eq(device, block_device),
+            None => true,
+        };
+        flushed &= !reported;
+        !reported
+    });
+    flushed.then_some(skipped)
+}
+// 模拟断电，用于测试崩溃之后文件系统的一致性：缓存中还没有写回的修改全部丢失。
+// 块缓存被回收时会写回被修改过的块，因此要先让它们忘掉自己的修改
+/// Drop all the cached blocks without writing back the modified ones, as if the power was lost
+pub fn block_cache_discard_all() {
+    let mut manager = BLOCK_CACHE_MANAGER.lock();
+    for (_, _, cache, _) in manager.queue.iter() {
+        cache.lock().discard();
     }
+    manager.queue.clear();
 }
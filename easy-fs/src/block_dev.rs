@@ -1,4 +1,8 @@
+use super::BLOCK_SZ;
+use alloc::vec;
+use alloc::vec::Vec;
 use core::any::Any;
+use spin::Mutex;
 /// Trait for block devices
 /// which reads and writes data in the unit of blocks
 // Any trait 是一个 trait 对象，它允许类型安全地对任何类型进行类型检查和类型转换
@@ -8,4 +12,35 @@ pub trait BlockDevice: Send + Sync + Any {
     fn read_block(&self, block_id: usize, buf: &mut [u8]);
     ///Write data from buffer to block
     fn write_block(&self, block_id: usize, buf: &[u8]);
+    // 设备自身也可能缓存写入的数据，flush 要求设备将它们真正写入持久化的存储介质。没有缓存的设备（比如内存中的设备）不需要实现它。
+    // 无法发出 flush 请求的设备必须返回 false ，而不是假装已经写入了存储介质
+    ///Make the data written before persistent. Return false if the device cannot flush,
+    ///true by default for devices without a write cache
+    fn flush(&self) -> bool {
+        true
+    }
+}
+
+// 以内存中的一段缓冲区模拟的块设备，不需要真实的磁盘就可以在内存中创建和使用 easy-fs ，主要用于测试
+/// A block device backed by memory
+pub struct MemBlockDevice(Mutex<Vec<u8>>);
+
+impl MemBlockDevice {
+    /// Create a zeroed block device of `blocks` blocks
+    pub fn new(blocks: usize) -> Self {
+        Self(Mutex::new(vec![0u8; blocks * BLOCK_SZ]))
+    }
+}
+
+impl BlockDevice for MemBlockDevice {
+    fn read_block(&self, block_id: usize, buf: &mut [u8]) {
+        let data = self.0.lock();
+        let start = block_id * BLOCK_SZ;
+        buf.copy_from_slice(&data[start..start + BLOCK_SZ]);
+    }
+    fn write_block(&self, block_id: usize, buf: &[u8]) {
+        let mut data = self.0.lock();
+        let start = block_id * BLOCK_SZ;
+        data[start..start + BLOCK_SZ].copy_from_slice(buf);
+    }
 }
@@ -0,0 +1,108 @@
+// 离线的碎片整理：依次把每个文件的内容读出并释放它原来的块，再分配一段连续的块写回去，使顺序读取时访问的块在设备上也是连续的
+// 整理过程中索引节点的编号和目录项都不会改变，只会改变索引节点中的块编号以及数据位图，所以整理时不能有打开着的 Inode
+use super::{
+    get_block_cache, DirEntry, DiskInode, EasyFileSystem, SuperBlock, BLOCK_SZ, DIRENT_SZ,
+};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Relocate the blocks of each file to be contiguous, return the number of files relocated
+pub fn defragment(efs: &mut EasyFileSystem) -> usize {
+    let block_device = Arc::clone(&efs.block_device);
+    let data_area_blocks = get_block_cache(0, Arc::clone(&block_device))
+        .lock()
+        .read(0, |super_block: &SuperBlock| {
+            super_block.data_area_blocks as usize
+        });
+    // 先从根目录出发收集所有可以到达的索引节点，整理不会改变目录中的内容
+    let mut inodes: Vec<u32> = Vec::new();
+    let mut stack: Vec<u32> = Vec::from([0]);
+    while let Some(inode_id) = stack.pop() {
+        inodes.push(inode_id);
+        let (block_id, block_offset) = efs.get_disk_inode_pos(inode_id);
+        get_block_cache(block_id as usize, Arc::clone(&block_device))
+            .lock()
+            .read(block_offset, |disk_inode: &DiskInode| {
+                if disk_inode.is_dir() {
+                    let mut dirent = DirEntry::empty();
+                    for i in 0..disk_inode.size as usize / DIRENT_SZ {
+                        disk_inode.read_at(i * DIRENT_SZ, dirent.as_bytes_mut(), &block_device);
+                        if !dirent.name().is_empty() {
+                            stack.push(dirent.inode_number());
+                        }
+                    }
+                }
+            });
+    }
+
+    let mut relocated = 0;
+    for inode_id in inodes {
+        let (block_id, block_offset) = efs.get_disk_inode_pos(inode_id);
+        let inode_block = get_block_cache(block_id as usize, Arc::clone(&block_device));
+        // 数据块和索引块按照 alloc_blocks 分配它们的顺序排列，已经连续的文件不需要移动。空洞不占用块，整理之后仍然是空洞
+        let (size, blocks) = inode_block
+            .lock()
+            .read(block_offset, |disk_inode: &DiskInode| {
+                (
+                    disk_inode.size,
+                    disk_inode.referenced_blocks(|_| true, &block_device),
+                )
+            });
+        if blocks.windows(2).all(|pair| pair[1] == pair[0] + 1) {
+            continue;
+        }
+        let mapped: Vec<u32> = inode_block
+            .lock()
+            .read(block_offset, |disk_inode: &DiskInode| {
+                (0..disk_inode.data_blocks())
+                    .filter(|inner_id| disk_inode.get_block_id(*inner_id, &block_device) != 0)
+                    .collect()
+            });
+        let mut data = vec![0u8; size as usize];
+        let freed = inode_block
+            .lock()
+            .modify(block_offset, |disk_inode: &mut DiskInode| {
+                disk_inode.read_at(0, &mut data, &block_device);
+                disk_inode.clear_size(&block_device)
+            });
+        for block_id in freed {
+            efs.dealloc_data(block_id);
+        }
+        // 找不到足够长的连续空闲区域时退回到逐块分配
+        let count = blocks.len();
+        let new_blocks: Vec<u32> =
+            match efs
+                .data_bitmap
+                .alloc_contiguous(&block_device, count, data_area_blocks)
+            {
+                Some(start) => (start..start + count)
+                    .map(|bit| efs.get_data_block_id(bit as u32))
+                    .collect(),
+                None => (0..count).map(|_| efs.alloc_data()).collect(),
+            };
+        let mut new_blocks = new_blocks.into_iter();
+        inode_block
+            .lock()
+            .modify(block_offset, |disk_inode: &mut DiskInode| {
+                disk_inode.increase_size(size);
+                for inner_id in mapped {
+                    disk_inode.alloc_blocks(
+                        inner_id,
+                        inner_id + 1,
+                        || new_blocks.next().unwrap(),
+                        &block_device,
+                    );
+                    let start = inner_id as usize * BLOCK_SZ;
+                    let end = (start + BLOCK_SZ).min(size as usize);
+                    disk_inode.write_at(start, &data[start..end], &block_device);
+                }
+            });
+        // 原来没有指向任何块的空索引块不会再被分配，把多余的块还回去
+        for block_id in new_blocks {
+            efs.dealloc_data(block_id);
+        }
+        relocated += 1;
+    }
+    relocated
+}
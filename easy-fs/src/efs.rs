@@ -2,11 +2,14 @@
 
 // 从这一层开始，所有的数据结构就都放在内存上了
 use super::{
-    block_cache_sync_all, get_block_cache, Bitmap, BlockDevice, DiskInode, DiskInodeType, Inode,
-    SuperBlock,
+    block_cache_begin, block_cache_end, block_cache_sync_all, block_cache_try_sync, defrag, fsck,
+    get_block_cache, get_metadata_block_cache, Bitmap, BlockDevice, DiskInode, DiskInodeType,
+    FsckProblem, Inode, Journal, SuperBlock, JOURNAL_BLOCKS,
 };
 use crate::BLOCK_SZ;
+use alloc::collections::{BTreeMap, BTreeSet};
 use alloc::sync::Arc;
+use alloc::vec::Vec;
 use spin::Mutex;
 ///An easy file system on block
 pub struct EasyFileSystem {
@@ -21,24 +24,74 @@ pub struct EasyFileSystem {
     // 还记录下索引节点区域和数据块区域起始块编号方便确定每个索引节点和数据块在磁盘上的具体位置
     inode_area_start_block: u32,
     data_area_start_block: u32,
+    // 每个索引节点当前有多少个 Inode 指向它。被 rename 覆盖掉的目标文件先从目录中移除，
+    // 记在 orphan_inodes 中，等到最后一个指向它的 Inode 被释放时才真正回收它的数据块和索引节点
+    open_inodes: BTreeMap<u32, usize>,
+    orphan_inodes: BTreeSet<u32>,
+    // 旧镜像没有日志区域，元数据修改仍然逐块直接写回磁盘
+    journal: Option<Journal>,
 }
 
 type DataBlock = [u8; BLOCK_SZ];
+
+// 文件系统的使用情况，以 C 的内存布局排列，内核可以直接将它交给应用
+/// Usage of a filesystem
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FsStat {
+    /// size of a block in bytes
+    pub block_size: usize,
+    /// number of blocks in the data area
+    pub total_blocks: usize,
+    /// number of free blocks in the data area
+    pub free_blocks: usize,
+    /// max number of inodes
+    pub total_inodes: usize,
+    /// number of free inodes
+    pub free_inodes: usize,
+}
+
 /// An easy fs over a block device
 impl EasyFileSystem {
-    /// A data block of block size
+    /// Create a filesystem with `inode_bitmap_blocks` blocks of inode bitmap, each of which
+    /// brings 4096 inodes
     pub fn create(
         block_device: Arc<dyn BlockDevice>,
         total_blocks: u32,
         inode_bitmap_blocks: u32,
     ) -> Arc<Mutex<Self>> {
+        Self::create_with_inodes(
+            block_device,
+            total_blocks,
+            inode_bitmap_blocks * (BLOCK_SZ * 8) as u32,
+        )
+        .expect("Too many inodes for the block device!")
+    }
+    // 由镜像的制作者决定索引节点的数量：存放大量小文件时需要更多的索引节点，反之可以把更多的块留给数据区域。
+    // 索引节点区域和索引节点位图的大小都由索引节点的数量决定，剩下的块才属于数据位图和数据区域
+    /// Create a filesystem with room for `inodes` inodes, rounded up to fill the blocks of the
+    /// inode area. Return `None` if the inodes leave no room for data blocks on the device
+    pub fn create_with_inodes(
+        block_device: Arc<dyn BlockDevice>,
+        total_blocks: u32,
+        inodes: u32,
+    ) -> Option<Arc<Mutex<Self>>> {
         // calculate block size of areas & create bitmaps
-        let inode_bitmap = Bitmap::new(1, inode_bitmap_blocks as usize);
-        let inode_num = inode_bitmap.maximum();
-        let inode_area_blocks =
-            ((inode_num * core::mem::size_of::<DiskInode>() + BLOCK_SZ - 1) / BLOCK_SZ) as u32;
+        let inodes_per_block = (BLOCK_SZ / core::mem::size_of::<DiskInode>()) as u32;
+        let inode_area_blocks = inodes.div_ceil(inodes_per_block);
+        if inodes == 0 || inode_area_blocks >= total_blocks {
+            return None;
+        }
+        let inode_num = inode_area_blocks * inodes_per_block;
+        let inode_bitmap_blocks = inode_num.div_ceil((BLOCK_SZ * 8) as u32);
         let inode_total_blocks = inode_bitmap_blocks + inode_area_blocks;
-        let data_total_blocks = total_blocks - 1 - inode_total_blocks;
+        // 除了超级块、索引节点相关的区域和日志区域，至少还要留下一个数据位图块和一个数据块
+        if 1 + inode_total_blocks + JOURNAL_BLOCKS + 2 > total_blocks {
+            return None;
+        }
+        let inode_bitmap =
+            Bitmap::with_maximum(1, inode_bitmap_blocks as usize, inode_num as usize);
+        let data_total_blocks = total_blocks - 1 - inode_total_blocks - JOURNAL_BLOCKS;
         let data_bitmap_blocks = (data_total_blocks + 4096) / 4097;
         let data_area_blocks = data_total_blocks - data_bitmap_blocks;
         let data_bitmap = Bitmap::new(
@@ -51,6 +104,9 @@ impl EasyFileSystem {
             data_bitmap,
             inode_area_start_block: 1 + inode_bitmap_blocks,
             data_area_start_block: 1 + inode_total_blocks + data_bitmap_blocks,
+            open_inodes: BTreeMap::new(),
+            orphan_inodes: BTreeSet::new(),
+            journal: Some(Journal::new(total_blocks - JOURNAL_BLOCKS, JOURNAL_BLOCKS)),
         };
         // 将块设备的前 total_blocks 个块清零，因为 easy-fs 要用到它们，这也是为初始化做准备
         // clear all blocks
@@ -65,60 +121,136 @@ impl EasyFileSystem {
         }
         // 将位于块设备编号为 0 块上的超级块进行初始化，只需传入之前计算得到的每个区域的块数就行了
         // initialize SuperBlock
-        get_block_cache(0, Arc::clone(&block_device)).lock().modify(
-            0,
-            |super_block: &mut SuperBlock| {
+        get_metadata_block_cache(0, Arc::clone(&block_device))
+            .lock()
+            .modify(0, |super_block: &mut SuperBlock| {
                 super_block.initialize(
                     total_blocks,
                     inode_bitmap_blocks,
                     inode_area_blocks,
                     data_bitmap_blocks,
                     data_area_blocks,
+                    JOURNAL_BLOCKS,
                 );
-            },
-        );
+            });
         // write back immediately
         // create a inode for root node "/"
         assert_eq!(efs.alloc_inode(), 0);
         let (root_inode_block_id, root_inode_offset) = efs.get_disk_inode_pos(0);
-        get_block_cache(root_inode_block_id as usize, Arc::clone(&block_device))
+        get_metadata_block_cache(root_inode_block_id as usize, Arc::clone(&block_device))
             .lock()
             .modify(root_inode_offset, |disk_inode: &mut DiskInode| {
                 disk_inode.initialize(DiskInodeType::Directory);
             });
         block_cache_sync_all();
-        Arc::new(Mutex::new(efs))
+        Some(Arc::new(Mutex::new(efs)))
     }
     // 通过 open 方法可以从一个已写入了 easy-fs 镜像的块设备上打开我们的 easy-fs
     /// Open a block device as a filesystem
     pub fn open(block_device: Arc<dyn BlockDevice>) -> Arc<Mutex<Self>> {
         // read SuperBlock
-        get_block_cache(0, Arc::clone(&block_device))
-            .lock()
-            .read(0, |super_block: &SuperBlock| {
+        let mut efs = get_block_cache(0, Arc::clone(&block_device)).lock().read(
+            0,
+            |super_block: &SuperBlock| {
                 assert!(super_block.is_valid(), "Error loading EFS!");
                 let inode_total_blocks =
                     super_block.inode_bitmap_blocks + super_block.inode_area_blocks;
-                let efs = Self {
+                // 索引节点的数量受限于索引节点区域的大小，可能少于索引节点位图中 bit 的数量
+                let inodes_per_block = BLOCK_SZ / core::mem::size_of::<DiskInode>();
+                let inode_num = (super_block.inode_area_blocks as usize * inodes_per_block)
+                    .min(super_block.inode_bitmap_blocks as usize * BLOCK_SZ * 8);
+                Self {
                     block_device,
-                    inode_bitmap: Bitmap::new(1, super_block.inode_bitmap_blocks as usize),
+                    inode_bitmap: Bitmap::with_maximum(
+                        1,
+                        super_block.inode_bitmap_blocks as usize,
+                        inode_num,
+                    ),
                     data_bitmap: Bitmap::new(
                         (1 + inode_total_blocks) as usize,
                         super_block.data_bitmap_blocks as usize,
                     ),
                     inode_area_start_block: 1 + super_block.inode_bitmap_blocks,
                     data_area_start_block: 1 + inode_total_blocks + super_block.data_bitmap_blocks,
-                };
-                Arc::new(Mutex::new(efs))
-            })
+                    open_inodes: BTreeMap::new(),
+                    orphan_inodes: BTreeSet::new(),
+                    journal: (super_block.journal_blocks > 0).then(|| {
+                        Journal::new(super_block.journal_start, super_block.journal_blocks)
+                    }),
+                }
+            },
+        );
+        // 先重放上次崩溃时已经提交的事务，再扫描一遍位图得到已分配的数量
+        efs.recover();
+        efs.inode_bitmap.recount(&efs.block_device);
+        efs.data_bitmap.recount(&efs.block_device);
+        Arc::new(Mutex::new(efs))
+    }
+    /// Replay the transaction committed to the journal but not yet fully written back before
+    /// a crash, return the number of blocks written back
+    pub fn recover(&self) -> usize {
+        self.journal
+            .as_ref()
+            .map_or(0, |journal| journal.recover(&self.block_device))
+    }
+    // 创建、删除和重命名都要修改多个元数据块，它们在 begin 和 commit 之间的修改作为一个事务整体提交。
+    // 一个事务修改的元数据块不能超过日志的容量
+    /// Start a transaction, holding back the metadata blocks modified from now on
+    pub(crate) fn begin(&self) {
+        if let Some(journal) = self.journal.as_ref() {
+            block_cache_begin(&self.block_device, journal.capacity());
+        }
+    }
+    /// Commit the transaction started by `begin` through the journal
+    pub(crate) fn commit(&self) {
+        if let Some(journal) = self.journal.as_ref() {
+            let block_ids = block_cache_end(&self.block_device);
+            journal.commit(&block_ids, &self.block_device);
+        }
+    }
+    // 已分配的块和索引节点的数量由位图中的计数器直接给出，不需要扫描位图
+    /// Get the usage of the filesystem
+    pub fn stat(&self) -> FsStat {
+        let total_blocks = get_block_cache(0, Arc::clone(&self.block_device))
+            .lock()
+            .read(0, |super_block: &SuperBlock| {
+                super_block.data_area_blocks as usize
+            });
+        let total_inodes = self.inode_bitmap.maximum();
+        FsStat {
+            block_size: BLOCK_SZ,
+            total_blocks,
+            free_blocks: total_blocks - self.data_bitmap.allocated(),
+            total_inodes,
+            free_inodes: self.inode_bitmap.free(),
+        }
+    }
+    // 调用者持有文件系统的锁时没有正在进行的文件系统操作，不会有块被跳过
+    /// Sync the cached blocks of the filesystem to the device and flush it, skipping the
+    /// blocks locked by others. Return the number of blocks skipped, or None if the device
+    /// cannot flush or has failed to flush since the last sync
+    pub fn sync(&self) -> Option<usize> {
+        block_cache_try_sync(Some(&self.block_device))
+    }
+    // 检查文件系统的一致性并返回发现的所有问题，检查过程不会修改磁盘上的任何内容
+    /// Check the consistency of the filesystem, return the problems found
+    pub fn check(&self) -> Vec<FsckProblem> {
+        fsck::check(self)
+    }
+    // 每个 Inode 都持有文件系统的一个引用，只有调用者持有唯一的引用时才能确定没有打开着的文件，可以安全地移动数据块
+    /// Relocate the blocks of each file to be contiguous while no inode is open,
+    /// return the number of files relocated, or `None` if the filesystem is in use
+    pub fn defragment(efs: &Arc<Mutex<Self>>) -> Option<usize> {
+        if Arc::strong_count(efs) > 1 {
+            return None;
+        }
+        let relocated = defrag::defragment(&mut efs.lock());
+        block_cache_sync_all();
+        Some(relocated)
     }
     /// Get the root inode of the filesystem
     pub fn root_inode(efs: &Arc<Mutex<Self>>) -> Inode {
-        let block_device = Arc::clone(&efs.lock().block_device);
-        // acquire efs lock temporarily
-        let (block_id, block_offset) = efs.lock().get_disk_inode_pos(0);
-        // release efs lock
-        Inode::new(block_id, block_offset, Arc::clone(efs), block_device)
+        Inode::new(0, efs, &mut efs.lock())
     }
     /// Get inode by id
     pub fn get_disk_inode_pos(&self, inode_id: u32) -> (u32, usize) {
@@ -130,6 +262,13 @@ impl EasyFileSystem {
             (inode_id % inodes_per_block) as usize * inode_size,
         )
     }
+    /// Get inode id by the position of its disk inode, the inverse of `get_disk_inode_pos`
+    pub fn get_inode_id(&self, block_id: u32, block_offset: usize) -> u32 {
+        let inode_size = core::mem::size_of::<DiskInode>();
+        let inodes_per_block = (BLOCK_SZ / inode_size) as u32;
+        (block_id - self.inode_area_start_block) * inodes_per_block
+            + (block_offset / inode_size) as u32
+    }
     /// Get data block by id
     pub fn get_data_block_id(&self, data_block_id: u32) -> u32 {
         self.data_area_start_block + data_block_id
@@ -158,4 +297,45 @@ impl EasyFileSystem {
             (block_id - self.data_area_start_block) as usize,
         )
     }
+    /// Record one more vfs inode referring to the inode
+    pub(crate) fn open_inode(&mut self, inode_id: u32) {
+        *self.open_inodes.entry(inode_id).or_insert(0) += 1;
+    }
+    /// Record one less vfs inode referring to the inode, free it if it is the last one
+    /// referring to an orphan inode
+    pub(crate) fn close_inode(&mut self, inode_id: u32) {
+        let count = self.open_inodes.get_mut(&inode_id).unwrap();
+        *count -= 1;
+        if *count == 0 {
+            self.open_inodes.remove(&inode_id);
+            if self.orphan_inodes.remove(&inode_id) {
+                self.free_inode(inode_id);
+            }
+        }
+    }
+    // 已经从目录中移除的索引节点：没有打开着的 Inode 时立即回收，否则推迟到最后一个 Inode 被释放时
+    /// Free an inode no longer in any directory once no vfs inode refers to it
+    pub(crate) fn orphan_inode(&mut self, inode_id: u32) {
+        if self.open_inodes.contains_key(&inode_id) {
+            self.orphan_inodes.insert(inode_id);
+        } else {
+            self.free_inode(inode_id);
+        }
+    }
+    /// Deallocate an inode along with its data blocks
+    fn free_inode(&mut self, inode_id: u32) {
+        let (block_id, block_offset) = self.get_disk_inode_pos(inode_id);
+        let data_blocks_dealloc =
+            get_metadata_block_cache(block_id as usize, Arc::clone(&self.block_device))
+                .lock()
+                .modify(block_offset, |disk_inode: &mut DiskInode| {
+                    disk_inode.clear_size(&self.block_device)
+                });
+        for data_block in data_blocks_dealloc.into_iter() {
+            self.dealloc_data(data_block);
+        }
+        self.inode_bitmap
+            .dealloc(&self.block_device, inode_id as usize);
+        block_cache_sync_all();
+    }
 }
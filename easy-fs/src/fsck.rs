@@ -0,0 +1,151 @@
+// 只读的一致性检查：从根目录出发遍历所有可以到达的索引节点，收集它们引用的数据块和索引块，再与两个位图对照。
+// 每个索引节点和每个块至多被访问一次，检查的时间与镜像中元数据的规模成正比；发现的问题只会被报告，不会被修复
+use super::{
+    get_block_cache, DirEntry, DiskInode, EasyFileSystem, SuperBlock, BLOCK_SZ, DIRENT_SZ,
+};
+use alloc::collections::BTreeSet;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter, Result};
+
+/// A problem found by the consistency checker
+#[derive(Debug, PartialEq, Eq)]
+pub enum FsckProblem {
+    /// The super block does not carry the easy-fs magic number
+    BadMagic,
+    /// A directory entry refers to an inode id out of the inode area
+    InodeOutOfRange(u32),
+    /// A reachable inode is free in the inode bitmap
+    InodeNotAllocated(u32),
+    /// An inode allocated in the inode bitmap is not reachable from the root directory
+    InodeLeaked(u32),
+    /// The inode (first) refers to a block (second) out of the data area
+    BlockOutOfRange(u32, u32),
+    /// The inode (first) refers to a block (second) which is free in the data bitmap
+    BlockNotAllocated(u32, u32),
+    /// The inode (first) refers to a block (second) which has already been referred
+    BlockMultiplyReferenced(u32, u32),
+    /// A block allocated in the data bitmap is not referred by any inode
+    BlockLeaked(u32),
+}
+
+impl Display for FsckProblem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Self::BadMagic => write!(f, "bad magic number in super block"),
+            Self::InodeOutOfRange(inode_id) => write!(f, "inode {} is out of range", inode_id),
+            Self::InodeNotAllocated(inode_id) => {
+                write!(f, "inode {} is in use but not allocated", inode_id)
+            }
+            Self::InodeLeaked(inode_id) => {
+                write!(f, "inode {} is allocated but unreachable", inode_id)
+            }
+            Self::BlockOutOfRange(inode_id, block_id) => {
+                write!(f, "inode {} refers to invalid block {}", inode_id, block_id)
+            }
+            Self::BlockNotAllocated(inode_id, block_id) => write!(
+                f,
+                "inode {} refers to unallocated block {}",
+                inode_id, block_id
+            ),
+            Self::BlockMultiplyReferenced(inode_id, block_id) => write!(
+                f,
+                "inode {} refers to block {} which is already in use",
+                inode_id, block_id
+            ),
+            Self::BlockLeaked(block_id) => {
+                write!(f, "block {} is allocated but not referred", block_id)
+            }
+        }
+    }
+}
+
+/// Check the consistency of the filesystem without modifying it
+pub fn check(efs: &EasyFileSystem) -> Vec<FsckProblem> {
+    let block_device = &efs.block_device;
+    let mut problems: Vec<FsckProblem> = Vec::new();
+    // 超级块不合法时各个区域的位置都不可信，没有必要继续检查
+    let (valid, inode_area_blocks, data_area_blocks) = get_block_cache(0, Arc::clone(block_device))
+        .lock()
+        .read(0, |super_block: &SuperBlock| {
+            (
+                super_block.is_valid(),
+                super_block.inode_area_blocks,
+                super_block.data_area_blocks,
+            )
+        });
+    if !valid {
+        problems.push(FsckProblem::BadMagic);
+        return problems;
+    }
+    let inodes_per_block = (BLOCK_SZ / core::mem::size_of::<DiskInode>()) as u32;
+    let inode_count = (inode_area_blocks * inodes_per_block).min(efs.inode_bitmap.maximum() as u32);
+    let data_start = efs.get_data_block_id(0);
+    let data_end = data_start + data_area_blocks;
+    let in_data_area = |block_id: u32| block_id >= data_start && block_id < data_end;
+    let allocated_inodes: BTreeSet<u32> = efs
+        .inode_bitmap
+        .allocated_bits(block_device)
+        .into_iter()
+        .map(|bit| bit as u32)
+        .collect();
+    let allocated_blocks: BTreeSet<u32> = efs
+        .data_bitmap
+        .allocated_bits(block_device)
+        .into_iter()
+        .map(|bit| efs.get_data_block_id(bit as u32))
+        .collect();
+
+    let mut reached_inodes: BTreeSet<u32> = BTreeSet::new();
+    let mut referenced_blocks: BTreeSet<u32> = BTreeSet::new();
+    let mut stack: Vec<u32> = Vec::from([0]);
+    reached_inodes.insert(0);
+    while let Some(inode_id) = stack.pop() {
+        if !allocated_inodes.contains(&inode_id) {
+            problems.push(FsckProblem::InodeNotAllocated(inode_id));
+        }
+        let (block_id, block_offset) = efs.get_disk_inode_pos(inode_id);
+        let children = get_block_cache(block_id as usize, Arc::clone(block_device))
+            .lock()
+            .read(block_offset, |disk_inode: &DiskInode| {
+                let mut blocks_valid = true;
+                for block_id in disk_inode.referenced_blocks(in_data_area, block_device) {
+                    if !in_data_area(block_id) {
+                        problems.push(FsckProblem::BlockOutOfRange(inode_id, block_id));
+                        blocks_valid = false;
+                    } else if !referenced_blocks.insert(block_id) {
+                        problems.push(FsckProblem::BlockMultiplyReferenced(inode_id, block_id));
+                    } else if !allocated_blocks.contains(&block_id) {
+                        problems.push(FsckProblem::BlockNotAllocated(inode_id, block_id));
+                    }
+                }
+                // 只有所有的块都位于数据区域之内时，才能安全地读出目录中的目录项
+                let mut children: Vec<u32> = Vec::new();
+                if disk_inode.is_dir() && blocks_valid {
+                    let mut dirent = DirEntry::empty();
+                    for i in 0..disk_inode.size as usize / DIRENT_SZ {
+                        disk_inode.read_at(i * DIRENT_SZ, dirent.as_bytes_mut(), block_device);
+                        if !dirent.name().is_empty() {
+                            children.push(dirent.inode_number());
+                        }
+                    }
+                }
+                children
+            });
+        for child in children {
+            if child >= inode_count {
+                problems.push(FsckProblem::InodeOutOfRange(child));
+            } else if reached_inodes.insert(child) {
+                stack.push(child);
+            }
+        }
+    }
+
+    for inode_id in allocated_inodes.difference(&reached_inodes) {
+        problems.push(FsckProblem::InodeLeaked(*inode_id));
+    }
+    for block_id in allocated_blocks.difference(&referenced_blocks) {
+        problems.push(FsckProblem::BlockLeaked(*block_id));
+    }
+    problems
+}
@@ -0,0 +1,95 @@
+// 日志区域保证一次元数据修改要么全部生效，要么完全没有发生：事务修改过的元数据块先完整地写入日志区域，
+// 再写入日志头作为提交点，之后才写回它们在磁盘上的原位。在写回的过程中崩溃，下次挂载时按照日志头重放一遍即可。
+// 日志区域的第一个块是日志头，依次记录事务中的块数和每个块的原位编号，之后的块依次存放这些块的新内容。
+// 日志区域直接通过块设备读写，不经过块缓存。设备无法 flush 时写入的顺序得不到保证，失败被记下并由下一次 sync 报告
+use super::{
+    block_cache_flush, block_cache_install, block_cache_sync, get_block_cache, BlockDevice,
+    BLOCK_SZ,
+};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+/// Number of blocks reserved for the journal when creating a filesystem
+pub const JOURNAL_BLOCKS: u32 = 32;
+
+type DataBlock = [u8; BLOCK_SZ];
+
+/// The journal area at the end of the device
+pub struct Journal {
+    start: u32,
+    blocks: u32,
+}
+
+impl Journal {
+    /// Use `blocks` blocks starting from `start` as the journal
+    pub fn new(start: u32, blocks: u32) -> Self {
+        Self { start, blocks }
+    }
+    /// Max number of blocks a transaction can commit atomically, limited by the journal area
+    /// and by the targets a header can record
+    pub fn capacity(&self) -> usize {
+        (self.blocks as usize - 1).min(BLOCK_SZ / 4 - 1)
+    }
+    fn read_header(&self, block_device: &Arc<dyn BlockDevice>) -> Vec<usize> {
+        let mut header = [0u8; BLOCK_SZ];
+        block_device.read_block(self.start as usize, &mut header);
+        let mut words = header
+            .chunks_exact(4)
+            .map(|word| u32::from_le_bytes(word.try_into().unwrap()) as usize);
+        let count = words.next().unwrap();
+        words.take(count.min(self.capacity())).collect()
+    }
+    fn write_header(&self, block_ids: &[usize], block_device: &Arc<dyn BlockDevice>) {
+        let mut header = [0u8; BLOCK_SZ];
+        let words = core::iter::once(block_ids.len()).chain(block_ids.iter().copied());
+        for (word, value) in header.chunks_exact_mut(4).zip(words) {
+            word.copy_from_slice(&(value as u32).to_le_bytes());
+        }
+        block_device.write_block(self.start as usize, &header);
+        block_cache_flush(block_device);
+    }
+    // 事务在块缓存中扣留的块不会超过日志的容量，总是可以整体写入日志
+    /// Commit the blocks modified by a transaction, which are still held in the block cache,
+    /// then write them back to where they belong
+    pub fn commit(&self, block_ids: &[usize], block_device: &Arc<dyn BlockDevice>) {
+        if block_ids.is_empty() {
+            return;
+        }
+        assert!(block_ids.len() <= self.capacity());
+        let mut data = [0u8; BLOCK_SZ];
+        for (i, block_id) in block_ids.iter().enumerate() {
+            get_block_cache(*block_id, Arc::clone(block_device))
+                .lock()
+                .read(0, |data_block: &DataBlock| data.copy_from_slice(data_block));
+            block_device.write_block(self.start as usize + 1 + i, &data);
+        }
+        block_cache_flush(block_device);
+        // 日志头写入磁盘之后事务就提交了
+        self.write_header(block_ids, block_device);
+        block_cache_install(block_ids, block_device);
+        block_cache_flush(block_device);
+        self.write_header(&[], block_device);
+    }
+    // 重放是幂等的：已经写回原位的块再写一次也不会有变化，因此重放过程中再次崩溃也没有关系
+    /// Replay the committed transaction in the journal if any, return the number of blocks
+    /// written back
+    pub fn recover(&self, block_device: &Arc<dyn BlockDevice>) -> usize {
+        let block_ids = self.read_header(block_device);
+        if block_ids.is_empty() {
+            return 0;
+        }
+        let mut data = [0u8; BLOCK_SZ];
+        for (i, block_id) in block_ids.iter().enumerate() {
+            block_device.read_block(self.start as usize + 1 + i, &mut data);
+            get_block_cache(*block_id, Arc::clone(block_device))
+                .lock()
+                .modify(0, |data_block: &mut DataBlock| {
+                    data_block.copy_from_slice(&data)
+                });
+        }
+        block_cache_sync(&block_ids, block_device);
+        block_cache_flush(block_device);
+        self.write_header(&[], block_device);
+        block_ids.len()
+    }
+}
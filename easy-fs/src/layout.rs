@@ -1,9 +1,8 @@
-use super::{get_block_cache, BlockDevice, BLOCK_SZ};
+use super::{get_block_cache, get_metadata_block_cache, BlockDevice, BLOCK_SZ};
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use core::fmt::{Debug, Formatter, Result};
 
-
 /// Magic number for sanity check
 const EFS_MAGIC: u32 = 0x3b800001;
 /// The max number of direct inodes
@@ -18,8 +17,9 @@ const INODE_INDIRECT2_COUNT: usize = INODE_INDIRECT1_COUNT * INODE_INDIRECT1_COU
 const DIRECT_BOUND: usize = INODE_DIRECT_COUNT;
 /// The upper bound of indirect1 inode index
 const INDIRECT1_BOUND: usize = DIRECT_BOUND + INODE_INDIRECT1_COUNT;
+/// The max size of a file in bytes
+pub const MAX_FILE_SIZE: u32 = ((INDIRECT1_BOUND + INODE_INDIRECT2_COUNT) * BLOCK_SZ) as u32;
 /// The upper bound of indirect2 inode indexs
-#[allow(unused)]
 const INDIRECT2_BOUND: usize = INDIRECT1_BOUND + INODE_INDIRECT2_COUNT;
 /// Super block of a filesystem
 #[repr(C)]
@@ -32,6 +32,9 @@ pub struct SuperBlock {
     pub inode_area_blocks: u32,
     pub data_bitmap_blocks: u32,
     pub data_area_blocks: u32,
+    // 日志区域位于设备的末尾、数据区域之后。旧镜像中这两个字段都是 0 ，即没有日志
+    pub journal_start: u32,
+    pub journal_blocks: u32,
 }
 
 impl Debug for SuperBlock {
@@ -42,6 +45,8 @@ impl Debug for SuperBlock {
             .field("inode_area_blocks", &self.inode_area_blocks)
             .field("data_bitmap_blocks", &self.data_bitmap_blocks)
             .field("data_area_blocks", &self.data_area_blocks)
+            .field("journal_start", &self.journal_start)
+            .field("journal_blocks", &self.journal_blocks)
             .finish()
     }
 }
@@ -56,6 +61,7 @@ impl SuperBlock {
         inode_area_blocks: u32,
         data_bitmap_blocks: u32,
         data_area_blocks: u32,
+        journal_blocks: u32,
     ) {
         *self = Self {
             magic: EFS_MAGIC,
@@ -64,6 +70,8 @@ impl SuperBlock {
             inode_area_blocks,
             data_bitmap_blocks,
             data_area_blocks,
+            journal_start: total_blocks - journal_blocks,
+            journal_blocks,
         }
     }
     // is_valid 则可以通过魔数判断超级块所在的文件系统是否合法
@@ -73,10 +81,14 @@ impl SuperBlock {
     }
 }
 /// Type of a disk inode
-#[derive(PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DiskInodeType {
+    /// regular file
     File,
+    /// directory
     Directory,
+    /// device file
+    Device,
 }
 
 // 在磁盘上的索引节点区域，每个块上都保存着若干个索引节点 DiskInode
@@ -97,10 +109,23 @@ pub struct DiskInode {
     // 因此，最多能够索引512/4=128个数据块，对应 64KiB 的内容
     pub indirect1: u32,
     pub indirect2: u32,
-    // type_ 表示索引节点的类型 DiskInodeType，目前仅支持文件 File 和目录 Directory 两种类型
+    // type_ 表示索引节点的类型 DiskInodeType，支持文件 File 、目录 Directory 和设备文件 Device 三种类型
     type_: DiskInodeType,
+    // mode 是文件的权限位（低 12 位），它占用了 type_ 之后原本用于对齐的填充字节，DiskInode 的大小仍然是 128 字节。
+    // 旧镜像中这些字节都是 0 ，即没有任何权限
+    mode: u16,
 }
 
+// 增加字段时不能改变 DiskInode 的大小，否则已有镜像中索引节点的位置都会改变
+const _: () = assert!(core::mem::size_of::<DiskInode>() == 128);
+
+/// Permission bits of a newly created file
+const FILE_DEFAULT_MODE: u16 = 0o644;
+/// Permission bits of a newly created directory
+const DIRECTORY_DEFAULT_MODE: u16 = 0o755;
+/// Mask of the permission bits
+const MODE_MASK: u16 = 0o7777;
+
 impl DiskInode {
     // initialize 方法可以初始化一个 DiskInode 为一个文件或目录
     /// Initialize a disk inode, as well as all direct inodes under it
@@ -111,8 +136,20 @@ impl DiskInode {
         self.direct.iter_mut().for_each(|v| *v = 0);
         self.indirect1 = 0;
         self.indirect2 = 0;
+        self.mode = match type_ {
+            DiskInodeType::File | DiskInodeType::Device => FILE_DEFAULT_MODE,
+            DiskInodeType::Directory => DIRECTORY_DEFAULT_MODE,
+        };
         self.type_ = type_;
     }
+    /// Get the permission bits
+    pub fn mode(&self) -> u16 {
+        self.mode
+    }
+    /// Set the permission bits, other bits of `mode` are ignored
+    pub fn set_mode(&mut self, mode: u16) {
+        self.mode = mode & MODE_MASK;
+    }
     /// Whether this inode is a directory
     pub fn is_dir(&self) -> bool {
         self.type_ == DiskInodeType::Directory
@@ -122,6 +159,20 @@ impl DiskInode {
     pub fn is_file(&self) -> bool {
         self.type_ == DiskInodeType::File
     }
+    // 设备文件没有数据，大小始终为 0 ，设备号就保存在不会被用到的 direct[0] 中
+    /// Get the type of this inode
+    pub fn type_(&self) -> DiskInodeType {
+        self.type_
+    }
+    /// Get the device number if this inode is a device
+    pub fn device(&self) -> Option<u32> {
+        (self.type_ == DiskInodeType::Device).then_some(self.direct[0])
+    }
+    /// Set the device number of a device inode
+    pub fn set_device(&mut self, dev: u32) {
+        assert!(self.type_ == DiskInodeType::Device);
+        self.direct[0] = dev;
+    }
     // data_blocks 方法可以计算为了容纳自身 size 字节的内容需要多少个数据块。计算的过程只需用 size 除以每个块的大小 BLOCK_SZ 并向上取整
     /// Return block number correspond to size.
     pub fn data_blocks(&self) -> u32 {
@@ -130,203 +181,321 @@ impl DiskInode {
     fn _data_blocks(size: u32) -> u32 {
         (size + BLOCK_SZ as u32 - 1) / BLOCK_SZ as u32
     }
-    // total_blocks 不仅包含数据块，还需要统计索引块
-    /// Return number of blocks needed include indirect1/2.
-    pub fn total_blocks(size: u32) -> u32 {
-        let data_blocks = Self::_data_blocks(size) as usize;
-        let mut total = data_blocks as usize;
-        // indirect1
-        if data_blocks > INODE_DIRECT_COUNT {
-            total += 1;
-        }
-        // indirect2
-        if data_blocks > INDIRECT1_BOUND {
-            total += 1;
-            // sub indirect1
-            total +=
-                (data_blocks - INDIRECT1_BOUND + INODE_INDIRECT1_COUNT - 1) / INODE_INDIRECT1_COUNT;
-        }
-        total as u32
-    }
-    // blocks_num_needed 可以计算将一个 DiskInode 的 size 扩容到 new_size 需要额外多少个数据和索引块。这只需要调用两次 total_blocks 作差即可
-    /// Get the number of data blocks that have to be allocated given the new size of data
-    pub fn blocks_num_needed(&self, new_size: u32) -> u32 {
-        assert!(new_size >= self.size);
-        Self::total_blocks(new_size) - Self::total_blocks(self.size)
-    }
-    // 从索引中查到它自身用于保存文件内容的第 block_id 个数据块的块编号
-    /// Get id of block given inner id
+    // 文件中可以有空洞：编号为 0 的数据块或索引块还没有被分配，空洞读出来都是 0 ，也不占用磁盘空间，直到被写入时才分配。
+    // 0 号块是超级块，不会被分配给任何文件，因此可以用 0 表示空洞
+    /// Get id of block given inner id, or 0 if the block is a hole
     pub fn get_block_id(&self, inner_id: u32, block_device: &Arc<dyn BlockDevice>) -> u32 {
         let inner_id = inner_id as usize;
         if inner_id < INODE_DIRECT_COUNT {
             self.direct[inner_id]
         } else if inner_id < INDIRECT1_BOUND {
-            get_block_cache(self.indirect1 as usize, Arc::clone(block_device))
+            if self.indirect1 == 0 {
+                return 0;
+            }
+            get_metadata_block_cache(self.indirect1 as usize, Arc::clone(block_device))
                 .lock()
                 .read(0, |indirect_block: &IndirectBlock| {
                     indirect_block[inner_id - INODE_DIRECT_COUNT]
                 })
         } else {
+            if self.indirect2 == 0 {
+                return 0;
+            }
             let last = inner_id - INDIRECT1_BOUND;
-            let indirect1 = get_block_cache(self.indirect2 as usize, Arc::clone(block_device))
-                .lock()
-                .read(0, |indirect2: &IndirectBlock| {
-                    indirect2[last / INODE_INDIRECT1_COUNT]
-                });
-            get_block_cache(indirect1 as usize, Arc::clone(block_device))
+            let indirect1 =
+                get_metadata_block_cache(self.indirect2 as usize, Arc::clone(block_device))
+                    .lock()
+                    .read(0, |indirect2: &IndirectBlock| {
+                        indirect2[last / INODE_INDIRECT1_COUNT]
+                    });
+            if indirect1 == 0 {
+                return 0;
+            }
+            get_metadata_block_cache(indirect1 as usize, Arc::clone(block_device))
                 .lock()
                 .read(0, |indirect1: &IndirectBlock| {
                     indirect1[last % INODE_INDIRECT1_COUNT]
                 })
         }
     }
-    /// Inncrease the size of current disk inode
-    pub fn increase_size(
+    // 为第 inner_id 个数据块分配一个块并返回它的编号，途中缺少的索引块也一并分配；已经分配过的块不会改变。
+    // 新分配的块一定全为 0 （块在回收时会被清零），新的索引块中的每一项都是空洞
+    /// Get id of block given inner id, allocating it along with the index blocks
+    /// leading to it by `alloc` if it is a hole
+    fn map_block(
         &mut self,
-        new_size: u32,
-        new_blocks: Vec<u32>,
+        inner_id: u32,
+        alloc: &mut impl FnMut() -> u32,
         block_device: &Arc<dyn BlockDevice>,
-    ) {
-        let mut current_blocks = self.data_blocks();
-        self.size = new_size;
-        let mut total_blocks = self.data_blocks();
-        let mut new_blocks = new_blocks.into_iter();
-        // fill direct
-        while current_blocks < total_blocks.min(INODE_DIRECT_COUNT as u32) {
-            self.direct[current_blocks as usize] = new_blocks.next().unwrap();
-            current_blocks += 1;
+    ) -> u32 {
+        let inner_id = inner_id as usize;
+        if inner_id < INODE_DIRECT_COUNT {
+            if self.direct[inner_id] == 0 {
+                self.direct[inner_id] = alloc();
+            }
+            return self.direct[inner_id];
         }
-        // alloc indirect1
-        if total_blocks > INODE_DIRECT_COUNT as u32 {
-            if current_blocks == INODE_DIRECT_COUNT as u32 {
-                self.indirect1 = new_blocks.next().unwrap();
+        let (indirect1, index) = if inner_id < INDIRECT1_BOUND {
+            if self.indirect1 == 0 {
+                self.indirect1 = alloc();
             }
-            current_blocks -= INODE_DIRECT_COUNT as u32;
-            total_blocks -= INODE_DIRECT_COUNT as u32;
+            (self.indirect1, inner_id - INODE_DIRECT_COUNT)
         } else {
-            return;
-        }
-        // fill indirect1
-        get_block_cache(self.indirect1 as usize, Arc::clone(block_device))
+            if self.indirect2 == 0 {
+                self.indirect2 = alloc();
+            }
+            let last = inner_id - INDIRECT1_BOUND;
+            let indirect1 = Self::map_entry(
+                self.indirect2,
+                last / INODE_INDIRECT1_COUNT,
+                alloc,
+                block_device,
+            );
+            (indirect1, last % INODE_INDIRECT1_COUNT)
+        };
+        Self::map_entry(indirect1, index, alloc, block_device)
+    }
+    /// Get the `index`-th entry of an index block, allocating a block for it if it is a hole
+    fn map_entry(
+        block_id: u32,
+        index: usize,
+        alloc: &mut impl FnMut() -> u32,
+        block_device: &Arc<dyn BlockDevice>,
+    ) -> u32 {
+        get_metadata_block_cache(block_id as usize, Arc::clone(block_device))
             .lock()
-            .modify(0, |indirect1: &mut IndirectBlock| {
-                while current_blocks < total_blocks.min(INODE_INDIRECT1_COUNT as u32) {
-                    indirect1[current_blocks as usize] = new_blocks.next().unwrap();
-                    current_blocks += 1;
+            .modify(0, |indirect_block: &mut IndirectBlock| {
+                if indirect_block[index] == 0 {
+                    indirect_block[index] = alloc();
                 }
-            });
-        // alloc indirect2
-        if total_blocks > INODE_INDIRECT1_COUNT as u32 {
-            if current_blocks == INODE_INDIRECT1_COUNT as u32 {
-                self.indirect2 = new_blocks.next().unwrap();
-            }
-            current_blocks -= INODE_INDIRECT1_COUNT as u32;
-            total_blocks -= INODE_INDIRECT1_COUNT as u32;
+                indirect_block[index]
+            })
+    }
+    // 将第 inner_id 个数据块变回空洞，返回它原来的块编号，由调用者负责回收；索引块不会被回收
+    /// Turn the block of given inner id into a hole, return its id or 0 if it is a hole
+    fn unmap_block(&mut self, inner_id: u32, block_device: &Arc<dyn BlockDevice>) -> u32 {
+        let inner_id = inner_id as usize;
+        if inner_id < INODE_DIRECT_COUNT {
+            return core::mem::take(&mut self.direct[inner_id]);
+        }
+        let (indirect1, index) = if inner_id < INDIRECT1_BOUND {
+            (self.indirect1, inner_id - INODE_DIRECT_COUNT)
         } else {
-            return;
+            let last = inner_id - INDIRECT1_BOUND;
+            let indirect1 = if self.indirect2 == 0 {
+                0
+            } else {
+                get_metadata_block_cache(self.indirect2 as usize, Arc::clone(block_device))
+                    .lock()
+                    .read(0, |indirect2: &IndirectBlock| {
+                        indirect2[last / INODE_INDIRECT1_COUNT]
+                    })
+            };
+            (indirect1, last % INODE_INDIRECT1_COUNT)
+        };
+        if indirect1 == 0 {
+            return 0;
         }
-        // fill indirect2 from (a0, b0) -> (a1, b1)
-        let mut a0 = current_blocks as usize / INODE_INDIRECT1_COUNT;
-        let mut b0 = current_blocks as usize % INODE_INDIRECT1_COUNT;
-        let a1 = total_blocks as usize / INODE_INDIRECT1_COUNT;
-        let b1 = total_blocks as usize % INODE_INDIRECT1_COUNT;
-        // alloc low-level indirect1
-        get_block_cache(self.indirect2 as usize, Arc::clone(block_device))
+        get_metadata_block_cache(indirect1 as usize, Arc::clone(block_device))
             .lock()
-            .modify(0, |indirect2: &mut IndirectBlock| {
-                while (a0 < a1) || (a0 == a1 && b0 < b1) {
-                    if b0 == 0 {
-                        indirect2[a0] = new_blocks.next().unwrap();
-                    }
-                    // fill current
-                    get_block_cache(indirect2[a0] as usize, Arc::clone(block_device))
-                        .lock()
-                        .modify(0, |indirect1: &mut IndirectBlock| {
-                            indirect1[b0] = new_blocks.next().unwrap();
-                        });
-                    // move to next
-                    b0 += 1;
-                    if b0 == INODE_INDIRECT1_COUNT {
-                        b0 = 0;
-                        a0 += 1;
+            .modify(0, |indirect_block: &mut IndirectBlock| {
+                core::mem::take(&mut indirect_block[index])
+            })
+    }
+    // 收集文件内容中第 start..end 个数据块的块编号，以及访问它们需要经过的一级/二级索引块的块编号
+    /// Get ids of data blocks in `start..end` along with the index blocks leading to them
+    pub fn block_ids_in_range(
+        &self,
+        start: u32,
+        end: u32,
+        block_device: &Arc<dyn BlockDevice>,
+    ) -> Vec<u32> {
+        let end = end.min(self.data_blocks()) as usize;
+        let start = start as usize;
+        let mut v: Vec<u32> = Vec::new();
+        if start >= end {
+            return v;
+        }
+        // 空洞没有对应的块
+        for inner_id in start..end {
+            let block_id = self.get_block_id(inner_id as u32, block_device);
+            if block_id != 0 {
+                v.push(block_id);
+            }
+        }
+        // indirect1
+        if start < INDIRECT1_BOUND && end > DIRECT_BOUND && self.indirect1 != 0 {
+            v.push(self.indirect1);
+        }
+        // indirect2 and the sub indirect1 blocks covering the range
+        if end > INDIRECT1_BOUND && self.indirect2 != 0 {
+            v.push(self.indirect2);
+            let first = start.max(INDIRECT1_BOUND) - INDIRECT1_BOUND;
+            let last = end - 1 - INDIRECT1_BOUND;
+            get_metadata_block_cache(self.indirect2 as usize, Arc::clone(block_device))
+                .lock()
+                .read(0, |indirect2: &IndirectBlock| {
+                    for entry in indirect2
+                        .iter()
+                        .take(last / INODE_INDIRECT1_COUNT + 1)
+                        .skip(first / INODE_INDIRECT1_COUNT)
+                        .filter(|entry| **entry != 0)
+                    {
+                        v.push(*entry);
                     }
-                }
-            });
+                });
+        }
+        v
     }
-
-    /// Clear size to zero and return blocks that should be deallocated.
-    /// We will clear the block contents to zero later.
-    pub fn clear_size(&mut self, block_device: &Arc<dyn BlockDevice>) -> Vec<u32> {
+    // 收集 DiskInode 引用的所有数据块和索引块，供一致性检查使用，空洞不会被收集。镜像可能已经损坏，
+    // 因此只会读取 follow 认为合法的索引块，数据块的数量也不会超过最大文件大小对应的块数
+    /// Get ids of all the data and index blocks referred by the disk inode,
+    /// index blocks rejected by `follow` are returned but not read
+    pub fn referenced_blocks(
+        &self,
+        follow: impl Fn(u32) -> bool,
+        block_device: &Arc<dyn BlockDevice>,
+    ) -> Vec<u32> {
+        let data_blocks = (self.data_blocks() as usize).min(INDIRECT2_BOUND);
         let mut v: Vec<u32> = Vec::new();
-        let mut data_blocks = self.data_blocks() as usize;
-        self.size = 0;
-        let mut current_blocks = 0usize;
+        let extend = |v: &mut Vec<u32>, entries: &[u32]| {
+            v.extend(entries.iter().filter(|block_id| **block_id != 0));
+        };
         // direct
-        while current_blocks < data_blocks.min(INODE_DIRECT_COUNT) {
-            v.push(self.direct[current_blocks]);
-            self.direct[current_blocks] = 0;
-            current_blocks += 1;
-        }
-        // indirect1 block
-        if data_blocks > INODE_DIRECT_COUNT {
+        extend(&mut v, &self.direct[..data_blocks.min(DIRECT_BOUND)]);
+        // indirect1
+        if data_blocks > DIRECT_BOUND && self.indirect1 != 0 {
             v.push(self.indirect1);
-            data_blocks -= INODE_DIRECT_COUNT;
-            current_blocks = 0;
-        } else {
+            if follow(self.indirect1) {
+                get_metadata_block_cache(self.indirect1 as usize, Arc::clone(block_device))
+                    .lock()
+                    .read(0, |indirect1: &IndirectBlock| {
+                        extend(
+                            &mut v,
+                            &indirect1[..data_blocks.min(INDIRECT1_BOUND) - DIRECT_BOUND],
+                        );
+                    });
+            }
+        }
+        // indirect2
+        if data_blocks <= INDIRECT1_BOUND || self.indirect2 == 0 {
             return v;
         }
-        // indirect1
-        get_block_cache(self.indirect1 as usize, Arc::clone(block_device))
-            .lock()
-            .modify(0, |indirect1: &mut IndirectBlock| {
-                while current_blocks < data_blocks.min(INODE_INDIRECT1_COUNT) {
-                    v.push(indirect1[current_blocks]);
-                    //indirect1[current_blocks] = 0;
-                    current_blocks += 1;
-                }
-            });
-        self.indirect1 = 0;
-        // indirect2 block
-        if data_blocks > INODE_INDIRECT1_COUNT {
-            v.push(self.indirect2);
-            data_blocks -= INODE_INDIRECT1_COUNT;
-        } else {
+        v.push(self.indirect2);
+        if !follow(self.indirect2) {
             return v;
         }
-        // indirect2
-        assert!(data_blocks <= INODE_INDIRECT2_COUNT);
-        let a1 = data_blocks / INODE_INDIRECT1_COUNT;
-        let b1 = data_blocks % INODE_INDIRECT1_COUNT;
-        get_block_cache(self.indirect2 as usize, Arc::clone(block_device))
-            .lock()
-            .modify(0, |indirect2: &mut IndirectBlock| {
-                // full indirect1 blocks
-                for entry in indirect2.iter_mut().take(a1) {
-                    v.push(*entry);
-                    get_block_cache(*entry as usize, Arc::clone(block_device))
-                        .lock()
-                        .modify(0, |indirect1: &mut IndirectBlock| {
-                            for entry in indirect1.iter() {
-                                v.push(*entry);
-                            }
-                        });
-                }
-                // last indirect1 block
-                if b1 > 0 {
-                    v.push(indirect2[a1]);
-                    get_block_cache(indirect2[a1] as usize, Arc::clone(block_device))
-                        .lock()
-                        .modify(0, |indirect1: &mut IndirectBlock| {
-                            for entry in indirect1.iter().take(b1) {
-                                v.push(*entry);
-                            }
-                        });
-                    //indirect2[a1] = 0;
-                }
-            });
-        self.indirect2 = 0;
+        let last = data_blocks - INDIRECT1_BOUND;
+        let sub_indirect1: Vec<u32> =
+            get_metadata_block_cache(self.indirect2 as usize, Arc::clone(block_device))
+                .lock()
+                .read(0, |indirect2: &IndirectBlock| {
+                    indirect2
+                        .iter()
+                        .take(last.div_ceil(INODE_INDIRECT1_COUNT))
+                        .copied()
+                        .collect()
+                });
+        for (i, indirect1) in sub_indirect1.into_iter().enumerate() {
+            if indirect1 == 0 {
+                continue;
+            }
+            v.push(indirect1);
+            if follow(indirect1) {
+                let count = (last - i * INODE_INDIRECT1_COUNT).min(INODE_INDIRECT1_COUNT);
+                get_metadata_block_cache(indirect1 as usize, Arc::clone(block_device))
+                    .lock()
+                    .read(0, |indirect1: &IndirectBlock| {
+                        extend(&mut v, &indirect1[..count]);
+                    });
+            }
+        }
+        v
+    }
+    // 扩大文件时只修改文件大小，扩大的部分是一个空洞，真正写入时才通过 alloc_blocks 分配块
+    /// Increase the size of current disk inode, leaving a hole in the increased part
+    pub fn increase_size(&mut self, new_size: u32) {
+        assert!(new_size >= self.size);
+        self.size = new_size;
+    }
+    /// Allocate blocks by `alloc` for the holes among the data blocks in `start..end`
+    pub fn alloc_blocks(
+        &mut self,
+        start: u32,
+        end: u32,
+        mut alloc: impl FnMut() -> u32,
+        block_device: &Arc<dyn BlockDevice>,
+    ) {
+        for inner_id in start..end.min(self.data_blocks()) {
+            self.map_block(inner_id, &mut alloc, block_device);
+        }
+    }
+
+    // decrease_size 是 increase_size 的逆操作：将文件缩小到 new_size 字节，返回不再需要的数据块和索引块，由调用者负责回收。
+    // 被截掉的数据块在索引中变回空洞；最后一个数据块中超出 new_size 的部分会被清零，这样之后再扩大文件时这部分读出来仍然是 0
+    /// Decrease the size of current disk inode and return blocks that should be deallocated.
+    pub fn decrease_size(
+        &mut self,
+        new_size: u32,
+        block_device: &Arc<dyn BlockDevice>,
+    ) -> Vec<u32> {
+        assert!(new_size <= self.size);
+        let old_blocks = self.data_blocks() as usize;
+        let new_blocks = Self::_data_blocks(new_size) as usize;
+        // zero the tail of the last remaining block
+        let tail = new_size as usize % BLOCK_SZ;
+        let block_id = match tail {
+            0 => 0,
+            _ => self.get_block_id(new_blocks as u32 - 1, block_device),
+        };
+        if block_id != 0 {
+            get_block_cache(block_id as usize, Arc::clone(block_device))
+                .lock()
+                .modify(0, |data_block: &mut DataBlock| {
+                    data_block[tail..].iter_mut().for_each(|p| *p = 0);
+                });
+        }
+        let mut v: Vec<u32> = Vec::new();
+        for inner_id in new_blocks..old_blocks {
+            let block_id = self.unmap_block(inner_id as u32, block_device);
+            if block_id != 0 {
+                v.push(block_id);
+            }
+        }
+        // low-level indirect1 blocks under indirect2
+        if old_blocks > INDIRECT1_BOUND && self.indirect2 != 0 {
+            let indirect1_blocks = |blocks: usize| {
+                blocks
+                    .saturating_sub(INDIRECT1_BOUND)
+                    .div_ceil(INODE_INDIRECT1_COUNT)
+            };
+            let (a0, a1) = (indirect1_blocks(new_blocks), indirect1_blocks(old_blocks));
+            get_metadata_block_cache(self.indirect2 as usize, Arc::clone(block_device))
+                .lock()
+                .modify(0, |indirect2: &mut IndirectBlock| {
+                    for entry in indirect2[a0..a1].iter_mut() {
+                        if *entry != 0 {
+                            v.push(core::mem::take(entry));
+                        }
+                    }
+                });
+            if new_blocks <= INDIRECT1_BOUND {
+                v.push(core::mem::take(&mut self.indirect2));
+            }
+        }
+        // indirect1 block
+        if new_blocks <= INODE_DIRECT_COUNT && self.indirect1 != 0 {
+            v.push(core::mem::take(&mut self.indirect1));
+        }
+        self.size = new_size;
         v
     }
+
+    /// Clear size to zero and return blocks that should be deallocated.
+    /// We will clear the block contents to zero later.
+    pub fn clear_size(&mut self, block_device: &Arc<dyn BlockDevice>) -> Vec<u32> {
+        self.decrease_size(0, block_device)
+    }
     // 将文件内容从 offset 字节开始的部分读到内存中的缓冲区 buf 中，并返回实际读到的字节数
     // 如果文件剩下的内容还足够多，那么缓冲区会被填满；否则文件剩下的全部内容都会被读到缓冲区中
     /// Read data from current disk inode
@@ -350,15 +519,16 @@ impl DiskInode {
             // read and update read size
             let block_read_size = end_current_block - start;
             let dst = &mut buf[read_size..read_size + block_read_size];
-            get_block_cache(
-                self.get_block_id(start_block as u32, block_device) as usize,
-                Arc::clone(block_device),
-            )
-            .lock()
-            .read(0, |data_block: &DataBlock| {
-                let src = &data_block[start % BLOCK_SZ..start % BLOCK_SZ + block_read_size];
-                dst.copy_from_slice(src);
-            });
+            // 空洞读出来都是 0
+            match self.get_block_id(start_block as u32, block_device) {
+                0 => dst.fill(0),
+                block_id => get_block_cache(block_id as usize, Arc::clone(block_device))
+                    .lock()
+                    .read(0, |data_block: &DataBlock| {
+                        let src = &data_block[start % BLOCK_SZ..start % BLOCK_SZ + block_read_size];
+                        dst.copy_from_slice(src);
+                    }),
+            }
             read_size += block_read_size;
             // move to next block
             if end_current_block == end {
@@ -370,9 +540,10 @@ impl DiskInode {
         read_size
     }
     // write_at 不会出现失败的情况；只要 Inode 管理的数据块的大小足够，传入的整个缓冲区的数据都必定会被写入到文件中。
-    // 当从 offset 开始的区间超出了文件范围的时候，就需要调用者在调用 write_at 之前提前调用 increase_size ，将文件大小扩充到区间的右端，保证写入的完整性
+    // 当从 offset 开始的区间超出了文件范围的时候，就需要调用者在调用 write_at 之前提前调用 increase_size ，将文件大小扩充到区间的右端，
+    // 并调用 alloc_blocks 为区间中的空洞分配块，保证写入的完整性
     /// Write data into current disk inode
-    /// size must be adjusted properly beforehand
+    /// size must be adjusted and holes in the range must be allocated properly beforehand
     pub fn write_at(
         &mut self,
         offset: usize,
@@ -390,12 +561,15 @@ impl DiskInode {
             end_current_block = end_current_block.min(end);
             // write and update write size
             let block_write_size = end_current_block - start;
-            get_block_cache(
-                self.get_block_id(start_block as u32, block_device) as usize,
-                Arc::clone(block_device),
-            )
-            .lock()
-            .modify(0, |data_block: &mut DataBlock| {
+            let block_id = self.get_block_id(start_block as u32, block_device);
+            assert_ne!(block_id, 0);
+            // 目录的内容就是目录项，与索引节点一样属于元数据
+            let block_cache = if self.is_dir() {
+                get_metadata_block_cache(block_id as usize, Arc::clone(block_device))
+            } else {
+                get_block_cache(block_id as usize, Arc::clone(block_device))
+            };
+            block_cache.lock().modify(0, |data_block: &mut DataBlock| {
                 let src = &buf[write_size..write_size + block_write_size];
                 let dst = &mut data_block[start % BLOCK_SZ..start % BLOCK_SZ + block_write_size];
                 dst.copy_from_slice(src);
@@ -5,14 +5,27 @@ extern crate alloc;
 mod bitmap;
 mod block_cache;
 mod block_dev;
+mod defrag;
 mod efs;
+mod fsck;
+mod journal;
 mod layout;
 mod vfs;
 /// Use a block size of 512 bytes
 pub const BLOCK_SZ: usize = 512;
 use bitmap::Bitmap;
-use block_cache::{block_cache_sync_all, get_block_cache};
-pub use block_dev::BlockDevice;
-pub use efs::EasyFileSystem;
+use block_cache::{
+    block_cache_begin, block_cache_end, block_cache_evict, block_cache_flush, block_cache_install,
+    block_cache_sync, get_block_cache, get_metadata_block_cache,
+};
+pub use block_cache::{
+    block_cache_discard_all, block_cache_set_policy, block_cache_sync_all, block_cache_try_sync,
+    CachePolicy,
+};
+pub use block_dev::{BlockDevice, MemBlockDevice};
+pub use efs::{EasyFileSystem, FsStat};
+pub use fsck::FsckProblem;
+use journal::{Journal, JOURNAL_BLOCKS};
+pub use layout::{DiskInodeType, MAX_FILE_SIZE};
 use layout::*;
 pub use vfs::Inode;
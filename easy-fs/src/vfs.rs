@@ -1,11 +1,14 @@
 use super::{
-    block_cache_sync_all, get_block_cache, BlockDevice, DirEntry, DiskInode, DiskInodeType,
-    EasyFileSystem, DIRENT_SZ,
+    block_cache_evict, block_cache_flush, block_cache_sync, block_cache_sync_all, get_block_cache,
+    get_metadata_block_cache, BlockDevice, DirEntry, DiskInode, DiskInodeType, EasyFileSystem,
+    BLOCK_SZ, DIRENT_SZ, MAX_FILE_SIZE,
 };
 use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use spin::{Mutex, MutexGuard};
+/// Max blocks one transaction cuts off when a directory shrinks
+const TRUNCATE_STEP_BLOCKS: u32 = 16;
 // EasyFileSystem 实现了磁盘布局并能够将磁盘块有效的管理起来。但是对于文件系统的使用者而言，
 // 他们往往不关心磁盘布局是如何实现的，而是更希望能够直接看到目录树结构中逻辑上的文件和目录。
 // 为此需要设计索引节点 Inode 暴露给文件系统的使用者，让他们能够直接对文件和目录进行操作
@@ -20,32 +23,76 @@ pub struct Inode {
 }
 
 impl Inode {
-    /// Create a vfs inode
-    pub fn new(
-        block_id: u32,
-        block_offset: usize,
-        fs: Arc<Mutex<EasyFileSystem>>,
-        block_device: Arc<dyn BlockDevice>,
+    /// Create a vfs inode referring to inode `inode_id`, `efs` being the locked `fs`
+    pub(crate) fn new(
+        inode_id: u32,
+        fs: &Arc<Mutex<EasyFileSystem>>,
+        efs: &mut EasyFileSystem,
     ) -> Self {
+        let (block_id, block_offset) = efs.get_disk_inode_pos(inode_id);
+        efs.open_inode(inode_id);
         Self {
             block_id: block_id as usize,
             block_offset,
-            fs,
-            block_device,
+            fs: Arc::clone(fs),
+            block_device: Arc::clone(&efs.block_device),
         }
     }
+    /// Get the inode number of current inode
+    pub fn inode_id(&self) -> u32 {
+        self.fs
+            .lock()
+            .get_inode_id(self.block_id as u32, self.block_offset)
+    }
     /// Call a function over a disk inode to read it
     fn read_disk_inode<V>(&self, f: impl FnOnce(&DiskInode) -> V) -> V {
-        get_block_cache(self.block_id, Arc::clone(&self.block_device))
+        get_metadata_block_cache(self.block_id, Arc::clone(&self.block_device))
             .lock()
             .read(self.block_offset, f)
     }
     /// Call a function over a disk inode to modify it
     fn modify_disk_inode<V>(&self, f: impl FnOnce(&mut DiskInode) -> V) -> V {
-        get_block_cache(self.block_id, Arc::clone(&self.block_device))
+        get_metadata_block_cache(self.block_id, Arc::clone(&self.block_device))
             .lock()
             .modify(self.block_offset, f)
     }
+    /// Get the size of current inode in bytes
+    pub fn size(&self) -> u32 {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| disk_inode.size)
+    }
+    // 空洞不占用块，稀疏文件占用的块数可能远小于它的大小所对应的块数
+    /// Get the number of data and index blocks allocated to current inode
+    pub fn allocated_blocks(&self) -> u32 {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| {
+            disk_inode
+                .referenced_blocks(|_| true, &self.block_device)
+                .len() as u32
+        })
+    }
+    /// Whether current inode is a directory
+    pub fn is_dir(&self) -> bool {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| disk_inode.is_dir())
+    }
+    /// Get the device number if current inode is a device
+    pub fn device(&self) -> Option<u32> {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| disk_inode.device())
+    }
+    /// Get the permission bits of current inode
+    pub fn mode(&self) -> u16 {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| disk_inode.mode())
+    }
+    // 权限和其他元数据一样，修改之后立即写回磁盘
+    /// Set the permission bits of current inode, other bits of `mode` are ignored
+    pub fn set_mode(&self, mode: u16) {
+        let _fs = self.fs.lock();
+        self.modify_disk_inode(|disk_inode| disk_inode.set_mode(mode));
+        block_cache_sync_all();
+    }
     /// Find inode under a disk inode by name
     fn find_inode_id(&self, name: &str, disk_inode: &DiskInode) -> Option<u32> {
         // assert it is a directory
@@ -65,66 +112,79 @@ impl Inode {
     }
     /// Find inode under current inode by name
     pub fn find(&self, name: &str) -> Option<Arc<Inode>> {
-        let fs = self.fs.lock();
-        self.read_disk_inode(|disk_inode| {
-            self.find_inode_id(name, disk_inode).map(|inode_id| {
-                let (block_id, block_offset) = fs.get_disk_inode_pos(inode_id);
-                Arc::new(Self::new(
-                    block_id,
-                    block_offset,
-                    self.fs.clone(),
-                    self.block_device.clone(),
-                ))
-            })
-        })
+        let mut fs = self.fs.lock();
+        let inode_id = self.read_disk_inode(|disk_inode| self.find_inode_id(name, disk_inode))?;
+        Some(Arc::new(Self::new(inode_id, &self.fs, &mut fs)))
     }
-    /// Increase the size of a disk inode
+    // 将文件扩大到至少 end 字节，只为 [start, end) 范围所在的空洞分配块，范围之前扩大的部分仍然是空洞，不占用磁盘空间
+    /// Increase the size of a disk inode to at least `end` bytes and allocate blocks for
+    /// the holes backing `start..end`
     fn increase_size(
         &self,
-        new_size: u32,
+        start: usize,
+        end: usize,
         disk_inode: &mut DiskInode,
         fs: &mut MutexGuard<EasyFileSystem>,
     ) {
-        if new_size < disk_inode.size {
-            return;
-        }
-        let blocks_needed = disk_inode.blocks_num_needed(new_size);
-        let mut v: Vec<u32> = Vec::new();
-        for _ in 0..blocks_needed {
-            v.push(fs.alloc_data());
+        if end as u32 > disk_inode.size {
+            disk_inode.increase_size(end as u32);
         }
-        disk_inode.increase_size(new_size, v, &self.block_device);
+        disk_inode.alloc_blocks(
+            (start / BLOCK_SZ) as u32,
+            end.div_ceil(BLOCK_SZ) as u32,
+            || fs.alloc_data(),
+            &self.block_device,
+        );
     }
     // create 方法可以在根目录下创建一个文件，该方法只有根目录的 Inode 会调用
     /// Create inode under current inode by name
     pub fn create(&self, name: &str) -> Option<Arc<Inode>> {
+        self.create_inode(name, |new_inode| new_inode.initialize(DiskInodeType::File))
+    }
+    // 子目录和根目录一样，目录项依次保存在它的数据中，新建的目录是空的
+    /// Create an empty directory under current inode by name
+    pub fn mkdir(&self, name: &str) -> Option<Arc<Inode>> {
+        self.create_inode(name, |new_inode| {
+            new_inode.initialize(DiskInodeType::Directory)
+        })
+    }
+    // mknod 方法在根目录下创建一个设备文件，记录下设备号，打开它时由内核根据设备号找到对应的设备
+    /// Create a device inode with device number `dev` under current inode by name
+    pub fn mknod(&self, name: &str, dev: u32) -> Option<Arc<Inode>> {
+        self.create_inode(name, |new_inode| {
+            new_inode.initialize(DiskInodeType::Device);
+            new_inode.set_device(dev);
+        })
+    }
+    /// Create inode under current inode by name, initialized by `init`
+    fn create_inode(&self, name: &str, init: impl FnOnce(&mut DiskInode)) -> Option<Arc<Inode>> {
         let mut fs = self.fs.lock();
-        // 检查文件是否已经在根目录下，如果找到的话返回 None
+        // 检查文件是否已经在根目录下
         let op = |root_inode: &DiskInode| {
             // assert it is a directory
             assert!(root_inode.is_dir());
             // has the file been created?
             self.find_inode_id(name, root_inode)
         };
-        if self.read_disk_inode(op).is_some() {
+        // 文件已经存在，或者已经没有空闲的索引节点
+        if self.read_disk_inode(op).is_some() || fs.inode_bitmap.free() == 0 {
             return None;
         }
+        fs.begin();
         // create a new file
         // alloc a inode with an indirect block
         let new_inode_id = fs.alloc_inode();
         // initialize inode
         let (new_inode_block_id, new_inode_block_offset) = fs.get_disk_inode_pos(new_inode_id);
-        get_block_cache(new_inode_block_id as usize, Arc::clone(&self.block_device))
+        get_metadata_block_cache(new_inode_block_id as usize, Arc::clone(&self.block_device))
             .lock()
-            .modify(new_inode_block_offset, |new_inode: &mut DiskInode| {
-                new_inode.initialize(DiskInodeType::File);
-            });
+            .modify(new_inode_block_offset, init);
         self.modify_disk_inode(|root_inode| {
             // append file in the dirent
             let file_count = (root_inode.size as usize) / DIRENT_SZ;
             let new_size = (file_count + 1) * DIRENT_SZ;
             // increase size
-            self.increase_size(new_size as u32, root_inode, &mut fs);
+            self.increase_size(file_count * DIRENT_SZ, new_size, root_inode, &mut fs);
             // write dirent
             let dirent = DirEntry::new(name, new_inode_id);
             root_inode.write_at(
@@ -133,16 +193,11 @@ impl Inode {
                 &self.block_device,
             );
         });
+        fs.commit();
 
-        let (block_id, block_offset) = fs.get_disk_inode_pos(new_inode_id);
         block_cache_sync_all();
         // return inode
-        Some(Arc::new(Self::new(
-            block_id,
-            block_offset,
-            self.fs.clone(),
-            self.block_device.clone(),
-        )))
+        Some(Arc::new(Self::new(new_inode_id, &self.fs, &mut fs)))
         // release efs lock automatically by compiler
     }
     // ls 方法可以收集根目录下的所有文件的文件名并以向量的形式返回，这个方法只有根目录的 Inode 才会调用
@@ -158,33 +213,315 @@ impl Inode {
                     disk_inode.read_at(i * DIRENT_SZ, dirent.as_bytes_mut(), &self.block_device,),
                     DIRENT_SZ,
                 );
-                v.push(String::from(dirent.name()));
+                if !dirent.name().is_empty() {
+                    v.push(String::from(dirent.name()));
+                }
             }
             v
         })
     }
+    // 目录游标：从第 pos 个目录项开始读出至多 max 个目录项，并返回下一次应当开始的位置，连续多次读取整个目录的总开销是线性的。
+    // 两次读取之间新加入的目录项位于目录末尾，之后仍然可以读到；名字为空的目录项是无效的，直接跳过
+    // 目录项的类型来自它指向的索引节点，编号超出索引节点区域的目录项是损坏的，类型为 None 。
+    // 子节点可能与当前目录位于同一个块中，因此要等读完目录、放开这个块的锁之后再去读它们
+    /// Read at most `max` entries, as (name, inode number, inode type) tuples, of current
+    /// directory starting from the `pos`-th slot. Return them along with the slot to resume
+    /// from. The type is `None` if the inode of the entry cannot be read
+    pub fn read_dir(
+        &self,
+        pos: usize,
+        max: usize,
+    ) -> (Vec<(String, u32, Option<DiskInodeType>)>, usize) {
+        let fs = self.fs.lock();
+        let (entries, next) = self.read_disk_inode(|disk_inode| {
+            assert!(disk_inode.is_dir());
+            let file_count = (disk_inode.size as usize) / DIRENT_SZ;
+            let mut v: Vec<(String, u32)> = Vec::new();
+            let mut pos = pos;
+            let mut dirent = DirEntry::empty();
+            while pos < file_count && v.len() < max {
+                disk_inode.read_at(pos * DIRENT_SZ, dirent.as_bytes_mut(), &self.block_device);
+                pos += 1;
+                if !dirent.name().is_empty() {
+                    v.push((String::from(dirent.name()), dirent.inode_number()));
+                }
+            }
+            (v, pos)
+        });
+        let entries = entries
+            .into_iter()
+            .map(|(name, inode_id)| {
+                let type_ = (inode_id < fs.inode_bitmap.maximum() as u32).then(|| {
+                    let (block_id, block_offset) = fs.get_disk_inode_pos(inode_id);
+                    get_metadata_block_cache(block_id as usize, Arc::clone(&self.block_device))
+                        .lock()
+                        .read(block_offset, |disk_inode: &DiskInode| disk_inode.type_())
+                });
+                (name, inode_id, type_)
+            })
+            .collect();
+        (entries, next)
+    }
+    // rename 在持有文件系统锁的情况下一次性完成对目录项的修改，并发的 find 要么看到修改之前的目录，要么看到修改之后的目录。
+    // 目标已经存在时，直接把目标的目录项指向被重命名的索引节点，再将原来的目录项置为无效；
+    // 被覆盖的目标如果还被打开着，它的数据块要等到最后一个 Inode 被释放时才会回收，打开着的文件仍能读到原来的内容
+    /// Rename entry `old` under current directory to `new`, replacing the file named `new`
+    /// if any. Return false if `old` does not exist or `new` is a directory
+    pub fn rename(&self, old: &str, new: &str) -> bool {
+        let mut fs = self.fs.lock();
+        let slot_of = |name: &str, disk_inode: &DiskInode| {
+            let mut dirent = DirEntry::empty();
+            (0..disk_inode.size as usize / DIRENT_SZ).find_map(|i| {
+                disk_inode.read_at(i * DIRENT_SZ, dirent.as_bytes_mut(), &self.block_device);
+                (dirent.name() == name).then(|| (i, dirent.inode_number()))
+            })
+        };
+        let (old_slot, new_slot) = self.read_disk_inode(|disk_inode| {
+            assert!(disk_inode.is_dir());
+            (slot_of(old, disk_inode), slot_of(new, disk_inode))
+        });
+        let (old_slot, inode_id) = match old_slot {
+            Some(old_slot) => old_slot,
+            None => return false,
+        };
+        let replaced = match new_slot {
+            // 重命名为自己，什么都不用做
+            Some((_, target_id)) if target_id == inode_id => return true,
+            Some((new_slot, target_id)) => {
+                let (block_id, block_offset) = fs.get_disk_inode_pos(target_id);
+                let is_dir =
+                    get_metadata_block_cache(block_id as usize, Arc::clone(&self.block_device))
+                        .lock()
+                        .read(block_offset, |disk_inode: &DiskInode| disk_inode.is_dir());
+                if is_dir {
+                    return false;
+                }
+                Some((new_slot, target_id))
+            }
+            None => None,
+        };
+        fs.begin();
+        self.modify_disk_inode(|disk_inode| match replaced {
+            Some((new_slot, _)) => {
+                let dirent = DirEntry::new(new, inode_id);
+                disk_inode.write_at(new_slot * DIRENT_SZ, dirent.as_bytes(), &self.block_device);
+                let dirent = DirEntry::empty();
+                disk_inode.write_at(old_slot * DIRENT_SZ, dirent.as_bytes(), &self.block_device);
+            }
+            None => {
+                let dirent = DirEntry::new(new, inode_id);
+                disk_inode.write_at(old_slot * DIRENT_SZ, dirent.as_bytes(), &self.block_device);
+            }
+        });
+        fs.commit();
+        block_cache_sync_all();
+        // 被覆盖的目标在事务提交之后才回收，此时崩溃只会泄漏它占用的索引节点和数据块
+        if let Some((_, target_id)) = replaced {
+            fs.orphan_inode(target_id);
+        }
+        true
+    }
+    // unlink 和 rmdir 将目录项置为无效，被移除的索引节点与 rename 覆盖的目标一样，还被打开着时推迟到最后一个 Inode 被释放时回收。
+    // 目录末尾的无效目录项被一起截掉，删除刚刚创建的文件或目录之后，所在的目录会缩回原来的大小
+    /// Remove the entry `name` under current directory, which must not be a directory.
+    /// Return false if it does not exist or is a directory
+    pub fn unlink(&self, name: &str) -> bool {
+        self.remove_entry(name, |disk_inode, _| !disk_inode.is_dir())
+    }
+    /// Remove the empty directory `name` under current directory. Return false if it does
+    /// not exist, is not a directory or is not empty
+    pub fn rmdir(&self, name: &str) -> bool {
+        self.remove_entry(name, |disk_inode, block_device| {
+            let mut dirent = DirEntry::empty();
+            disk_inode.is_dir()
+                && (0..disk_inode.size as usize / DIRENT_SZ).all(|i| {
+                    disk_inode.read_at(i * DIRENT_SZ, dirent.as_bytes_mut(), block_device);
+                    dirent.name().is_empty()
+                })
+        })
+    }
+    /// Remove the entry `name` under current directory if `removable` holds for its inode
+    fn remove_entry(
+        &self,
+        name: &str,
+        removable: impl FnOnce(&DiskInode, &Arc<dyn BlockDevice>) -> bool,
+    ) -> bool {
+        let mut fs = self.fs.lock();
+        let found = self.read_disk_inode(|disk_inode| {
+            assert!(disk_inode.is_dir());
+            let mut dirent = DirEntry::empty();
+            (0..disk_inode.size as usize / DIRENT_SZ).find_map(|i| {
+                disk_inode.read_at(i * DIRENT_SZ, dirent.as_bytes_mut(), &self.block_device);
+                (!name.is_empty() && dirent.name() == name).then(|| (i, dirent.inode_number()))
+            })
+        });
+        let Some((slot, inode_id)) = found else {
+            return false;
+        };
+        let (block_id, block_offset) = fs.get_disk_inode_pos(inode_id);
+        if !get_metadata_block_cache(block_id as usize, Arc::clone(&self.block_device))
+            .lock()
+            .read(block_offset, |disk_inode: &DiskInode| {
+                removable(disk_inode, &self.block_device)
+            })
+        {
+            return false;
+        }
+        fs.begin();
+        let file_count = self.modify_disk_inode(|disk_inode| {
+            let dirent = DirEntry::empty();
+            disk_inode.write_at(slot * DIRENT_SZ, dirent.as_bytes(), &self.block_device);
+            let mut dirent = DirEntry::empty();
+            let mut file_count = disk_inode.size as usize / DIRENT_SZ;
+            while file_count > 0 {
+                disk_inode.read_at(
+                    (file_count - 1) * DIRENT_SZ,
+                    dirent.as_bytes_mut(),
+                    &self.block_device,
+                );
+                if !dirent.name().is_empty() {
+                    break;
+                }
+                file_count -= 1;
+            }
+            file_count
+        });
+        fs.commit();
+        fs.orphan_inode(inode_id);
+        // 截掉一大段无效目录项要修改的索引块和位图块可能超过日志的容量，因此每个事务只截掉至多
+        // TRUNCATE_STEP_BLOCKS 个块。中途崩溃时目录末尾只是还留着一些无效目录项
+        let new_size = (file_count * DIRENT_SZ) as u32;
+        loop {
+            let size = self.read_disk_inode(|disk_inode| disk_inode.size);
+            if size <= new_size {
+                break;
+            }
+            let blocks = size.div_ceil(BLOCK_SZ as u32);
+            let step_size =
+                new_size.max(blocks.saturating_sub(TRUNCATE_STEP_BLOCKS) * BLOCK_SZ as u32);
+            fs.begin();
+            self.modify_disk_inode(|disk_inode| {
+                let data_blocks_dealloc = disk_inode.decrease_size(step_size, &self.block_device);
+                for data_block in data_blocks_dealloc.into_iter() {
+                    fs.dealloc_data(data_block);
+                }
+            });
+            fs.commit();
+        }
+        block_cache_sync_all();
+        true
+    }
     /// Read data from current inode
     pub fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
         let _fs = self.fs.lock();
         self.read_disk_inode(|disk_inode| disk_inode.read_at(offset, buf, &self.block_device))
     }
-    /// Write data to current inode
+    // 预读：把从 offset 所在块边界开始的至多 blocks 个数据块提前读入块缓存，之后真正读到它们时就不必再等待块设备。
+    // 只会读取文件大小以内的块，不会越过文件末尾；空洞没有对应的块，也不会被读取
+    /// Load up to `blocks` data blocks of current inode, starting from the first block
+    /// boundary at or after `offset`, into the block cache ahead of use.
+    /// Return the number of blocks, holes excluded, that were requested
+    pub fn prefetch(&self, offset: usize, blocks: usize) -> usize {
+        let _fs = self.fs.lock();
+        let block_ids: Vec<u32> = self.read_disk_inode(|disk_inode| {
+            let start = offset.div_ceil(BLOCK_SZ);
+            let end = (start + blocks).min(disk_inode.data_blocks() as usize);
+            (start..end)
+                .map(|inner_id| disk_inode.get_block_id(inner_id as u32, &self.block_device))
+                .filter(|block_id| *block_id != 0)
+                .collect()
+        });
+        for block_id in block_ids.iter() {
+            get_block_cache(*block_id as usize, Arc::clone(&self.block_device));
+        }
+        block_ids.len()
+    }
+    // 把文件中 [offset, offset + len) 范围所在的块（包括通往它们的索引块）从块缓存中移除，腾出位置给其他的块
+    /// Evict the blocks backing `offset..offset + len` of current inode from the block cache
+    pub fn evict_range(&self, offset: usize, len: usize) {
+        let _fs = self.fs.lock();
+        // 超出文件末尾的部分没有对应的块，block_ids_in_range 会将范围截断到文件大小
+        let start = (offset / BLOCK_SZ).min(u32::MAX as usize) as u32;
+        let end = offset
+            .saturating_add(len)
+            .div_ceil(BLOCK_SZ)
+            .min(u32::MAX as usize) as u32;
+        let block_ids: Vec<usize> = self
+            .read_disk_inode(|disk_inode| {
+                disk_inode.block_ids_in_range(start, end, &self.block_device)
+            })
+            .into_iter()
+            .map(|block_id| block_id as usize)
+            .collect();
+        block_cache_evict(&block_ids, &self.block_device);
+    }
+    // 写入的数据先停留在块缓存中，等到块被替换出去或者被显式同步时才会写回磁盘。
+    // 需要写入之后立即持久化的调用者（比如以 O_SYNC 打开的文件）在写入之后调用 sync_range。
+    // 与 resize 一样，超出最大文件大小的写入被整个拒绝
+    /// Write data to current inode. The data blocks are written back lazily, call `sync_range`
+    /// to make them durable. Nothing is written and 0 is returned if the data would end beyond
+    /// the max file size
     pub fn write_at(&self, offset: usize, buf: &[u8]) -> usize {
+        match offset.checked_add(buf.len()) {
+            Some(end) if end <= MAX_FILE_SIZE as usize => {}
+            _ => return 0,
+        }
         let mut fs = self.fs.lock();
-        let size = self.modify_disk_inode(|disk_inode| {
-            self.increase_size((offset + buf.len()) as u32, disk_inode, &mut fs);
+        self.modify_disk_inode(|disk_inode| {
+            self.increase_size(offset, offset + buf.len(), disk_inode, &mut fs);
             disk_inode.write_at(offset, buf, &self.block_device)
+        })
+    }
+    // 将文件中 [offset, offset + len) 范围所在的块写回磁盘：除了数据块和通往它们的索引块，
+    // 还要写回 DiskInode 所在的块（文件大小可能改变了）以及数据块位图（可能分配了新的数据块），否则写回的数据在磁盘上无法被找到
+    /// Sync the blocks backing `offset..offset + len` of current inode, along with the
+    /// metadata describing them, to block device. Return false if the device cannot flush
+    pub fn sync_range(&self, offset: usize, len: usize) -> bool {
+        let fs = self.fs.lock();
+        let start = (offset / BLOCK_SZ) as u32;
+        let end = (offset + len).div_ceil(BLOCK_SZ) as u32;
+        let mut block_ids: Vec<usize> = self
+            .read_disk_inode(|disk_inode| {
+                disk_inode.block_ids_in_range(start, end, &self.block_device)
+            })
+            .into_iter()
+            .map(|block_id| block_id as usize)
+            .collect();
+        block_ids.push(self.block_id);
+        block_ids.extend(fs.data_bitmap.block_ids());
+        block_cache_sync(&block_ids, &self.block_device);
+        block_cache_flush(&self.block_device)
+    }
+    // resize 将文件扩大或缩小到 new_size 字节：扩大的部分是一个空洞，读出来都是 0 ，缩小时回收不再需要的数据块和索引块
+    /// Resize current inode to `new_size` bytes, return false if it exceeds the max file size
+    /// or current inode is a device
+    pub fn resize(&self, new_size: u32) -> bool {
+        if new_size > MAX_FILE_SIZE {
+            return false;
+        }
+        let mut fs = self.fs.lock();
+        // 设备文件的 direct[0] 保存的是设备号，不能被当作数据块
+        if self.read_disk_inode(|disk_inode| disk_inode.device().is_some()) {
+            return false;
+        }
+        self.modify_disk_inode(|disk_inode| {
+            if new_size >= disk_inode.size {
+                disk_inode.increase_size(new_size);
+            } else {
+                let data_blocks_dealloc = disk_inode.decrease_size(new_size, &self.block_device);
+                for data_block in data_blocks_dealloc.into_iter() {
+                    fs.dealloc_data(data_block);
+                }
+            }
         });
         block_cache_sync_all();
-        size
+        true
     }
     /// Clear the data in current inode
     pub fn clear(&self) {
         let mut fs = self.fs.lock();
         self.modify_disk_inode(|disk_inode| {
-            let size = disk_inode.size;
             let data_blocks_dealloc = disk_inode.clear_size(&self.block_device);
-            assert!(data_blocks_dealloc.len() == DiskInode::total_blocks(size) as usize);
             for data_block in data_blocks_dealloc.into_iter() {
                 fs.dealloc_data(data_block);
             }
@@ -192,3 +529,11 @@ impl Inode {
         block_cache_sync_all();
     }
 }
+
+impl Drop for Inode {
+    fn drop(&mut self) {
+        let mut fs = self.fs.lock();
+        let inode_id = fs.get_inode_id(self.block_id as u32, self.block_offset);
+        fs.close_inode(inode_id);
+    }
+}
@@ -17,5 +17,57 @@ pub const PAGE_SIZE_BITS: usize = 0xc;
 
 pub const TRAMPOLINE: usize = usize::MAX - PAGE_SIZE + 1;
 pub const TRAP_CONTEXT: usize = TRAMPOLINE - PAGE_SIZE;
+// 用户通过 mmap 等方式可以使用的虚拟地址上界，即 SV39 中地址空间的低半部分
+pub const USER_SPACE_END: usize = 1 << 38;
+// 堆通过 sbrk 最多可以增长到这么大。紧接在堆的上限之上直到 USER_SPACE_END 都留给 mmap ，两者不会相互侵占
+pub const USER_HEAP_SIZE: usize = 0x400_0000;
+
+// 进程因 SIGSEGV/SIGILL 终止时是否打印 Trap 上下文中的寄存器以及用户栈顶部的内容，便于调试
+pub const COREDUMP: bool = false;
+// coredump 时从用户栈指针开始打印的字节数
+pub const COREDUMP_STACK_BYTES: usize = 128;
+
+// ELF 中同时可写又可执行的 LOAD 段：为 true 时拒绝加载这样的程序（exec 返回 -1），为 false 时打印警告并去掉这个段的可执行权限
+pub const WX_STRICT: bool = true;
+
+// 调试模式下，一个锁被持有超过这么多毫秒时锁的看门狗会让内核 panic
+pub const LOCK_WATCHDOG_MS: usize = 1000;
+
+// 等待 I/O 的任务等到数据之后，接下来这么多次进入就绪队列时都会被排在队头
+pub const IO_BOOST_ROUNDS: usize = 3;
+
+// 进程的 nice 值的范围，与 Unix 相同
+pub const MIN_NICE: isize = -20;
+pub const MAX_NICE: isize = 19;
+
+// 空闲的物理页帧数降到这个值以下时，向通过 sys_register_lowmem 登记过的进程发送 SIGMEMPRESSURE
+pub const LOWMEM_FRAMES: usize = 1024;
+
+// 页着色：假设最后一级缓存为物理索引，每一路的大小（组数乘以缓存行大小）是 PAGE_COLORS 个页面，
+// 比如 1MiB、16 路组相联的缓存每一路为 64KiB ，即 16 种颜色。物理页号对 PAGE_COLORS 取模相同的页帧映射到同一批缓存组，
+// 开启 PAGE_COLORING 后，逻辑段中连续的虚拟页面会被分配到颜色不同的页帧上，减少它们在缓存中的冲突
+pub const PAGE_COLORING: bool = false;
+pub const PAGE_COLORS: usize = 16;
+
+// 取消映射的页面超过这么多时，用一条 sfence.vma 清空整个快表，而不是逐页刷新。
+// 逐页刷新的开销与页数成正比，清空快表的开销则在于之后重新填充其他页面的表项。
+// 这只是一个估计值，换用不同的平台时可以用 mm::tlb_flush_bench 测量两者的开销后重新选择
+pub const TLB_FLUSH_BATCH: usize = 64;
+
+// 内核目前只在编号为 0 的一个 hart 上运行。CPU 亲和性掩码的第 i 位表示允许在 hart i 上运行，只有低 HART_NUM 位有效
+pub const HART_NUM: usize = 1;
+
+// 块缓存的替换算法，可选 Fifo 、Lru 和 Lru2 。Lru2 优先替换只用过一次的块，
+// 顺序扫描大文件时不会把反复使用的元数据块和小文件的块挤出缓存
+pub const BLOCK_CACHE_POLICY: easy_fs::CachePolicy = easy_fs::CachePolicy::Lru2;
+
+// 内核性能剖析的直方图把内核代码段平均分成这么多个区间
+pub const PROFILE_BUCKETS: usize = 256;
+
+// 一次 poll 最多可以等待的文件描述符个数
+pub const POLL_MAX_FDS: usize = 1024;
+
+// 内核传给初始进程的环境变量，之后会随着 fork 和 exec 被所有进程继承
+pub const INIT_ENV: &[&str] = &["PATH=/"];
 
 pub use crate::board::{CLOCK_FREQ, MEMORY_END, MMIO};
@@ -33,6 +33,12 @@ impl BlockDevice for VirtIOBlock {
             .write_block(block_id, buf)
             .expect("Error when writing VirtIOBlk");
     }
+    // 目前使用的 virtio-drivers 版本没有提供 flush 请求，也不暴露请求队列，无法发出 VIRTIO_BLK_T_FLUSH ，
+    // 初始化时也不会与设备协商 VIRTIO_BLK_F_FLUSH 特性。没有协商该特性时设备应当工作在写直达（write-through）模式下，
+    // 但这只是设备的承诺而不是一次真正的 flush ，因此如实返回 false
+    fn flush(&self) -> bool {
+        false
+    }
 }
 
 impl VirtIOBlock {
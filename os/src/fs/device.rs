@@ -0,0 +1,114 @@
+//! Device files
+// mknod 创建的设备文件在文件系统中只记录一个设备号，打开它时得到的是内核中具有这个设备号的设备
+use super::{File, Stdin, Stdout, POLLIN, POLLOUT};
+use crate::mm::UserBuffer;
+use crate::task::TaskControlBlock;
+use alloc::sync::Arc;
+use alloc::vec;
+
+// 设备号的编码与 Linux 早期的 16 位设备号相同：高 8 位是主设备号，低 8 位是次设备号
+/// Make a device number from the major and minor numbers
+pub const fn makedev(major: u32, minor: u32) -> u32 {
+    (major << 8) | minor
+}
+/// Device number of the null device
+pub const DEV_NULL: u32 = makedev(1, 3);
+/// Device number of the zero device
+pub const DEV_ZERO: u32 = makedev(1, 5);
+/// Device number of the console
+pub const DEV_CONSOLE: u32 = makedev(5, 1);
+
+/// In-kernel devices which can be opened through a device file
+#[derive(Clone, Copy)]
+enum Device {
+    /// Discard all writes and read nothing
+    Null,
+    /// Discard all writes and read zeros
+    Zero,
+    /// Read from stdin and write to stdout
+    Console,
+}
+
+/// An opened device file
+pub struct DeviceFile {
+    device: Device,
+    readable: bool,
+    writable: bool,
+}
+
+/// Open the device numbered `dev`, `None` if there is no such device
+pub fn open_device(dev: u32, readable: bool, writable: bool) -> Option<Arc<DeviceFile>> {
+    let device = match dev {
+        DEV_NULL => Device::Null,
+        DEV_ZERO => Device::Zero,
+        DEV_CONSOLE => Device::Console,
+        _ => return None,
+    };
+    Some(Arc::new(DeviceFile {
+        device,
+        readable,
+        writable,
+    }))
+}
+
+impl File for DeviceFile {
+    fn readable(&self) -> bool {
+        self.readable
+    }
+    fn writable(&self) -> bool {
+        self.writable
+    }
+    fn read(&self, user_buf: UserBuffer) -> usize {
+        match self.device {
+            Device::Null => 0,
+            Device::Zero => {
+                let mut user_buf = user_buf;
+                for buffer in user_buf.buffers.iter_mut() {
+                    buffer.fill(0);
+                }
+                user_buf.len()
+            }
+            // Stdin 每次只读入一个字节
+            Device::Console => match user_buf.buffers.into_iter().find(|b| !b.is_empty()) {
+                Some(buffer) => Stdin.read(UserBuffer::new(vec![buffer.split_at_mut(1).0])),
+                None => 0,
+            },
+        }
+    }
+    fn write(&self, user_buf: UserBuffer) -> usize {
+        match self.device {
+            Device::Null | Device::Zero => user_buf.len(),
+            Device::Console => Stdout.write(user_buf),
+        }
+    }
+    fn ioctl(&self, cmd: usize, arg: usize) -> isize {
+        match self.device {
+            Device::Console => Stdout.ioctl(cmd, arg),
+            _ => -1,
+        }
+    }
+    // 控制台的可读状态与 Stdin 相同，其他设备总是可以读写
+    fn poll(&self) -> u16 {
+        let mut revents = 0;
+        if self.readable {
+            revents |= match self.device {
+                Device::Console => Stdin.poll(),
+                _ => POLLIN,
+            };
+        }
+        if self.writable {
+            revents |= POLLOUT;
+        }
+        revents
+    }
+    fn watch(&self, task: &Arc<TaskControlBlock>) {
+        if let Device::Console = self.device {
+            Stdin.watch(task);
+        }
+    }
+    fn unwatch(&self, task: &Arc<TaskControlBlock>) {
+        if let Device::Console = self.device {
+            Stdin.unwatch(task);
+        }
+    }
+}
@@ -0,0 +1,139 @@
+//! Advisory file locks (flock)
+use crate::sync::UPSafeCell;
+use crate::task::{
+    block_current_and_run_next, current_has_pending_signal, current_task, wakeup_task,
+    TaskControlBlock,
+};
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use lazy_static::*;
+
+/// Place a shared lock
+pub const LOCK_SH: usize = 1;
+/// Place an exclusive lock
+pub const LOCK_EX: usize = 2;
+/// Do not block when the lock is held by others
+pub const LOCK_NB: usize = 4;
+/// Remove an existing lock
+pub const LOCK_UN: usize = 8;
+
+// 与 Unix 的 flock 一致，锁属于打开的文件（即 OSInode ，fork 和 dup 得到的文件描述符共享同一把锁），
+// 用它的地址作为锁的持有者。锁只是建议性的，不会阻止没有加锁的进程读写文件
+/// Lock state of an inode
+#[derive(Default)]
+struct FileLock {
+    /// holders of the shared lock
+    shared: BTreeSet<usize>,
+    /// holder of the exclusive lock
+    exclusive: Option<usize>,
+    /// tasks waiting for the lock to be released
+    waiters: Vec<Arc<TaskControlBlock>>,
+}
+
+impl FileLock {
+    // 尝试为 owner 加锁，已经持有锁的 owner 可以在共享锁和互斥锁之间转换
+    fn try_lock(&mut self, owner: usize, exclusive: bool) -> bool {
+        if self.exclusive.is_some() && self.exclusive != Some(owner) {
+            return false;
+        }
+        if exclusive {
+            if self.shared.iter().any(|holder| *holder != owner) {
+                return false;
+            }
+            self.shared.remove(&owner);
+            self.exclusive = Some(owner);
+        } else {
+            self.exclusive = None;
+            self.shared.insert(owner);
+        }
+        true
+    }
+    fn unlock(&mut self, owner: usize) {
+        self.shared.remove(&owner);
+        if self.exclusive == Some(owner) {
+            self.exclusive = None;
+        }
+    }
+    fn is_unused(&self) -> bool {
+        self.shared.is_empty() && self.exclusive.is_none() && self.waiters.is_empty()
+    }
+}
+
+lazy_static! {
+    /// Lock states indexed by inode number
+    static ref FLOCK_TABLE: UPSafeCell<BTreeMap<u32, FileLock>> =
+        unsafe { UPSafeCell::new(BTreeMap::new()) };
+}
+
+/// Acquire a shared or an exclusive lock on inode `inode_id` for `owner`, blocking until
+/// the lock is available unless `nonblock` is set.
+/// Return false if the lock is busy in nonblocking mode or the wait is interrupted by a signal.
+pub fn flock_acquire(inode_id: u32, owner: usize, exclusive: bool, nonblock: bool) -> bool {
+    let task = current_task().unwrap();
+    loop {
+        let mut table = FLOCK_TABLE.exclusive_access();
+        let lock = table.entry(inode_id).or_default();
+        // 互斥锁降级为共享锁之后，等待共享锁的进程就可以获得锁了
+        let downgrade = lock.exclusive == Some(owner) && !exclusive;
+        if lock.try_lock(owner, exclusive) {
+            let woken = if downgrade {
+                core::mem::take(&mut lock.waiters)
+            } else {
+                Vec::new()
+            };
+            drop(table);
+            for waiter in woken {
+                wakeup_task(waiter);
+            }
+            return true;
+        }
+        // 与 Unix 一致，共享锁转换为互斥锁时先释放原先持有的共享锁，以免两个同时升级的进程互相等待
+        let woken = if lock.shared.remove(&owner) {
+            core::mem::take(&mut lock.waiters)
+        } else {
+            Vec::new()
+        };
+        let give_up = nonblock || current_has_pending_signal();
+        if give_up {
+            if lock.is_unused() {
+                table.remove(&inode_id);
+            }
+        } else {
+            lock.waiters.push(task.clone());
+        }
+        drop(table);
+        for waiter in woken {
+            wakeup_task(waiter);
+        }
+        if give_up {
+            return false;
+        }
+        block_current_and_run_next();
+        // 被唤醒的原因可能是锁被释放或者收到了信号，从等待队列中移除自己之后重新检查
+        let mut table = FLOCK_TABLE.exclusive_access();
+        if let Some(lock) = table.get_mut(&inode_id) {
+            lock.waiters.retain(|waiter| !Arc::ptr_eq(waiter, &task));
+        }
+    }
+}
+
+/// Release the lock held by `owner` on inode `inode_id`
+pub fn flock_release(inode_id: u32, owner: usize) {
+    let mut table = FLOCK_TABLE.exclusive_access();
+    let woken = match table.get_mut(&inode_id) {
+        Some(lock) => {
+            lock.unlock(owner);
+            let woken = core::mem::take(&mut lock.waiters);
+            if lock.is_unused() {
+                table.remove(&inode_id);
+            }
+            woken
+        }
+        None => Vec::new(),
+    };
+    drop(table);
+    for waiter in woken {
+        wakeup_task(waiter);
+    }
+}
@@ -2,17 +2,23 @@
 //! we need to wrap `Inode` into `Arc`,but `Mutex` in `Inode` prevents
 //! file systems from being accessed simultaneously
 //!
-//! `UPSafeCell<OSInodeInner>` -> `OSInode`: for static `ROOT_INODE`,we
-//! need to wrap `OSInodeInner` into `UPSafeCell`
-use super::File;
+//! `Mutex<OSInodeInner>` -> `OSInode`: an opened file may be shared by
+//! several processes after `fork`, so the offset is protected by a `Mutex`
+use super::{canonicalize, flock_release, open_device, File};
+use crate::config::BLOCK_CACHE_POLICY;
 use crate::drivers::BLOCK_DEVICE;
 use crate::mm::UserBuffer;
-use crate::sync::UPSafeCell;
+use crate::task::PreemptGuard;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use bitflags::*;
-use easy_fs::{EasyFileSystem, Inode};
+use easy_fs::{
+    block_cache_set_policy, DiskInodeType, EasyFileSystem, FsStat, Inode, BLOCK_SZ, MAX_FILE_SIZE,
+};
 use lazy_static::*;
+use spin::Mutex;
 // 站在用户的角度看来，在一个进程中可以使用多种不同的标志来打开一个文件，这会影响到打开的这个文件可以用何种方式被访问。
 // 此外，在连续调用 sys_read/write 读写一个文件的时候，我们知道进程中也存在着一个文件读写的当前偏移量，它也随着文件读写的进行而被不断更新。
 // 这些用户视角中的文件系统抽象特征需要内核来实现，与进程有很大的关系，而 easy-fs 文件系统不必涉及这些与进程结合紧密的属性。
@@ -23,28 +29,185 @@ use lazy_static::*;
 pub struct OSInode {
     readable: bool,
     writable: bool,
-    inner: UPSafeCell<OSInodeInner>,
+    // 以 SYNC 标志打开的文件每次 write 返回前都会将写过的块同步到磁盘。
+    // 设备无法 flush 时写入照常返回，失败被记下并由下一次 sync/syncfs 报告
+    sync: bool,
+    // 打开时使用的规范化的绝对路径，fchdir 通过它得知目录所在的位置
+    path: String,
+    inner: Mutex<OSInodeInner>,
 }
 
 // 在 sys_read/write 期间被维护偏移量 offset 和它在 easy-fs 中的 Inode 则加上一把互斥锁丢到 OSInodeInner 中。这在提供内部可变性的同时，也可以简单应对多个进程同时读写一个文件的情况
-/// The OS inode inner in 'UPSafeCell'
+// fork 之后父子进程的文件描述符表指向同一个 OSInode ，它们交替读写时通过这把锁互斥地访问同一个偏移量
+/// The OS inode inner in 'Mutex'
 pub struct OSInodeInner {
     offset: usize,
     inode: Arc<Inode>,
+    // 访问模式的提示：上一次读结束的位置。下一次读恰好从这里开始时认为是顺序访问，才会进行预读
+    read_end: usize,
+    // 应用通过 fadvise 给出的访问模式，决定读的时候是否预读以及预读多少
+    advice: usize,
+    // 累计预读的块数
+    read_ahead: usize,
+    // 目录游标：打开的是目录时，下一次 getdents 从第几个目录项开始读
+    dir_pos: usize,
+}
+
+// 顺序读时在每次读完之后预先读入的块数，保守起见只预读紧接着的两个块
+const READ_AHEAD_BLOCKS: usize = 2;
+// 应用声明会顺序读时，不论实际的访问模式如何都预读更多的块
+const SEQUENTIAL_READ_AHEAD_BLOCKS: usize = 4;
+
+// fadvise 的访问模式，取值与 Linux 相同
+/// No advice, read ahead only when the reads are detected to be sequential
+pub const POSIX_FADV_NORMAL: usize = 0;
+/// Random access expected, never read ahead
+pub const POSIX_FADV_RANDOM: usize = 1;
+/// Sequential access expected, always read ahead aggressively
+pub const POSIX_FADV_SEQUENTIAL: usize = 2;
+/// The range will not be accessed soon, drop its blocks from the block cache
+pub const POSIX_FADV_DONTNEED: usize = 4;
+
+// 文件的状态，以 C 的内存布局排列，内核可以直接将它交给应用
+/// Status of a file
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Stat {
+    /// inode number
+    pub ino: usize,
+    /// file type and permission bits
+    pub mode: u32,
+    /// size of the file in bytes
+    pub size: usize,
+}
+
+/// File type bits of a regular file in `Stat::mode`
+pub const S_IFREG: u32 = 0o100000;
+/// File type bits of a directory in `Stat::mode`
+pub const S_IFDIR: u32 = 0o040000;
+/// File type bits of a character device in `Stat::mode`
+pub const S_IFCHR: u32 = 0o020000;
+/// Mask of the file type bits in `Stat::mode`
+pub const S_IFMT: u32 = 0o170000;
+
+// statx 的 mask 各位的取值与 Linux 相同
+/// `Statx::mode` has the file type bits
+pub const STATX_TYPE: u32 = 0x1;
+/// `Statx::mode` has the permission bits
+pub const STATX_MODE: u32 = 0x2;
+/// `Statx::ino` is valid
+pub const STATX_INO: u32 = 0x100;
+/// `Statx::size` is valid
+pub const STATX_SIZE: u32 = 0x200;
+/// `Statx::blocks` is valid
+pub const STATX_BLOCKS: u32 = 0x400;
+// Linux 没有使用这一位，这里用它表示 easy-fs 特有的读写字节数
+/// `Statx::bytes_read` and `Statx::bytes_written` are valid
+pub const STATX_IO: u32 = 0x1000_0000;
+
+// statx 返回的扩展状态。调用者通过 mask 只请求需要的字段，内核在返回的 mask 中给出实际填写了的字段，
+// 其余字段为 0 。easy-fs 的索引节点中没有时间戳，因此时间戳字段（STATX_ATIME/MTIME/CTIME）总是无效的
+/// Extended status of a file
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Statx {
+    /// `STATX_*` bits of the fields filled in
+    pub mask: u32,
+    /// preferred size of an I/O, which is always filled in
+    pub blksize: u32,
+    /// inode number
+    pub ino: u64,
+    /// file type and permission bits
+    pub mode: u32,
+    /// size of the file in bytes
+    pub size: u64,
+    /// number of 512-byte blocks allocated, including index blocks
+    pub blocks: u64,
+    /// time of last access in milliseconds
+    pub atime_ms: u64,
+    /// time of last modification in milliseconds
+    pub mtime_ms: u64,
+    /// time of last status change in milliseconds
+    pub ctime_ms: u64,
+    /// bytes read from the file since it was created or the kernel booted
+    pub bytes_read: u64,
+    /// bytes written to the file since it was created or the kernel booted
+    pub bytes_written: u64,
+}
+
+/// Max length of a file name in `Dirent`, excluding the ending `\0`
+const NAME_LENGTH_LIMIT: usize = 27;
+
+// 目录项类型的取值与 Linux 的 d_type 相同
+/// Type of an entry whose inode cannot be read
+const DT_UNKNOWN: u8 = 0;
+/// Type of a character device entry
+const DT_CHR: u8 = 2;
+/// Type of a directory entry
+const DT_DIR: u8 = 4;
+/// Type of a regular file entry
+const DT_REG: u8 = 8;
+
+// getdents 返回的目录项，以 C 的内存布局排列，每一项的大小都是固定的。
+// d_type 占用了 ino 之后原本用于对齐的填充字节，目录项的大小仍然是 40 字节
+/// A directory entry returned by `getdents`
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct Dirent {
+    /// inode number
+    pub ino: u64,
+    /// one of `DT_*`
+    pub d_type: u8,
+    /// file name ending with `\0`
+    pub name: [u8; NAME_LENGTH_LIMIT + 1],
+}
+
+const _: () = assert!(core::mem::size_of::<Dirent>() == 40);
+
+impl Dirent {
+    fn new(name: &str, ino: u32, type_: Option<DiskInodeType>) -> Self {
+        let mut bytes = [0u8; NAME_LENGTH_LIMIT + 1];
+        bytes[..name.len()].copy_from_slice(name.as_bytes());
+        Self {
+            ino: ino as u64,
+            d_type: match type_ {
+                Some(DiskInodeType::File) => DT_REG,
+                Some(DiskInodeType::Directory) => DT_DIR,
+                Some(DiskInodeType::Device) => DT_CHR,
+                None => DT_UNKNOWN,
+            },
+            name: bytes,
+        }
+    }
 }
 
 impl OSInode {
-    /// Construct an OS inode from a inode
-    pub fn new(readable: bool, writable: bool, inode: Arc<Inode>) -> Self {
+    /// Construct an OS inode from a inode opened through the canonical `path`
+    pub fn new(
+        path: String,
+        readable: bool,
+        writable: bool,
+        sync: bool,
+        inode: Arc<Inode>,
+    ) -> Self {
         Self {
             readable,
             writable,
-            inner: unsafe { UPSafeCell::new(OSInodeInner { offset: 0, inode }) },
+            sync,
+            path,
+            inner: Mutex::new(OSInodeInner {
+                offset: 0,
+                inode,
+                read_end: 0,
+                advice: POSIX_FADV_NORMAL,
+                read_ahead: 0,
+                dir_pos: 0,
+            }),
         }
     }
     /// Read all data inside a inode into vector
     pub fn read_all(&self) -> Vec<u8> {
-        let mut inner = self.inner.exclusive_access();
+        let mut inner = self.inner.lock();
         let mut buffer = [0u8; 512];
         let mut v: Vec<u8> = Vec::new();
         loop {
@@ -57,16 +220,129 @@ impl OSInode {
         }
         v
     }
+    /// Number of blocks read ahead so far
+    pub fn read_ahead(&self) -> usize {
+        self.inner.lock().read_ahead
+    }
+    /// Device number if the inode is a device file
+    pub fn device(&self) -> Option<u32> {
+        self.inner.lock().inode.device()
+    }
+    // 数据经过内核中的一个块大小的缓冲区逐块复制，两个文件的锁不会同时持有，因此可以在同一个文件内部复制
+    /// Copy at most `len` bytes from `offset` of the file to `out_offset` of `out`, using and
+    /// moving the file offset instead where the offset is `None`. Return the number of bytes
+    /// copied, or `None` if either file is a directory, the two ranges overlap in one file or
+    /// the copy would end beyond the max file size
+    pub fn copy_range(
+        &self,
+        offset: Option<usize>,
+        out: &OSInode,
+        out_offset: Option<usize>,
+        len: usize,
+    ) -> Option<usize> {
+        // 复制过程中反复获取文件系统和块缓存的锁，整个复制过程都不能被抢占
+        let _preempt = PreemptGuard::new();
+        let inode = self.inner.lock().inode.clone();
+        let out_inode = out.inner.lock().inode.clone();
+        if inode.is_dir() || out_inode.is_dir() {
+            return None;
+        }
+        let start = offset.unwrap_or_else(|| self.inner.lock().offset);
+        let out_start = out_offset.unwrap_or_else(|| out.inner.lock().offset);
+        // 源文件末尾之后没有数据可以复制
+        let len = len.min((inode.size() as usize).saturating_sub(start));
+        // 目标范围超出了最大文件大小时什么都不复制
+        match out_start.checked_add(len) {
+            Some(out_end) if out_end <= MAX_FILE_SIZE as usize => {}
+            _ => return None,
+        }
+        if inode.inode_id() == out_inode.inode_id()
+            && start < out_start + len
+            && out_start < start + len
+        {
+            return None;
+        }
+        let mut buffer = [0u8; BLOCK_SZ];
+        let mut copied = 0usize;
+        while copied < len {
+            let chunk = (len - copied).min(BLOCK_SZ);
+            let read_size = inode.read_at(start + copied, &mut buffer[..chunk]);
+            if read_size == 0 {
+                break;
+            }
+            let write_size = out_inode.write_at(out_start + copied, &buffer[..read_size]);
+            copied += write_size;
+            if write_size < read_size {
+                break;
+            }
+        }
+        if out.sync {
+            out_inode.sync_range(out_start, copied);
+        }
+        if offset.is_none() {
+            self.inner.lock().offset = start + copied;
+        }
+        if out_offset.is_none() {
+            out.inner.lock().offset = out_start + copied;
+        }
+        io_count(inode.inode_id(), copied, 0);
+        io_count(out_inode.inode_id(), 0, copied);
+        Some(copied)
+    }
 }
 
 
+// 每个索引节点被读写的字节数，通过所有 OSInode 读写同一个文件都累计到一处，文件关闭之后也不会清零。
+// 计数只保存在内存中，从内核启动或者文件被创建时开始累计
 lazy_static! {
-    pub static ref ROOT_INODE: Arc<Inode> = {
+    /// Bytes read from and written to each inode, by inode id
+    static ref IO_COUNTERS: Mutex<BTreeMap<u32, (u64, u64)>> = Mutex::new(BTreeMap::new());
+}
+/// Add to the bytes read from and written to inode `inode_id`
+fn io_count(inode_id: u32, read: usize, written: usize) {
+    let mut counters = IO_COUNTERS.lock();
+    let (bytes_read, bytes_written) = counters.entry(inode_id).or_default();
+    *bytes_read += read as u64;
+    *bytes_written += written as u64;
+}
+/// Bytes read from and written to inode `inode_id`
+fn io_counters(inode_id: u32) -> (u64, u64) {
+    IO_COUNTERS
+        .lock()
+        .get(&inode_id)
+        .copied()
+        .unwrap_or_default()
+}
+
+lazy_static! {
+    /// The easy-fs on the block device
+    pub static ref EFS: Arc<Mutex<EasyFileSystem>> = {
+        block_cache_set_policy(BLOCK_CACHE_POLICY);
         // 从块设备 BLOCK_DEVICE 上打开文件系统
         let efs = EasyFileSystem::open(BLOCK_DEVICE.clone());
-        // 从文件系统中获取根目录的 inode 
-        Arc::new(EasyFileSystem::root_inode(&efs))
+        // 块设备不支持 flush 时，无法保证数据写入了存储介质，sync 和 syncfs 都会返回 -1
+        if !BLOCK_DEVICE.flush() {
+            println!("[kernel] block device cannot flush, sync and syncfs will fail");
+        }
+        // 挂载时检查一遍文件系统的一致性，只打印警告而不做修复
+        for problem in efs.lock().check() {
+            println!("[kernel] easy-fs check: {}", problem);
+        }
+        efs
     };
+    // 从文件系统中获取根目录的 inode
+    pub static ref ROOT_INODE: Arc<Inode> = Arc::new(EasyFileSystem::root_inode(&EFS));
+}
+/// Get the usage of the filesystem
+pub fn fs_stat() -> FsStat {
+    let _preempt = PreemptGuard::new();
+    EFS.lock().stat()
+}
+/// Write back the cached blocks of the filesystem, return the number of blocks skipped, or
+/// None if the block device cannot flush
+pub fn sync_fs() -> Option<usize> {
+    let _preempt = PreemptGuard::new();
+    EFS.lock().sync()
 }
 /// List all files in the filesystems
 pub fn list_apps() {
@@ -90,6 +366,12 @@ bitflags! {
         const CREATE = 1 << 9;
         ///Clear file and return an empty one
         const TRUNC = 1 << 10;
+        ///Return instead of waiting when reading would block
+        const NONBLOCK = 1 << 11;
+        ///Sync written data to disk before write returns
+        const SYNC = 1 << 12;
+        ///Close the file descriptor on exec
+        const CLOEXEC = 1 << 19;
     }
 }
 
@@ -97,37 +379,185 @@ impl OpenFlags {
     /// Do not check validity for simplicity
     /// Return (readable, writable)
     pub fn read_write(&self) -> (bool, bool) {
-        if self.is_empty() {
+        // 只有最低两位表示访问模式
+        let access = *self & (Self::WRONLY | Self::RDWR);
+        if access.is_empty() {
             (true, false)
-        } else if self.contains(Self::WRONLY) {
+        } else if access.contains(Self::WRONLY) {
             (false, true)
         } else {
             (true, true)
         }
     }
 }
-///Open file with flags
-pub fn open_file(name: &str, flags: OpenFlags) -> Option<Arc<OSInode>> {
-    let (readable, writable) = flags.read_write();
-    // 只有 flags 参数包含 CREATE 标志位才允许创建文件.而如果文件已经存在，则清空文件的内容
-    if flags.contains(OpenFlags::CREATE) {
-        if let Some(inode) = ROOT_INODE.find(name) {
-            // clear size
-            inode.clear();
-            Some(Arc::new(OSInode::new(readable, writable, inode)))
-        } else {
-            // create file
-            ROOT_INODE
-                .create(name)
-                .map(|inode| Arc::new(OSInode::new(readable, writable, inode)))
+// 打开设备文件时得到的是它的设备号对应的设备，其他文件和目录得到的是 OSInode
+///Open file with flags, a device file gives the device it refers to
+pub fn open_file(path: &str, flags: OpenFlags) -> Option<Arc<dyn File + Send + Sync>> {
+    let inode = open_inode(path, flags)?;
+    match inode.device() {
+        Some(dev) => open_device(dev, inode.readable, inode.writable)
+            .map(|device| device as Arc<dyn File + Send + Sync>),
+        None => Some(inode),
+    }
+}
+
+/// Why a path cannot be resolved or created
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathError {
+    /// A component of the path does not exist
+    NotFound,
+    /// A component used as a directory is not a directory
+    NotDir,
+    /// The path to create already exists
+    Exists,
+    /// No free inode is left
+    NoSpace,
+    /// The file to unlink is a directory
+    IsDir,
+    /// The directory to remove is not empty
+    NotEmpty,
+    /// The root directory cannot be removed
+    Busy,
+}
+
+// 从根目录出发逐级查找规范化之后的路径，除了最后一级之外的每一级都必须是目录
+fn lookup(path: &str) -> Result<Arc<Inode>, PathError> {
+    let mut inode = ROOT_INODE.clone();
+    for name in path.split('/').filter(|name| !name.is_empty()) {
+        if !inode.is_dir() {
+            return Err(PathError::NotDir);
         }
-    } else {
-        ROOT_INODE.find(name).map(|inode| {
-            if flags.contains(OpenFlags::TRUNC) {
+        inode = inode.find(name).ok_or(PathError::NotFound)?;
+    }
+    Ok(inode)
+}
+
+// 查找规范化之后的路径所在的目录，返回这个目录和路径的最后一级名字。根目录已经存在，它没有所在的目录
+fn lookup_parent(path: &str) -> Result<(Arc<Inode>, &str), PathError> {
+    let (parent, name) = path.rsplit_once('/').unwrap();
+    if name.is_empty() {
+        return Err(PathError::Exists);
+    }
+    let parent = lookup(parent)?;
+    if !parent.is_dir() {
+        return Err(PathError::NotDir);
+    }
+    Ok((parent, name))
+}
+
+// 打开文件在文件系统中的索引节点本身，设备文件也不例外。exec 读取应用以及按路径修改文件属性时使用
+///Open the inode of a file with flags
+pub fn open_inode(path: &str, flags: OpenFlags) -> Option<Arc<OSInode>> {
+    let path = canonicalize(path, "/");
+    let (readable, writable) = flags.read_write();
+    let sync = flags.contains(OpenFlags::SYNC);
+    // 只有 flags 参数包含 CREATE 标志位才允许创建文件，而如果文件已经存在，则清空文件的内容
+    let inode = if flags.contains(OpenFlags::CREATE) {
+        let (parent, name) = lookup_parent(&path).ok()?;
+        match parent.find(name) {
+            Some(inode) if inode.is_dir() => return None,
+            Some(inode) => {
                 inode.clear();
+                inode
             }
-            Arc::new(OSInode::new(readable, writable, inode))
-        })
+            None => {
+                // 索引节点编号可能来自一个已经删除的文件，新文件的计数从 0 开始
+                let inode = parent.create(name)?;
+                IO_COUNTERS.lock().remove(&inode.inode_id());
+                inode
+            }
+        }
+    } else {
+        lookup(&path).ok()?
+    };
+    // 目录只能以只读的方式打开，其中的目录项只能通过 getdents 读取而不能使用 read
+    if inode.is_dir() {
+        if writable || flags.contains(OpenFlags::TRUNC) {
+            return None;
+        }
+        return Some(Arc::new(OSInode::new(path, false, false, false, inode)));
+    }
+    if flags.contains(OpenFlags::TRUNC) {
+        inode.clear();
+    }
+    Some(Arc::new(OSInode::new(
+        path, readable, writable, sync, inode,
+    )))
+}
+
+// 在 path 所在的目录下创建一个设备文件，其中只记录设备号 dev ，不检查这个设备是否存在
+/// Create a device file with permission bits `mode` referring to device `dev`
+pub fn mknod_file(path: &str, mode: u32, dev: u32) -> bool {
+    let path = canonicalize(path, "/");
+    let Ok((parent, name)) = lookup_parent(&path) else {
+        return false;
+    };
+    match parent.mknod(name, dev) {
+        Some(inode) => {
+            inode.set_mode(mode as u16);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Create an empty directory with permission bits `mode`
+pub fn mkdir_path(path: &str, mode: u32) -> Result<(), PathError> {
+    let path = canonicalize(path, "/");
+    let (parent, name) = lookup_parent(&path)?;
+    if parent.find(name).is_some() {
+        return Err(PathError::Exists);
+    }
+    let inode = parent.mkdir(name).ok_or(PathError::NoSpace)?;
+    inode.set_mode(mode as u16);
+    Ok(())
+}
+
+/// Remove the file at `path`, which must not be a directory
+pub fn unlink_path(path: &str) -> Result<(), PathError> {
+    let path = canonicalize(path, "/");
+    let (parent, name) = lookup_parent(&path).map_err(|error| match error {
+        PathError::Exists => PathError::IsDir,
+        error => error,
+    })?;
+    let inode = parent.find(name).ok_or(PathError::NotFound)?;
+    if inode.is_dir() {
+        return Err(PathError::IsDir);
+    }
+    parent.unlink(name);
+    Ok(())
+}
+
+// 目录是否为空由 easy-fs 在持有文件系统锁的情况下检查，非空的目录不会被移除
+/// Remove the empty directory at `path`
+pub fn rmdir_path(path: &str) -> Result<(), PathError> {
+    let path = canonicalize(path, "/");
+    let (parent, name) = lookup_parent(&path).map_err(|error| match error {
+        PathError::Exists => PathError::Busy,
+        error => error,
+    })?;
+    let inode = parent.find(name).ok_or(PathError::NotFound)?;
+    if !inode.is_dir() {
+        return Err(PathError::NotDir);
+    }
+    if !parent.rmdir(name) {
+        return Err(PathError::NotEmpty);
+    }
+    Ok(())
+}
+
+// 目录项的修改由 easy-fs 在持有文件系统锁的情况下一次性完成，因此只支持在同一个目录中重命名
+/// Rename file `old` to `new` in the same directory, replacing the file named `new` if any
+pub fn rename_file(old: &str, new: &str) -> bool {
+    let old = canonicalize(old, "/");
+    let new = canonicalize(new, "/");
+    match (lookup_parent(&old), lookup_parent(&new)) {
+        (Ok((old_parent, old)), Ok((new_parent, new)))
+            if old_parent.inode_id() == new_parent.inode_id() =>
+        {
+            old_parent.rename(old, new)
+        }
+        _ => false,
     }
 }
 
@@ -140,7 +570,11 @@ impl File for OSInode {
     }
     // 遍历 UserBuffer 中的每个缓冲区片段，调用 Inode 写好的 read/write_at 接口就好了
     fn read(&self, mut buf: UserBuffer) -> usize {
-        let mut inner = self.inner.exclusive_access();
+        // 持有文件的锁期间不能被抢占
+        let _preempt = PreemptGuard::new();
+        let mut inner = self.inner.lock();
+        // 两次读之间有写入（偏移量被移动）就不再是顺序访问
+        let sequential = inner.offset == inner.read_end;
         let mut total_read_size = 0usize;
         for slice in buf.buffers.iter_mut() {
             let read_size = inner.inode.read_at(inner.offset, *slice);
@@ -150,17 +584,207 @@ impl File for OSInode {
             inner.offset += read_size;
             total_read_size += read_size;
         }
+        inner.read_end = inner.offset;
+        let blocks = match inner.advice {
+            POSIX_FADV_SEQUENTIAL => SEQUENTIAL_READ_AHEAD_BLOCKS,
+            POSIX_FADV_NORMAL if sequential => READ_AHEAD_BLOCKS,
+            _ => 0,
+        };
+        // 预读不会越过文件末尾，读到文件末尾之后就没有块可以预读了
+        if blocks > 0 && total_read_size > 0 {
+            inner.read_ahead += inner.inode.prefetch(inner.offset, blocks);
+        }
+        io_count(inner.inode.inode_id(), total_read_size, 0);
         total_read_size
     }
     fn write(&self, buf: UserBuffer) -> usize {
-        let mut inner = self.inner.exclusive_access();
+        // 持有文件的锁期间不能被抢占
+        let _preempt = PreemptGuard::new();
+        let mut inner = self.inner.lock();
+        let start = inner.offset;
         let mut total_write_size = 0usize;
         for slice in buf.buffers.iter() {
             let write_size = inner.inode.write_at(inner.offset, *slice);
-            assert_eq!(write_size, slice.len());
             inner.offset += write_size;
             total_write_size += write_size;
+            // 超出了最大文件大小，后面的片段不再写
+            if write_size < slice.len() {
+                break;
+            }
         }
+        if self.sync {
+            inner.inode.sync_range(start, total_write_size);
+        }
+        io_count(inner.inode.inode_id(), 0, total_write_size);
         total_write_size
     }
+    // 定位读写与 read/write 相同，只是从给定的位置开始，也不更新偏移量和顺序访问的状态，因此不触发预读
+    fn read_at(&self, offset: usize, mut buf: UserBuffer) -> Option<usize> {
+        // 持有文件的锁期间不能被抢占
+        let _preempt = PreemptGuard::new();
+        let inner = self.inner.lock();
+        let mut total_read_size = 0usize;
+        for slice in buf.buffers.iter_mut() {
+            let read_size = inner.inode.read_at(offset + total_read_size, *slice);
+            total_read_size += read_size;
+            // 读到文件末尾了，后面的片段不再读
+            if read_size < slice.len() {
+                break;
+            }
+        }
+        io_count(inner.inode.inode_id(), total_read_size, 0);
+        Some(total_read_size)
+    }
+    fn write_at(&self, offset: usize, buf: UserBuffer) -> Option<usize> {
+        // 持有文件的锁期间不能被抢占
+        let _preempt = PreemptGuard::new();
+        // 写入的范围超出了最大文件大小时什么都不写
+        match offset.checked_add(buf.len()) {
+            Some(end) if end <= MAX_FILE_SIZE as usize => {}
+            _ => return None,
+        }
+        let inner = self.inner.lock();
+        let mut total_write_size = 0usize;
+        for slice in buf.buffers.iter() {
+            let write_size = inner.inode.write_at(offset + total_write_size, *slice);
+            total_write_size += write_size;
+        }
+        if self.sync {
+            inner.inode.sync_range(offset, total_write_size);
+        }
+        io_count(inner.inode.inode_id(), 0, total_write_size);
+        Some(total_write_size)
+    }
+    // 截断或扩展文件需要文件以可写的方式打开，扩展的部分读出来都是 0
+    fn truncate(&self, len: usize) -> bool {
+        if !self.writable || len > u32::MAX as usize {
+            return false;
+        }
+        self.inner.lock().inode.resize(len as u32)
+    }
+    fn fadvise(&self, offset: usize, len: usize, advice: usize) -> bool {
+        let mut inner = self.inner.lock();
+        match advice {
+            POSIX_FADV_NORMAL | POSIX_FADV_RANDOM | POSIX_FADV_SEQUENTIAL => {
+                inner.advice = advice;
+            }
+            // 长度为 0 表示一直到文件末尾
+            POSIX_FADV_DONTNEED => {
+                let len = if len == 0 { usize::MAX } else { len };
+                inner.inode.evict_range(offset, len);
+            }
+            _ => return false,
+        }
+        true
+    }
+    fn stat(&self) -> Option<Stat> {
+        let inner = self.inner.lock();
+        let type_ = if inner.inode.is_dir() {
+            S_IFDIR
+        } else if inner.inode.device().is_some() {
+            S_IFCHR
+        } else {
+            S_IFREG
+        };
+        Some(Stat {
+            ino: inner.inode.inode_id() as usize,
+            mode: type_ | inner.inode.mode() as u32,
+            size: inner.inode.size() as usize,
+        })
+    }
+    // 占用的块数需要遍历索引块才能得到，只在调用者请求时计算
+    fn statx(&self, mask: u32) -> Option<Statx> {
+        let stat = self.stat()?;
+        let mut statx = Statx {
+            blksize: BLOCK_SZ as u32,
+            ..Statx::default()
+        };
+        if mask & STATX_TYPE != 0 {
+            statx.mode |= stat.mode & S_IFMT;
+        }
+        if mask & STATX_MODE != 0 {
+            statx.mode |= stat.mode & !S_IFMT;
+        }
+        if mask & STATX_INO != 0 {
+            statx.ino = stat.ino as u64;
+        }
+        if mask & STATX_SIZE != 0 {
+            statx.size = stat.size as u64;
+        }
+        if mask & STATX_BLOCKS != 0 {
+            let blocks = self.inner.lock().inode.allocated_blocks();
+            statx.blocks = blocks as u64 * (BLOCK_SZ / 512) as u64;
+        }
+        if mask & STATX_IO != 0 {
+            (statx.bytes_read, statx.bytes_written) = io_counters(stat.ino as u32);
+        }
+        statx.mask =
+            mask & (STATX_TYPE | STATX_MODE | STATX_INO | STATX_SIZE | STATX_BLOCKS | STATX_IO);
+        Some(statx)
+    }
+    // 权限保存在 DiskInode 中，同一个文件的所有 OSInode 都能立即看到修改。目前还没有用户的概念，任何进程都可以修改权限
+    fn chmod(&self, mode: u32) -> bool {
+        self.inner.lock().inode.set_mode(mode as u16);
+        true
+    }
+    // 从游标处继续读取，连续多次 getdents 不会从头重新扫描目录
+    fn read_dir(&self, max: usize) -> Option<Vec<Dirent>> {
+        let mut inner = self.inner.lock();
+        if !inner.inode.is_dir() {
+            return None;
+        }
+        let (entries, next) = inner.inode.read_dir(inner.dir_pos, max);
+        inner.dir_pos = next;
+        Some(
+            entries
+                .iter()
+                .map(|(name, ino, type_)| Dirent::new(name, *ino, *type_))
+                .collect(),
+        )
+    }
+    fn dir_path(&self) -> Option<String> {
+        if self.inner.lock().inode.is_dir() {
+            Some(self.path.clone())
+        } else {
+            None
+        }
+    }
+    fn inode_id(&self) -> Option<u32> {
+        Some(self.inner.lock().inode.inode_id())
+    }
+    fn os_inode(&self) -> Option<&OSInode> {
+        Some(self)
+    }
+}
+
+// 最后一个引用该打开文件的文件描述符被关闭（包括进程退出时关闭所有文件描述符）时，释放它持有的文件锁
+impl Drop for OSInode {
+    fn drop(&mut self) {
+        let inode_id = self.inner.lock().inode.inode_id();
+        flock_release(inode_id, self as *const Self as usize);
+    }
+}
+
+#[allow(unused)]
+/// a simple test for the read-ahead hints given by fadvise
+pub fn fadvise_test() {
+    use alloc::boxed::Box;
+    let file = open_inode("initproc", OpenFlags::RDONLY).unwrap();
+    let read_block = || {
+        let buffer = Box::leak(vec![0u8; easy_fs::BLOCK_SZ].into_boxed_slice());
+        file.read(UserBuffer::new(vec![buffer]))
+    };
+    // 声明顺序读之后每次读都会预读
+    assert!(file.fadvise(0, 0, POSIX_FADV_SEQUENTIAL));
+    assert!(read_block() > 0);
+    let read_ahead = file.read_ahead();
+    assert!(read_ahead > 0);
+    // 声明随机读之后即使实际上是顺序读也不再预读
+    assert!(file.fadvise(0, 0, POSIX_FADV_RANDOM));
+    read_block();
+    read_block();
+    assert_eq!(file.read_ahead(), read_ahead);
+    assert!(file.fadvise(0, 0, POSIX_FADV_DONTNEED));
+    assert!(!file.fadvise(0, 0, 42));
+    println!("fadvise_test passed!");
 }
@@ -0,0 +1,75 @@
+//! Submission and completion rings for batched I/O
+// 进程在自己的地址空间中准备两个环形队列：提交队列中放入读写请求，内核在一次 sys_io_submit 中依次执行它们，
+// 并把结果放入完成队列，这样多次读写只需要陷入内核一次。
+// 每个队列由 RingHeader 和紧随其后的 entries 个表项组成。head 和 tail 都是只增不减的计数，
+// 表项下标为计数对 entries 取模。提交队列的 tail 和完成队列的 head 由进程推进，另外两个由内核推进
+use core::mem::size_of;
+
+/// Read from `fd` into the buffer
+pub const IORING_OP_READ: u32 = 22;
+/// Write the buffer to `fd`
+pub const IORING_OP_WRITE: u32 = 23;
+/// Maximum number of entries of a ring
+pub const IO_RING_MAX_ENTRIES: usize = 256;
+
+/// Head and tail of a ring, followed by its entries
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct RingHeader {
+    /// count of entries consumed
+    pub head: usize,
+    /// count of entries produced
+    pub tail: usize,
+}
+
+/// A read/write request in the submission ring
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct IoSqe {
+    /// `IORING_OP_READ` or `IORING_OP_WRITE`
+    pub opcode: u32,
+    /// the file descriptor to operate on
+    pub fd: u32,
+    /// address of the user buffer
+    pub buf: usize,
+    /// length of the user buffer
+    pub len: usize,
+    /// copied to the completion unchanged
+    pub user_data: usize,
+}
+
+/// The result of a request in the completion ring
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct IoCqe {
+    /// `user_data` of the request
+    pub user_data: usize,
+    /// what read/write would return for the request
+    pub result: isize,
+}
+
+/// The rings registered by a process, all addresses in its address space
+#[derive(Clone, Copy)]
+pub struct IoRings {
+    /// address of the submission ring
+    pub sq: usize,
+    /// address of the completion ring
+    pub cq: usize,
+    /// number of entries of each ring
+    pub entries: usize,
+}
+
+impl IoRings {
+    /// Address of the submission entry numbered `count`
+    pub fn sqe(&self, count: usize) -> usize {
+        self.sq + size_of::<RingHeader>() + count % self.entries * size_of::<IoSqe>()
+    }
+    /// Address of the completion entry numbered `count`
+    pub fn cqe(&self, count: usize) -> usize {
+        self.cq + size_of::<RingHeader>() + count % self.entries * size_of::<IoCqe>()
+    }
+    /// Size in bytes of the completion ring
+    pub fn cq_size(&self) -> usize {
+        size_of::<RingHeader>() + self.entries * size_of::<IoCqe>()
+    }
+}
@@ -1,9 +1,20 @@
 //! File system in os
+mod device;
+mod flock;
 mod inode;
+mod io_ring;
+mod path;
 mod pipe;
+mod poll;
+mod signalfd;
 mod stdio;
+mod timerfd;
 
-use crate::mm::UserBuffer;
+use crate::mm::{FrameTracker, UserBuffer};
+use crate::task::{SignalFlags, TaskControlBlock};
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
 /// File trait
 pub trait File: Send + Sync {
     /// If readable
@@ -14,8 +25,120 @@ pub trait File: Send + Sync {
     fn read(&self, buf: UserBuffer) -> usize;
     /// Write `UserBuffer` to file
     fn write(&self, buf: UserBuffer) -> usize;
+    /// Device specific control, unsupported by default
+    fn ioctl(&self, _cmd: usize, _arg: usize) -> isize {
+        -1
+    }
+    /// Read file to `UserBuffer`, waiting for data at most `_timeout_ms` milliseconds.
+    /// Return `None` if the wait is interrupted by a signal before any byte is read.
+    /// Files which never block on reading simply perform a normal read by default.
+    fn read_timeout(&self, buf: UserBuffer, _timeout_ms: usize) -> Option<usize> {
+        Some(self.read(buf))
+    }
+    /// Read file to `UserBuffer` starting at `offset` without moving the file offset,
+    /// `None` if the file cannot be accessed positionally like devices and pipes
+    fn read_at(&self, _offset: usize, _buf: UserBuffer) -> Option<usize> {
+        None
+    }
+    /// Write `UserBuffer` to file starting at `offset` without moving the file offset,
+    /// `None` if the file cannot be accessed positionally like devices and pipes
+    fn write_at(&self, _offset: usize, _buf: UserBuffer) -> Option<usize> {
+        None
+    }
+    /// Change the size of the file to `len` bytes, unsupported by default
+    fn truncate(&self, _len: usize) -> bool {
+        false
+    }
+    /// Advise the access pattern of `offset..offset + len` of the file, `len` 0 means
+    /// to the end of the file. Unsupported by default
+    fn fadvise(&self, _offset: usize, _len: usize, _advice: usize) -> bool {
+        false
+    }
+    /// Status of the file on the file system, `None` for devices and pipes
+    fn stat(&self) -> Option<Stat> {
+        None
+    }
+    /// Extended status of the file with the fields requested by the `STATX_*` bits in `mask`,
+    /// `None` for devices and pipes
+    fn statx(&self, _mask: u32) -> Option<Statx> {
+        None
+    }
+    /// Change the permission bits of the file, unsupported by default
+    fn chmod(&self, _mode: u32) -> bool {
+        false
+    }
+    /// Read at most `max` entries of the directory, continuing from where the last read
+    /// stopped. `None` if the file is not a directory
+    fn read_dir(&self, _max: usize) -> Option<Vec<Dirent>> {
+        None
+    }
+    /// Canonical path of the directory, `None` if the file is not a directory
+    fn dir_path(&self) -> Option<String> {
+        None
+    }
+    /// Inode number of the file on the file system, `None` for devices and pipes
+    fn inode_id(&self) -> Option<u32> {
+        None
+    }
+    /// The file as an opened inode, `None` for devices and pipes
+    fn os_inode(&self) -> Option<&OSInode> {
+        None
+    }
+    /// Append a whole page of data by taking over its frame, giving the frame back
+    /// if the file cannot hold frames or gives up waiting for room. Unsupported by default
+    fn push_page(&self, frame: FrameTracker) -> Result<(), FrameTracker> {
+        Err(frame)
+    }
+    /// Wait for data and take the frame holding it if the data starts with a whole page
+    /// pushed by `push_page`, `None` otherwise or if the wait is cancelled. Unsupported by
+    /// default
+    fn pop_page(&self) -> Option<FrameTracker> {
+        None
+    }
+    /// Events of `POLLIN`/`POLLOUT`/`POLLHUP` which are ready now.
+    /// Readable files are always ready to read and writable ones ready to write by default
+    fn poll(&self) -> u16 {
+        let mut revents = 0;
+        if self.readable() {
+            revents |= POLLIN;
+        }
+        if self.writable() {
+            revents |= POLLOUT;
+        }
+        revents
+    }
+    /// Wake up `task` when the result of `poll` may have changed, until `unwatch` is called.
+    /// Files whose readiness never changes need not remember it
+    fn watch(&self, _task: &Arc<TaskControlBlock>) {}
+    /// Stop waking up `task` set by `watch`
+    fn unwatch(&self, _task: &Arc<TaskControlBlock>) {}
+    /// Arm the timer to expire after `value_ms` and then every `interval_ms` (once if 0),
+    /// or disarm it if `value_ms` is 0. Only timer files support it
+    fn settime(&self, _interval_ms: usize, _value_ms: usize) -> bool {
+        false
+    }
+    /// Signals the owner receives by reading this file instead of having them delivered.
+    /// Only signalfds have any
+    fn signalfd_mask(&self) -> SignalFlags {
+        SignalFlags::empty()
+    }
 }
 
-pub use inode::{list_apps, open_file, OSInode, OpenFlags};
+pub use device::{makedev, open_device, DeviceFile, DEV_CONSOLE, DEV_NULL, DEV_ZERO};
+pub use easy_fs::{block_cache_discard_all, block_cache_sync_all, block_cache_try_sync, FsStat};
+pub use flock::{flock_acquire, flock_release, LOCK_EX, LOCK_NB, LOCK_SH, LOCK_UN};
+pub use inode::{
+    fadvise_test, fs_stat, list_apps, mkdir_path, mknod_file, open_file, open_inode, rename_file,
+    rmdir_path, sync_fs, unlink_path, Dirent, OSInode, OpenFlags, PathError, Stat, Statx,
+    POSIX_FADV_DONTNEED, POSIX_FADV_NORMAL, POSIX_FADV_RANDOM, POSIX_FADV_SEQUENTIAL, S_IFCHR,
+    S_IFDIR, S_IFMT, S_IFREG,
+};
+pub use io_ring::{
+    IoCqe, IoRings, IoSqe, RingHeader, IORING_OP_READ, IORING_OP_WRITE, IO_RING_MAX_ENTRIES,
+};
+pub use path::{canonicalize, canonicalize_test};
 pub use pipe::{make_pipe, Pipe};
-pub use stdio::{Stdin, Stdout};
+pub use poll::{PollFd, POLLERR, POLLHUP, POLLIN, POLLNVAL, POLLOUT};
+pub use signalfd::SignalFd;
+pub use stdio::{acquire_console_session, console_poll, release_console_session, Stdin, Stdout};
+pub use timerfd::TimerFd;
@@ -0,0 +1,48 @@
+//! Path manipulation helpers
+use alloc::string::String;
+use alloc::vec::Vec;
+
+// 将 path 相对于当前工作目录 cwd 解析为一个规范的绝对路径：
+// 忽略空的路径分量（连续的 / 以及末尾的 /）和 . ，遇到 .. 时回到上一级目录，在根目录下的 .. 仍然是根目录
+/// Resolve `path` against the absolute directory `cwd` and collapse `.`/`..` components
+pub fn canonicalize(path: &str, cwd: &str) -> String {
+    let mut components: Vec<&str> = Vec::new();
+    // 绝对路径不需要 cwd
+    let base = if path.starts_with('/') { "" } else { cwd };
+    for component in base.split('/').chain(path.split('/')) {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop();
+            }
+            name => components.push(name),
+        }
+    }
+    let mut result = String::new();
+    for name in components.iter() {
+        result.push('/');
+        result.push_str(name);
+    }
+    if result.is_empty() {
+        result.push('/');
+    }
+    result
+}
+
+#[allow(unused)]
+/// a simple test for path canonicalization
+pub fn canonicalize_test() {
+    assert_eq!(canonicalize("initproc", "/"), "/initproc");
+    assert_eq!(canonicalize("/a/b", "/c"), "/a/b");
+    assert_eq!(canonicalize("b", "/a"), "/a/b");
+    assert_eq!(canonicalize("./b/./c", "/a"), "/a/b/c");
+    assert_eq!(canonicalize("../b", "/a/c"), "/a/b");
+    assert_eq!(canonicalize("..", "/"), "/");
+    assert_eq!(canonicalize("../../..", "/a"), "/");
+    assert_eq!(canonicalize("/../a", "/b"), "/a");
+    assert_eq!(canonicalize("a/", "/"), "/a");
+    assert_eq!(canonicalize("a//b///", "/"), "/a/b");
+    assert_eq!(canonicalize("", "/a/b"), "/a/b");
+    assert_eq!(canonicalize(".", "/"), "/");
+    println!("canonicalize_test passed!");
+}
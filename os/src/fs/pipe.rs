@@ -1,9 +1,14 @@
-use super::File;
-use crate::mm::UserBuffer;
+use super::{File, POLLHUP, POLLIN, POLLOUT};
+use crate::config::PAGE_SIZE;
+use crate::mm::{FrameTracker, UserBuffer};
 use crate::sync::UPSafeCell;
+use alloc::collections::VecDeque;
 use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
 
-use crate::task::suspend_current_and_run_next;
+use crate::task::{
+    current_cancelled, suspend_current_and_run_next, wakeup_task, yield_for_io, TaskControlBlock,
+};
 
 // 将管道的一端（读端或写端）抽象为 Pipe 类型
 pub struct Pipe {
@@ -30,9 +35,25 @@ impl Pipe {
             buffer,
         }
     }
+    // 管道中的数据或者空闲空间发生变化，或者有一端被关闭时，唤醒所有在 poll 中等待这个管道的进程。
+    // 调用者不能持有管道的借用
+    fn notify(&self) {
+        let waiters = core::mem::take(&mut self.buffer.exclusive_access().waiters);
+        for task in waiters {
+            wakeup_task(task);
+        }
+    }
+}
+
+impl Drop for Pipe {
+    fn drop(&mut self) {
+        self.notify();
+    }
 }
 
 const RING_BUFFER_SIZE: usize = 32;
+// 管道中最多能排队的整页数据的页数
+const MAX_PIPE_PAGES: usize = 16;
 // RingBufferStatus 记录了缓冲区目前的状态
 #[derive(Copy, Clone, PartialEq)]
 enum RingBufferStatus {
@@ -52,6 +73,14 @@ pub struct PipeRingBuffer {
     status: RingBufferStatus,
     // write_end 字段还保存了它的写端的一个弱引用计数，这是由于在某些情况下需要确认该管道所有的写端是否都已经被关闭了，通过这个字段很容易确认这一点
     write_end: Option<Weak<Pipe>>,
+    // 读端同样保存一个弱引用计数，所有读端都被关闭之后写者就不必再等待空闲空间了
+    read_end: Option<Weak<Pipe>>,
+    // vmsplice 移入的整页数据直接以物理页帧的形式排在循环队列中的数据之后，page_offset 是队头页面中已经被读走的字节数。
+    // 为了保持数据的顺序，队列中还有页面时不能再向循环队列中写入
+    pages: VecDeque<FrameTracker>,
+    page_offset: usize,
+    // 在 poll 中等待这个管道的进程
+    waiters: Vec<Arc<TaskControlBlock>>,
 }
 
 
@@ -63,29 +92,47 @@ impl PipeRingBuffer {
             tail: 0,
             status: RingBufferStatus::Empty,
             write_end: None,
+            read_end: None,
+            pages: VecDeque::new(),
+            page_offset: 0,
+            waiters: Vec::new(),
         }
     }
     pub fn set_write_end(&mut self, write_end: &Arc<Pipe>) {
         self.write_end = Some(Arc::downgrade(write_end));
     }
-    pub fn write_byte(&mut self, byte: u8) {
-        self.status = RingBufferStatus::Normal;
-        self.arr[self.tail] = byte;
-        self.tail = (self.tail + 1) % RING_BUFFER_SIZE;
-        if self.tail == self.head {
-            self.status = RingBufferStatus::Full;
-        }
+    pub fn set_read_end(&mut self, read_end: &Arc<Pipe>) {
+        self.read_end = Some(Arc::downgrade(read_end));
     }
-    // 管道中读取一个字节，注意在调用它之前需要确保管道缓冲区中不是空的
-    pub fn read_byte(&mut self) -> u8 {
+    // 批量读写：循环队列中的数据至多分为两段连续的区域（队尾回绕到数组开头），每段用一次 copy_from_slice 完成拷贝。
+    // 调用者需要保证 out/data 的长度分别不超过 available_read/available_write
+    pub fn read_bytes(&mut self, out: &mut [u8]) {
+        let len = out.len();
+        if len == 0 {
+            return;
+        }
+        let first = len.min(RING_BUFFER_SIZE - self.head);
+        out[..first].copy_from_slice(&self.arr[self.head..self.head + first]);
+        out[first..].copy_from_slice(&self.arr[..len - first]);
         self.status = RingBufferStatus::Normal;
-        let c = self.arr[self.head];
-        // 更新循环队列队头的位置，并比较队头和队尾是否相同，如果相同的话则说明管道的状态变为空 EMPTY
-        self.head = (self.head + 1) % RING_BUFFER_SIZE;
+        self.head = (self.head + len) % RING_BUFFER_SIZE;
         if self.head == self.tail {
             self.status = RingBufferStatus::Empty;
         }
-        c
+    }
+    pub fn write_bytes(&mut self, data: &[u8]) {
+        let len = data.len();
+        if len == 0 {
+            return;
+        }
+        let first = len.min(RING_BUFFER_SIZE - self.tail);
+        self.arr[self.tail..self.tail + first].copy_from_slice(&data[..first]);
+        self.arr[..len - first].copy_from_slice(&data[first..]);
+        self.status = RingBufferStatus::Normal;
+        self.tail = (self.tail + len) % RING_BUFFER_SIZE;
+        if self.tail == self.head {
+            self.status = RingBufferStatus::Full;
+        }
     }
     // available_read 可以计算管道中还有多少个字符可以读取。我们首先需要判断队列是否为空，因为队头和队尾相等可能表示队列为空或队列已满
     pub fn available_read(&self) -> usize {
@@ -98,17 +145,34 @@ impl PipeRingBuffer {
         }
     }
     pub fn available_write(&self) -> usize {
-        if self.status == RingBufferStatus::Full {
+        if self.status == RingBufferStatus::Full || !self.pages.is_empty() {
             0
         } else {
             RING_BUFFER_SIZE - self.available_read()
         }
     }
+    // 循环队列读空之后才轮到队头页面，把其中还没被读走的部分交给 f ，返回 f 实际读走的字节数
+    fn read_page(&mut self, f: impl FnOnce(&[u8]) -> usize) -> Option<usize> {
+        if self.available_read() > 0 {
+            return None;
+        }
+        let frame = self.pages.front()?;
+        let len = f(&frame.ppn.get_bytes_array()[self.page_offset..]);
+        self.page_offset += len;
+        if self.page_offset == PAGE_SIZE {
+            self.pages.pop_front();
+            self.page_offset = 0;
+        }
+        Some(len)
+    }
     // all_write_ends_closed 可以判断管道的所有写端是否都被关闭了，这是通过尝试将管道中保存的写端的弱引用计数升级为强引用计数来实现的。
     // 如果升级失败的话，说明管道写端的强引用计数为 0 ，也就意味着管道所有写端都被关闭了，从而管道中的数据不会再得到补充，待管道中仅剩的数据被读取完毕之后，管道就可以被销毁了
     pub fn all_write_ends_closed(&self) -> bool {
         self.write_end.as_ref().unwrap().upgrade().is_none()
     }
+    pub fn all_read_ends_closed(&self) -> bool {
+        self.read_end.as_ref().unwrap().upgrade().is_none()
+    }
 }
 
 // make_pipe 方法可以创建一个管道并返回它的读端和写端
@@ -117,8 +181,9 @@ pub fn make_pipe() -> (Arc<Pipe>, Arc<Pipe>) {
     let buffer = Arc::new(unsafe { UPSafeCell::new(PipeRingBuffer::new()) });
     let read_end = Arc::new(Pipe::read_end_with_buffer(buffer.clone()));
     let write_end = Arc::new(Pipe::write_end_with_buffer(buffer.clone()));
-    // 调用 PipeRingBuffer::set_write_end 在管道中保留它的写端的弱引用计数
+    // 调用 PipeRingBuffer::set_write_end/set_read_end 在管道中保留它的写端和读端的弱引用计数
     buffer.exclusive_access().set_write_end(&write_end);
+    buffer.exclusive_access().set_read_end(&read_end);
     (read_end, write_end)
 }
 
@@ -130,71 +195,162 @@ impl File for Pipe {
         self.writable
     }
     // read 的语义是要从文件中最多读取应用缓冲区大小那么多字符。这可能超出了循环队列的大小，或者由于尚未有进程从管道的写端写入足够的字符，
-    // 因此我们需要将整个读取的过程放在一个循环中，当循环队列中不存在足够字符的时候暂时进行任务切换，等待循环队列中的字符得到补充之后再继续读取
-    fn read(&self, buf: UserBuffer) -> usize {
+    // 因此我们需要将整个读取的过程放在一个循环中，当循环队列中不存在足够字符的时候暂时进行任务切换，等待循环队列中的字符得到补充之后再继续读取。
+    // 只要还有写端没有关闭，read 就会一直等待直到读满缓冲区，即使写者中途停顿了很久也不会提前返回；
+    // 只有所有写端都已关闭才会返回不足缓冲区大小的字节数，因此返回 0 （缓冲区长度不为 0 时）一定意味着读到了文件末尾
+    fn read(&self, mut buf: UserBuffer) -> usize {
         assert!(self.readable());
         let want_to_read = buf.len();
-        // buf_iter 将传入的应用缓冲区 buf 转化为一个能够逐字节对于缓冲区进行访问的迭代器，每次调用 buf_iter.next() 即可按顺序取出用于访问缓冲区中一个字节的裸指针
-        let mut buf_iter = buf.into_iter();
         // already_read 用来维护实际有多少字节从管道读入应用的缓冲区
         let mut already_read = 0usize;
+        let mut chunk = [0u8; RING_BUFFER_SIZE];
         loop {
+            if already_read == want_to_read {
+                return already_read;
+            }
             let mut ring_buffer = self.buffer.exclusive_access();
             // loop_read 来表示循环这一轮次中可以从管道循环队列中读取多少字符
             let loop_read = ring_buffer.available_read();
+            // 队头的页面直接拷贝到应用缓冲区中
+            if let Some(len) = ring_buffer.read_page(|data| {
+                let len = data.len().min(want_to_read - already_read);
+                buf.write_at(already_read, &data[..len])
+            }) {
+                drop(ring_buffer);
+                self.notify();
+                already_read += len;
+                continue;
+            }
             // 如果管道为空
             if loop_read == 0 {
-                // 则会检查管道的所有写端是否都已经被关闭，如果是的话，说明我们已经没有任何字符可以读取了，这时可以直接返回
+                // 则会检查管道的所有写端是否都已经被关闭，如果是的话，说明我们已经没有任何字符可以读取了，这时可以直接返回。
+                // 检查与 available_read 在同一次加锁中进行，写者在关闭之前写入的数据一定已经在缓冲区中了
                 if ring_buffer.all_write_ends_closed() {
                     return already_read;
                 }
-                // 否则我们需要等管道的字符得到填充之后再继续读取，因此我们调用 suspend_current_and_run_next 切换到其他任务，
+                // 否则我们需要等管道的字符得到填充之后再继续读取，因此我们调用 yield_for_io 切换到其他任务，
                 // 等到切换回来之后回到循环开头再看一下管道中是否有字符了。
                 // 在调用之前我们需要手动释放管道自身的锁，因为切换任务时候的 __switch 跨越了正常函数调用的边界
                 drop(ring_buffer);
-                suspend_current_and_run_next();
-                continue;
-            }
-            // 如果 loop_read 不为 0 ，在这一轮次中管道中就有 loop_read 个字节可以读取
-            for _ in 0..loop_read {
-                // 迭代应用缓冲区中的每个字节指针，并调用 PipeRingBuffer::read_byte 方法来从管道中进行读取
-                if let Some(byte_ref) = buf_iter.next() {
-                    unsafe { *byte_ref = ring_buffer.read_byte() };
-                    already_read += 1;
-                    if already_read == want_to_read {
-                        return want_to_read;
-                    }
-                } else {
+                yield_for_io();
+                // 等待期间被 SIGKILL 终止时放弃读取，返回用户态之前进程就会退出
+                if current_cancelled() {
                     return already_read;
                 }
+                continue;
             }
+            // 如果 loop_read 不为 0 ，在这一轮次中管道中就有 loop_read 个字节可以读取，
+            // 先整体取出到内核栈上的 chunk 中，再一次性拷贝到应用缓冲区的 already_read 处
+            let len = loop_read.min(want_to_read - already_read);
+            ring_buffer.read_bytes(&mut chunk[..len]);
+            drop(ring_buffer);
+            self.notify();
+            already_read += buf.write_at(already_read, &chunk[..len]);
         }
     }
     fn write(&self, buf: UserBuffer) -> usize {
         assert!(self.writable());
         let want_to_write = buf.len();
-        let mut buf_iter = buf.into_iter();
         let mut already_write = 0usize;
+        let mut chunk = [0u8; RING_BUFFER_SIZE];
         loop {
+            if already_write == want_to_write {
+                return already_write;
+            }
             let mut ring_buffer = self.buffer.exclusive_access();
             let loop_write = ring_buffer.available_write();
             if loop_write == 0 {
+                // 所有读端都已关闭时空闲空间不会再出现，不再等待
+                if ring_buffer.all_read_ends_closed() {
+                    return already_write;
+                }
                 drop(ring_buffer);
                 suspend_current_and_run_next();
+                if current_cancelled() {
+                    return already_write;
+                }
                 continue;
             }
             // write at most loop_write bytes
-            for _ in 0..loop_write {
-                if let Some(byte_ref) = buf_iter.next() {
-                    ring_buffer.write_byte(unsafe { *byte_ref });
-                    already_write += 1;
-                    if already_write == want_to_write {
-                        return want_to_write;
-                    }
-                } else {
-                    return already_write;
+            let len = loop_write.min(want_to_write - already_write);
+            buf.read_at(already_write, &mut chunk[..len]);
+            ring_buffer.write_bytes(&chunk[..len]);
+            drop(ring_buffer);
+            self.notify();
+            already_write += len;
+        }
+    }
+    // 与 write 一样，等待期间被 SIGKILL 终止或者所有读端都已关闭时放弃，页帧交还给调用者
+    fn push_page(&self, frame: FrameTracker) -> Result<(), FrameTracker> {
+        assert!(self.writable());
+        loop {
+            let mut ring_buffer = self.buffer.exclusive_access();
+            if ring_buffer.pages.len() < MAX_PIPE_PAGES {
+                ring_buffer.pages.push_back(frame);
+                drop(ring_buffer);
+                self.notify();
+                return Ok(());
+            }
+            if ring_buffer.all_read_ends_closed() {
+                return Err(frame);
+            }
+            drop(ring_buffer);
+            suspend_current_and_run_next();
+            if current_cancelled() {
+                return Err(frame);
+            }
+        }
+    }
+    fn pop_page(&self) -> Option<FrameTracker> {
+        assert!(self.readable());
+        loop {
+            let mut ring_buffer = self.buffer.exclusive_access();
+            if ring_buffer.available_read() > 0 {
+                return None;
+            }
+            if !ring_buffer.pages.is_empty() {
+                // 队头页面已经被读走了一部分的话，只能拷贝剩下的部分
+                if ring_buffer.page_offset > 0 {
+                    return None;
                 }
+                let frame = ring_buffer.pages.pop_front();
+                drop(ring_buffer);
+                self.notify();
+                return frame;
+            }
+            if ring_buffer.all_write_ends_closed() {
+                return None;
+            }
+            drop(ring_buffer);
+            yield_for_io();
+            if current_cancelled() {
+                return None;
             }
         }
     }
+    fn poll(&self) -> u16 {
+        let ring_buffer = self.buffer.exclusive_access();
+        if self.readable {
+            if ring_buffer.available_read() > 0 || !ring_buffer.pages.is_empty() {
+                POLLIN
+            } else if ring_buffer.all_write_ends_closed() {
+                POLLHUP
+            } else {
+                0
+            }
+        } else if ring_buffer.available_write() > 0 {
+            POLLOUT
+        } else {
+            0
+        }
+    }
+    fn watch(&self, task: &Arc<TaskControlBlock>) {
+        self.buffer.exclusive_access().waiters.push(task.clone());
+    }
+    fn unwatch(&self, task: &Arc<TaskControlBlock>) {
+        self.buffer
+            .exclusive_access()
+            .waiters
+            .retain(|waiter| !Arc::ptr_eq(waiter, task));
+    }
 }
@@ -0,0 +1,25 @@
+//! Types for polling the readiness of files
+// 与 Linux 相同的 pollfd 结构体和事件位，poll 等待的就是这些事件中的任意一个发生
+
+/// There is data to read
+pub const POLLIN: u16 = 0x1;
+/// Writing now will not block
+pub const POLLOUT: u16 = 0x4;
+/// Error condition, always reported
+pub const POLLERR: u16 = 0x8;
+/// All the writers have hung up, always reported
+pub const POLLHUP: u16 = 0x10;
+/// The fd is not open, always reported
+pub const POLLNVAL: u16 = 0x20;
+
+/// A file descriptor to poll, passed to `sys_poll`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PollFd {
+    /// the fd to poll, negative ones are ignored
+    pub fd: i32,
+    /// events to wait for
+    pub events: u16,
+    /// events which have happened, filled by the kernel
+    pub revents: u16,
+}
@@ -0,0 +1,77 @@
+//! A file through which a task receives its own signals by reading
+// 被 signalfd 关注的信号不再按照通常的方式处理（调用处理例程或者杀死进程），而是留在进程的 signals 中，
+// 直到进程读这个文件时取走。发送信号时 send_signal 总会唤醒阻塞中的进程，因此等待这些信号的读和 poll 不需要另外登记
+use super::{File, POLLIN};
+use crate::mm::UserBuffer;
+use crate::task::{block_for_io, current_has_pending_signal, current_task, SignalFlags, MAX_SIG};
+
+pub struct SignalFd {
+    mask: SignalFlags,
+}
+
+impl SignalFd {
+    pub fn new(mask: SignalFlags) -> Self {
+        Self { mask }
+    }
+    // 当前进程收到的信号中被这个 signalfd 关注的那些
+    fn pending(&self) -> SignalFlags {
+        current_task().unwrap().inner_exclusive_access().signals & self.mask
+    }
+}
+
+// 读到的每条记录就是一个 u32 的信号编号
+const SIGNALFD_SIGINFO_SIZE: usize = core::mem::size_of::<u32>();
+
+impl File for SignalFd {
+    fn readable(&self) -> bool {
+        true
+    }
+    fn writable(&self) -> bool {
+        false
+    }
+    // 按照信号编号从小到大取走尽可能多的关注的信号，每个信号对应一条记录；
+    // 没有这样的信号时等待，等待期间收到其他信号或者缓冲区连一条记录都放不下时返回 0
+    fn read(&self, mut buf: UserBuffer) -> usize {
+        if buf.len() < SIGNALFD_SIGINFO_SIZE {
+            return 0;
+        }
+        let task = current_task().unwrap();
+        loop {
+            let mut inner = task.inner_exclusive_access();
+            let ready = inner.signals & self.mask;
+            if !ready.is_empty() {
+                let mut written = 0;
+                for sig in 0..(MAX_SIG + 1) {
+                    let signal = SignalFlags::from_bits_truncate(1 << sig);
+                    if !ready.contains(signal) {
+                        continue;
+                    }
+                    if written + SIGNALFD_SIGINFO_SIZE > buf.len() {
+                        break;
+                    }
+                    inner.signals.remove(signal);
+                    written += buf.write_at(written, &(sig as u32).to_ne_bytes());
+                }
+                return written;
+            }
+            drop(inner);
+            if current_has_pending_signal() {
+                return 0;
+            }
+            block_for_io();
+        }
+    }
+    fn write(&self, _buf: UserBuffer) -> usize {
+        0
+    }
+    fn poll(&self) -> u16 {
+        if self.pending().is_empty() {
+            0
+        } else {
+            POLLIN
+        }
+    }
+    fn signalfd_mask(&self) -> SignalFlags {
+        self.mask
+    }
+}
@@ -1,13 +1,207 @@
 //!Stdin & Stdout
-use super::File;
-use crate::mm::UserBuffer;
+use super::{File, POLLIN};
+use crate::mm::{copy_in_value, copy_out_value, UserBuffer};
 use crate::sbi::console_getchar;
-use crate::task::suspend_current_and_run_next;
+use crate::sync::UPSafeCell;
+use crate::task::{
+    block_for_io, current_has_pending_signal, current_task, current_unshare_zero_range,
+    current_user_token, pgid2tasks, send_signal_to_group, wakeup_task, yield_for_io, SignalFlags,
+    TaskControlBlock,
+};
+use crate::timer::{add_timer, get_time_ms, remove_timer};
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use lazy_static::*;
 ///Standard input
 pub struct Stdin;
 ///Standard output
 pub struct Stdout;
 
+/// Get the foreground process group of the terminal
+pub const TIOCGPGRP: usize = 0x540F;
+/// Set the foreground process group of the terminal
+pub const TIOCSPGRP: usize = 0x5410;
+/// Insert a byte into the terminal input queue as if it was typed
+pub const TIOCSTI: usize = 0x5412;
+/// Get the terminal attributes
+pub const TCGETS: usize = 0x5401;
+/// Set the terminal attributes
+pub const TCSETS: usize = 0x5402;
+
+/// Generate signals for INTR/QUIT/SUSP characters
+pub const ISIG: u32 = 0o1;
+
+const CTRL_C: u8 = 0x03;
+const CTRL_BACKSLASH: u8 = 0x1c;
+const CTRL_Z: u8 = 0x1a;
+
+// 终端属性，目前只支持 lflag 中的 ISIG 位：置位时终端处于 cooked 模式，控制字符会被转换为信号；
+// 清零时处于 raw 模式，所有字节都原样交给读者
+///Attributes of a terminal
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct Termios {
+    /// local modes
+    pub lflag: u32,
+}
+
+// 控制台是系统中唯一的终端。它最多作为一个会话的控制终端，并记录该会话中的前台进程组，
+// 键盘产生的信号只会发送给前台进程组
+///Controlling terminal state of the console
+pub struct ConsoleTty {
+    /// session which the console is the controlling terminal of
+    session: Option<usize>,
+    /// foreground process group of that session
+    foreground: Option<usize>,
+    /// bytes received but not read yet
+    input: VecDeque<u8>,
+    /// terminal attributes
+    termios: Termios,
+    /// tasks blocked in a timed read or poll, woken up when input arrives
+    readers: Vec<Arc<TaskControlBlock>>,
+}
+
+impl ConsoleTty {
+    ///Create a console which belongs to no session
+    pub fn new() -> Self {
+        Self {
+            session: None,
+            foreground: None,
+            input: VecDeque::new(),
+            termios: Termios { lflag: ISIG },
+            readers: Vec::new(),
+        }
+    }
+    // 对收到的字节进行处理：控制字符转换为发给前台进程组的信号，其他字节进入输入队列等待读取
+    fn receive(&mut self, c: u8) -> Option<(usize, SignalFlags)> {
+        if self.termios.lflag & ISIG == 0 {
+            self.input.push_back(c);
+            return None;
+        }
+        let signal = match c {
+            CTRL_C => SignalFlags::SIGINT,
+            CTRL_BACKSLASH => SignalFlags::SIGQUIT,
+            CTRL_Z => SignalFlags::SIGTSTP,
+            _ => {
+                self.input.push_back(c);
+                return None;
+            }
+        };
+        self.foreground.map(|pgid| (pgid, signal))
+    }
+}
+
+lazy_static! {
+    ///The console terminal
+    pub static ref CONSOLE_TTY: UPSafeCell<ConsoleTty> =
+        unsafe { UPSafeCell::new(ConsoleTty::new()) };
+}
+
+/// Feed a byte received from the console into the terminal
+pub fn console_receive(c: u8) {
+    let mut tty = CONSOLE_TTY.exclusive_access();
+    let target = tty.receive(c);
+    // 有新的输入字节时唤醒所有阻塞在限时读上的进程
+    let readers = if tty.input.is_empty() {
+        Vec::new()
+    } else {
+        core::mem::take(&mut tty.readers)
+    };
+    drop(tty);
+    for task in readers {
+        wakeup_task(task);
+    }
+    if let Some((pgid, signal)) = target {
+        send_signal_to_group(pgid, signal);
+    }
+}
+
+// 控制台没有输入时 SBI 返回 0（或者 -1），其他超出字节范围的值同样视为没有输入
+fn console_getchar_nb() -> Option<u8> {
+    match console_getchar() {
+        0 => None,
+        c if c > u8::MAX as usize => None,
+        c => Some(c as u8),
+    }
+}
+
+// 只有在读 Stdin 时才检查控制台输入的话，没有进程读终端时 Ctrl-C 就无法打断正在运行的前台进程，
+// 因此在每次时钟中断时都会轮询一次控制台
+/// Poll the console for a typed byte
+pub fn console_poll() {
+    if let Some(c) = console_getchar_nb() {
+        console_receive(c);
+    }
+}
+
+/// Make the console the controlling terminal of session `sid` if it is not owned by any session
+pub fn acquire_console_session(sid: usize) {
+    let mut tty = CONSOLE_TTY.exclusive_access();
+    if tty.session.is_none() {
+        tty.session = Some(sid);
+        tty.foreground = Some(sid);
+    }
+}
+
+/// Detach the console from session `sid` when its leader exits
+pub fn release_console_session(sid: usize) {
+    let mut tty = CONSOLE_TTY.exclusive_access();
+    if tty.session == Some(sid) {
+        tty.session = None;
+        tty.foreground = None;
+    }
+}
+
+// 终端相关的 ioctl 只允许控制终端所在会话中的进程使用
+fn console_ioctl(cmd: usize, arg: usize) -> isize {
+    let token = current_user_token();
+    let sid = current_task().unwrap().inner_exclusive_access().sid;
+    let session = CONSOLE_TTY.exclusive_access().session;
+    if session != Some(sid) {
+        return -1;
+    }
+    match cmd {
+        TIOCGPGRP => {
+            let foreground = CONSOLE_TTY.exclusive_access().foreground;
+            current_unshare_zero_range(arg, core::mem::size_of::<usize>());
+            copy_out_value(token, arg as *mut usize, &foreground.unwrap());
+            0
+        }
+        TIOCSPGRP => {
+            let pgid = copy_in_value(token, arg as *const usize);
+            // 新的前台进程组必须存在且属于同一个会话
+            let tasks = pgid2tasks(pgid);
+            if tasks.is_empty()
+                || tasks
+                    .iter()
+                    .any(|task| task.inner_exclusive_access().sid != sid)
+            {
+                return -1;
+            }
+            CONSOLE_TTY.exclusive_access().foreground = Some(pgid);
+            0
+        }
+        TCGETS => {
+            let termios = CONSOLE_TTY.exclusive_access().termios;
+            current_unshare_zero_range(arg, core::mem::size_of::<Termios>());
+            copy_out_value(token, arg as *mut Termios, &termios);
+            0
+        }
+        TCSETS => {
+            let termios = copy_in_value(token, arg as *const Termios);
+            CONSOLE_TTY.exclusive_access().termios = termios;
+            0
+        }
+        TIOCSTI => {
+            let c = copy_in_value(token, arg as *const u8);
+            console_receive(c);
+            0
+        }
+        _ => -1,
+    }
+}
+
 impl File for Stdin {
     fn readable(&self) -> bool {
         true
@@ -19,25 +213,84 @@ impl File for Stdin {
     fn read(&self, mut user_buf: UserBuffer) -> usize {
         assert_eq!(user_buf.len(), 1);
         // busy loop
-        let mut c: usize;
+        let ch: u8;
         loop {
-            c = console_getchar();
-            if c == 0 {
-                suspend_current_and_run_next();
-                continue;
-            } else {
+            if let Some(c) = CONSOLE_TTY.exclusive_access().input.pop_front() {
+                ch = c;
                 break;
             }
+            if let Some(c) = console_getchar_nb() {
+                console_receive(c);
+            } else {
+                yield_for_io();
+            }
+            // 等待输入期间收到了信号（比如被 Ctrl-C 打断），则提前返回以便尽快处理信号
+            if current_has_pending_signal() {
+                return 0;
+            }
         }
-        let ch = c as u8;
-        unsafe {
-            user_buf.buffers[0].as_mut_ptr().write_volatile(ch);
-        }
-        1
+        user_buf.write_at(0, &[ch])
     }
     fn write(&self, _user_buf: UserBuffer) -> usize {
         panic!("Cannot write to stdin!");
     }
+    // 限时读不会忙等：没有输入时进程阻塞在定时器队列上，直到超时、有新的输入（由时钟中断中的 console_poll 发现）或者收到信号时才被唤醒。
+    // 与 read 不同，它会一次性读走输入队列中所有可用的字节（最多填满 user_buf）
+    fn read_timeout(&self, mut user_buf: UserBuffer, timeout_ms: usize) -> Option<usize> {
+        let deadline = get_time_ms() + timeout_ms;
+        loop {
+            let mut tty = CONSOLE_TTY.exclusive_access();
+            if !tty.input.is_empty() {
+                let count = tty.input.len().min(user_buf.len());
+                let data: Vec<u8> = tty.input.drain(..count).collect();
+                return Some(user_buf.write_at(0, &data));
+            }
+            drop(tty);
+            if let Some(c) = console_getchar_nb() {
+                console_receive(c);
+                continue;
+            }
+            if current_has_pending_signal() {
+                return None;
+            }
+            if get_time_ms() >= deadline {
+                return Some(0);
+            }
+            let task = current_task().unwrap();
+            add_timer(deadline, task.clone());
+            CONSOLE_TTY.exclusive_access().readers.push(task.clone());
+            block_for_io();
+            // 被唤醒的原因可能是超时、有输入或者收到信号，撤销剩下的等待登记后重新检查
+            remove_timer(&task);
+            CONSOLE_TTY
+                .exclusive_access()
+                .readers
+                .retain(|reader| !Arc::ptr_eq(reader, &task));
+        }
+    }
+    // 先把控制台中已经到达的字节收进输入队列，再检查队列是否为空
+    fn poll(&self) -> u16 {
+        if let Some(c) = console_getchar_nb() {
+            console_receive(c);
+        }
+        if CONSOLE_TTY.exclusive_access().input.is_empty() {
+            0
+        } else {
+            POLLIN
+        }
+    }
+    fn watch(&self, task: &Arc<TaskControlBlock>) {
+        CONSOLE_TTY.exclusive_access().readers.push(task.clone());
+    }
+    fn unwatch(&self, task: &Arc<TaskControlBlock>) {
+        CONSOLE_TTY
+            .exclusive_access()
+            .readers
+            .retain(|reader| !Arc::ptr_eq(reader, task));
+    }
+    fn ioctl(&self, cmd: usize, arg: usize) -> isize {
+        console_ioctl(cmd, arg)
+    }
 }
 
 impl File for Stdout {
@@ -50,11 +303,14 @@ impl File for Stdout {
     fn read(&self, _user_buf: UserBuffer) -> usize {
         panic!("Cannot read from stdout!");
     }
-    // 标准输出文件 Stdout 是只写文件，只允许进程通过 write 写入到里面，实现方法是遍历每个切片，将其转化为字符串通过 print! 宏来输出
+    // 标准输出文件 Stdout 是只写文件，只允许进程通过 write 写入到里面，实现方法是将所有切片拼接起来，转化为字符串通过 print! 宏来输出。
+    // 先拼接再转换是因为一个多字节的 UTF-8 字符可能恰好跨越两个页面，被拆分到两个切片中
     fn write(&self, user_buf: UserBuffer) -> usize {
-        for buffer in user_buf.buffers.iter() {
-            print!("{}", core::str::from_utf8(*buffer).unwrap());
-        }
-        user_buf.len()
+        let data = user_buf.copy_to_vec();
+        print!("{}", core::str::from_utf8(&data).unwrap());
+        data.len()
+    }
+    fn ioctl(&self, cmd: usize, arg: usize) -> isize {
+        console_ioctl(cmd, arg)
     }
 }
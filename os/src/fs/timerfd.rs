@@ -0,0 +1,114 @@
+//! A file which becomes readable when its timer expires
+// 定时器文件不需要在时钟中断中更新：到期次数在读或者 poll 时根据当前时间算出。
+// 等待它的进程在下一次到期的时刻挂到内核的定时器队列上，由 check_timer 叫醒
+use super::{File, POLLIN};
+use crate::mm::UserBuffer;
+use crate::sync::UPSafeCell;
+use crate::task::{block_for_io, current_has_pending_signal, current_task, TaskControlBlock};
+use crate::timer::{add_timer, get_time_ms, remove_timer};
+use alloc::sync::Arc;
+
+pub struct TimerFd {
+    inner: UPSafeCell<TimerFdInner>,
+}
+
+struct TimerFdInner {
+    // 下一次到期的时刻（毫秒），None 表示定时器没有启动
+    next_expire_ms: Option<usize>,
+    // 周期，0 表示只到期一次
+    interval_ms: usize,
+    // 上一次读之后已经到期的次数
+    expirations: u64,
+}
+
+impl TimerFdInner {
+    // 把到 now 为止新到期的次数累加到 expirations 中
+    fn update(&mut self, now: usize) {
+        let Some(next) = self.next_expire_ms.filter(|&next| next <= now) else {
+            return;
+        };
+        if self.interval_ms == 0 {
+            self.expirations += 1;
+            self.next_expire_ms = None;
+        } else {
+            let count = (now - next) / self.interval_ms + 1;
+            self.expirations += count as u64;
+            self.next_expire_ms = Some(next + count * self.interval_ms);
+        }
+    }
+}
+
+impl TimerFd {
+    pub fn new() -> Self {
+        Self {
+            inner: unsafe {
+                UPSafeCell::new(TimerFdInner {
+                    next_expire_ms: None,
+                    interval_ms: 0,
+                    expirations: 0,
+                })
+            },
+        }
+    }
+}
+
+impl File for TimerFd {
+    fn readable(&self) -> bool {
+        true
+    }
+    fn writable(&self) -> bool {
+        false
+    }
+    // 读出 8 字节的到期次数并清零，还没有到期时等待下一次到期；缓冲区不足 8 字节或者等待时收到信号则返回 0
+    fn read(&self, mut buf: UserBuffer) -> usize {
+        if buf.len() < core::mem::size_of::<u64>() {
+            return 0;
+        }
+        let task = current_task().unwrap();
+        loop {
+            let mut inner = self.inner.exclusive_access();
+            inner.update(get_time_ms());
+            if inner.expirations > 0 {
+                let expirations = core::mem::take(&mut inner.expirations);
+                return buf.write_at(0, &expirations.to_ne_bytes());
+            }
+            let next_expire_ms = inner.next_expire_ms;
+            drop(inner);
+            if current_has_pending_signal() {
+                return 0;
+            }
+            // 没有启动的定时器不会到期，只有信号能叫醒等待的进程
+            if let Some(next_expire_ms) = next_expire_ms {
+                add_timer(next_expire_ms, task.clone());
+            }
+            block_for_io();
+            remove_timer(&task);
+        }
+    }
+    fn write(&self, _buf: UserBuffer) -> usize {
+        0
+    }
+    fn settime(&self, interval_ms: usize, value_ms: usize) -> bool {
+        let mut inner = self.inner.exclusive_access();
+        inner.interval_ms = interval_ms;
+        inner.next_expire_ms = (value_ms > 0).then(|| get_time_ms() + value_ms);
+        inner.expirations = 0;
+        true
+    }
+    fn poll(&self) -> u16 {
+        let mut inner = self.inner.exclusive_access();
+        inner.update(get_time_ms());
+        if inner.expirations > 0 {
+            POLLIN
+        } else {
+            0
+        }
+    }
+    fn watch(&self, task: &Arc<TaskControlBlock>) {
+        if let Some(next_expire_ms) = self.inner.exclusive_access().next_expire_ms {
+            add_timer(next_expire_ms, task.clone());
+        }
+    }
+    // 调用者在 unwatch 之前已经撤销了这个进程的所有定时器
+    fn unwatch(&self, _task: &Arc<TaskControlBlock>) {}
+}
@@ -0,0 +1,74 @@
+//! Hardware capabilities
+// 用户程序可以通过 sys_hwcap 查询有哪些 ISA 扩展可用，从而在运行时选择不同的实现
+// misa 是 M 特权级的 CSR ，S 特权级的内核无法读取，因此只能在启动时逐项探测
+use crate::sync::UPSafeCell;
+use alloc::string::String;
+use lazy_static::*;
+use riscv::register::sstatus::{self, FS};
+
+// 与 Linux 的 AT_HWCAP 相同，扩展字母 x 对应第 x - 'a' 位
+const fn isa_bit(ext: u8) -> usize {
+    1 << (ext - b'a')
+}
+pub const HWCAP_I: usize = isa_bit(b'i');
+pub const HWCAP_M: usize = isa_bit(b'm');
+pub const HWCAP_A: usize = isa_bit(b'a');
+pub const HWCAP_F: usize = isa_bit(b'f');
+pub const HWCAP_D: usize = isa_bit(b'd');
+pub const HWCAP_C: usize = isa_bit(b'c');
+
+// 内核切换任务时不保存浮点寄存器，用户态的 sstatus.FS 也一直是 Off ，
+// 所以即使硬件有 FPU ，用户程序也不能使用浮点指令
+const USER_UNUSABLE: usize = HWCAP_F | HWCAP_D;
+
+lazy_static! {
+    static ref HWCAP: UPSafeCell<usize> = unsafe { UPSafeCell::new(0) };
+}
+
+// 内核本身就是按照这些扩展编译的，能够运行到这里说明它们都存在
+fn compiled_extensions() -> usize {
+    let mut hwcap = HWCAP_I;
+    if cfg!(target_feature = "m") {
+        hwcap |= HWCAP_M;
+    }
+    if cfg!(target_feature = "a") {
+        hwcap |= HWCAP_A;
+    }
+    if cfg!(target_feature = "c") {
+        hwcap |= HWCAP_C;
+    }
+    hwcap
+}
+
+// 没有 FPU 时 sstatus.FS 字段恒为 0 ，写入之后再读回来就能知道 FPU 是否存在
+fn probe_fpu() -> bool {
+    let old = sstatus::read().fs();
+    unsafe {
+        sstatus::set_fs(FS::Initial);
+    }
+    let present = sstatus::read().fs() != FS::Off;
+    unsafe {
+        sstatus::set_fs(old);
+    }
+    present
+}
+
+/// Probe the available ISA extensions and cache them
+pub fn init() {
+    let mut hwcap = compiled_extensions();
+    // 只能知道 FPU 存在，无法区分单精度和双精度，按照 G 的约定认为 F 和 D 同时存在
+    if probe_fpu() {
+        hwcap |= HWCAP_F | HWCAP_D;
+    }
+    *HWCAP.exclusive_access() = hwcap;
+    let isa: String = (b'a'..=b'z')
+        .filter(|ext| hwcap & isa_bit(*ext) != 0)
+        .map(char::from)
+        .collect();
+    println!("[kernel] hwcap: rv64{}", isa);
+}
+
+/// ISA extensions which user programs can use
+pub fn user_hwcap() -> usize {
+    *HWCAP.exclusive_access() & !USER_UNUSABLE
+}
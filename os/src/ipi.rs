@@ -0,0 +1,110 @@
+//! Inter-processor interrupts
+// 多个 hart 同时运行时，一个 hart 上的修改有时需要通知其他 hart ：取消映射之后，其他 hart 的快表中可能还缓存着旧的表项；
+// 新加入就绪队列的任务也需要叫醒停在 wfi 上的空闲 hart 。发送方先把要做的工作放进目标 hart 的队列，再通过 SBI 向它发送 IPI ，
+// 目标 hart 在 S 特权级软件中断（或者从 wfi 醒来之后）处理自己的队列
+use crate::config::HART_NUM;
+use crate::mm::{flush_local_range, VirtPageNum};
+use crate::sbi::send_ipi;
+use crate::sync::UPSafeCell;
+use crate::task::current_hart_id;
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::arch::asm;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use lazy_static::*;
+use riscv::register::{sie, sip};
+
+/// Work a hart is asked to do by an IPI
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpiWork {
+    /// Flush `[start, end)` from the TLB of the hart
+    FlushTlb(VirtPageNum, VirtPageNum),
+    /// Leave `wfi` and look at the ready queue again
+    Wakeup,
+}
+
+lazy_static! {
+    static ref IPI_QUEUES: Vec<UPSafeCell<VecDeque<IpiWork>>> = (0..HART_NUM)
+        .map(|_| unsafe { UPSafeCell::new(VecDeque::new()) })
+        .collect();
+}
+
+// 第 i 位为 1 表示 hart i 没有可以运行的任务，正停在 wfi 上
+static PARKED_HARTS: AtomicUsize = AtomicUsize::new(0);
+
+/// Enable supervisor software interrupts, which is how IPIs arrive
+pub fn init() {
+    unsafe {
+        sie::set_ssoft();
+    }
+}
+
+fn send(hart: usize, work: IpiWork) {
+    IPI_QUEUES[hart].exclusive_access().push_back(work);
+    send_ipi(1 << hart);
+}
+
+/// Ask every other hart to flush `[start, end)` from its TLB
+pub fn tlb_shootdown(start: VirtPageNum, end: VirtPageNum) {
+    let this_hart = current_hart_id();
+    for hart in (0..HART_NUM).filter(|&hart| hart != this_hart) {
+        send(hart, IpiWork::FlushTlb(start, end));
+    }
+}
+
+// 正在执行这段代码的 hart 自己不可能停在 wfi 上，所以被标记的一定是其他 hart ，叫醒其中一个就足够取走这个新任务
+/// Wake up one hart parked in `wfi`, if any, to pick up a newly ready task
+pub fn wake_parked_hart() {
+    let parked = PARKED_HARTS.load(Ordering::Acquire);
+    if parked != 0 {
+        send(parked.trailing_zeros() as usize, IpiWork::Wakeup);
+    }
+}
+
+/// Wait in `wfi` until an interrupt arrives, then do the IPI work queued for this hart
+pub fn park() {
+    let bit = 1 << current_hart_id();
+    PARKED_HARTS.fetch_or(bit, Ordering::AcqRel);
+    // 内核态屏蔽了中断，wfi 在有中断待处理时返回但不会进入 trap ，因此醒来之后直接处理队列
+    unsafe {
+        asm!("wfi");
+    }
+    PARKED_HARTS.fetch_and(!bit, Ordering::AcqRel);
+    handle_ipi();
+}
+
+/// Clear the pending software interrupt and do the IPI work queued for this hart
+pub fn handle_ipi() {
+    // sip.SSIP 只能由软件清除，否则回到用户态之后会立刻再次陷入
+    unsafe {
+        asm!("csrc sip, {}", in(reg) 1 << 1);
+    }
+    let mut queue = IPI_QUEUES[current_hart_id()].exclusive_access();
+    while let Some(work) = queue.pop_front() {
+        match work {
+            IpiWork::FlushTlb(start, end) => flush_local_range(start, end),
+            IpiWork::Wakeup => {}
+        }
+    }
+}
+
+#[allow(unused)]
+/// Send an IPI to this hart itself and check that it arrives and its work is done
+pub fn ipi_test() {
+    let hart = current_hart_id();
+    let start = VirtPageNum(0x10);
+    send(hart, IpiWork::FlushTlb(start, VirtPageNum(start.0 + 1)));
+    // SBI 实现在 M 特权级处理完发送请求之后才把 sip.SSIP 置位，稍等片刻
+    let mut arrived = false;
+    for _ in 0..1_000_000 {
+        if sip::read().ssoft() {
+            arrived = true;
+            break;
+        }
+    }
+    assert!(arrived, "IPI to hart {} never arrived", hart);
+    handle_ipi();
+    assert!(!sip::read().ssoft());
+    assert!(IPI_QUEUES[hart].exclusive_access().is_empty());
+    println!("ipi_test passed!");
+}
@@ -1,8 +1,13 @@
 // os/src/lang_item.rs
+use crate::config::{KERNEL_STACK_SIZE, PAGE_SIZE, TRAMPOLINE};
 use crate::sbi::shutdown;
+use core::arch::asm;
 use core::panic::PanicInfo;
 use log::*;
 
+/// max depth of the backtrace, in case the stack is corrupted
+const MAX_BACKTRACE_DEPTH: usize = 32;
+
 #[panic_handler]
 /// panic handler
 fn panic(info: &PanicInfo) -> ! {
@@ -16,5 +21,65 @@ fn panic(info: &PanicInfo) -> ! {
     } else {
         error!("[kernel] Panicked: {}", info.message().unwrap());
     }
+    backtrace();
     shutdown(true)
 }
+
+// 返回 fp 所在的内核栈的范围 [bottom, top]：要么是启动时使用的 boot stack ，要么是内核地址空间中某个进程的内核栈
+fn kernel_stack_bounds(fp: usize) -> Option<(usize, usize)> {
+    extern "C" {
+        fn boot_stack_lower_bound();
+        fn boot_stack_top();
+    }
+    let (boot_bottom, boot_top) = (boot_stack_lower_bound as usize, boot_stack_top as usize);
+    if fp > boot_bottom && fp <= boot_top {
+        return Some((boot_bottom, boot_top));
+    }
+    if fp >= TRAMPOLINE {
+        return None;
+    }
+    // 与 kernel_stack_position 相同的布局：每个内核栈下方都有一个保护页
+    let app_id = (TRAMPOLINE - fp) / (KERNEL_STACK_SIZE + PAGE_SIZE);
+    let top = TRAMPOLINE - app_id * (KERNEL_STACK_SIZE + PAGE_SIZE);
+    let bottom = top - KERNEL_STACK_SIZE;
+    if fp > bottom && fp <= top {
+        Some((bottom, top))
+    } else {
+        None
+    }
+}
+
+// 内核在编译时开启了帧指针（-Cforce-frame-pointers=yes），每个栈帧中 fp-8 处保存返回地址 ra ，fp-16 处保存调用者的 fp 。
+// 运行时没有符号表，因此只打印返回地址，可以离线借助 ELF 文件（如 addr2line）将它们解析为函数名
+/// Walk the saved frame pointers on the kernel stack and print return addresses
+fn backtrace() {
+    let mut fp: usize;
+    unsafe {
+        asm!("mv {}, fp", out(reg) fp);
+    }
+    println!("[kernel] backtrace:");
+    let (bottom, top) = match kernel_stack_bounds(fp) {
+        Some(bounds) => bounds,
+        None => {
+            println!("[kernel]   <fp {:#x} is not on a kernel stack>", fp);
+            return;
+        }
+    };
+    for depth in 0..MAX_BACKTRACE_DEPTH {
+        // 保存的 ra 和 fp 必须都位于同一个内核栈内
+        if fp % core::mem::size_of::<usize>() != 0 || fp < bottom + 16 || fp > top {
+            break;
+        }
+        let ra = unsafe { *((fp - 8) as *const usize) };
+        let prev_fp = unsafe { *((fp - 16) as *const usize) };
+        if ra == 0 {
+            break;
+        }
+        println!("[kernel]   #{:<2} ra = {:#x}", depth, ra);
+        // 栈向低地址增长，调用者的栈帧一定在更高的地址上，否则说明栈已经被破坏
+        if prev_fp <= fp {
+            break;
+        }
+        fp = prev_fp;
+    }
+}
@@ -19,9 +19,12 @@ mod console;
 mod config;
 mod drivers;
 pub mod fs;
+pub mod hwcap;
+pub mod ipi;
 pub mod lang_items;
 // pub mod loader;
 pub mod mm;
+pub mod profile;
 pub mod sbi;
 // 第二章专属模块，后面弃用
 // pub mod batch;
@@ -46,12 +49,23 @@ pub fn rust_main() -> ! {
     clear_bss();
     println!("[kernel] Hello, world!");
     mm::init();
+    hwcap::init();
+    #[cfg(debug_assertions)]
+    sync::lock_watchdog_test();
     mm::remap_test();
+    mm::frame_coloring_test();
+    mm::user_buffer_test();
+    fs::canonicalize_test();
     trap::init();
     //trap::enable_interrupt();
     trap::enable_timer_interrupt();
+    ipi::init();
+    ipi::ipi_test();
+    task::preempt_test();
     timer::set_next_trigger();
     fs::list_apps();
+    fs::fadvise_test();
+    mm::user_space_test();
     task::add_initproc();
     task::run_tasks();
     panic!("Unreachable in rust_main!");
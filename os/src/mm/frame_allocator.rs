@@ -2,10 +2,11 @@
 //! controls all the frames in the operating system.
 
 use super::{PhysAddr, PhysPageNum};
-use crate::config::MEMORY_END;
+use crate::config::{LOWMEM_FRAMES, MEMORY_END, PAGE_COLORING, PAGE_COLORS};
 use crate::sync::UPSafeCell;
 use alloc::vec::Vec;
 use core::fmt::{self, Debug, Formatter};
+use core::sync::atomic::{AtomicBool, Ordering};
 use lazy_static::*;
 
 // 借用了 RAII 的思想，将一个物理页帧的生命周期绑定到一个 FrameTracker 变量上，当一个 FrameTracker 被创建的时候，我们需要从 FRAME_ALLOCATOR 中分配一个物理页帧：
@@ -41,7 +42,9 @@ impl Drop for FrameTracker {
 trait FrameAllocator {
     fn new() -> Self;
     fn alloc(&mut self) -> Option<PhysPageNum>;
+    fn alloc_colored(&mut self, color: usize) -> Option<PhysPageNum>;
     fn dealloc(&mut self, ppn: PhysPageNum);
+    fn free_frames(&self) -> usize;
 }
 
 // 最简单的栈式物理页帧管理策略 StackFrameAllocator
@@ -76,6 +79,31 @@ impl FrameAllocator for StackFrameAllocator {
             Some((self.current - 1).into())
         }
     }
+    // 页帧的颜色为物理页号对 PAGE_COLORS 取模
+    fn alloc_colored(&mut self, color: usize) -> Option<PhysPageNum> {
+        // 先在回收栈的顶部查找该颜色的页帧，只查看有限的几个以免每次分配都遍历整个栈
+        if let Some(index) = self
+            .recycled
+            .iter()
+            .rev()
+            .take(PAGE_COLORS * 2)
+            .position(|&ppn| ppn % PAGE_COLORS == color)
+        {
+            let index = self.recycled.len() - 1 - index;
+            return Some(self.recycled.swap_remove(index).into());
+        }
+        // 再从未分配过的页帧中查找，跳过的页帧放入回收栈留给之后的分配
+        while self.current < self.end {
+            let ppn = self.current;
+            self.current += 1;
+            if ppn % PAGE_COLORS == color {
+                return Some(ppn.into());
+            }
+            self.recycled.push(ppn);
+        }
+        // 找不到该颜色的页帧时退化为普通的分配
+        self.alloc()
+    }
     fn dealloc(&mut self, ppn: PhysPageNum) {
         let ppn = ppn.0;
         // 回收页面合法有两个条件：
@@ -88,6 +116,9 @@ impl FrameAllocator for StackFrameAllocator {
         // recycle
         self.recycled.push(ppn);
     }
+    fn free_frames(&self) -> usize {
+        self.end - self.current + self.recycled.len()
+    }
 }
 
 type FrameAllocatorImpl = StackFrameAllocator;
@@ -109,17 +140,68 @@ pub fn init_frame_allocator() {
     );
 }
 
+// 空闲页帧数是否低于 LOWMEM_FRAMES ，以及是否发生了尚未通知用户进程的内存压力事件。
+// 分配页帧时调用者往往正持有着当前进程的 inner ，因此这里只做记录，由 take_lowmem_event 的调用者在之后发送信号
+static BELOW_LOWMEM: AtomicBool = AtomicBool::new(false);
+static LOWMEM_EVENT: AtomicBool = AtomicBool::new(false);
+
+// 空闲页帧数从水位线以上降到水位线以下时记录一次事件，回到水位线以上之后才会再次记录
+fn update_lowmem(free_frames: usize) {
+    let low = free_frames < LOWMEM_FRAMES;
+    if BELOW_LOWMEM.swap(low, Ordering::Relaxed) != low && low {
+        LOWMEM_EVENT.store(true, Ordering::Relaxed);
+    }
+}
+
 /// allocate a frame
 pub fn frame_alloc() -> Option<FrameTracker> {
-    FRAME_ALLOCATOR
-        .exclusive_access()
-        .alloc()
-        .map(FrameTracker::new)
+    let mut allocator = FRAME_ALLOCATOR.exclusive_access();
+    let ppn = allocator.alloc();
+    update_lowmem(allocator.free_frames());
+    drop(allocator);
+    ppn.map(FrameTracker::new)
+}
+
+// 分配一个颜色为 color 的页帧，不受 PAGE_COLORING 的影响
+fn alloc_colored_frame(color: usize) -> Option<FrameTracker> {
+    let mut allocator = FRAME_ALLOCATOR.exclusive_access();
+    let ppn = allocator.alloc_colored(color);
+    update_lowmem(allocator.free_frames());
+    drop(allocator);
+    ppn.map(FrameTracker::new)
+}
+
+/// Allocate a frame whose color is given by the low bits of `color_hint`
+/// if page coloring is enabled, or any frame otherwise
+pub fn frame_alloc_colored(color_hint: usize) -> Option<FrameTracker> {
+    if PAGE_COLORING {
+        alloc_colored_frame(color_hint % PAGE_COLORS)
+    } else {
+        frame_alloc()
+    }
+}
+
+/// Whether free frames have dropped below `LOWMEM_FRAMES` since the last call
+pub fn take_lowmem_event() -> bool {
+    LOWMEM_EVENT.swap(false, Ordering::Relaxed)
+}
+
+// 全局唯一的只读零页：所有尚未被写入过的按需清零页面都映射到它，首次写入时才为其分配私有的物理页帧
+lazy_static! {
+    /// a frame filled with zeros, shared read-only by all demand-zero pages
+    static ref ZERO_FRAME: FrameTracker = frame_alloc().unwrap();
+}
+
+/// physical page number of the shared zero frame
+pub fn zero_frame() -> PhysPageNum {
+    ZERO_FRAME.ppn
 }
 
 /// deallocate a frame
 pub fn frame_dealloc(ppn: PhysPageNum) {
-    FRAME_ALLOCATOR.exclusive_access().dealloc(ppn);
+    let mut allocator = FRAME_ALLOCATOR.exclusive_access();
+    allocator.dealloc(ppn);
+    update_lowmem(allocator.free_frames());
 }
 
 #[allow(unused)]
@@ -140,3 +222,21 @@ pub fn frame_allocator_test() {
     drop(v);
     println!("frame_allocator_test passed!");
 }
+
+#[allow(unused)]
+/// Check that colored allocations for a range of pages are spread over all colors
+pub fn frame_coloring_test() {
+    let rounds = 4;
+    let frames: Vec<FrameTracker> = (0..PAGE_COLORS * rounds)
+        .map(|vpn| alloc_colored_frame(vpn % PAGE_COLORS).unwrap())
+        .collect();
+    let mut counts = [0usize; PAGE_COLORS];
+    for (vpn, frame) in frames.iter().enumerate() {
+        let color = frame.ppn.0 % PAGE_COLORS;
+        assert_eq!(color, vpn % PAGE_COLORS);
+        counts[color] += 1;
+    }
+    assert!(counts.iter().all(|&count| count == rounds));
+    drop(frames);
+    println!("frame_coloring_test passed!");
+}
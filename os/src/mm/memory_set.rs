@@ -1,12 +1,19 @@
 //! Implementation of [`MapArea`] and [`MemorySet`].
 
-use super::{frame_alloc, FrameTracker};
+use super::{frame_alloc, frame_alloc_colored, zero_frame, FrameTracker};
 use super::{PTEFlags, PageTable, PageTableEntry};
 use super::{PhysAddr, PhysPageNum, VirtAddr, VirtPageNum};
 use super::{StepByOne, VPNRange};
-use crate::config::{MEMORY_END, MMIO, PAGE_SIZE, TRAMPOLINE, TRAP_CONTEXT, USER_STACK_SIZE};
+use crate::config::{
+    MEMORY_END, MMIO, PAGE_SIZE, TLB_FLUSH_BATCH, TRAMPOLINE, TRAP_CONTEXT, USER_HEAP_SIZE,
+    USER_SPACE_END, USER_STACK_SIZE, WX_STRICT,
+};
+use crate::ipi::tlb_shootdown;
 use crate::sync::UPSafeCell;
+use alloc::borrow::Cow;
 use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use core::arch::asm;
@@ -38,10 +45,47 @@ pub fn kernel_token() -> usize {
     KERNEL_SPACE.exclusive_access().token()
 }
 
+/// e_machine of RISC-V executables
+const EM_RISCV: u16 = 243;
+
+/// reasons for [`MemorySet::from_elf`] to reject an executable
+#[derive(Debug)]
+pub enum ElfError {
+    /// rejected by xmas_elf
+    Parse(&'static str),
+    /// wrong magic number
+    BadMagic,
+    /// not a 64-bit RISC-V executable
+    UnsupportedArch,
+    /// program headers or segment data beyond the end of the file
+    Truncated,
+    /// segment with a wrapping, overlapping or non-user address range
+    BadSegment,
+    /// segment both writable and executable, rejected when `WX_STRICT`
+    WritableExecutable,
+}
+
+// TLS 块放在用户栈顶，最多占用一半的用户栈
+const MAX_TLS_SIZE: usize = USER_STACK_SIZE / 2;
+
+/// Initialization image of thread-local storage from the `PT_TLS` program header
+pub struct TlsTemplate<'a> {
+    /// contents of `.tdata`
+    pub data: &'a [u8],
+    /// size of `.tdata` and `.tbss` together
+    pub mem_size: usize,
+    /// alignment of the TLS block, a power of two
+    pub align: usize,
+}
+
 /// memory set structure, controls virtual-memory space
 pub struct MemorySet {
     page_table: PageTable,
     areas: Vec<MapArea>,
+    // 用户地址空间中 [heap_bottom, mmap_base) 留给通过 sbrk 增长的堆，mmap 只能使用 mmap_base 以上的部分，
+    // 两者互不侵占。还没有堆的地址空间中两者都为 0
+    heap_bottom: usize,
+    mmap_base: usize,
 }
 
 impl MemorySet {
@@ -50,6 +94,8 @@ impl MemorySet {
         Self {
             page_table: PageTable::new(),
             areas: Vec::new(),
+            heap_bottom: 0,
+            mmap_base: 0,
         }
     }
     pub fn token(&self) -> usize {
@@ -61,12 +107,93 @@ impl MemorySet {
         start_va: VirtAddr,
         end_va: VirtAddr,
         permission: MapPermission,
+        name: impl Into<Cow<'static, str>>,
+    ) {
+        self.push(
+            MapArea::new(start_va, end_va, MapType::Framed, permission).named(name),
+            None,
+        );
+    }
+    // 按需清零的逻辑段在插入时只会把每个页面只读地映射到共享零页上，并不分配物理页帧
+    /// Assume that no conflicts.
+    pub fn insert_demand_zero_area(
+        &mut self,
+        start_va: VirtAddr,
+        end_va: VirtAddr,
+        permission: MapPermission,
+        name: impl Into<Cow<'static, str>>,
     ) {
         self.push(
-            MapArea::new(start_va, end_va, MapType::Framed, permission),
+            MapArea::new(start_va, end_va, MapType::DemandZero, permission).named(name),
             None,
         );
     }
+    /// Insert an empty heap area at `heap_bottom`,
+    /// reserving the `USER_HEAP_SIZE` bytes above it for the heap to grow into
+    pub fn insert_heap_area(&mut self, heap_bottom: usize) {
+        self.insert_framed_area(
+            heap_bottom.into(),
+            heap_bottom.into(),
+            MapPermission::R | MapPermission::W | MapPermission::U,
+            "[heap]",
+        );
+        self.heap_bottom = heap_bottom;
+        self.mmap_base = heap_bottom + USER_HEAP_SIZE;
+    }
+    /// Lowest address available to mmap, the heap never grows beyond it
+    pub fn mmap_base(&self) -> usize {
+        self.mmap_base
+    }
+    /// Check whether `[start_vpn, end_vpn)` intersects the range reserved for the heap
+    pub fn overlaps_heap(&self, start_vpn: VirtPageNum, end_vpn: VirtPageNum) -> bool {
+        VirtAddr::from(self.heap_bottom).floor() < end_vpn
+            && start_vpn < VirtAddr::from(self.mmap_base).ceil()
+    }
+    // 从用户地址空间的顶端向下寻找第一个足够大的空隙，这样 mmap 得到的内存与下方的堆相距尽可能远
+    /// Find `pages` free pages between `mmap_base` and `USER_SPACE_END`,
+    /// returning the first of them
+    pub fn find_free_area(&self, pages: usize) -> Option<VirtPageNum> {
+        let mut ranges: Vec<(usize, usize)> = self
+            .areas
+            .iter()
+            .map(|area| (area.vpn_range.get_start().0, area.vpn_range.get_end().0))
+            .collect();
+        ranges.sort_by_key(|&(start, _)| core::cmp::Reverse(start));
+        let base = VirtAddr::from(self.mmap_base).ceil().0;
+        let mut top = VirtAddr::from(USER_SPACE_END).floor().0;
+        // 位于 USER_SPACE_END 之上的 Trap 上下文不影响 top
+        for (start, end) in ranges {
+            let bottom = end.max(base);
+            if bottom < top && top - bottom >= pages {
+                return Some(VirtPageNum(top - pages));
+            }
+            top = top.min(start);
+        }
+        if base < top && top - base >= pages {
+            Some(VirtPageNum(top - pages))
+        } else {
+            None
+        }
+    }
+    /// Check whether `[start_vpn, end_vpn)` intersects any `MapArea`
+    pub fn overlaps(&self, start_vpn: VirtPageNum, end_vpn: VirtPageNum) -> bool {
+        self.areas.iter().any(|area| {
+            area.vpn_range.get_start() < end_vpn && start_vpn < area.vpn_range.get_end()
+        })
+    }
+    /// Remove the `MapArea` exactly covering `[start_vpn, end_vpn)`
+    pub fn remove_area(&mut self, start_vpn: VirtPageNum, end_vpn: VirtPageNum) -> bool {
+        if let Some(idx) = self.areas.iter().position(|area| {
+            area.vpn_range.get_start() == start_vpn && area.vpn_range.get_end() == end_vpn
+        }) {
+            self.areas[idx].unmap(&mut self.page_table);
+            self.areas.remove(idx);
+            flush_range(start_vpn, end_vpn);
+            true
+        } else {
+            false
+        }
+    }
     ///Remove `MapArea` that starts with `start_vpn`
     pub fn remove_area_with_start_vpn(&mut self, start_vpn: VirtPageNum) {
         if let Some((idx, area)) = self
@@ -76,7 +203,9 @@ impl MemorySet {
             .find(|(_, area)| area.vpn_range.get_start() == start_vpn)
         {
             area.unmap(&mut self.page_table);
+            let end_vpn = area.vpn_range.get_end();
             self.areas.remove(idx);
+            flush_range(start_vpn, end_vpn);
         }
     }
     // 在当前地址空间插入一个新的逻辑段 map_area
@@ -115,7 +244,8 @@ impl MemorySet {
                 (etext as usize).into(),
                 MapType::Identical,
                 MapPermission::R | MapPermission::X,
-            ),
+            )
+            .named(".text"),
             None,
         );
         println!("mapping .rodata section");
@@ -125,7 +255,8 @@ impl MemorySet {
                 (erodata as usize).into(),
                 MapType::Identical,
                 MapPermission::R,
-            ),
+            )
+            .named(".rodata"),
             None,
         );
         println!("mapping .data section");
@@ -135,7 +266,8 @@ impl MemorySet {
                 (edata as usize).into(),
                 MapType::Identical,
                 MapPermission::R | MapPermission::W,
-            ),
+            )
+            .named(".data"),
             None,
         );
         println!("mapping .bss section");
@@ -145,7 +277,8 @@ impl MemorySet {
                 (ebss as usize).into(),
                 MapType::Identical,
                 MapPermission::R | MapPermission::W,
-            ),
+            )
+            .named(".bss"),
             None,
         );
         println!("mapping physical memory");
@@ -155,7 +288,8 @@ impl MemorySet {
                 MEMORY_END.into(),
                 MapType::Identical,
                 MapPermission::R | MapPermission::W,
-            ),
+            )
+            .named("[physical memory]"),
             None,
         );
         println!("mapping memory-mapped registers");
@@ -167,7 +301,8 @@ impl MemorySet {
                     // 透明的恒等映射，从而让内核可以兼容于直接访问物理地址的设备驱动库
                     MapType::Identical,
                     MapPermission::R | MapPermission::W,
-                ),
+                )
+                .named("[mmio]"),
                 None,
             );
         }
@@ -175,27 +310,90 @@ impl MemorySet {
     }
     /// Include sections in elf and trampoline and TrapContext and user stack,
     /// also returns user_sp and entry point.
-    pub fn from_elf(elf_data: &[u8]) -> (Self, usize, usize) {
+    pub fn from_elf(
+        elf_data: &[u8],
+    ) -> Result<(Self, usize, usize, Option<TlsTemplate<'_>>), ElfError> {
         let mut memory_set = Self::new_bare();
         // 将跳板插入到应用地址空间
         // map trampoline
         memory_set.map_trampoline();
         // 使用外部 crate xmas_elf 来解析传入的应用 ELF 数据并可以轻松取出各个部分
         // map program headers of elf, with U flag
-        let elf = xmas_elf::ElfFile::new(elf_data).unwrap();
+        let elf = xmas_elf::ElfFile::new(elf_data).map_err(ElfError::Parse)?;
         let elf_header = elf.header;
         let magic = elf_header.pt1.magic;
-        assert_eq!(magic, [0x7f, 0x45, 0x4c, 0x46], "invalid elf!");
+        if magic != [0x7f, 0x45, 0x4c, 0x46] {
+            return Err(ElfError::BadMagic);
+        }
+        // 只能加载 64 位的 RISC-V 可执行文件。e_machine 字段位于 ELF 头中偏移 18 字节处
+        let machine = u16::from_le_bytes([elf_data[18], elf_data[19]]);
+        if elf_header.pt1.class() != xmas_elf::header::Class::SixtyFour || machine != EM_RISCV {
+            return Err(ElfError::UnsupportedArch);
+        }
         // 得到 program header 的数目
         let ph_count = elf_header.pt2.ph_count();
+        // program header 表必须完整地位于 ELF 数据中，否则 xmas_elf 解析 program header 时会越界
+        let ph_entry_size = elf_header.pt2.ph_entry_size() as usize;
+        if ph_entry_size < core::mem::size_of::<xmas_elf::program::ProgramHeader64>() {
+            return Err(ElfError::Truncated);
+        }
+        let ph_table_end = (ph_count as usize)
+            .checked_mul(ph_entry_size)
+            .and_then(|size| size.checked_add(elf_header.pt2.ph_offset() as usize))
+            .ok_or(ElfError::Truncated)?;
+        if ph_table_end > elf_data.len() {
+            return Err(ElfError::Truncated);
+        }
         let mut max_end_vpn = VirtPageNum(0);
+        let mut tls = None;
         for i in 0..ph_count {
-            let ph = elf.program_header(i).unwrap();
+            let ph = elf.program_header(i).map_err(ElfError::Parse)?;
+            // TLS 段的内容同时也位于某个 LOAD 段中，这里只记下它作为每个线程 TLS 块的初始化映像，由 exec 在用户栈上建立 TLS 块
+            if ph.get_type() == Ok(xmas_elf::program::Type::Tls) {
+                let file_start = ph.offset() as usize;
+                let file_end = file_start
+                    .checked_add(ph.file_size() as usize)
+                    .ok_or(ElfError::Truncated)?;
+                if file_end > elf_data.len() {
+                    return Err(ElfError::Truncated);
+                }
+                let align = (ph.align() as usize).max(1);
+                if ph.file_size() > ph.mem_size()
+                    || ph.mem_size() as usize > MAX_TLS_SIZE
+                    || !align.is_power_of_two()
+                    || align > PAGE_SIZE
+                {
+                    return Err(ElfError::BadSegment);
+                }
+                tls = Some(TlsTemplate {
+                    data: &elf_data[file_start..file_end],
+                    mem_size: ph.mem_size() as usize,
+                    align,
+                });
+            }
             // 确认 program header 的类型是 LOAD ，这表明它有被内核加载的必要，此时不必理会其他类型的 program header 
-            if ph.get_type().unwrap() == xmas_elf::program::Type::Load {
+            if ph.get_type() == Ok(xmas_elf::program::Type::Load) {
+                // 文件中的数据必须完整地位于 ELF 数据中且不多于内存中的大小，虚拟地址区间不能回绕、超出用户地址空间或者与之前的区域重叠
+                let file_start = ph.offset() as usize;
+                let file_end = file_start
+                    .checked_add(ph.file_size() as usize)
+                    .ok_or(ElfError::Truncated)?;
+                if file_end > elf_data.len() {
+                    return Err(ElfError::Truncated);
+                }
+                let va_end = ph
+                    .virtual_addr()
+                    .checked_add(ph.mem_size())
+                    .ok_or(ElfError::BadSegment)? as usize;
+                if ph.file_size() > ph.mem_size() || va_end > USER_SPACE_END {
+                    return Err(ElfError::BadSegment);
+                }
                 // 通过 ph.virtual_addr() 和 ph.mem_size() 来计算这一区域在应用地址空间中的位置
                 let start_va: VirtAddr = (ph.virtual_addr() as usize).into();
-                let end_va: VirtAddr = ((ph.virtual_addr() + ph.mem_size()) as usize).into();
+                let end_va: VirtAddr = va_end.into();
+                if memory_set.overlaps(start_va.floor(), end_va.ceil()) {
+                    return Err(ElfError::BadSegment);
+                }
                 // 通过 ph.flags() 来确认这一区域访问方式的限制并将其转换为 MapPermission 类型（注意它默认包含 U 标志位）
                 let mut map_perm = MapPermission::U;
                 let ph_flags = ph.flags();
@@ -208,12 +406,42 @@ impl MemorySet {
                 if ph_flags.is_execute() {
                     map_perm |= MapPermission::X;
                 }
-                let map_area = MapArea::new(start_va, end_va, MapType::Framed, map_perm);
-                max_end_vpn = map_area.vpn_range.get_end();
-                memory_set.push(
-                    map_area,
-                    Some(&elf.input[ph.offset() as usize..(ph.offset() + ph.file_size()) as usize]),
-                );
+                // 同时可写又可执行的段让程序能在运行时写入并执行任意代码
+                if map_perm.contains(MapPermission::W | MapPermission::X) {
+                    if WX_STRICT {
+                        return Err(ElfError::WritableExecutable);
+                    }
+                    println!(
+                        "[kernel] segment {} is both writable and executable, mapping it without X",
+                        i
+                    );
+                    map_perm.remove(MapPermission::X);
+                }
+                // 只有包含文件数据的页面才需要分配物理页帧，其后完全属于 .bss 的页面按需清零，首次写入前都共享同一个零页
+                let file_end_va: VirtAddr =
+                    ((ph.virtual_addr() + ph.file_size()) as usize).into();
+                // 以 program header 的序号命名，同一个段的两部分名字相同
+                let name = format!("[segment {}]", i);
+                if ph.file_size() > 0 {
+                    memory_set.push(
+                        MapArea::new(start_va, file_end_va, MapType::Framed, map_perm)
+                            .named(name.clone()),
+                        Some(&elf.input[file_start..file_end]),
+                    );
+                }
+                let zero_start_vpn = if ph.file_size() > 0 {
+                    file_end_va.ceil()
+                } else {
+                    start_va.floor()
+                };
+                if zero_start_vpn < end_va.ceil() {
+                    memory_set.push(
+                        MapArea::new(zero_start_vpn.into(), end_va, MapType::DemandZero, map_perm)
+                            .named(name),
+                        None,
+                    );
+                }
+                max_end_vpn = max_end_vpn.max(end_va.ceil());
             }
         }
         // 处理用户栈。注意在前面加载各个 program header 的时候，我们就已经维护了 max_end_vpn 记录目前涉及到的最大的虚拟页号，只需紧接着在它上面再放置一个保护页面和用户栈即可
@@ -229,18 +457,8 @@ impl MemorySet {
                 user_stack_top.into(),
                 MapType::Framed,
                 MapPermission::R | MapPermission::W | MapPermission::U,
-            ),
-            None,
-        );
-        
-        // used in sbrk
-        memory_set.push(
-            MapArea::new(
-                user_stack_top.into(),
-                user_stack_top.into(),
-                MapType::Framed,
-                MapPermission::R | MapPermission::W | MapPermission::U,
-            ),
+            )
+            .named("[stack]"),
             None,
         );
         // 在应用地址空间中映射次高页面来存放 Trap 上下文
@@ -251,23 +469,28 @@ impl MemorySet {
                 TRAMPOLINE.into(),
                 MapType::Framed,
                 MapPermission::R | MapPermission::W,
-            ),
+            )
+            .named("[trap context]"),
             None,
         );
-        (
+        Ok((
             // 返回应用地址空间 memory_set
             memory_set,
             // 返回用户栈虚拟地址 user_stack_top
             user_stack_top,
             // 从解析 ELF 得到的该应用入口点地址
             elf.header.pt2.entry_point() as usize,
-        )
+            // 没有 TLS 段时为 None
+            tls,
+        ))
     }
     // 复制一个完全相同的地址空间
     ///Clone a same `MemorySet`
     pub fn from_existed_user(user_space: &Self) -> Self {
         // 通过 new_bare 新创建一个空的地址空间
         let mut memory_set = Self::new_bare();
+        memory_set.heap_bottom = user_space.heap_bottom;
+        memory_set.mmap_base = user_space.mmap_base;
         // 通过 map_trampoline 为这个地址空间映射上跳板页面，这是因为我们解析 ELF 创建地址空间的时候，并没有将跳板页作为一个单独的逻辑段插入到地址空间的逻辑段向量 areas 中，所以这里需要单独映射上
         // map trampoline
         memory_set.map_trampoline();
@@ -279,6 +502,11 @@ impl MemorySet {
             // 遍历逻辑段中的每个虚拟页面，对应完成数据复制，这只需要找出两个地址空间中的虚拟页面各被映射到哪个物理页帧，就可转化为将数据从物理内存中的一个位置复制到另一个位置，使用 copy_from_slice 即可轻松实现
             // copy data from another space
             for vpn in area.vpn_range {
+                // 仍映射到共享零页的页面在子进程中同样映射到零页，无需复制；已被写过的页面则需要先在子进程中换成私有页帧再复制
+                if area.is_shared_zero(vpn) {
+                    continue;
+                }
+                memory_set.unshare_zero_page(vpn);
                 let src_ppn = user_space.translate(vpn).unwrap().ppn();
                 let dst_ppn = memory_set.translate(vpn).unwrap().ppn();
                 dst_ppn
@@ -301,6 +529,106 @@ impl MemorySet {
     pub fn translate(&self, vpn: VirtPageNum) -> Option<PageTableEntry> {
         self.page_table.translate(vpn)
     }
+    // 写入一个仍映射到共享零页的页面时触发 StorePageFault ，此时为它分配一个私有的（已清零的）物理页帧并以完整的权限重新映射
+    /// Replace the shared zero frame mapped at `vpn` with a private frame,
+    /// return false if `vpn` is not a writable demand-zero page still mapped to it
+    pub fn unshare_zero_page(&mut self, vpn: VirtPageNum) -> bool {
+        let unshared = match self.areas.iter_mut().find(|area| area.contains(vpn)) {
+            Some(area) => area.unshare_zero_page(&mut self.page_table, vpn),
+            None => false,
+        };
+        if unshared {
+            // 旧的只读映射可能还留在快表中
+            flush_page(vpn);
+        }
+        unshared
+    }
+    // 内核通过查页表直接访问物理内存来写用户缓冲区，不会触发缺页，因此写之前需要先把其中的共享零页换成私有页帧
+    /// Make sure `[start, start + len)` is not backed by the shared zero frame
+    pub fn unshare_zero_range(&mut self, start: usize, len: usize) {
+        let start_vpn = VirtAddr::from(start).floor();
+        let end_vpn = VirtAddr::from(start.saturating_add(len)).ceil();
+        if len == 0 || start_vpn >= end_vpn {
+            return;
+        }
+        for vpn in VPNRange::new(start_vpn, end_vpn) {
+            self.unshare_zero_page(vpn);
+        }
+    }
+    // vmsplice 在进程之间移动整页的数据时直接转移物理页帧的所有权，而不是复制其中的内容。
+    // 只有按需清零逻辑段中可写的页面才能这样移动，被移走页帧的页面重新映射到共享零页
+    /// Take the private frame of the writable demand-zero page `vpn`,
+    /// `None` if there is no such frame
+    pub fn take_page(&mut self, vpn: VirtPageNum) -> Option<FrameTracker> {
+        let frame = self
+            .areas
+            .iter_mut()
+            .find(|area| area.contains(vpn))?
+            .take_page(&mut self.page_table, vpn)?;
+        flush_page(vpn);
+        Some(frame)
+    }
+    /// Map `frame` at the writable demand-zero page `vpn` in place of its old content,
+    /// giving the frame back if there is no such page
+    pub fn give_page(&mut self, vpn: VirtPageNum, frame: FrameTracker) -> Result<(), FrameTracker> {
+        match self.areas.iter_mut().find(|area| area.contains(vpn)) {
+            Some(area) => area.give_page(&mut self.page_table, vpn, frame)?,
+            None => return Err(frame),
+        }
+        flush_page(vpn);
+        Ok(())
+    }
+    // 每行描述一个逻辑段：起止地址、权限和名字，按起始地址排序，格式类似于 Linux 的 /proc/<pid>/maps
+    /// Describe every `MapArea` of this space, one per line
+    pub fn maps(&self) -> String {
+        let mut areas: Vec<&MapArea> = self.areas.iter().collect();
+        areas.sort_by_key(|area| area.vpn_range.get_start());
+        let mut maps = String::new();
+        for area in areas {
+            let start: VirtAddr = area.vpn_range.get_start().into();
+            let end: VirtAddr = area.vpn_range.get_end().into();
+            let perm: String = [
+                (MapPermission::R, 'r'),
+                (MapPermission::W, 'w'),
+                (MapPermission::X, 'x'),
+                (MapPermission::U, 'u'),
+            ]
+            .iter()
+            .map(|&(flag, ch)| {
+                if area.map_perm.contains(flag) {
+                    ch
+                } else {
+                    '-'
+                }
+            })
+            .collect();
+            maps += &format!(
+                "{:#010x}-{:#010x} {} {}\n",
+                start.0,
+                end.0,
+                perm,
+                area.name.as_deref().unwrap_or(""),
+            );
+        }
+        maps
+    }
+    /// Number of frames currently holding data of this space
+    pub fn resident_pages(&self) -> usize {
+        self.areas.iter().map(|area| area.data_frames.len()).sum()
+    }
+    // 按需清零的页面在首次写入之前只映射到共享零页，页表项虽然有效，但还没有属于自己的页帧
+    /// Whether the page `vpn` is backed by a frame of its own,
+    /// `None` if no `MapArea` contains it
+    pub fn resident(&self, vpn: VirtPageNum) -> Option<bool> {
+        let area = self.areas.iter().find(|area| area.contains(vpn))?;
+        Some(match area.map_type {
+            MapType::DemandZero => area.data_frames.contains_key(&vpn),
+            _ => self
+                .page_table
+                .translate(vpn)
+                .map_or(false, |pte| pte.is_valid()),
+        })
+    }
     // MemorySet::recycle_data_pages 只是将地址空间中的逻辑段列表 areas 清空（即执行 Vec 向量清空），
     // 这将导致应用地址空间被回收（即进程的数据和代码对应的物理页帧都被回收），但用来存放页表的那些物理页帧此时还不会被回收（会由父进程最后回收子进程剩余的占用资源）
     ///Remove all `MapArea`
@@ -308,27 +636,32 @@ impl MemorySet {
         //*self = Self::new_bare();
         self.areas.clear();
     }
-    #[allow(unused)]
     pub fn shrink_to(&mut self, start: VirtAddr, new_end: VirtAddr) -> bool {
         if let Some(area) = self
             .areas
             .iter_mut()
             .find(|area| area.vpn_range.get_start() == start.floor())
         {
+            let old_end = area.vpn_range.get_end();
             area.shrink_to(&mut self.page_table, new_end.ceil());
+            flush_range(new_end.ceil(), old_end);
             true
         } else {
             false
         }
     }
-    #[allow(unused)]
     pub fn append_to(&mut self, start: VirtAddr, new_end: VirtAddr) -> bool {
-        if let Some(area) = self
+        if let Some(idx) = self
             .areas
-            .iter_mut()
-            .find(|area| area.vpn_range.get_start() == start.floor())
+            .iter()
+            .position(|area| area.vpn_range.get_start() == start.floor())
         {
-            area.append_to(&mut self.page_table, new_end.ceil());
+            // 新扩展出来的部分不能与其他逻辑段重叠
+            let old_end = self.areas[idx].vpn_range.get_end();
+            if new_end.ceil() > old_end && self.overlaps(old_end, new_end.ceil()) {
+                return false;
+            }
+            self.areas[idx].append_to(&mut self.page_table, new_end.ceil());
             true
         } else {
             false
@@ -337,6 +670,78 @@ impl MemorySet {
     
 }
 
+/// Flush the stale mapping of `vpn` from the TLB
+fn flush_page(vpn: VirtPageNum) {
+    let va: VirtAddr = vpn.into();
+    unsafe {
+        asm!("sfence.vma {}, zero", in(reg) va.0);
+    }
+}
+
+/// Flush the whole TLB
+fn flush_all() {
+    unsafe {
+        asm!("sfence.vma");
+    }
+}
+
+// 取消映射一段页面之后刷新快表：页面不多时逐页刷新，保留快表中其他页面的表项；
+// 超过 TLB_FLUSH_BATCH 时只用一条指令清空整个快表
+/// Flush the stale mappings of `[start_vpn, end_vpn)` from the TLB of this hart
+pub fn flush_local_range(start_vpn: VirtPageNum, end_vpn: VirtPageNum) {
+    if end_vpn.0.saturating_sub(start_vpn.0) > TLB_FLUSH_BATCH {
+        flush_all();
+    } else {
+        for vpn in VPNRange::new(start_vpn, end_vpn) {
+            flush_page(vpn);
+        }
+    }
+}
+
+// 其他 hart 的快表中也可能缓存着这些表项，通过 IPI 让它们各自刷新
+/// Flush the stale mappings of `[start_vpn, end_vpn)` from the TLB of every hart
+fn flush_range(start_vpn: VirtPageNum, end_vpn: VirtPageNum) {
+    flush_local_range(start_vpn, end_vpn);
+    tlb_shootdown(start_vpn, end_vpn);
+}
+
+#[allow(unused)]
+/// Print the cost of flushing pages one by one and of flushing the whole TLB,
+/// for choosing `TLB_FLUSH_BATCH` on a platform
+pub fn tlb_flush_bench() {
+    use crate::timer::get_time;
+    // 清空快表之后，内核接下来访问的页面都要重新查页表，用依次读取若干个页面来模拟这部分开销
+    const WORKING_SET: usize = 32;
+    let touch = || {
+        for i in 0..WORKING_SET {
+            let addr = ekernel as usize + i * PAGE_SIZE;
+            unsafe {
+                (addr as *const usize).read_volatile();
+            }
+        }
+    };
+    // 逐页刷新的页面不与上面读取的页面重叠
+    let base = VirtAddr::from(ekernel as usize).floor().0 + WORKING_SET;
+    for pages in [8, 16, 32, 64, 128, 256, 512] {
+        touch();
+        let start = get_time();
+        for vpn in base..base + pages {
+            flush_page(VirtPageNum(vpn));
+        }
+        touch();
+        let one_by_one = get_time() - start;
+        touch();
+        let start = get_time();
+        flush_all();
+        touch();
+        let all = get_time() - start;
+        println!(
+            "[kernel] flushing {} pages: {} ticks one by one, {} ticks all at once",
+            pages, one_by_one, all
+        );
+    }
+}
+
 /// map area structure, controls a contiguous piece of virtual memory
 pub struct MapArea {
     // VPNRange 描述一段虚拟页号的连续区间，表示该逻辑段在地址区间中的位置和长度。它是一个迭代器，可以使用 Rust 的语法糖 for-loop 进行迭代
@@ -345,6 +750,8 @@ pub struct MapArea {
     data_frames: BTreeMap<VirtPageNum, FrameTracker>,
     map_type: MapType,
     map_perm: MapPermission,
+    // 仅用于调试时辨认逻辑段，静态的名字不需要分配内存
+    name: Option<Cow<'static, str>>,
 }
 
 impl MapArea {
@@ -361,8 +768,14 @@ impl MapArea {
             data_frames: BTreeMap::new(),
             map_type,
             map_perm,
+            name: None,
         }
     }
+    /// Label the area in address-space dumps
+    pub fn named(mut self, name: impl Into<Cow<'static, str>>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
     // 从一个逻辑段复制得到一个虚拟地址区间、映射方式和权限控制均相同的逻辑段，不同的是由于它还没有真正被映射到物理页帧上，所以 data_frames 字段为空
     pub fn from_another(another: &Self) -> Self {
         Self {
@@ -370,10 +783,67 @@ impl MapArea {
             data_frames: BTreeMap::new(),
             map_type: another.map_type,
             map_perm: another.map_perm,
+            name: another.name.clone(),
+        }
+    }
+    pub fn contains(&self, vpn: VirtPageNum) -> bool {
+        self.vpn_range.get_start() <= vpn && vpn < self.vpn_range.get_end()
+    }
+    // 按需清零的逻辑段中，尚未分配私有页帧的页面都映射到共享零页
+    pub fn is_shared_zero(&self, vpn: VirtPageNum) -> bool {
+        self.map_type == MapType::DemandZero
+            && self.contains(vpn)
+            && !self.data_frames.contains_key(&vpn)
+    }
+    pub fn unshare_zero_page(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) -> bool {
+        if !self.is_shared_zero(vpn) || !self.map_perm.contains(MapPermission::W) {
+            return false;
+        }
+        page_table.unmap(vpn);
+        // 新分配的页帧已经被 FrameTracker::new 清零，和零页的内容一致，无需复制
+        let frame = frame_alloc().unwrap();
+        let pte_flags = PTEFlags::from_bits(self.map_perm.bits).unwrap();
+        page_table.map(vpn, frame.ppn, pte_flags);
+        self.data_frames.insert(vpn, frame);
+        true
+    }
+    fn movable(&self, vpn: VirtPageNum) -> bool {
+        self.map_type == MapType::DemandZero
+            && self.contains(vpn)
+            && self.map_perm.contains(MapPermission::W)
+    }
+    pub fn take_page(
+        &mut self,
+        page_table: &mut PageTable,
+        vpn: VirtPageNum,
+    ) -> Option<FrameTracker> {
+        if !self.movable(vpn) {
+            return None;
+        }
+        let frame = self.data_frames.remove(&vpn)?;
+        page_table.unmap(vpn);
+        self.map_one(page_table, vpn);
+        Some(frame)
+    }
+    pub fn give_page(
+        &mut self,
+        page_table: &mut PageTable,
+        vpn: VirtPageNum,
+        frame: FrameTracker,
+    ) -> Result<(), FrameTracker> {
+        if !self.movable(vpn) {
+            return Err(frame);
         }
+        // 页面原来的页帧（如果有的话）在被替换掉时回收
+        page_table.unmap(vpn);
+        let pte_flags = PTEFlags::from_bits(self.map_perm.bits).unwrap();
+        page_table.map(vpn, frame.ppn, pte_flags);
+        self.data_frames.insert(vpn, frame);
+        Ok(())
     }
     pub fn map_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) {
         let ppn: PhysPageNum;
+        let mut map_perm = self.map_perm;
         match self.map_type {
             // 当以恒等映射 Identical 方式映射的时候，物理页号就等于虚拟页号
             MapType::Identical => {
@@ -381,17 +851,24 @@ impl MapArea {
             }
             // 当以 Framed 方式映射时，需要分配一个物理页帧让当前的虚拟页面可以映射过去，此时页表项中的物理页号自然就是 这个被分配的物理页帧的物理页号。此时还需要将这个物理页帧挂在逻辑段的 data_frames 字段下
             MapType::Framed => {
-                let frame = frame_alloc().unwrap();
+                // 以虚拟页号的低位作为颜色，使连续的虚拟页面落在不同的缓存组中
+                let frame = frame_alloc_colored(vpn.0).unwrap();
                 ppn = frame.ppn;
                 self.data_frames.insert(vpn, frame);
             }
+            // 当以 DemandZero 方式映射时，先只读地映射到共享零页，首次写入时再分配私有页帧。这样的页面不计入 data_frames
+            MapType::DemandZero => {
+                ppn = zero_frame();
+                map_perm.remove(MapPermission::W);
+                map_perm.insert(MapPermission::R);
+            }
         }
-        let pte_flags = PTEFlags::from_bits(self.map_perm.bits).unwrap();
+        let pte_flags = PTEFlags::from_bits(map_perm.bits).unwrap();
         page_table.map(vpn, ppn, pte_flags);
     }
     #[allow(unused)]
     pub fn unmap_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) {
-        if self.map_type == MapType::Framed {
+        if self.map_type != MapType::Identical {
             self.data_frames.remove(&vpn);
         }
         page_table.unmap(vpn);
@@ -408,14 +885,12 @@ impl MapArea {
             self.unmap_one(page_table, vpn);
         }
     }
-    #[allow(unused)]
     pub fn shrink_to(&mut self, page_table: &mut PageTable, new_end: VirtPageNum) {
         for vpn in VPNRange::new(new_end, self.vpn_range.get_end()) {
             self.unmap_one(page_table, vpn)
         }
         self.vpn_range = VPNRange::new(self.vpn_range.get_start(), new_end);
     }
-    #[allow(unused)]
     pub fn append_to(&mut self, page_table: &mut PageTable, new_end: VirtPageNum) {
         for vpn in VPNRange::new(self.vpn_range.get_end(), new_end) {
             self.map_one(page_table, vpn)
@@ -449,12 +924,14 @@ impl MapArea {
 }
 
 #[derive(Copy, Clone, PartialEq, Debug)]
-/// map type for memory set: identical or framed
+/// map type for memory set: identical, framed or demand-zero
 pub enum MapType {
     // Identical 表示恒等映射方式，主要是用在启用多级页表之后，内核仍能够在虚存地址空间中访问一个特定的物理地址指向的物理内存
     Identical,
     // Framed 表示对于每个虚拟页面都有一个新分配的物理页帧与之对应，虚地址与物理地址的映射关系是相对随机的
     Framed,
+    // DemandZero 表示页面初始内容全为零，在第一次被写入之前都共享同一个只读的零页
+    DemandZero,
 }
 
 bitflags! {
@@ -490,3 +967,55 @@ pub fn remap_test() {
         .executable(),);
     println!("remap_test passed!");
 }
+
+// 检查 from_elf 建立的用户地址空间的布局：用户栈下方的保护页面没有被映射，用户栈在用户态可以读写，
+// Trap 上下文和跳板只能在内核态访问。调试模式下每次建立用户地址空间之后都会检查，尽早发现布局上的错误
+/// Check the layout of a user space built by `from_elf` whose user stack ends at `user_stack_top`
+pub fn verify_user_space(memory_set: &MemorySet, user_stack_top: usize) {
+    let flags = |va: usize| {
+        memory_set
+            .page_table
+            .translate(VirtAddr::from(va).floor())
+            .filter(|pte| pte.is_valid())
+            .map(|pte| pte.flags())
+    };
+    let user_stack_bottom = user_stack_top - USER_STACK_SIZE;
+    assert!(
+        flags(user_stack_bottom - PAGE_SIZE).is_none(),
+        "guard page below the user stack is mapped"
+    );
+    let user_rw = PTEFlags::R | PTEFlags::W | PTEFlags::U;
+    for va in [user_stack_bottom, user_stack_top - PAGE_SIZE] {
+        assert!(
+            flags(va).map_or(false, |flags| flags.contains(user_rw)),
+            "user stack page {:#x} is not user read/write",
+            va
+        );
+    }
+    let trap_cx = flags(TRAP_CONTEXT).expect("trap context is not mapped");
+    assert!(
+        trap_cx.contains(PTEFlags::R | PTEFlags::W)
+            && !trap_cx.intersects(PTEFlags::U | PTEFlags::X),
+        "trap context is mapped with {:?}",
+        trap_cx
+    );
+    let trampoline = flags(TRAMPOLINE).expect("trampoline is not mapped");
+    assert!(
+        trampoline.contains(PTEFlags::R | PTEFlags::X)
+            && !trampoline.intersects(PTEFlags::U | PTEFlags::W),
+        "trampoline is mapped with {:?}",
+        trampoline
+    );
+}
+
+#[allow(unused)]
+/// Build the user spaces of a few apps and check their layout
+pub fn user_space_test() {
+    use crate::fs::{open_inode, OpenFlags};
+    for app in ["initproc", "user_shell"] {
+        let elf_data = open_inode(app, OpenFlags::RDONLY).unwrap().read_all();
+        let (memory_set, user_stack_top, _, _) = MemorySet::from_elf(&elf_data).unwrap();
+        verify_user_space(&memory_set, user_stack_top);
+    }
+    println!("user_space_test passed!");
+}
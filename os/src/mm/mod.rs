@@ -14,13 +14,20 @@ mod page_table;
 
 use address::VPNRange;
 pub use address::{PhysAddr, PhysPageNum, StepByOne, VirtAddr, VirtPageNum};
-pub use frame_allocator::{frame_alloc, frame_dealloc, FrameTracker};
-pub use memory_set::remap_test;
-pub use memory_set::{kernel_token, MapPermission, MemorySet, KERNEL_SPACE};
+pub use frame_allocator::{
+    frame_alloc, frame_alloc_colored, frame_coloring_test, frame_dealloc, take_lowmem_event,
+    zero_frame, FrameTracker,
+};
+pub use memory_set::{
+    flush_local_range, kernel_token, ElfError, MapPermission, MemorySet, TlsTemplate, KERNEL_SPACE,
+};
+pub use memory_set::{remap_test, tlb_flush_bench, user_space_test, verify_user_space};
 use page_table::PTEFlags;
 pub use page_table::{
-    translated_byte_buffer, translated_ref, translated_refmut, translated_str, PageTable,
-    PageTableEntry, UserBuffer, UserBufferIterator,
+    copy_in_value, copy_out, copy_out_value, translated_byte_buffer, translated_bytes_copy_in,
+    translated_ref, translated_refmut, translated_str, try_translated_ref, try_translated_str,
+    user_buffer_test, user_range_readable, IoVec, PageTable, PageTableEntry, UserBuffer,
+    UserBufferIterator,
 };
 
 /// initiate heap allocator, frame allocator and kernel space
@@ -1,5 +1,6 @@
 //! Implementation of [`PageTableEntry`] and [`PageTable`].
 use super::{frame_alloc, FrameTracker, PhysAddr, PhysPageNum, StepByOne, VirtAddr, VirtPageNum};
+use crate::config::PAGE_SIZE;
 use alloc::string::String;
 use alloc::vec;
 use alloc::vec::Vec;
@@ -193,6 +194,37 @@ pub fn translated_byte_buffer(token: usize, ptr: *const u8, len: usize) -> Vec<&
     }
     v
 }
+// 大多数系统调用只需要在内核与用户缓冲区之间传递几个字节，直接拷贝一份比逐段处理 translated_byte_buffer 返回的切片更简单，
+// 而且可以正确处理跨越页面边界的结构体。大块数据的传输仍然使用不需要拷贝的 translated_byte_buffer
+/// Copy `len` bytes of the user buffer at `ptr` into a contiguous kernel vector
+pub fn translated_bytes_copy_in(token: usize, ptr: *const u8, len: usize) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(len);
+    for buffer in translated_byte_buffer(token, ptr, len) {
+        bytes.extend_from_slice(buffer);
+    }
+    bytes
+}
+/// Copy `data` into the user buffer at `ptr`.
+/// Shared zero pages in the buffer must have been given private frames by the caller.
+pub fn copy_out(token: usize, ptr: *mut u8, data: &[u8]) {
+    let mut copied = 0;
+    for buffer in translated_byte_buffer(token, ptr, data.len()) {
+        buffer.copy_from_slice(&data[copied..copied + buffer.len()]);
+        copied += buffer.len();
+    }
+}
+/// Read a value of type `T` from user space by copy
+pub fn copy_in_value<T: Copy>(token: usize, ptr: *const T) -> T {
+    let bytes = translated_bytes_copy_in(token, ptr as *const u8, core::mem::size_of::<T>());
+    unsafe { (bytes.as_ptr() as *const T).read_unaligned() }
+}
+/// Write `value` to user space by copy
+pub fn copy_out_value<T: Copy>(token: usize, ptr: *mut T, value: &T) {
+    let bytes = unsafe {
+        core::slice::from_raw_parts(value as *const T as *const u8, core::mem::size_of::<T>())
+    };
+    copy_out(token, ptr as *mut u8, bytes);
+}
 // 针对应用的字符串中字符的用户态虚拟地址，查页表，找到对应的内核虚拟地址，逐字节地构造字符串，直到发现一个 \0 为止
 /// translate a pointer to a mutable u8 Vec end with `\0` through page table to a `String`
 pub fn translated_str(token: usize, ptr: *const u8) -> String {
@@ -222,6 +254,57 @@ pub fn translated_ref<T>(token: usize, ptr: *const T) -> &'static T {
         .unwrap()
         .get_ref()
 }
+// 以下几个 try_ 开头的辅助函数用于访问用户传入的、不一定合法的指针：当地址没有以用户可读的方式被映射时返回 None 而不是让内核 panic
+/// Check that the page holding `va` is mapped readable in user space
+fn user_readable(page_table: &PageTable, va: VirtAddr) -> bool {
+    match page_table.translate(va.floor()) {
+        Some(pte) => pte.is_valid() && pte.readable() && pte.flags().contains(PTEFlags::U),
+        None => false,
+    }
+}
+/// Check that every page of `start..start + len` is mapped readable in user space
+pub fn user_range_readable(token: usize, start: usize, len: usize) -> bool {
+    let page_table = PageTable::from_token(token);
+    let Some(end) = start.checked_add(len) else {
+        return false;
+    };
+    (start / PAGE_SIZE..end.div_ceil(PAGE_SIZE))
+        .all(|page| user_readable(&page_table, VirtAddr::from(page * PAGE_SIZE)))
+}
+///Translate a generic through page table, return None if it is not readable by user
+pub fn try_translated_ref<T>(token: usize, ptr: *const T) -> Option<&'static T> {
+    let page_table = PageTable::from_token(token);
+    let start = ptr as usize;
+    let end = start.checked_add(core::mem::size_of::<T>())?;
+    // 要求对象按自身的对齐方式对齐，且不跨越页面边界
+    if start % core::mem::align_of::<T>() != 0 || (end - 1) / PAGE_SIZE != start / PAGE_SIZE {
+        return None;
+    }
+    let va = VirtAddr::from(start);
+    if !user_readable(&page_table, va) {
+        return None;
+    }
+    Some(page_table.translate_va(va)?.get_ref())
+}
+///Translate a `\0` terminated string of at most `max_len` bytes, return None if it is
+///longer or not readable by user
+pub fn try_translated_str(token: usize, ptr: *const u8, max_len: usize) -> Option<String> {
+    let page_table = PageTable::from_token(token);
+    let mut string = String::new();
+    let start = ptr as usize;
+    for len in 0..=max_len {
+        let va = VirtAddr::from(start.checked_add(len)?);
+        if !user_readable(&page_table, va) {
+            return None;
+        }
+        let ch: u8 = *(page_table.translate_va(va)?.get_ref());
+        if ch == 0 {
+            return Some(string);
+        }
+        string.push(ch as char);
+    }
+    None
+}
 ///translate a generic through page table and return a mutable reference
 pub fn translated_refmut<T>(token: usize, ptr: *mut T) -> &'static mut T {
     //println!("into translated_refmut!");
@@ -234,6 +317,16 @@ pub fn translated_refmut<T>(token: usize, ptr: *mut T) -> &'static mut T {
         .get_mut()
 }
 
+/// A user buffer of a vectored I/O request, passed to `sys_preadv`/`sys_pwritev`
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct IoVec {
+    /// start address of the buffer
+    pub base: usize,
+    /// length of the buffer
+    pub len: usize,
+}
+
 // 用户缓冲区的抽象 UserBuffer只是将我们调用 translated_byte_buffer 获得的包含多个切片的 Vec 进一步包装起来
 ///Array of u8 slice that user communicate with os
 pub struct UserBuffer {
@@ -255,6 +348,58 @@ impl UserBuffer {
         }
         total
     }
+    // 以下方法按片段整体拷贝，而不是像迭代器那样逐字节访问：先跳过 offset 之前的完整片段，
+    // 之后每个片段（通常就是一个页面内的一段）用一次 copy_from_slice 完成拷贝，一次拷贝可以跨越多个片段
+    /// Copy `data` into the buffer starting at `offset`, return the number of bytes copied
+    pub fn write_at(&mut self, offset: usize, data: &[u8]) -> usize {
+        let mut start = offset;
+        let mut copied = 0usize;
+        for buffer in self.buffers.iter_mut() {
+            if copied == data.len() {
+                break;
+            }
+            if start >= buffer.len() {
+                start -= buffer.len();
+                continue;
+            }
+            let len = (buffer.len() - start).min(data.len() - copied);
+            buffer[start..start + len].copy_from_slice(&data[copied..copied + len]);
+            copied += len;
+            start = 0;
+        }
+        copied
+    }
+    /// Copy bytes of the buffer starting at `offset` into `out`, return the number of bytes copied
+    pub fn read_at(&self, offset: usize, out: &mut [u8]) -> usize {
+        let mut start = offset;
+        let mut copied = 0usize;
+        for buffer in self.buffers.iter() {
+            if copied == out.len() {
+                break;
+            }
+            if start >= buffer.len() {
+                start -= buffer.len();
+                continue;
+            }
+            let len = (buffer.len() - start).min(out.len() - copied);
+            out[copied..copied + len].copy_from_slice(&buffer[start..start + len]);
+            copied += len;
+            start = 0;
+        }
+        copied
+    }
+    /// Fill the buffer from the beginning with `data`, return the number of bytes copied
+    pub fn copy_from_slice(&mut self, data: &[u8]) -> usize {
+        self.write_at(0, data)
+    }
+    /// Collect the whole buffer into a `Vec`
+    pub fn copy_to_vec(&self) -> Vec<u8> {
+        let mut v = Vec::with_capacity(self.len());
+        for buffer in self.buffers.iter() {
+            v.extend_from_slice(buffer);
+        }
+        v
+    }
 }
 
 // 让它作为一个迭代器可以逐字节进行读写
@@ -292,4 +437,33 @@ impl Iterator for UserBufferIterator {
             Some(r)
         }
     }
-}
\ No newline at end of file
+}
+#[allow(unused)]
+/// a simple test for copies across the segments of `UserBuffer`
+pub fn user_buffer_test() {
+    use alloc::boxed::Box;
+    // 模拟一个跨越三个页面的用户缓冲区，三个片段的长度分别为 3, 5, 4
+    let segments: Vec<&'static mut [u8]> = [3, 5, 4]
+        .iter()
+        .map(|&len| Box::leak(vec![0u8; len].into_boxed_slice()) as &'static mut [u8])
+        .collect();
+    let mut buf = UserBuffer::new(segments);
+    assert_eq!(buf.len(), 12);
+    // 恰好写满第一个片段
+    assert_eq!(buf.write_at(0, b"abc"), 3);
+    // 跨越第一个和第二个片段
+    assert_eq!(buf.write_at(2, b"XYZ"), 3);
+    assert_eq!(&*buf.buffers[0], b"abX");
+    assert_eq!(&buf.buffers[1][..2], b"YZ");
+    // 跨越全部三个片段，超出缓冲区末尾的部分被截断
+    assert_eq!(buf.copy_from_slice(b"0123456789abcdef"), 12);
+    assert_eq!(buf.copy_to_vec(), b"0123456789ab");
+    let mut out = [0u8; 6];
+    assert_eq!(buf.read_at(6, &mut out), 6);
+    assert_eq!(&out, b"6789ab");
+    assert_eq!(buf.read_at(7, &mut out), 5);
+    assert_eq!(&out[..5], b"789ab");
+    assert_eq!(buf.read_at(12, &mut out), 0);
+    assert_eq!(buf.write_at(20, b"x"), 0);
+    println!("user_buffer_test passed!");
+}
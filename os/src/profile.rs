@@ -0,0 +1,100 @@
+//! Statistical kernel profiler sampling the interrupted pc on timer interrupts
+// 开始采样之后，内核在处理系统调用期间打开 S 特权级中断。时钟中断到来时 trap_from_kernel 记下被打断的 sepc ，
+// 按照地址所在的区间累加到直方图中，之后保持中断屏蔽返回，这个中断留到回到用户态之后再照常处理。
+// 采样点可能打断内核中的任何位置，因此这里只使用原子变量，不获取任何锁
+use crate::config::PROFILE_BUCKETS;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+extern "C" {
+    fn stext();
+    fn etext();
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static USER_SAMPLES: AtomicUsize = AtomicUsize::new(0);
+static KERNEL_SAMPLES: AtomicUsize = AtomicUsize::new(0);
+// 在内核态采过样的时钟中断仍在等待处理，回到用户态之后处理它时不能再算作一个用户态样本
+static TICK_SAMPLED: AtomicBool = AtomicBool::new(false);
+#[allow(clippy::declare_interior_mutable_const)]
+const ZERO: AtomicUsize = AtomicUsize::new(0);
+// 内核代码段被平均分成 PROFILE_BUCKETS 个区间，直方图的大小与内核的大小无关
+static BUCKETS: [AtomicUsize; PROFILE_BUCKETS] = [ZERO; PROFILE_BUCKETS];
+
+/// The kernel profile returned by `sys_profile`
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct ProfileStats {
+    /// start address of the kernel text, which bucket 0 starts at
+    pub text_start: usize,
+    /// bytes of kernel text covered by each bucket
+    pub bucket_size: usize,
+    /// samples taken while running in user mode
+    pub user_samples: usize,
+    /// samples taken while running in kernel mode
+    pub kernel_samples: usize,
+    /// kernel samples in each bucket
+    pub buckets: [usize; PROFILE_BUCKETS],
+}
+
+/// Bytes of kernel text covered by each bucket
+fn bucket_size() -> usize {
+    (etext as usize - stext as usize).div_ceil(PROFILE_BUCKETS)
+}
+
+/// Whether the profiler is sampling
+pub fn profile_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Clear the profile and start sampling
+pub fn profile_start() {
+    USER_SAMPLES.store(0, Ordering::Relaxed);
+    KERNEL_SAMPLES.store(0, Ordering::Relaxed);
+    TICK_SAMPLED.store(false, Ordering::Relaxed);
+    for bucket in BUCKETS.iter() {
+        bucket.store(0, Ordering::Relaxed);
+    }
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Stop sampling, keeping the profile taken so far
+pub fn profile_stop() {
+    ENABLED.store(false, Ordering::Relaxed);
+}
+
+/// Record a timer interrupt handled in user mode, unless it was sampled in kernel mode already
+pub fn profile_user() {
+    if !TICK_SAMPLED.swap(false, Ordering::Relaxed) && profile_enabled() {
+        USER_SAMPLES.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Record a timer interrupt taken in kernel mode at `pc`
+pub fn profile_kernel(pc: usize) {
+    if !profile_enabled() {
+        return;
+    }
+    TICK_SAMPLED.store(true, Ordering::Relaxed);
+    KERNEL_SAMPLES.fetch_add(1, Ordering::Relaxed);
+    // 代码段之外的地址不会出现在 sepc 中，真的出现时只计入总数
+    if let Some(offset) = pc.checked_sub(stext as usize) {
+        if let Some(bucket) = BUCKETS.get(offset / bucket_size()) {
+            bucket.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// The profile taken since the last `profile_start`
+pub fn profile_stats() -> ProfileStats {
+    let mut stats = ProfileStats {
+        text_start: stext as usize,
+        bucket_size: bucket_size(),
+        user_samples: USER_SAMPLES.load(Ordering::Relaxed),
+        kernel_samples: KERNEL_SAMPLES.load(Ordering::Relaxed),
+        buckets: [0; PROFILE_BUCKETS],
+    };
+    for (count, bucket) in stats.buckets.iter_mut().zip(BUCKETS.iter()) {
+        *count = bucket.load(Ordering::Relaxed);
+    }
+    stats
+}
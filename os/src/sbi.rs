@@ -20,6 +20,11 @@ pub fn set_timer(timer: usize) {
     sbi_rt::set_timer(timer as _);
 }
 
+/// use sbi call to send an IPI to every hart in `hart_mask`
+pub fn send_ipi(hart_mask: usize) {
+    sbi_rt::send_ipi(hart_mask, 0);
+}
+
 pub fn shutdown(failure: bool) -> ! {
     use sbi_rt::{system_reset, NoReason, Shutdown, SystemFailure};
     if !failure {
@@ -1,5 +1,9 @@
 //! Synchronization and interior mutability primitives
 
 mod up;
+#[cfg(debug_assertions)]
+mod watchdog;
 
-pub use up::UPSafeCell;
+pub use up::{UPRefMut, UPSafeCell};
+#[cfg(debug_assertions)]
+pub use watchdog::{check_lock_watchdog, lock_watchdog_test};
@@ -1,8 +1,17 @@
 use core::cell::{RefCell, RefMut};
+use core::ops::{Deref, DerefMut};
+#[cfg(debug_assertions)]
+use core::{cell::Cell, panic::Location};
+
+#[cfg(debug_assertions)]
+use super::watchdog::{lock_acquired, lock_released};
 
 pub struct UPSafeCell<T> {
     /// inner data
     inner: RefCell<T>,
+    /// call site of the latest exclusive borrow, used to diagnose borrow conflicts
+    #[cfg(debug_assertions)]
+    borrowed_at: Cell<Option<&'static Location<'static>>>,
 }
 
 unsafe impl<T> Sync for UPSafeCell<T> {}
@@ -11,10 +20,68 @@ impl<T> UPSafeCell<T> {
     /// User is responsible to guarantee that inner struct is only used in
     /// uniprocessor.
     pub unsafe fn new(value: T) -> Self {
-        Self { inner: RefCell::new(value) }
+        Self {
+            inner: RefCell::new(value),
+            #[cfg(debug_assertions)]
+            borrowed_at: Cell::new(None),
+        }
     }
+    // 同一时刻只能有一个借用，最近一次成功的借用一定就是仍然存活的那个借用。
+    // 因此在调试模式下记录每次借用的调用位置，发生冲突时就可以同时打印出两处调用位置，便于定位嵌套借用
     /// Panic if the data has been borrowed.
-    pub fn exclusive_access(&self) -> RefMut<'_, T> {
-        self.inner.borrow_mut()
+    #[track_caller]
+    pub fn exclusive_access(&self) -> UPRefMut<'_, T> {
+        #[cfg(debug_assertions)]
+        {
+            match self.inner.try_borrow_mut() {
+                Ok(inner) => {
+                    self.borrowed_at.set(Some(Location::caller()));
+                    let lock = self as *const _ as usize;
+                    lock_acquired(lock, Location::caller());
+                    UPRefMut { inner, lock }
+                }
+                Err(_) => {
+                    println!(
+                        "[kernel] UPSafeCell borrow conflict at {}, already borrowed at {}",
+                        Location::caller(),
+                        self.borrowed_at.get().unwrap()
+                    );
+                    panic!("UPSafeCell already borrowed");
+                }
+            }
+        }
+        #[cfg(not(debug_assertions))]
+        UPRefMut {
+            inner: self.inner.borrow_mut(),
+        }
     }
-}
\ No newline at end of file
+}
+
+// 在 RefMut 之外再包装一层，调试模式下借用结束时通知锁的看门狗
+/// A wrapper type for a mutably borrowed value from a `UPSafeCell`
+pub struct UPRefMut<'a, T> {
+    inner: RefMut<'a, T>,
+    /// address of the borrowed `UPSafeCell`
+    #[cfg(debug_assertions)]
+    lock: usize,
+}
+
+impl<T> Deref for UPRefMut<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T> DerefMut for UPRefMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+}
+
+#[cfg(debug_assertions)]
+impl<T> Drop for UPRefMut<'_, T> {
+    fn drop(&mut self) {
+        lock_released(self.lock);
+    }
+}
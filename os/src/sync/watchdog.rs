@@ -0,0 +1,85 @@
+//! Watchdog for locks held too long, only in debug builds
+// 内核态的 Trap 会直接 panic ，时钟中断只可能发生在用户态，此时内核中不应该还有任何锁被持有。
+// 如果某个锁的借用在返回用户态之后仍未释放（比如被保存在某个结构体中或者被 mem::forget ），
+// 之后再次获取它时内核就会卡住或者报错，而且很难看出它是在哪里被获取的。
+// 因此调试模式下记录所有被持有的锁的获取位置和时间，每次时钟中断时检查有没有锁被持有得过久
+use crate::config::LOCK_WATCHDOG_MS;
+use crate::timer::get_time_ms;
+use core::panic::Location;
+use spin::Mutex;
+
+// 同一时刻被持有的锁一般只有几个，超出容量的锁不再被记录
+const MAX_HELD_LOCKS: usize = 32;
+
+/// A lock being held
+#[derive(Clone, Copy)]
+struct HeldLock {
+    /// address of the lock
+    lock: usize,
+    /// where the lock was acquired
+    site: &'static Location<'static>,
+    /// when the lock was acquired, in milliseconds
+    since: usize,
+}
+
+// 记录本身不能使用 UPSafeCell ，否则每次获取它都要再记录一次
+static HELD_LOCKS: Mutex<[Option<HeldLock>; MAX_HELD_LOCKS]> = Mutex::new([None; MAX_HELD_LOCKS]);
+
+/// Record that the lock at address `lock` has been acquired at `site`
+pub fn lock_acquired(lock: usize, site: &'static Location<'static>) {
+    let mut held_locks = HELD_LOCKS.lock();
+    if let Some(slot) = held_locks.iter_mut().find(|slot| slot.is_none()) {
+        *slot = Some(HeldLock {
+            lock,
+            site,
+            since: get_time_ms(),
+        });
+    }
+}
+
+/// Record that the lock at address `lock` has been released
+pub fn lock_released(lock: usize) {
+    let mut held_locks = HELD_LOCKS.lock();
+    if let Some(slot) = held_locks
+        .iter_mut()
+        .find(|slot| matches!(slot, Some(held) if held.lock == lock))
+    {
+        *slot = None;
+    }
+}
+
+/// Find a lock which has been held for more than `LOCK_WATCHDOG_MS` at time `now`,
+/// returning where it was acquired and how long it has been held
+pub fn stuck_lock(now: usize) -> Option<(&'static Location<'static>, usize)> {
+    HELD_LOCKS
+        .lock()
+        .iter()
+        .flatten()
+        .map(|held| (held.site, now.saturating_sub(held.since)))
+        .find(|(_, held_ms)| *held_ms > LOCK_WATCHDOG_MS)
+}
+
+/// Panic if any lock has been held for too long, called on each timer tick
+pub fn check_lock_watchdog() {
+    if let Some((site, held_ms)) = stuck_lock(get_time_ms()) {
+        panic!("lock acquired at {} has been held for {}ms", site, held_ms);
+    }
+}
+
+#[allow(unused)]
+/// Hold a lock past the limit and check that the watchdog finds it
+pub fn lock_watchdog_test() {
+    use super::UPSafeCell;
+    let cell = unsafe { UPSafeCell::new(0usize) };
+    let guard = cell.exclusive_access();
+    let line = line!() - 1;
+    let now = get_time_ms();
+    assert!(stuck_lock(now).is_none());
+    // 不必真的等待，把检查的时刻推迟到超时之后即可
+    let (site, held_ms) = stuck_lock(now + LOCK_WATCHDOG_MS + 1).unwrap();
+    assert_eq!((site.file(), site.line()), (file!(), line));
+    assert!(held_ms > LOCK_WATCHDOG_MS);
+    drop(guard);
+    assert!(stuck_lock(now + LOCK_WATCHDOG_MS + 1).is_none());
+    println!("lock_watchdog_test passed!");
+}
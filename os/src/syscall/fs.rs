@@ -1,8 +1,24 @@
 //! File and filesystem-related syscalls
-use crate::fs::{make_pipe, open_file, OpenFlags};
-use crate::mm::{translated_byte_buffer, translated_refmut, translated_str, UserBuffer};
-use crate::task::{current_task, current_user_token};
+use crate::config::{PAGE_SIZE, POLL_MAX_FDS};
+use crate::fs::{
+    block_cache_try_sync, canonicalize, flock_acquire, flock_release, fs_stat, make_pipe,
+    mkdir_path, mknod_file, open_file, open_inode, rename_file, rmdir_path, sync_fs, unlink_path,
+    Dirent, File, FsStat, IoCqe, IoRings, IoSqe, OpenFlags, PathError, PollFd, RingHeader,
+    SignalFd, Stat, Statx, TimerFd, IORING_OP_READ, IORING_OP_WRITE, IO_RING_MAX_ENTRIES, LOCK_EX,
+    LOCK_NB, LOCK_SH, LOCK_UN, POLLERR, POLLHUP, POLLIN, POLLNVAL, S_IFCHR, S_IFMT,
+};
+use crate::mm::{
+    copy_in_value, copy_out, copy_out_value, translated_byte_buffer, translated_str,
+    user_range_readable, IoVec, UserBuffer, VirtAddr,
+};
+use crate::task::{
+    block_for_io, current_has_pending_signal, current_task, current_unshare_zero_range,
+    current_user_token, cwd_in_use, end_current_io_wait, SignalFlags, TaskControlBlockInner,
+};
+use crate::timer::{add_timer, get_time_ms, remove_timer};
+use alloc::string::String;
 use alloc::sync::Arc;
+use alloc::vec::Vec;
 
 // 基于文件抽象接口和文件描述符表，我们可以按照无结构的字节流来处理基本的文件读写，这样可以让文件读写系统调用 sys_read/write 变得更加具有普适性
 pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> isize {
@@ -37,22 +53,488 @@ pub fn sys_read(fd: usize, buf: *const u8, len: usize) -> isize {
         if !file.readable() {
             return -1;
         }
+        let nonblock = inner.fd_nonblock.contains(&fd);
         // release current task TCB manually to avoid multi-borrow
         drop(inner);
-        file.read(UserBuffer::new(translated_byte_buffer(token, buf, len))) as isize
+        // 非阻塞的文件描述符上既没有数据、也没有到达文件末尾时不等待
+        if nonblock && file.poll() & (POLLIN | POLLHUP) == 0 {
+            return -EAGAIN;
+        }
+        current_unshare_zero_range(buf as usize, len);
+        let count = file.read(UserBuffer::new(translated_byte_buffer(token, buf, len)));
+        // 如果读的过程中等待过数据，接下来几轮调度优先运行当前任务
+        end_current_io_wait();
+        count as isize
+    } else {
+        -1
+    }
+}
+
+/// 路径中的某一级不存在
+const ENOENT: isize = 2;
+/// 系统调用被信号打断时返回 -EINTR
+const EINTR: isize = 4;
+/// 非阻塞的 read 没有数据可读时返回 -EAGAIN
+const EAGAIN: isize = 11;
+/// 完成队列已满、无法再执行任何请求时 io_submit 返回 -EBUSY
+const EBUSY: isize = 16;
+/// 要创建的路径已经存在
+const EEXIST: isize = 17;
+/// 路径中作为目录的某一级不是目录
+const ENOTDIR: isize = 20;
+/// 要删除的文件是一个目录
+const EISDIR: isize = 21;
+/// 没有空闲的索引节点
+const ENOSPC: isize = 28;
+/// 要删除的目录不是空的
+const ENOTEMPTY: isize = 39;
+
+fn path_errno(error: PathError) -> isize {
+    match error {
+        PathError::NotFound => -ENOENT,
+        PathError::NotDir => -ENOTDIR,
+        PathError::Exists => -EEXIST,
+        PathError::NoSpace => -ENOSPC,
+        PathError::IsDir => -EISDIR,
+        PathError::NotEmpty => -ENOTEMPTY,
+        PathError::Busy => -EBUSY,
+    }
+}
+
+/// 功能：与 read 相同，但在没有数据可读时最多等待 timeout_ms 毫秒。
+/// 参数：fd 为文件描述符，buf 和 len 描述应用地址空间中的缓冲区，timeout_ms 为最长等待时间（毫秒）。
+/// 返回值：实际读到的字节数，超时仍没有数据时返回 0 ；出现错误时返回 -1 ；
+/// 等待期间收到信号时返回 -EINTR 。
+/// syscall ID：2000
+pub fn sys_read_timeout(fd: usize, buf: *const u8, len: usize, timeout_ms: usize) -> isize {
+    let token = current_user_token();
+    let task = current_task().unwrap();
+    let inner = task.inner_exclusive_access();
+    if fd >= inner.fd_table.len() {
+        return -1;
+    }
+    if let Some(file) = &inner.fd_table[fd] {
+        let file = file.clone();
+        if !file.readable() {
+            return -1;
+        }
+        // release current task TCB manually to avoid multi-borrow
+        drop(inner);
+        current_unshare_zero_range(buf as usize, len);
+        let count = file.read_timeout(
+            UserBuffer::new(translated_byte_buffer(token, buf, len)),
+            timeout_ms,
+        );
+        end_current_io_wait();
+        match count {
+            Some(count) => count as isize,
+            None => -EINTR,
+        }
     } else {
         -1
     }
 }
 
+/// 一次定位读写最多的缓冲区个数
+const IOV_MAX: usize = 1024;
+
+// 把应用地址空间中的 iovcnt 个缓冲区依次拼接成一个 UserBuffer ，读操作需要先让这些缓冲区不再共享零页
+fn iovec_buffer(token: usize, iov: *const IoVec, iovcnt: usize, reading: bool) -> UserBuffer {
+    let mut buffers = Vec::new();
+    for i in 0..iovcnt {
+        let iovec = copy_in_value(token, unsafe { iov.add(i) });
+        if reading {
+            current_unshare_zero_range(iovec.base, iovec.len);
+        }
+        buffers.extend(translated_byte_buffer(
+            token,
+            iovec.base as *const u8,
+            iovec.len,
+        ));
+    }
+    UserBuffer::new(buffers)
+}
+
+/// 功能：从文件的 offset 处开始依次读满 iov 描述的各个缓冲区，不改变文件描述符的偏移量。
+/// 参数：fd 为文件描述符，iov 指向 iovcnt 个 IoVec 组成的数组，offset 为开始读的位置。
+/// 返回值：实际读到的字节数，到达文件末尾时可能少于缓冲区的总长度；
+/// fd 不合法、不可读或者不支持定位读写（设备和管道），以及 iovcnt 超过上限时返回 -1 。
+/// syscall ID：69
+pub fn sys_preadv(fd: usize, iov: *const IoVec, iovcnt: usize, offset: usize) -> isize {
+    let token = current_user_token();
+    let task = current_task().unwrap();
+    let inner = task.inner_exclusive_access();
+    if fd >= inner.fd_table.len() || iovcnt > IOV_MAX {
+        return -1;
+    }
+    if let Some(file) = &inner.fd_table[fd] {
+        let file = file.clone();
+        if !file.readable() {
+            return -1;
+        }
+        // release current task TCB manually to avoid multi-borrow
+        drop(inner);
+        match file.read_at(offset, iovec_buffer(token, iov, iovcnt, true)) {
+            Some(count) => count as isize,
+            None => -1,
+        }
+    } else {
+        -1
+    }
+}
+
+/// 功能：把 iov 描述的各个缓冲区依次写入文件的 offset 处，不改变文件描述符的偏移量。
+/// 参数：fd 为文件描述符，iov 指向 iovcnt 个 IoVec 组成的数组，offset 为开始写的位置，超出文件末尾时文件随之扩展。
+/// 返回值：实际写入的字节数；fd 不合法、不可写或者不支持定位读写（设备和管道），iovcnt 超过上限，
+/// 以及写入的范围超出最大文件大小时返回 -1 。
+/// syscall ID：70
+pub fn sys_pwritev(fd: usize, iov: *const IoVec, iovcnt: usize, offset: usize) -> isize {
+    let token = current_user_token();
+    let task = current_task().unwrap();
+    let inner = task.inner_exclusive_access();
+    if fd >= inner.fd_table.len() || iovcnt > IOV_MAX {
+        return -1;
+    }
+    if let Some(file) = &inner.fd_table[fd] {
+        if !file.writable() {
+            return -1;
+        }
+        let file = file.clone();
+        // release current task TCB manually to avoid multi-borrow
+        drop(inner);
+        match file.write_at(offset, iovec_buffer(token, iov, iovcnt, false)) {
+            Some(count) => count as isize,
+            None => -1,
+        }
+    } else {
+        -1
+    }
+}
+
+/// 功能：在内核中把 in_fd 从 in_off 处开始的最多 len 字节复制到 out_fd 的 out_off 处，数据不经过应用的缓冲区。
+/// 参数：in_off 和 out_off 为应用地址空间中保存偏移量的地址，复制之后偏移量增加实际复制的字节数，文件描述符的偏移量不变；
+/// 为 0 时改为使用并移动文件描述符自己的偏移量。两个文件都必须是常规文件。
+/// 返回值：实际复制的字节数，源文件提前到达末尾时少于 len ，偏移量已经位于源文件末尾时返回 0 ；
+/// 如果出现了错误则返回 -1 。可能的错误原因是：文件描述符不合法，in_fd 不可读或者 out_fd 不可写，
+/// 任一方是目录、设备或者管道，两个区间位于同一个文件中并且相互重叠，目标区间超出最大文件大小。
+/// syscall ID：285
+pub fn sys_copy_file_range(
+    in_fd: usize,
+    in_off: *mut usize,
+    out_fd: usize,
+    out_off: *mut usize,
+    len: usize,
+) -> isize {
+    let token = current_user_token();
+    let task = current_task().unwrap();
+    let inner = task.inner_exclusive_access();
+    let file = |fd: usize| inner.fd_table.get(fd).cloned().flatten();
+    let (Some(in_file), Some(out_file)) = (file(in_fd), file(out_fd)) else {
+        return -1;
+    };
+    // release current task TCB manually to avoid multi-borrow
+    drop(inner);
+    if !in_file.readable() || !out_file.writable() {
+        return -1;
+    }
+    let (Some(in_inode), Some(out_inode)) = (in_file.os_inode(), out_file.os_inode()) else {
+        return -1;
+    };
+    let offset = (!in_off.is_null()).then(|| copy_in_value(token, in_off));
+    let out_offset = (!out_off.is_null()).then(|| copy_in_value(token, out_off));
+    let Some(copied) = in_inode.copy_range(offset, out_inode, out_offset, len) else {
+        return -1;
+    };
+    if let Some(offset) = offset {
+        current_unshare_zero_range(in_off as usize, core::mem::size_of::<usize>());
+        copy_out_value(token, in_off, &(offset + copied));
+    }
+    if let Some(out_offset) = out_offset {
+        current_unshare_zero_range(out_off as usize, core::mem::size_of::<usize>());
+        copy_out_value(token, out_off, &(out_offset + copied));
+    }
+    copied as isize
+}
+
+/// 功能：在应用缓冲区和管道之间移动数据。fd 为写端时把缓冲区中的数据移入管道，为读端时从管道中读出数据填满缓冲区。
+/// 缓冲区中按页对齐的整页如果位于 mmap 得到的可写内存中，就直接移交它的物理页帧而不复制：
+/// 移入管道之后这个页面的内容变回全零，读出时这个页面直接映射到管道中的页帧上。其余部分按照 write/read 的方式复制。
+/// 与 read/write 一样，等待期间被 SIGKILL 终止或者移入时所有读端都已关闭则提前返回。
+/// 参数：fd 为管道的文件描述符，buf 和 len 描述应用地址空间中的缓冲区。
+/// 返回值：实际移动的字节数；fd 不合法时返回 -1 。
+/// syscall ID：75
+pub fn sys_vmsplice(fd: usize, buf: usize, len: usize) -> isize {
+    let token = current_user_token();
+    let task = current_task().unwrap();
+    let inner = task.inner_exclusive_access();
+    let file = match inner.fd_table.get(fd) {
+        Some(Some(file)) => file.clone(),
+        _ => return -1,
+    };
+    drop(inner);
+    let writing = file.writable();
+    if !writing && !file.readable() {
+        return -1;
+    }
+    let end = buf.saturating_add(len);
+    let mut pos = buf;
+    while pos < end {
+        let chunk_end = ((pos / PAGE_SIZE + 1) * PAGE_SIZE).min(end);
+        let chunk_len = chunk_end - pos;
+        if chunk_len == PAGE_SIZE {
+            let vpn = VirtAddr::from(pos).floor();
+            if writing {
+                let frame = task.inner_exclusive_access().memory_set.take_page(vpn);
+                if let Some(frame) = frame {
+                    match file.push_page(frame) {
+                        Ok(()) => {
+                            pos = chunk_end;
+                            continue;
+                        }
+                        // 不接受页帧的文件则把页帧还回去，然后复制
+                        Err(frame) => {
+                            let mut inner = task.inner_exclusive_access();
+                            assert!(inner.memory_set.give_page(vpn, frame).is_ok());
+                        }
+                    }
+                }
+            } else if let Some(frame) = file.pop_page() {
+                let given = task
+                    .inner_exclusive_access()
+                    .memory_set
+                    .give_page(vpn, frame);
+                // 不能直接映射的页面只能把页帧中的数据复制过去
+                if let Err(frame) = given {
+                    current_unshare_zero_range(pos, PAGE_SIZE);
+                    UserBuffer::new(translated_byte_buffer(token, pos as *const u8, PAGE_SIZE))
+                        .write_at(0, frame.ppn.get_bytes_array());
+                }
+                pos = chunk_end;
+                continue;
+            }
+        }
+        // 读出时必须先换掉共享零页再翻译地址，否则数据会写进所有按需清零页面共享的零页帧中
+        if !writing {
+            current_unshare_zero_range(pos, chunk_len);
+        }
+        let user_buf = UserBuffer::new(translated_byte_buffer(token, pos as *const u8, chunk_len));
+        let count = if writing {
+            file.write(user_buf)
+        } else {
+            file.read(user_buf)
+        };
+        pos += count;
+        if count < chunk_len {
+            break;
+        }
+    }
+    if !writing {
+        end_current_io_wait();
+    }
+    (pos - buf) as isize
+}
+
+/// 功能：等待一组文件描述符中的任意一个就绪。等待期间进程阻塞在这些文件的等待队列和定时器队列上，不会忙等。
+/// 参数：fds 指向 nfds 个 PollFd 组成的数组，其中 fd 为负数的项被忽略；
+/// timeout_ms 为最长等待时间（毫秒），为 0 时立即返回，为负数时一直等待。
+/// 返回值：revents 不为 0 的项数，超时时返回 0 ；等待期间收到信号时返回 -EINTR ；
+/// nfds 超过 POLL_MAX_FDS 或者 fds 数组没有完整地映射在用户地址空间中时返回 -1 。
+/// 每一项的 revents 被设置为已经就绪的事件，POLLHUP/POLLERR/POLLNVAL 无论是否在 events 中都会报告。
+/// syscall ID：2002
+pub fn sys_poll(fds: *mut PollFd, nfds: usize, timeout_ms: isize) -> isize {
+    let token = current_user_token();
+    let task = current_task().unwrap();
+    if nfds > POLL_MAX_FDS
+        || !user_range_readable(token, fds as usize, nfds * core::mem::size_of::<PollFd>())
+    {
+        return -1;
+    }
+    let mut pollfds: Vec<PollFd> = (0..nfds)
+        .map(|i| copy_in_value(token, unsafe { fds.add(i) } as *const PollFd))
+        .collect();
+    // 不存在的文件描述符对应 None ，总是报告 POLLNVAL
+    let inner = task.inner_exclusive_access();
+    let files: Vec<Option<Arc<dyn File + Send + Sync>>> = pollfds
+        .iter()
+        .map(|pollfd| match inner.fd_table.get(pollfd.fd as usize) {
+            Some(Some(file)) => Some(file.clone()),
+            _ => None,
+        })
+        .collect();
+    drop(inner);
+    let deadline = (timeout_ms >= 0).then(|| get_time_ms() + timeout_ms as usize);
+    let ready = loop {
+        let mut ready = 0;
+        for (pollfd, file) in pollfds.iter_mut().zip(files.iter()) {
+            pollfd.revents = if pollfd.fd < 0 {
+                0
+            } else {
+                match file {
+                    Some(file) => file.poll() & (pollfd.events | POLLHUP | POLLERR),
+                    None => POLLNVAL,
+                }
+            };
+            if pollfd.revents != 0 {
+                ready += 1;
+            }
+        }
+        if ready > 0 || deadline.map_or(false, |deadline| get_time_ms() >= deadline) {
+            break ready;
+        }
+        if current_has_pending_signal() {
+            return -EINTR;
+        }
+        // 先登记到所有文件的等待队列上再阻塞，文件状态变化、超时或者收到信号时被唤醒
+        for file in files.iter().flatten() {
+            file.watch(&task);
+        }
+        if let Some(deadline) = deadline {
+            add_timer(deadline, task.clone());
+        }
+        block_for_io();
+        remove_timer(&task);
+        for file in files.iter().flatten() {
+            file.unwatch(&task);
+        }
+    };
+    end_current_io_wait();
+    current_unshare_zero_range(fds as usize, nfds * core::mem::size_of::<PollFd>());
+    for (i, pollfd) in pollfds.iter().enumerate() {
+        copy_out_value(token, unsafe { fds.add(i) }, pollfd);
+    }
+    ready
+}
+
+/// 功能：登记当前进程用于批量读写的提交队列和完成队列，它们位于进程自己的地址空间中。
+/// 每个队列由 head 、tail 两个计数和紧随其后的 entries 个表项组成。
+/// 参数：sq 和 cq 分别为提交队列和完成队列的地址，entries 为每个队列的表项数，为 0 时取消登记。
+/// 返回值：成功返回 0 ；entries 超过上限时返回 -1 。
+/// syscall ID：2003
+pub fn sys_io_setup(sq: usize, cq: usize, entries: usize) -> isize {
+    if entries > IO_RING_MAX_ENTRIES {
+        return -1;
+    }
+    let task = current_task().unwrap();
+    task.inner_exclusive_access().io_rings = (entries > 0).then_some(IoRings { sq, cq, entries });
+    0
+}
+
+/// 功能：依次执行提交队列中所有的读写请求，每个请求执行完毕之后把结果放入完成队列。
+/// 完成队列已满时停止执行，剩下的请求留在提交队列中，进程取走一些结果之后可以再次提交。
+/// 返回值：执行了的请求数；没有登记队列或者队列被破坏时返回 -1 ；
+/// 提交队列不为空但完成队列已满时返回 -EBUSY 。
+/// syscall ID：2004
+pub fn sys_io_submit() -> isize {
+    let token = current_user_token();
+    let task = current_task().unwrap();
+    let rings = match task.inner_exclusive_access().io_rings {
+        Some(rings) => rings,
+        None => return -1,
+    };
+    let sq_header = rings.sq as *mut RingHeader;
+    let cq_header = rings.cq as *mut RingHeader;
+    current_unshare_zero_range(rings.sq, core::mem::size_of::<RingHeader>());
+    current_unshare_zero_range(rings.cq, rings.cq_size());
+    let mut submitted = 0;
+    loop {
+        let mut sq = copy_in_value(token, sq_header as *const RingHeader);
+        let mut cq = copy_in_value(token, cq_header as *const RingHeader);
+        let pending = sq.tail.wrapping_sub(sq.head);
+        let completed = cq.tail.wrapping_sub(cq.head);
+        if pending > rings.entries || completed > rings.entries {
+            return -1;
+        }
+        if pending == 0 {
+            break;
+        }
+        // 先确认有地方存放结果再执行请求，避免执行了请求却丢掉结果
+        if completed == rings.entries {
+            if submitted == 0 {
+                return -EBUSY;
+            }
+            break;
+        }
+        let sqe = copy_in_value(token, rings.sqe(sq.head) as *const IoSqe);
+        sq.head = sq.head.wrapping_add(1);
+        copy_out_value(token, sq_header, &sq);
+        // 与单独调用 read/write 完全相同，读写管道时同样可能等待
+        let fd = sqe.fd as usize;
+        let result = match sqe.opcode {
+            IORING_OP_READ => sys_read(fd, sqe.buf as *const u8, sqe.len),
+            IORING_OP_WRITE => sys_write(fd, sqe.buf as *const u8, sqe.len),
+            _ => -1,
+        };
+        let cqe = IoCqe {
+            user_data: sqe.user_data,
+            result,
+        };
+        // 等待期间进程的其他部分不会运行，完成队列的 head 不会改变，这里的 cq 仍然有效
+        copy_out_value(token, rings.cqe(cq.tail) as *mut IoCqe, &cqe);
+        cq.tail = cq.tail.wrapping_add(1);
+        copy_out_value(token, cq_header, &cq);
+        submitted += 1;
+    }
+    submitted
+}
+
 pub fn sys_open(path: *const u8, flags: u32) -> isize {
     let task = current_task().unwrap();
     let token = current_user_token();
-    let path = translated_str(token, path);
-    if let Some(inode) = open_file(path.as_str(), OpenFlags::from_bits(flags).unwrap()) {
+    let path = canonicalize(
+        &translated_str(token, path),
+        &task.inner_exclusive_access().cwd,
+    );
+    open_path(path.as_str(), flags)
+}
+
+/// dirfd 取这个值时，openat 和 fstatat 的相对路径基于当前的工作目录解析
+const AT_FDCWD: isize = -100;
+
+// openat 和 fstatat 共用的部分：绝对路径忽略 dirfd ，相对路径基于 dirfd 指向的目录解析，dirfd 为 AT_FDCWD 时基于当前的工作目录。
+// 与 fchdir 一样，目录的路径在打开时就已经确定
+fn resolve_at(dirfd: isize, path: *const u8) -> Option<String> {
+    let task = current_task().unwrap();
+    let path = translated_str(current_user_token(), path);
+    if path.starts_with('/') {
+        return Some(canonicalize(&path, "/"));
+    }
+    let base = if dirfd == AT_FDCWD {
+        task.inner_exclusive_access().cwd.clone()
+    } else {
+        let inner = task.inner_exclusive_access();
+        let file = inner.fd_table.get(dirfd as usize)?.clone()?;
+        // release current task TCB manually to avoid multi-borrow
+        drop(inner);
+        file.dir_path()?
+    };
+    Some(canonicalize(&path, &base))
+}
+
+/// 功能：与 open 相同，但相对路径基于已打开的目录 dirfd 解析，不必拼出完整的路径。
+/// 参数：dirfd 为已打开的目录的文件描述符，为 AT_FDCWD（-100）时基于当前的工作目录；path 为绝对路径时忽略 dirfd ；
+/// flags 与 open 相同。
+/// 返回值：如果出现了错误则返回 -1，否则返回新的文件描述符。可能的错误原因是：dirfd 不合法或者不是目录，文件不存在。
+/// syscall ID：2013
+pub fn sys_openat(dirfd: isize, path: *const u8, flags: u32) -> isize {
+    match resolve_at(dirfd, path) {
+        Some(path) => open_path(path.as_str(), flags),
+        None => -1,
+    }
+}
+
+// 打开规范化之后的路径并分配文件描述符
+fn open_path(path: &str, flags: u32) -> isize {
+    let task = current_task().unwrap();
+    if let Some(inode) = open_file(path, OpenFlags::from_bits(flags).unwrap()) {
         let mut inner = task.inner_exclusive_access();
         let fd = inner.alloc_fd();
         inner.fd_table[fd] = Some(inode);
+        if flags & OpenFlags::CLOEXEC.bits() != 0 {
+            inner.fd_cloexec.insert(fd);
+        }
+        if flags & OpenFlags::NONBLOCK.bits() != 0 {
+            inner.fd_nonblock.insert(fd);
+        }
         fd as isize
     } else {
         -1
@@ -69,9 +551,43 @@ pub fn sys_close(fd: usize) -> isize {
         return -1;
     }
     inner.fd_table[fd].take();
+    inner.fd_cloexec.remove(&fd);
+    inner.fd_nonblock.remove(&fd);
     0
 }
 
+const F_GETFL: usize = 3;
+const F_SETFL: usize = 4;
+
+/// 功能：读取或修改文件描述符的状态标志，目前只支持 OpenFlags::NONBLOCK 。
+/// 与 Linux 不同，这个标志属于文件描述符而不是打开的文件，dup 得到的文件描述符不会继承它；
+/// 而且它只保证没有任何数据可读时 read 不等待，管道中已有的数据不够填满缓冲区时 read 仍会等待写者。
+/// 参数：fd 为文件描述符；cmd 为 F_GETFL 时读取状态标志，为 F_SETFL 时把状态标志设为 arg 。
+/// 返回值：F_GETFL 返回状态标志，F_SETFL 成功时返回 0 ；fd 不合法、cmd 不支持或者 arg 中有其他标志时返回 -1 。
+/// syscall ID：25
+pub fn sys_fcntl(fd: usize, cmd: usize, arg: usize) -> isize {
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    if !matches!(inner.fd_table.get(fd), Some(Some(_))) {
+        return -1;
+    }
+    let nonblock = OpenFlags::NONBLOCK.bits() as usize;
+    match cmd {
+        F_GETFL if inner.fd_nonblock.contains(&fd) => nonblock as isize,
+        F_GETFL => 0,
+        F_SETFL if arg & !nonblock != 0 => -1,
+        F_SETFL => {
+            if arg & nonblock != 0 {
+                inner.fd_nonblock.insert(fd);
+            } else {
+                inner.fd_nonblock.remove(&fd);
+            }
+            0
+        }
+        _ => -1,
+    }
+}
+
 /// 功能：为当前进程打开一个管道。
 /// 参数：pipe 表示应用地址空间中的一个长度为 2 的 usize 数组的起始地址，内核需要按顺序将管道读端
 /// 和写端的文件描述符写入到数组中。
@@ -88,11 +604,58 @@ pub fn sys_pipe(pipe: *mut usize) -> isize {
     let write_fd = inner.alloc_fd();
     inner.fd_table[write_fd] = Some(pipe_write);
     // 将读端和写端的文件描述符写回到应用地址空间
-    *translated_refmut(token, pipe) = read_fd;
-    *translated_refmut(token, unsafe { pipe.add(1) }) = write_fd;
+    inner
+        .memory_set
+        .unshare_zero_range(pipe as usize, 2 * core::mem::size_of::<usize>());
+    copy_out_value(token, pipe as *mut [usize; 2], &[read_fd, write_fd]);
     0
 }
 
+/// 功能：创建一个定时器文件，启动之后每次到期都会使它变为可读，可以和其他文件一起用 sys_poll 等待。
+/// 返回值：定时器文件的文件描述符。新创建的定时器没有启动。
+/// syscall ID：85
+pub fn sys_timerfd_create() -> isize {
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    let fd = inner.alloc_fd();
+    inner.fd_table[fd] = Some(Arc::new(TimerFd::new()));
+    fd as isize
+}
+
+/// 功能：启动或者停止一个定时器文件，之前累计的到期次数被清零。
+/// 参数：fd 为定时器文件的文件描述符；value_ms 为距离第一次到期的时间（毫秒），为 0 时停止定时器；
+/// interval_ms 为之后每次到期的间隔（毫秒），为 0 时只到期一次。
+/// 读定时器文件时得到一个 u64 ，表示上一次读之后到期的次数，还没有到期时阻塞。
+/// 返回值：成功返回 0 ；fd 不存在或者不是定时器文件时返回 -1 。
+/// syscall ID：86
+pub fn sys_timerfd_settime(fd: usize, interval_ms: usize, value_ms: usize) -> isize {
+    let task = current_task().unwrap();
+    let inner = task.inner_exclusive_access();
+    match inner.fd_table.get(fd) {
+        Some(Some(file)) if file.settime(interval_ms, value_ms) => 0,
+        _ => -1,
+    }
+}
+
+/// 功能：创建一个 signalfd ，通过读它来同步地接收当前进程的信号。
+/// 只要这个文件还打开着，mask 中的信号就不再调用处理例程或者按照默认方式处理，而是留着等待读取，
+/// 有这样的信号时文件可读，可以和其他文件一起用 sys_poll 等待。SIGKILL 和 SIGSTOP 总是按照通常的方式处理。
+/// 读到的每条记录是一个 u32 的信号编号，读出的信号从进程收到的信号中移除。
+/// 参数：mask 为关注的信号集合，与 sigprocmask 的参数格式相同。
+/// 返回值：成功返回文件描述符；mask 不合法时返回 -1 。
+/// syscall ID：74
+pub fn sys_signalfd(mask: u32) -> isize {
+    let Some(mask) = SignalFlags::from_bits(mask) else {
+        return -1;
+    };
+    let mask = mask - (SignalFlags::SIGKILL | SignalFlags::SIGSTOP);
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    let fd = inner.alloc_fd();
+    inner.fd_table[fd] = Some(Arc::new(SignalFd::new(mask)));
+    fd as isize
+}
+
 pub fn sys_dup(fd: usize) -> isize {
     let task = current_task().unwrap();
     let mut inner = task.inner_exclusive_access();
@@ -109,3 +672,570 @@ pub fn sys_dup(fd: usize) -> isize {
     inner.fd_table[new_fd] = Some(Arc::clone(inner.fd_table[fd].as_ref().unwrap()));
     new_fd as isize
 }
+
+/// 文件描述符的上限，用来限制 dup2/dup3 可以指定的目标文件描述符
+const MAX_FD: usize = 256;
+
+// dup2 和 dup3 共用的复制逻辑：让 new_fd 指向 old_fd 对应的已打开文件，如果 new_fd 此前已经打开则先将其关闭，
+// 并根据 cloexec 设置 new_fd 的 close-on-exec 标志。old_fd 不合法或 new_fd 超出上限时返回 -1
+fn dup_to(inner: &mut TaskControlBlockInner, old_fd: usize, new_fd: usize, cloexec: bool) -> isize {
+    if old_fd >= inner.fd_table.len() || inner.fd_table[old_fd].is_none() || new_fd >= MAX_FD {
+        return -1;
+    }
+    let file = Arc::clone(inner.fd_table[old_fd].as_ref().unwrap());
+    while inner.fd_table.len() <= new_fd {
+        inner.fd_table.push(None);
+    }
+    inner.fd_table[new_fd] = Some(file);
+    inner.fd_nonblock.remove(&new_fd);
+    if cloexec {
+        inner.fd_cloexec.insert(new_fd);
+    } else {
+        inner.fd_cloexec.remove(&new_fd);
+    }
+    new_fd as isize
+}
+
+/// 功能：将进程中一个已经打开的文件复制到指定的文件描述符 new_fd 上，如果 new_fd 已经打开则先将其关闭。
+/// 参数：old_fd 为要复制的文件描述符，new_fd 为目标文件描述符。
+/// 返回值：如果出现了错误则返回 -1，否则返回 new_fd 。可能的错误原因是：old_fd 不是一个已打开的文件描述符，或者 new_fd 超出上限。
+/// 当 old_fd 与 new_fd 相同时不做任何操作。
+/// syscall ID：23
+pub fn sys_dup2(old_fd: usize, new_fd: usize) -> isize {
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    if old_fd == new_fd {
+        if old_fd >= inner.fd_table.len() || inner.fd_table[old_fd].is_none() {
+            return -1;
+        }
+        return new_fd as isize;
+    }
+    dup_to(&mut inner, old_fd, new_fd, false)
+}
+
+/// 功能：与 dup2 相同，但可以通过 flags 原子地为 new_fd 设置 close-on-exec 标志。
+/// 参数：old_fd 为要复制的文件描述符，new_fd 为目标文件描述符，flags 只能为 0 或 OpenFlags::CLOEXEC 。
+/// 返回值：如果出现了错误则返回 -1，否则返回 new_fd 。可能的错误原因是：old_fd 不是一个已打开的文件描述符，
+/// new_fd 超出上限，flags 不合法，或者 old_fd 与 new_fd 相同。
+/// syscall ID：26
+pub fn sys_dup3(old_fd: usize, new_fd: usize, flags: u32) -> isize {
+    if old_fd == new_fd || flags & !OpenFlags::CLOEXEC.bits() != 0 {
+        return -1;
+    }
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    dup_to(&mut inner, old_fd, new_fd, flags != 0)
+}
+
+/// 功能：将一个已打开的文件截断或扩展到 len 字节，扩展的部分读出来都是 0 。
+/// 参数：fd 为以可写方式打开的文件的文件描述符，len 为新的文件长度。
+/// 返回值：如果出现了错误则返回 -1，否则返回 0 。可能的错误原因是：fd 不合法，文件不可写或者不是文件系统中的文件，
+/// len 超出了文件长度的上限。
+/// syscall ID：46
+pub fn sys_ftruncate(fd: usize, len: usize) -> isize {
+    let task = current_task().unwrap();
+    let inner = task.inner_exclusive_access();
+    if fd >= inner.fd_table.len() {
+        return -1;
+    }
+    if let Some(file) = &inner.fd_table[fd] {
+        let file = file.clone();
+        // release current task TCB manually to avoid multi-borrow
+        drop(inner);
+        truncate_file(file, len)
+    } else {
+        -1
+    }
+}
+
+/// 功能：与 ftruncate 相同，但通过路径指定文件，不需要事先打开它。
+/// 参数：path 为文件路径，len 为新的文件长度。
+/// 返回值：如果出现了错误则返回 -1，否则返回 0 。可能的错误原因是：文件不存在或者是一个目录，len 超出了文件长度的上限。
+/// syscall ID：45
+pub fn sys_truncate(path: *const u8, len: usize) -> isize {
+    let task = current_task().unwrap();
+    let token = current_user_token();
+    let path = canonicalize(
+        &translated_str(token, path),
+        &task.inner_exclusive_access().cwd,
+    );
+    // 以可写的方式临时打开文件，目录无法被这样打开
+    match open_inode(path.as_str(), OpenFlags::WRONLY) {
+        Some(inode) => truncate_file(inode, len),
+        None => -1,
+    }
+}
+
+/// 功能：创建一个设备文件，打开它时得到的是设备号为 dev 的设备。
+/// 参数：path 为设备文件的路径；mode 的文件类型位必须是 S_IFCHR ，低 12 位为权限位；dev 为设备号，高 8 位是主设备号，低 8 位是次设备号。
+/// 返回值：如果出现了错误则返回 -1，否则返回 0 。可能的错误原因是：当前进程不是特权用户（用户号不为 0），
+/// 文件类型不是字符设备，路径已经存在，没有空闲的索引节点。
+/// syscall ID：33
+pub fn sys_mknod(path: *const u8, mode: u32, dev: u32) -> isize {
+    let task = current_task().unwrap();
+    let token = current_user_token();
+    let path = canonicalize(
+        &translated_str(token, path),
+        &task.inner_exclusive_access().cwd,
+    );
+    // 设备文件绕过了文件的权限位，只有特权用户才能创建
+    if task.inner_exclusive_access().uid != 0 {
+        return -1;
+    }
+    if mode & S_IFMT != S_IFCHR || !mknod_file(path.as_str(), mode & !S_IFMT, dev) {
+        return -1;
+    }
+    0
+}
+
+/// 功能：创建一个空目录，它所在的目录必须已经存在。
+/// 参数：path 为目录的路径，相对路径基于当前的工作目录解析；mode 的低 12 位为权限位。
+/// 返回值：成功返回 0 ；路径中的某一级不存在时返回 -ENOENT ，某一级不是目录时返回 -ENOTDIR ，
+/// 路径已经存在（无论是不是目录）时返回 -EEXIST ，没有空闲的索引节点时返回 -ENOSPC 。
+/// syscall ID：2011
+pub fn sys_mkdir(path: *const u8, mode: u32) -> isize {
+    let task = current_task().unwrap();
+    let token = current_user_token();
+    let path = canonicalize(
+        &translated_str(token, path),
+        &task.inner_exclusive_access().cwd,
+    );
+    match mkdir_path(path.as_str(), mode & !S_IFMT) {
+        Ok(()) => 0,
+        Err(error) => path_errno(error),
+    }
+}
+
+/// 功能：删除文件 path ，它不能是目录。文件如果仍被打开着，已经打开它的文件描述符仍然可以读写，
+/// 直到最后一个文件描述符被关闭时它才会被回收。
+/// 参数：path 为文件的路径，相对路径基于当前的工作目录解析。
+/// 返回值：成功返回 0 ；路径中的某一级不存在时返回 -ENOENT ，某一级不是目录时返回 -ENOTDIR ，
+/// path 是目录时返回 -EISDIR 。
+/// syscall ID：35
+pub fn sys_unlink(path: *const u8) -> isize {
+    let task = current_task().unwrap();
+    let token = current_user_token();
+    let path = canonicalize(
+        &translated_str(token, path),
+        &task.inner_exclusive_access().cwd,
+    );
+    match unlink_path(path.as_str()) {
+        Ok(()) => 0,
+        Err(error) => path_errno(error),
+    }
+}
+
+/// 功能：删除空目录 path 。
+/// 参数：path 为目录的路径，相对路径基于当前的工作目录解析。
+/// 返回值：成功返回 0 ；路径中的某一级不存在时返回 -ENOENT ，某一级或者 path 本身不是目录时返回 -ENOTDIR ，
+/// 目录不是空的时返回 -ENOTEMPTY ，path 是根目录或者某个进程（包括调用者自己）的工作目录时返回 -EBUSY 。
+/// syscall ID：2012
+pub fn sys_rmdir(path: *const u8) -> isize {
+    let task = current_task().unwrap();
+    let token = current_user_token();
+    let path = canonicalize(
+        &translated_str(token, path),
+        &task.inner_exclusive_access().cwd,
+    );
+    // 工作目录以路径的形式记录，目录被删除之后这些进程就无法再解析相对路径
+    if cwd_in_use(path.as_str()) {
+        return -EBUSY;
+    }
+    match rmdir_path(path.as_str()) {
+        Ok(()) => 0,
+        Err(error) => path_errno(error),
+    }
+}
+
+/// 功能：将文件 old 重命名为 new ，如果 new 已经存在则将其替换。替换是原子的：同时打开 new 的进程要么打开原来的文件，要么打开重命名后的文件。
+/// 被替换的文件如果仍被打开着，已经打开它的文件描述符仍然可以读写原来的内容，直到最后一个文件描述符被关闭时它才会被回收。
+/// 参数：old 和 new 分别为原来的路径和新的路径，相对路径基于当前的工作目录解析。
+/// 返回值：如果出现了错误则返回 -1，否则返回 0 。可能的错误原因是：old 不存在，old 和 new 不在同一个目录中，new 是一个目录。
+/// syscall ID：38
+pub fn sys_rename(old: *const u8, new: *const u8) -> isize {
+    let task = current_task().unwrap();
+    let token = current_user_token();
+    let cwd = task.inner_exclusive_access().cwd.clone();
+    let old = canonicalize(&translated_str(token, old), &cwd);
+    let new = canonicalize(&translated_str(token, new), &cwd);
+    if rename_file(old.as_str(), new.as_str()) {
+        0
+    } else {
+        -1
+    }
+}
+
+/// 功能：获取路径所在的文件系统的使用情况。
+/// 参数：path 为文件系统中的任意一个文件或目录的路径，buf 为保存结果的 FsStat 结构体的地址。
+/// 返回值：如果出现了错误则返回 -1，否则返回 0 。可能的错误原因是：路径不存在。
+/// syscall ID：43
+pub fn sys_statfs(path: *const u8, buf: *mut FsStat) -> isize {
+    let task = current_task().unwrap();
+    let token = current_user_token();
+    let path = canonicalize(
+        &translated_str(token, path),
+        &task.inner_exclusive_access().cwd,
+    );
+    // 目前只有一个文件系统，只需要确认路径存在
+    if path != "/" && open_inode(path.as_str(), OpenFlags::RDONLY).is_none() {
+        return -1;
+    }
+    current_unshare_zero_range(buf as usize, core::mem::size_of::<FsStat>());
+    copy_out_value(token, buf, &fs_stat());
+    0
+}
+
+/// 功能：将所有文件系统在块缓存中被修改过的块写回块设备，并要求设备将它们写入存储介质。
+/// 正被其他操作锁住的块会被跳过，它们在之后的同步或者被替换出去时写回，因此不会等待正在进行的 I/O 。
+/// 返回值：如果出现了错误则返回 -1，否则返回 0 。可能的错误原因是：块设备无法 flush ，
+/// 或者自上一次 sync/syncfs 以来块设备有过一次 flush 失败。
+/// syscall ID：81
+pub fn sys_sync() -> isize {
+    match block_cache_try_sync(None) {
+        Some(_) => 0,
+        None => -1,
+    }
+}
+
+/// 功能：与 sync 相同，但只写回已打开文件 fd 所在的文件系统的块。
+/// 参数：fd 为文件描述符。
+/// 返回值：如果出现了错误则返回 -1，否则返回 0 。可能的错误原因是：fd 不合法或者不是文件系统中的文件，
+/// 块设备无法 flush 或者自上一次 sync/syncfs 以来有过一次 flush 失败。
+/// syscall ID：267
+pub fn sys_syncfs(fd: usize) -> isize {
+    let task = current_task().unwrap();
+    let inner = task.inner_exclusive_access();
+    let file = match inner.fd_table.get(fd) {
+        Some(Some(file)) => file.clone(),
+        _ => return -1,
+    };
+    drop(inner);
+    // 目前只有一个文件系统，文件系统中的文件都有索引节点编号
+    if file.inode_id().is_none() {
+        return -1;
+    }
+    match sync_fs() {
+        Some(_) => 0,
+        None => -1,
+    }
+}
+
+/// 功能：模拟断电：丢弃块缓存中所有还没有写回的块，然后立即关机，用于测试文件系统在崩溃之后的一致性。
+/// 只有以 crash_test 特性编译的内核才提供这个系统调用。
+/// 返回值：不会返回。
+/// syscall ID：2014
+#[cfg(feature = "crash_test")]
+pub fn sys_crash() -> isize {
+    println!("[kernel] Crash injected, unsynced blocks are lost");
+    crate::fs::block_cache_discard_all();
+    crate::sbi::shutdown(false)
+}
+
+/// 功能：向内核声明应用将以何种模式访问一个已打开文件中的一段内容，内核据此调整预读和块缓存的使用。
+/// 参数：fd 为文件描述符；offset 和 len 给出文件中的范围，len 为 0 表示一直到文件末尾；
+/// advice 为 POSIX_FADV_NORMAL（只在检测到顺序读时预读）、POSIX_FADV_RANDOM（不预读）、
+/// POSIX_FADV_SEQUENTIAL（总是预读更多的块）或 POSIX_FADV_DONTNEED（将范围内的块从块缓存中移除）之一。
+/// 前三者对整个文件生效，不区分范围。
+/// 返回值：如果出现了错误则返回 -1，否则返回 0 。可能的错误原因是：fd 不合法或者不是文件系统中的文件，advice 不合法。
+/// syscall ID：223
+pub fn sys_fadvise(fd: usize, offset: usize, len: usize, advice: usize) -> isize {
+    let task = current_task().unwrap();
+    let inner = task.inner_exclusive_access();
+    if fd >= inner.fd_table.len() {
+        return -1;
+    }
+    if let Some(file) = &inner.fd_table[fd] {
+        let file = file.clone();
+        // release current task TCB manually to avoid multi-borrow
+        drop(inner);
+        if file.fadvise(offset, len, advice) {
+            0
+        } else {
+            -1
+        }
+    } else {
+        -1
+    }
+}
+
+/// 功能：修改一个已打开文件的权限位。即使文件的路径已经无法得知，也可以通过文件描述符修改。
+/// 参数：fd 为文件描述符，mode 的低 12 位为新的权限位，其余的位被忽略。
+/// 返回值：如果出现了错误则返回 -1，否则返回 0 。可能的错误原因是：fd 不合法或者不是文件系统中的文件。
+/// syscall ID：52
+pub fn sys_fchmod(fd: usize, mode: u32) -> isize {
+    let task = current_task().unwrap();
+    let inner = task.inner_exclusive_access();
+    if fd >= inner.fd_table.len() {
+        return -1;
+    }
+    if let Some(file) = &inner.fd_table[fd] {
+        let file = file.clone();
+        // release current task TCB manually to avoid multi-borrow
+        drop(inner);
+        chmod_file(file, mode)
+    } else {
+        -1
+    }
+}
+
+/// 功能：与 fchmod 相同，但通过路径指定文件，不需要事先打开它。
+/// 参数：path 为文件路径，mode 的低 12 位为新的权限位。
+/// 返回值：如果出现了错误则返回 -1，否则返回 0 。可能的错误原因是：文件不存在。
+/// syscall ID：53
+pub fn sys_chmod(path: *const u8, mode: u32) -> isize {
+    let task = current_task().unwrap();
+    let token = current_user_token();
+    let path = canonicalize(
+        &translated_str(token, path),
+        &task.inner_exclusive_access().cwd,
+    );
+    match open_inode(path.as_str(), OpenFlags::RDONLY) {
+        Some(inode) => chmod_file(inode, mode),
+        None => -1,
+    }
+}
+
+// fchmod 和 chmod 共用的部分
+fn chmod_file(file: Arc<dyn File + Send + Sync>, mode: u32) -> isize {
+    if file.chmod(mode) {
+        0
+    } else {
+        -1
+    }
+}
+
+/// 功能：获取一个已打开文件的状态，包括索引节点编号、类型和权限位以及文件大小。
+/// 参数：fd 为文件描述符，buf 为保存结果的 Stat 结构体的地址。
+/// 返回值：如果出现了错误则返回 -1，否则返回 0 。可能的错误原因是：fd 不合法或者不是文件系统中的文件。
+/// syscall ID：80
+pub fn sys_fstat(fd: usize, buf: *mut Stat) -> isize {
+    let token = current_user_token();
+    let task = current_task().unwrap();
+    let inner = task.inner_exclusive_access();
+    if fd >= inner.fd_table.len() {
+        return -1;
+    }
+    let file = match &inner.fd_table[fd] {
+        Some(file) => file.clone(),
+        None => return -1,
+    };
+    // release current task TCB manually to avoid multi-borrow
+    drop(inner);
+    match file.stat() {
+        Some(stat) => {
+            current_unshare_zero_range(buf as usize, core::mem::size_of::<Stat>());
+            copy_out_value(token, buf, &stat);
+            0
+        }
+        None => -1,
+    }
+}
+
+/// 功能：获取 path 的状态，与打开它之后调用 fstat 的结果相同。设备文件得到的是它在文件系统中的索引节点的状态。
+/// 参数：dirfd 和 path 的含义与 openat 相同，buf 为保存结果的 Stat 结构体的地址。
+/// 返回值：如果出现了错误则返回 -1，否则返回 0 。可能的错误原因是：dirfd 不合法或者不是目录，文件不存在。
+/// syscall ID：79
+pub fn sys_fstatat(dirfd: isize, path: *const u8, buf: *mut Stat) -> isize {
+    let Some(path) = resolve_at(dirfd, path) else {
+        return -1;
+    };
+    match open_inode(path.as_str(), OpenFlags::RDONLY).and_then(|inode| inode.stat()) {
+        Some(stat) => {
+            current_unshare_zero_range(buf as usize, core::mem::size_of::<Stat>());
+            copy_out_value(current_user_token(), buf, &stat);
+            0
+        }
+        None => -1,
+    }
+}
+
+/// 功能：获取一个已打开文件的扩展状态，除了 fstat 的内容之外还有占用的块数、适合的 I/O 大小和读写的字节数。
+/// 参数：fd 为文件描述符；mask 为需要的字段，由 STATX_TYPE/MODE/INO/SIZE/BLOCKS/IO 等位组成；buf 为保存结果的 Statx 结构体的地址。
+/// 结果中的 mask 给出实际填写了的字段，没有填写的字段为 0 。占用的块数以 512 字节为单位并且包括索引块，
+/// 文件中的空洞不占用块，因此稀疏文件的块数可能远小于它的大小。文件系统不记录时间戳，时间戳字段总是无效的。
+/// STATX_IO 是非标准的位，读写的字节数是通过这个文件的所有打开实例累计的，从内核启动或者文件被创建时开始计算。
+/// 返回值：如果出现了错误则返回 -1，否则返回 0 。可能的错误原因是：fd 不合法或者不是文件系统中的文件。
+/// syscall ID：291
+pub fn sys_statx(fd: usize, mask: u32, buf: *mut Statx) -> isize {
+    let token = current_user_token();
+    let task = current_task().unwrap();
+    let inner = task.inner_exclusive_access();
+    let file = match inner.fd_table.get(fd) {
+        Some(Some(file)) => file.clone(),
+        _ => return -1,
+    };
+    drop(inner);
+    match file.statx(mask) {
+        Some(statx) => {
+            current_unshare_zero_range(buf as usize, core::mem::size_of::<Statx>());
+            copy_out_value(token, buf, &statx);
+            0
+        }
+        None => -1,
+    }
+}
+
+/// 功能：从一个已打开的目录中读取若干个目录项。每次调用都从上一次结束的位置继续读取。
+/// 参数：fd 为以只读方式打开的目录的文件描述符，buf 为保存目录项的 Dirent 数组的地址，count 为数组的长度。
+/// 每个目录项的 d_type 给出它指向的文件的类型（DT_REG/DT_DIR/DT_CHR），读不出索引节点时为 DT_UNKNOWN ，
+/// 这样列出目录时不需要再对每一项调用 stat 。
+/// 返回值：如果出现了错误则返回 -1，否则返回读到的目录项个数，返回 0 表示已经读完了整个目录。
+/// 可能的错误原因是：fd 不合法或者不是目录。
+/// syscall ID：61
+pub fn sys_getdents(fd: usize, buf: *mut Dirent, count: usize) -> isize {
+    let token = current_user_token();
+    let task = current_task().unwrap();
+    let inner = task.inner_exclusive_access();
+    if fd >= inner.fd_table.len() {
+        return -1;
+    }
+    let file = match &inner.fd_table[fd] {
+        Some(file) => file.clone(),
+        None => return -1,
+    };
+    // release current task TCB manually to avoid multi-borrow
+    drop(inner);
+    match file.read_dir(count) {
+        Some(entries) => {
+            current_unshare_zero_range(buf as usize, count * core::mem::size_of::<Dirent>());
+            for (i, entry) in entries.iter().enumerate() {
+                copy_out_value(token, buf.wrapping_add(i), entry);
+            }
+            entries.len() as isize
+        }
+        None => -1,
+    }
+}
+
+/// 功能：将当前进程的工作目录修改为 path 指向的目录。
+/// 参数：path 为目录的路径，相对路径基于当前的工作目录解析。
+/// 返回值：如果出现了错误则返回 -1，否则返回 0 。可能的错误原因是：路径不存在或者不是目录。
+/// syscall ID：49
+pub fn sys_chdir(path: *const u8) -> isize {
+    let task = current_task().unwrap();
+    let token = current_user_token();
+    let path = canonicalize(
+        &translated_str(token, path),
+        &task.inner_exclusive_access().cwd,
+    );
+    match open_file(path.as_str(), OpenFlags::RDONLY) {
+        Some(inode) => chdir_to(inode),
+        None => -1,
+    }
+}
+
+/// 功能：将当前进程的工作目录修改为一个已打开的目录。目录在打开时就已经确定，不会受到之后路径变化的影响。
+/// 参数：fd 为已打开的目录的文件描述符。
+/// 返回值：如果出现了错误则返回 -1，否则返回 0 。可能的错误原因是：fd 不合法或者不是目录。
+/// syscall ID：50
+pub fn sys_fchdir(fd: usize) -> isize {
+    let task = current_task().unwrap();
+    let inner = task.inner_exclusive_access();
+    if fd >= inner.fd_table.len() {
+        return -1;
+    }
+    if let Some(file) = &inner.fd_table[fd] {
+        let file = file.clone();
+        // release current task TCB manually to avoid multi-borrow
+        drop(inner);
+        chdir_to(file)
+    } else {
+        -1
+    }
+}
+
+// chdir 和 fchdir 共用的部分：只有目录才能成为工作目录
+fn chdir_to(file: Arc<dyn File + Send + Sync>) -> isize {
+    match file.dir_path() {
+        Some(path) => {
+            current_task().unwrap().inner_exclusive_access().cwd = path;
+            0
+        }
+        None => -1,
+    }
+}
+
+/// 功能：获取当前进程的工作目录。
+/// 参数：buf 为保存以 \0 结尾的工作目录路径的缓冲区，len 为缓冲区的长度。
+/// 返回值：如果出现了错误则返回 -1，否则返回路径的长度（包括结尾的 \0）。可能的错误原因是：缓冲区太小。
+/// syscall ID：17
+pub fn sys_getcwd(buf: *mut u8, len: usize) -> isize {
+    let token = current_user_token();
+    let mut cwd = current_task().unwrap().inner_exclusive_access().cwd.clone();
+    cwd.push('\0');
+    if cwd.len() > len {
+        return -1;
+    }
+    current_unshare_zero_range(buf as usize, cwd.len());
+    copy_out(token, buf, cwd.as_bytes());
+    cwd.len() as isize
+}
+
+// ftruncate 和 truncate 共用的部分
+fn truncate_file(file: Arc<dyn File + Send + Sync>, len: usize) -> isize {
+    if file.truncate(len) {
+        0
+    } else {
+        -1
+    }
+}
+
+/// 功能：对一个已打开的文件加上或解除建议性的文件锁。
+/// 参数：fd 为文件描述符；op 为 LOCK_SH（共享锁）、LOCK_EX（互斥锁）或 LOCK_UN（解锁）之一，
+/// 加锁时可以再或上 LOCK_NB ，在锁被其他打开的文件持有时立即返回而不阻塞。
+/// 返回值：如果出现了错误则返回 -1，否则返回 0 。可能的错误原因是：fd 不合法或者不是文件系统中的文件，
+/// op 不合法，非阻塞模式下锁已被占用，或者等待期间收到了信号。
+/// syscall ID：32
+pub fn sys_flock(fd: usize, op: usize) -> isize {
+    let task = current_task().unwrap();
+    let inner = task.inner_exclusive_access();
+    if fd >= inner.fd_table.len() {
+        return -1;
+    }
+    let file = match &inner.fd_table[fd] {
+        Some(file) => file.clone(),
+        None => return -1,
+    };
+    drop(inner);
+    let inode_id = match file.inode_id() {
+        Some(inode_id) => inode_id,
+        None => return -1,
+    };
+    // 锁属于打开的文件而不是文件描述符
+    let owner = Arc::as_ptr(&file) as *const () as usize;
+    let nonblock = op & LOCK_NB != 0;
+    match op & !LOCK_NB {
+        LOCK_SH | LOCK_EX => {
+            if flock_acquire(inode_id, owner, op & LOCK_EX != 0, nonblock) {
+                0
+            } else {
+                -1
+            }
+        }
+        LOCK_UN => {
+            flock_release(inode_id, owner);
+            0
+        }
+        _ => -1,
+    }
+}
+
+/// 功能：对设备文件进行特定的控制操作，目前仅控制台终端支持。
+/// 参数：fd 为设备文件的文件描述符，cmd 为控制命令，arg 为命令的参数（通常是一个指针）。
+/// 返回值：如果出现了错误则返回 -1，否则返回 0 。
+/// syscall ID：29
+pub fn sys_ioctl(fd: usize, cmd: usize, arg: usize) -> isize {
+    let task = current_task().unwrap();
+    let inner = task.inner_exclusive_access();
+    if fd >= inner.fd_table.len() {
+        return -1;
+    }
+    if let Some(file) = &inner.fd_table[fd] {
+        let file = file.clone();
+        // release current task TCB manually to avoid multi-borrow
+        drop(inner);
+        file.ioctl(cmd, arg)
+    } else {
+        -1
+    }
+}
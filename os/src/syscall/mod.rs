@@ -9,23 +9,90 @@
 //! For clarity, each single syscall is implemented as its own function, named
 //! `sys_` then the name of the syscall. You can find functions like this in
 //! submodules, and you should also implement syscalls this way.
+const SYSCALL_GETCWD: usize = 17;
+const SYSCALL_DUP2: usize = 23;
 const SYSCALL_DUP: usize = 24;
+const SYSCALL_FCNTL: usize = 25;
+const SYSCALL_DUP3: usize = 26;
+const SYSCALL_IOCTL: usize = 29;
+const SYSCALL_FLOCK: usize = 32;
+const SYSCALL_MKNOD: usize = 33;
+const SYSCALL_UNLINK: usize = 35;
+const SYSCALL_RENAME: usize = 38;
+const SYSCALL_STATFS: usize = 43;
+const SYSCALL_TRUNCATE: usize = 45;
+const SYSCALL_FTRUNCATE: usize = 46;
+const SYSCALL_CHDIR: usize = 49;
+const SYSCALL_FCHDIR: usize = 50;
+const SYSCALL_FCHMOD: usize = 52;
+const SYSCALL_CHMOD: usize = 53;
+const SYSCALL_PAUSE: usize = 34;
 const SYSCALL_OPEN: usize = 56;
 const SYSCALL_CLOSE: usize = 57;
 const SYSCALL_PIPE: usize = 59;
+const SYSCALL_GETDENTS: usize = 61;
 const SYSCALL_READ: usize = 63;
 const SYSCALL_WRITE: usize = 64;
+const SYSCALL_PREADV: usize = 69;
+const SYSCALL_PWRITEV: usize = 70;
+const SYSCALL_SIGNALFD: usize = 74;
+const SYSCALL_VMSPLICE: usize = 75;
+const SYSCALL_FSTATAT: usize = 79;
+const SYSCALL_FSTAT: usize = 80;
+const SYSCALL_SYNC: usize = 81;
+const SYSCALL_TIMERFD_CREATE: usize = 85;
+const SYSCALL_TIMERFD_SETTIME: usize = 86;
 const SYSCALL_EXIT: usize = 93;
+const SYSCALL_SET_ROBUST_LIST: usize = 99;
+const SYSCALL_GET_ROBUST_LIST: usize = 100;
+const SYSCALL_SCHED_SETAFFINITY: usize = 122;
+const SYSCALL_SCHED_GETAFFINITY: usize = 123;
 const SYSCALL_YIELD: usize = 124;
 const SYSCALL_KILL: usize = 129;
+const SYSCALL_TGKILL: usize = 131;
 const SYSCALL_SIGACTION: usize = 134;
 const SYSCALL_SIGPROCMASK: usize = 135;
 const SYSCALL_SIGRETURN: usize = 139;
+const SYSCALL_SETUID: usize = 146;
+const SYSCALL_SETPGID: usize = 154;
+const SYSCALL_GETPGID: usize = 155;
+const SYSCALL_SETSID: usize = 157;
+const SYSCALL_GETRUSAGE: usize = 165;
+const SYSCALL_GETCPU: usize = 168;
+const SYSCALL_PRCTL: usize = 167;
 const SYSCALL_GET_TIME: usize = 169;
 const SYSCALL_GETPID: usize = 172;
+const SYSCALL_GETUID: usize = 174;
+const SYSCALL_SBRK: usize = 214;
+const SYSCALL_MUNMAP: usize = 215;
 const SYSCALL_FORK: usize = 220;
 const SYSCALL_EXEC: usize = 221;
+const SYSCALL_MMAP: usize = 222;
+const SYSCALL_MINCORE: usize = 232;
+const SYSCALL_FADVISE: usize = 223;
 const SYSCALL_WAITPID: usize = 260;
+const SYSCALL_SYNCFS: usize = 267;
+const SYSCALL_COPY_FILE_RANGE: usize = 285;
+const SYSCALL_STATX: usize = 291;
+// 非标准的系统调用
+const SYSCALL_READ_TIMEOUT: usize = 2000;
+const SYSCALL_HWCAP: usize = 2001;
+const SYSCALL_POLL: usize = 2002;
+const SYSCALL_IO_SETUP: usize = 2003;
+const SYSCALL_IO_SUBMIT: usize = 2004;
+const SYSCALL_SECCOMP: usize = 2005;
+const SYSCALL_REGISTER_LOWMEM: usize = 2006;
+const SYSCALL_DUMP_MAPS: usize = 2007;
+const SYSCALL_SETTLS: usize = 2008;
+const SYSCALL_LOADAVG: usize = 2009;
+const SYSCALL_NICE: usize = 2010;
+const SYSCALL_MKDIR: usize = 2011;
+const SYSCALL_RMDIR: usize = 2012;
+const SYSCALL_OPENAT: usize = 2013;
+#[cfg(feature = "crash_test")]
+const SYSCALL_CRASH: usize = 2014;
+const SYSCALL_IRQ_STATS: usize = 2015;
+const SYSCALL_PROFILE: usize = 2016;
 
 mod fs;
 mod process;
@@ -33,20 +100,102 @@ mod process;
 use fs::*;
 use process::*;
 
-use crate::task::SignalAction;
+use crate::fs::{Dirent, FsStat, PollFd, Stat, Statx};
+use crate::mm::IoVec;
+use crate::profile::ProfileStats;
+use crate::task::{
+    current_add_signal, current_task, LoadAvg, Rusage, SignalAction, SignalFlags, SyscallFilter,
+};
+use crate::timer::IrqStats;
+
+// exit 和 sigreturn 总是允许的，否则沙箱中的进程无法正常退出，也无法从 SIGSYS 的处理例程返回
+fn syscall_permitted(syscall_id: usize) -> bool {
+    syscall_id == SYSCALL_EXIT
+        || syscall_id == SYSCALL_SIGRETURN
+        || current_task()
+            .unwrap()
+            .inner_exclusive_access()
+            .syscall_allowed(syscall_id)
+}
 
 // sys_write 我们将传入的位于应用程序内的缓冲区的开始地址和长度转化为一个字符串 &str ，然后使用批处理操作系统已经实现的 print! 宏打印出来
-pub fn syscall(syscall_id: usize, args: [usize; 3]) -> isize {
+pub fn syscall(syscall_id: usize, args: [usize; 5]) -> isize {
+    // 被 seccomp 过滤器禁止的系统调用不会执行，进程收到 SIGSYS ，默认会被杀死
+    if !syscall_permitted(syscall_id) {
+        current_add_signal(SignalFlags::SIGSYS);
+        return -1;
+    }
     match syscall_id {
+        SYSCALL_GETCWD => sys_getcwd(args[0] as *mut u8, args[1]),
+        SYSCALL_DUP2 => sys_dup2(args[0], args[1]),
         SYSCALL_DUP => sys_dup(args[0]),
+        SYSCALL_FCNTL => sys_fcntl(args[0], args[1], args[2]),
+        SYSCALL_DUP3 => sys_dup3(args[0], args[1], args[2] as u32),
+        SYSCALL_IOCTL => sys_ioctl(args[0], args[1], args[2]),
+        SYSCALL_FLOCK => sys_flock(args[0], args[1]),
+        SYSCALL_MKNOD => sys_mknod(args[0] as *const u8, args[1] as u32, args[2] as u32),
+        SYSCALL_UNLINK => sys_unlink(args[0] as *const u8),
+        SYSCALL_RENAME => sys_rename(args[0] as *const u8, args[1] as *const u8),
+        SYSCALL_STATFS => sys_statfs(args[0] as *const u8, args[1] as *mut FsStat),
+        SYSCALL_SYNC => sys_sync(),
+        SYSCALL_SYNCFS => sys_syncfs(args[0]),
+        SYSCALL_TRUNCATE => sys_truncate(args[0] as *const u8, args[1]),
+        SYSCALL_FTRUNCATE => sys_ftruncate(args[0], args[1]),
+        SYSCALL_CHDIR => sys_chdir(args[0] as *const u8),
+        SYSCALL_FCHDIR => sys_fchdir(args[0]),
+        SYSCALL_FCHMOD => sys_fchmod(args[0], args[1] as u32),
+        SYSCALL_CHMOD => sys_chmod(args[0] as *const u8, args[1] as u32),
+        SYSCALL_PAUSE => sys_pause(),
         SYSCALL_OPEN => sys_open(args[0] as *const u8, args[1] as u32),
         SYSCALL_CLOSE => sys_close(args[0]),
         SYSCALL_PIPE => sys_pipe(args[0] as *mut usize),
+        SYSCALL_GETDENTS => sys_getdents(args[0], args[1] as *mut Dirent, args[2]),
         SYSCALL_READ => sys_read(args[0], args[1] as *const u8, args[2]),
+        SYSCALL_READ_TIMEOUT => sys_read_timeout(args[0], args[1] as *const u8, args[2], args[3]),
+        SYSCALL_HWCAP => sys_hwcap(),
+        SYSCALL_POLL => sys_poll(args[0] as *mut PollFd, args[1], args[2] as isize),
+        SYSCALL_IO_SETUP => sys_io_setup(args[0], args[1], args[2]),
+        SYSCALL_IO_SUBMIT => sys_io_submit(),
+        SYSCALL_SECCOMP => sys_seccomp(args[0] as *const SyscallFilter),
+        SYSCALL_REGISTER_LOWMEM => sys_register_lowmem(),
+        SYSCALL_DUMP_MAPS => sys_dump_maps(args[0] as *mut u8, args[1]),
+        SYSCALL_SETTLS => sys_settls(args[0]),
+        SYSCALL_LOADAVG => sys_loadavg(args[0] as *mut LoadAvg),
+        SYSCALL_NICE => sys_nice(args[0] as isize),
+        SYSCALL_IRQ_STATS => sys_irq_stats(args[0] as *mut IrqStats, args[1]),
+        SYSCALL_PROFILE => sys_profile(args[0], args[1] as *mut ProfileStats),
+        SYSCALL_MKDIR => sys_mkdir(args[0] as *const u8, args[1] as u32),
+        SYSCALL_RMDIR => sys_rmdir(args[0] as *const u8),
+        SYSCALL_OPENAT => sys_openat(args[0] as isize, args[1] as *const u8, args[2] as u32),
         SYSCALL_WRITE => sys_write(args[0], args[1] as *const u8, args[2]),
+        SYSCALL_PREADV => sys_preadv(args[0], args[1] as *const IoVec, args[2], args[3]),
+        SYSCALL_PWRITEV => sys_pwritev(args[0], args[1] as *const IoVec, args[2], args[3]),
+        SYSCALL_COPY_FILE_RANGE => sys_copy_file_range(
+            args[0],
+            args[1] as *mut usize,
+            args[2],
+            args[3] as *mut usize,
+            args[4],
+        ),
+        SYSCALL_SIGNALFD => sys_signalfd(args[0] as u32),
+        SYSCALL_VMSPLICE => sys_vmsplice(args[0], args[1], args[2]),
+        SYSCALL_FSTATAT => {
+            sys_fstatat(args[0] as isize, args[1] as *const u8, args[2] as *mut Stat)
+        }
+        SYSCALL_FSTAT => sys_fstat(args[0], args[1] as *mut Stat),
+        SYSCALL_STATX => sys_statx(args[0], args[1] as u32, args[2] as *mut Statx),
+        SYSCALL_TIMERFD_CREATE => sys_timerfd_create(),
+        SYSCALL_TIMERFD_SETTIME => sys_timerfd_settime(args[0], args[1], args[2]),
         SYSCALL_EXIT => sys_exit(args[0] as i32),
+        SYSCALL_SET_ROBUST_LIST => sys_set_robust_list(args[0], args[1]),
+        SYSCALL_GET_ROBUST_LIST => {
+            sys_get_robust_list(args[0], args[1] as *mut usize, args[2] as *mut usize)
+        }
+        SYSCALL_SCHED_SETAFFINITY => sys_sched_setaffinity(args[0], args[1]),
+        SYSCALL_SCHED_GETAFFINITY => sys_sched_getaffinity(args[0]),
         SYSCALL_YIELD => sys_yield(),
         SYSCALL_KILL => sys_kill(args[0], args[1] as i32),
+        SYSCALL_TGKILL => sys_tgkill(args[0], args[1], args[2] as i32),
         SYSCALL_SIGACTION => sys_sigaction(
             args[0] as i32,
             args[1] as *const SignalAction,
@@ -54,12 +203,30 @@ pub fn syscall(syscall_id: usize, args: [usize; 3]) -> isize {
         ),
         SYSCALL_SIGPROCMASK => sys_sigprocmask(args[0] as u32),
         SYSCALL_SIGRETURN => sys_sigreturn(),
+        SYSCALL_SETUID => sys_setuid(args[0] as u32),
+        SYSCALL_SETPGID => sys_setpgid(args[0], args[1]),
+        SYSCALL_GETPGID => sys_getpgid(args[0]),
+        SYSCALL_SETSID => sys_setsid(),
+        SYSCALL_GETRUSAGE => sys_getrusage(args[0] as isize, args[1] as *mut Rusage),
+        SYSCALL_GETCPU => sys_getcpu(args[0] as *mut u32, args[1] as *mut u32),
+        SYSCALL_PRCTL => sys_prctl(args[0], args[1]),
         SYSCALL_GET_TIME => sys_get_time(),
         SYSCALL_GETPID => sys_getpid(),
+        SYSCALL_GETUID => sys_getuid(),
+        SYSCALL_SBRK => sys_sbrk(args[0] as i32),
+        SYSCALL_MUNMAP => sys_munmap(args[0], args[1]),
         SYSCALL_FORK => sys_fork(),
-        SYSCALL_EXEC => sys_exec(args[0] as *const u8, args[1] as *const usize),
-        SYSCALL_WAITPID => sys_waitpid(args[0] as isize, args[1] as *mut i32),
-        // SYSCALL_SBRK => sys_sbrk(args[0] as i32),
+        SYSCALL_EXEC => sys_exec(
+            args[0] as *const u8,
+            args[1] as *const usize,
+            args[2] as *const usize,
+        ),
+        SYSCALL_MMAP => sys_mmap(args[0], args[1], args[2], args[3] as *const u8),
+        SYSCALL_MINCORE => sys_mincore(args[0], args[1], args[2] as *mut u8),
+        SYSCALL_FADVISE => sys_fadvise(args[0], args[1], args[2], args[3]),
+        SYSCALL_WAITPID => sys_waitpid(args[0] as isize, args[1] as *mut i32, args[2]),
+        #[cfg(feature = "crash_test")]
+        SYSCALL_CRASH => sys_crash(),
         _ => panic!("Unsupported syscall_id: {}", syscall_id),
     }
 }
\ No newline at end of file
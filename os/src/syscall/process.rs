@@ -1,15 +1,27 @@
 //! App management syscalls
 // use crate::batch::run_next_app;
-use crate::fs::{open_file, OpenFlags};
-use crate::mm::{translated_ref, translated_refmut, translated_str};
+use crate::config::{HART_NUM, MAX_NICE, MIN_NICE, PAGE_SIZE, USER_SPACE_END};
+use crate::fs::{acquire_console_session, canonicalize, open_inode, OpenFlags};
+use crate::hwcap::user_hwcap;
+use crate::mm::{
+    copy_in_value, copy_out, copy_out_value, try_translated_ref, try_translated_str, MapPermission,
+    VirtAddr,
+};
+use crate::profile::{profile_start, profile_stats, profile_stop, ProfileStats};
 use crate::task::{
-    add_task, current_task, current_user_token, exit_current_and_run_next, pid2task,
-    suspend_current_and_run_next, SignalAction, SignalFlags, MAX_SIG,
+    add_task, block_current_and_run_next, change_program_brk, cpu_times_ms, current_hart_id,
+    current_has_pending_signal, current_task, current_trap_cx, current_unshare_zero_range,
+    current_user_token, exit_current_and_run_next, load_averages, pgid2tasks, pid2task,
+    reap_orphans, register_lowmem, suspend_current_and_run_next, wakeup_task, LoadAvg, Rusage,
+    SignalAction, SignalFlags, SyscallFilter, TaskControlBlock, MAX_SIG,
 };
-use crate::timer::get_time_ms;
+use crate::timer::{get_time_ms, irq_stats, IrqStats};
+use alloc::borrow::Cow;
+use alloc::format;
 use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
+use log::debug;
 
 pub fn sys_exit(exit_code: i32) -> ! {
     exit_current_and_run_next(exit_code);
@@ -28,23 +40,494 @@ pub fn sys_get_time() -> isize {
     get_time_ms() as isize
 }
 
+/// 功能：查询用户程序可以使用的 ISA 扩展。
+/// 返回值：扩展字母 x 可用时第 x - 'a' 位为 1 的位图。
+/// syscall ID：2001
+pub fn sys_hwcap() -> isize {
+    user_hwcap() as isize
+}
+
+const PR_SET_NO_NEW_PRIVS: usize = 38;
+const PR_GET_NO_NEW_PRIVS: usize = 39;
+
+/// 功能：设置或查询进程的属性，目前只支持 no_new_privs 标志。
+/// no_new_privs 一旦设置就不能清除，并且会被子进程继承，设置它之后才能使用 seccomp 。
+/// 参数：option 为 PR_SET_NO_NEW_PRIVS(38) 时 arg2 必须为 1 ；为 PR_GET_NO_NEW_PRIVS(39) 时忽略 arg2 。
+/// 返回值：设置成功时返回 0 ，查询时返回标志的值；option 或 arg2 不合法时返回 -1 。
+/// syscall ID：167
+pub fn sys_prctl(option: usize, arg2: usize) -> isize {
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    match (option, arg2) {
+        (PR_SET_NO_NEW_PRIVS, 1) => {
+            inner.no_new_privs = true;
+            0
+        }
+        (PR_GET_NO_NEW_PRIVS, _) => inner.no_new_privs as isize,
+        _ => -1,
+    }
+}
+
+/// 功能：限制当前进程只能使用 filter 中允许的系统调用，调用被禁止的系统调用时进程收到 SIGSYS 。
+/// 已经设置过过滤器时取两者的交集，因此过滤器只能收紧不能放宽。过滤器会被 fork 和 exec 继承。
+/// exit 和 sigreturn 总是允许的。
+/// 参数：filter 为允许的系统调用号的位图，第 i 位为 1 表示允许系统调用号为 i 的系统调用。
+/// 返回值：成功返回 0 ；没有通过 prctl 设置 no_new_privs 时返回 -1 。
+/// syscall ID：2005
+pub fn sys_seccomp(filter: *const SyscallFilter) -> isize {
+    let token = current_user_token();
+    let mut filter = copy_in_value(token, filter);
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    if !inner.no_new_privs {
+        return -1;
+    }
+    if let Some(current) = inner.seccomp {
+        filter.restrict(&current);
+    }
+    inner.seccomp = Some(filter);
+    0
+}
+
+/// 功能：登记当前进程，之后每当空闲的物理内存降到水位线以下时，进程都会收到 SIGMEMPRESSURE（即 SIGPWR ），
+/// 可以在信号处理例程中释放缓存。登记在进程退出时失效。
+/// 返回值：总是返回 0 。
+/// syscall ID：2006
+pub fn sys_register_lowmem() -> isize {
+    register_lowmem(current_task().unwrap().getpid());
+    0
+}
+
 pub fn sys_getpid() -> isize {
     current_task().unwrap().pid.0 as isize
 }
 
-/// change data segment size
-// pub fn sys_sbrk(size: i32) -> isize {
-//     if let Some(old_brk) = change_program_brk(size) {
-//         old_brk as isize
-//     } else {
-//         -1
-//     }
-// }
+const RUSAGE_SELF: isize = 0;
+const RUSAGE_CHILDREN: isize = -1;
+
+/// 功能：获取资源使用统计，包括缺页次数、主动/被动上下文切换次数和最大驻留集大小。
+/// 参数：who 为 RUSAGE_SELF(0) 时统计当前进程，为 RUSAGE_CHILDREN(-1) 时统计所有已被回收的子进程；
+/// usage 为保存统计结果的 Rusage 结构体的地址。
+/// 返回值：who 不合法时返回 -1 ，否则返回 0 。
+/// syscall ID：165
+pub fn sys_getrusage(who: isize, usage: *mut Rusage) -> isize {
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    let token = inner.get_user_token();
+    let rusage = match who {
+        RUSAGE_SELF => {
+            inner.update_maxrss();
+            inner.rusage
+        }
+        RUSAGE_CHILDREN => inner.children_rusage,
+        _ => return -1,
+    };
+    inner
+        .memory_set
+        .unshare_zero_range(usage as usize, core::mem::size_of::<Rusage>());
+    copy_out_value(token, usage, &rusage);
+    0
+}
+
+/// 功能：获取当前进程正在运行的 hart 编号和 NUMA 节点编号，节点编号总是 0 。
+/// 参数：cpu 和 node 为保存结果的地址，为 0 时不写入对应的值。
+/// 返回值：0 。
+/// syscall ID：168
+pub fn sys_getcpu(cpu: *mut u32, node: *mut u32) -> isize {
+    let hart = current_hart_id() as u32;
+    let token = current_user_token();
+    for (ptr, value) in [(cpu, hart), (node, 0)] {
+        if !ptr.is_null() {
+            current_unshare_zero_range(ptr as usize, core::mem::size_of::<u32>());
+            copy_out_value(token, ptr, &value);
+        }
+    }
+    0
+}
+
+const MAX_MMAP_NAME_LEN: usize = 32;
+
+/// 功能：将从 start 开始的 len 字节的匿名内存映射到当前进程的地址空间。映射的内存内容全为零，
+/// 在第一次被写入之前所有页面都共享同一个只读的零页，并不占用额外的物理页帧。
+/// 映射只能位于堆的保留区域之上（堆最多增长到 USER_HEAP_SIZE ），因此与通过 sbrk 增长的堆不会冲突。
+/// 参数：start 为映射的起始地址，需要按页对齐，为 0 时由内核选择一段空闲的地址；len 为映射的长度，会被向上取整到页的大小；
+/// prot 的第 0/1/2 位分别表示是否可读/可写/可执行，其余位必须为 0 ，且不能全为 0 ；可写的映射总是也可读；
+/// name 为空指针，或者指向以 \0 结尾、不超过 MAX_MMAP_NAME_LEN 字节的名字，在 sys_dump_maps 中显示为 [mmap:name] 。
+/// 返回值：start 不为 0 时成功返回 0 ，为 0 时成功返回内核选择的起始地址；
+/// 参数不合法、与已有的映射或者堆的保留区域重叠、或者没有足够大的空闲地址时返回 -1 。
+/// syscall ID：222
+pub fn sys_mmap(start: usize, len: usize, prot: usize, name: *const u8) -> isize {
+    if start % PAGE_SIZE != 0 || len == 0 || prot & !0x7 != 0 || prot & 0x7 == 0 {
+        return -1;
+    }
+    let name: Cow<'static, str> = if name.is_null() {
+        "[mmap]".into()
+    } else {
+        match try_translated_str(current_user_token(), name, MAX_MMAP_NAME_LEN) {
+            Some(name) => format!("[mmap:{}]", name).into(),
+            None => return -1,
+        }
+    };
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    let memory_set = &mut inner.memory_set;
+    let addr = if start == 0 {
+        match memory_set.find_free_area(len.div_ceil(PAGE_SIZE)) {
+            Some(start_vpn) => VirtAddr::from(start_vpn).0,
+            None => return -1,
+        }
+    } else {
+        start
+    };
+    let end = match addr.checked_add(len) {
+        Some(end) if end <= USER_SPACE_END => end,
+        _ => return -1,
+    };
+    let start_vpn = VirtAddr::from(addr).floor();
+    let end_vpn = VirtAddr::from(end).ceil();
+    if memory_set.overlaps(start_vpn, end_vpn) || memory_set.overlaps_heap(start_vpn, end_vpn) {
+        return -1;
+    }
+    // prot 的各位恰好与 MapPermission 中的 R/W/X 差一位
+    let mut permission = MapPermission::from_bits((prot << 1) as u8).unwrap() | MapPermission::U;
+    // RISC-V 的页表项中可写而不可读是保留的编码，与 Linux 一样让可写的映射同时可读
+    if permission.contains(MapPermission::W) {
+        permission |= MapPermission::R;
+    }
+    memory_set.insert_demand_zero_area(addr.into(), end.into(), permission, name);
+    if start == 0 {
+        addr as isize
+    } else {
+        0
+    }
+}
+
+/// 功能：取消一段之前通过 mmap 建立的映射。
+/// 参数：start 和 len 必须与 mmap 时传入的参数对应同一段页面。
+/// 返回值：成功返回 0 ，找不到对应的映射则返回 -1 。
+/// syscall ID：215
+pub fn sys_munmap(start: usize, len: usize) -> isize {
+    if start % PAGE_SIZE != 0 || len == 0 {
+        return -1;
+    }
+    let end = match start.checked_add(len) {
+        Some(end) if end <= USER_SPACE_END => end,
+        _ => return -1,
+    };
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    if inner
+        .memory_set
+        .remove_area(VirtAddr::from(start).floor(), VirtAddr::from(end).ceil())
+    {
+        0
+    } else {
+        -1
+    }
+}
+
+/// 功能：查询一段地址区间中的每个页面是否驻留在内存中，即是否已经有属于自己的物理页帧。
+/// 仍映射到共享零页的按需清零页面不算驻留。
+/// 参数：start 必须按页对齐，len 为区间的字节数；vec 指向的数组中每个字节对应区间中的一个页面，
+/// 驻留时写入 1 ，否则写入 0 。
+/// 返回值：成功返回 0 ；如果 start 没有按页对齐或者区间中有页面不属于任何映射则返回 -1 。
+/// syscall ID：232
+pub fn sys_mincore(start: usize, len: usize, vec: *mut u8) -> isize {
+    if start % PAGE_SIZE != 0 {
+        return -1;
+    }
+    let end = match start.checked_add(len) {
+        Some(end) if end <= USER_SPACE_END => end,
+        _ => return -1,
+    };
+    let start_vpn = VirtAddr::from(start).floor();
+    let end_vpn = VirtAddr::from(end).ceil();
+    let task = current_task().unwrap();
+    let inner = task.inner_exclusive_access();
+    let mut residency = Vec::new();
+    for vpn in start_vpn.0..end_vpn.0 {
+        match inner.memory_set.resident(vpn.into()) {
+            Some(resident) => residency.push(resident as u8),
+            None => return -1,
+        }
+    }
+    drop(inner);
+    let token = current_user_token();
+    current_unshare_zero_range(vec as usize, residency.len());
+    // vec 可能跨越多个页面，copy_out 通过 translated_byte_buffer 逐页写入
+    copy_out(token, vec, &residency);
+    0
+}
+
+/// 功能：将当前进程地址空间中各个逻辑段的起止地址、权限和名字以文本形式写入 buf ，每个逻辑段一行。
+/// 返回值：成功返回写入的字节数；buf 放不下全部内容则返回 -1 。
+/// syscall ID：2007
+pub fn sys_dump_maps(buf: *mut u8, len: usize) -> isize {
+    let task = current_task().unwrap();
+    let maps = task.inner_exclusive_access().memory_set.maps();
+    if maps.len() > len {
+        return -1;
+    }
+    let token = current_user_token();
+    current_unshare_zero_range(buf as usize, maps.len());
+    copy_out(token, buf, maps.as_bytes());
+    maps.len() as isize
+}
+
+/// 功能：将当前进程的线程指针 tp 设为 ptr ，此后 tp 随 Trap 上下文保存和恢复，始终指向 ptr 处的 TLS 块。
+/// 参数：ptr 为 TLS 块的起始地址，内核不检查它是否可以访问。
+/// 返回值：0 。
+/// syscall ID：2008
+pub fn sys_settls(ptr: usize) -> isize {
+    current_trap_cx().x[4] = ptr;
+    0
+}
+
+/// 功能：获取系统在最近 1 秒、5 秒和 15 秒内的平均负载（就绪和正在运行的任务数），以及所有 hart 运行任务和空闲的总时间。
+/// 参数：load 为保存结果的 LoadAvg 结构体的地址，其中平均负载是有 FSHIFT 位小数的定点数。
+/// 返回值：0 。
+/// syscall ID：2009
+pub fn sys_loadavg(load: *mut LoadAvg) -> isize {
+    let (busy_ms, idle_ms) = cpu_times_ms();
+    let result = LoadAvg {
+        loads: load_averages(),
+        busy_ms,
+        idle_ms,
+    };
+    current_unshare_zero_range(load as usize, core::mem::size_of::<LoadAvg>());
+    copy_out_value(current_user_token(), load, &result);
+    0
+}
+
+/// 功能：获取时钟中断的延迟统计，即从设定的触发时刻到内核开始处理这个中断所经过的时间。
+/// 内核态屏蔽了中断，在内核中长时间运行而不返回用户态（比如一次很长的文件操作）会使延迟增大。
+/// 参数：stats 为保存结果的 IrqStats 结构体的地址，其中包括处理过的时钟中断数以及最大和平均延迟（微秒）；
+/// reset 不为 0 时在读出之后清零统计。
+/// 返回值：0 。
+/// syscall ID：2015
+pub fn sys_irq_stats(stats: *mut IrqStats, reset: usize) -> isize {
+    let result = irq_stats(reset != 0);
+    current_unshare_zero_range(stats as usize, core::mem::size_of::<IrqStats>());
+    copy_out_value(current_user_token(), stats, &result);
+    0
+}
+
+/// 功能：控制内核性能剖析。采样期间每次时钟中断记录一次被打断的位置：发生在用户态时只计数，
+/// 发生在内核态（系统调用期间）时按照 sepc 所在的区间累加到内核代码段的直方图中。
+/// 参数：enable 为 0 时停止采样，否则清零统计并开始采样；stats 不为空时，
+/// 把这次调用之前采集到的统计结果写到 stats 指向的 ProfileStats 结构体中。
+/// 返回值：0 。
+/// syscall ID：2016
+pub fn sys_profile(enable: usize, stats: *mut ProfileStats) -> isize {
+    if enable == 0 {
+        profile_stop();
+    }
+    if !stats.is_null() {
+        let result = profile_stats();
+        current_unshare_zero_range(stats as usize, core::mem::size_of::<ProfileStats>());
+        copy_out_value(current_user_token(), stats, &result);
+    }
+    if enable != 0 {
+        profile_start();
+    }
+    0
+}
+
+/// 功能：将当前进程的 nice 值增加 delta ，结果限制在 MIN_NICE 到 MAX_NICE 之间。nice 值越大，进程分到的处理器时间越少。
+/// 参数：delta 为 nice 值的增量，可以为负数。
+/// 返回值：调整之后的 nice 值。
+/// syscall ID：2010
+pub fn sys_nice(delta: isize) -> isize {
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    inner.nice = inner.nice.saturating_add(delta).clamp(MIN_NICE, MAX_NICE);
+    inner.nice
+}
+
+/// robust 链表头 struct robust_list_head 的大小：链表指针、futex 字相对于链表项的偏移和正在加锁的链表项
+const ROBUST_LIST_HEAD_SIZE: usize = 3 * core::mem::size_of::<usize>();
+
+/// 功能：登记当前进程的 robust futex 链表头。内核还没有 futex ，进程退出时无法遍历链表释放其中的锁，
+/// 登记了也不会生效，因此在实现 futex 之前不支持登记。
+/// 参数：head 为用户态链表头的地址，len 为链表头的大小。
+/// 返回值：总是返回 -1 。
+/// syscall ID：99
+pub fn sys_set_robust_list(_head: usize, _len: usize) -> isize {
+    -1
+}
+
+/// 功能：获取进程 pid 登记的 robust futex 链表头。目前不支持登记，链表头的地址总是 0 。
+/// 参数：pid 为 0 时表示当前进程；链表头的地址和大小分别写入 head_ptr 和 len_ptr 。
+/// 返回值：成功返回 0 ；进程不存在时返回 -1 。
+/// syscall ID：100
+pub fn sys_get_robust_list(pid: usize, head_ptr: *mut usize, len_ptr: *mut usize) -> isize {
+    if pid != 0 && pid2task(pid).is_none() {
+        return -1;
+    }
+    let token = current_user_token();
+    for (ptr, value) in [(head_ptr, 0), (len_ptr, ROBUST_LIST_HEAD_SIZE)] {
+        current_unshare_zero_range(ptr as usize, core::mem::size_of::<usize>());
+        copy_out_value(token, ptr, &value);
+    }
+    0
+}
+
+/// 功能：设置进程 pid 的 CPU 亲和性掩码，第 i 位为 1 表示允许它在 hart i 上运行。
+/// 参数：pid 为 0 时表示当前进程；mask 不能为空，也不能包含不存在的 hart 。
+/// 返回值：成功返回 0 ；进程不存在或者 mask 不合法时返回 -1 。
+/// syscall ID：122
+pub fn sys_sched_setaffinity(pid: usize, mask: usize) -> isize {
+    let all_harts = (1 << HART_NUM) - 1;
+    if mask == 0 || mask & !all_harts != 0 {
+        return -1;
+    }
+    let current = current_task().unwrap();
+    let is_current = pid == 0 || pid == current.getpid();
+    let task = if is_current {
+        current
+    } else if let Some(task) = pid2task(pid) {
+        task
+    } else {
+        return -1;
+    };
+    task.inner_exclusive_access().affinity = mask;
+    drop(task);
+    // 当前进程不再允许在这个 hart 上运行时立即让出，它只会被允许的 hart 取出继续运行
+    if is_current && mask & (1 << current_hart_id()) == 0 {
+        suspend_current_and_run_next();
+    }
+    0
+}
+
+/// 功能：获取进程 pid 的 CPU 亲和性掩码。
+/// 参数：pid 为 0 时表示当前进程。
+/// 返回值：进程的 CPU 亲和性掩码；进程不存在时返回 -1 。
+/// syscall ID：123
+pub fn sys_sched_getaffinity(pid: usize) -> isize {
+    let task = if pid == 0 {
+        current_task().unwrap()
+    } else if let Some(task) = pid2task(pid) {
+        task
+    } else {
+        return -1;
+    };
+    let mask = task.inner_exclusive_access().affinity;
+    mask as isize
+}
+
+/// 功能：设置进程 pid 所属的进程组为 pgid。
+/// 参数：pid 为 0 时表示当前进程；pgid 为 0 时表示使用 pid 作为进程组号，即创建一个新的进程组。
+/// 返回值：成功返回 0 ；如果目标不是当前进程或其子进程、是会话首进程，或者加入的进程组不在同一会话中则返回 -1 。
+/// syscall ID：154
+pub fn sys_setpgid(pid: usize, pgid: usize) -> isize {
+    let task = current_task().unwrap();
+    let pid = if pid == 0 { task.getpid() } else { pid };
+    let pgid = if pgid == 0 { pid } else { pgid };
+    let inner = task.inner_exclusive_access();
+    let sid = inner.sid;
+    // 只能修改当前进程或者其子进程的进程组
+    let target = if pid == task.getpid() {
+        task.clone()
+    } else if let Some(child) = inner.children.iter().find(|child| child.getpid() == pid) {
+        child.clone()
+    } else {
+        return -1;
+    };
+    drop(inner);
+    let group = pgid2tasks(pgid);
+    let mut target_inner = target.inner_exclusive_access();
+    // 会话首进程不能改变进程组，且目标进程必须与当前进程处于同一会话中
+    if target_inner.sid == pid || target_inner.sid != sid {
+        return -1;
+    }
+    // 加入已有的进程组时，该进程组必须存在且在同一个会话中
+    if pgid != pid
+        && (group.is_empty()
+            || group
+                .iter()
+                .any(|t| !Arc::ptr_eq(t, &target) && t.inner_exclusive_access().sid != sid))
+    {
+        return -1;
+    }
+    target_inner.pgid = pgid;
+    0
+}
+
+/// 功能：获取进程 pid 所属的进程组号。
+/// 参数：pid 为 0 时表示当前进程。
+/// 返回值：进程不存在时返回 -1 ，否则返回进程组号。
+/// syscall ID：155
+pub fn sys_getpgid(pid: usize) -> isize {
+    let task = if pid == 0 {
+        current_task()
+    } else {
+        pid2task(pid)
+    };
+    if let Some(task) = task {
+        task.inner_exclusive_access().pgid as isize
+    } else {
+        -1
+    }
+}
+
+/// 功能：创建一个新的会话，当前进程成为会话首进程以及新进程组的组长。
+/// 如果控制台终端还不属于任何会话，它会成为新会话的控制终端，新进程组成为前台进程组。
+/// 返回值：如果当前进程已经是一个进程组的组长则返回 -1 ，否则返回新会话的会话号。
+/// syscall ID：157
+pub fn sys_setsid() -> isize {
+    let task = current_task().unwrap();
+    let pid = task.getpid();
+    // 进程组组长不能创建新会话，否则原进程组中的其他进程会与组长处于不同的会话
+    if !pgid2tasks(pid).is_empty() {
+        return -1;
+    }
+    let mut inner = task.inner_exclusive_access();
+    inner.pgid = pid;
+    inner.sid = pid;
+    drop(inner);
+    acquire_console_session(pid);
+    pid as isize
+}
+
+/// 功能：获取当前进程的用户号。
+/// 返回值：当前进程的用户号，0 表示特权用户。
+/// syscall ID：174
+pub fn sys_getuid() -> isize {
+    current_task().unwrap().inner_exclusive_access().uid as isize
+}
+
+/// 功能：设置当前进程的用户号。特权用户可以设置任意的用户号，放弃特权之后就无法再恢复。
+/// 参数：uid 为新的用户号。
+/// 返回值：如果出现了错误则返回 -1，否则返回 0 。可能的错误原因是：当前进程不是特权用户且 uid 与当前的用户号不同。
+/// syscall ID：146
+pub fn sys_setuid(uid: u32) -> isize {
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    if inner.uid != 0 && inner.uid != uid {
+        return -1;
+    }
+    inner.uid = uid;
+    0
+}
+
+/// 功能：将当前进程的 program break 移动 size 字节，从而扩展或收缩堆空间。
+/// 参数：size 为正时扩展堆，为负时收缩堆。
+/// 返回值：成功返回原来的 program break ；如果收缩后低于堆底则返回 -1 。
+/// syscall ID：214
+pub fn sys_sbrk(size: i32) -> isize {
+    if let Some(old_brk) = change_program_brk(size) {
+        old_brk as isize
+    } else {
+        -1
+    }
+}
 
 /// 功能：当前进程 fork 出来一个子进程。
 /// 返回值：对于子进程返回 0，对于当前进程则返回子进程的 PID 。
 /// syscall ID：220
 pub fn sys_fork() -> isize {
+    // 最后退出的孤儿进程要等到它切换出去之后才能回收，在这里补上
+    reap_orphans();
     let current_task = current_task().unwrap();
     let new_task = current_task.fork();
     let new_pid = new_task.pid.0;
@@ -59,34 +542,67 @@ pub fn sys_fork() -> isize {
     new_pid as isize
 }
 
-/// 功能：将当前进程的地址空间清空并加载一个特定的可执行文件，返回用户态后开始它的执行。
-/// 参数：path 给出了要加载的可执行文件的名字；args 指向命令行参数字符串起始地址数组中的一个位置
-/// 返回值：如果出错的话（如找不到名字相符的可执行文件）则返回 -1，否则不应该返回。
-/// syscall ID：221
-// path 作为 &str 类型是一个胖指针，既有起始地址又包含长度信息。在实际进行系统调用的时候，我们只会将起始地址传给内核（对标 C 语言仅会传入一个 char* ）。这就需要应用负责在传入的字符串的末尾加上一个 \0 ，这样内核才能知道字符串的长度。
-pub fn sys_exec(path: *const u8, mut args: *const usize) -> isize {
-    let token = current_user_token();
-    // 调用 translated_str 找到要执行的应用名
-    let path = translated_str(token, path);
-    let mut args_vec: Vec<String> = Vec::new();
+// 命令行参数和环境变量的总个数以及每一项的长度都有上限，这样它们在 exec 时总能被放进用户栈中
+const MAX_ARG_NUM: usize = 32;
+const MAX_ARG_LEN: usize = 128;
+const MAX_PATH_LEN: usize = 256;
+
+// 读取一个以 0 结尾的字符串指针数组，最多 max 项
+fn read_string_array(token: usize, mut array: *const usize, max: usize) -> Option<Vec<String>> {
+    let mut strings: Vec<String> = Vec::new();
     loop {
-        let arg_str_ptr = *translated_ref(token, args);
-        if arg_str_ptr == 0 {
-            break;
+        // 用户传入的数组可能没有以 0 结尾或者指向未映射的内存，此时返回 None 而不是一直读下去或者让内核 panic
+        let str_ptr = *try_translated_ref(token, array)?;
+        if str_ptr == 0 {
+            return Some(strings);
+        }
+        if strings.len() == max {
+            return None;
         }
-        // 每次我们都可以从一个起始地址通过 translated_str 拿到一个字符串，直到 args 为 0 就说明没有更多命令行参数了
-        args_vec.push(translated_str(token, arg_str_ptr as *const u8));
+        // 每次我们都可以从一个起始地址通过 try_translated_str 拿到一个字符串，直到遇到 0 就说明没有更多字符串了
+        let string = try_translated_str(token, str_ptr as *const u8, MAX_ARG_LEN)?;
+        strings.push(string);
         unsafe {
-            args = args.add(1);
+            array = array.add(1);
         }
     }
+}
+
+/// 功能：将当前进程的地址空间清空并加载一个特定的可执行文件，返回用户态后开始它的执行。
+/// 参数：path 给出了要加载的可执行文件的名字；args 指向命令行参数字符串起始地址数组中的一个位置；
+/// envp 指向新程序的环境变量字符串起始地址数组，为 0 时新程序继承当前进程的环境变量
+/// 返回值：如果出错的话（如找不到名字相符的可执行文件，它不是一个合法的 ELF 文件，或者参数数组不合法）则返回 -1，否则不应该返回。
+/// syscall ID：221
+// path 作为 &str 类型是一个胖指针，既有起始地址又包含长度信息。在实际进行系统调用的时候，我们只会将起始地址传给内核（对标 C 语言仅会传入一个 char* ）。这就需要应用负责在传入的字符串的末尾加上一个 \0 ，这样内核才能知道字符串的长度。
+pub fn sys_exec(path: *const u8, args: *const usize, envp: *const usize) -> isize {
+    let token = current_user_token();
+    // 调用 try_translated_str 找到要执行的应用名，并相对于当前工作目录将其解析为绝对路径
+    let task = current_task().unwrap();
+    let path = match try_translated_str(token, path, MAX_PATH_LEN) {
+        Some(path) => canonicalize(&path, &task.inner_exclusive_access().cwd),
+        None => return -1,
+    };
+    let args_vec = match read_string_array(token, args, MAX_ARG_NUM) {
+        Some(args_vec) => args_vec,
+        None => return -1,
+    };
+    let env = if envp.is_null() {
+        None
+    } else {
+        match read_string_array(token, envp, MAX_ARG_NUM - args_vec.len()) {
+            Some(env) => Some(env),
+            None => return -1,
+        }
+    };
     // 有了文件系统支持之后，我们在 sys_exec 所需的应用的 ELF 文件格式的数据就不再需要通过应用加载器从内核的数据段获取，而是从文件系统中获取，这样内核与应用的代码/数据就解耦了
-    // 调用 open_file 函数，以只读的方式在内核中打开应用文件并获取它对应的 OSInode
-    if let Some(app_inode) = open_file(path.as_str(), OpenFlags::RDONLY) {
+    // 调用 open_inode 函数，以只读的方式在内核中打开应用文件并获取它对应的 OSInode
+    if let Some(app_inode) = open_inode(path.as_str(), OpenFlags::RDONLY) {
+        debug!("[kernel] exec {}", path);
         let all_data = app_inode.read_all();
-        let task = current_task().unwrap();
         let argc = args_vec.len();
-        task.exec(all_data.as_slice(), args_vec);
+        if task.exec(all_data.as_slice(), args_vec, env).is_err() {
+            return -1;
+        }
         // return argc because cx.x[10] will be covered with it later
         argc as isize
     } else {
@@ -94,16 +610,21 @@ pub fn sys_exec(path: *const u8, mut args: *const usize) -> isize {
     }
 }
 
+/// sys_waitpid 写入编码后的状态而不是原始的退出码
+const WSTATUS: usize = 1 << 30;
+
 /// 功能：当前进程等待一个子进程变为僵尸进程，回收其全部资源并收集其返回值。
 /// 参数：pid 表示要等待的子进程的进程 ID，如果为 -1 的话表示等待任意一个子进程；
 /// exit_code 表示保存子进程返回值的地址，如果这个地址为 0 的话表示不必保存。
 /// 返回值：如果要等待的子进程不存在则返回 -1；
 /// 否则如果要等待的子进程均未结束则返回 -2，通知用户库 user_lib （是实际发出系统调用的地方），这样用户库看到是 -2 后，就进一步调用 sys_yield 系统调用，让当前父进程进入等待状态；
 /// 如果果存在一个进程 ID 为 pid 的僵尸子进程，则正常回收并返回子进程的 pid，并更新系统调用的退出码参数为 exit_code。
+/// options 中包含 WSTATUS 时写入的不是退出码，而是与 Linux 相同编码的状态：正常退出时为 (退出码 & 0xff) << 8 ，
+/// 被信号终止时为信号编号。
 /// syscall ID：260
 /// If there is not a child process whose pid is same as given, return -1.
 /// Else if there is a child process but it is still running, return -2.
-pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32) -> isize {
+pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32, options: usize) -> isize {
     let task = current_task().unwrap();
     // find a child process
 
@@ -134,11 +655,23 @@ pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32) -> isize {
         // 将收集的子进程信息返回：
         let found_pid = child.getpid();
         // ++++ temporarily access child TCB exclusively
-        let exit_code = child.inner_exclusive_access().exit_code;
+        let child_inner = child.inner_exclusive_access();
+        let exit_code = match (options & WSTATUS != 0, child_inner.term_signal) {
+            (false, _) => child_inner.exit_code,
+            (true, 0) => (child_inner.exit_code & 0xff) << 8,
+            (true, signum) => signum as i32,
+        };
+        // 被回收的子进程（以及它回收过的子进程）的资源使用情况累加到当前进程的 children_rusage 中
+        inner.children_rusage.merge(&child_inner.rusage);
+        inner.children_rusage.merge(&child_inner.children_rusage);
+        drop(child_inner);
         // ++++ release child PCB
         // 写入到当前进程的应用地址空间中。由于应用传递给内核的仅仅是一个指向应用地址空间中保存子进程返回值的内存区域的指针，
         // 我们还需要在 translated_refmut 中手动查页表找到应该写入到物理内存中的哪个位置，这样才能把子进程的退出码 exit_code 返回给父进程
-        *translated_refmut(inner.memory_set.token(), exit_code_ptr) = exit_code;
+        inner
+            .memory_set
+            .unshare_zero_range(exit_code_ptr as usize, core::mem::size_of::<i32>());
+        copy_out_value(inner.memory_set.token(), exit_code_ptr, &exit_code);
         found_pid as isize
     } else {
         -2
@@ -148,22 +681,61 @@ pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32) -> isize {
 
 pub fn sys_kill(pid: usize, signum: i32) -> isize {
     if let Some(task) = pid2task(pid) {
-        if let Some(flag) = SignalFlags::from_bits(1 << signum) {
-            // insert the signal if legal
-            let mut task_ref = task.inner_exclusive_access();
-            if task_ref.signals.contains(flag) {
-                return -1;
-            }
-            task_ref.signals.insert(flag);
-            0
-        } else {
-            -1
+        send_signal(task, signum)
+    } else {
+        -1
+    }
+}
+
+// 将编号为 signum 的信号插入到 task 的待处理信号集合中
+fn send_signal(task: Arc<TaskControlBlock>, signum: i32) -> isize {
+    if signum < 0 || signum as usize > MAX_SIG {
+        return -1;
+    }
+    if let Some(flag) = SignalFlags::from_bits(1 << signum) {
+        // insert the signal if legal
+        let mut task_ref = task.inner_exclusive_access();
+        if task_ref.signals.contains(flag) {
+            return -1;
         }
+        task_ref.signals.insert(flag);
+        drop(task_ref);
+        // 信号会唤醒阻塞中的进程（比如调用了 sys_pause 的进程）
+        wakeup_task(task);
+        0
     } else {
         -1
     }
 }
 
+/// 功能：向线程组 tgid 中的线程 tid 发送一个信号。目前一个进程只有一个线程，因此 tid 必须等于 tgid 。
+/// 向自身发送信号时（即 raise），信号会在这次系统调用返回用户态之前的 handle_signals 中被同步处理，
+/// 致命信号会让进程立即终止而不会回到用户态继续执行。
+/// 返回值：如果目标不存在或者信号不合法则返回 -1 ，否则返回 0 。
+/// syscall ID：131
+pub fn sys_tgkill(tgid: usize, tid: usize, signum: i32) -> isize {
+    if tgid != tid {
+        return -1;
+    }
+    let task = current_task().unwrap();
+    if task.getpid() == tid {
+        send_signal(task, signum)
+    } else {
+        sys_kill(tid, signum)
+    }
+}
+
+/// 功能：阻塞当前进程，直到它收到一个信号。
+/// 返回值：在信号处理例程执行完毕后返回 -1 ；如果进程被信号杀死则不会返回。
+/// syscall ID：34
+pub fn sys_pause() -> isize {
+    // 被屏蔽的信号不会唤醒进程，因此被唤醒后需要再次检查
+    while !current_has_pending_signal() {
+        block_current_and_run_next();
+    }
+    -1
+}
+
 // 进程可以通过 sigprocmask 系统调用直接设置自身的全局信号掩码
 pub fn sys_sigprocmask(mask: u32) -> isize {
     if let Some(task) = current_task() {
@@ -224,9 +796,9 @@ pub fn sys_sigaction(
     action: *const SignalAction,
     old_action: *mut SignalAction,
 ) -> isize {
-    let token = current_user_token();
     let task = current_task().unwrap();
     let mut inner = task.inner_exclusive_access();
+    let token = inner.get_user_token();
     if signum as usize > MAX_SIG {
         return -1;
     }
@@ -237,8 +809,11 @@ pub fn sys_sigaction(
         }
         let prev_action = inner.signal_actions.table[signum as usize];
         // 使用 translated_ref(mut) 将进程提交的信号处理例程保存到进程控制块
-        *translated_refmut(token, old_action) = prev_action;
-        inner.signal_actions.table[signum as usize] = *translated_ref(token, action);
+        inner
+            .memory_set
+            .unshare_zero_range(old_action as usize, core::mem::size_of::<SignalAction>());
+        copy_out_value(token, old_action, &prev_action);
+        inner.signal_actions.table[signum as usize] = copy_in_value(token, action);
         0
     } else {
         -1
@@ -0,0 +1,64 @@
+//! Load average of the run queue
+// 与 Linux 相同，负载是就绪队列中的任务数加上正在运行的任务数，内核用定点数保存它在不同时间窗口上的指数加权平均值。
+// 每 LOAD_SAMPLE_MS 采样一次：有任务运行时在时钟中断中采样，hart 空闲时收不到时钟中断，改由 idle 控制流采样
+use super::manager::ready_task_count;
+use crate::sync::UPSafeCell;
+use crate::timer::get_time_ms;
+use lazy_static::*;
+
+/// Number of fraction bits of the load averages
+const FSHIFT: usize = 16;
+const FIXED_1: usize = 1 << FSHIFT;
+const LOAD_SAMPLE_MS: usize = 10;
+// 每个采样周期的衰减系数 e^(-10ms / 1s), e^(-10ms / 5s), e^(-10ms / 15s)
+const EXP: [usize; 3] = [64884, 65405, 65492];
+// 最多补上这么多个错过的采样周期，再早的历史在 15 秒的窗口中也已经衰减得差不多了
+const MAX_CATCH_UP: usize = 3000;
+
+/// System load returned by `sys_loadavg`
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct LoadAvg {
+    /// load averages over 1, 5 and 15 seconds, fixed point with `FSHIFT` fraction bits
+    pub loads: [usize; 3],
+    /// time all harts spent running tasks, in milliseconds
+    pub busy_ms: usize,
+    /// time all harts spent idle, in milliseconds
+    pub idle_ms: usize,
+}
+
+struct LoadTracker {
+    loads: [usize; 3],
+    last_sample_ms: usize,
+}
+
+lazy_static! {
+    static ref LOAD: UPSafeCell<LoadTracker> = unsafe {
+        UPSafeCell::new(LoadTracker {
+            loads: [0; 3],
+            last_sample_ms: 0,
+        })
+    };
+}
+
+/// Sample the ready queue plus `running` tasks if a sampling period has passed
+pub fn sample_load(running: usize) {
+    let now = get_time_ms();
+    let mut tracker = LOAD.exclusive_access();
+    let periods = (now - tracker.last_sample_ms) / LOAD_SAMPLE_MS;
+    if periods == 0 {
+        return;
+    }
+    tracker.last_sample_ms += periods * LOAD_SAMPLE_MS;
+    let active = (ready_task_count() + running) * FIXED_1;
+    for _ in 0..periods.min(MAX_CATCH_UP) {
+        for (load, exp) in tracker.loads.iter_mut().zip(EXP) {
+            *load = (*load * exp + active * (FIXED_1 - exp) + FIXED_1 / 2) >> FSHIFT;
+        }
+    }
+}
+
+/// Load averages over 1, 5 and 15 seconds
+pub fn load_averages() -> [usize; 3] {
+    LOAD.exclusive_access().loads
+}
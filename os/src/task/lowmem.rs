@@ -0,0 +1,44 @@
+//! Memory pressure notification
+// 长期运行的进程可以在内存紧张时释放自己的缓存。进程通过 sys_register_lowmem 登记之后，
+// 每当空闲的物理页帧数降到 LOWMEM_FRAMES 以下，内核就向它发送 SIGMEMPRESSURE
+use super::{pid2task, wakeup_task, SignalFlags};
+use crate::mm::take_lowmem_event;
+use crate::sync::UPSafeCell;
+use alloc::vec::Vec;
+use lazy_static::*;
+
+/// Signal sent to registered processes when free memory runs low
+// 信号编号已经全部被占用，借用内核中没有其他用途的 SIGPWR
+pub const SIGMEMPRESSURE: SignalFlags = SignalFlags::SIGPWR;
+
+lazy_static! {
+    /// pids of the processes to notify of memory pressure
+    static ref LOWMEM_PIDS: UPSafeCell<Vec<usize>> = unsafe { UPSafeCell::new(Vec::new()) };
+}
+
+/// Notify the process `pid` of memory pressure from now on
+pub fn register_lowmem(pid: usize) {
+    let mut pids = LOWMEM_PIDS.exclusive_access();
+    if !pids.contains(&pid) {
+        pids.push(pid);
+    }
+}
+
+/// Stop notifying the process `pid`, called when it exits
+pub fn unregister_lowmem(pid: usize) {
+    LOWMEM_PIDS.exclusive_access().retain(|&p| p != pid);
+}
+
+/// Send `SIGMEMPRESSURE` to registered processes if free memory has run low,
+/// called before returning to user mode when no task is borrowed
+pub fn notify_lowmem() {
+    if !take_lowmem_event() {
+        return;
+    }
+    let pids = LOWMEM_PIDS.exclusive_access().clone();
+    for task in pids.into_iter().filter_map(pid2task) {
+        task.inner_exclusive_access().signals |= SIGMEMPRESSURE;
+        // 信号会唤醒阻塞中的进程
+        wakeup_task(task);
+    }
+}
@@ -1,8 +1,11 @@
 //!Implementation of [`TaskManager`]
 use super::TaskControlBlock;
+use crate::config::MIN_NICE;
+use crate::ipi::wake_parked_hart;
 use crate::sync::UPSafeCell;
 use alloc::collections::{BTreeMap, VecDeque};
 use alloc::sync::Arc;
+use alloc::vec::Vec;
 use lazy_static::*;
 // 将所有的任务控制块用引用计数 Arc 智能指针包裹后放在一个双端队列 VecDeque 中
 ///A array of `TaskControlBlock` that is thread-safe
@@ -10,7 +13,22 @@ pub struct TaskManager {
     ready_queue: VecDeque<Arc<TaskControlBlock>>,
 }
 
-/// A simple FIFO scheduler.
+// nice 值从 -20 到 19 对应的权重，与 Linux 相同：nice 值每增加 1 ，分到的处理器时间大约减少 10%
+const NICE_WEIGHTS: [usize; 40] = [
+    88761, 71755, 56483, 46273, 36291, 29154, 23254, 18705, 14949, 11916, 9548, 7620, 6100, 4904,
+    3906, 3121, 2501, 1991, 1586, 1277, 1024, 820, 655, 526, 423, 335, 272, 215, 172, 137, 110, 87,
+    70, 56, 45, 36, 29, 23, 18, 15,
+];
+const BIG_STRIDE: usize = 1 << 20;
+
+/// Distance the pass of a task with `nice` advances each time it is scheduled
+fn stride(nice: isize) -> usize {
+    BIG_STRIDE / NICE_WEIGHTS[(nice - MIN_NICE) as usize]
+}
+
+// 步长调度：每次取出 pass 最小的任务，并将它的 pass 增加与权重成反比的步长。
+// 所有任务的 nice 值相同时，这与原来的先进先出调度相同
+/// A stride scheduler.
 impl TaskManager {
     ///Creat an empty TaskManager
     pub fn new() -> Self {
@@ -18,15 +36,48 @@ impl TaskManager {
             ready_queue: VecDeque::new(),
         }
     }
-    // 将一个任务加入队尾
+    fn min_pass(&self) -> Option<usize> {
+        self.ready_queue
+            .iter()
+            .map(|task| task.inner_exclusive_access().pass)
+            .min()
+    }
+    // 将一个任务加入队尾。刚等到 I/O 数据的任务则被加入队头，并且 pass 降到队列中的最小值，每次这样做都会消耗一轮优先调度。
+    // 阻塞了很久的任务的 pass 落后于其他任务，把它提升到队列中的最小值，避免它醒来之后长时间独占处理器
     ///Add a task to `TaskManager`
     pub fn add(&mut self, task: Arc<TaskControlBlock>) {
-        self.ready_queue.push_back(task);
+        let min_pass = self.min_pass();
+        let mut inner = task.inner_exclusive_access();
+        if inner.io_boost > 0 {
+            inner.io_boost -= 1;
+            if let Some(min_pass) = min_pass {
+                inner.pass = min_pass;
+            }
+            drop(inner);
+            self.ready_queue.push_front(task);
+        } else {
+            inner.pass = inner.pass.max(min_pass.unwrap_or(0));
+            drop(inner);
+            self.ready_queue.push_back(task);
+        }
     }
-    // 从队头中取出一个任务来执行
-    ///Remove the first task and return it,or `None` if `TaskManager` is empty
-    pub fn fetch(&mut self) -> Option<Arc<TaskControlBlock>> {
-        self.ready_queue.pop_front()
+    // 取出亲和性允许在 hart 上运行的任务中 pass 最小的一个来执行，pass 相同时取靠近队头的
+    ///Remove the task allowed on `hart` with the smallest pass and return it, or `None` if there is no such task
+    pub fn fetch(&mut self, hart: usize) -> Option<Arc<TaskControlBlock>> {
+        let (_, index) = self
+            .ready_queue
+            .iter()
+            .enumerate()
+            .filter_map(|(index, task)| {
+                let inner = task.inner_exclusive_access();
+                (inner.affinity & (1 << hart) != 0).then_some((inner.pass, index))
+            })
+            .min()?;
+        let task = self.ready_queue.remove(index)?;
+        let mut inner = task.inner_exclusive_access();
+        inner.pass += stride(inner.nice);
+        drop(inner);
+        Some(task)
     }
 }
 
@@ -42,10 +93,15 @@ pub fn add_task(task: Arc<TaskControlBlock>) {
         .exclusive_access()
         .insert(task.getpid(), Arc::clone(&task));
     TASK_MANAGER.exclusive_access().add(task);
+    wake_parked_hart();
+}
+///Number of tasks waiting in the ready queue
+pub fn ready_task_count() -> usize {
+    TASK_MANAGER.exclusive_access().ready_queue.len()
 }
-///Interface offered to pop the first task
-pub fn fetch_task() -> Option<Arc<TaskControlBlock>> {
-    TASK_MANAGER.exclusive_access().fetch()
+///Interface offered to pop the first task allowed on `hart`
+pub fn fetch_task(hart: usize) -> Option<Arc<TaskControlBlock>> {
+    TASK_MANAGER.exclusive_access().fetch(hart)
 }
 
 pub fn pid2task(pid: usize) -> Option<Arc<TaskControlBlock>> {
@@ -58,4 +114,20 @@ pub fn remove_from_pid2task(pid: usize) {
     if map.remove(&pid).is_none() {
         panic!("cannot find pid {} in pid2task!", pid);
     }
-}
\ No newline at end of file
+}
+/// Get all alive tasks in the process group `pgid`
+// 需要借用每个进程的 inner ，因此调用者不能持有任何进程（包括当前进程）的 inner 的借用
+pub fn pgid2tasks(pgid: usize) -> Vec<Arc<TaskControlBlock>> {
+    let map = PID2TCB.exclusive_access();
+    map.values()
+        .filter(|task| task.inner_exclusive_access().pgid == pgid)
+        .map(Arc::clone)
+        .collect()
+}
+/// Whether any alive task has the canonical path `path` as its working directory
+// 与 pgid2tasks 一样，调用者不能持有任何进程的 inner 的借用
+pub fn cwd_in_use(path: &str) -> bool {
+    let map = PID2TCB.exclusive_access();
+    map.values()
+        .any(|task| task.inner_exclusive_access().cwd == path)
+}
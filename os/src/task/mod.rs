@@ -11,9 +11,13 @@
 
 mod action;
 mod context;
+mod loadavg;
+mod lowmem;
 mod manager;
 mod pid;
 mod processor;
+mod rusage;
+mod seccomp;
 mod signal;
 mod switch;
 
@@ -25,27 +29,49 @@ mod task;
 
 // use crate::config::MAX_APP_NUM;
 // use crate::loader::{get_num_app, init_app_cx};
-use crate::fs::{open_file, OpenFlags};
+use crate::config::INIT_ENV;
+use crate::fs::{block_cache_sync_all, open_inode, release_console_session, OpenFlags};
+use crate::mm::VirtAddr;
 use crate::sbi::shutdown;
+use alloc::string::String;
 use alloc::sync::Arc;
+use alloc::vec::Vec;
 pub use context::TaskContext;
 use lazy_static::*;
 use manager::fetch_task;
+use lowmem::unregister_lowmem;
 use manager::remove_from_pid2task;
 use switch::__switch;
-use task::{TaskControlBlock, TaskStatus};
+use task::TaskStatus;
 
 pub use action::{SignalAction, SignalActions};
-pub use manager::{add_task, pid2task};
+pub use loadavg::{load_averages, sample_load, LoadAvg};
+pub use lowmem::{notify_lowmem, register_lowmem};
+pub use manager::{add_task, cwd_in_use, pgid2tasks, pid2task};
 pub use pid::{pid_alloc, KernelStack, PidHandle};
 pub use processor::{
-    current_task, current_trap_cx, current_user_token, run_tasks, schedule, take_current_task,
+    cpu_times_ms, current_hart_id, current_task, current_trap_cx, current_user_token,
+    preempt_count, preempt_disable, preempt_enable, preempt_on_tick, preempt_test, run_tasks,
+    schedule, take_current_task, take_need_resched, PreemptGuard,
 };
+pub use rusage::Rusage;
+pub use seccomp::SyscallFilter;
 pub use signal::{SignalFlags, MAX_SIG};
+pub use task::{TaskControlBlock, TaskControlBlockInner};
 
 
 /// Suspend the current 'Running' task and run the next task in task list.
 pub fn suspend_current_and_run_next() {
+    switch_out_current(true);
+}
+
+/// Preempt the current 'Running' task when its time slice is used up.
+pub fn preempt_current_and_run_next() {
+    switch_out_current(false);
+}
+
+// voluntary 区分进程是主动让出处理器（比如 sys_yield 或者等待输入）还是因时钟中断被抢占，用于统计上下文切换次数
+fn switch_out_current(voluntary: bool) {
     // 首先通过 take_current_task 来取出当前正在执行的任务，修改其进程控制块内的状态
     // There must be an application running.
     let task = take_current_task().unwrap();
@@ -55,6 +81,14 @@ pub fn suspend_current_and_run_next() {
     let task_cx_ptr = &mut task_inner.task_cx as *mut TaskContext;
     // Change status to Ready
     task_inner.task_status = TaskStatus::Ready;
+    if voluntary {
+        task_inner.rusage.nvcsw += 1;
+    } else {
+        task_inner.rusage.nivcsw += 1;
+        task_inner.update_maxrss();
+        // 用完了整个时间片说明它正在做计算，不再享有等待 I/O 带来的优先调度
+        task_inner.io_boost = 0;
+    }
     drop(task_inner);
     // ---- release current PCB
     // 随后将这个任务放入任务管理器的队尾
@@ -68,6 +102,51 @@ pub fn suspend_current_and_run_next() {
     // 注意，当仅有一个任务的时候， suspend_current_and_run_next 的效果是会继续执行这个任务
 }
 
+// File::read 中没有数据可读时通过它让出处理器，而不是 suspend_current_and_run_next
+/// Suspend the current task which is waiting for I/O and run the next task.
+pub fn yield_for_io() {
+    current_task().unwrap().inner_exclusive_access().io_waiting = true;
+    suspend_current_and_run_next();
+}
+
+/// Block the current task which is waiting for I/O and run the next task.
+pub fn block_for_io() {
+    current_task().unwrap().inner_exclusive_access().io_waiting = true;
+    block_current_and_run_next();
+}
+
+/// Grant the current task a priority boost if it has just waited for I/O.
+pub fn end_current_io_wait() {
+    current_task().unwrap().inner_exclusive_access().end_io_wait();
+}
+
+/// Block the current 'Running' task and run the next task in task list.
+pub fn block_current_and_run_next() {
+    let task = take_current_task().unwrap();
+    let mut task_inner = task.inner_exclusive_access();
+    let task_cx_ptr = &mut task_inner.task_cx as *mut TaskContext;
+    task_inner.task_status = TaskStatus::Blocked;
+    task_inner.rusage.nvcsw += 1;
+    drop(task_inner);
+    // 与 suspend_current_and_run_next 不同，阻塞的任务不会被放回就绪队列，
+    // 它仍被 PID2TCB 和父进程持有，直到被 wakeup_task 重新加入任务管理器
+    drop(task);
+    schedule(task_cx_ptr);
+}
+
+/// Wake up a blocked task by putting it back to the ready queue
+pub fn wakeup_task(task: Arc<TaskControlBlock>) {
+    let mut task_inner = task.inner_exclusive_access();
+    if task_inner.task_status != TaskStatus::Blocked {
+        return;
+    }
+    task_inner.task_status = TaskStatus::Ready;
+    // 因为有数据到达而被唤醒的任务这一次就会被排在就绪队列的队头
+    task_inner.end_io_wait();
+    drop(task_inner);
+    add_task(task);
+}
+
 /// pid of usertests app in make run TEST=1
 pub const IDLE_PID: usize = 0;
 
@@ -83,6 +162,8 @@ pub fn exit_current_and_run_next(exit_code: i32) {
             "[kernel] Idle process exit with exit_code {} ...",
             exit_code
         );
+        // 文件的写入平时只停留在块缓存中，关机前全部写回磁盘
+        block_cache_sync_all();
         if exit_code != 0 {
             //crate::sbi::shutdown(255); //255 == -1 for err hint
             shutdown(true)
@@ -93,6 +174,7 @@ pub fn exit_current_and_run_next(exit_code: i32) {
     }
     // remove from pid2task
     remove_from_pid2task(task.getpid());
+    unregister_lowmem(pid);
     // **** access current TCB exclusively
     let mut inner = task.inner_exclusive_access();
     // 进程控制块中的状态修改为 TaskStatus::Zombie 即僵尸进程，这样它后续才能被父进程在 waitpid 系统调用的时候回收
@@ -101,6 +183,11 @@ pub fn exit_current_and_run_next(exit_code: i32) {
     // 将传入的退出码 exit_code 写入进程控制块中，后续父进程在 waitpid 的时候可以收集
     // Record exit code
     inner.exit_code = exit_code;
+    inner.update_maxrss();
+    // 会话首进程退出后，其会话失去控制终端
+    if inner.sid == pid {
+        release_console_session(pid);
+    }
     // do not move to its parent but under initproc
 
     // 将当前进程的所有子进程挂在初始进程 initproc 下面，其做法是遍历每个子进程，修改其父进程为初始进程，并加入初始进程的孩子向量中
@@ -108,9 +195,15 @@ pub fn exit_current_and_run_next(exit_code: i32) {
     {
         let mut initproc_inner = INITPROC.inner_exclusive_access();
         for child in inner.children.iter() {
-            child.inner_exclusive_access().parent = Some(Arc::downgrade(&INITPROC));
+            let mut child_inner = child.inner_exclusive_access();
+            child_inner.parent = Some(Arc::downgrade(&INITPROC));
+            child_inner.orphan = true;
+            drop(child_inner);
             initproc_inner.children.push(child.clone());
         }
+        // 被过继给 initproc 的孤儿进程退出后会一直以僵尸进程的形式挂在 initproc 的孩子向量中，
+        // 因此每当有进程退出时，内核都顺便直接回收 initproc 下已经成为僵尸的孤儿，避免它们无限累积
+        reap_zombie_orphans(&mut initproc_inner.children);
     }
     // ++++++ release parent PCB
     // 将当前进程的孩子向量清空
@@ -118,6 +211,10 @@ pub fn exit_current_and_run_next(exit_code: i32) {
     // 对于当前进程占用的资源进行早期回收
     // deallocate user space
     inner.memory_set.recycle_data_pages();
+    // 关闭所有打开的文件，这样管道的写端和文件锁等资源不必等到父进程回收时才被释放
+    inner.fd_table.clear();
+    inner.fd_cloexec.clear();
+    inner.fd_nonblock.clear();
     drop(inner);
     // **** release current PCB
     // drop task manually to maintain rc correctly
@@ -128,13 +225,41 @@ pub fn exit_current_and_run_next(exit_code: i32) {
     schedule(&mut _unused as *mut _);
 }
 
+/// Exit the current task because it was killed by signal `signum`
+pub fn kill_current_and_run_next(signum: u32) {
+    current_task().unwrap().inner_exclusive_access().term_signal = signum;
+    exit_current_and_run_next(-(signum as i32));
+}
+
+/// Release zombie orphans adopted by initproc which nobody else holds a reference to.
+fn reap_zombie_orphans(children: &mut Vec<Arc<TaskControlBlock>>) {
+    // 只有强引用计数为 1 的僵尸进程才能被安全地回收：正在退出的当前进程仍被 exit_current_and_run_next 持有
+    // （它的内核栈也还在使用中），所以会先被跳过，等到下一次有进程退出或者 fork 时再回收。
+    // 先判断引用计数可以避免再次借用当前进程已被独占访问的 inner 。
+    // initproc 自己创建的孩子仍然由它通过 waitpid 回收，内核不会拿走它们的退出码
+    children.retain(|child| {
+        if Arc::strong_count(child) != 1 {
+            return true;
+        }
+        let child_inner = child.inner_exclusive_access();
+        !(child_inner.orphan && child_inner.is_zombie())
+    });
+}
+
+/// Release the zombie orphans adopted by initproc, called before allocating a new process
+/// so that the pids they hold are reused
+pub fn reap_orphans() {
+    reap_zombie_orphans(&mut INITPROC.inner_exclusive_access().children);
+}
+
 lazy_static! {
     // 调用 TaskControlBlock::new 来创建一个进程控制块，它需要传入 ELF 可执行文件的数据切片作为参数
     ///Globle process that init user shell
     pub static ref INITPROC: Arc<TaskControlBlock> = Arc::new({
-        let inode = open_file("initproc", OpenFlags::RDONLY).unwrap();
+        let inode = open_inode("initproc", OpenFlags::RDONLY).unwrap();
         let v = inode.read_all();
-        TaskControlBlock::new(v.as_slice())
+        let env = INIT_ENV.iter().map(|var| String::from(*var)).collect();
+        TaskControlBlock::new_with_args(v.as_slice(), Vec::new(), env)
     });
 }
 ///Add init process to the manager
@@ -149,7 +274,37 @@ pub fn check_signals_error_of_current() -> Option<(i32, &'static str)> {
     //     "[K] check_signals_error_of_current {:?}",
     //     task_inner.signals
     // );
-    task_inner.signals.check_error()
+    // 交给 signalfd 读取的信号不会杀死进程
+    (task_inner.signals - task_inner.signalfd_mask()).check_error()
+}
+
+/// Count a page fault of the current task
+pub fn current_add_page_fault() {
+    let task = current_task().unwrap();
+    task.inner_exclusive_access().rusage.minflt += 1;
+}
+
+/// Change the current task's program break
+pub fn change_program_brk(size: i32) -> Option<usize> {
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    inner.change_program_brk(size)
+}
+
+/// Resolve a store fault of the current task on a page mapped to the shared zero frame
+pub fn current_unshare_zero_page(addr: usize) -> bool {
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    inner
+        .memory_set
+        .unshare_zero_page(VirtAddr::from(addr).floor())
+}
+
+/// Give private frames to the shared zero pages in `[start, start + len)` of the current task
+pub fn current_unshare_zero_range(start: usize, len: usize) {
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    inner.memory_set.unshare_zero_range(start, len);
 }
 
 pub fn current_add_signal(signal: SignalFlags) {
@@ -161,14 +316,41 @@ pub fn current_add_signal(signal: SignalFlags) {
     //     task_inner.signals
     // );
 }
-fn call_kernel_signal_handler(signal: SignalFlags) {
+/// Send `signal` to every task in the process group `pgid`, return false if the group is empty
+pub fn send_signal_to_group(pgid: usize, signal: SignalFlags) -> bool {
+    let tasks = pgid2tasks(pgid);
+    let found = !tasks.is_empty();
+    for task in tasks.into_iter() {
+        task.inner_exclusive_access().signals |= signal;
+        // 信号会唤醒阻塞中的进程
+        wakeup_task(task);
+    }
+    found
+}
+
+/// Whether the current task has a pending signal which is neither masked nor left for a signalfd
+pub fn current_has_pending_signal() -> bool {
     let task = current_task().unwrap();
-    let mut task_inner = task.inner_exclusive_access();
+    let task_inner = task.inner_exclusive_access();
+    !(task_inner.signals - task_inner.signal_mask - task_inner.signalfd_mask()).is_empty()
+}
+
+/// Whether the current task has been sent SIGKILL, in which case a blocking
+/// operation should give up waiting so that the task can exit
+pub fn current_cancelled() -> bool {
+    let task = current_task().unwrap();
+    let task_inner = task.inner_exclusive_access();
+    // SIGKILL 不能被屏蔽，因此不考虑信号掩码
+    task_inner.signals.contains(SignalFlags::SIGKILL)
+}
+
+// 信号处理函数直接使用调用者已经持有的借用，而不是再次借用当前进程的 inner
+fn call_kernel_signal_handler(task_inner: &mut TaskControlBlockInner, signal: SignalFlags) {
     match signal {
-        SignalFlags::SIGSTOP => {
+        SignalFlags::SIGSTOP | SignalFlags::SIGTSTP => {
             task_inner.frozen = true;
             // 清除掉接收到的信号避免它们再次被处理
-            task_inner.signals ^= SignalFlags::SIGSTOP;
+            task_inner.signals ^= signal;
         }
         SignalFlags::SIGCONT => {
             if task_inner.signals.contains(SignalFlags::SIGCONT) {
@@ -187,9 +369,11 @@ fn call_kernel_signal_handler(signal: SignalFlags) {
     }
 }
 
-fn call_user_signal_handler(sig: usize, signal: SignalFlags) {
-    let task = current_task().unwrap();
-    let mut task_inner = task.inner_exclusive_access();
+fn call_user_signal_handler(
+    task_inner: &mut TaskControlBlockInner,
+    sig: usize,
+    signal: SignalFlags,
+) {
     // 首先检查进程是否提供了该信号的处理例程，如果没有提供的话直接忽略该信号。否则就调用信号处理例程
     let handler = task_inner.signal_actions.table[sig].handler;
     if handler != 0 {
@@ -200,7 +384,7 @@ fn call_user_signal_handler(sig: usize, signal: SignalFlags) {
         task_inner.signals ^= signal;
 
         // backup trapframe
-        let mut trap_ctx = task_inner.get_trap_cx();
+        let trap_ctx = task_inner.get_trap_cx();
         task_inner.trap_ctx_backup = Some(*trap_ctx);
 
         // 修改 Trap 上下文的 sepc 到应用设置的例程地址使得 Trap 回到用户态之后就会跳转到例程入口并开始执行
@@ -215,14 +399,18 @@ fn call_user_signal_handler(sig: usize, signal: SignalFlags) {
         println!("[K] task/call_user_signal_handler: default action: ignore it or kill process");
     }
 }
+
+// 整个检查过程只借用一次当前进程的 inner ，避免在每个信号上反复借用
 fn check_pending_signals() {
+    let task = current_task().unwrap();
+    let mut task_inner = task.inner_exclusive_access();
+    // 被 signalfd 关注的信号和被屏蔽的信号一样留在 signals 中
+    let blocked = task_inner.signal_mask | task_inner.signalfd_mask();
     // 最外层循环遍历所有信号
     for sig in 0..(MAX_SIG + 1) {
-        let task = current_task().unwrap();
-        let task_inner = task.inner_exclusive_access();
         let signal = SignalFlags::from_bits(1 << sig).unwrap();
         // 检查当前进程是否接收到了遍历到的信号（条件 1）以及该信号是否未被当前进程全局屏蔽（条件 2）
-        if task_inner.signals.contains(signal) && (!task_inner.signal_mask.contains(signal)) {
+        if task_inner.signals.contains(signal) && (!blocked.contains(signal)) {
             let mut masked = true;
             let handling_sig = task_inner.handling_sig;
             // 检查该信号是否未被当前正在执行的信号处理例程屏蔽（条件 3）
@@ -239,20 +427,21 @@ fn check_pending_signals() {
             }
             // 当 3 个条件全部满足的时候，开始处理该信号
             if !masked {
-                drop(task_inner);
-                drop(task);
+                let has_handler = task_inner.signal_actions.table[sig].handler != 0;
                 // 目前的设计是：如果信号类型为 SIGKILL/SIGSTOP/SIGCONT/SIGDEF 四者之一，则该信号只能由内核来处理
                 // 否则调用 call_user_signal_handler 函数尝试使用进程提供的信号处理例程来处理
+                // 没有设置处理例程的 SIGTSTP 按照默认方式由内核暂停进程
                 if signal == SignalFlags::SIGKILL
                     || signal == SignalFlags::SIGSTOP
                     || signal == SignalFlags::SIGCONT
                     || signal == SignalFlags::SIGDEF
+                    || (signal == SignalFlags::SIGTSTP && !has_handler)
                 {
                     // signal is a kernel signal
-                    call_kernel_signal_handler(signal);
+                    call_kernel_signal_handler(&mut task_inner, signal);
                 } else {
                     // signal is a user signal
-                    call_user_signal_handler(sig, signal);
+                    call_user_signal_handler(&mut task_inner, sig, signal);
                     return;
                 }
             }
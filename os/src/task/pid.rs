@@ -1,50 +1,51 @@
 //!Implementation of [`PidAllocator`]
 use crate::config::{KERNEL_STACK_SIZE, PAGE_SIZE, TRAMPOLINE};
 use crate::mm::{MapPermission, VirtAddr, KERNEL_SPACE};
-use crate::sync::UPSafeCell;
+use alloc::format;
 use alloc::vec::Vec;
-use lazy_static::*;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use spin::Mutex;
 
 // 类似之前的物理页帧分配器 FrameAllocator ，我们实现一个同样使用简单栈式分配策略的进程标识符分配器 PidAllocator ，并将其全局实例化为 PID_ALLOCATOR 
+// 分配新的 pid 只需要原子地递增 current ，不必加锁；回收的 pid 则放在一个由自旋锁保护的栈 recycled 中。
+// 任何时刻每个 pid 要么不小于 current （从未分配过），要么被唯一一个 PidHandle 持有，要么在 recycled 中恰好出现一次：
+// fetch_add 保证多个核同时分配时得到的新 pid 互不相同，而从 recycled 中弹出也是在锁内进行的，同一个回收的 pid 不会被分配两次
 ///Pid Allocator struct
 pub struct PidAllocator {
-    current: usize,
-    recycled: Vec<usize>,
+    current: AtomicUsize,
+    recycled: Mutex<Vec<usize>>,
 }
 
 impl PidAllocator {
     ///Create an empty `PidAllocator`
-    pub fn new() -> Self {
+    pub const fn new() -> Self {
         PidAllocator {
-            current: 0,
-            recycled: Vec::new(),
+            current: AtomicUsize::new(0),
+            recycled: Mutex::new(Vec::new()),
         }
     }
     ///Allocate a pid
-    pub fn alloc(&mut self) -> PidHandle {
-        if let Some(pid) = self.recycled.pop() {
+    pub fn alloc(&self) -> PidHandle {
+        if let Some(pid) = self.recycled.lock().pop() {
             PidHandle(pid)
         } else {
-            self.current += 1;
-            PidHandle(self.current - 1)
+            PidHandle(self.current.fetch_add(1, Ordering::Relaxed))
         }
     }
     ///Recycle a pid
-    pub fn dealloc(&mut self, pid: usize) {
-        assert!(pid < self.current);
+    pub fn dealloc(&self, pid: usize) {
+        assert!(pid < self.current.load(Ordering::Relaxed));
+        let mut recycled = self.recycled.lock();
         assert!(
-            !self.recycled.iter().any(|ppid| *ppid == pid),
+            !recycled.iter().any(|ppid| *ppid == pid),
             "pid {} has been deallocated!",
             pid
         );
-        self.recycled.push(pid);
+        recycled.push(pid);
     }
 }
 
-lazy_static! {
-    pub static ref PID_ALLOCATOR: UPSafeCell<PidAllocator> =
-        unsafe { UPSafeCell::new(PidAllocator::new()) };
-}
+pub static PID_ALLOCATOR: PidAllocator = PidAllocator::new();
 
 // 同一时间存在的所有进程都有一个唯一的进程标识符，它们是互不相同的整数，这样才能表示表示进程的唯一性。
 // 使用 RAII 的思想，将其抽象为一个 PidHandle 类型，当它的生命周期结束后对应的整数会被编译器自动回收
@@ -55,13 +56,13 @@ pub struct PidHandle(pub usize);
 impl Drop for PidHandle {
     fn drop(&mut self) {
         //println!("drop pid {}", self.0);
-        PID_ALLOCATOR.exclusive_access().dealloc(self.0);
+        PID_ALLOCATOR.dealloc(self.0);
     }
 }
 //PidAllocator::alloc 将会分配出去一个将 usize 包装之后的 PidHandle 。我们将其包装为一个全局分配进程标识符的接口 pid_alloc 提供给内核的其他子模块
 ///Allocate a pid from PID_ALLOCATOR
 pub fn pid_alloc() -> PidHandle {
-    PID_ALLOCATOR.exclusive_access().alloc()
+    PID_ALLOCATOR.alloc()
 }
 
 /// Return (bottom, top) of a kernel stack in kernel space.
@@ -85,6 +86,7 @@ impl KernelStack {
             kernel_stack_bottom.into(),
             kernel_stack_top.into(),
             MapPermission::R | MapPermission::W,
+            format!("[kernel stack {}]", pid),
         );
         KernelStack { pid: pid_handle.0 }
     }
@@ -2,12 +2,19 @@
 // Processor 有一个不同的 idle 控制流，它运行在这个 CPU 核的启动栈上，功能是尝试从任务管理器中选出一个任务来在当前 CPU 核上执行。
 // 在内核初始化完毕之后，会通过调用 run_tasks 函数来进入 idle 控制流
 use super::__switch;
+use super::sample_load;
 use super::{fetch_task, TaskStatus};
 use super::{TaskContext, TaskControlBlock};
+use crate::config::{CLOCK_FREQ, HART_NUM};
+use crate::ipi::park;
+use crate::sbi::set_timer;
 use crate::sync::UPSafeCell;
+use crate::timer::get_time;
 use crate::trap::TrapContext;
 use alloc::sync::Arc;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use lazy_static::*;
+use riscv::register::sstatus;
 ///Processor management structure
 pub struct Processor {
     // 在当前处理器上正在执行的任务
@@ -16,6 +23,12 @@ pub struct Processor {
     // 当前处理器上的 idle 控制流的任务上下文
     ///The basic control flow of each core, helping to select and switch process
     idle_task_cx: TaskContext,
+    ///Id of the hart this processor runs on
+    hart_id: usize,
+    // 这个 hart 运行任务和空闲的总时间，以及上一次在两者之间切换的时刻，单位都是 mtime 的计数
+    busy_time: usize,
+    idle_time: usize,
+    last_switch: usize,
 }
 
 impl Processor {
@@ -24,8 +37,24 @@ impl Processor {
         Self {
             current: None,
             idle_task_cx: TaskContext::zero_init(),
+            hart_id: 0,
+            busy_time: 0,
+            idle_time: 0,
+            last_switch: 0,
         }
     }
+    // 把从上一次切换到现在的这段时间记为运行任务（busy）或者空闲的时间
+    ///Charge the time since the last switch to busy or idle time
+    fn account(&mut self, busy: bool) {
+        let now = get_time();
+        let elapsed = now - self.last_switch;
+        if busy {
+            self.busy_time += elapsed;
+        } else {
+            self.idle_time += elapsed;
+        }
+        self.last_switch = now;
+    }
     // 将 self.idle_task_cx 的可变引用转换为一个指向 TaskContext 的原始指针 (*mut TaskContext)。这通常用于需要传递指针而不是引用的情况
     ///Get mutable reference to `idle_task_cx`
     fn get_idle_task_cx_ptr(&mut self) -> *mut TaskContext {
@@ -49,13 +78,20 @@ impl Processor {
 lazy_static! {
     pub static ref PROCESSOR: UPSafeCell<Processor> = unsafe { UPSafeCell::new(Processor::new()) };
 }
+///Id of the hart the current task runs on
+pub fn current_hart_id() -> usize {
+    PROCESSOR.exclusive_access().hart_id
+}
 ///The main part of process execution and scheduling
 ///Loop `fetch_task` to get the process that needs to run, and switch the process through `__switch`
 pub fn run_tasks() {
+    // 内核初始化的时间不计入空闲时间
+    PROCESSOR.exclusive_access().last_switch = get_time();
     // 循环调用 fetch_task 直到顺利从任务管理器中取出一个任务，随后便准备通过任务切换的方式来执行
     loop {
         let mut processor = PROCESSOR.exclusive_access();
-        if let Some(task) = fetch_task() {
+        if let Some(task) = fetch_task(processor.hart_id) {
+            processor.account(false);
             let idle_task_cx_ptr = processor.get_idle_task_cx_ptr();
             // access coming task TCB exclusively
             let mut task_inner = task.inner_exclusive_access();
@@ -75,9 +111,30 @@ pub fn run_tasks() {
             unsafe {
                 __switch(idle_task_cx_ptr, next_task_cx_ptr);
             }
+            // 任务让出了处理器，从切换过去到现在 hart 一直在运行任务
+            PROCESSOR.exclusive_access().account(true);
+        } else {
+            drop(processor);
+            // 空闲的 hart 收不到时钟中断，由 idle 控制流来采样负载
+            sample_load(0);
+            // 没有可以运行的任务时停在 wfi 上，等其他 hart 加入新任务时用 IPI 叫醒。
+            // 只有一个 hart 时没有谁能在这期间加入任务，仍然像原来一样继续轮询
+            if HART_NUM > 1 {
+                park();
+            }
         }
     }
 }
+// 目前只有一个 Processor ，它的统计就是整个系统的统计；有多个 hart 时这里对所有 hart 求和
+///Total time all harts spent running tasks and idle, in milliseconds
+pub fn cpu_times_ms() -> (usize, usize) {
+    let processor = PROCESSOR.exclusive_access();
+    let ticks_per_ms = CLOCK_FREQ / 1000;
+    (
+        processor.busy_time / ticks_per_ms,
+        processor.idle_time / ticks_per_ms,
+    )
+}
 // 下面这两个函数是对 Processor::take_current/current 进行封装并提供给内核其他子模块的接口
 ///Take the current task,leaving a None in its place
 pub fn take_current_task() -> Option<Arc<TaskControlBlock>> {
@@ -105,9 +162,99 @@ pub fn current_trap_cx() -> &'static mut TrapContext {
 ///Return to idle control flow for new scheduling
 pub fn schedule(switched_task_cx_ptr: *mut TaskContext) {
     let mut processor = PROCESSOR.exclusive_access();
+    // 在不可抢占区间中切换出去，区间就会延续到其他任务中
+    debug_assert_eq!(preempt_count(), 0, "schedule with preemption disabled");
     let idle_task_cx_ptr = processor.get_idle_task_cx_ptr();
     drop(processor);
     unsafe {
         __switch(switched_task_cx_ptr, idle_task_cx_ptr);
     }
 }
+
+// 内核中持有锁的区间不应该被抢占：被抢占之后，同一个 hart 上的其他任务再获取这个锁只会一直自旋。
+// 时钟中断到来时如果正处于这样的区间中，只记下需要调度，等到区间结束、返回用户态之前再切换。
+// 内核态的时钟中断可能打断任何位置，包括正借用着 PROCESSOR 的代码，因此这两个状态放在原子变量中而不是 Processor 中。
+// 与 PROCESSOR 一样目前只有一份
+// 不可抢占区间的嵌套层数，不为 0 时时钟中断不会切换任务
+static PREEMPT_COUNT: AtomicUsize = AtomicUsize::new(0);
+// 不可抢占期间有时钟中断到来，区间结束之后需要补上一次调度
+static NEED_RESCHED: AtomicBool = AtomicBool::new(false);
+///Enter a preemption-disabled section, which may be nested
+pub fn preempt_disable() {
+    PREEMPT_COUNT.fetch_add(1, Ordering::Relaxed);
+}
+///Leave a preemption-disabled section entered by `preempt_disable`
+pub fn preempt_enable() {
+    let count = PREEMPT_COUNT.fetch_sub(1, Ordering::Relaxed);
+    assert!(count > 0, "unbalanced preempt_enable");
+}
+///Nesting depth of the preemption-disabled sections on this hart
+pub fn preempt_count() -> usize {
+    PREEMPT_COUNT.load(Ordering::Relaxed)
+}
+///Called on a timer tick: whether the current task may be preempted now. If not, the
+///preemption is deferred until all preemption-disabled sections have been left
+pub fn preempt_on_tick() -> bool {
+    if preempt_count() > 0 {
+        NEED_RESCHED.store(true, Ordering::Relaxed);
+        return false;
+    }
+    true
+}
+///Take the preemption deferred by preemption-disabled sections which have all been left
+pub fn take_need_resched() -> bool {
+    if preempt_count() > 0 {
+        return false;
+    }
+    NEED_RESCHED.swap(false, Ordering::Relaxed)
+}
+
+///A preemption-disabled section lasting until the guard is dropped
+pub struct PreemptGuard;
+
+impl PreemptGuard {
+    ///Disable preemption until the returned guard is dropped
+    pub fn new() -> Self {
+        preempt_disable();
+        Self
+    }
+}
+
+impl Drop for PreemptGuard {
+    fn drop(&mut self) {
+        preempt_enable();
+    }
+}
+
+#[allow(unused)]
+///Let a timer tick arrive inside nested preemption-disabled sections and check that the
+///switch is deferred until the outermost section ends
+pub fn preempt_test() {
+    assert_eq!(preempt_count(), 0);
+    let outer = PreemptGuard::new();
+    let inner = PreemptGuard::new();
+    assert_eq!(preempt_count(), 2);
+    // 让时钟中断立即到来并打开中断，等待 trap_from_kernel 记下需要调度。
+    // trap_from_kernel 返回时保持中断屏蔽，这个时钟中断一直挂起到之后重新设置下一次时钟中断为止
+    set_timer(get_time());
+    unsafe {
+        sstatus::set_sie();
+    }
+    let deadline = get_time() + CLOCK_FREQ;
+    while !NEED_RESCHED.load(Ordering::Relaxed) {
+        assert!(get_time() < deadline, "no timer interrupt in kernel mode");
+    }
+    unsafe {
+        sstatus::clear_sie();
+    }
+    drop(inner);
+    assert!(!preempt_on_tick());
+    assert!(!take_need_resched());
+    drop(outer);
+    assert!(take_need_resched());
+    assert!(!take_need_resched());
+    // 不在不可抢占区间中时立即可以切换，不会留下需要补上的调度
+    assert!(preempt_on_tick());
+    assert!(!take_need_resched());
+    println!("preempt_test passed!");
+}
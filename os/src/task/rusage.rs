@@ -0,0 +1,29 @@
+//! Resource usage statistics of a task
+
+/// Resource usage of a task, returned by `sys_getrusage`
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct Rusage {
+    /// maximum resident set size in KiB
+    pub maxrss: usize,
+    /// page faults serviced without any I/O
+    pub minflt: usize,
+    /// page faults which required I/O
+    pub majflt: usize,
+    /// voluntary context switches
+    pub nvcsw: usize,
+    /// involuntary context switches
+    pub nivcsw: usize,
+}
+
+impl Rusage {
+    // 将一个已经被回收的子进程的资源使用情况累加进来，其中 maxrss 取最大值而不是求和
+    /// Accumulate the usage of a reaped child
+    pub fn merge(&mut self, other: &Rusage) {
+        self.maxrss = self.maxrss.max(other.maxrss);
+        self.minflt += other.minflt;
+        self.majflt += other.majflt;
+        self.nvcsw += other.nvcsw;
+        self.nivcsw += other.nivcsw;
+    }
+}
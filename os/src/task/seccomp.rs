@@ -0,0 +1,28 @@
+//! Per-process syscall filter
+// 沙箱中的进程只能使用过滤器允许的系统调用，调用其他系统调用时会收到 SIGSYS 。
+// 过滤器只能收紧不能放宽：设置新的过滤器时与原有的过滤器取交集，并且 fork 和 exec 之后仍然保留
+
+// 非标准的系统调用号也在 2000 附近，2048 位足以覆盖所有的系统调用
+const FILTER_WORDS: usize = 32;
+
+/// Bitmap of allowed syscall IDs, passed to `sys_seccomp`
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct SyscallFilter {
+    bits: [u64; FILTER_WORDS],
+}
+
+impl SyscallFilter {
+    /// If the syscall numbered `id` is allowed
+    pub fn allows(&self, id: usize) -> bool {
+        self.bits
+            .get(id / 64)
+            .map_or(false, |word| word & (1 << (id % 64)) != 0)
+    }
+    /// Allow only the syscalls allowed by both `self` and `other`
+    pub fn restrict(&mut self, other: &SyscallFilter) {
+        for (word, other) in self.bits.iter_mut().zip(other.bits.iter()) {
+            *word &= *other;
+        }
+    }
+}
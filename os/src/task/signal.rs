@@ -43,6 +43,8 @@ impl SignalFlags {
     pub fn check_error(&self) -> Option<(i32, &'static str)> {
         if self.contains(Self::SIGINT) {
             Some((-2, "Killed, SIGINT=2"))
+        } else if self.contains(Self::SIGQUIT) {
+            Some((-3, "Quit, SIGQUIT=3"))
         } else if self.contains(Self::SIGILL) {
             Some((-4, "Illegal Instruction, SIGILL=4"))
         } else if self.contains(Self::SIGABRT) {
@@ -53,6 +55,8 @@ impl SignalFlags {
             Some((-9, "Killed, SIGKILL=9"))
         } else if self.contains(Self::SIGSEGV) {
             Some((-11, "Segmentation Fault, SIGSEGV=11"))
+        } else if self.contains(Self::SIGSYS) {
+            Some((-31, "Bad System Call, SIGSYS=31"))
         } else {
             //println!("[K] signalflags check_error  {:?}", self);
             None
@@ -1,16 +1,21 @@
 //!Implementation of [`TaskControlBlock`]
 use super::{pid_alloc, KernelStack, PidHandle, SignalFlags};
-use super::{SignalActions, TaskContext};
-use crate::config::TRAP_CONTEXT;
-use crate::fs::{File, Stdin, Stdout};
-use crate::mm::{translated_refmut, MemorySet, PhysPageNum, VirtAddr, KERNEL_SPACE};
-use crate::sync::UPSafeCell;
+use super::{Rusage, SignalActions, SyscallFilter, TaskContext};
+use crate::config::{HART_NUM, IO_BOOST_ROUNDS, PAGE_SIZE, TRAP_CONTEXT};
+use crate::fs::{File, IoRings, Stdin, Stdout};
+#[cfg(debug_assertions)]
+use crate::mm::verify_user_space;
+use crate::mm::{
+    copy_out, translated_refmut, ElfError, MemorySet, PhysPageNum, TlsTemplate, VirtAddr,
+    KERNEL_SPACE,
+};
+use crate::sync::{UPRefMut, UPSafeCell};
 use crate::trap::{trap_handler, TrapContext};
+use alloc::collections::BTreeSet;
 use alloc::string::String;
 use alloc::sync::{Arc, Weak};
 use alloc::vec;
 use alloc::vec::Vec;
-use core::cell::RefMut;
 
 // 一旦引入了任务切换机制就没有那么简单了。在一段时间内，内核需要管理多个未完成的应用，而且我们不能对应用完成的顺序做任何假定，并不是先加入的应用就一定会先完成。这种情况下，我们必须在内核中对每个应用分别维护它的运行状态
 // 通过 #[derive(...)] 可以让编译器为你的类型提供一些 Trait 的默认实现。
@@ -21,6 +26,8 @@ use core::cell::RefMut;
 pub enum TaskStatus {
     Ready,
     Running,
+    // 阻塞状态的进程不在任务管理器的就绪队列中，需要被唤醒之后才能再次被调度
+    Blocked,
     Zombie,
 }
 
@@ -51,14 +58,22 @@ pub struct TaskControlBlockInner {
     pub parent: Option<Weak<TaskControlBlock>>,
     // children 则将当前进程的所有子进程的任务控制块以 Arc 智能指针的形式保存在一个向量中，这样才能够更方便的找到它们
     pub children: Vec<Arc<TaskControlBlock>>,
+    // 父进程先退出、被过继给 initproc 的孤儿进程，它们退出之后由内核直接回收，而不是等待 initproc 调用 waitpid
+    pub orphan: bool,
     // 进程调用 exit 系统调用主动退出或者执行出错由内核终止的时候，它的退出码 exit_code 会被内核保存在它的任务控制块中，并等待它的父进程通过 waitpid 回收它的资源的同时也收集它的 PID 以及退出码
     pub exit_code: i32,
+    // 被信号终止时为信号编号，正常退出时为 0 。这时 exit_code 为信号编号的相反数，只凭它无法与以负数正常退出区分开
+    pub term_signal: u32,
     // 文件描述符表的相应字段
     // Vec 的动态长度特性使得我们无需设置一个固定的文件描述符数量上限，我们可以更加灵活的使用内存，而不必操心内存管理问题
     // Option 使得我们可以区分一个文件描述符当前是否空闲，当它是 None 的时候是空闲的，而 Some 则代表它已被占用
     // Arc 首先提供了共享引用能力,可能会有多个进程共享同一个文件对它进行读写。此外被它包裹的内容会被放到内核堆而不是栈上，于是它便不需要在编译期有着确定的大小
     // dyn 关键字表明 Arc 里面的类型实现了 File/Send/Sync 三个 Trait ，但是编译期无法知道它具体是哪个类型（可能是任何实现了 File Trait 的类型如 Stdin/Stdout ，故而它所占的空间大小自然也无法确定），需要等到运行时才能知道它的具体类型，对于一些抽象方法的调用也是在那个时候才能找到该类型实现的方法并跳转过去
     pub fd_table: Vec<Option<Arc<dyn File + Send + Sync>>>,
+    // 设置了 close-on-exec 标志的文件描述符，它们会在 exec 时被自动关闭
+    pub fd_cloexec: BTreeSet<usize>,
+    // 设置了 O_NONBLOCK 标志的文件描述符，在它们上面读不到数据时立即返回
+    pub fd_nonblock: BTreeSet<usize>,
     // signals 字段记录对应进程目前已经收到了哪些信号尚未处理，它的类型同样是 SignalFlags 表示一个信号集合
     pub signals: SignalFlags,
     // 进程的全局信号掩码
@@ -76,9 +91,34 @@ pub struct TaskControlBlockInner {
     pub frozen: bool,
     // trap_ctx_backup 则表示进程执行信号处理例程之前的 Trap 上下文
     pub trap_ctx_backup: Option<TrapContext>,
+    // pgid 为进程所属的进程组，sid 为进程所属的会话，它们都用组长/首进程的 pid 来标识
+    pub pgid: usize,
+    pub sid: usize,
+    // 进程的用户号，0 为特权用户。fork 时被子进程继承，exec 时不变
+    pub uid: u32,
+    // 进程自身的资源使用统计，以及所有已被回收的子进程的资源使用统计之和
+    pub rusage: Rusage,
+    pub children_rusage: Rusage,
     // 应用动态内存分配的堆空间的大小
-    // pub heap_bottom: usize,
-    // pub program_brk: usize,
+    pub heap_bottom: usize,
+    pub program_brk: usize,
+    // 当前工作目录，总是一个规范化的绝对路径
+    pub cwd: String,
+    // io_waiting 表示进程正在 read 中等待数据，io_boost 为等到数据之后剩余的优先调度轮数
+    pub io_waiting: bool,
+    pub io_boost: usize,
+    // 通过 sys_io_setup 登记的提交队列和完成队列
+    pub io_rings: Option<IoRings>,
+    // no_new_privs 一旦设置就不能清除；seccomp 为允许使用的系统调用，None 表示不加限制
+    pub no_new_privs: bool,
+    pub seccomp: Option<SyscallFilter>,
+    // CPU 亲和性掩码：第 i 位为 1 时进程可以在 hart i 上运行，默认可以在所有 hart 上运行
+    pub affinity: usize,
+    // nice 值越大，进程每次被调度时 pass 增加得越多，分到的处理器时间就越少
+    pub nice: isize,
+    pub pass: usize,
+    // 环境变量，每一项的形式为 NAME=value ，exec 时被压入新程序的用户栈
+    pub env: Vec<String>,
 }
 
 impl TaskControlBlockInner {
@@ -99,6 +139,55 @@ impl TaskControlBlockInner {
     pub fn is_zombie(&self) -> bool {
         self.get_status() == TaskStatus::Zombie
     }
+    /// If the syscall numbered `id` passes the seccomp filter
+    pub fn syscall_allowed(&self, id: usize) -> bool {
+        self.seccomp.map_or(true, |filter| filter.allows(id))
+    }
+    /// Signals which are left pending for the signalfds in the fd table to read
+    pub fn signalfd_mask(&self) -> SignalFlags {
+        self.fd_table
+            .iter()
+            .flatten()
+            .fold(SignalFlags::empty(), |mask, file| {
+                mask | file.signalfd_mask()
+            })
+    }
+    // 堆空间对应的逻辑段从 heap_bottom 开始，改变 program_brk 时通过 append_to/shrink_to 相应地扩展或收缩这个逻辑段
+    /// change the location of the program break. return None if failed.
+    pub fn change_program_brk(&mut self, size: i32) -> Option<usize> {
+        let old_break = self.program_brk;
+        let new_brk = self.program_brk as isize + size as isize;
+        // 堆不能越过 mmap 区的下界
+        if new_brk < self.heap_bottom as isize || new_brk as usize > self.memory_set.mmap_base() {
+            return None;
+        }
+        let result = if size < 0 {
+            self.memory_set
+                .shrink_to(VirtAddr(self.heap_bottom), VirtAddr(new_brk as usize))
+        } else {
+            self.memory_set
+                .append_to(VirtAddr(self.heap_bottom), VirtAddr(new_brk as usize))
+        };
+        if result {
+            self.program_brk = new_brk as usize;
+            Some(old_break)
+        } else {
+            None
+        }
+    }
+    // 等待 I/O 的进程多半是交互式的，等到数据之后给它几轮优先调度，使它能尽快处理这些数据
+    /// Grant a priority boost if the task has been waiting for I/O
+    pub fn end_io_wait(&mut self) {
+        if self.io_waiting {
+            self.io_waiting = false;
+            self.io_boost = IO_BOOST_ROUNDS;
+        }
+    }
+    // 用地址空间当前驻留的物理页帧数更新最大驻留集大小
+    pub fn update_maxrss(&mut self) {
+        let rss = self.memory_set.resident_pages() * PAGE_SIZE / 1024;
+        self.rusage.maxrss = self.rusage.maxrss.max(rss);
+    }
     // 在进程控制块中分配一个最小的空闲文件描述符来访问一个新打开的文件。它先从小到大遍历所有曾经被分配过的文件描述符尝试找到一个空闲的，如果没有的话就需要拓展文件描述符表的长度并新分配一个
     pub fn alloc_fd(&mut self) -> usize {
         if let Some(fd) = (0..self.fd_table.len()).find(|fd: &usize| self.fd_table[*fd].is_none()) {
@@ -110,15 +199,73 @@ impl TaskControlBlockInner {
     }
 }
 
+// 堆空间放在用户栈的上方，两者之间隔着一个保护页，这样栈和堆都不会在不知不觉中越界到对方的区域。
+// 初始时堆对应的逻辑段长度为 0 ，之后通过 sbrk 从 heap_bottom 开始向高地址扩展，最多扩展 USER_HEAP_SIZE 字节
+/// Insert an empty heap area above the user stack and return its bottom
+fn map_user_heap(memory_set: &mut MemorySet, user_stack_top: usize) -> usize {
+    let heap_bottom = user_stack_top + PAGE_SIZE;
+    memory_set.insert_heap_area(heap_bottom);
+    heap_bottom
+}
+
+// 首先需要在用户栈上分配一个字符串指针数组，数组中的每个元素都指向一个用户栈更低处的字符串的起始地址，
+// 数组以 0 结尾。然后逐个将字符串压入到用户栈中。之前压入的字符串可能使 user_sp 没有对齐，数组需要先对齐
+/// Push `strings` and an array pointing to them onto the user stack, returning the array base
+fn push_strings(token: usize, user_sp: &mut usize, strings: &[String]) -> usize {
+    let size = core::mem::size_of::<usize>();
+    *user_sp -= *user_sp % size;
+    *user_sp -= (strings.len() + 1) * size;
+    let base = *user_sp;
+    let mut ptrs: Vec<_> = (0..=strings.len())
+        .map(|i| translated_refmut(token, (base + i * size) as *mut usize))
+        .collect();
+    *ptrs[strings.len()] = 0;
+    for (ptr, string) in ptrs.iter_mut().zip(strings.iter()) {
+        *user_sp -= string.len() + 1;
+        **ptr = *user_sp;
+        copy_out(token, *user_sp as *mut u8, string.as_bytes());
+        copy_out(token, (*user_sp + string.len()) as *mut u8, &[0]);
+    }
+    base
+}
+
+// 在用户栈顶建立主线程的 TLS 块：先复制 .tdata 的内容，其余的 .tbss 部分清零。
+// RISC-V 的 tp 直接指向 TLS 块的起始位置，返回这个地址作为 tp 的初值
+fn push_tls(token: usize, user_sp: &mut usize, tls: Option<TlsTemplate>) -> usize {
+    let tls = match tls {
+        Some(tls) => tls,
+        None => return 0,
+    };
+    *user_sp -= tls.mem_size;
+    *user_sp -= *user_sp % tls.align;
+    copy_out(token, *user_sp as *mut u8, tls.data);
+    let bss = vec![0u8; tls.mem_size - tls.data.len()];
+    copy_out(token, (*user_sp + tls.data.len()) as *mut u8, &bss);
+    *user_sp
+}
+
 impl TaskControlBlock {
-    pub fn inner_exclusive_access(&self) -> RefMut<'_, TaskControlBlockInner> {
+    #[track_caller]
+    pub fn inner_exclusive_access(&self) -> UPRefMut<'_, TaskControlBlockInner> {
         self.inner.exclusive_access()
     }
     // new 用来创建一个新的进程，目前仅用于内核中手动创建唯一一个初始进程 initproc
     pub fn new(elf_data: &[u8]) -> Self {
+        Self::new_with_args(elf_data, Vec::new(), Vec::new())
+    }
+    // 与 new 相同，但像 exec 一样把命令行参数和环境变量压入用户栈
+    pub fn new_with_args(elf_data: &[u8], args: Vec<String>, env: Vec<String>) -> Self {
         // memory_set with elf program headers/trampoline/trap context/user stack
         // 解析应用的 ELF 执行文件得到应用地址空间 memory_set ，用户栈在应用地址空间中的位置 user_sp 以及应用的入口点 entry_point
-        let (memory_set, user_sp, entry_point) = MemorySet::from_elf(elf_data);
+        let (mut memory_set, mut user_sp, entry_point, tls) = MemorySet::from_elf(elf_data)
+            .unwrap_or_else(|err| panic!("invalid elf of initproc: {:?}", err));
+        #[cfg(debug_assertions)]
+        verify_user_space(&memory_set, user_sp);
+        let heap_bottom = map_user_heap(&mut memory_set, user_sp);
+        let tls_base = push_tls(memory_set.token(), &mut user_sp, tls);
+        let envp_base = push_strings(memory_set.token(), &mut user_sp, &env);
+        let argv_base = push_strings(memory_set.token(), &mut user_sp, &args);
+        user_sp -= user_sp % core::mem::size_of::<usize>();
         // 手动查页表找到位于应用地址空间中新创建的Trap 上下文被实际放在哪个物理页帧上，用来做后续的初始化
         let trap_cx_ppn = memory_set
             .translate(VirtAddr::from(TRAP_CONTEXT).into())
@@ -127,6 +274,7 @@ impl TaskControlBlock {
         // 为该进程分配 PID 以及内核栈，并记录下内核栈在内核地址空间的位置 kernel_stack_top
         // alloc a pid and a kernel stack in kernel space
         let pid_handle = pid_alloc();
+        let pid = pid_handle.0;
         let kernel_stack = KernelStack::new(&pid_handle);
         let kernel_stack_top = kernel_stack.get_top();
         // push a task context which goes to trap_return to the top of kernel stack
@@ -142,7 +290,9 @@ impl TaskControlBlock {
                     memory_set,
                     parent: None,
                     children: Vec::new(),
+                    orphan: false,
                     exit_code: 0,
+                    term_signal: 0,
                     // 当一个进程被创建的时候，内核会默认为其打开三个缺省就存在的文件：文件描述符为 0 的标准输入、文件描述符为 1 的标准输出、文件描述符为 2 的标准错误输出
                     fd_table: vec![
                         // 0 -> stdin
@@ -152,6 +302,8 @@ impl TaskControlBlock {
                         // 2 -> stderr
                         Some(Arc::new(Stdout)),
                     ],
+                    fd_cloexec: BTreeSet::new(),
+                    fd_nonblock: BTreeSet::new(),
                     signals: SignalFlags::empty(),
                     signal_mask: SignalFlags::empty(),
                     handling_sig: -1,
@@ -159,6 +311,24 @@ impl TaskControlBlock {
                     killed: false,
                     frozen: false,
                     trap_ctx_backup: None,
+                    // 初始进程自成一个进程组和会话
+                    pgid: pid,
+                    sid: pid,
+                    uid: 0,
+                    rusage: Rusage::default(),
+                    children_rusage: Rusage::default(),
+                    heap_bottom,
+                    program_brk: heap_bottom,
+                    cwd: String::from("/"),
+                    io_waiting: false,
+                    io_boost: 0,
+                    io_rings: None,
+                    no_new_privs: false,
+                    seccomp: None,
+                    affinity: (1 << HART_NUM) - 1,
+                    nice: 0,
+                    pass: 0,
+                    env,
                 })
             },
         };
@@ -172,40 +342,36 @@ impl TaskControlBlock {
             kernel_stack_top,
             trap_handler as usize,
         );
+        trap_cx.x[10] = args.len();
+        trap_cx.x[11] = argv_base;
+        trap_cx.x[12] = envp_base;
+        // tp 指向主线程的 TLS 块，没有 TLS 段时为 0
+        trap_cx.x[4] = tls_base;
         task_control_block
     }
-    // exec 用来实现 exec 系统调用，即当前进程加载并执行另一个 ELF 格式可执行文件
-    pub fn exec(&self, elf_data: &[u8], args: Vec<String>) {
+    // exec 用来实现 exec 系统调用，即当前进程加载并执行另一个 ELF 格式可执行文件。如果 ELF 文件不合法则返回错误，此时当前进程保持不变
+    pub fn exec(
+        &self,
+        elf_data: &[u8],
+        args: Vec<String>,
+        env: Option<Vec<String>>,
+    ) -> Result<(), ElfError> {
         // memory_set with elf program headers/trampoline/trap context/user stack
-        let (memory_set, mut user_sp, entry_point) = MemorySet::from_elf(elf_data);
+        let (mut memory_set, mut user_sp, entry_point, tls) = MemorySet::from_elf(elf_data)?;
+        #[cfg(debug_assertions)]
+        verify_user_space(&memory_set, user_sp);
+        let heap_bottom = map_user_heap(&mut memory_set, user_sp);
+        // TLS 块位于用户栈顶，在它下面才是环境变量和命令行参数
+        let tls_base = push_tls(memory_set.token(), &mut user_sp, tls);
         let trap_cx_ppn = memory_set
             .translate(VirtAddr::from(TRAP_CONTEXT).into())
             .unwrap()
             .ppn();
-        // 首先需要在用户栈上分配一个字符串指针数组。数组中的每个元素都指向一个用户栈更低处的命令行参数字符串的起始地址
-        // push arguments on user stack
-        user_sp -= (args.len() + 1) * core::mem::size_of::<usize>();
-        let argv_base = user_sp;
-        let mut argv: Vec<_> = (0..=args.len())
-            .map(|arg| {
-                translated_refmut(
-                    memory_set.token(),
-                    (argv_base + arg * core::mem::size_of::<usize>()) as *mut usize,
-                )
-            })
-            .collect();
-        *argv[args.len()] = 0;
-        // 我们逐个将传入的 args 中的字符串压入到用户栈中
-        for i in 0..args.len() {
-            user_sp -= args[i].len() + 1;
-            *argv[i] = user_sp;
-            let mut p = user_sp;
-            for c in args[i].as_bytes() {
-                *translated_refmut(memory_set.token(), p as *mut u8) = *c;
-                p += 1;
-            }
-            *translated_refmut(memory_set.token(), p as *mut u8) = 0;
-        }
+        // 没有指定环境变量时新程序继承原有的环境变量，它们和命令行参数一起被压入用户栈
+        // push environment and arguments on user stack
+        let env = env.unwrap_or_else(|| self.inner_exclusive_access().env.clone());
+        let envp_base = push_strings(memory_set.token(), &mut user_sp, &env);
+        let argv_base = push_strings(memory_set.token(), &mut user_sp, &args);
         // 将 user_sp 以 8 字节对齐。这是因为命令行参数的长度不一，很有可能压入之后 user_sp 没有对齐到 8 字节
         // make the user_sp aligned to 8B for k210 platform
         user_sp -= user_sp % core::mem::size_of::<usize>();
@@ -218,6 +384,21 @@ impl TaskControlBlock {
         inner.trap_cx_ppn = trap_cx_ppn;
         // initialize base_size
         inner.base_size = user_sp;
+        // 原有地址空间中的信号处理例程在新程序中已经不再有效，需要恢复为默认处理方式
+        inner.signal_actions = SignalActions::default();
+        // 资源使用统计从新程序开始重新计数
+        inner.rusage = Rusage::default();
+        inner.heap_bottom = heap_bottom;
+        inner.program_brk = heap_bottom;
+        // 登记的队列随原有地址空间一起失效
+        inner.io_rings = None;
+        inner.env = env;
+        // 关闭所有设置了 close-on-exec 标志的文件描述符
+        let cloexec = core::mem::take(&mut inner.fd_cloexec);
+        for fd in cloexec {
+            inner.fd_table[fd] = None;
+            inner.fd_nonblock.remove(&fd);
+        }
         // 修改新的地址空间中的 Trap 上下文，将解析得到的应用入口点、用户栈位置以及一些内核的信息进行初始化，这样才能正常实现 Trap 机制
         // initialize trap_cx
         let trap_cx = inner.get_trap_cx();
@@ -229,11 +410,14 @@ impl TaskControlBlock {
             trap_handler as usize,
         );
         // 修改 Trap 上下文中的 a0/a1 寄存器，让 a0 表示命令行参数的个数，而 a1 则表示图中 argv_base 即字符串指针数组的起始地址
-        // 这两个参数在第一次进入对应应用的用户态的时候会被接收并用于还原命令行参数
+        // 这两个参数在第一次进入对应应用的用户态的时候会被接收并用于还原命令行参数，a2 则是环境变量指针数组的起始地址
         trap_cx.x[10] = args.len();
         trap_cx.x[11] = argv_base;
+        trap_cx.x[12] = envp_base;
+        // tp 指向主线程的 TLS 块，没有 TLS 段时为 0
+        trap_cx.x[4] = tls_base;
         // 无需对任务上下文进行处理，因为这个进程本身已经在执行了，而只有被暂停的应用才需要在内核栈上保留一个任务上下文
-        
+        Ok(())
         // **** release inner automatically
     }
     // fork 用来实现 fork 系统调用，即当前进程 fork 出来一个与之几乎相同的子进程
@@ -278,8 +462,12 @@ impl TaskControlBlock {
                     // 在需要使用父进程时，可以通过弱引用尝试获取其强引用，如果父进程已经被销毁，则获取到的结果会是 None
                     parent: Some(Arc::downgrade(self)),
                     children: Vec::new(),
+                    orphan: false,
                     exit_code: 0,
+                    term_signal: 0,
                     fd_table: new_fd_table,
+                    fd_cloexec: parent_inner.fd_cloexec.clone(),
+                    fd_nonblock: parent_inner.fd_nonblock.clone(),
                     signals: SignalFlags::empty(),
                     // inherit the signal_mask and signal_action
                     signal_mask: parent_inner.signal_mask,
@@ -288,6 +476,25 @@ impl TaskControlBlock {
                     killed: false,
                     frozen: false,
                     trap_ctx_backup: None,
+                    // 子进程与父进程处于同一个进程组和会话中
+                    pgid: parent_inner.pgid,
+                    sid: parent_inner.sid,
+                    uid: parent_inner.uid,
+                    rusage: Rusage::default(),
+                    children_rusage: Rusage::default(),
+                    heap_bottom: parent_inner.heap_bottom,
+                    program_brk: parent_inner.program_brk,
+                    cwd: parent_inner.cwd.clone(),
+                    io_waiting: false,
+                    io_boost: 0,
+                    // 子进程的地址空间是父进程的副本，队列也在相同的位置
+                    io_rings: parent_inner.io_rings,
+                    no_new_privs: parent_inner.no_new_privs,
+                    seccomp: parent_inner.seccomp,
+                    affinity: parent_inner.affinity,
+                    nice: parent_inner.nice,
+                    pass: parent_inner.pass,
+                    env: parent_inner.env.clone(),
                 })
             },
         });
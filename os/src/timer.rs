@@ -2,10 +2,18 @@
 
 use crate::config::CLOCK_FREQ;
 use crate::sbi::set_timer;
+use crate::sync::UPSafeCell;
+use crate::task::{wakeup_task, TaskControlBlock};
+use alloc::collections::BinaryHeap;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use lazy_static::*;
 use riscv::register::time;
 
 const TICKS_PER_SEC: usize = 100;
 const MSEC_PER_SEC: usize = 1000;
+const USEC_PER_SEC: usize = 1_000_000;
 
 // RISC-V 架构要求处理器要有一个内置时钟，其频率一般低于 CPU 主频。此外，还有一个计数器用来统计处理器自上电以来经过了多少个内置时钟的时钟周期。
 // 在 RISC-V 64 架构上，该计数器保存在一个 64 位的 CSR mtime 中，我们无需担心它的溢出问题，在内核运行全程可以认为它是一直递增的。
@@ -26,5 +34,125 @@ pub fn get_time_ms() -> usize {
 // 对 set_timer 进行了封装，它首先读取当前 mtime 的值，然后计算出 10ms 之内计数器的增量，再将 mtimecmp 设置为二者的和。这样，10ms 之后一个 S 特权级时钟中断就会被触发
 pub fn set_next_trigger() {
     // CLOCK_FREQ 除以常数 TICKS_PER_SEC 即是下一次时钟中断的计数器增量值
-    set_timer(get_time() + CLOCK_FREQ / TICKS_PER_SEC);
+    let deadline = get_time() + CLOCK_FREQ / TICKS_PER_SEC;
+    IRQ_LATENCY.exclusive_access().deadline = deadline;
+    set_timer(deadline);
+}
+
+// 时钟中断的延迟是从设定的时刻到 trap_handler 开始处理这个中断所经过的时间。
+// 内核态屏蔽了中断，时钟中断只能在回到用户态之后才被处理，因此长时间不返回用户态的系统调用会直接体现为延迟
+/// Latency statistics of the timer interrupt returned by `sys_irq_stats`
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct IrqStats {
+    /// number of timer interrupts handled
+    pub count: usize,
+    /// max latency in microseconds
+    pub max_us: usize,
+    /// average latency in microseconds
+    pub avg_us: usize,
+}
+
+/// Latency of the timer interrupts handled so far, in clock cycles
+#[derive(Default)]
+struct IrqLatency {
+    // 下一次时钟中断设定的时刻
+    deadline: usize,
+    count: usize,
+    total: usize,
+    max: usize,
+}
+
+lazy_static! {
+    static ref IRQ_LATENCY: UPSafeCell<IrqLatency> =
+        unsafe { UPSafeCell::new(IrqLatency::default()) };
+}
+
+/// Account a timer interrupt whose handling started at `entry_time`
+pub fn record_timer_latency(entry_time: usize) {
+    let mut latency = IRQ_LATENCY.exclusive_access();
+    let cycles = entry_time.saturating_sub(latency.deadline);
+    latency.count += 1;
+    latency.total += cycles;
+    latency.max = latency.max.max(cycles);
+}
+
+/// Latency statistics of the timer interrupt, cleared afterwards if `reset`
+pub fn irq_stats(reset: bool) -> IrqStats {
+    let mut latency = IRQ_LATENCY.exclusive_access();
+    let to_us = |cycles: usize| cycles * USEC_PER_SEC / CLOCK_FREQ;
+    let stats = IrqStats {
+        count: latency.count,
+        max_us: to_us(latency.max),
+        avg_us: to_us(latency.total.checked_div(latency.count).unwrap_or(0)),
+    };
+    if reset {
+        latency.count = 0;
+        latency.total = 0;
+        latency.max = 0;
+    }
+    stats
+}
+
+// 等待某个时刻到来的进程被挂在定时器队列上，每次时钟中断时唤醒所有已经到期的进程
+/// A task waiting until the time `expire_ms`
+pub struct TimerCondVar {
+    /// the time in milliseconds at which the task should be woken up
+    pub expire_ms: usize,
+    /// the waiting task
+    pub task: Arc<TaskControlBlock>,
+}
+
+impl PartialEq for TimerCondVar {
+    fn eq(&self, other: &Self) -> bool {
+        self.expire_ms == other.expire_ms
+    }
+}
+impl Eq for TimerCondVar {}
+// BinaryHeap 是大根堆，这里反转比较顺序，使得最早到期的定时器位于堆顶
+impl PartialOrd for TimerCondVar {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TimerCondVar {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.expire_ms.cmp(&self.expire_ms)
+    }
+}
+
+lazy_static! {
+    static ref TIMERS: UPSafeCell<BinaryHeap<TimerCondVar>> =
+        unsafe { UPSafeCell::new(BinaryHeap::<TimerCondVar>::new()) };
+}
+
+/// Wake up `task` once the time reaches `expire_ms`
+pub fn add_timer(expire_ms: usize, task: Arc<TaskControlBlock>) {
+    let mut timers = TIMERS.exclusive_access();
+    timers.push(TimerCondVar { expire_ms, task });
+}
+
+// 进程因为其他原因（比如收到信号）被提前唤醒时，需要撤销它尚未到期的定时器
+/// Remove all the timers of `task`
+pub fn remove_timer(task: &Arc<TaskControlBlock>) {
+    let mut timers = TIMERS.exclusive_access();
+    timers.retain(|condvar| !Arc::ptr_eq(&condvar.task, task));
+}
+
+/// Wake up all the tasks whose timers have expired
+pub fn check_timer() {
+    let current_ms = get_time_ms();
+    let mut expired = Vec::new();
+    let mut timers = TIMERS.exclusive_access();
+    while let Some(timer) = timers.peek() {
+        if timer.expire_ms > current_ms {
+            break;
+        }
+        expired.push(timers.pop().unwrap().task);
+    }
+    drop(timers);
+    for task in expired {
+        wakeup_task(task);
+    }
 }
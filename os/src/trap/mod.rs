@@ -14,29 +14,41 @@
 
 mod context;
 
-use crate::config::{TRAMPOLINE, TRAP_CONTEXT};
+use crate::config::{COREDUMP, COREDUMP_STACK_BYTES, TRAMPOLINE, TRAP_CONTEXT};
+use crate::fs::console_poll;
+use crate::ipi::handle_ipi;
+use crate::mm::{PageTable, VirtAddr};
+use crate::profile::{profile_enabled, profile_kernel, profile_user};
+#[cfg(debug_assertions)]
+use crate::sync::check_lock_watchdog;
 use crate::syscall::syscall;
 use crate::task::{
-    check_signals_error_of_current, current_add_signal, current_trap_cx, current_user_token,
-    exit_current_and_run_next, handle_signals, suspend_current_and_run_next, SignalFlags,
+    check_signals_error_of_current, current_add_page_fault, current_add_signal, current_task,
+    current_trap_cx, current_unshare_zero_page, current_user_token, handle_signals,
+    kill_current_and_run_next, notify_lowmem, preempt_current_and_run_next, preempt_on_tick,
+    sample_load, take_need_resched, SignalFlags,
 };
-use crate::timer::set_next_trigger;
+use crate::timer::{check_timer, get_time, record_timer_latency, set_next_trigger};
 use core::arch::{asm, global_asm};
 use riscv::register::{
     mtvec::TrapMode,
     scause::{self, Exception, Interrupt, Trap},
-    sie, stval, stvec,
+    sepc, sie, sstatus, stval, stvec,
 };
 
 global_asm!(include_str!("trap.S"));
+global_asm!(include_str!("kernel_trap.S"));
 /// initialize CSR `stvec` as the entry of `__alltraps`
 pub fn init() {
     set_kernel_trap_entry();
 }
 fn set_kernel_trap_entry() {
+    extern "C" {
+        fn __trap_from_kernel();
+    }
     // "stvec" 寄存器存储了异常处理程序的入口地址
     unsafe {
-        stvec::write(trap_from_kernel as usize, TrapMode::Direct);
+        stvec::write(__trap_from_kernel as usize, TrapMode::Direct);
     }
 }
 fn set_user_trap_entry() {
@@ -67,8 +79,10 @@ pub fn enable_timer_interrupt() {
 
 #[no_mangle]
 pub fn trap_handler() -> ! {
+    // 进入 trap_handler 的时刻，用来计算时钟中断的延迟
+    let entry_time = get_time();
     // 在 trap_handler 的开头还调用 set_kernel_trap_entry 将 stvec 修改为同模块下另一个函数 trap_from_kernel 的地址。这就是说，一旦进入内核后再次触发到 S态 Trap，则硬件在设置一些 CSR 寄存器之后，会跳过对通用寄存器的保存过程，直接跳转到 trap_from_kernel 函数，在这里直接 panic 退出。
-    // 这里为了简单起见，弱化了 S态 –> S态的 Trap 处理过程：直接 panic 。
+    // 这里为了简单起见，弱化了 S态 –> S态的 Trap 处理过程：除了剖析内核时打开中断之后到来的中断，其余都直接 panic 。
     set_kernel_trap_entry();
     // 由于应用的 Trap 上下文不在内核地址空间，因此我们调用 current_trap_cx 来获取当前应用的 Trap 上下文的可变引用而不是像之前那样作为参数传入 trap_handler
     let scause = scause::read();
@@ -79,13 +93,23 @@ pub fn trap_handler() -> ! {
             let mut cx = current_trap_cx();
             // 在调用 syscall 进行系统调用分发并具体调用 sys_fork 之前，trap_handler 已经将当前进程 Trap 上下文中的 sepc 向后移动了 4 字节，使得它回到用户态之后，会从发出系统调用的 ecall 指令的下一条指令开始执行
             cx.sepc += 4;
+            // 剖析内核时在系统调用期间打开中断，让时钟中断可以在内核态采样
+            if profile_enabled() {
+                unsafe {
+                    sstatus::set_sie();
+                }
+            }
             // get system call return value
-            let result = syscall(cx.x[17], [cx.x[10], cx.x[11], cx.x[12]]);
+            let result = syscall(cx.x[17], [cx.x[10], cx.x[11], cx.x[12], cx.x[13], cx.x[14]]);
             // cx is changed during sys_exec, so we have to call it again
             cx = current_trap_cx();
             // 父进程系统调用的返回值会在 trap_handler 中 syscall 返回之后再设置为 sys_fork 的返回值，这里我们返回子进程的 PID
             cx.x[10] = result as usize;
         }
+        // 写入仍映射到共享零页的按需清零页面：换成私有页帧之后回到用户态重新执行这条写指令即可
+        Trap::Exception(Exception::StorePageFault) if current_unshare_zero_page(stval) => {
+            current_add_page_fault();
+        }
         Trap::Exception(Exception::StoreFault)
         | Trap::Exception(Exception::StorePageFault)
         | Trap::Exception(Exception::InstructionFault)
@@ -100,6 +124,7 @@ pub fn trap_handler() -> ! {
             // );
             // page fault exit code
             // exit_current_and_run_next(-2);
+            current_add_page_fault();
             current_add_signal(SignalFlags::SIGSEGV);
         }
         Trap::Exception(Exception::IllegalInstruction) => {
@@ -109,8 +134,13 @@ pub fn trap_handler() -> ! {
             current_add_signal(SignalFlags::SIGILL);
         }
         Trap::Interrupt(Interrupt::SupervisorTimer) => {
-            set_next_trigger();
-            suspend_current_and_run_next();
+            handle_timer_tick(entry_time);
+            if preempt_on_tick() {
+                preempt_current_and_run_next();
+            }
+        }
+        Trap::Interrupt(Interrupt::SupervisorSoft) => {
+            handle_ipi();
         }
         _ => {
             panic!(
@@ -120,17 +150,77 @@ pub fn trap_handler() -> ! {
             );
         }
     }
+    // 时钟中断在内核态的不可抢占区间中到来，区间已经结束。这个中断仍然挂起着，在这里处理它并补上这次调度，
+    // 否则回到用户态之后它又会再切换一次
+    if take_need_resched() {
+        handle_timer_tick(get_time());
+        preempt_current_and_run_next();
+    }
     // handle signals (handle the sent signal)
+    // 内存压力事件在分配页帧时只被记录下来，此时没有进程的 inner 被借用，可以安全地发送信号
+    notify_lowmem();
     //println!("[K] trap_handler:: handle_signals");
     handle_signals();
 
     // check error signals (if error then exit)
     if let Some((errno, msg)) = check_signals_error_of_current() {
         println!("[kernel] {}", msg);
-        exit_current_and_run_next(errno);
+        // -4: SIGILL, -11: SIGSEGV
+        if COREDUMP && (errno == -4 || errno == -11) {
+            core_dump();
+        }
+        kill_current_and_run_next(-errno as u32);
     }
     trap_return();
 }
+
+/// Print registers and the top of the user stack of the current task
+fn core_dump() {
+    let cx = current_trap_cx();
+    println!(
+        "[kernel] core dump: sepc = {:#x}, sstatus = {:#x}",
+        cx.sepc,
+        cx.sstatus.bits()
+    );
+    for (i, chunk) in cx.x.chunks(4).enumerate() {
+        println!(
+            "[kernel]   x{:<2} = {:#018x}  x{:<2} = {:#018x}  x{:<2} = {:#018x}  x{:<2} = {:#018x}",
+            i * 4,
+            chunk[0],
+            i * 4 + 1,
+            chunk[1],
+            i * 4 + 2,
+            chunk[2],
+            i * 4 + 3,
+            chunk[3]
+        );
+    }
+    // 通过查页表读取用户栈的内容，而不是直接访问用户地址，这样即使栈指针指向了未映射的页面也不会让内核自己出错
+    let page_table = PageTable::from_token(current_user_token());
+    let word = core::mem::size_of::<usize>();
+    let sp = cx.x[2] & !(word - 1);
+    println!("[kernel] user stack from sp = {:#x}:", cx.x[2]);
+    for addr in (sp..sp.saturating_add(COREDUMP_STACK_BYTES)).step_by(word) {
+        let va = VirtAddr::from(addr);
+        match page_table.translate(va.floor()) {
+            Some(pte) if pte.is_valid() && pte.readable() => {
+                let pa = page_table.translate_va(va).unwrap();
+                println!("[kernel]   {:#018x}: {:#018x}", addr, *pa.get_ref::<usize>());
+            }
+            _ => println!("[kernel]   {:#018x}: <unmapped>", addr),
+        }
+    }
+    // 对照逻辑段的名字，可以看出出错的地址和栈指针落在哪个区域中
+    let maps = current_task()
+        .unwrap()
+        .inner_exclusive_access()
+        .memory_set
+        .maps();
+    println!("[kernel] address space:");
+    for line in maps.lines() {
+        println!("[kernel]   {}", line);
+    }
+}
 // ch4之前：
 // pub fn trap_handler(cx: &mut TrapContext) -> &mut TrapContext {
 //     let scause = scause::read();
@@ -143,6 +233,10 @@ pub fn trap_handler() -> ! {
 /// set the reg a0 = trap_cx_ptr, reg a1 = phy addr of usr page table,
 /// finally, jump to new addr of __restore asm function
 pub fn trap_return() -> ! {
+    // 系统调用期间可能为了剖析内核打开了中断，在 stvec 指向跳板页面之后不能再有内核态的 Trap
+    unsafe {
+        sstatus::clear_sie();
+    }
     // 在 trap_return 的开始处就调用 set_user_trap_entry ，来让应用 Trap 到 S 的时候可以跳转到 __alltraps
     set_user_trap_entry();
     // 准备好 __restore 需要两个参数：分别是 Trap 上下文在应用地址空间中的虚拟地址和要继续执行的应用地址空间的 token
@@ -170,11 +264,49 @@ pub fn trap_return() -> ! {
 }
 
 
+/// Handle a timer tick which arrived at `entry_time`, except for preempting the current task
+fn handle_timer_tick(entry_time: usize) {
+    record_timer_latency(entry_time);
+    profile_user();
+    set_next_trigger();
+    #[cfg(debug_assertions)]
+    check_lock_watchdog();
+    check_timer();
+    console_poll();
+    sample_load(1);
+}
+
 #[no_mangle]
-/// Unimplement: traps/interrupts/exceptions from kernel mode
-/// Todo: Chapter 9: I/O device
-pub fn trap_from_kernel() -> ! {
-    panic!("a trap from kernel!");
+/// Handle a trap from kernel mode. Interrupts are only enabled in kernel mode while the kernel
+/// is profiled, and are only sampled here: they stay pending and are handled once the hart is
+/// back in user mode, or once a preemption-disabled section the timer interrupt arrived in has
+/// been left. Exceptions from kernel mode are not supported
+pub fn trap_from_kernel() {
+    let scause = scause::read();
+    match scause.cause() {
+        Trap::Interrupt(interrupt) => {
+            if matches!(interrupt, Interrupt::SupervisorTimer) {
+                profile_kernel(sepc::read());
+                // 内核态从不在这里切换任务，只在不可抢占区间中记下需要调度；preempt_on_tick 不借用 PROCESSOR
+                preempt_on_tick();
+            }
+            // 清除 sstatus.spie ，sret 之后中断保持屏蔽，否则仍未处理的中断会立即再次进入这里
+            unsafe {
+                asm!("csrc sstatus, {}", in(reg) SSTATUS_SPIE);
+            }
+        }
+        Trap::Exception(_) => {
+            panic!(
+                "a trap from kernel! {:?}, stval = {:#x}, sepc = {:#x}",
+                scause.cause(),
+                stval::read(),
+                sepc::read()
+            );
+        }
+    }
 }
 
+/// The SPIE bit of `sstatus`
+const SSTATUS_SPIE: usize = 1 << 5;
+
 pub use context::TrapContext;
\ No newline at end of file
@@ -0,0 +1,51 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, sched_getaffinity, sched_setaffinity, waitpid, yield_};
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // 默认允许在所有 hart 上运行，至少包含 hart 0
+    let all = sched_getaffinity(0);
+    assert!(all > 0 && all & 1 == 1);
+    let all = all as usize;
+    assert_eq!(sched_setaffinity(0, 1), 0);
+    assert_eq!(sched_getaffinity(0), 1);
+    assert_eq!(sched_setaffinity(0, all), 0);
+
+    // 空掩码和不存在的 hart 都会被拒绝，原来的掩码保持不变
+    assert_eq!(sched_setaffinity(0, 0), -1);
+    assert_eq!(sched_setaffinity(0, all + 1), -1);
+    assert_eq!(sched_getaffinity(0), all as isize);
+
+    // 子进程继承父进程的掩码
+    assert_eq!(sched_setaffinity(0, 1), 0);
+    let pid = fork();
+    if pid == 0 {
+        exit(if sched_getaffinity(0) == 1 { 0 } else { 1 });
+    }
+    let mut exit_code: i32 = -1;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+
+    // 父进程也可以修改子进程的掩码
+    assert_eq!(sched_setaffinity(0, all), 0);
+    let pid = fork();
+    if pid == 0 {
+        yield_();
+        exit(0);
+    }
+    assert_eq!(sched_setaffinity(pid as usize, 1), 0);
+    assert_eq!(sched_getaffinity(pid as usize), 1);
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+
+    // 进程回收之后就找不到了
+    assert_eq!(sched_getaffinity(pid as usize), -1);
+    assert_eq!(sched_setaffinity(pid as usize, 1), -1);
+    println!("affinity_test passed!");
+    0
+}
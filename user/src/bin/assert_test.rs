@@ -0,0 +1,69 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+#[macro_use]
+extern crate user_lib;
+
+use alloc::string::String;
+use user_lib::{close, dup, exit, fork, pipe, read, waitpid, ASSERT_FAILED_EXIT_CODE, SIGABRT};
+
+// 在子进程中运行 f ，返回子进程的标准输出和退出码
+fn run_in_child(f: fn()) -> (String, i32) {
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    let pid = fork();
+    if pid == 0 {
+        close(1);
+        assert_eq!(dup(pipe_fd[1]), 1);
+        close(pipe_fd[0]);
+        close(pipe_fd[1]);
+        f();
+        exit(0);
+    }
+    close(pipe_fd[1]);
+    let mut output = String::new();
+    let mut buf = [0u8; 256];
+    loop {
+        let len = read(pipe_fd[0], &mut buf);
+        if len <= 0 {
+            break;
+        }
+        output.push_str(core::str::from_utf8(&buf[..len as usize]).unwrap());
+    }
+    close(pipe_fd[0]);
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    (output, exit_code)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // 成立的断言什么也不做
+    let (output, exit_code) = run_in_child(|| {
+        let value = 2;
+        user_assert!(value + 1 == 3);
+        user_assert!(value > 0, "value is {}", value);
+    });
+    assert_eq!((output.as_str(), exit_code), ("", 0));
+
+    // 失败的断言打印位置、表达式和附加的信息，并以专门的退出码退出
+    let (output, exit_code) = run_in_child(|| {
+        let value = 1;
+        user_assert!(value + 1 == 3, "value is {}", value);
+        println!("unreachable");
+    });
+    assert_eq!(exit_code, ASSERT_FAILED_EXIT_CODE);
+    assert!(output.contains("assertion failed at src/bin/assert_test.rs:"));
+    assert!(output.ends_with(": value + 1 == 3, value is 1\n"));
+    let (output, exit_code) = run_in_child(|| user_assert!(false));
+    assert_eq!(exit_code, ASSERT_FAILED_EXIT_CODE);
+    assert!(output.ends_with(": false\n"));
+
+    // panic 的退出码与之不同
+    let (_, exit_code) = run_in_child(|| panic!("not an assertion"));
+    assert_eq!(exit_code, -SIGABRT);
+    println!("assert_test passed!");
+    0
+}
@@ -0,0 +1,92 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mmap, munmap, sbrk, PROT_READ, PROT_WRITE};
+
+const PAGE_SIZE: usize = 4096;
+const ROUNDS: usize = 16;
+// 内核为堆保留的大小 USER_HEAP_SIZE
+const HEAP_LIMIT: usize = 0x400_0000;
+
+fn fill(start: usize, len: usize, seed: u8) {
+    let region = unsafe { core::slice::from_raw_parts_mut(start as *mut u8, len) };
+    for (i, byte) in region.iter_mut().enumerate() {
+        *byte = seed.wrapping_add(i as u8);
+    }
+}
+
+fn check(start: usize, len: usize, seed: u8) {
+    let region = unsafe { core::slice::from_raw_parts(start as *const u8, len) };
+    for (i, &byte) in region.iter().enumerate() {
+        assert_eq!(byte, seed.wrapping_add(i as u8));
+    }
+}
+
+fn disjoint((a, a_len): (usize, usize), (b, b_len): (usize, usize)) -> bool {
+    a + a_len <= b || b + b_len <= a
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let bottom = sbrk(0) as usize;
+    let mut brk = bottom;
+    let mut heap = [(0, 0); ROUNDS];
+    let mut mapped = [(0, 0); ROUNDS];
+
+    // 交替地扩展堆和让内核选择 mmap 的地址，每一块内存写入不同的内容
+    for round in 0..ROUNDS {
+        let len = (round % 3 + 1) * PAGE_SIZE;
+        assert_eq!(sbrk(len as i32), brk as isize);
+        heap[round] = (brk, len);
+        fill(brk, len, 2 * round as u8);
+        brk += len;
+
+        let addr = mmap(0, len, PROT_READ | PROT_WRITE);
+        assert!(addr > 0);
+        let addr = addr as usize;
+        assert_eq!(addr % PAGE_SIZE, 0);
+        // mmap 的内存位于堆的保留区域之上，与之前的映射也不重叠
+        assert!(addr >= bottom + HEAP_LIMIT);
+        assert!(mapped[..round]
+            .iter()
+            .all(|&region| disjoint(region, (addr, len))));
+        mapped[round] = (addr, len);
+        fill(addr, len, 2 * round as u8 + 1);
+    }
+    for round in 0..ROUNDS {
+        check(heap[round].0, heap[round].1, 2 * round as u8);
+        check(mapped[round].0, mapped[round].1, 2 * round as u8 + 1);
+    }
+
+    // 释放中间的一段映射之后，新的映射可以重新使用这段地址，其余的内容不受影响
+    let (hole, hole_len) = mapped[ROUNDS / 2];
+    assert_eq!(munmap(hole, hole_len), 0);
+    let addr = mmap(0, hole_len, PROT_READ | PROT_WRITE);
+    assert!(addr > 0);
+    let addr = addr as usize;
+    let others = (0..ROUNDS).filter(|&round| round != ROUNDS / 2);
+    assert!(others
+        .clone()
+        .all(|round| disjoint(mapped[round], (addr, hole_len))));
+    // 新的映射内容全为零
+    let region = unsafe { core::slice::from_raw_parts(addr as *const u8, hole_len) };
+    assert!(region.iter().all(|&byte| byte == 0));
+    for round in others {
+        check(mapped[round].0, mapped[round].1, 2 * round as u8 + 1);
+    }
+
+    // 堆不能增长到 mmap 的区域中，mmap 也不能占用堆的保留区域
+    assert_eq!(sbrk(HEAP_LIMIT as i32), -1);
+    assert_eq!(sbrk(0), brk as isize);
+    assert_eq!(mmap(brk + PAGE_SIZE, PAGE_SIZE, PROT_READ), -1);
+    let heap_end = bottom + HEAP_LIMIT;
+    assert_eq!(mmap(heap_end - PAGE_SIZE, 2 * PAGE_SIZE, PROT_READ), -1);
+
+    assert_eq!(sbrk(-((brk - bottom) as i32)), brk as isize);
+    check(mapped[0].0, mapped[0].1, 1);
+    println!("brk_mmap_test passed!");
+    0
+}
@@ -0,0 +1,82 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+#[macro_use]
+extern crate user_lib;
+
+use alloc::string::String;
+use user_lib::{
+    close, dup, exit, flush_stdout, fork, pipe, read, set_stdout_buffered, stdout_write_count,
+    waitpid,
+};
+
+const N: usize = 1000;
+
+// 子进程的标准输出被重定向到管道，检查 write 的次数时不会把终端刷满
+fn child() {
+    // 默认不缓冲：每次 print! 都是一次 write
+    let before = stdout_write_count();
+    for _ in 0..N {
+        print!(".");
+    }
+    assert_eq!(stdout_write_count() - before, N);
+
+    // 缓冲之后只在缓冲区满和遇到换行时 write
+    set_stdout_buffered(true);
+    let before = stdout_write_count();
+    for _ in 0..N {
+        print!(".");
+    }
+    println!("");
+    assert!(stdout_write_count() - before < N / 100);
+
+    let before = stdout_write_count();
+    print!("x");
+    assert_eq!(stdout_write_count(), before);
+    flush_stdout();
+    assert_eq!(stdout_write_count(), before + 1);
+
+    // 没有换行的输出在退出时写出
+    print!("tail");
+    assert_eq!(stdout_write_count(), before + 1);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    let pid = fork();
+    if pid == 0 {
+        close(1);
+        assert_eq!(dup(pipe_fd[1]), 1);
+        close(pipe_fd[0]);
+        close(pipe_fd[1]);
+        child();
+        exit(0);
+    }
+    close(pipe_fd[1]);
+    let mut output = String::new();
+    let mut buf = [0u8; 256];
+    loop {
+        let len = read(pipe_fd[0], &mut buf);
+        if len <= 0 {
+            break;
+        }
+        output.push_str(core::str::from_utf8(&buf[..len as usize]).unwrap());
+    }
+    close(pipe_fd[0]);
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+
+    let mut expected = String::new();
+    for _ in 0..2 * N {
+        expected.push('.');
+    }
+    expected.push_str("\nxtail");
+    assert_eq!(output, expected);
+    println!("buffered_print_test passed!");
+    0
+}
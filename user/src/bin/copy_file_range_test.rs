@@ -0,0 +1,110 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+#[macro_use]
+extern crate user_lib;
+
+use alloc::vec;
+use user_lib::{close, copy_file_range, open, pipe, read, unlink, write, OpenFlags};
+
+const SRC: &str = "/copy_src\0";
+const DST: &str = "/copy_dst\0";
+const LEN: usize = 3000;
+
+fn byte_at(i: usize) -> u8 {
+    (i * 7 % 251) as u8
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let src = open(SRC, OpenFlags::CREATE | OpenFlags::TRUNC | OpenFlags::RDWR);
+    let dst = open(DST, OpenFlags::CREATE | OpenFlags::TRUNC | OpenFlags::RDWR);
+    assert!(src > 0 && dst > 0);
+    let (src, dst) = (src as usize, dst as usize);
+    // 缓冲区都比较大，放在堆上以免用户栈溢出
+    let mut data = vec![0u8; LEN];
+    for (i, byte) in data.iter_mut().enumerate() {
+        *byte = byte_at(i);
+    }
+    assert_eq!(write(src, &data), LEN as isize);
+
+    // 给出偏移量时使用并更新它们，跨越多个块复制 [100, 2100) 到目标文件的 50 处
+    let mut in_off = 100;
+    let mut out_off = 50;
+    assert_eq!(
+        copy_file_range(src, Some(&mut in_off), dst, Some(&mut out_off), 2000),
+        2000
+    );
+    assert_eq!((in_off, out_off), (2100, 2050));
+    // 目标文件的偏移量没有移动，从头读出空洞和复制的数据
+    let mut buf = vec![0xffu8; 2050];
+    assert_eq!(read(dst, &mut buf), 2050);
+    assert!(buf[..50].iter().all(|&byte| byte == 0));
+    for (i, &byte) in buf[50..].iter().enumerate() {
+        assert_eq!(byte, byte_at(100 + i));
+    }
+
+    // 源文件提前到达末尾时只复制剩下的部分，为 None 的一方使用并移动文件描述符的偏移量
+    let mut in_off = LEN - 10;
+    assert_eq!(copy_file_range(src, Some(&mut in_off), dst, None, 100), 10);
+    assert_eq!(in_off, LEN);
+    assert_eq!(copy_file_range(src, Some(&mut in_off), dst, None, 100), 0);
+    // 复制回源文件的末尾，之后的写入紧接在复制的数据之后
+    let mut out_off = 2050;
+    assert_eq!(copy_file_range(dst, Some(&mut out_off), src, None, 100), 10);
+    assert_eq!(write(src, b"!"), 1);
+    close(dst);
+
+    // 同一个文件中不重叠的区间可以复制，重叠的不行
+    let mut in_off = 0;
+    let mut out_off = 2000;
+    assert_eq!(
+        copy_file_range(src, Some(&mut in_off), src, Some(&mut out_off), 1000),
+        1000
+    );
+    let mut in_off = 0;
+    let mut out_off = 500;
+    assert_eq!(
+        copy_file_range(src, Some(&mut in_off), src, Some(&mut out_off), 1000),
+        -1
+    );
+    let check = open(SRC, OpenFlags::RDONLY);
+    assert!(check > 0);
+    let mut buf = vec![0u8; LEN + 16];
+    assert_eq!(read(check as usize, &mut buf), (LEN + 11) as isize);
+    for (i, &byte) in buf[2000..LEN].iter().enumerate() {
+        assert_eq!(byte, byte_at(i));
+    }
+    assert_eq!(&buf[LEN..LEN + 10], &data[LEN - 10..]);
+    assert_eq!(buf[LEN + 10], b'!');
+    close(check as usize);
+
+    // 目标区间超出最大文件大小时失败，偏移量保持不变
+    for far in [16 << 20, 1 << 32, usize::MAX - 10] {
+        let mut in_off = 0;
+        let mut out_off = far;
+        assert_eq!(
+            copy_file_range(src, Some(&mut in_off), src, Some(&mut out_off), 100),
+            -1
+        );
+        assert_eq!((in_off, out_off), (0, far));
+    }
+
+    // 管道和只读的目标都不能用来复制
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    assert_eq!(copy_file_range(src, None, pipe_fd[1], None, 10), -1);
+    close(pipe_fd[0]);
+    close(pipe_fd[1]);
+    let readonly = open(DST, OpenFlags::RDONLY);
+    assert!(readonly > 0);
+    assert_eq!(copy_file_range(src, None, readonly as usize, None, 10), -1);
+    close(readonly as usize);
+    close(src);
+    assert_eq!(unlink(SRC), 0);
+    assert_eq!(unlink(DST), 0);
+    println!("copy_file_range_test passed!");
+    0
+}
@@ -0,0 +1,78 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, closedir, crash, fstat, mkdir, open, opendir, read, rmdir, sync, unlink, write,
+    OpenFlags, Stat,
+};
+
+// 由 make crash-test 作为初始进程启动两次：第一次启动时写入文件并模拟断电，第二次启动时检查哪些修改保留了下来
+const SYNCED: &str = "/crash_synced\0";
+const LOST: &str = "/crash_lost\0";
+const DIR: &str = "/crash_dir\0";
+const BLOCK: usize = 512;
+
+fn write_file(path: &str, flags: OpenFlags, byte: u8, blocks: usize) {
+    let fd = open(path, flags | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    for _ in 0..blocks {
+        assert_eq!(write(fd as usize, &[byte; BLOCK]), BLOCK as isize);
+    }
+    close(fd as usize);
+}
+
+fn read_file(path: &str) -> (usize, [u8; 4 * BLOCK]) {
+    let fd = open(path, OpenFlags::RDONLY);
+    assert!(fd > 0, "{} is lost", path);
+    let mut stat = Stat::default();
+    assert_eq!(fstat(fd as usize, &mut stat), 0);
+    let mut buf = [0u8; 4 * BLOCK];
+    assert_eq!(read(fd as usize, &mut buf), stat.size as isize);
+    close(fd as usize);
+    (stat.size, buf)
+}
+
+fn prepare() -> ! {
+    write_file(SYNCED, OpenFlags::CREATE, 0x11, 3);
+    sync();
+    // 之后的文件数据只停留在块缓存中，元数据则立即写回了磁盘
+    assert_eq!(mkdir(DIR, 0o755), 0);
+    write_file(LOST, OpenFlags::CREATE, 0x22, 2);
+    write_file(SYNCED, OpenFlags::empty(), 0x33, 1);
+    println!("crash_test: losing the power");
+    crash();
+}
+
+fn verify() -> i32 {
+    // 同步过的内容完整保留，之后的覆盖写入丢失了
+    let (size, buf) = read_file(SYNCED);
+    assert_eq!(size, 3 * BLOCK);
+    assert!(buf[..size].iter().all(|byte| *byte == 0x11));
+    // 目录和文件本身以及文件的大小都在，文件的数据丢失了
+    let dir = opendir(DIR);
+    assert!(dir.is_some());
+    closedir(dir.unwrap());
+    let (size, buf) = read_file(LOST);
+    assert_eq!(size, 2 * BLOCK);
+    assert!(buf[..size].iter().all(|byte| *byte != 0x22));
+
+    assert_eq!(unlink(SYNCED), 0);
+    assert_eq!(unlink(LOST), 0);
+    assert_eq!(rmdir(DIR), 0);
+    println!("crash_test passed!");
+    0
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let fd = open(SYNCED, OpenFlags::RDONLY);
+    if fd > 0 {
+        close(fd as usize);
+        verify()
+    } else {
+        prepare()
+    }
+}
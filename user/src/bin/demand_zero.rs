@@ -0,0 +1,90 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, exit, fork, getrusage, mmap, munmap, pipe, read, waitpid, write, Rusage, PROT_READ,
+    PROT_WRITE, RUSAGE_SELF,
+};
+
+const START: usize = 0x1000_0000;
+// 远大于实际写入的页面数，只有被写过的页面才会分配物理页帧
+const LEN: usize = 256 * 1024 * 1024;
+const PAGE_SIZE: usize = 4096;
+
+fn minflt() -> usize {
+    let mut usage = Rusage::default();
+    assert_eq!(getrusage(RUSAGE_SELF, &mut usage), 0);
+    usage.minflt
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(mmap(START, LEN, PROT_READ | PROT_WRITE), 0);
+    let region = unsafe { core::slice::from_raw_parts_mut(START as *mut u8, LEN) };
+
+    // reading untouched pages sees zeros without faulting
+    let faults = minflt();
+    for i in (0..LEN).step_by(LEN / 64) {
+        assert_eq!(region[i], 0);
+    }
+    assert_eq!(minflt(), faults);
+
+    // the first store to a page gives it a private frame
+    for i in 0..4 {
+        region[i * 16 * PAGE_SIZE + 7] = i as u8 + 1;
+    }
+    assert_eq!(minflt(), faults + 4);
+    for i in 0..4 {
+        assert_eq!(region[i * 16 * PAGE_SIZE + 7], i as u8 + 1);
+        assert_eq!(region[i * 16 * PAGE_SIZE + 8], 0);
+        assert_eq!(region[(i * 16 + 1) * PAGE_SIZE], 0);
+    }
+
+    // the kernel writing into a zero page must not clobber the shared frame
+    let mut fds = [0usize; 2];
+    assert_eq!(pipe(&mut fds), 0);
+    assert_eq!(write(fds[1], b"zero"), 4);
+    assert_eq!(read(fds[0], &mut region[LEN - PAGE_SIZE..LEN - PAGE_SIZE + 4]), 4);
+    assert_eq!(&region[LEN - PAGE_SIZE..LEN - PAGE_SIZE + 4], b"zero");
+    assert_eq!(region[LEN - 2 * PAGE_SIZE], 0);
+    close(fds[0]);
+    close(fds[1]);
+
+    // stores of the child are private to it
+    let pid = fork();
+    if pid == 0 {
+        assert_eq!(region[7], 1);
+        region[7] = 0xff;
+        region[2 * PAGE_SIZE] = 0x55;
+        exit(0);
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    assert_eq!(region[7], 1);
+    assert_eq!(region[2 * PAGE_SIZE], 0);
+
+    assert_eq!(munmap(START, LEN), 0);
+    assert_eq!(munmap(START, LEN), -1);
+
+    // invalid arguments and overlapping mappings are rejected
+    assert_eq!(mmap(START + 1, PAGE_SIZE, PROT_READ), -1);
+    assert_eq!(mmap(START, PAGE_SIZE, 0), -1);
+    assert_eq!(mmap(START, PAGE_SIZE, 1 << 3), -1);
+    assert_eq!(mmap(START, 2 * PAGE_SIZE, PROT_READ), 0);
+    assert_eq!(mmap(START + PAGE_SIZE, PAGE_SIZE, PROT_READ), -1);
+    assert_eq!(munmap(START, 2 * PAGE_SIZE), 0);
+
+    // a write-only mapping is readable as well
+    assert_eq!(mmap(START, PAGE_SIZE, PROT_WRITE), 0);
+    let page = unsafe { core::slice::from_raw_parts_mut(START as *mut u8, PAGE_SIZE) };
+    assert_eq!(page[9], 0);
+    page[9] = 0x42;
+    assert_eq!(page[9], 0x42);
+    assert_eq!(munmap(START, PAGE_SIZE), 0);
+    println!("demand_zero passed!");
+    0
+}
@@ -0,0 +1,39 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, closedir, makedev, mknod, open, opendir, readdir, OpenFlags, DT_CHR, DT_REG, DT_UNKNOWN,
+    S_IFCHR,
+};
+
+const FILE: &str = "dirent_type_file";
+const DEVICE: &str = "dirent_type_null";
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let fd = open("dirent_type_file\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    close(fd as usize);
+    // 设备文件可能是上一次运行时留下的，这时 mknod 失败也没有关系
+    mknod("dirent_type_null\0", S_IFCHR | 0o666, makedev(1, 3));
+
+    // 列出根目录时直接从目录项得到类型，不需要逐个 stat
+    let mut dir = opendir("/\0").unwrap();
+    let (mut file_type, mut device_type) = (None, None);
+    while let Some(entry) = readdir(&mut dir) {
+        assert_ne!(entry.d_type, DT_UNKNOWN, "{} has no type", entry.name);
+        if entry.name == FILE {
+            file_type = Some(entry.d_type);
+        } else if entry.name == DEVICE {
+            device_type = Some(entry.d_type);
+        }
+    }
+    closedir(dir);
+    assert_eq!(file_type, Some(DT_REG));
+    assert_eq!(device_type, Some(DT_CHR));
+    println!("dirent_type_test passed!");
+    0
+}
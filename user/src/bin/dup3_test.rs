@@ -0,0 +1,51 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, dup2, dup3, exec, fork, waitpid, write, OpenFlags};
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // unlike dup2, duplicating an fd onto itself is an error
+    assert_eq!(dup2(1, 1), 1);
+    assert_eq!(dup3(1, 1, OpenFlags::CLOEXEC), -1);
+    assert_eq!(dup3(1, 1, OpenFlags::empty()), -1);
+    // invalid fds and flags are rejected
+    assert_eq!(dup3(42, 5, OpenFlags::CLOEXEC), -1);
+    assert_eq!(dup3(1, 100000, OpenFlags::CLOEXEC), -1);
+    assert_eq!(dup3(1, 5, OpenFlags::TRUNC), -1);
+
+    assert_eq!(dup3(1, 5, OpenFlags::CLOEXEC), 5);
+    assert_eq!(dup2(1, 6), 6);
+    assert_eq!(write(5, b""), 0);
+    assert_eq!(write(6, b""), 0);
+    // dup3 onto an already open fd replaces it
+    assert_eq!(dup3(1, 7, OpenFlags::CLOEXEC), 7);
+    assert_eq!(dup3(2, 7, OpenFlags::empty()), 7);
+
+    // only fd 5 is closed when a new program is executed
+    let pid = fork();
+    if pid == 0 {
+        exec(
+            "fd_probe\0",
+            &[
+                "fd_probe\0".as_ptr(),
+                "5\0".as_ptr(),
+                "6\0".as_ptr(),
+                "7\0".as_ptr(),
+                core::ptr::null(),
+            ],
+        );
+        panic!("exec fd_probe failed");
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0b110);
+    close(5);
+    close(6);
+    close(7);
+    println!("dup3_test passed!");
+    0
+}
@@ -0,0 +1,40 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{environ, exec, fork, getenv, waitpid};
+
+// 环境变量由内核传给 initproc ，再经过 fork 和 exec 传到这里
+fn check_env() {
+    assert_eq!(getenv("PATH"), Some("/"));
+    assert!(environ().any(|var| var == "PATH=/"));
+    // 只有名字完全相同的环境变量才会匹配
+    assert_eq!(getenv("PAT"), None);
+    assert_eq!(getenv("PATH="), None);
+    assert_eq!(getenv("NO_SUCH_VAR"), None);
+}
+
+#[no_mangle]
+pub fn main(argc: usize, argv: &[&str]) -> i32 {
+    check_env();
+    if argc > 1 {
+        assert_eq!(argv[1], "child");
+        return 0;
+    }
+    // exec 之后环境变量和命令行参数都被正确地压入了新程序的用户栈
+    let pid = fork();
+    if pid == 0 {
+        exec(
+            "env_test\0",
+            &["env_test\0".as_ptr(), "child\0".as_ptr(), core::ptr::null()],
+        );
+        panic!("exec env_test failed");
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    println!("env_test passed!");
+    0
+}
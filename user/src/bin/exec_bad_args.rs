@@ -0,0 +1,41 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::vec::Vec;
+use user_lib::{exec, mmap, munmap, PROT_READ, PROT_WRITE};
+
+const ARGV_PAGE: usize = 0x2000_0000;
+const PAGE_SIZE: usize = 4096;
+const PTR_SIZE: usize = core::mem::size_of::<usize>();
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let arg = "arg\0".as_ptr();
+
+    // argv without a NULL terminator running into an unmapped page
+    assert_eq!(mmap(ARGV_PAGE, PAGE_SIZE, PROT_READ | PROT_WRITE), 0);
+    let argv = unsafe {
+        core::slice::from_raw_parts_mut((ARGV_PAGE + PAGE_SIZE - 4 * PTR_SIZE) as *mut *const u8, 4)
+    };
+    argv.fill(arg);
+    assert_eq!(exec("exit\0", argv), -1);
+    assert_eq!(munmap(ARGV_PAGE, PAGE_SIZE), 0);
+
+    // argv itself at an unmapped address
+    let unmapped = unsafe { core::slice::from_raw_parts(ARGV_PAGE as *const *const u8, 1) };
+    assert_eq!(exec("exit\0", unmapped), -1);
+
+    // an argument pointing to unmapped memory
+    assert_eq!(exec("exit\0", &[1 as *const u8, core::ptr::null()]), -1);
+
+    // too many arguments
+    let mut many: Vec<*const u8> = (0..1000).map(|_| arg).collect();
+    many.push(core::ptr::null());
+    assert_eq!(exec("exit\0", &many), -1);
+    println!("exec_bad_args passed!");
+    0
+}
@@ -0,0 +1,66 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::vec::Vec;
+use user_lib::{close, exec, open, read, write, OpenFlags};
+
+const BAD_ELF: &str = "bad_elf\0";
+
+fn read_file(path: &str) -> Vec<u8> {
+    let fd = open(path, OpenFlags::RDONLY);
+    assert!(fd > 0);
+    let mut data = Vec::new();
+    let mut buf = [0u8; 512];
+    loop {
+        let len = read(fd as usize, &mut buf);
+        assert!(len >= 0);
+        if len == 0 {
+            break;
+        }
+        data.extend_from_slice(&buf[..len as usize]);
+    }
+    close(fd as usize);
+    data
+}
+
+fn write_file(path: &str, data: &[u8]) {
+    let fd = open(path, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    assert_eq!(write(fd as usize, data), data.len() as isize);
+    close(fd as usize);
+}
+
+fn exec_bad_elf() -> isize {
+    exec(BAD_ELF, &[core::ptr::null::<u8>()])
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let elf = read_file("exit\0");
+    assert_eq!(&elf[..4], b"\x7fELF");
+
+    // 只保留开头的部分：包含完整的 ELF 头和 program header 表，但没有包含各个段的全部数据
+    // segments reaching past the end of the file
+    write_file(BAD_ELF, &elf[..512]);
+    assert_eq!(exec_bad_elf(), -1);
+
+    // program header table reaching past the end of the file
+    write_file(BAD_ELF, &elf[..64]);
+    assert_eq!(exec_bad_elf(), -1);
+
+    // not an elf at all
+    write_file(BAD_ELF, b"#!/bin/sh\necho this is not an executable\n");
+    assert_eq!(exec_bad_elf(), -1);
+
+    // a complete elf for another machine: e_machine of x86-64
+    let mut other_machine = elf.clone();
+    other_machine[18..20].copy_from_slice(&62u16.to_le_bytes());
+    write_file(BAD_ELF, &other_machine);
+    assert_eq!(exec_bad_elf(), -1);
+    println!("exec_bad_elf passed!");
+    0
+}
@@ -0,0 +1,82 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::vec::Vec;
+use user_lib::{close, exec, exit, fork, open, read, waitpid, write, OpenFlags};
+
+const WX_ELF: &str = "wx_elf\0";
+const PT_LOAD: u32 = 1;
+const PF_X: u32 = 1;
+const PF_W: u32 = 2;
+// 子进程中 exec 失败时的退出码
+const EXEC_REJECTED: i32 = 77;
+
+fn read_file(path: &str) -> Vec<u8> {
+    let fd = open(path, OpenFlags::RDONLY);
+    assert!(fd > 0);
+    let mut data = Vec::new();
+    let mut buf = [0u8; 512];
+    loop {
+        let len = read(fd as usize, &mut buf);
+        assert!(len >= 0);
+        if len == 0 {
+            break;
+        }
+        data.extend_from_slice(&buf[..len as usize]);
+    }
+    close(fd as usize);
+    data
+}
+
+fn write_file(path: &str, data: &[u8]) {
+    let fd = open(path, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    assert_eq!(write(fd as usize, data), data.len() as isize);
+    close(fd as usize);
+}
+
+// 给第一个可写的 LOAD 段加上可执行权限，返回是否找到了这样的段
+fn make_data_executable(elf: &mut [u8]) -> bool {
+    let ph_offset = u64::from_le_bytes(elf[32..40].try_into().unwrap()) as usize;
+    let ph_entry_size = u16::from_le_bytes([elf[54], elf[55]]) as usize;
+    let ph_count = u16::from_le_bytes([elf[56], elf[57]]) as usize;
+    for i in 0..ph_count {
+        let ph = ph_offset + i * ph_entry_size;
+        let p_type = u32::from_le_bytes(elf[ph..ph + 4].try_into().unwrap());
+        let p_flags = u32::from_le_bytes(elf[ph + 4..ph + 8].try_into().unwrap());
+        if p_type == PT_LOAD && p_flags & PF_W != 0 {
+            elf[ph + 4..ph + 8].copy_from_slice(&(p_flags | PF_X).to_le_bytes());
+            return true;
+        }
+    }
+    false
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // 把一个正常的程序改造成含有同时可写又可执行的段
+    let mut elf = read_file("hello_world\0");
+    assert_eq!(&elf[..4], b"\x7fELF");
+    assert!(make_data_executable(&mut elf));
+    write_file(WX_ELF, &elf);
+
+    let pid = fork();
+    if pid == 0 {
+        exec(WX_ELF, &[WX_ELF.as_ptr(), core::ptr::null::<u8>()]);
+        exit(EXEC_REJECTED);
+    }
+    let mut exit_code: i32 = -1;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    // 内核要么拒绝加载它（WX_STRICT），要么去掉这个段的可执行权限之后正常运行它，而不是让它带着 W+X 的段运行
+    match exit_code {
+        EXEC_REJECTED => println!("W+X segment rejected"),
+        0 => println!("W+X segment mapped without X"),
+        _ => panic!("unexpected exit code {}", exit_code),
+    }
+    println!("exec_wx_test passed!");
+    0
+}
@@ -0,0 +1,74 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{execve, execvp, fork, getenv, waitpid};
+
+const FOUND_EXIT: i32 = 42;
+
+// 在子进程中执行 f ，返回子进程的退出码
+fn in_child(f: impl FnOnce() -> isize) -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        f();
+        panic!("exec failed");
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    exit_code
+}
+
+fn run_found(name: &str) -> i32 {
+    in_child(|| {
+        execvp(
+            name,
+            &[
+                "execvp_test\0".as_ptr(),
+                "found\0".as_ptr(),
+                core::ptr::null(),
+            ],
+        )
+    })
+}
+
+// PATH 中前面的目录不存在时继续在后面的目录中查找
+fn search() -> i32 {
+    assert_eq!(getenv("PATH"), Some("/no_such_dir:/"));
+    assert_eq!(run_found("execvp_test\0"), FOUND_EXIT);
+    // 带有 / 的名字不在 PATH 中查找
+    assert_eq!(run_found("/execvp_test\0"), FOUND_EXIT);
+    // 所有目录中都找不到时返回 -1
+    assert_eq!(execvp("no_such_cmd\0", &[core::ptr::null()]), -1);
+    0
+}
+
+#[no_mangle]
+pub fn main(argc: usize, argv: &[&str]) -> i32 {
+    if argc > 1 {
+        return match argv[1] {
+            "found" => FOUND_EXIT,
+            "search" => search(),
+            _ => -1,
+        };
+    }
+    // 继承的 PATH 为 / ，直接用名字就能找到根目录中的应用
+    assert_eq!(getenv("PATH"), Some("/"));
+    assert_eq!(run_found("execvp_test\0"), FOUND_EXIT);
+    // 在新的 PATH 下重新执行自己进行查找
+    let exit_code = in_child(|| {
+        execve(
+            "execvp_test\0",
+            &[
+                "execvp_test\0".as_ptr(),
+                "search\0".as_ptr(),
+                core::ptr::null(),
+            ],
+            &["PATH=/no_such_dir:/\0".as_ptr(), core::ptr::null()],
+        )
+    });
+    assert_eq!(exit_code, 0);
+    println!("execvp_test passed!");
+    0
+}
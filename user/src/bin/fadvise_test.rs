@@ -0,0 +1,49 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, fadvise, open, pipe, read, write, OpenFlags, POSIX_FADV_DONTNEED, POSIX_FADV_NORMAL,
+    POSIX_FADV_RANDOM, POSIX_FADV_SEQUENTIAL,
+};
+
+const FILE: &str = "fadvise_file\0";
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let fd = open(FILE, OpenFlags::CREATE | OpenFlags::RDWR);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    let content = [b'f'; 8 * 512];
+    assert_eq!(write(fd, &content), content.len() as isize);
+    close(fd);
+
+    // 不论给出哪种访问模式，读出的内容都不变
+    let fd = open(FILE, OpenFlags::RDONLY) as usize;
+    let mut buf = [0u8; 512];
+    for advice in [
+        POSIX_FADV_SEQUENTIAL,
+        POSIX_FADV_RANDOM,
+        POSIX_FADV_DONTNEED,
+        POSIX_FADV_NORMAL,
+    ] {
+        assert_eq!(fadvise(fd, 0, 0, advice), 0);
+        assert_eq!(read(fd, &mut buf), 512);
+        assert!(buf.iter().all(|&b| b == b'f'));
+    }
+    // 不合法的访问模式
+    assert_eq!(fadvise(fd, 0, 0, 42), -1);
+    close(fd);
+
+    // 管道和不合法的文件描述符都不支持
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    assert_eq!(fadvise(pipe_fd[0], 0, 0, POSIX_FADV_SEQUENTIAL), -1);
+    close(pipe_fd[0]);
+    close(pipe_fd[1]);
+    assert_eq!(fadvise(fd, 0, 0, POSIX_FADV_NORMAL), -1);
+    println!("fadvise_test passed!");
+    0
+}
@@ -0,0 +1,47 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{chdir, close, fchdir, getcwd, open, OpenFlags};
+
+const FILE: &str = "fchdir_file\0";
+
+fn cwd_is(expected: &str) {
+    let mut buf = [0u8; 64];
+    let len = getcwd(&mut buf);
+    assert_eq!(len as usize, expected.len() + 1);
+    assert_eq!(&buf[..expected.len()], expected.as_bytes());
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let fd = open(FILE, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    close(fd as usize);
+
+    // 通过已打开的目录修改工作目录，之后的相对路径基于它解析
+    let dir = open("/\0", OpenFlags::RDONLY);
+    assert!(dir > 0);
+    let dir = dir as usize;
+    assert_eq!(fchdir(dir), 0);
+    cwd_is("/");
+    let fd = open("./fchdir_file\0", OpenFlags::RDONLY);
+    assert!(fd > 0);
+    // 普通文件不能成为工作目录
+    assert_eq!(fchdir(fd as usize), -1);
+    close(fd as usize);
+    close(dir);
+    assert_eq!(fchdir(dir), -1);
+
+    assert_eq!(chdir(FILE), -1);
+    assert_eq!(chdir("no_such_dir\0"), -1);
+    assert_eq!(chdir("/\0"), 0);
+    cwd_is("/");
+    // 缓冲区放不下路径和结尾的 \0
+    let mut buf = [0u8; 1];
+    assert_eq!(getcwd(&mut buf), -1);
+    println!("fchdir_test passed!");
+    0
+}
@@ -0,0 +1,54 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{chmod, close, fchmod, fstat, open, pipe, write, OpenFlags, Stat, S_IFDIR, S_IFREG};
+
+const FILE: &str = "fchmod_file\0";
+
+fn stat(fd: usize) -> Stat {
+    let mut buf = Stat::default();
+    assert_eq!(fstat(fd, &mut buf), 0);
+    buf
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let fd = open(FILE, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    assert_eq!(write(fd, b"hello"), 5);
+    let st = stat(fd);
+    assert_eq!(st.mode, S_IFREG | 0o644);
+    assert_eq!(st.size, 5);
+
+    // 另一个打开同一文件的文件描述符立即看到修改后的权限
+    let other = open(FILE, OpenFlags::RDONLY);
+    assert!(other > 0);
+    let other = other as usize;
+    assert_eq!(fchmod(fd, 0o600), 0);
+    assert_eq!(stat(fd).mode, S_IFREG | 0o600);
+    assert_eq!(stat(other).mode, S_IFREG | 0o600);
+    assert_eq!(stat(other).ino, st.ino);
+
+    // 通过路径修改，只保留权限位
+    assert_eq!(chmod(FILE, S_IFDIR | 0o755), 0);
+    assert_eq!(stat(fd).mode, S_IFREG | 0o755);
+    assert_eq!(chmod("no_such_file\0", 0o644), -1);
+    close(other);
+    close(fd);
+
+    // 管道和不合法的文件描述符都不支持
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    assert_eq!(fchmod(pipe_fd[0], 0o644), -1);
+    let mut buf = Stat::default();
+    assert_eq!(fstat(pipe_fd[1], &mut buf), -1);
+    close(pipe_fd[0]);
+    close(pipe_fd[1]);
+    assert_eq!(fchmod(fd, 0o644), -1);
+    println!("fchmod_test passed!");
+    0
+}
@@ -0,0 +1,21 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use user_lib::{close, dup};
+
+// 依次检查参数中给出的各个文件描述符是否处于打开状态，以位掩码的形式作为退出码返回：第 i 个参数对应的文件描述符打开时第 i 位为 1
+#[no_mangle]
+pub fn main(_argc: usize, argv: &[&str]) -> i32 {
+    let mut mask = 0;
+    for (i, arg) in argv.iter().skip(1).enumerate() {
+        let fd: usize = arg.parse().unwrap();
+        let new_fd = dup(fd);
+        if new_fd >= 0 {
+            close(new_fd as usize);
+            mask |= 1 << i;
+        }
+    }
+    mask
+}
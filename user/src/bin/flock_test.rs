@@ -0,0 +1,78 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::*;
+
+const FILE: &str = "flock_file\0";
+
+fn open_file() -> usize {
+    let fd = open(FILE, OpenFlags::CREATE | OpenFlags::RDWR);
+    assert!(fd > 0);
+    fd as usize
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let fd = open_file();
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    assert_eq!(flock(fd, LOCK_EX), 0);
+    // only regular files can be locked
+    assert_eq!(flock(pipe_fd[0], LOCK_EX), -1);
+    assert_eq!(flock(fd, 0), -1);
+
+    // the child blocks on the exclusive lock until the parent releases it
+    let pid = fork();
+    if pid == 0 {
+        close(pipe_fd[0]);
+        let fd = open_file();
+        assert_eq!(flock(fd, LOCK_EX | LOCK_NB), -1);
+        assert_eq!(flock(fd, LOCK_SH | LOCK_NB), -1);
+        assert_eq!(flock(fd, LOCK_EX), 0);
+        write(pipe_fd[1], b"c");
+        assert_eq!(flock(fd, LOCK_UN), 0);
+        exit(0);
+    }
+    sleep(100);
+    write(pipe_fd[1], b"p");
+    assert_eq!(flock(fd, LOCK_UN), 0);
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    close(pipe_fd[1]);
+    let mut order = [0u8; 2];
+    assert_eq!(read(pipe_fd[0], &mut order[..1]), 1);
+    assert_eq!(read(pipe_fd[0], &mut order[1..]), 1);
+    assert_eq!(&order, b"pc");
+    close(pipe_fd[0]);
+
+    // shared locks coexist, an exclusive one does not
+    assert_eq!(flock(fd, LOCK_SH), 0);
+    let pid = fork();
+    if pid == 0 {
+        let fd = open_file();
+        assert_eq!(flock(fd, LOCK_SH | LOCK_NB), 0);
+        assert_eq!(flock(fd, LOCK_EX | LOCK_NB), -1);
+        exit(0);
+    }
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    assert_eq!(flock(fd, LOCK_UN), 0);
+
+    // locks are released when the holder exits
+    let pid = fork();
+    if pid == 0 {
+        let fd = open_file();
+        assert_eq!(flock(fd, LOCK_EX), 0);
+        exit(0);
+    }
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    assert_eq!(flock(fd, LOCK_EX | LOCK_NB), 0);
+    close(fd);
+    println!("flock_test passed!");
+    0
+}
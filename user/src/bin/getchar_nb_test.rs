@@ -0,0 +1,58 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::console::getchar_nb;
+use user_lib::{
+    close, dup, fcntl, ioctl, pipe, read, write, OpenFlags, EAGAIN, F_GETFL, F_SETFL, TIOCSTI,
+};
+
+const NONBLOCK: usize = OpenFlags::NONBLOCK.bits() as usize;
+// 计数到这里时模拟用户按下一个键
+const KEY_AT: usize = 3;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // 没有输入时立即返回，而且不会改变标准输入的状态标志
+    assert_eq!(getchar_nb(), None);
+    assert_eq!(fcntl(0, F_GETFL, 0), 0);
+
+    // 一边打印计数一边轮询输入，按键出现之后的第一次轮询就能读到它
+    let mut counter = 0;
+    let key = loop {
+        println!("counter = {}", counter);
+        if counter == KEY_AT {
+            assert_eq!(ioctl(0, TIOCSTI, &b'q' as *const u8 as usize), 0);
+        }
+        if let Some(c) = getchar_nb() {
+            break c;
+        }
+        counter += 1;
+        assert!(counter <= KEY_AT);
+    };
+    assert_eq!((key, counter), (b'q', KEY_AT));
+    assert_eq!(getchar_nb(), None);
+
+    // 对管道同样有效：没有数据时返回 -EAGAIN ，写端全部关闭后返回 0
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    assert_eq!(fcntl(pipe_fd[0], F_SETFL, NONBLOCK), 0);
+    assert_eq!(fcntl(pipe_fd[0], F_GETFL, 0), NONBLOCK as isize);
+    let mut buf = [0u8; 2];
+    assert_eq!(read(pipe_fd[0], &mut buf), -EAGAIN);
+    assert_eq!(write(pipe_fd[1], b"ok"), 2);
+    assert_eq!(read(pipe_fd[0], &mut buf), 2);
+    assert_eq!(&buf, b"ok");
+    // 这个标志属于文件描述符，dup 出的文件描述符仍是阻塞的
+    let copy = dup(pipe_fd[0]) as usize;
+    assert_eq!(fcntl(copy, F_GETFL, 0), 0);
+    close(copy);
+    close(pipe_fd[1]);
+    assert_eq!(read(pipe_fd[0], &mut buf), 0);
+    assert_eq!(fcntl(pipe_fd[0], F_SETFL, 1 << 12), -1);
+    close(pipe_fd[0]);
+    println!("getchar_nb_test passed!");
+    0
+}
@@ -0,0 +1,54 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, getcpu, sched_getaffinity, sched_setaffinity, waitpid, yield_};
+
+// 反复让出处理器并检查每次回来时所在的 hart 都在 mask 之中
+fn check_runs_within(mask: usize) -> bool {
+    for _ in 0..20 {
+        let mut cpu = u32::MAX;
+        let mut node = u32::MAX;
+        assert_eq!(getcpu(Some(&mut cpu), Some(&mut node)), 0);
+        if mask & (1 << cpu) == 0 || node != 0 {
+            return false;
+        }
+        yield_();
+    }
+    true
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // 任何一项都可以不要
+    let mut cpu = u32::MAX;
+    assert_eq!(getcpu(Some(&mut cpu), None), 0);
+    assert!(sched_getaffinity(0) as usize & (1 << cpu) != 0);
+    let mut node = u32::MAX;
+    assert_eq!(getcpu(None, Some(&mut node)), 0);
+    assert_eq!(node, 0);
+    assert_eq!(getcpu(None, None), 0);
+
+    // 依次固定到每一个 hart 上，getcpu 总是报告这个 hart
+    let all = sched_getaffinity(0) as usize;
+    for hart in (0..usize::BITS as usize).filter(|hart| all & (1 << hart) != 0) {
+        assert_eq!(sched_setaffinity(0, 1 << hart), 0);
+        assert!(check_runs_within(1 << hart));
+    }
+
+    // 子进程继承固定的 hart
+    let last = 1 << (usize::BITS - 1 - all.leading_zeros());
+    assert_eq!(sched_setaffinity(0, last), 0);
+    let pid = fork();
+    if pid == 0 {
+        exit(if check_runs_within(last) { 0 } else { 1 });
+    }
+    let mut exit_code: i32 = -1;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    assert_eq!(sched_setaffinity(0, all), 0);
+    println!("getcpu_test passed!");
+    0
+}
@@ -0,0 +1,67 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use user_lib::{close, getdents, open, read, Dirent, OpenFlags};
+
+const FILES: usize = 20;
+
+fn create(name: &str) {
+    let fd = open(name, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    close(fd as usize);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    for i in 0..FILES {
+        create(format!("getdents_{}\0", i).as_str());
+    }
+    // 根目录只能以只读方式打开，也不能直接 read
+    assert_eq!(open("/\0", OpenFlags::WRONLY), -1);
+    let fd = open("/\0", OpenFlags::RDONLY);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    let mut buf = [0u8; 32];
+    assert_eq!(read(fd, &mut buf), -1);
+
+    // 每次只读 3 个目录项，中途新建的文件也能读到
+    let mut names: Vec<String> = Vec::new();
+    let mut dirents = [Dirent::default(); 3];
+    loop {
+        let n = getdents(fd, &mut dirents);
+        assert!(n >= 0);
+        if n == 0 {
+            break;
+        }
+        for dirent in dirents.iter().take(n as usize) {
+            assert!(dirent.ino > 0);
+            names.push(String::from(dirent.name()));
+        }
+        if names.len() == 3 {
+            create("getdents_late\0");
+        }
+    }
+    // 每个目录项恰好出现一次
+    for i in 0..FILES {
+        let name = format!("getdents_{}", i);
+        assert_eq!(names.iter().filter(|n| **n == name).count(), 1);
+    }
+    assert_eq!(names.iter().filter(|n| *n == "getdents_late").count(), 1);
+    // 已经读完的目录继续读返回 0
+    assert_eq!(getdents(fd, &mut dirents), 0);
+    close(fd);
+
+    // 普通文件不是目录
+    let fd = open("getdents_0\0", OpenFlags::RDONLY) as usize;
+    assert_eq!(getdents(fd, &mut dirents), -1);
+    close(fd);
+    println!("getdents_test passed!");
+    0
+}
@@ -0,0 +1,45 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use user_lib::sbrk;
+
+// 远超初始的静态堆空间，必须通过 sbrk 扩展堆才能分配成功
+const BIG_SIZE: usize = 4 * 1024 * 1024;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let brk_before = sbrk(0);
+    assert!(brk_before > 0);
+
+    let mut big: Vec<u8> = Vec::with_capacity(BIG_SIZE);
+    for i in 0..BIG_SIZE {
+        big.push(i as u8);
+    }
+    let mut boxes: Vec<Box<[usize; 64]>> = Vec::new();
+    for i in 0..1024 {
+        boxes.push(Box::new([i; 64]));
+    }
+    for (i, b) in boxes.iter().enumerate() {
+        assert!(b.iter().all(|&x| x == i));
+    }
+    for (i, &x) in big.iter().enumerate() {
+        assert_eq!(x, i as u8);
+    }
+    assert!(sbrk(0) as usize >= brk_before as usize + BIG_SIZE);
+    drop(boxes);
+    drop(big);
+
+    // freed memory is reused instead of growing the heap again
+    let brk_after = sbrk(0);
+    let again: Vec<u8> = alloc::vec![0xa5; BIG_SIZE];
+    assert!(again.iter().all(|&x| x == 0xa5));
+    assert_eq!(sbrk(0), brk_after);
+    println!("heap_grow passed!");
+    0
+}
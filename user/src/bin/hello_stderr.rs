@@ -0,0 +1,13 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    println!("Hello, stdout!");
+    eprint!("Hello, ");
+    eprintln!("stderr! {}", 2);
+    0
+}
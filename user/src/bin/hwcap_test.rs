@@ -0,0 +1,24 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{hwcap, HWCAP_A, HWCAP_C, HWCAP_D, HWCAP_F, HWCAP_I, HWCAP_M};
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let caps = hwcap();
+    // 应用是按照 riscv64gc 编译的，能够运行就说明 IMAC 都可用
+    assert_eq!(caps & HWCAP_I, HWCAP_I);
+    assert_eq!(caps & HWCAP_M, HWCAP_M);
+    assert_eq!(caps & HWCAP_A, HWCAP_A);
+    assert_eq!(caps & HWCAP_C, HWCAP_C);
+    // 用户态不能使用浮点指令
+    assert_eq!(caps & (HWCAP_F | HWCAP_D), 0);
+    // 位图中只有扩展字母对应的位
+    assert_eq!(caps >> 26, 0);
+    println!("hwcap = {:#x}", caps);
+    println!("hwcap_test passed!");
+    0
+}
@@ -3,27 +3,19 @@
 
 extern crate user_lib;
 
-use user_lib::{exec, fork, wait, yield_};
+use user_lib::{exec, fork, waitpid, yield_};
 
 #[no_mangle]
 fn main() -> i32 {
-    if fork() == 0 {
+    let shell = fork();
+    if shell == 0 {
         exec("user_shell\0", &[core::ptr::null::<u8>()]);
     } else {
+        // 过继给 initproc 的孤儿进程由内核回收，这里只需要等待 shell
+        let mut exit_code: i32 = 0;
+        waitpid(shell as usize, &mut exit_code);
         loop {
-            let mut exit_code: i32 = 0;
-            let pid = wait(&mut exit_code);
-            if pid == -1 {
-                yield_();
-                continue;
-            }
-            /*
-            println!(
-                "[initproc] Released a zombie process, pid={}, exit_code={}",
-                pid,
-                exit_code,
-            );
-            */
+            yield_();
         }
     }
     0
@@ -0,0 +1,74 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, exit, fork, get_time, pipe, read, waitpid, write};
+
+const HOGS: usize = 3;
+const ROUNDS: usize = 10;
+// 计算型进程持续占用处理器的时间（毫秒），要比所有往返都长
+const HOG_MS: isize = 3000;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // 后台的计算型进程从不等待 I/O ，只会被时钟中断抢占
+    let mut hogs = [0usize; HOGS];
+    for hog in hogs.iter_mut() {
+        let pid = fork();
+        if pid == 0 {
+            let start = get_time();
+            while get_time() - start < HOG_MS {}
+            exit(0);
+        }
+        *hog = pid as usize;
+    }
+
+    // 交互式的一对进程通过两个管道来回传递一个字节，每一次往返都要在 read 中等待对方
+    let mut request = [0usize; 2];
+    let mut reply = [0usize; 2];
+    assert_eq!(pipe(&mut request), 0);
+    assert_eq!(pipe(&mut reply), 0);
+    let echo = fork();
+    if echo == 0 {
+        close(request[1]);
+        close(reply[0]);
+        let mut byte = [0u8; 1];
+        while read(request[0], &mut byte) == 1 {
+            assert_eq!(write(reply[1], &byte), 1);
+        }
+        exit(0);
+    }
+    close(request[0]);
+    close(reply[1]);
+    let mut byte = [0u8; 1];
+    let mut max_latency = 0;
+    let start = get_time();
+    for round in 0..ROUNDS {
+        let sent = get_time();
+        assert_eq!(write(request[1], &[round as u8]), 1);
+        assert_eq!(read(reply[0], &mut byte), 1);
+        assert_eq!(byte[0], round as u8);
+        max_latency = max_latency.max(get_time() - sent);
+    }
+    let elapsed = get_time() - start;
+    close(request[1]);
+    close(reply[0]);
+    println!(
+        "{} round trips against {} hogs: {}ms in total, at most {}ms each",
+        ROUNDS, HOGS, elapsed, max_latency
+    );
+    // 交互式进程不必等到计算型进程全部结束才能完成往返
+    assert!(elapsed < HOG_MS);
+
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(echo as usize, &mut exit_code), echo);
+    assert_eq!(exit_code, 0);
+    for hog in hogs {
+        assert_eq!(waitpid(hog, &mut exit_code), hog as isize);
+        assert_eq!(exit_code, 0);
+    }
+    println!("io_boost_test passed!");
+    0
+}
@@ -0,0 +1,58 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, open, read, statx, unlink, write, OpenFlags, Statx, STATX_IO, STATX_SIZE};
+
+const FILE: &str = "io_counters\0";
+
+fn counters(fd: usize) -> (u64, u64) {
+    let mut st = Statx::default();
+    assert_eq!(statx(fd, STATX_IO, &mut st), 0);
+    assert_eq!(st.mask, STATX_IO);
+    (st.bytes_read, st.bytes_written)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // 重新创建的文件从 0 开始计数
+    unlink(FILE);
+    let fd = open(FILE, OpenFlags::CREATE | OpenFlags::RDWR);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    assert_eq!(counters(fd), (0, 0));
+    let data = [0x5au8; 1000];
+    assert_eq!(write(fd, &data), 1000);
+    assert_eq!(write(fd, &data[..24]), 24);
+    assert_eq!(counters(fd), (0, 1024));
+    close(fd);
+
+    // 计数属于文件而不是打开实例，关闭之后再打开仍然保留，多个打开实例累计到一起
+    let reader = open(FILE, OpenFlags::RDONLY);
+    let writer = open(FILE, OpenFlags::WRONLY);
+    assert!(reader > 0 && writer > 0);
+    let (reader, writer) = (reader as usize, writer as usize);
+    let mut buf = [0u8; 300];
+    assert_eq!(read(reader, &mut buf), 300);
+    assert_eq!(write(writer, &buf[..100]), 100);
+    assert_eq!(counters(reader), (300, 1124));
+    assert_eq!(counters(writer), (300, 1124));
+    // 读到文件末尾之后不再计数
+    let mut rest = [0u8; 1024];
+    assert_eq!(read(reader, &mut rest), 724);
+    assert_eq!(read(reader, &mut rest), 0);
+    assert_eq!(counters(reader), (1024, 1124));
+
+    // 没有请求的字段不会填写
+    let mut st = Statx::default();
+    assert_eq!(statx(reader, STATX_SIZE, &mut st), 0);
+    assert_eq!(st.mask, STATX_SIZE);
+    assert_eq!((st.bytes_read, st.bytes_written), (0, 0));
+    close(reader);
+    close(writer);
+    assert_eq!(unlink(FILE), 0);
+    println!("io_counters_test passed!");
+    0
+}
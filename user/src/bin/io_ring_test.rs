@@ -0,0 +1,91 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, io_setup, io_submit, open, pipe, write, IoCqe, IoRing, IoSqe, OpenFlags, EBUSY,
+    IORING_OP_READ, IORING_OP_WRITE,
+};
+
+const FILE: &str = "io_ring_file\0";
+const CONTENT: &[u8] = b"hello, io ring";
+const ENTRIES: usize = 4;
+
+// 缓冲区通过可变指针交给内核，内核在 io_submit 中修改它们之后编译器不会沿用旧的值
+fn sqe(opcode: u32, fd: usize, buf: &mut [u8], user_data: usize) -> IoSqe {
+    IoSqe {
+        opcode,
+        fd: fd as u32,
+        buf: buf.as_mut_ptr() as usize,
+        len: buf.len(),
+        user_data,
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let fd = open(FILE, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    assert_eq!(write(fd as usize, CONTENT), CONTENT.len() as isize);
+    close(fd as usize);
+    let file = open(FILE, OpenFlags::RDONLY) as usize;
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+
+    let mut sq: IoRing<IoSqe, ENTRIES> = IoRing::new();
+    let mut cq: IoRing<IoCqe, ENTRIES> = IoRing::new();
+    // 没有登记队列时不能提交
+    assert_eq!(io_submit(), -1);
+    assert_eq!(io_setup(&mut sq, &mut cq), 0);
+
+    // 一次提交中连续读两次文件，再先写管道后从管道读出
+    let mut head = [0u8; 5];
+    let mut rest = [0u8; 16];
+    let mut message = *b"ping";
+    let mut echo = [0u8; 4];
+    assert!(sq.push(sqe(IORING_OP_READ, file, &mut head, 1)));
+    assert!(sq.push(sqe(IORING_OP_READ, file, &mut rest, 2)));
+    assert!(sq.push(sqe(IORING_OP_WRITE, pipe_fd[1], &mut message, 3)));
+    assert!(sq.push(sqe(IORING_OP_READ, pipe_fd[0], &mut echo, 4)));
+    assert!(!sq.push(sqe(IORING_OP_READ, file, &mut rest, 5)));
+    assert_eq!(io_submit(), ENTRIES as isize);
+    assert!(sq.is_empty());
+
+    // 结果按照提交的顺序出现在完成队列中
+    let rest_len = CONTENT.len() - head.len();
+    for (user_data, result) in [(1, head.len()), (2, rest_len), (3, 4), (4, 4)] {
+        let cqe = cq.pop().unwrap();
+        assert_eq!(cqe.user_data, user_data);
+        assert_eq!(cqe.result, result as isize);
+    }
+    assert!(cq.pop().is_none());
+    assert_eq!(&head, &CONTENT[..head.len()]);
+    assert_eq!(&rest[..rest_len], &CONTENT[head.len()..]);
+    assert_eq!(&echo, &message);
+
+    // 完成队列已满时一个请求也不执行，请求留在提交队列中，取走一个结果之后才能继续
+    for user_data in 0..ENTRIES {
+        assert!(sq.push(sqe(IORING_OP_WRITE, pipe_fd[1], &mut message, user_data)));
+    }
+    assert_eq!(io_submit(), ENTRIES as isize);
+    assert!(sq.push(sqe(IORING_OP_READ, 42, &mut rest, ENTRIES)));
+    assert_eq!(io_submit(), -EBUSY);
+    assert_eq!(sq.len(), 1);
+    assert_eq!(cq.pop().unwrap().user_data, 0);
+    assert_eq!(io_submit(), 1);
+    assert!(sq.is_empty());
+    // 不合法的 fd 与 read 一样返回 -1
+    for (user_data, result) in [(1, 4), (2, 4), (3, 4), (ENTRIES, -1)] {
+        let cqe = cq.pop().unwrap();
+        assert_eq!(cqe.user_data, user_data);
+        assert_eq!(cqe.result, result);
+    }
+
+    close(file);
+    close(pipe_fd[0]);
+    close(pipe_fd[1]);
+    println!("io_ring_test passed!");
+    0
+}
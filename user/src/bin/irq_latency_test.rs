@@ -0,0 +1,68 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+#[macro_use]
+extern crate user_lib;
+
+use alloc::vec;
+use user_lib::{
+    close, copy_file_range, get_time, irq_stats, open, unlink, write, IrqStats, OpenFlags,
+};
+
+const SRC: &str = "/irq_src\0";
+const DST: &str = "/irq_dst\0";
+// 一次在内核中至少要运行这么久，才能保证期间有时钟中断到期（时钟中断的间隔为 10ms）
+const LONG_OP_MS: isize = 30;
+const MAX_SIZE: usize = 2 << 20;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut stats = IrqStats::default();
+    assert_eq!(irq_stats(&mut stats, true), 0);
+    // 在用户态忙等，期间到期的时钟中断都能立即被处理
+    let start = get_time();
+    while get_time() - start < 50 {}
+    assert_eq!(irq_stats(&mut stats, true), 0);
+    assert!(stats.count > 0);
+    assert!(stats.avg_us <= stats.max_us);
+    let quiet_max_us = stats.max_us;
+
+    // 长时间的内核操作：在内核中复制一个大文件，期间到期的时钟中断要等到返回用户态之后才能处理。
+    // 文件逐渐增大，直到一次复制的时间足够长
+    let src = open(SRC, OpenFlags::CREATE | OpenFlags::TRUNC | OpenFlags::RDWR);
+    let dst = open(DST, OpenFlags::CREATE | OpenFlags::TRUNC | OpenFlags::RDWR);
+    assert!(src > 0 && dst > 0);
+    let (src, dst) = (src as usize, dst as usize);
+    let chunk = vec![0x5au8; 4096];
+    let mut size = 0;
+    let mut elapsed = 0;
+    while elapsed < LONG_OP_MS && size < MAX_SIZE {
+        for _ in 0..16 {
+            assert_eq!(write(src, &chunk), chunk.len() as isize);
+            size += chunk.len();
+        }
+        assert_eq!(irq_stats(&mut stats, true), 0);
+        let (mut in_off, mut out_off) = (0, 0);
+        let start = get_time();
+        let copied = copy_file_range(src, Some(&mut in_off), dst, Some(&mut out_off), size);
+        elapsed = get_time() - start;
+        assert_eq!(copied, size as isize);
+    }
+    assert_eq!(irq_stats(&mut stats, false), 0);
+    close(src);
+    close(dst);
+    assert_eq!(unlink(SRC), 0);
+    assert_eq!(unlink(DST), 0);
+    println!(
+        "quiet max latency {}us, copying {} bytes took {}ms, max latency {}us",
+        quiet_max_us, size, elapsed, stats.max_us
+    );
+    assert!(elapsed >= LONG_OP_MS);
+    // 到期时刻位于复制开始之后的 10ms 以内，直到复制结束才被处理，再留出 get_time 毫秒精度的误差
+    assert!(stats.max_us >= (elapsed - 12) as usize * 1000);
+    assert!(stats.max_us > quiet_max_us);
+    println!("irq_latency_test passed!");
+    0
+}
@@ -0,0 +1,63 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, get_time, loadavg, sleep, waitpid, LoadAvg, LOAD_FSHIFT};
+
+const BUSY_CHILDREN: usize = 4;
+const BUSY_MS: isize = 3000;
+
+fn print_load(label: &str, load: &LoadAvg) {
+    let one = load.loads[0];
+    println!(
+        "{}: load {}.{:02}, busy {} ms, idle {} ms",
+        label,
+        one >> LOAD_FSHIFT,
+        ((one & ((1 << LOAD_FSHIFT) - 1)) * 100) >> LOAD_FSHIFT,
+        load.busy_ms,
+        load.idle_ms
+    );
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // 先让前一个测试留下的负载在 1 秒的窗口中衰减掉
+    sleep(1500);
+    let mut before = LoadAvg::default();
+    assert_eq!(loadavg(&mut before), 0);
+    print_load("before", &before);
+
+    let start = get_time();
+    let mut pids = [0isize; BUSY_CHILDREN];
+    for pid in pids.iter_mut() {
+        *pid = fork();
+        if *pid == 0 {
+            // 一直计算，不主动让出处理器
+            while get_time() - start < BUSY_MS {}
+            exit(0);
+        }
+    }
+    // 2 秒之后 1 秒窗口的平均值已经接近新的负载
+    sleep(2000);
+    let mut after = LoadAvg::default();
+    assert_eq!(loadavg(&mut after), 0);
+    let elapsed = (get_time() - start) as usize;
+    print_load("after", &after);
+    for pid in pids {
+        let mut exit_code: i32 = -1;
+        assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+        assert_eq!(exit_code, 0);
+    }
+
+    // 1 秒窗口的负载上升了接近 BUSY_CHILDREN ，但不会超过太多
+    let one = 1 << LOAD_FSHIFT;
+    assert!(after.loads[0] >= before.loads[0] + BUSY_CHILDREN * one * 3 / 4);
+    assert!(after.loads[0] <= before.loads[0] + (BUSY_CHILDREN + 1) * one);
+    // 这段时间处理器一直在运行任务
+    assert!(after.busy_ms - before.busy_ms >= elapsed / 2);
+    assert!(after.idle_ms >= before.idle_ms);
+    println!("loadavg_test passed!");
+    0
+}
@@ -0,0 +1,75 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicBool, Ordering};
+use user_lib::{
+    close, exit, fork, pipe, read, register_lowmem, sbrk, sigaction, sigreturn, waitpid, write,
+    SignalAction, SIGMEMPRESSURE,
+};
+
+const PAGE_SIZE: usize = 4096;
+
+static NOTIFIED: AtomicBool = AtomicBool::new(false);
+
+fn lowmem_handler() {
+    NOTIFIED.store(true, Ordering::SeqCst);
+    sigreturn();
+}
+
+fn handle_lowmem() {
+    let mut action = SignalAction::default();
+    action.handler = lowmem_handler as usize;
+    assert_eq!(
+        sigaction(
+            SIGMEMPRESSURE,
+            Some(&action),
+            Some(&mut SignalAction::default())
+        ),
+        0
+    );
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    handle_lowmem();
+    // 子进程同样设置了处理例程但是没有登记，内存紧张时不应收到通知
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    let pid = fork();
+    if pid == 0 {
+        close(pipe_fd[1]);
+        let mut byte = [0u8; 1];
+        assert_eq!(read(pipe_fd[0], &mut byte), 1);
+        exit(NOTIFIED.load(Ordering::SeqCst) as i32);
+    }
+    close(pipe_fd[0]);
+    assert_eq!(register_lowmem(), 0);
+
+    // 逐页扩展堆并写入，直到空闲内存降到水位线以下收到通知。水位线之下仍留有余量，不会真的耗尽内存
+    let bottom = sbrk(0) as usize;
+    let mut pages = 0;
+    while !NOTIFIED.load(Ordering::SeqCst) {
+        assert!(
+            sbrk(PAGE_SIZE as i32) > 0,
+            "out of memory without notification"
+        );
+        unsafe {
+            ((bottom + pages * PAGE_SIZE) as *mut u8).write_volatile(1);
+        }
+        pages += 1;
+    }
+    println!("lowmem_test: notified after {} pages", pages);
+    // 收到通知之后释放内存
+    assert!(sbrk(-((pages * PAGE_SIZE) as i32)) > 0);
+
+    assert_eq!(write(pipe_fd[1], b"x"), 1);
+    close(pipe_fd[1]);
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    println!("lowmem_test passed!");
+    0
+}
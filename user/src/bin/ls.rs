@@ -0,0 +1,25 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{closedir, opendir, readdir};
+
+#[no_mangle]
+pub fn main(argc: usize, argv: &[&str]) -> i32 {
+    // 不带参数时列出当前的工作目录
+    let path = if argc > 1 { argv[1] } else { ".\0" };
+    let mut dir = match opendir(path) {
+        Some(dir) => dir,
+        None => {
+            println!("ls: cannot open directory {}", path.trim_end_matches('\0'));
+            return -1;
+        }
+    };
+    while let Some(entry) = readdir(&mut dir) {
+        println!("{}", entry.name);
+    }
+    closedir(dir);
+    0
+}
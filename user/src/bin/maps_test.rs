@@ -0,0 +1,75 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{dump_maps, exit, fork, mmap, mmap_named, munmap, waitpid, PROT_READ, PROT_WRITE};
+
+const NAMED: usize = 0x1000_0000;
+const UNNAMED: usize = 0x2000_0000;
+const LEN: usize = 2 * 4096;
+
+// 返回地址空间中名为 name 的逻辑段所在的行
+fn find<'a>(maps: &'a str, name: &str) -> Option<&'a str> {
+    maps.lines().find(|line| line.ends_with(name))
+}
+
+fn check_maps(buffer: &mut [u8]) {
+    let len = dump_maps(buffer);
+    assert!(len > 0);
+    let maps = core::str::from_utf8(&buffer[..len as usize]).unwrap();
+    assert_eq!(
+        find(maps, "[mmap:libfoo]"),
+        Some("0x10000000-0x10002000 rw-u [mmap:libfoo]")
+    );
+    assert_eq!(
+        find(maps, "[mmap]"),
+        Some("0x20000000-0x20002000 r--u [mmap]")
+    );
+    // 程序的各个段、栈、堆和 Trap 上下文都有名字
+    assert!(maps.lines().any(|line| line.contains(" [segment ")));
+    assert!(find(maps, "[stack]").unwrap().contains(" rw-u "));
+    assert!(find(maps, "[heap]").is_some());
+    assert!(find(maps, "[trap context]").unwrap().contains(" rw-- "));
+    // 各行按起始地址排列
+    let starts = maps
+        .lines()
+        .map(|line| usize::from_str_radix(&line[2..line.find('-').unwrap()], 16).unwrap());
+    assert!(starts.clone().zip(starts.skip(1)).all(|(a, b)| a < b));
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(
+        mmap_named(NAMED, LEN, PROT_READ | PROT_WRITE, "libfoo\0"),
+        0
+    );
+    assert_eq!(mmap(UNNAMED, LEN, PROT_READ), 0);
+    // 名字过长的映射不会建立
+    let long_name = "a_name_that_is_much_too_long_for_a_mapping\0";
+    assert_eq!(mmap_named(0x3000_0000, LEN, PROT_READ, long_name), -1);
+
+    // 结果写入一段按需清零的内存中
+    let buffer = unsafe { core::slice::from_raw_parts_mut(NAMED as *mut u8, LEN) };
+    check_maps(buffer);
+    assert_eq!(dump_maps(&mut buffer[..16]), -1);
+
+    // fork 出的子进程中逻辑段的名字保持不变
+    let pid = fork();
+    if pid == 0 {
+        check_maps(buffer);
+        exit(0);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+
+    assert_eq!(munmap(UNNAMED, LEN), 0);
+    let len = dump_maps(buffer);
+    let maps = core::str::from_utf8(&buffer[..len as usize]).unwrap();
+    assert!(find(maps, "[mmap]").is_none());
+    assert_eq!(munmap(NAMED, LEN), 0);
+    println!("maps_test passed!");
+    0
+}
@@ -0,0 +1,55 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mincore, mmap, munmap, PROT_READ, PROT_WRITE};
+
+const START: usize = 0x1000_0000;
+const PAGES: usize = 16;
+const PAGE_SIZE: usize = 4096;
+// 存放结果的数组跨越这一段映射中两个页面的边界
+const VEC_AREA: usize = 0x2000_0000;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let len = PAGES * PAGE_SIZE;
+    assert_eq!(mmap(START, len, PROT_READ | PROT_WRITE), 0);
+    assert_eq!(mmap(VEC_AREA, 2 * PAGE_SIZE, PROT_READ | PROT_WRITE), 0);
+    let vec = unsafe {
+        core::slice::from_raw_parts_mut((VEC_AREA + PAGE_SIZE - PAGES / 2) as *mut u8, PAGES)
+    };
+
+    // 刚映射的页面都还没有属于自己的页帧
+    assert_eq!(mincore(START, len, vec), 0);
+    assert!(vec.iter().all(|&resident| resident == 0));
+
+    // 写入偶数页，读取奇数页。读取只会映射到共享零页，不会分配页帧
+    for page in 0..PAGES {
+        let addr = START + page * PAGE_SIZE;
+        unsafe {
+            if page % 2 == 0 {
+                (addr as *mut u8).write_volatile(1);
+            } else {
+                assert_eq!((addr as *const u8).read_volatile(), 0);
+            }
+        }
+    }
+    assert_eq!(mincore(START, len, vec), 0);
+    for (page, &resident) in vec.iter().enumerate() {
+        assert_eq!(resident, (page % 2 == 0) as u8, "page {}", page);
+    }
+    // 区间的长度不必是页面大小的整数倍
+    let mut one = [0xffu8; 1];
+    assert_eq!(mincore(START + PAGE_SIZE, 1, &mut one), 0);
+    assert_eq!(one[0], 0);
+
+    // 起始地址没有对齐，或者区间中有不属于任何映射的页面
+    assert_eq!(mincore(START + 1, PAGE_SIZE, vec), -1);
+    assert_eq!(mincore(START + len - PAGE_SIZE, 2 * PAGE_SIZE, vec), -1);
+    assert_eq!(munmap(START, len), 0);
+    assert_eq!(mincore(START, PAGE_SIZE, vec), -1);
+    println!("mincore_test passed!");
+    0
+}
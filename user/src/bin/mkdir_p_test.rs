@@ -0,0 +1,57 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, closedir, mkdir, mkdir_p, open, opendir, readdir, write, OpenFlags, DT_DIR, EEXIST,
+    ENOENT, ENOTDIR,
+};
+
+// 镜像中可能已经有上一次运行时创建的目录，mkdir_p 会跳过它们
+const DEEP: &str = "/mkdir_p_a/b/c\0";
+const FILE: &str = "/mkdir_p_a/file\0";
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(mkdir_p(DEEP, 0o755), 0);
+    for path in ["/mkdir_p_a\0", "/mkdir_p_a/b\0", DEEP] {
+        let dir = opendir(path);
+        assert!(dir.is_some(), "{} is not a directory", path);
+        closedir(dir.unwrap());
+    }
+    let mut dir = opendir("/mkdir_p_a/b\0").unwrap();
+    let mut found = false;
+    while let Some(entry) = readdir(&mut dir) {
+        if entry.name == "c" {
+            assert_eq!(entry.d_type, DT_DIR);
+            found = true;
+        }
+    }
+    closedir(dir);
+    assert!(found);
+    // 所有目录都已经存在时什么也不做
+    assert_eq!(mkdir_p(DEEP, 0o755), 0);
+    assert_eq!(mkdir(DEEP, 0o755), -EEXIST);
+    assert_eq!(mkdir("/mkdir_p_missing/d\0", 0o755), -ENOENT);
+
+    // 新建的目录中可以创建普通文件
+    let fd = open(
+        "/mkdir_p_a/b/c/data\0",
+        OpenFlags::CREATE | OpenFlags::WRONLY,
+    );
+    assert!(fd > 0);
+    assert_eq!(write(fd as usize, b"hello"), 5);
+    close(fd as usize);
+
+    // 路径中的某一级是普通文件
+    let fd = open(FILE, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    close(fd as usize);
+    assert_eq!(mkdir_p("/mkdir_p_a/file/d\0", 0o755), -ENOTDIR);
+    assert_eq!(mkdir_p(FILE, 0o755), -ENOTDIR);
+    assert_eq!(mkdir("/mkdir_p_a/file/d\0", 0o755), -ENOTDIR);
+    println!("mkdir_p_test passed!");
+    0
+}
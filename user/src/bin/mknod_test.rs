@@ -0,0 +1,77 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, exit, fork, fstat, getuid, makedev, mknod, open, read, setuid, waitpid, write,
+    OpenFlags, Stat, S_IFCHR, S_IFREG,
+};
+
+// 设备文件放在根目录下
+const NULL: &str = "mknod_null\0";
+const ZERO: &str = "mknod_zero\0";
+
+// 镜像中可能已经有上一次运行时创建的设备文件
+fn make_device(path: &str, dev: u32) {
+    let fd = open(path, OpenFlags::RDONLY);
+    if fd < 0 {
+        assert_eq!(mknod(path, S_IFCHR | 0o666, dev), 0);
+    } else {
+        close(fd as usize);
+    }
+    assert_eq!(mknod(path, S_IFCHR | 0o666, dev), -1);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    make_device(NULL, makedev(1, 3));
+    make_device(ZERO, makedev(1, 5));
+    // 只能创建字符设备
+    assert_eq!(mknod("mknod_regular\0", S_IFREG | 0o644, makedev(1, 3)), -1);
+
+    // 放弃特权之后不能再创建设备文件，也无法恢复特权
+    assert_eq!(getuid(), 0);
+    let pid = fork();
+    if pid == 0 {
+        assert_eq!(setuid(1000), 0);
+        assert_eq!(getuid(), 1000);
+        assert_eq!(mknod("mknod_denied\0", S_IFCHR | 0o666, makedev(1, 3)), -1);
+        assert!(open("mknod_denied\0", OpenFlags::RDONLY) < 0);
+        assert_eq!(setuid(0), -1);
+        assert_eq!(setuid(1000), 0);
+        exit(0);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    assert_eq!(getuid(), 0);
+
+    // 写入 null 的数据都被丢弃，读不出任何东西
+    let fd = open(NULL, OpenFlags::RDWR);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    assert_eq!(write(fd, b"discarded"), 9);
+    let mut buf = [0xffu8; 16];
+    assert_eq!(read(fd, &mut buf), 0);
+    // 打开的是设备而不是文件系统中的文件
+    let mut stat = Stat::default();
+    assert_eq!(fstat(fd, &mut stat), -1);
+    close(fd);
+    // 以 CREATE 方式重新打开也不会把它变成普通文件
+    let fd = open(NULL, OpenFlags::CREATE | OpenFlags::RDONLY);
+    assert!(fd > 0);
+    assert_eq!(read(fd as usize, &mut buf), 0);
+    close(fd as usize);
+
+    let fd = open(ZERO, OpenFlags::RDWR);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    assert_eq!(write(fd, b"discarded"), 9);
+    assert_eq!(read(fd, &mut buf), buf.len() as isize);
+    assert!(buf.iter().all(|byte| *byte == 0));
+    close(fd);
+    println!("mknod_test passed!");
+    0
+}
@@ -0,0 +1,72 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, exit, fork, get_time, nice, pipe, read, wait, waitpid, write};
+
+const RUN_MS: isize = 1000;
+
+// 在子进程中把 nice 值调整 delta ，然后一直计算到 deadline ，把完成的循环次数写入管道
+fn spawn_counter(delta: isize, start: isize) -> (isize, usize) {
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    let pid = fork();
+    if pid == 0 {
+        close(pipe_fd[0]);
+        assert_eq!(nice(delta), delta);
+        while get_time() < start {}
+        let mut count: usize = 0;
+        while get_time() < start + RUN_MS {
+            count += 1;
+        }
+        write(pipe_fd[1], &count.to_ne_bytes());
+        exit(0);
+    }
+    close(pipe_fd[1]);
+    (pid, pipe_fd[0])
+}
+
+fn collect(pid: isize, fd: usize) -> usize {
+    let mut buf = [0u8; 8];
+    assert_eq!(read(fd, &mut buf), 8);
+    close(fd);
+    let mut exit_code: i32 = -1;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    usize::from_ne_bytes(buf)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // nice 值被限制在 -20 到 19 之间，子进程继承父进程的 nice 值
+    assert_eq!(nice(0), 0);
+    let pid = fork();
+    if pid == 0 {
+        assert_eq!(nice(100), 19);
+        assert_eq!(nice(-100), -20);
+        assert_eq!(nice(25), 5);
+        if fork() == 0 {
+            exit(if nice(0) == 5 { 0 } else { 1 });
+        }
+        let mut exit_code: i32 = -1;
+        wait(&mut exit_code);
+        exit(exit_code);
+    }
+    let mut exit_code: i32 = -1;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    assert_eq!(nice(0), 0);
+
+    // 两个兄弟进程同时计算，nice 值为 10 的那个分到的处理器时间少得多
+    let start = get_time() + 100;
+    let (normal_pid, normal_fd) = spawn_counter(0, start);
+    let (nicer_pid, nicer_fd) = spawn_counter(10, start);
+    let normal = collect(normal_pid, normal_fd);
+    let nicer = collect(nicer_pid, nicer_fd);
+    println!("nice 0: {} loops, nice 10: {} loops", normal, nicer);
+    assert!(normal > nicer * 3);
+    println!("nice_test passed!");
+    0
+}
@@ -0,0 +1,60 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    chdir, close, fstatat, mkdir_p, open, openat, read, write, OpenFlags, Stat, AT_FDCWD, S_IFDIR,
+    S_IFMT, S_IFREG,
+};
+
+const CONTENT: &[u8] = b"openat!";
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(mkdir_p("/openat_dir/sub\0", 0o755), 0);
+    let dirfd = open("/openat_dir\0", OpenFlags::RDONLY);
+    assert!(dirfd > 0);
+
+    // 相对路径基于 dirfd 解析，不需要拼出完整的路径
+    let fd = openat(dirfd, "sub/file\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    assert_eq!(write(fd as usize, CONTENT), CONTENT.len() as isize);
+    close(fd as usize);
+    let mut stat = Stat::default();
+    assert_eq!(fstatat(dirfd, "sub/file\0", &mut stat), 0);
+    assert_eq!(stat.mode & S_IFMT, S_IFREG);
+    assert_eq!(stat.size, CONTENT.len());
+    assert_eq!(fstatat(dirfd, "sub\0", &mut stat), 0);
+    assert_eq!(stat.mode & S_IFMT, S_IFDIR);
+    assert_eq!(fstatat(dirfd, "missing\0", &mut stat), -1);
+
+    // 工作目录与 dirfd 无关
+    assert_eq!(chdir("/\0"), 0);
+    let fd = openat(dirfd, "./sub/../sub/file\0", OpenFlags::RDONLY);
+    assert!(fd > 0);
+    let mut buf = [0u8; 16];
+    assert_eq!(read(fd as usize, &mut buf), CONTENT.len() as isize);
+    assert_eq!(&buf[..CONTENT.len()], CONTENT);
+    close(fd as usize);
+
+    // 绝对路径忽略 dirfd ，AT_FDCWD 基于当前的工作目录
+    let fd = openat(-1, "/openat_dir/sub/file\0", OpenFlags::RDONLY);
+    assert!(fd > 0);
+    close(fd as usize);
+    assert_eq!(openat(AT_FDCWD, "sub/file\0", OpenFlags::RDONLY), -1);
+    assert_eq!(chdir("/openat_dir\0"), 0);
+    assert_eq!(fstatat(AT_FDCWD, "sub/file\0", &mut stat), 0);
+    assert_eq!(stat.size, CONTENT.len());
+    assert_eq!(chdir("/\0"), 0);
+
+    // dirfd 必须是已打开的目录
+    let fd = open("/openat_dir/sub/file\0", OpenFlags::RDONLY);
+    assert_eq!(openat(fd, "file\0", OpenFlags::RDONLY), -1);
+    close(fd as usize);
+    assert_eq!(openat(-1, "sub/file\0", OpenFlags::RDONLY), -1);
+    close(dirfd as usize);
+    println!("openat_test passed!");
+    0
+}
@@ -0,0 +1,83 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, exit, fork, pipe, read, sleep, waitpid, write};
+
+const NUM_ORPHANS: usize = 3;
+const MAX_PROBES: usize = 32;
+
+// 子进程 fork 出若干孙子进程后立即退出，孙子进程被过继给 initproc 并在之后退出。
+// initproc 不会为它们调用 waitpid ，只有内核回收了这些孤儿进程，它们的 pid 才会被回收。
+// 之后同时保持若干探测进程存活，直到所有孤儿进程的 pid 都被探测进程重新用到，与 pid 的复用顺序无关
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut pipe_fd = [0usize; 2];
+    pipe(&mut pipe_fd);
+    let child = fork();
+    if child == 0 {
+        close(pipe_fd[0]);
+        for _ in 0..NUM_ORPHANS {
+            let pid = fork();
+            if pid == 0 {
+                // outlive the parent
+                sleep(100);
+                exit(0);
+            }
+            write(pipe_fd[1], &(pid as usize).to_ne_bytes());
+        }
+        close(pipe_fd[1]);
+        exit(0);
+    }
+    close(pipe_fd[1]);
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(child as usize, &mut exit_code), child);
+    let mut orphans = [0usize; NUM_ORPHANS];
+    for orphan in orphans.iter_mut() {
+        let mut buf = [0u8; core::mem::size_of::<usize>()];
+        assert_eq!(read(pipe_fd[0], &mut buf), buf.len() as isize);
+        *orphan = usize::from_ne_bytes(buf);
+    }
+    close(pipe_fd[0]);
+    // wait until all orphans have exited
+    sleep(300);
+    // probes block on the pipe until it is closed, so they hold their pids together
+    let mut hold_fd = [0usize; 2];
+    pipe(&mut hold_fd);
+    let mut probes = [0usize; MAX_PROBES];
+    let mut num_probes = 0;
+    while num_probes < MAX_PROBES {
+        let pid = fork();
+        if pid == 0 {
+            close(hold_fd[1]);
+            let mut buf = [0u8; 1];
+            read(hold_fd[0], &mut buf);
+            exit(0);
+        }
+        probes[num_probes] = pid as usize;
+        num_probes += 1;
+        if orphans
+            .iter()
+            .all(|orphan| probes[..num_probes].contains(orphan))
+        {
+            break;
+        }
+    }
+    close(hold_fd[0]);
+    close(hold_fd[1]);
+    let probes = &probes[..num_probes];
+    for probe in probes.iter() {
+        assert_eq!(waitpid(*probe, &mut exit_code), *probe as isize);
+    }
+    for orphan in orphans.iter() {
+        assert!(
+            probes.contains(orphan),
+            "orphan {} is not reclaimed",
+            orphan
+        );
+    }
+    println!("orphan_reap passed!");
+    0
+}
@@ -0,0 +1,72 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+#[macro_use]
+extern crate user_lib;
+
+use alloc::format;
+use alloc::string::String;
+use user_lib::{close, dup, fork, pipe, read, waitpid, SIGABRT};
+
+// 在几层函数调用之后 panic ，回溯中应当能看到每一层的返回地址
+#[inline(never)]
+fn level3(depth: usize) -> usize {
+    if depth > 0 {
+        panic!("deliberate panic at depth {}", depth);
+    }
+    depth
+}
+
+#[inline(never)]
+fn level2(depth: usize) -> usize {
+    level3(depth + 1) + 1
+}
+
+#[inline(never)]
+fn level1(depth: usize) -> usize {
+    level2(depth + 1) + 1
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    let pid = fork();
+    if pid == 0 {
+        // 子进程的标准输出重定向到管道的写端
+        close(1);
+        assert_eq!(dup(pipe_fd[1]), 1);
+        close(pipe_fd[0]);
+        close(pipe_fd[1]);
+        level1(0);
+        unreachable!();
+    }
+    close(pipe_fd[1]);
+    let mut output = String::new();
+    let mut buf = [0u8; 256];
+    loop {
+        let len = read(pipe_fd[0], &mut buf);
+        if len <= 0 {
+            break;
+        }
+        output.push_str(core::str::from_utf8(&buf[..len as usize]).unwrap());
+    }
+    close(pipe_fd[0]);
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    // panic 之后进程仍然以 SIGABRT 异常退出
+    assert_eq!(exit_code, -SIGABRT);
+
+    let header = format!("[pid {}] Panicked at ", pid);
+    assert!(output.contains(&header), "no panic message in {:?}", output);
+    assert!(output.contains("deliberate panic at depth 2"));
+    let trace = &output[output.find("backtrace:\n").unwrap()..];
+    // 预编译的 core 库可能没有开启帧指针，回溯不一定能越过其中的栈帧，
+    // 但至少能走完用户库中 backtrace 和 panic 处理函数的两个栈帧
+    let frames = trace.lines().filter(|line| line.starts_with("  #")).count();
+    assert!(frames >= 2, "only {} frames in {:?}", frames, trace);
+    println!("panic_backtrace passed!");
+    0
+}
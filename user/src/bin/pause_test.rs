@@ -0,0 +1,49 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicBool, Ordering};
+use user_lib::*;
+
+static WOKEN: AtomicBool = AtomicBool::new(false);
+
+fn func() {
+    WOKEN.store(true, Ordering::SeqCst);
+    sigreturn();
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        let mut new = SignalAction::default();
+        let mut old = SignalAction::default();
+        new.handler = func as usize;
+        if sigaction(SIGUSR1, Some(&new), Some(&mut old)) < 0 {
+            panic!("Sigaction failed!");
+        }
+        assert_eq!(pause(), -1);
+        assert!(WOKEN.load(Ordering::SeqCst));
+        exit(0);
+    }
+    sleep(100);
+    assert_eq!(kill(pid as usize, SIGUSR1), 0);
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+
+    // a paused process can still be killed
+    let pid = fork();
+    if pid == 0 {
+        pause();
+        exit(0);
+    }
+    sleep(100);
+    assert_eq!(kill(pid as usize, SIGKILL), 0);
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, -9);
+    println!("pause_test passed!");
+    0
+}
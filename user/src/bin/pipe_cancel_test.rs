@@ -0,0 +1,99 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, exit, fork, kill, mmap, munmap, pipe, read, sleep, vmsplice, waitpid, write, PROT_READ,
+    PROT_WRITE, SIGKILL,
+};
+
+const PAGE_SIZE: usize = 4096;
+// 多于管道中最多能排队的整页数
+const PAGES: usize = 32;
+
+// 阻塞在管道上的方式
+#[derive(Clone, Copy)]
+enum Blocked {
+    Read,
+    Write,
+    VmspliceRead,
+    VmspliceWrite,
+}
+
+// 映射 pages 个页面并写入内容，让每个页面都有自己的物理页帧
+fn touched_pages(pages: usize) -> &'static mut [u8] {
+    let addr = mmap(0, pages * PAGE_SIZE, PROT_READ | PROT_WRITE);
+    assert!(addr > 0);
+    let region = unsafe { core::slice::from_raw_parts_mut(addr as *mut u8, pages * PAGE_SIZE) };
+    region.iter_mut().for_each(|byte| *byte = 0x5a);
+    region
+}
+
+// 子进程阻塞在管道上之后被 SIGKILL 终止，阻塞的 read/write/vmsplice 不会让它一直无法退出
+fn cancel_blocked(blocked: Blocked) {
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    let pid = fork();
+    if pid == 0 {
+        match blocked {
+            // 父进程还持有写端，管道一直为空
+            Blocked::Read => {
+                let mut buf = [0u8; 16];
+                read(pipe_fd[0], &mut buf);
+            }
+            // 父进程不读，管道写满之后就会阻塞
+            Blocked::Write => {
+                let buf = [0u8; 4096];
+                write(pipe_fd[1], &buf);
+            }
+            Blocked::VmspliceRead => {
+                vmsplice(pipe_fd[0], touched_pages(1));
+            }
+            // 整页移入的页帧排满之后就会阻塞
+            Blocked::VmspliceWrite => {
+                vmsplice(pipe_fd[1], touched_pages(PAGES));
+            }
+        }
+        exit(0);
+    }
+    sleep(50);
+    assert_eq!(kill(pid as usize, SIGKILL), 0);
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, -SIGKILL);
+    close(pipe_fd[0]);
+    close(pipe_fd[1]);
+}
+
+// 所有读端都已关闭时，写满的管道不会再有空闲空间，写入不再等待而是提前返回
+fn write_without_reader() {
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    close(pipe_fd[0]);
+    let buf = [0u8; 4096];
+    let written = write(pipe_fd[1], &buf);
+    assert!(written >= 0 && (written as usize) < buf.len());
+    close(pipe_fd[1]);
+
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    close(pipe_fd[0]);
+    let region = touched_pages(PAGES);
+    let (start, len) = (region.as_ptr() as usize, region.len());
+    let moved = vmsplice(pipe_fd[1], region);
+    assert!(moved >= 0 && (moved as usize) < len);
+    close(pipe_fd[1]);
+    assert_eq!(munmap(start, len), 0);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    cancel_blocked(Blocked::Read);
+    cancel_blocked(Blocked::Write);
+    cancel_blocked(Blocked::VmspliceRead);
+    cancel_blocked(Blocked::VmspliceWrite);
+    write_without_reader();
+    println!("pipe_cancel_test passed!");
+    0
+}
@@ -0,0 +1,51 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, exit, fork, pipe, read, sleep, waitpid, write};
+
+const FIRST: &[u8] = b"hello, ";
+const SECOND: &[u8] = b"world!";
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    let pid = fork();
+    if pid == 0 {
+        // 写者写到一半停顿一段时间，然后写完剩下的数据并关闭写端
+        close(pipe_fd[0]);
+        assert_eq!(write(pipe_fd[1], FIRST), FIRST.len() as isize);
+        sleep(100);
+        assert_eq!(write(pipe_fd[1], SECOND), SECOND.len() as isize);
+        close(pipe_fd[1]);
+        exit(0);
+    }
+    close(pipe_fd[1]);
+    // 写者停顿期间管道是空的，但写端仍然打开，读者必须等待而不是看到文件末尾
+    let mut buffer = [0u8; 32];
+    let total = FIRST.len() + SECOND.len();
+    assert_eq!(read(pipe_fd[0], &mut buffer[..total]), total as isize);
+    assert_eq!(&buffer[..FIRST.len()], FIRST);
+    assert_eq!(&buffer[FIRST.len()..total], SECOND);
+    // 所有写端都关闭并且数据读完之后，读才会返回 0 ，并且之后一直如此
+    assert_eq!(read(pipe_fd[0], &mut buffer), 0);
+    assert_eq!(read(pipe_fd[0], &mut buffer), 0);
+    close(pipe_fd[0]);
+
+    // 写者关闭时还没读完的数据仍然可以读出来，读到末尾时返回的是不足缓冲区大小的字节数
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    assert_eq!(write(pipe_fd[1], FIRST), FIRST.len() as isize);
+    close(pipe_fd[1]);
+    assert_eq!(read(pipe_fd[0], &mut buffer), FIRST.len() as isize);
+    assert_eq!(read(pipe_fd[0], &mut buffer), 0);
+    close(pipe_fd[0]);
+
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    println!("pipe_eof_test passed!");
+    0
+}
@@ -7,7 +7,7 @@ extern crate user_lib;
 extern crate alloc;
 
 use alloc::format;
-use user_lib::{close, fork, get_time, pipe, read, wait, write};
+use user_lib::{close, fork, pipe, read, wait, write, Rng};
 
 const LENGTH: usize = 3000;
 #[no_mangle]
@@ -40,8 +40,9 @@ pub fn main() -> i32 {
         // close write end of up pipe
         close(up_pipe_fd[1]);
         // generate a long random string
+        let mut rng = Rng::from_time();
         for ch in random_str.iter_mut() {
-            *ch = get_time() as u8;
+            *ch = rng.next_u64() as u8;
         }
         // send it
         assert_eq!(
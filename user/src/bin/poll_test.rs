@@ -0,0 +1,114 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+#[macro_use]
+extern crate user_lib;
+
+use alloc::vec;
+use user_lib::{
+    close, exit, fork, get_time, getrusage, mmap, munmap, pipe, poll, read, sleep, waitpid, write,
+    PollFd, Rusage, POLLHUP, POLLIN, POLLNVAL, POLLOUT, PROT_READ, PROT_WRITE, RUSAGE_SELF,
+};
+
+const TIMEOUT_MS: isize = 100;
+const FDS_PAGE: usize = 0x2000_0000;
+const PAGE_SIZE: usize = 4096;
+// 内核一次 poll 最多接受的文件描述符个数
+const POLL_MAX_FDS: usize = 1024;
+
+fn nvcsw() -> usize {
+    let mut usage = Rusage::default();
+    assert_eq!(getrusage(RUSAGE_SELF, &mut usage), 0);
+    usage.nvcsw
+}
+
+fn pollfd(fd: usize, events: u16) -> PollFd {
+    PollFd {
+        fd: fd as i32,
+        events,
+        revents: 0,
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut a = [0usize; 2];
+    let mut b = [0usize; 2];
+    assert_eq!(pipe(&mut a), 0);
+    assert_eq!(pipe(&mut b), 0);
+
+    // 两个管道都没有数据，poll 应当阻塞到超时，期间只发生一次主动切换而不是反复让出处理器
+    let mut fds = [pollfd(a[0], POLLIN), pollfd(b[0], POLLIN)];
+    let switches = nvcsw();
+    let start = get_time();
+    assert_eq!(poll(&mut fds, TIMEOUT_MS), 0);
+    let elapsed = get_time() - start;
+    assert!(elapsed >= TIMEOUT_MS, "poll returned after {}ms", elapsed);
+    assert!(
+        elapsed < 10 * TIMEOUT_MS,
+        "poll returned after {}ms",
+        elapsed
+    );
+    assert!(nvcsw() - switches <= 3, "poll was spinning");
+    assert!(fds.iter().all(|fd| fd.revents == 0));
+
+    // 空管道的写端可以立即写入，超时为 0 时不等待
+    let mut fds = [pollfd(a[1], POLLOUT)];
+    assert_eq!(poll(&mut fds, 0), 1);
+    assert_eq!(fds[0].revents, POLLOUT);
+
+    // 子进程稍后向第二个管道写入，poll 被它唤醒且只报告这一个管道
+    let pid = fork();
+    if pid == 0 {
+        sleep(50);
+        assert_eq!(write(b[1], b"x"), 1);
+        exit(0);
+    }
+    let mut fds = [pollfd(a[0], POLLIN), pollfd(b[0], POLLIN)];
+    assert_eq!(poll(&mut fds, -1), 1);
+    assert_eq!(fds[0].revents, 0);
+    assert_eq!(fds[1].revents, POLLIN);
+    let mut byte = [0u8; 1];
+    assert_eq!(read(b[0], &mut byte), 1);
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+
+    // 所有写端都关闭之后报告 POLLHUP ，即使 events 中没有它
+    close(b[1]);
+    let mut fds = [pollfd(b[0], POLLIN)];
+    assert_eq!(poll(&mut fds, -1), 1);
+    assert_eq!(fds[0].revents, POLLHUP);
+
+    // 没有打开的 fd 报告 POLLNVAL ，负数的 fd 被忽略
+    close(b[0]);
+    let mut fds = [
+        pollfd(b[0], POLLIN),
+        PollFd {
+            fd: -1,
+            ..pollfd(a[0], POLLIN)
+        },
+    ];
+    assert_eq!(poll(&mut fds, 0), 1);
+    assert_eq!(fds[0].revents, POLLNVAL);
+    assert_eq!(fds[1].revents, 0);
+
+    // fds 数组越过了映射的页面，或者整个位于未映射的地址上
+    let size = core::mem::size_of::<PollFd>();
+    assert_eq!(mmap(FDS_PAGE, PAGE_SIZE, PROT_READ | PROT_WRITE), 0);
+    let fds =
+        unsafe { core::slice::from_raw_parts_mut((FDS_PAGE + PAGE_SIZE - size) as *mut PollFd, 2) };
+    fds[0] = pollfd(a[0], POLLIN);
+    assert_eq!(poll(fds, 0), -1);
+    assert_eq!(munmap(FDS_PAGE, PAGE_SIZE), 0);
+    let fds = unsafe { core::slice::from_raw_parts_mut(FDS_PAGE as *mut PollFd, 1) };
+    assert_eq!(poll(fds, 0), -1);
+    // 文件描述符太多
+    let mut many = vec![pollfd(a[0], POLLIN); POLL_MAX_FDS + 1];
+    assert_eq!(poll(&mut many, 0), -1);
+    assert!(poll(&mut many[..POLL_MAX_FDS], 0) >= 0);
+
+    println!("poll_test passed!");
+    0
+}
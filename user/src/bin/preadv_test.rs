@@ -0,0 +1,74 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, fstat, open, pipe, preadv, pwritev, read, unlink, write, OpenFlags, Stat};
+
+const FILE: &str = "/preadv_file\0";
+const OFFSET: usize = 1024;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let fd = open(FILE, OpenFlags::CREATE | OpenFlags::TRUNC | OpenFlags::RDWR);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    assert_eq!(write(fd, b"head"), 4);
+
+    // 三个缓冲区依次写到 1024 处，偏移量仍然停在 4
+    let bufs: [&[u8]; 3] = [b"first-", b"second-", b"third"];
+    assert_eq!(pwritev(fd, &bufs, OFFSET), 18);
+    assert_eq!(write(fd, b"!"), 1);
+    let mut stat = Stat::default();
+    assert_eq!(fstat(fd, &mut stat), 0);
+    assert_eq!(stat.size, OFFSET + 18);
+
+    // 按照与写入时不同的切分读回来
+    let mut a = [0u8; 3];
+    let mut b = [0u8; 10];
+    let mut c = [0u8; 5];
+    assert_eq!(preadv(fd, &mut [&mut a, &mut b, &mut c], OFFSET), 18);
+    assert_eq!(&a, b"fir");
+    assert_eq!(&b, b"st-second-");
+    assert_eq!(&c, b"third");
+    // 文件头和中间的空洞
+    let mut head = [0u8; 5];
+    let mut hole = [0xffu8; 8];
+    assert_eq!(preadv(fd, &mut [&mut head, &mut hole], 0), 13);
+    assert_eq!(&head, b"head!");
+    assert!(hole.iter().all(|&byte| byte == 0));
+
+    // 读到文件末尾时只读出剩下的部分，后面的缓冲区不被改动
+    let mut a = [0u8; 4];
+    let mut b = [0u8; 4];
+    let mut c = [0xffu8; 4];
+    assert_eq!(preadv(fd, &mut [&mut a, &mut b, &mut c], OFFSET + 13), 5);
+    assert_eq!(&a, b"thir");
+    assert_eq!(&b[..1], b"d");
+    assert_eq!(c, [0xff; 4]);
+    assert_eq!(preadv(fd, &mut [&mut a], OFFSET + 18), 0);
+
+    // 定位读写都没有移动偏移量，顺序读从 5 开始
+    let mut buf = [0xffu8; 4];
+    assert_eq!(read(fd, &mut buf), 4);
+    assert_eq!(buf, [0; 4]);
+
+    // 超出最大文件大小的定位写入失败，文件保持不变
+    assert_eq!(pwritev(fd, &bufs, 16 << 20), -1);
+    assert_eq!(pwritev(fd, &bufs, 1 << 32), -1);
+    assert_eq!(fstat(fd, &mut stat), 0);
+    assert_eq!(stat.size, OFFSET + 18);
+    close(fd);
+
+    // 管道不支持定位读写
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    assert_eq!(pwritev(pipe_fd[1], &bufs, 0), -1);
+    assert_eq!(preadv(pipe_fd[0], &mut [&mut a], 0), -1);
+    close(pipe_fd[0]);
+    close(pipe_fd[1]);
+    assert_eq!(unlink(FILE), 0);
+    println!("preadv_test passed!");
+    0
+}
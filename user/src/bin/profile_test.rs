@@ -0,0 +1,72 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, copy_file_range, get_time, open, profile_start, profile_stop, unlink, write, OpenFlags,
+    ProfileStats, PROFILE_BUCKETS,
+};
+
+const SRC: &str = "/profile_src\0";
+const DST: &str = "/profile_dst\0";
+// 两个文件都能留在块缓存中，复制时只在块缓存和内核缓冲区之间搬运数据，不访问块设备
+const SIZE: usize = 2048;
+// 时钟中断的间隔为 10ms ，采样这么久可以得到几十个样本
+const PROFILE_MS: isize = 300;
+// 最热的这么多个区间应当占到内核态样本的一半以上
+const HOT_BUCKETS: usize = 8;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let src = open(SRC, OpenFlags::CREATE | OpenFlags::TRUNC | OpenFlags::RDWR);
+    let dst = open(DST, OpenFlags::CREATE | OpenFlags::TRUNC | OpenFlags::RDWR);
+    assert!(src > 0 && dst > 0);
+    let (src, dst) = (src as usize, dst as usize);
+    assert_eq!(write(src, &[0x5au8; SIZE]), SIZE as isize);
+
+    // 反复在两个文件之间复制数据，时间几乎都花在内核中
+    assert_eq!(profile_start(), 0);
+    let start = get_time();
+    while get_time() - start < PROFILE_MS {
+        for _ in 0..16 {
+            let (mut in_off, mut out_off) = (0, 0);
+            let copied = copy_file_range(src, Some(&mut in_off), dst, Some(&mut out_off), SIZE);
+            assert_eq!(copied, SIZE as isize);
+        }
+    }
+    let mut stats = ProfileStats::default();
+    assert_eq!(profile_stop(&mut stats), 0);
+    close(src);
+    close(dst);
+    assert_eq!(unlink(SRC), 0);
+    assert_eq!(unlink(DST), 0);
+
+    println!(
+        "{} kernel samples, {} user samples",
+        stats.kernel_samples, stats.user_samples
+    );
+    assert!(stats.kernel_samples >= 10);
+    assert!(stats.kernel_samples > 2 * stats.user_samples);
+    // 按样本数从多到少列出最热的区间，用内核的符号表可以查到其中的函数
+    let mut order = [0usize; PROFILE_BUCKETS];
+    for (i, bucket) in order.iter_mut().enumerate() {
+        *bucket = i;
+    }
+    order.sort_unstable_by_key(|&i| core::cmp::Reverse(stats.buckets[i]));
+    let mut hot_samples = 0;
+    for &i in order.iter().take(HOT_BUCKETS) {
+        let (start, end) = stats.bucket_range(i);
+        println!("[{:#x}, {:#x}): {}", start, end, stats.buckets[i]);
+        hot_samples += stats.buckets[i];
+    }
+    let samples: usize = stats.buckets.iter().sum();
+    assert!(hot_samples * 2 >= samples);
+    // 采样已经停止，之后不会再有新的样本，停止时采集到的结果仍然可以读出
+    let kernel_samples = stats.kernel_samples;
+    assert_eq!(profile_stop(&mut stats), 0);
+    assert_eq!(stats.kernel_samples, kernel_samples);
+    println!("profile_test passed!");
+    0
+}
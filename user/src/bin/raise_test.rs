@@ -0,0 +1,45 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{abort, exit, fork, raise, waitpid, SIGABRT, SIGSEGV};
+
+// 在子进程中执行 f ，返回子进程的退出码
+fn run_in_child(f: fn()) -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        f();
+        // a self-raised fatal signal never returns here
+        exit(0);
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    exit_code
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(
+        run_in_child(|| {
+            abort();
+        }),
+        -SIGABRT
+    );
+    assert_eq!(
+        run_in_child(|| {
+            raise(SIGABRT);
+        }),
+        -SIGABRT
+    );
+    assert_eq!(
+        run_in_child(|| {
+            raise(SIGSEGV);
+        }),
+        -SIGSEGV
+    );
+    assert_eq!(raise(64), -1);
+    println!("raise_test passed!");
+    0
+}
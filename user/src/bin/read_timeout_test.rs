@@ -0,0 +1,50 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicBool, Ordering};
+use user_lib::*;
+
+static WOKEN: AtomicBool = AtomicBool::new(false);
+
+fn func() {
+    WOKEN.store(true, Ordering::SeqCst);
+    sigreturn();
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut buf = [0u8; 16];
+    // no input arrives: the read gives up after the timeout with zero bytes
+    let start = get_time();
+    assert_eq!(read_timeout(0, &mut buf, 50), 0);
+    let elapsed = get_time() - start;
+    assert!(elapsed >= 50 && elapsed < 1000);
+    assert_eq!(read_timeout(42, &mut buf, 50), -1);
+    assert_eq!(read_timeout(1, &mut buf, 50), -1);
+
+    // a signal interrupts the wait long before the timeout
+    let pid = fork();
+    if pid == 0 {
+        let mut new = SignalAction::default();
+        let mut old = SignalAction::default();
+        new.handler = func as usize;
+        if sigaction(SIGUSR1, Some(&new), Some(&mut old)) < 0 {
+            panic!("Sigaction failed!");
+        }
+        let start = get_time();
+        assert_eq!(read_timeout(0, &mut buf, 5000), -EINTR);
+        assert!(get_time() - start < 5000);
+        assert!(WOKEN.load(Ordering::SeqCst));
+        exit(0);
+    }
+    sleep(100);
+    assert_eq!(kill(pid as usize, SIGUSR1), 0);
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    println!("read_timeout_test passed!");
+    0
+}
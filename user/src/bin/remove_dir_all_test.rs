@@ -0,0 +1,52 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, mkdir, mkdir_p, open, opendir, remove_dir_all, rmdir, statfs, unlink, write, FsStat,
+    OpenFlags, EISDIR, ENOENT, ENOTDIR, ENOTEMPTY,
+};
+
+const TREE: &str = "/rm_tree\0";
+
+fn create_file(path: &str, blocks: usize) {
+    let fd = open(path, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    let block = [0x5au8; 512];
+    for _ in 0..blocks {
+        assert_eq!(write(fd as usize, &block), 512);
+    }
+    close(fd as usize);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // 上一次运行中途退出时可能留下了这棵目录树
+    remove_dir_all(TREE);
+    let mut before = FsStat::default();
+    assert_eq!(statfs("/\0", &mut before), 0);
+
+    assert_eq!(mkdir_p("/rm_tree/sub/deeper\0", 0o755), 0);
+    assert_eq!(mkdir("/rm_tree/empty\0", 0o755), 0);
+    create_file("/rm_tree/f1\0", 3);
+    create_file("/rm_tree/sub/f2\0", 1);
+    create_file("/rm_tree/sub/deeper/f3\0", 20);
+    assert_eq!(rmdir(TREE), -ENOTEMPTY);
+    assert_eq!(rmdir("/rm_tree/f1\0"), -ENOTDIR);
+    assert_eq!(unlink("/rm_tree/sub\0"), -EISDIR);
+    assert_eq!(unlink("/rm_tree/missing\0"), -ENOENT);
+    assert_eq!(remove_dir_all("/rm_tree/f1\0"), -ENOTDIR);
+
+    assert_eq!(remove_dir_all(TREE), 0);
+    assert!(opendir(TREE).is_none());
+    assert_eq!(remove_dir_all(TREE), -ENOENT);
+    // 所有数据块和索引节点都被回收，根目录也缩回了原来的大小
+    let mut after = FsStat::default();
+    assert_eq!(statfs("/\0", &mut after), 0);
+    assert_eq!(after.free_blocks, before.free_blocks);
+    assert_eq!(after.free_inodes, before.free_inodes);
+    println!("remove_dir_all_test passed!");
+    0
+}
@@ -0,0 +1,52 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, open, read, rename, write, OpenFlags};
+
+const TARGET: &str = "rename_target\0";
+const TMP: &str = "rename_tmp\0";
+
+fn write_file(path: &str, content: &[u8]) {
+    let fd = open(path, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    assert_eq!(write(fd as usize, content), content.len() as isize);
+    close(fd as usize);
+}
+
+// 从文件描述符的当前位置读出全部内容，检查与 expected 一致
+fn check_fd(fd: usize, expected: &[u8]) {
+    let mut buffer = [0u8; 64];
+    let len = read(fd, &mut buffer);
+    assert_eq!(&buffer[..len as usize], expected);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    write_file(TARGET, b"old content");
+    let old_fd = open(TARGET, OpenFlags::RDONLY);
+    assert!(old_fd > 0);
+    let old_fd = old_fd as usize;
+
+    // 先写入临时文件，再把它重命名覆盖目标文件
+    write_file(TMP, b"new content");
+    assert_eq!(rename(TMP, TARGET), 0);
+    assert_eq!(open(TMP, OpenFlags::RDONLY), -1);
+    let new_fd = open(TARGET, OpenFlags::RDONLY);
+    assert!(new_fd > 0);
+    check_fd(new_fd as usize, b"new content");
+    close(new_fd as usize);
+    // 已经打开的文件描述符仍然读到被替换之前的内容
+    check_fd(old_fd, b"old content");
+    close(old_fd);
+
+    assert_eq!(rename("rename_missing\0", TARGET), -1);
+    assert_eq!(rename(TARGET, "/\0"), -1);
+    assert_eq!(rename("/\0", TMP), -1);
+    assert_eq!(rename(TARGET, TMP), 0);
+    assert_eq!(open(TARGET, OpenFlags::RDONLY), -1);
+    println!("rename_test passed!");
+    0
+}
@@ -0,0 +1,69 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    chdir, close, exit, fork, mkdir, open, opendir, pipe, read, remove_dir_all, rmdir, waitpid,
+    write, OpenFlags, EBUSY, ENOTEMPTY,
+};
+
+const DIR: &str = "/rmdir_dir\0";
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // 上一次运行中途退出时可能留下了这个目录
+    remove_dir_all(DIR);
+
+    // 空目录可以直接删除
+    assert_eq!(mkdir(DIR, 0o755), 0);
+    assert_eq!(rmdir(DIR), 0);
+    assert!(opendir(DIR).is_none());
+
+    // 非空目录不能删除，删掉其中的文件之后才可以
+    assert_eq!(mkdir(DIR, 0o755), 0);
+    let fd = open("/rmdir_dir/file\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    close(fd as usize);
+    assert_eq!(rmdir(DIR), -ENOTEMPTY);
+    assert!(opendir(DIR).is_some());
+    assert_eq!(remove_dir_all(DIR), 0);
+
+    // 根目录和自己的工作目录都不能删除
+    assert_eq!(rmdir("/\0"), -EBUSY);
+    assert_eq!(mkdir(DIR, 0o755), 0);
+    assert_eq!(chdir(DIR), 0);
+    assert_eq!(rmdir(DIR), -EBUSY);
+    assert_eq!(chdir("/\0"), 0);
+
+    // 另一个进程的工作目录也不能删除，这个进程退出之后才可以
+    let mut ready = [0usize; 2];
+    let mut done = [0usize; 2];
+    assert_eq!(pipe(&mut ready), 0);
+    assert_eq!(pipe(&mut done), 0);
+    let pid = fork();
+    if pid == 0 {
+        close(ready[0]);
+        close(done[1]);
+        assert_eq!(chdir(DIR), 0);
+        assert_eq!(write(ready[1], b"x"), 1);
+        // 等待父进程检查完毕
+        let mut buf = [0u8; 1];
+        read(done[0], &mut buf);
+        exit(0);
+    }
+    close(ready[1]);
+    close(done[0]);
+    let mut buf = [0u8; 1];
+    assert_eq!(read(ready[0], &mut buf), 1);
+    assert_eq!(rmdir(DIR), -EBUSY);
+    close(done[1]);
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    close(ready[0]);
+    assert_eq!(rmdir(DIR), 0);
+    println!("rmdir_test passed!");
+    0
+}
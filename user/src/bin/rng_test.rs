@@ -0,0 +1,44 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::Rng;
+
+const SEED: u64 = 42;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // 固定种子产生的序列不能随实现的改动而变化，否则依赖它复现的测试就失去了意义
+    let mut rng = Rng::new(SEED);
+    let expected = [
+        0x31b0_ece7_c4f6_97a2,
+        0x9008_a3b1_cb68_6f03,
+        0x7c71_73ab_d97b_e16f,
+        0x4567_2c8c_8d6b_8c4f,
+    ];
+    for value in expected {
+        assert_eq!(rng.next_u64(), value);
+    }
+    let mut rng = Rng::new(SEED);
+    let dice = [5, 2, 6, 2, 3, 4, 4, 6, 2, 4];
+    for value in dice {
+        assert_eq!(rng.next_range(1, 7), value);
+    }
+    // 种子为 0 时状态也不能为 0 ，否则 xorshift 只会产生 0
+    assert_ne!(Rng::new(0).next_u64(), 0);
+    assert_ne!(Rng::new(SEED).next_u64(), Rng::new(SEED + 1).next_u64());
+
+    let mut rng = Rng::from_time();
+    let mut counts = [0usize; 6];
+    for _ in 0..6000 {
+        let value = rng.next_range(10, 16);
+        assert!((10..16).contains(&value));
+        counts[(value - 10) as usize] += 1;
+    }
+    assert!(counts.iter().all(|&count| count > 800 && count < 1200));
+    assert_eq!(rng.next_range(u64::MAX - 1, u64::MAX), u64::MAX - 1);
+    println!("rng_test passed!");
+    0
+}
@@ -0,0 +1,44 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, get_robust_list, set_robust_list, waitpid, RobustListHead};
+
+fn robust_list_of(pid: usize) -> Option<usize> {
+    let mut head = usize::MAX;
+    let mut len = 0;
+    if get_robust_list(pid, &mut head, &mut len) != 0 {
+        return None;
+    }
+    assert_eq!(len, core::mem::size_of::<RobustListHead>());
+    Some(head)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // 没有登记时链表头为 0
+    assert_eq!(robust_list_of(0), Some(0));
+    // 内核还没有 futex ，登记总是失败，链表头仍然为 0
+    let mut head = RobustListHead::default();
+    head.list = &head as *const RobustListHead as usize;
+    assert_eq!(set_robust_list(&head), -1);
+    assert_eq!(robust_list_of(0), Some(0));
+
+    // fork 出的子进程同样没有登记，也无法登记
+    let pid = fork();
+    if pid == 0 {
+        if robust_list_of(0) != Some(0) {
+            exit(1);
+        }
+        exit(if set_robust_list(&head) == -1 { 0 } else { 2 });
+    }
+    let mut exit_code: i32 = -1;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    // 子进程被回收之后就查询不到了
+    assert_eq!(robust_list_of(pid as usize), None);
+    println!("robust_list_test passed!");
+    0
+}
@@ -4,18 +4,13 @@
 #[macro_use]
 extern crate user_lib;
 
-use user_lib::{exec, fork, wait};
+use user_lib::spawn;
 
 #[no_mangle]
 pub fn main() -> i32 {
     for i in 0..5 {
-        if fork() == 0 {
-            exec("pipe_large_test\0", &[core::ptr::null::<u8>()]);
-        } else {
-            let mut _unused: i32 = 0;
-            wait(&mut _unused);
-            println!("Iter {} OK.", i);
-        }
+        assert_eq!(spawn("pipe_large_test\0", &[core::ptr::null::<u8>()]), 0);
+        println!("Iter {} OK.", i);
     }
     0
 }
@@ -0,0 +1,49 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    exit, fork, get_time, getrusage, waitpid, yield_, Rusage, RUSAGE_CHILDREN, RUSAGE_SELF,
+};
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut before = Rusage::default();
+    assert_eq!(getrusage(RUSAGE_SELF, &mut before), 0);
+    assert!(before.maxrss > 0);
+    // voluntary context switches
+    for _ in 0..10 {
+        yield_();
+    }
+    // involuntary context switches: spin across several timer ticks
+    let start = get_time();
+    while get_time() < start + 50 {
+        core::hint::spin_loop();
+    }
+    let mut after = Rusage::default();
+    assert_eq!(getrusage(RUSAGE_SELF, &mut after), 0);
+    assert!(after.nvcsw >= before.nvcsw + 10);
+    assert!(after.nivcsw > before.nivcsw);
+
+    // page faults of a reaped child are accounted to RUSAGE_CHILDREN
+    let mut children = Rusage::default();
+    assert_eq!(getrusage(RUSAGE_CHILDREN, &mut children), 0);
+    let pid = fork();
+    if pid == 0 {
+        unsafe {
+            core::ptr::null_mut::<u8>().write_volatile(0);
+        }
+        exit(0);
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, -11);
+    let mut children_after = Rusage::default();
+    assert_eq!(getrusage(RUSAGE_CHILDREN, &mut children_after), 0);
+    assert!(children_after.minflt > children.minflt);
+    assert_eq!(getrusage(1, &mut children_after), -1);
+    println!("rusage_test passed!");
+    0
+}
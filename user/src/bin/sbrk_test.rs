@@ -0,0 +1,58 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, sbrk, waitpid};
+
+const PAGE_SIZE: usize = 4096;
+
+// 在子进程中访问 addr ，返回子进程的退出码
+fn touch_in_child(addr: usize) -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        unsafe {
+            (addr as *mut u8).write_volatile(1);
+        }
+        exit(0);
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    exit_code
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let bottom = sbrk(0);
+    assert!(bottom > 0);
+    let bottom = bottom as usize;
+    assert_eq!(bottom % PAGE_SIZE, 0);
+
+    // grow
+    assert_eq!(sbrk(4 * PAGE_SIZE as i32), bottom as isize);
+    assert_eq!(sbrk(0), (bottom + 4 * PAGE_SIZE) as isize);
+    let heap = unsafe { core::slice::from_raw_parts_mut(bottom as *mut u8, 4 * PAGE_SIZE) };
+    for (i, byte) in heap.iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+
+    // shrink, the remaining part keeps its contents
+    assert_eq!(sbrk(-2 * PAGE_SIZE as i32), (bottom + 4 * PAGE_SIZE) as isize);
+    assert_eq!(sbrk(0), (bottom + 2 * PAGE_SIZE) as isize);
+    for (i, &byte) in heap[..2 * PAGE_SIZE].iter().enumerate() {
+        assert_eq!(byte, i as u8);
+    }
+    assert_eq!(touch_in_child(bottom + PAGE_SIZE), 0);
+    assert_eq!(touch_in_child(bottom + 2 * PAGE_SIZE), -11);
+
+    // the break cannot go below the heap bottom
+    assert_eq!(sbrk(-2 * PAGE_SIZE as i32 - 1), -1);
+    assert_eq!(sbrk(-2 * PAGE_SIZE as i32), (bottom + 2 * PAGE_SIZE) as isize);
+    assert_eq!(sbrk(0), bottom as isize);
+    assert_eq!(touch_in_child(bottom), -11);
+    // a guard page separates the heap from the user stack
+    assert_eq!(touch_in_child(bottom - 1), -11);
+    println!("sbrk_test passed!");
+    0
+}
@@ -0,0 +1,62 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    exit, fork, getpid, open, prctl, seccomp, waitpid, OpenFlags, SyscallFilter,
+    PR_GET_NO_NEW_PRIVS, PR_SET_NO_NEW_PRIVS, SYSCALL_OPEN,
+};
+
+const FILE: &str = "seccomp_file\0";
+const SIGSYS_EXIT: i32 = -31;
+
+fn wait_child(pid: isize) -> i32 {
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    exit_code
+}
+
+// 在子进程中调用 f ，返回子进程的退出码
+fn in_child(f: fn() -> i32) -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        exit(f());
+    }
+    wait_child(pid)
+}
+
+fn try_open() -> i32 {
+    open(FILE, OpenFlags::CREATE | OpenFlags::WRONLY);
+    0
+}
+
+fn sandbox() -> i32 {
+    let mut filter = SyscallFilter::allow_all();
+    filter.deny(SYSCALL_OPEN);
+    // 没有设置 no_new_privs 时不能使用 seccomp
+    assert_eq!(seccomp(&filter), -1);
+    assert_eq!(prctl(PR_GET_NO_NEW_PRIVS, 0), 0);
+    assert_eq!(prctl(PR_SET_NO_NEW_PRIVS, 0), -1);
+    assert_eq!(prctl(PR_SET_NO_NEW_PRIVS, 1), 0);
+    assert_eq!(prctl(PR_GET_NO_NEW_PRIVS, 0), 1);
+    assert_eq!(seccomp(&filter), 0);
+    // 其他系统调用不受影响，过滤器被子进程继承
+    assert!(getpid() > 0);
+    assert_eq!(in_child(try_open), SIGSYS_EXIT);
+    // 过滤器不能放宽，允许所有系统调用之后 open 仍然被禁止
+    assert_eq!(seccomp(&SyscallFilter::allow_all()), 0);
+    try_open();
+    println!("open should have been forbidden");
+    1
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // 没有沙箱的进程可以正常打开文件
+    assert_eq!(in_child(try_open), 0);
+    assert_eq!(in_child(sandbox), SIGSYS_EXIT);
+    println!("seccomp_test passed!");
+    0
+}
@@ -0,0 +1,61 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    exit, fork, getpgid, getpid, ioctl, setpgid, setsid, sleep, tcgetpgrp, tcsetpgrp, waitpid,
+    yield_, TIOCSTI,
+};
+
+const CTRL_C: u8 = 0x03;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // setsid in a child which is not a group leader
+    let pid = fork();
+    if pid == 0 {
+        let pid = getpid();
+        assert_eq!(setsid(), pid);
+        assert_eq!(getpgid(0), pid);
+        // already a group leader
+        assert_eq!(setsid(), -1);
+        exit(0);
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+
+    // keyboard generated signals only go to the foreground process group
+    let old_foreground = tcgetpgrp(0);
+    assert!(old_foreground >= 0);
+    let foreground = fork();
+    if foreground == 0 {
+        setpgid(0, 0);
+        loop {
+            yield_();
+        }
+    }
+    let background = fork();
+    if background == 0 {
+        setpgid(0, 0);
+        sleep(300);
+        exit(0);
+    }
+    setpgid(foreground as usize, 0);
+    setpgid(background as usize, 0);
+    assert_eq!(getpgid(foreground as usize), foreground);
+    assert_eq!(getpgid(background as usize), background);
+    assert_eq!(tcsetpgrp(0, foreground as usize), 0);
+    assert_eq!(tcgetpgrp(0), foreground);
+    // type Ctrl-C
+    assert_eq!(ioctl(0, TIOCSTI, &CTRL_C as *const u8 as usize), 0);
+    assert_eq!(waitpid(foreground as usize, &mut exit_code), foreground);
+    assert_eq!(exit_code, -2);
+    assert_eq!(tcsetpgrp(0, old_foreground as usize), 0);
+    assert_eq!(waitpid(background as usize, &mut exit_code), background);
+    assert_eq!(exit_code, 0);
+    println!("session_test passed!");
+    0
+}
@@ -0,0 +1,58 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::*;
+
+const FILE: &str = "shared_fd_file\0";
+const LEN: usize = 200;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut content = [0u8; LEN];
+    for (i, byte) in content.iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+    let fd = open(FILE, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    assert_eq!(write(fd as usize, &content), LEN as isize);
+    close(fd as usize);
+
+    // parent and child read from the same fd and share its offset
+    let fd = open(FILE, OpenFlags::RDONLY) as usize;
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    let pid = fork();
+    if pid == 0 {
+        close(pipe_fd[0]);
+        let mut byte = [0u8; 1];
+        while read(fd, &mut byte) == 1 {
+            write(pipe_fd[1], &byte);
+            yield_();
+        }
+        exit(0);
+    }
+    close(pipe_fd[1]);
+    let mut seen = [false; LEN];
+    let mut byte = [0u8; 1];
+    while read(fd, &mut byte) == 1 {
+        assert!(!seen[byte[0] as usize]);
+        seen[byte[0] as usize] = true;
+        yield_();
+    }
+    // every byte is read exactly once by one of the processes
+    while read(pipe_fd[0], &mut byte) == 1 {
+        assert!(!seen[byte[0] as usize]);
+        seen[byte[0] as usize] = true;
+    }
+    assert!(seen.iter().all(|s| *s));
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    close(pipe_fd[0]);
+    close(fd);
+    println!("shared_fd_test passed!");
+    0
+}
@@ -0,0 +1,79 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+#[macro_use]
+extern crate user_lib;
+
+use alloc::string::String;
+use user_lib::{close, dup, exec, fork, pipe, read, waitpid};
+
+// 文件系统中目前只有根目录，cd 的结果只能通过它报告的错误以及之后的 pwd 观察。
+// exit 之后的命令不应被执行
+const SCRIPT: [&str; 9] = [
+    "cd no_such_dir\0",
+    "pwd\0",
+    "cd\0",
+    "pwd\0",
+    "cd /.\0",
+    "pwd\0",
+    "exit seven\0",
+    "exit 7\0",
+    "hello_world\0",
+];
+
+// 这些内容应当按顺序出现在 shell 的输出中
+const EXPECTED: [&str; 5] = [
+    "cd: no_such_dir: No such directory\n",
+    "/\n",
+    "/\n",
+    "/\n",
+    "exit: numeric argument required\n",
+];
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    let pid = fork();
+    if pid == 0 {
+        // shell 的标准输出重定向到管道的写端
+        close(1);
+        assert_eq!(dup(pipe_fd[1]), 1);
+        close(pipe_fd[0]);
+        close(pipe_fd[1]);
+        let mut args = [core::ptr::null::<u8>(); SCRIPT.len() + 2];
+        args[0] = "user_shell\0".as_ptr();
+        for (arg, line) in args[1..].iter_mut().zip(SCRIPT.iter()) {
+            *arg = line.as_ptr();
+        }
+        exec("user_shell\0", &args);
+        panic!("exec user_shell failed");
+    }
+    close(pipe_fd[1]);
+    let mut output = String::new();
+    let mut buf = [0u8; 256];
+    loop {
+        let len = read(pipe_fd[0], &mut buf);
+        if len <= 0 {
+            break;
+        }
+        output.push_str(core::str::from_utf8(&buf[..len as usize]).unwrap());
+    }
+    close(pipe_fd[0]);
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 7);
+
+    let mut rest = output.as_str();
+    for expected in EXPECTED.iter() {
+        let Some(index) = rest.find(expected) else {
+            panic!("{:?} not found in shell output:\n{}", expected, output);
+        };
+        rest = &rest[index + expected.len()..];
+    }
+    assert!(rest.is_empty(), "unexpected shell output: {:?}", rest);
+    println!("shell_builtins_test passed!");
+    0
+}
@@ -0,0 +1,104 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+#[macro_use]
+extern crate user_lib;
+
+use alloc::string::String;
+use user_lib::{close, dup, exec, fork, open, pipe, read, waitpid, write, OpenFlags};
+
+const TAB: &str = "\t";
+const BACKSPACE: &str = "\x08";
+const BEL: char = '\x07';
+
+// 两个文件名有共同的前缀 tab_candidate_ ，tab_unique 开头的只有一个
+const FILES: [(&str, &str); 3] = [
+    ("tab_candidate_1\0", "first candidate\n"),
+    ("tab_candidate_2\0", "second candidate\n"),
+    ("tab_unique_file\0", "unique file\n"),
+];
+
+#[no_mangle]
+pub fn main() -> i32 {
+    for (name, content) in FILES {
+        let fd = open(
+            name,
+            OpenFlags::CREATE | OpenFlags::WRONLY | OpenFlags::TRUNC,
+        );
+        assert!(fd > 0);
+        assert_eq!(
+            write(fd as usize, content.as_bytes()),
+            content.len() as isize
+        );
+        close(fd as usize);
+    }
+    let mut input = [0usize; 2];
+    let mut output = [0usize; 2];
+    assert_eq!(pipe(&mut input), 0);
+    assert_eq!(pipe(&mut output), 0);
+    let pid = fork();
+    if pid == 0 {
+        // shell 从管道中读取按键，输出也写入管道
+        close(0);
+        assert_eq!(dup(input[0]), 0);
+        close(1);
+        assert_eq!(dup(output[1]), 1);
+        for fd in [input[0], input[1], output[0], output[1]] {
+            close(fd);
+        }
+        exec(
+            "user_shell\0",
+            &["user_shell\0".as_ptr(), core::ptr::null()],
+        );
+        panic!("exec user_shell failed");
+    }
+    close(input[0]);
+    close(output[1]);
+    let mut keys = String::new();
+    // 命令名在 PATH 中补全，参数在当前目录中补全
+    for key in ["cat tab_u", TAB, "\n", "hello_wo", TAB, "\n"] {
+        keys.push_str(key);
+    }
+    // 有多个候选时补全到共同的前缀并列出候选
+    for key in ["cat tab_cand", TAB, "2\n"] {
+        keys.push_str(key);
+    }
+    // 没有候选时只响铃而不修改当前行
+    for key in ["exit 3q", TAB, BACKSPACE, "\n"] {
+        keys.push_str(key);
+    }
+    assert_eq!(write(input[1], keys.as_bytes()), keys.len() as isize);
+    close(input[1]);
+
+    let mut shell_output = String::new();
+    let mut buf = [0u8; 256];
+    loop {
+        let len = read(output[0], &mut buf);
+        if len <= 0 {
+            break;
+        }
+        shell_output.push_str(core::str::from_utf8(&buf[..len as usize]).unwrap());
+    }
+    close(output[0]);
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 3, "shell output:\n{}", shell_output);
+    for expected in [
+        "unique file\n",
+        "Hello world from user mode program!\n",
+        "tab_candidate_1  tab_candidate_2\n",
+        "second candidate\n",
+    ] {
+        assert!(
+            shell_output.contains(expected),
+            "{:?} not found in shell output:\n{}",
+            expected,
+            shell_output
+        );
+    }
+    assert_eq!(shell_output.matches(BEL).count(), 1);
+    println!("shell_complete_test passed!");
+    0
+}
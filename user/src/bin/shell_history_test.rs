@@ -0,0 +1,74 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+#[macro_use]
+extern crate user_lib;
+
+use alloc::string::String;
+use user_lib::{close, dup, exec, fork, pipe, read, waitpid, write};
+
+const UP: &str = "\x1b[A";
+const DOWN: &str = "\x1b[B";
+const BACKSPACE: &str = "\x08";
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut input = [0usize; 2];
+    let mut output = [0usize; 2];
+    assert_eq!(pipe(&mut input), 0);
+    assert_eq!(pipe(&mut output), 0);
+    let pid = fork();
+    if pid == 0 {
+        // shell 从管道中读取按键，输出也写入管道
+        close(0);
+        assert_eq!(dup(input[0]), 0);
+        close(1);
+        assert_eq!(dup(output[1]), 1);
+        for fd in [input[0], input[1], output[0], output[1]] {
+            close(fd);
+        }
+        exec(
+            "user_shell\0",
+            &["user_shell\0".as_ptr(), core::ptr::null()],
+        );
+        panic!("exec user_shell failed");
+    }
+    close(input[0]);
+    close(output[1]);
+    let mut keys = String::new();
+    keys.push_str("pwd\n");
+    keys.push_str("exit x\n");
+    // 越过最早的一条时停留在 pwd ，向下越过最新的一条时回到正在输入的空行，之后再向上两次取出 pwd 执行
+    for key in [UP, UP, UP, DOWN, DOWN, DOWN, UP, UP] {
+        keys.push_str(key);
+    }
+    keys.push('\n');
+    // 取出 exit x 并把它编辑为 exit 7
+    keys.push_str(UP);
+    keys.push_str(UP);
+    keys.push_str(BACKSPACE);
+    keys.push_str("7\n");
+    assert_eq!(write(input[1], keys.as_bytes()), keys.len() as isize);
+    close(input[1]);
+
+    let mut shell_output = String::new();
+    let mut buf = [0u8; 256];
+    loop {
+        let len = read(output[0], &mut buf);
+        if len <= 0 {
+            break;
+        }
+        shell_output.push_str(core::str::from_utf8(&buf[..len as usize]).unwrap());
+    }
+    close(output[0]);
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 7, "shell output:\n{}", shell_output);
+    // 第一次 pwd 和从历史记录中取出的 pwd 各输出一次
+    assert_eq!(shell_output.matches("\n/\n").count(), 2);
+    assert_eq!(shell_output.matches("numeric argument required").count(), 1);
+    println!("shell_history_test passed!");
+    0
+}
@@ -0,0 +1,77 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+#[macro_use]
+extern crate user_lib;
+
+use alloc::string::String;
+use user_lib::{close, dup, exec, fork, pipe, read, waitpid};
+
+// 依次交给 shell 执行的命令：hello_world 在前台的 sleep_simple 运行期间结束，在下一条命令之前被报告
+const SCRIPT: [&str; 7] = [
+    "hello_world &\0",
+    "sleep_simple\0",
+    "sleep_simple &\0",
+    "jobs\0",
+    "fg 1\0",
+    "jobs\0",
+    "fg 1\0",
+];
+
+// 这些内容应当按顺序出现在 shell 的输出中
+const EXPECTED: [&str; 5] = [
+    "Hello world from user mode program!",
+    "[1] Done  hello_world",
+    "[1] Running  sleep_simple",
+    "r_sleep passed!",
+    "fg: no such job",
+];
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    let pid = fork();
+    if pid == 0 {
+        // shell 的标准输出重定向到管道的写端
+        close(1);
+        assert_eq!(dup(pipe_fd[1]), 1);
+        close(pipe_fd[0]);
+        close(pipe_fd[1]);
+        let mut args = [core::ptr::null::<u8>(); SCRIPT.len() + 2];
+        args[0] = "user_shell\0".as_ptr();
+        for (arg, line) in args[1..].iter_mut().zip(SCRIPT.iter()) {
+            *arg = line.as_ptr();
+        }
+        exec("user_shell\0", &args);
+        panic!("exec user_shell failed");
+    }
+    close(pipe_fd[1]);
+    let mut output = String::new();
+    let mut buf = [0u8; 256];
+    loop {
+        let len = read(pipe_fd[0], &mut buf);
+        if len <= 0 {
+            break;
+        }
+        output.push_str(core::str::from_utf8(&buf[..len as usize]).unwrap());
+    }
+    close(pipe_fd[0]);
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+
+    let mut rest = output.as_str();
+    for expected in EXPECTED.iter() {
+        let Some(index) = rest.find(expected) else {
+            panic!("{:?} not found in shell output:\n{}", expected, output);
+        };
+        rest = &rest[index + expected.len()..];
+    }
+    // fg 1 之后后台作业已经结束，第二次 jobs 不输出任何内容
+    assert_eq!(output.matches("Running").count(), 1);
+    println!("shell_jobs_test passed!");
+    0
+}
@@ -0,0 +1,90 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::vec::Vec;
+use core::ptr::null_mut;
+use core::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+use user_lib::*;
+
+const SIGNALS: usize = 50;
+const WORDS: usize = 8;
+
+static HANDLED: AtomicUsize = AtomicUsize::new(0);
+// 主流程分配、由处理例程释放的内存，以及处理例程分配、由主流程释放的内存
+static TO_HANDLER: AtomicPtr<[usize; WORDS]> = AtomicPtr::new(null_mut());
+static FROM_HANDLER: AtomicPtr<[usize; WORDS]> = AtomicPtr::new(null_mut());
+
+// 信号可能恰好在主流程位于分配器中时到来，处理例程中的分配和释放不能因此卡住
+fn func() {
+    let n = HANDLED.load(Ordering::SeqCst);
+    let squares: Vec<usize> = (0..32).map(|i| i * i).collect();
+    assert_eq!(squares.iter().sum::<usize>(), 10416);
+    let message = format!("signal {}", n);
+    assert!(message.ends_with(&format!("{}", n)));
+    let from_main = TO_HANDLER.swap(null_mut(), Ordering::SeqCst);
+    if !from_main.is_null() {
+        let from_main = unsafe { Box::from_raw(from_main) };
+        assert!(from_main.iter().all(|&word| word == from_main[0]));
+    }
+    let old = FROM_HANDLER.swap(Box::into_raw(Box::new([n; WORDS])), Ordering::SeqCst);
+    if !old.is_null() {
+        drop(unsafe { Box::from_raw(old) });
+    }
+    HANDLED.fetch_add(1, Ordering::SeqCst);
+    sigreturn();
+}
+
+// 主流程不停地分配和释放不同大小的内存
+fn alloc_round(round: usize) {
+    let mut numbers = Vec::new();
+    for i in 0..(round % 64 + 1) {
+        numbers.push(round + i);
+    }
+    assert!(numbers.iter().enumerate().all(|(i, &n)| n == round + i));
+    let old = TO_HANDLER.swap(Box::into_raw(Box::new([round; WORDS])), Ordering::SeqCst);
+    if !old.is_null() {
+        drop(unsafe { Box::from_raw(old) });
+    }
+    let from_handler = FROM_HANDLER.swap(null_mut(), Ordering::SeqCst);
+    if !from_handler.is_null() {
+        let from_handler = unsafe { Box::from_raw(from_handler) };
+        assert!(from_handler.iter().all(|&word| word == from_handler[0]));
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        let mut new = SignalAction::default();
+        let mut old = SignalAction::default();
+        new.handler = func as usize;
+        if sigaction(SIGUSR1, Some(&new), Some(&mut old)) < 0 {
+            panic!("Sigaction failed!");
+        }
+        let mut round = 0;
+        while HANDLED.load(Ordering::SeqCst) < SIGNALS {
+            alloc_round(round);
+            round += 1;
+        }
+        exit(0);
+    }
+    let mut exit_code: i32 = 0;
+    // a signal may still be pending when the next one is sent, in which case kill fails and we retry
+    loop {
+        kill(pid as usize, SIGUSR1);
+        yield_();
+        if waitpid_nb(pid as usize, &mut exit_code) == pid {
+            break;
+        }
+    }
+    assert_eq!(exit_code, 0);
+    println!("signal_alloc_test passed!");
+    0
+}
@@ -0,0 +1,75 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::*;
+
+const FILE: &str = "signal_io_file\0";
+const SIGNALS: usize = 50;
+
+static HANDLED: AtomicUsize = AtomicUsize::new(0);
+
+fn func() {
+    HANDLED.fetch_add(1, Ordering::SeqCst);
+    sigreturn();
+}
+
+// 一轮文件、管道以及其他会访问进程控制块的系统调用
+fn io_round(round: usize) {
+    let data = [round as u8; 100];
+    let fd = open(FILE, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    assert_eq!(write(fd as usize, &data), 100);
+    close(fd as usize);
+    let fd = open(FILE, OpenFlags::RDONLY);
+    assert!(fd > 0);
+    let mut buffer = [0u8; 100];
+    assert_eq!(read(fd as usize, &mut buffer), 100);
+    assert_eq!(buffer, data);
+    close(fd as usize);
+
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    assert_eq!(write(pipe_fd[1], &data[..10]), 10);
+    assert_eq!(read(pipe_fd[0], &mut buffer[..10]), 10);
+    close(pipe_fd[0]);
+    close(pipe_fd[1]);
+
+    let mut usage = Rusage::default();
+    assert_eq!(getrusage(RUSAGE_SELF, &mut usage), 0);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        let mut new = SignalAction::default();
+        let mut old = SignalAction::default();
+        new.handler = func as usize;
+        if sigaction(SIGUSR1, Some(&new), Some(&mut old)) < 0 {
+            panic!("Sigaction failed!");
+        }
+        let mut round = 0;
+        // keep doing I/O while the parent keeps sending signals
+        while HANDLED.load(Ordering::SeqCst) < SIGNALS {
+            io_round(round);
+            round += 1;
+        }
+        exit(0);
+    }
+    let mut exit_code: i32 = 0;
+    // a signal may still be pending when the next one is sent, in which case kill fails and we retry
+    loop {
+        kill(pid as usize, SIGUSR1);
+        yield_();
+        if waitpid_nb(pid as usize, &mut exit_code) == pid {
+            break;
+        }
+    }
+    assert_eq!(exit_code, 0);
+    println!("signal_io_stress passed!");
+    0
+}
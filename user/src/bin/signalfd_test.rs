@@ -0,0 +1,77 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, exit, fork, getpid, kill, poll, read_signalfd, signalfd, sleep, waitpid, PollFd,
+    SignalFlags, SignalfdSiginfo, POLLIN, SIGINT, SIGUSR1, SIGUSR2,
+};
+
+fn poll_readable(fd: usize, timeout_ms: isize) -> bool {
+    let mut fds = [PollFd {
+        fd: fd as i32,
+        events: POLLIN,
+        revents: 0,
+    }];
+    poll(&mut fds, timeout_ms) == 1 && fds[0].revents == POLLIN
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let pid = getpid() as usize;
+    let fd = signalfd(SignalFlags::SIGUSR1 | SignalFlags::SIGUSR2 | SignalFlags::SIGINT);
+    assert!(fd >= 0);
+    let fd = fd as usize;
+    let mut infos = [SignalfdSiginfo::default(); 4];
+
+    // 没有信号时不可读
+    assert!(!poll_readable(fd, 0));
+
+    // 发给自己的 SIGUSR1 没有调用处理例程，而是留在 signalfd 中等待读取
+    assert_eq!(kill(pid, SIGUSR1), 0);
+    assert!(poll_readable(fd, 0));
+    assert_eq!(read_signalfd(fd, &mut infos), 1);
+    assert_eq!(infos[0].signo, SIGUSR1 as u32);
+    assert!(!poll_readable(fd, 0));
+
+    // 读会阻塞到另一个进程发来信号为止
+    let child = fork();
+    if child == 0 {
+        sleep(50);
+        assert_eq!(kill(pid, SIGUSR1), 0);
+        exit(0);
+    }
+    assert_eq!(read_signalfd(fd, &mut infos), 1);
+    assert_eq!(infos[0].signo, SIGUSR1 as u32);
+    let mut exit_code = 0;
+    assert_eq!(waitpid(child as usize, &mut exit_code), child);
+
+    // 一次读取多个信号，按照信号编号排列；SIGINT 不会按照默认方式杀死进程
+    assert_eq!(kill(pid, SIGUSR2), 0);
+    assert_eq!(kill(pid, SIGINT), 0);
+    assert_eq!(kill(pid, SIGUSR1), 0);
+    assert_eq!(read_signalfd(fd, &mut infos), 3);
+    assert_eq!(infos[0].signo, SIGINT as u32);
+    assert_eq!(infos[1].signo, SIGUSR1 as u32);
+    assert_eq!(infos[2].signo, SIGUSR2 as u32);
+
+    // 关闭 signalfd 之后信号恢复通常的处理方式。子进程继承的 signalfd 同样有效，需要一并关闭
+    let child = fork();
+    if child == 0 {
+        close(fd);
+        let child_fd = signalfd(SignalFlags::SIGINT) as usize;
+        assert_eq!(kill(getpid() as usize, SIGINT), 0);
+        assert_eq!(read_signalfd(child_fd, &mut infos), 1);
+        close(child_fd);
+        kill(getpid() as usize, SIGINT);
+        exit(0);
+    }
+    assert_eq!(waitpid(child as usize, &mut exit_code), child);
+    assert_eq!(exit_code, -SIGINT);
+
+    close(fd);
+    println!("signalfd_test passed!");
+    0
+}
@@ -0,0 +1,32 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{spawn, spawn_nowait, waitpid, SPAWN_EXEC_FAILED};
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let args = [
+        "cmdline_args\0".as_ptr(),
+        "spawn\0".as_ptr(),
+        core::ptr::null::<u8>(),
+    ];
+    assert_eq!(spawn("cmdline_args\0", &args), 0);
+    // 被信号杀死的子进程的退出码与 waitpid 得到的相同
+    assert_eq!(spawn("priv_inst\0", &[core::ptr::null::<u8>()]), -4);
+    // exec 失败时只有子进程退出，调用者照常继续
+    assert_eq!(
+        spawn("no_such_app\0", &[core::ptr::null::<u8>()]),
+        SPAWN_EXEC_FAILED
+    );
+
+    let pid = spawn_nowait("priv_csr\0", &[core::ptr::null::<u8>()]);
+    assert!(pid > 0);
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, -4);
+    println!("spawn_test passed!");
+    0
+}
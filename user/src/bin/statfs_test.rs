@@ -0,0 +1,46 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, open, statfs, truncate, write, FsStat, OpenFlags};
+
+const FILE: &str = "statfs_file\0";
+
+fn stat() -> FsStat {
+    let mut buf = FsStat::default();
+    assert_eq!(statfs("/\0", &mut buf), 0);
+    buf
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let before = stat();
+    assert_eq!(before.block_size, 512);
+    assert!(before.free_blocks <= before.total_blocks);
+    assert!(before.free_inodes < before.total_inodes);
+
+    // 创建文件占用一个索引节点，写入 16 个数据块的内容占用 16 个数据块
+    let fd = open(FILE, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    let content = [b'z'; 16 * 512];
+    assert_eq!(write(fd, &content), content.len() as isize);
+    close(fd);
+    let after = stat();
+    assert_eq!(after.free_inodes, before.free_inodes - 1);
+    assert_eq!(after.free_blocks, before.free_blocks - 16);
+
+    // 截断之后数据块被回收
+    assert_eq!(truncate(FILE, 0), 0);
+    assert_eq!(stat().free_blocks, before.free_blocks);
+
+    // 任何存在的路径都可以，不存在的路径返回 -1
+    let mut buf = FsStat::default();
+    assert_eq!(statfs(FILE, &mut buf), 0);
+    assert_eq!(buf.total_blocks, before.total_blocks);
+    assert_eq!(statfs("no_such_file\0", &mut buf), -1);
+    println!("statfs_test passed!");
+    0
+}
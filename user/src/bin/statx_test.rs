@@ -0,0 +1,56 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, fstat, ftruncate, open, statx, write, OpenFlags, Stat, Statx, STATX_BLOCKS, STATX_INO,
+    STATX_MODE, STATX_MTIME, STATX_SIZE, STATX_TYPE, S_IFREG,
+};
+
+const BLOCK_SIZE: u32 = 512;
+const SPARSE_SIZE: usize = 1 << 20;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let fd = open("statx_sparse\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    // 只写入开头的一块，之后扩大文件，扩大的部分是空洞
+    assert_eq!(write(fd, &[0x5a; BLOCK_SIZE as usize]), BLOCK_SIZE as isize);
+    assert_eq!(ftruncate(fd, SPARSE_SIZE), 0);
+
+    let mut st = Statx::default();
+    let all = STATX_TYPE | STATX_MODE | STATX_INO | STATX_SIZE | STATX_BLOCKS;
+    assert_eq!(statx(fd, all, &mut st), 0);
+    assert_eq!(st.mask, all);
+    assert_eq!(st.blksize, BLOCK_SIZE);
+    assert_eq!(st.size, SPARSE_SIZE as u64);
+    // 占用的块数远小于大小对应的块数
+    assert!(st.blocks >= 1);
+    assert!(
+        st.blocks < (SPARSE_SIZE / BLOCK_SIZE as usize) as u64 / 100,
+        "sparse file takes {} blocks",
+        st.blocks
+    );
+    let mut stat = Stat::default();
+    assert_eq!(fstat(fd, &mut stat), 0);
+    assert_eq!(st.ino, stat.ino as u64);
+    assert_eq!(st.mode, stat.mode);
+    assert_eq!(st.mode & S_IFREG, S_IFREG);
+
+    // 只填写请求了的字段，文件系统没有时间戳，请求了也不会填写
+    let mut st = Statx::default();
+    assert_eq!(statx(fd, STATX_SIZE | STATX_MTIME, &mut st), 0);
+    assert_eq!(st.mask, STATX_SIZE);
+    assert_eq!(st.size, SPARSE_SIZE as u64);
+    assert_eq!(st.blocks, 0);
+    assert_eq!(st.mode, 0);
+
+    close(fd);
+    // 不存在的 fd 返回 -1
+    assert_eq!(statx(fd, all, &mut st), -1);
+    println!("statx_test passed!");
+    0
+}
@@ -0,0 +1,55 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+#[macro_use]
+extern crate user_lib;
+
+use alloc::string::String;
+use user_lib::{close, dup2, exec, exit, fork, pipe, read, waitpid};
+
+fn read_all(fd: usize) -> String {
+    let mut output = String::new();
+    let mut buf = [0u8; 64];
+    loop {
+        let len = read(fd, &mut buf);
+        if len <= 0 {
+            break;
+        }
+        output.push_str(core::str::from_utf8(&buf[..len as usize]).unwrap());
+    }
+    close(fd);
+    output
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // 把 hello_stderr 的标准输出和标准错误分别重定向到两个管道
+    let mut out_fd = [0usize; 2];
+    let mut err_fd = [0usize; 2];
+    assert_eq!(pipe(&mut out_fd), 0);
+    assert_eq!(pipe(&mut err_fd), 0);
+    let pid = fork();
+    if pid == 0 {
+        assert_eq!(dup2(out_fd[1], 1), 1);
+        assert_eq!(dup2(err_fd[1], 2), 2);
+        for fd in [out_fd[0], out_fd[1], err_fd[0], err_fd[1]] {
+            close(fd);
+        }
+        exec("hello_stderr\0", &[core::ptr::null::<u8>()]);
+        exit(-1);
+    }
+    close(out_fd[1]);
+    close(err_fd[1]);
+    // 输出都很短，不会填满管道，依次读完两个管道不会死锁
+    let stdout = read_all(out_fd[0]);
+    let stderr = read_all(err_fd[0]);
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    assert_eq!(stdout, "Hello, stdout!\n");
+    assert_eq!(stderr, "Hello, stderr! 2\n");
+    println!("stderr_test passed!");
+    0
+}
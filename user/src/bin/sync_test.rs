@@ -0,0 +1,44 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, open, pipe, read, sync, syncfs, unlink, write, OpenFlags};
+
+const FILE: &str = "sync_file\0";
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let fd = open(FILE, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    let data = [0x5au8; 4096];
+    assert_eq!(write(fd, &data), data.len() as isize);
+    // 这里的 VirtIO 块设备无法 flush ：块照常写回，但无法保证它们到达了存储介质，
+    // 因此无论有没有需要写回的块，sync 和 syncfs 都报告失败
+    assert_eq!(sync(), -1);
+    assert_eq!(sync(), -1);
+    assert_eq!(write(fd, &data[..100]), 100);
+    assert_eq!(syncfs(fd), -1);
+    close(fd);
+
+    // 写回之后内容不变
+    let fd = open(FILE, OpenFlags::RDONLY);
+    assert!(fd > 0);
+    let mut buf = [0u8; 8192];
+    assert_eq!(read(fd as usize, &mut buf), 4196);
+    assert!(buf[..4196].iter().all(|byte| *byte == 0x5a));
+    close(fd as usize);
+    assert_eq!(unlink(FILE), 0);
+
+    // syncfs 需要文件系统中的文件
+    let mut fds = [0usize; 2];
+    assert_eq!(pipe(&mut fds), 0);
+    assert_eq!(syncfs(fds[0]), -1);
+    close(fds[0]);
+    close(fds[1]);
+    assert_eq!(syncfs(fds[0]), -1);
+    println!("sync_test passed!");
+    0
+}
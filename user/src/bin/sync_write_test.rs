@@ -0,0 +1,38 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, open, read, write, OpenFlags};
+
+const FILE: &str = "sync_write_file\0";
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let fd = open(
+        FILE,
+        OpenFlags::CREATE | OpenFlags::WRONLY | OpenFlags::SYNC,
+    );
+    assert!(fd > 0);
+    let fd = fd as usize;
+    // 每次写入都跨越块边界，写入返回时数据已经被同步到磁盘
+    let mut chunk = [0u8; 700];
+    for round in 0..20u8 {
+        chunk.iter_mut().for_each(|byte| *byte = b'a' + round);
+        assert_eq!(write(fd, &chunk), chunk.len() as isize);
+    }
+    close(fd);
+
+    let fd = open(FILE, OpenFlags::RDONLY);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    for round in 0..20u8 {
+        assert_eq!(read(fd, &mut chunk), chunk.len() as isize);
+        assert!(chunk.iter().all(|byte| *byte == b'a' + round));
+    }
+    assert_eq!(read(fd, &mut chunk), 0);
+    close(fd);
+    println!("sync_write_test passed!");
+    0
+}
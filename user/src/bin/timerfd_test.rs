@@ -0,0 +1,90 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, get_time, poll, read, sleep, timerfd_create, timerfd_settime, PollFd, POLLIN,
+};
+
+const PERIOD_MS: isize = 50;
+const ROUNDS: isize = 5;
+
+fn pollfd(fd: usize) -> PollFd {
+    PollFd {
+        fd: fd as i32,
+        events: POLLIN,
+        revents: 0,
+    }
+}
+
+fn read_expirations(fd: usize) -> u64 {
+    let mut buf = [0u8; 8];
+    assert_eq!(read(fd, &mut buf), 8);
+    u64::from_ne_bytes(buf)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let fd = timerfd_create();
+    assert!(fd >= 0);
+    let fd = fd as usize;
+
+    // 没有启动的定时器不可读
+    let mut fds = [pollfd(fd)];
+    assert_eq!(poll(&mut fds, 0), 0);
+
+    // 周期定时器：每隔 PERIOD_MS 变为可读一次，读出到期次数之后重新变为不可读
+    let start = get_time();
+    assert_eq!(
+        timerfd_settime(fd, PERIOD_MS as usize, PERIOD_MS as usize),
+        0
+    );
+    for round in 1..=ROUNDS {
+        let mut fds = [pollfd(fd)];
+        assert_eq!(poll(&mut fds, -1), 1);
+        assert_eq!(fds[0].revents, POLLIN);
+        let elapsed = get_time() - start;
+        assert!(
+            elapsed >= round * PERIOD_MS,
+            "round {} readable after {}ms",
+            round,
+            elapsed
+        );
+        assert!(
+            elapsed < (round + 1) * PERIOD_MS + PERIOD_MS / 2,
+            "round {} readable after {}ms",
+            round,
+            elapsed
+        );
+        assert_eq!(read_expirations(fd), 1);
+        let mut fds = [pollfd(fd)];
+        assert_eq!(poll(&mut fds, 0), 0);
+    }
+
+    // 一段时间不读，到期次数会累积起来
+    sleep(3 * PERIOD_MS as usize + PERIOD_MS as usize / 2);
+    assert!(read_expirations(fd) >= 3);
+
+    // 停止定时器之后不再变为可读
+    assert_eq!(timerfd_settime(fd, 0, 0), 0);
+    let mut fds = [pollfd(fd)];
+    assert_eq!(poll(&mut fds, 2 * PERIOD_MS), 0);
+
+    // 一次性定时器：到期一次之后不再变为可读，读会一直阻塞到到期
+    let start = get_time();
+    assert_eq!(timerfd_settime(fd, 0, PERIOD_MS as usize), 0);
+    assert_eq!(read_expirations(fd), 1);
+    assert!(get_time() - start >= PERIOD_MS);
+    let mut fds = [pollfd(fd)];
+    assert_eq!(poll(&mut fds, 2 * PERIOD_MS), 0);
+
+    // 不是定时器文件的 fd 不能设置
+    assert_eq!(timerfd_settime(0, 0, PERIOD_MS as usize), -1);
+    close(fd);
+    assert_eq!(timerfd_settime(fd, 0, PERIOD_MS as usize), -1);
+
+    println!("timerfd_test passed!");
+    0
+}
@@ -0,0 +1,74 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, mmap, munmap, sbrk, waitpid, PROT_READ, PROT_WRITE};
+
+const START: usize = 0x1000_0000;
+// 在取消映射之后仍然保留的页面
+const KEPT: usize = 0x2000_0000;
+const PAGE_SIZE: usize = 4096;
+// 内核在取消映射的页面不超过 64 个时逐页刷新快表，超过时清空整个快表，两种情况都要测试
+const SMALL: usize = 4;
+const LARGE: usize = 256;
+
+fn touch(addr: usize, value: usize) {
+    unsafe {
+        (addr as *mut usize).write_volatile(value);
+        assert_eq!((addr as *const usize).read_volatile(), value);
+    }
+}
+
+fn mmap_pages(pages: usize) -> usize {
+    assert_eq!(mmap(START, pages * PAGE_SIZE, PROT_READ | PROT_WRITE), 0);
+    START
+}
+
+fn munmap_pages(start: usize, pages: usize) {
+    assert_eq!(munmap(start, pages * PAGE_SIZE), 0);
+}
+
+fn grow_heap(pages: usize) -> usize {
+    let old_brk = sbrk((pages * PAGE_SIZE) as i32);
+    assert!(old_brk > 0);
+    old_brk as usize
+}
+
+fn shrink_heap(_start: usize, pages: usize) {
+    assert!(sbrk(-((pages * PAGE_SIZE) as i32)) > 0);
+}
+
+// 在子进程中用 map 映射 pages 个页面并逐一写入使它们进入快表，再用 unmap 取消映射，
+// 之后访问最后一个页面应当收到 SIGSEGV ，而保留的页面不受影响。返回子进程的退出码
+fn access_after_unmap(pages: usize, map: fn(usize) -> usize, unmap: fn(usize, usize)) -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        assert_eq!(mmap(KEPT, PAGE_SIZE, PROT_READ | PROT_WRITE), 0);
+        touch(KEPT, 42);
+        let start = map(pages);
+        for page in 0..pages {
+            touch(start + page * PAGE_SIZE, page);
+        }
+        unmap(start, pages);
+        unsafe {
+            assert_eq!((KEPT as *const usize).read_volatile(), 42);
+            ((start + (pages - 1) * PAGE_SIZE) as *const usize).read_volatile();
+        }
+        exit(0);
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    exit_code
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    for pages in [SMALL, LARGE] {
+        assert_eq!(access_after_unmap(pages, mmap_pages, munmap_pages), -11);
+        assert_eq!(access_after_unmap(pages, grow_heap, shrink_heap), -11);
+    }
+    println!("tlb_flush_test passed!");
+    0
+}
@@ -0,0 +1,52 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::arch::asm;
+use user_lib::{exit, fork, settls, tls_pointer, waitpid, yield_};
+
+const ROUNDS: usize = 100;
+
+// 通过 tp 读出 TLS 块中的第一个字
+fn tls_word() -> usize {
+    let value: usize;
+    unsafe {
+        asm!("ld {}, 0(tp)", out(reg) value);
+    }
+    value
+}
+
+// 两个执行流交替运行，各自通过 tp 读到的必须一直是自己的值
+fn check_own_value(value: usize) {
+    let block = [value, 0];
+    assert_eq!(settls(block.as_ptr() as usize), 0);
+    assert_eq!(tls_pointer(), block.as_ptr() as usize);
+    for _ in 0..ROUNDS {
+        yield_();
+        assert_eq!(tls_word(), value);
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // 用户程序都没有 TLS 段，tp 初始为 0
+    assert_eq!(tls_pointer(), 0);
+
+    let parent_block = [0usize; 2];
+    settls(parent_block.as_ptr() as usize);
+    let pid = fork();
+    if pid == 0 {
+        // fork 出的子进程继承 tp
+        assert_eq!(tls_pointer(), parent_block.as_ptr() as usize);
+        check_own_value(0xc0ffee);
+        exit(0);
+    }
+    check_own_value(0xbeef);
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    println!("tls_test passed!");
+    0
+}
@@ -0,0 +1,70 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, ftruncate, open, read, truncate, write, OpenFlags};
+
+const FILE: &str = "truncate_file\0";
+
+// 重新打开文件并读出全部内容，返回文件长度，并检查内容的前 prefix 字节与 expected 一致，其余部分全为 0
+fn check_file(expected: &[u8], prefix: usize) -> usize {
+    let fd = open(FILE, OpenFlags::RDONLY);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    let mut buffer = [0u8; 64];
+    let mut total = 0;
+    loop {
+        let len = read(fd, &mut buffer) as usize;
+        if len == 0 {
+            break;
+        }
+        for (i, byte) in buffer[..len].iter().enumerate() {
+            let pos = total + i;
+            if pos < prefix {
+                assert_eq!(*byte, expected[pos]);
+            } else {
+                assert_eq!(*byte, 0);
+            }
+        }
+        total += len;
+    }
+    close(fd);
+    total
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let content = [b'x'; 1000];
+    let fd = open(FILE, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    assert_eq!(write(fd, &content), 1000);
+    close(fd);
+
+    // truncate by path to a smaller and then a larger size
+    assert_eq!(truncate(FILE, 300), 0);
+    assert_eq!(check_file(&content, 300), 300);
+    assert_eq!(truncate(FILE, 1500), 0);
+    assert_eq!(check_file(&content, 300), 1500);
+
+    // ftruncate shares the same logic but needs a writable fd
+    let fd = open(FILE, OpenFlags::RDONLY) as usize;
+    assert_eq!(ftruncate(fd, 10), -1);
+    close(fd);
+    let fd = open(FILE, OpenFlags::WRONLY) as usize;
+    assert_eq!(ftruncate(fd, 10), 0);
+    close(fd);
+    assert_eq!(check_file(&content, 10), 10);
+    assert_eq!(ftruncate(1, 10), -1);
+
+    // missing files and directories cannot be truncated
+    assert_eq!(truncate("no_such_file\0", 0), -1);
+    assert_eq!(truncate("/\0", 0), -1);
+    assert_eq!(truncate(FILE, usize::MAX), -1);
+    assert_eq!(truncate(FILE, 0), 0);
+    assert_eq!(check_file(&content, 0), 0);
+    println!("truncate_test passed!");
+    0
+}
@@ -0,0 +1,54 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    fork, ioctl, read, setpgid, tcgetattr, tcgetpgrp, tcsetattr, tcsetpgrp, waitpid, Termios,
+    ISIG, TIOCSTI,
+};
+
+const CTRL_C: u8 = 0x03;
+const CTRL_BACKSLASH: u8 = 0x1c;
+
+// 在前台运行一个不做任何系统调用的死循环，模拟键盘输入控制字符后检查它的退出码
+fn type_to_busy_loop(c: u8) -> i32 {
+    let old_foreground = tcgetpgrp(0);
+    assert!(old_foreground >= 0);
+    let pid = fork();
+    if pid == 0 {
+        setpgid(0, 0);
+        loop {
+            core::hint::spin_loop();
+        }
+    }
+    setpgid(pid as usize, 0);
+    assert_eq!(tcsetpgrp(0, pid as usize), 0);
+    assert_eq!(ioctl(0, TIOCSTI, &c as *const u8 as usize), 0);
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(tcsetpgrp(0, old_foreground as usize), 0);
+    exit_code
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(type_to_busy_loop(CTRL_C), -2);
+    assert_eq!(type_to_busy_loop(CTRL_BACKSLASH), -3);
+
+    // in raw mode control characters are passed through
+    let mut termios = Termios::default();
+    assert_eq!(tcgetattr(0, &mut termios), 0);
+    assert_ne!(termios.lflag & ISIG, 0);
+    let cooked = termios;
+    termios.lflag &= !ISIG;
+    assert_eq!(tcsetattr(0, &termios), 0);
+    assert_eq!(ioctl(0, TIOCSTI, &CTRL_C as *const u8 as usize), 0);
+    let mut buf = [0u8; 1];
+    assert_eq!(read(0, &mut buf), 1);
+    assert_eq!(buf[0], CTRL_C);
+    assert_eq!(tcsetattr(0, &cooked), 0);
+    println!("tty_signals passed!");
+    0
+}
@@ -11,12 +11,22 @@ const LF: u8 = 0x0au8;
 const CR: u8 = 0x0du8;
 const DL: u8 = 0x7fu8;
 const BS: u8 = 0x08u8;
+const ESC: u8 = 0x1bu8;
+const TAB: u8 = 0x09u8;
+const BEL: u8 = 0x07u8;
 const LINE_START: &str = ">> ";
+// 最多记住的命令行数，更早的命令行会被丢弃
+const HISTORY_SIZE: usize = 32;
 
+use alloc::collections::VecDeque;
 use alloc::string::String;
 use alloc::vec::Vec;
 use user_lib::console::getchar;
-use user_lib::{close, dup, exec, fork, open, pipe, waitpid, OpenFlags};
+use user_lib::{
+    chdir, close, closedir, dup, execvp, exit, fork, getcwd, getenv, getpid, kill, open, opendir,
+    pipe, readdir, setpgid, setsid, sigaction, sigreturn, tcsetpgrp, waitpid_nb, waitpid_status,
+    OpenFlags, SignalAction, SIGCONT, SIGINT, SIGQUIT, SIGTSTP, WIFSIGNALED, WTERMSIG,
+};
 
 #[derive(Debug)]
 struct ProcessArguments {
@@ -80,134 +90,489 @@ impl ProcessArguments {
     }
 }
 
+/// Recently executed command lines, oldest first
+struct History {
+    lines: VecDeque<String>,
+    /// index of the line being shown, `lines.len()` for the line being typed
+    cursor: usize,
+    /// the line being typed before browsing the history
+    draft: String,
+}
+
+impl History {
+    fn new() -> Self {
+        Self {
+            lines: VecDeque::new(),
+            cursor: 0,
+            draft: String::new(),
+        }
+    }
+    // 记录一条执行过的命令行并回到正在输入的行。空行以及与上一条相同的命令行不会被记录
+    fn push(&mut self, line: &str) {
+        if !line.is_empty() && self.lines.back().map(|last| last.as_str()) != Some(line) {
+            if self.lines.len() == HISTORY_SIZE {
+                self.lines.pop_front();
+            }
+            self.lines.push_back(String::from(line));
+        }
+        self.cursor = self.lines.len();
+        self.draft.clear();
+    }
+    // 上方向键：取出更早的一条命令行，已经是最早的一条时返回 None 。
+    // 离开正在输入的行时先保存它，以便之后用下方向键回到这一行
+    fn older(&mut self, line: &str) -> Option<String> {
+        if self.cursor == 0 {
+            return None;
+        }
+        if self.cursor == self.lines.len() {
+            self.draft = String::from(line);
+        }
+        self.cursor -= 1;
+        Some(self.lines[self.cursor].clone())
+    }
+    // 下方向键：取出更晚的一条命令行，越过最新的一条之后回到正在输入的行
+    fn newer(&mut self) -> Option<String> {
+        if self.cursor == self.lines.len() {
+            return None;
+        }
+        self.cursor += 1;
+        Some(
+            self.lines
+                .get(self.cursor)
+                .cloned()
+                .unwrap_or_else(|| self.draft.clone()),
+        )
+    }
+}
+
+// 方向键产生 ESC [ A 这样的多字节转义序列，记录已经收到了序列中的哪一部分
+#[derive(Clone, Copy)]
+enum Escape {
+    None,
+    /// received ESC
+    Start,
+    /// received ESC [, waiting for parameters or the final byte
+    Csi,
+}
+
+/// A command line running in the background
+struct Job {
+    /// number shown by `jobs` and passed to `fg`
+    id: usize,
+    /// process group of the pipeline
+    pgid: usize,
+    /// processes of the pipeline which have not been reaped
+    pids: Vec<usize>,
+    command: String,
+}
+
+// 等待前台进程退出。正常退出时不打印任何信息，被信号终止时告诉用户是哪个信号
+fn wait_foreground(pid: usize) {
+    let mut status: i32 = 0;
+    assert_eq!(waitpid_status(pid as isize, &mut status), pid as isize);
+    if WIFSIGNALED(status) {
+        println!(
+            "Shell: Process {} killed by signal {}",
+            pid,
+            WTERMSIG(status)
+        );
+    }
+}
+
+impl Job {
+    // 用 waitpid_nb 回收已经退出的进程，所有进程都退出之后作业才算完成
+    fn reap(&mut self) -> bool {
+        let mut exit_code: i32 = 0;
+        self.pids
+            .retain(|&pid| waitpid_nb(pid, &mut exit_code) != pid as isize);
+        self.pids.is_empty()
+    }
+}
+
+// shell 自身不应被 Ctrl-C/Ctrl-\/Ctrl-Z 终止或暂停，因此为这些信号设置一个什么都不做的处理例程
+fn ignore_handler() {
+    sigreturn();
+}
+
+// 报告并移除已经完成的后台作业。shell 不会在后台作业退出时被通知，因此在每次输出提示符之前检查一遍
+fn report_done_jobs(jobs: &mut Vec<Job>) {
+    jobs.retain_mut(|job| {
+        let done = job.reap();
+        if done {
+            println!("[{}] Done  {}", job.id, job.command);
+        }
+        !done
+    });
+}
+
+// 列出所有后台作业及其状态，已经完成的作业在列出之后移除
+fn list_jobs(jobs: &mut Vec<Job>) {
+    jobs.retain_mut(|job| {
+        let done = job.reap();
+        let status = if done { "Done" } else { "Running" };
+        println!("[{}] {}  {}", job.id, status, job.command);
+        !done
+    });
+}
+
+// 将编号为 id 的后台作业切换到前台并等待它结束，id 为空时选择最近的作业
+fn foreground_job(jobs: &mut Vec<Job>, id: Option<&str>, shell_pgid: usize) {
+    let index = match id {
+        Some(id) => id
+            .parse::<usize>()
+            .ok()
+            .and_then(|id| jobs.iter().position(|job| job.id == id)),
+        None => jobs.len().checked_sub(1),
+    };
+    let Some(index) = index else {
+        println!("fg: no such job");
+        return;
+    };
+    let job = jobs.remove(index);
+    println!("{}", job.command);
+    tcsetpgrp(0, job.pgid);
+    // 作业可能被 Ctrl-Z 暂停过，先让它继续运行
+    for &pid in job.pids.iter() {
+        kill(pid, SIGCONT);
+    }
+    for &pid in job.pids.iter() {
+        wait_foreground(pid);
+    }
+    tcsetpgrp(0, shell_pgid);
+}
+
+// cd 必须修改 shell 自己的工作目录，没有参数时回到根目录
+fn change_dir(dir: Option<&str>) {
+    let dir = dir.unwrap_or("/");
+    let mut path = String::from(dir);
+    path.push('\0');
+    if chdir(path.as_str()) == -1 {
+        println!("cd: {}: No such directory", dir);
+    }
+}
+
+fn print_cwd() {
+    let mut buf = [0u8; 256];
+    let len = getcwd(&mut buf);
+    if len <= 0 {
+        println!("pwd: cannot get the working directory");
+        return;
+    }
+    // 去掉结尾的 \0
+    let cwd = core::str::from_utf8(&buf[..len as usize - 1]).unwrap();
+    println!("{}", cwd);
+}
+
+// 以 code 作为退出码结束 shell ，没有参数时退出码为 0
+fn exit_shell(code: Option<&str>) {
+    match code.map(|code| code.parse::<i32>()) {
+        None => exit(0),
+        Some(Ok(code)) => exit(code),
+        Some(Err(_)) => println!("exit: numeric argument required"),
+    }
+}
+
+// 列出目录 dir 中所有以 prefix 开头的文件名
+fn matching_names(dir: &str, prefix: &str) -> Vec<String> {
+    let mut path = String::from(dir);
+    path.push('\0');
+    let mut names = Vec::new();
+    if let Some(mut dir) = opendir(path.as_str()) {
+        while let Some(entry) = readdir(&mut dir) {
+            if entry.name.starts_with(prefix) {
+                names.push(entry.name);
+            }
+        }
+        closedir(dir);
+    }
+    names
+}
+
+// 补全命令行中的最后一个词：第一个词在 PATH 中的各个目录下查找命令，之后的词以及含有 / 的词在词中给出的目录下查找文件。
+// 返回所有可能的完整的词，按字典序排列且没有重复
+fn completions(line: &str) -> Vec<String> {
+    let start = line.rfind(' ').map_or(0, |index| index + 1);
+    let word = &line[start..];
+    let mut candidates = Vec::new();
+    if line[..start].trim().is_empty() && !word.contains('/') {
+        let dirs = getenv("PATH").unwrap_or("/");
+        for dir in dirs.split(':').filter(|dir| !dir.is_empty()) {
+            candidates.extend(matching_names(dir, word));
+        }
+    } else {
+        let (dir, prefix) = match word.rfind('/') {
+            Some(index) => (&word[..=index], &word[index + 1..]),
+            None => ("", word),
+        };
+        let search_dir = if dir.is_empty() { "." } else { dir };
+        for name in matching_names(search_dir, prefix) {
+            candidates.push(alloc::format!("{}{}", dir, name));
+        }
+    }
+    candidates.sort();
+    candidates.dedup();
+    candidates
+}
+
+// 所有候选共同的最长前缀
+fn common_prefix(candidates: &[String]) -> &str {
+    let first = candidates[0].as_str();
+    let mut len = candidates.iter().fold(first.len(), |len, candidate| {
+        first
+            .bytes()
+            .zip(candidate.bytes())
+            .take(len)
+            .take_while(|(a, b)| a == b)
+            .count()
+    });
+    while !first.is_char_boundary(len) {
+        len -= 1;
+    }
+    &first[..len]
+}
+
+// 按下 Tab 时补全当前行的最后一个词。没有候选时响铃；只有一个候选时补全整个词并加上空格；
+// 有多个候选时补全到它们共同的前缀，并列出所有候选后重新输出提示符和当前行
+fn complete_line(line: &mut String) {
+    let candidates = completions(line.as_str());
+    if candidates.is_empty() {
+        print!("{}", BEL as char);
+        return;
+    }
+    let start = line.rfind(' ').map_or(0, |index| index + 1);
+    // 每个候选都以当前的词开头
+    let rest = &common_prefix(&candidates)[line.len() - start..];
+    print!("{}", rest);
+    line.push_str(rest);
+    if candidates.len() == 1 {
+        print!(" ");
+        line.push(' ');
+        return;
+    }
+    println!("");
+    println!("{}", candidates.join("  "));
+    print!("{}{}", LINE_START, line);
+}
+
+// 执行一行命令。命令以 & 结尾时不等待它结束，而是将它记录为后台作业
+fn run_line(line: &str, jobs: &mut Vec<Job>, shell_pgid: usize) {
+    let mut line = line.trim();
+    let background = line.ends_with('&');
+    if background {
+        line = line.trim_end_matches('&').trim_end();
+    }
+    if line.is_empty() {
+        return;
+    }
+    // 内建命令会修改 shell 自身的状态，因此由 shell 自己执行而不创建子进程
+    let mut words = line.split_whitespace();
+    match words.next() {
+        Some("cd") => return change_dir(words.next()),
+        Some("pwd") => return print_cwd(),
+        Some("exit") => return exit_shell(words.next()),
+        Some("jobs") => return list_jobs(jobs),
+        Some("fg") => return foreground_job(jobs, words.next(), shell_pgid),
+        _ => {}
+    }
+    let splited: Vec<_> = line.split('|').collect();
+    let process_arguments_list: Vec<_> = splited
+        .iter()
+        .map(|&cmd| ProcessArguments::new(cmd))
+        .collect();
+    let mut valid = true;
+    for (i, process_args) in process_arguments_list.iter().enumerate() {
+        if i == 0 {
+            if !process_args.output.is_empty() {
+                valid = false;
+            }
+        } else if i == process_arguments_list.len() - 1 {
+            if !process_args.input.is_empty() {
+                valid = false;
+            }
+        } else if !process_args.output.is_empty() || !process_args.input.is_empty() {
+            valid = false;
+        }
+    }
+    if process_arguments_list.len() == 1 {
+        valid = true;
+    }
+    if !valid {
+        println!("Invalid command: Inputs/Outputs cannot be correctly binded!");
+        return;
+    }
+    // create pipes
+    let mut pipes_fd: Vec<[usize; 2]> = Vec::new();
+    if !process_arguments_list.is_empty() {
+        for _ in 0..process_arguments_list.len() - 1 {
+            let mut pipe_fd = [0usize; 2];
+            pipe(&mut pipe_fd);
+            pipes_fd.push(pipe_fd);
+        }
+    }
+    let mut children: Vec<usize> = Vec::new();
+    for (i, process_argument) in process_arguments_list.iter().enumerate() {
+        let pid = fork();
+        // 管道中的所有进程处于同一个进程组，组号为第一个进程的 pid
+        let pgid = children.first().map_or(0, |&first| first);
+        if pid == 0 {
+            setpgid(0, pgid);
+            // 打开文件和替换的过程则发生在 fork 之后的子进程分支中
+            let input = &process_argument.input;
+            let output = &process_argument.output;
+            let args_copy = &process_argument.args_copy;
+            let args_addr = &process_argument.args_addr;
+            // redirect input
+            if !input.is_empty() {
+                let input_fd = open(input.as_str(), OpenFlags::RDONLY);
+                if input_fd == -1 {
+                    println!("Error when opening file {}", input);
+                    exit(-4);
+                }
+                let input_fd = input_fd as usize;
+                // 首先通过 close 关闭标准输入所在的文件描述符 0
+                close(0);
+                // 通过 dup 来分配一个新的文件描述符来访问 input_fd 对应的输入文件
+                // 这里用到了文件描述符分配的重要性质：即必定分配可用描述符中编号最小的一个。由于我们刚刚关闭了描述符 0 ，那么在 dup 的时候一定会将它分配出去，于是现在应用进程的文件描述符 0 就对应到输入文件了
+                assert_eq!(dup(input_fd), 0);
+                // 因为应用进程的后续执行不会用到输入文件原来的描述符 input_fd ，所以就将其关掉
+                close(input_fd);
+            }
+            // redirect output
+            if !output.is_empty() {
+                let output_fd = open(output.as_str(), OpenFlags::CREATE | OpenFlags::WRONLY);
+                if output_fd == -1 {
+                    println!("Error when opening file {}", output);
+                    exit(-4);
+                }
+                let output_fd = output_fd as usize;
+                close(1);
+                assert_eq!(dup(output_fd), 1);
+                close(output_fd);
+            }
+            // receive input from the previous process
+            if i > 0 {
+                close(0);
+                let read_end = pipes_fd.get(i - 1).unwrap()[0];
+                assert_eq!(dup(read_end), 0);
+            }
+            // send output to the next process
+            if i < process_arguments_list.len() - 1 {
+                close(1);
+                let write_end = pipes_fd.get(i).unwrap()[1];
+                assert_eq!(dup(write_end), 1);
+            }
+            // close all pipe ends inherited from the parent process
+            for pipe_fd in pipes_fd.iter() {
+                close(pipe_fd[0]);
+                close(pipe_fd[1]);
+            }
+            // execute new application
+            if execvp(args_copy[0].as_str(), args_addr.as_slice()) == -1 {
+                println!("Error when executing!");
+                exit(-4);
+            }
+            unreachable!();
+        } else {
+            setpgid(pid as usize, pgid);
+            children.push(pid as usize);
+        }
+    }
+    for pipe_fd in pipes_fd.iter() {
+        close(pipe_fd[0]);
+        close(pipe_fd[1]);
+    }
+    let Some(&pgid) = children.first() else {
+        return;
+    };
+    if background {
+        // 后台作业不取得控制台，编号为当前最大的编号加一
+        let id = jobs.iter().map(|job| job.id).max().unwrap_or(0) + 1;
+        println!("[{}] {}", id, pgid);
+        jobs.push(Job {
+            id,
+            pgid,
+            pids: children,
+            command: String::from(line),
+        });
+        return;
+    }
+    tcsetpgrp(0, pgid);
+    for pid in children.into_iter() {
+        wait_foreground(pid);
+    }
+    tcsetpgrp(0, shell_pgid);
+}
+
 #[no_mangle]
-pub fn main() -> i32 {
+pub fn main(argc: usize, argv: &[&str]) -> i32 {
+    // shell 作为会话首进程并取得控制台作为控制终端，每条命令在自己的进程组中作为前台进程组运行
+    setsid();
+    let shell_pgid = getpid() as usize;
+    let mut action = SignalAction::default();
+    action.handler = ignore_handler as usize;
+    for signum in [SIGINT, SIGQUIT, SIGTSTP] {
+        sigaction(signum, Some(&action), Some(&mut SignalAction::default()));
+    }
+    let mut jobs: Vec<Job> = Vec::new();
+    // 带有参数时依次执行每个参数给出的命令行然后退出，而不是从控制台读取命令
+    if argc > 1 {
+        for line in argv[1..].iter() {
+            report_done_jobs(&mut jobs);
+            run_line(line, &mut jobs, shell_pgid);
+        }
+        return 0;
+    }
     println!("Rust user shell");
     let mut line: String = String::new();
+    let mut history = History::new();
+    let mut escape = Escape::None;
     print!("{}", LINE_START);
     loop {
         let c = getchar();
+        match (escape, c) {
+            (Escape::None, _) => {}
+            (Escape::Start, b'[') => {
+                escape = Escape::Csi;
+                continue;
+            }
+            // 参数部分的字节，比如 ESC [ 1 ; 5 A 中的 1 ; 5
+            (Escape::Csi, b'0'..=b'?') => continue,
+            (Escape::Csi, _) => {
+                escape = Escape::None;
+                // 用取出的命令行替换当前行：回到行首重新输出提示符和命令行，再清除到行尾。
+                // 取出的命令行是一份拷贝，编辑它不会修改历史记录
+                let recalled = match c {
+                    b'A' => history.older(line.as_str()),
+                    b'B' => history.newer(),
+                    _ => None,
+                };
+                if let Some(recalled) = recalled {
+                    line = recalled;
+                    print!("\r{}{}\x1b[K", LINE_START, line);
+                }
+                continue;
+            }
+            // 不认识的转义序列被丢弃
+            (Escape::Start, _) => {
+                escape = Escape::None;
+                continue;
+            }
+        }
         match c {
+            // read is interrupted by a signal
+            0 => {}
+            ESC => escape = Escape::Start,
             LF | CR => {
                 println!("");
+                history.push(line.as_str());
                 if !line.is_empty() {
-                    let splited: Vec<_> = line.as_str().split('|').collect();
-                    let process_arguments_list: Vec<_> = splited
-                        .iter()
-                        .map(|&cmd| ProcessArguments::new(cmd))
-                        .collect();
-                    let mut valid = true;
-                    for (i, process_args) in process_arguments_list.iter().enumerate() {
-                        if i == 0 {
-                            if !process_args.output.is_empty() {
-                                valid = false;
-                            }
-                        } else if i == process_arguments_list.len() - 1 {
-                            if !process_args.input.is_empty() {
-                                valid = false;
-                            }
-                        } else if !process_args.output.is_empty() || !process_args.input.is_empty()
-                        {
-                            valid = false;
-                        }
-                    }
-                    if process_arguments_list.len() == 1 {
-                        valid = true;
-                    }
-                    if !valid {
-                        println!("Invalid command: Inputs/Outputs cannot be correctly binded!");
-                    } else {
-                        // create pipes
-                        let mut pipes_fd: Vec<[usize; 2]> = Vec::new();
-                        if !process_arguments_list.is_empty() {
-                            for _ in 0..process_arguments_list.len() - 1 {
-                                let mut pipe_fd = [0usize; 2];
-                                pipe(&mut pipe_fd);
-                                pipes_fd.push(pipe_fd);
-                            }
-                        }
-                        let mut children: Vec<_> = Vec::new();
-                        for (i, process_argument) in process_arguments_list.iter().enumerate() {
-                            let pid = fork();
-                            if pid == 0 {
-                                // 打开文件和替换的过程则发生在 fork 之后的子进程分支中
-                                let input = &process_argument.input;
-                                let output = &process_argument.output;
-                                let args_copy = &process_argument.args_copy;
-                                let args_addr = &process_argument.args_addr;
-                                // redirect input
-                                if !input.is_empty() {
-                                    let input_fd = open(input.as_str(), OpenFlags::RDONLY);
-                                    if input_fd == -1 {
-                                        println!("Error when opening file {}", input);
-                                        return -4;
-                                    }
-                                    let input_fd = input_fd as usize;
-                                    // 首先通过 close 关闭标准输入所在的文件描述符 0 
-                                    close(0);
-                                    // 通过 dup 来分配一个新的文件描述符来访问 input_fd 对应的输入文件
-                                    // 这里用到了文件描述符分配的重要性质：即必定分配可用描述符中编号最小的一个。由于我们刚刚关闭了描述符 0 ，那么在 dup 的时候一定会将它分配出去，于是现在应用进程的文件描述符 0 就对应到输入文件了
-                                    assert_eq!(dup(input_fd), 0);
-                                    // 因为应用进程的后续执行不会用到输入文件原来的描述符 input_fd ，所以就将其关掉
-                                    close(input_fd);
-                                }
-                                // redirect output
-                                if !output.is_empty() {
-                                    let output_fd = open(
-                                        output.as_str(),
-                                        OpenFlags::CREATE | OpenFlags::WRONLY,
-                                    );
-                                    if output_fd == -1 {
-                                        println!("Error when opening file {}", output);
-                                        return -4;
-                                    }
-                                    let output_fd = output_fd as usize;
-                                    close(1);
-                                    assert_eq!(dup(output_fd), 1);
-                                    close(output_fd);
-                                }
-                                // receive input from the previous process
-                                if i > 0 {
-                                    close(0);
-                                    let read_end = pipes_fd.get(i - 1).unwrap()[0];
-                                    assert_eq!(dup(read_end), 0);
-                                }
-                                // send output to the next process
-                                if i < process_arguments_list.len() - 1 {
-                                    close(1);
-                                    let write_end = pipes_fd.get(i).unwrap()[1];
-                                    assert_eq!(dup(write_end), 1);
-                                }
-                                // close all pipe ends inherited from the parent process
-                                for pipe_fd in pipes_fd.iter() {
-                                    close(pipe_fd[0]);
-                                    close(pipe_fd[1]);
-                                }
-                                // execute new application
-                                if exec(args_copy[0].as_str(), args_addr.as_slice()) == -1 {
-                                    println!("Error when executing!");
-                                    return -4;
-                                }
-                                unreachable!();
-                            } else {
-                                children.push(pid);
-                            }
-                        }
-                        for pipe_fd in pipes_fd.iter() {
-                            close(pipe_fd[0]);
-                            close(pipe_fd[1]);
-                        }
-                        let mut exit_code: i32 = 0;
-                        for pid in children.into_iter() {
-                            let exit_pid = waitpid(pid as usize, &mut exit_code);
-                            assert_eq!(pid, exit_pid);
-                            //println!("Shell: Process {} exited with code {}", pid, exit_code);
-                        }
-                    }
+                    run_line(line.as_str(), &mut jobs, shell_pgid);
                     line.clear();
                 }
+                report_done_jobs(&mut jobs);
                 print!("{}", LINE_START);
             }
+            TAB => complete_line(&mut line),
             BS | DL => {
                 if !line.is_empty() {
                     print!("{}", BS as char);
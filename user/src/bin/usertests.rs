@@ -5,30 +5,105 @@
 extern crate user_lib;
 
 // not in SUCC_TESTS & FAIL_TESTS
-// count_lines, infloop, user_shell, usertests
+// count_lines, fd_probe, hello_stderr, infloop, user_shell, usertests
 
 // item of TESTS : app_name(argv_0), argv_1, argv_2, argv_3, exit_code
 static SUCC_TESTS: &[(&str, &str, &str, &str, i32)] = &[
     ("filetest_simple\0", "\0", "\0", "\0", 0),
+    ("flock_test\0", "\0", "\0", "\0", 0),
+    ("affinity_test\0", "\0", "\0", "\0", 0),
+    ("assert_test\0", "\0", "\0", "\0", 0),
+    ("brk_mmap_test\0", "\0", "\0", "\0", 0),
+    ("buffered_print_test\0", "\0", "\0", "\0", 0),
     ("cat\0", "filea\0", "\0", "\0", 0),
     ("cmdline_args\0", "1\0", "2\0", "3\0", 0),
+    ("copy_file_range_test\0", "\0", "\0", "\0", 0),
+    ("demand_zero\0", "\0", "\0", "\0", 0),
+    ("dirent_type_test\0", "\0", "\0", "\0", 0),
+    ("dup3_test\0", "\0", "\0", "\0", 0),
+    ("env_test\0", "\0", "\0", "\0", 0),
+    ("exec_bad_args\0", "\0", "\0", "\0", 0),
+    ("exec_bad_elf\0", "\0", "\0", "\0", 0),
+    ("exec_wx_test\0", "\0", "\0", "\0", 0),
+    ("execvp_test\0", "\0", "\0", "\0", 0),
     ("exit\0", "\0", "\0", "\0", 0),
+    ("fadvise_test\0", "\0", "\0", "\0", 0),
     ("fantastic_text\0", "\0", "\0", "\0", 0),
+    ("fchdir_test\0", "\0", "\0", "\0", 0),
+    ("fchmod_test\0", "\0", "\0", "\0", 0),
     ("forktest_simple\0", "\0", "\0", "\0", 0),
     ("forktest\0", "\0", "\0", "\0", 0),
     ("forktest2\0", "\0", "\0", "\0", 0),
     ("forktree\0", "\0", "\0", "\0", 0),
+    ("getdents_test\0", "\0", "\0", "\0", 0),
+    ("getchar_nb_test\0", "\0", "\0", "\0", 0),
+    ("getcpu_test\0", "\0", "\0", "\0", 0),
     ("hello_world\0", "\0", "\0", "\0", 0),
+    ("heap_grow\0", "\0", "\0", "\0", 0),
     ("huge_write\0", "\0", "\0", "\0", 0),
+    ("hwcap_test\0", "\0", "\0", "\0", 0),
+    ("io_boost_test\0", "\0", "\0", "\0", 0),
+    ("io_counters_test\0", "\0", "\0", "\0", 0),
+    ("io_ring_test\0", "\0", "\0", "\0", 0),
+    ("irq_latency_test\0", "\0", "\0", "\0", 0),
+    ("ls\0", "/\0", "\0", "\0", 0),
+    ("loadavg_test\0", "\0", "\0", "\0", 0),
+    ("lowmem_test\0", "\0", "\0", "\0", 0),
+    ("maps_test\0", "\0", "\0", "\0", 0),
     ("matrix\0", "\0", "\0", "\0", 0),
+    ("mincore_test\0", "\0", "\0", "\0", 0),
+    ("mkdir_p_test\0", "\0", "\0", "\0", 0),
+    ("mknod_test\0", "\0", "\0", "\0", 0),
+    ("nice_test\0", "\0", "\0", "\0", 0),
+    ("openat_test\0", "\0", "\0", "\0", 0),
+    ("orphan_reap\0", "\0", "\0", "\0", 0),
+    ("panic_backtrace\0", "\0", "\0", "\0", 0),
+    ("pause_test\0", "\0", "\0", "\0", 0),
+    ("pipe_cancel_test\0", "\0", "\0", "\0", 0),
+    ("pipe_eof_test\0", "\0", "\0", "\0", 0),
     ("pipe_large_test\0", "\0", "\0", "\0", 0),
     ("pipetest\0", "\0", "\0", "\0", 0),
+    ("poll_test\0", "\0", "\0", "\0", 0),
+    ("preadv_test\0", "\0", "\0", "\0", 0),
+    ("profile_test\0", "\0", "\0", "\0", 0),
+    ("raise_test\0", "\0", "\0", "\0", 0),
+    ("read_timeout_test\0", "\0", "\0", "\0", 0),
+    ("remove_dir_all_test\0", "\0", "\0", "\0", 0),
+    ("rename_test\0", "\0", "\0", "\0", 0),
+    ("rmdir_test\0", "\0", "\0", "\0", 0),
+    ("rng_test\0", "\0", "\0", "\0", 0),
+    ("robust_list_test\0", "\0", "\0", "\0", 0),
     ("run_pipe_test\0", "\0", "\0", "\0", 0),
+    ("rusage_test\0", "\0", "\0", "\0", 0),
+    ("sbrk_test\0", "\0", "\0", "\0", 0),
+    ("seccomp_test\0", "\0", "\0", "\0", 0),
+    ("session_test\0", "\0", "\0", "\0", 0),
     ("sleep_simple\0", "\0", "\0", "\0", 0),
     ("sleep\0", "\0", "\0", "\0", 0),
+    ("spawn_test\0", "\0", "\0", "\0", 0),
+    ("shared_fd_test\0", "\0", "\0", "\0", 0),
+    ("shell_builtins_test\0", "\0", "\0", "\0", 0),
+    ("shell_complete_test\0", "\0", "\0", "\0", 0),
+    ("shell_history_test\0", "\0", "\0", "\0", 0),
+    ("shell_jobs_test\0", "\0", "\0", "\0", 0),
     ("sig_simple\0", "\0", "\0", "\0", 0),
     ("sig_simple2\0", "\0", "\0", "\0", 0),
     ("sig_tests\0", "\0", "\0", "\0", 0),
+    ("signal_alloc_test\0", "\0", "\0", "\0", 0),
+    ("signalfd_test\0", "\0", "\0", "\0", 0),
+    ("signal_io_stress\0", "\0", "\0", "\0", 0),
+    ("statfs_test\0", "\0", "\0", "\0", 0),
+    ("statx_test\0", "\0", "\0", "\0", 0),
+    ("stderr_test\0", "\0", "\0", "\0", 0),
+    ("sync_test\0", "\0", "\0", "\0", 0),
+    ("sync_write_test\0", "\0", "\0", "\0", 0),
+    ("timerfd_test\0", "\0", "\0", "\0", 0),
+    ("tlb_flush_test\0", "\0", "\0", "\0", 0),
+    ("tls_test\0", "\0", "\0", "\0", 0),
+    ("truncate_test\0", "\0", "\0", "\0", 0),
+    ("tty_signals\0", "\0", "\0", "\0", 0),
+    ("vmsplice_test\0", "\0", "\0", "\0", 0),
+    ("wait_status_test\0", "\0", "\0", "\0", 0),
     ("yield\0", "\0", "\0", "\0", 0),
 ];
 
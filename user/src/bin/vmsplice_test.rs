@@ -0,0 +1,107 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, exit, fork, getrusage, mmap, pipe, read, vmsplice, waitpid, Rusage, PROT_READ,
+    PROT_WRITE, RUSAGE_SELF,
+};
+
+const START: usize = 0x1000_0000;
+// 另一块从未访问过的内存，用来检查共享的零页帧没有被写坏
+const UNTOUCHED: usize = 0x2000_0000;
+const PAGE_SIZE: usize = 4096;
+const PAGES: usize = 4;
+const TAIL: &[u8] = b"tail";
+
+fn minflt() -> usize {
+    let mut usage = Rusage::default();
+    assert_eq!(getrusage(RUSAGE_SELF, &mut usage), 0);
+    usage.minflt
+}
+
+fn pattern(page: usize, i: usize) -> u8 {
+    (page * 31 + i * 7) as u8
+}
+
+fn writer(fd: usize, region: &mut [u8]) {
+    for (page, data) in region.chunks_mut(PAGE_SIZE).enumerate() {
+        for (i, byte) in data.iter_mut().enumerate() {
+            *byte = pattern(page, i);
+        }
+    }
+    // 前两页整页移入管道，第三页从页中间开始，不能移交页帧只能复制
+    assert_eq!(
+        vmsplice(fd, &mut region[..2 * PAGE_SIZE]),
+        2 * PAGE_SIZE as isize
+    );
+    let partial = &mut region[2 * PAGE_SIZE + 100..3 * PAGE_SIZE];
+    assert_eq!(vmsplice(fd, partial), (PAGE_SIZE - 100) as isize);
+    let mut tail = *b"tail";
+    assert_eq!(vmsplice(fd, &mut tail), TAIL.len() as isize);
+    // 页帧被移走的页面重新变回按需清零的页面：内容全为零，再次写入时又会缺页
+    assert!(region[..2 * PAGE_SIZE].iter().all(|byte| *byte == 0));
+    let faults = minflt();
+    region[0] = 1;
+    assert_eq!(minflt(), faults + 1);
+    // 复制出去的页面保持原样
+    assert_eq!(region[2 * PAGE_SIZE + 100], pattern(2, 100));
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    let pid = fork();
+    if pid == 0 {
+        close(pipe_fd[0]);
+        assert_eq!(mmap(START, PAGES * PAGE_SIZE, PROT_READ | PROT_WRITE), 0);
+        let region =
+            unsafe { core::slice::from_raw_parts_mut(START as *mut u8, PAGES * PAGE_SIZE) };
+        writer(pipe_fd[1], region);
+        close(pipe_fd[1]);
+        exit(0);
+    }
+    close(pipe_fd[1]);
+    assert_eq!(mmap(START, PAGES * PAGE_SIZE, PROT_READ | PROT_WRITE), 0);
+    let region = unsafe { core::slice::from_raw_parts_mut(START as *mut u8, PAGES * PAGE_SIZE) };
+
+    // 第一页直接映射到管道中的页帧上：内容与写者的相同，写入它也不会缺页
+    assert_eq!(
+        vmsplice(pipe_fd[0], &mut region[..PAGE_SIZE]),
+        PAGE_SIZE as isize
+    );
+    let faults = minflt();
+    assert!((0..PAGE_SIZE).all(|i| region[i] == pattern(0, i)));
+    region[0] = 0xff;
+    assert_eq!(minflt(), faults);
+
+    // 第二页用普通的 read 读出来，并且放到不对齐的位置上，只能复制
+    let copied = &mut region[PAGE_SIZE + 8..2 * PAGE_SIZE + 8];
+    assert_eq!(read(pipe_fd[0], copied), PAGE_SIZE as isize);
+    assert!((0..PAGE_SIZE).all(|i| copied[i] == pattern(1, i)));
+
+    // 剩下的是复制进管道的数据，一直读到文件末尾
+    let rest = &mut region[3 * PAGE_SIZE..];
+    let len = PAGE_SIZE - 100 + TAIL.len();
+    assert_eq!(vmsplice(pipe_fd[0], rest), len as isize);
+    assert!((0..PAGE_SIZE - 100).all(|i| rest[i] == pattern(2, 100 + i)));
+    assert_eq!(&rest[PAGE_SIZE - 100..len], TAIL);
+    assert_eq!(read(pipe_fd[0], rest), 0);
+    close(pipe_fd[0]);
+    // 最后一页读入之前从未访问过，仍映射在共享零页上。数据必须写进它自己的页帧，
+    // 其他从未访问过的页面仍然全为零
+    assert!(rest[len..].iter().all(|byte| *byte == 0));
+    assert_eq!(mmap(UNTOUCHED, PAGES * PAGE_SIZE, PROT_READ), 0);
+    let untouched =
+        unsafe { core::slice::from_raw_parts(UNTOUCHED as *const u8, PAGES * PAGE_SIZE) };
+    assert!(untouched.iter().all(|byte| *byte == 0));
+
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    println!("vmsplice_test passed!");
+    0
+}
@@ -0,0 +1,54 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    exit, fork, kill, waitpid, waitpid_status, yield_, SIGKILL, WEXITSTATUS, WIFEXITED,
+    WIFSIGNALED, WTERMSIG,
+};
+
+// 以负数退出的进程，它原始的退出码看起来像是被信号终止
+const NEGATIVE_CODE: i32 = -9;
+
+fn spin() -> ! {
+    loop {
+        yield_();
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        spin();
+    }
+    assert_eq!(kill(pid as usize, SIGKILL), 0);
+    let mut status = 0;
+    assert_eq!(waitpid_status(pid, &mut status), pid);
+    assert!(WIFSIGNALED(status) && !WIFEXITED(status));
+    assert_eq!(WTERMSIG(status), SIGKILL);
+    println!("child {} killed by signal {}", pid, WTERMSIG(status));
+
+    let pid = fork();
+    if pid == 0 {
+        exit(NEGATIVE_CODE);
+    }
+    assert_eq!(waitpid_status(-1, &mut status), pid);
+    assert!(WIFEXITED(status) && !WIFSIGNALED(status));
+    assert_eq!(WEXITSTATUS(status), NEGATIVE_CODE & 0xff);
+    println!("child {} exited with code {}", pid, WEXITSTATUS(status));
+
+    // waitpid 仍然得到原始的退出码
+    let pid = fork();
+    if pid == 0 {
+        spin();
+    }
+    kill(pid as usize, SIGKILL);
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, -SIGKILL);
+    println!("wait_status_test passed!");
+    0
+}
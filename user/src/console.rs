@@ -1,18 +1,106 @@
 // use crate::sbi::console_putchar;
-use super::{read, write};
+use super::{exit, fcntl, getpid, read, write, OpenFlags, F_GETFL, F_SETFL};
 use core::fmt::{self, Write};
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
 struct Stdout;
+struct Stderr;
 const STDIN: usize = 0;
 const STDOUT: usize = 1;
+const STDERR: usize = 2;
+const STDOUT_BUFFER_SIZE: usize = 256;
+
+// 默认每次格式化输出都直接 write 一次，交互式的提示符能立刻显示出来；
+// 调用 set_stdout_buffered(true) 之后先放在 STDOUT_BUFFER 中，遇到换行、缓冲区满或者调用 flush_stdout 时才写出
+static BUFFERED: AtomicBool = AtomicBool::new(false);
+// 信号处理例程可能打断正在使用缓冲区的主流程，这时它绕过缓冲区直接输出，因此它的输出可能先于缓冲区中的内容出现
+static BUFFER_BUSY: AtomicBool = AtomicBool::new(false);
+static mut STDOUT_BUFFER: StdoutBuffer = StdoutBuffer {
+    bytes: [0; STDOUT_BUFFER_SIZE],
+    len: 0,
+};
+static STDOUT_WRITES: AtomicUsize = AtomicUsize::new(0);
+
+struct StdoutBuffer {
+    bytes: [u8; STDOUT_BUFFER_SIZE],
+    len: usize,
+}
+
+impl StdoutBuffer {
+    fn push(&mut self, mut s: &[u8]) {
+        let newline = s.contains(&b'\n');
+        while !s.is_empty() {
+            let n = s.len().min(STDOUT_BUFFER_SIZE - self.len);
+            self.bytes[self.len..self.len + n].copy_from_slice(&s[..n]);
+            self.len += n;
+            s = &s[n..];
+            if self.len == STDOUT_BUFFER_SIZE {
+                self.flush();
+            }
+        }
+        if newline {
+            self.flush();
+        }
+    }
+    fn flush(&mut self) {
+        if self.len > 0 {
+            write_stdout(&self.bytes[..self.len]);
+            self.len = 0;
+        }
+    }
+}
+
+fn write_stdout(bytes: &[u8]) {
+    STDOUT_WRITES.fetch_add(1, Ordering::Relaxed);
+    write(STDOUT, bytes);
+}
+
+// 缓冲区正被打断的主流程使用时返回 None
+fn with_buffer<T>(f: impl FnOnce(&mut StdoutBuffer) -> T) -> Option<T> {
+    if BUFFER_BUSY.swap(true, Ordering::Acquire) {
+        return None;
+    }
+    let ret = f(unsafe { &mut *core::ptr::addr_of_mut!(STDOUT_BUFFER) });
+    BUFFER_BUSY.store(false, Ordering::Release);
+    Some(ret)
+}
 
 impl Write for Stdout {
     fn write_str(&mut self, s: &str) -> fmt::Result {
-        write(STDOUT, s.as_bytes());
+        if !BUFFERED.load(Ordering::Relaxed) || with_buffer(|buf| buf.push(s.as_bytes())).is_none()
+        {
+            write_stdout(s.as_bytes());
+        }
         Ok(())
     }
 }
 
+// 标准错误从不缓冲，诊断信息总能立刻输出
+impl Write for Stderr {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        write(STDERR, s.as_bytes());
+        Ok(())
+    }
+}
+
+/// Line-buffer `print!`/`println!` (opt-in) or go back to one `write` per print
+pub fn set_stdout_buffered(buffered: bool) {
+    if !buffered {
+        flush_stdout();
+    }
+    BUFFERED.store(buffered, Ordering::Relaxed);
+}
+
+/// Write out whatever `print!` has buffered so far
+pub fn flush_stdout() {
+    with_buffer(|buf| buf.flush());
+}
+
+/// Number of `write` syscalls the console has issued so far
+pub fn stdout_write_count() -> usize {
+    STDOUT_WRITES.load(Ordering::Relaxed)
+}
+
 pub fn print(args: fmt::Arguments) {
     Stdout.write_fmt(args).unwrap();
 }
@@ -31,9 +119,82 @@ macro_rules! println {
     }
 }
 
+pub fn eprint(args: fmt::Arguments) {
+    Stderr.write_fmt(args).unwrap();
+}
+
+#[macro_export]
+macro_rules! eprint {
+    ($fmt: literal $(, $($arg: tt)+)?) => {
+        $crate::console::eprint(format_args!($fmt $(, $($arg)+)?));
+    }
+}
+
+#[macro_export]
+macro_rules! eprintln {
+    ($fmt: literal $(, $($arg: tt)+)?) => {
+        $crate::console::eprint(format_args!(concat!($fmt, "\n") $(, $($arg)+)?));
+    }
+}
+
+/// Exit code of a process whose `user_assert!` failed
+pub const ASSERT_FAILED_EXIT_CODE: i32 = 101;
+
+// user_assert! 失败时调用：打印断言所在的位置和表达式，然后以 ASSERT_FAILED_EXIT_CODE 退出，
+// 测试程序据此可以将断言失败与 panic（-SIGABRT）和其他异常退出区分开
+#[doc(hidden)]
+pub fn assert_failed(file: &str, line: u32, expr: &str, message: Option<fmt::Arguments>) -> ! {
+    print(format_args!(
+        "[pid {}] assertion failed at {}:{}: {}",
+        getpid(),
+        file,
+        line,
+        expr
+    ));
+    if let Some(message) = message {
+        print(format_args!(", {}", message));
+    }
+    print(format_args!("\n"));
+    exit(ASSERT_FAILED_EXIT_CODE)
+}
+
+#[macro_export]
+macro_rules! user_assert {
+    ($cond: expr $(,)?) => {
+        if !$cond {
+            $crate::console::assert_failed(file!(), line!(), stringify!($cond), None);
+        }
+    };
+    ($cond: expr, $($arg: tt)+) => {
+        if !$cond {
+            $crate::console::assert_failed(
+                file!(),
+                line!(),
+                stringify!($cond),
+                Some(format_args!($($arg)+)),
+            );
+        }
+    };
+}
+
 // 在用户库中将read进一步封装成每次能够从 标准输入 中获取一个字符的 getchar 函数
 pub fn getchar() -> u8 {
     let mut c = [0u8; 1];
     read(STDIN, &mut c);
     c[0]
 }
+
+/// Like `getchar`, but return `None` at once instead of waiting when no input is ready
+pub fn getchar_nb() -> Option<u8> {
+    // 只在这一次读的期间给标准输入加上 NONBLOCK ，getchar 仍然是阻塞的
+    let flags = fcntl(STDIN, F_GETFL, 0);
+    if flags < 0 {
+        return None;
+    }
+    let nonblock = flags as usize | OpenFlags::NONBLOCK.bits() as usize;
+    fcntl(STDIN, F_SETFL, nonblock);
+    let mut c = [0u8; 1];
+    let len = read(STDIN, &mut c);
+    fcntl(STDIN, F_SETFL, flags as usize);
+    (len == 1).then_some(c[0])
+}
@@ -0,0 +1,143 @@
+//! Global allocator of the user library
+// 在 Rust 中可变长字符串类型 String 是基于动态内存分配的。因此本章我们还要在用户库 user_lib 中支持动态内存分配
+// 静态的堆空间只是启动时使用的一小块初始内存，用尽之后再通过 sbrk 向内核申请
+//
+// 进程只有一个执行流，信号处理例程却可能在主流程正持有堆的锁时被调用，这时在处理例程中再去获取这把锁就会永远等待。
+// 因此分配器只尝试获取锁：拿不到锁说明当前正处在打断了分配器的信号处理例程中，改为从单独的一小块 SIGNAL_HEAP 中分配；
+// 在处理例程中释放主堆上的内存时同样拿不到锁，先把它记在 DEFERRED 中，等下一次拿到主堆的锁时再真正释放。
+// 使用时的限制：
+// - 信号处理例程在主流程位于分配器中时只能使用 SIGNAL_HEAP_SIZE 字节，用尽之后分配失败
+// - 信号处理例程再被信号打断时，内层处理例程的分配会失败，释放的内存会泄漏；DEFERRED 已满时释放的内存也会泄漏
+// - fork 出的子进程复制了父进程的整个堆（包括锁的状态），因此不应在信号处理例程中 fork ；exec 会重新初始化堆
+use crate::syscall::sys_sbrk;
+use buddy_system_allocator::{Heap, LockedHeap};
+use core::alloc::{GlobalAlloc, Layout};
+use core::ptr::NonNull;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+const USER_HEAP_SIZE: usize = 16384;
+// 每次通过 sbrk 扩展堆空间的最小字节数
+const USER_HEAP_GROW_SIZE: usize = 0x10000;
+const SIGNAL_HEAP_SIZE: usize = 4096;
+const DEFERRED_FREES: usize = 16;
+
+static mut HEAP_SPACE: [u8; USER_HEAP_SIZE] = [0; USER_HEAP_SIZE];
+static mut SIGNAL_HEAP_SPACE: [u8; SIGNAL_HEAP_SIZE] = [0; SIGNAL_HEAP_SIZE];
+
+static HEAP: LockedHeap = LockedHeap::empty();
+static SIGNAL_HEAP: LockedHeap = LockedHeap::empty();
+
+/// A free of main-heap memory postponed until the heap lock is available
+struct DeferredFree {
+    // 0 表示空闲，CLAIMED 表示正在写入 size 和 align
+    ptr: AtomicUsize,
+    size: AtomicUsize,
+    align: AtomicUsize,
+}
+
+const CLAIMED: usize = 1;
+#[allow(clippy::declare_interior_mutable_const)]
+const NO_FREE: DeferredFree = DeferredFree {
+    ptr: AtomicUsize::new(0),
+    size: AtomicUsize::new(0),
+    align: AtomicUsize::new(0),
+};
+static DEFERRED: [DeferredFree; DEFERRED_FREES] = [NO_FREE; DEFERRED_FREES];
+
+/// Buddy allocator that grows through `sbrk` when running out of memory
+struct GrowingHeap;
+
+#[global_allocator]
+static GLOBAL_HEAP: GrowingHeap = GrowingHeap;
+
+/// Give the heaps their initial static memory
+pub(crate) fn init_heap() {
+    unsafe {
+        HEAP.lock()
+            .init(HEAP_SPACE.as_ptr() as usize, USER_HEAP_SIZE);
+        SIGNAL_HEAP
+            .lock()
+            .init(SIGNAL_HEAP_SPACE.as_ptr() as usize, SIGNAL_HEAP_SIZE);
+    }
+}
+
+fn in_signal_heap(ptr: *mut u8) -> bool {
+    let start = unsafe { SIGNAL_HEAP_SPACE.as_ptr() as usize };
+    (start..start + SIGNAL_HEAP_SIZE).contains(&(ptr as usize))
+}
+
+fn defer_free(ptr: *mut u8, layout: Layout) {
+    for slot in DEFERRED.iter() {
+        if slot
+            .ptr
+            .compare_exchange(0, CLAIMED, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+        {
+            slot.size.store(layout.size(), Ordering::Relaxed);
+            slot.align.store(layout.align(), Ordering::Relaxed);
+            slot.ptr.store(ptr as usize, Ordering::Release);
+            return;
+        }
+    }
+}
+
+// 主流程在释放的过程中被打断时，处理例程看到的仍是尚未清空的位置，不会重复使用它
+unsafe fn free_deferred(heap: &mut Heap) {
+    for slot in DEFERRED.iter() {
+        let ptr = slot.ptr.load(Ordering::Acquire);
+        if ptr > CLAIMED {
+            let layout = Layout::from_size_align_unchecked(
+                slot.size.load(Ordering::Relaxed),
+                slot.align.load(Ordering::Relaxed),
+            );
+            heap.dealloc(NonNull::new_unchecked(ptr as *mut u8), layout);
+            slot.ptr.store(0, Ordering::Release);
+        }
+    }
+}
+
+unsafe fn alloc_growing(heap: &mut Heap, layout: Layout) -> *mut u8 {
+    if let Ok(ptr) = heap.alloc(layout) {
+        return ptr.as_ptr();
+    }
+    // 伙伴系统只能分配按大小对齐的 2 的幂大小的块，扩展两倍大小才能保证新加入的内存中一定有一个足够大的对齐的块
+    let block = layout.size().max(layout.align()).next_power_of_two();
+    let size = (block * 2).max(USER_HEAP_GROW_SIZE);
+    if size > i32::MAX as usize {
+        return core::ptr::null_mut();
+    }
+    let old_brk = sys_sbrk(size as i32);
+    if old_brk < 0 {
+        return core::ptr::null_mut();
+    }
+    heap.add_to_heap(old_brk as usize, old_brk as usize + size);
+    heap.alloc(layout)
+        .map_or(core::ptr::null_mut(), |ptr| ptr.as_ptr())
+}
+
+unsafe impl GlobalAlloc for GrowingHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if let Some(mut heap) = HEAP.try_lock() {
+            free_deferred(&mut heap);
+            return alloc_growing(&mut heap, layout);
+        }
+        match SIGNAL_HEAP.try_lock() {
+            Some(mut heap) => heap
+                .alloc(layout)
+                .map_or(core::ptr::null_mut(), |ptr| ptr.as_ptr()),
+            None => core::ptr::null_mut(),
+        }
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if in_signal_heap(ptr) {
+            if let Some(mut heap) = SIGNAL_HEAP.try_lock() {
+                heap.dealloc(NonNull::new_unchecked(ptr), layout);
+            }
+        } else if let Some(mut heap) = HEAP.try_lock() {
+            heap.dealloc(NonNull::new_unchecked(ptr), layout);
+            free_deferred(&mut heap);
+        } else {
+            defer_free(ptr, layout);
+        }
+    }
+}
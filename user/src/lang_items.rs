@@ -1,18 +1,64 @@
-use super::{getpid, kill, SignalFlags};
+use super::{abort, getpid};
+use core::arch::asm;
+
+/// max depth of the backtrace, in case the stack is corrupted
+const MAX_BACKTRACE_DEPTH: usize = 32;
+
+// _start 的帧指针，它之下才是应用自己的栈帧，回溯到这里为止
+static mut STACK_BASE: usize = 0;
 
 #[panic_handler]
 fn panic_handler(panic_info: &core::panic::PanicInfo) -> ! {
     let err = panic_info.message().unwrap();
+    let pid = getpid();
     if let Some(location) = panic_info.location() {
         println!(
-            "Panicked at {}:{}, {}",
+            "[pid {}] Panicked at {}:{}, {}",
+            pid,
             location.file(),
             location.line(),
             err
         );
     } else {
-        println!("Panicked: {}", err);
+        println!("[pid {}] Panicked: {}", pid, err);
+    }
+    backtrace();
+    abort()
+}
+
+/// Remember the frame pointer of the caller as the base of the user stack,
+/// must be inlined into `_start`
+#[inline(always)]
+pub(crate) fn record_stack_base() {
+    unsafe {
+        asm!("mv {}, fp", out(reg) STACK_BASE);
+    }
+}
+
+// 用户程序在编译时开启了帧指针（-Cforce-frame-pointers=yes），每个栈帧中 fp-8 处保存返回地址 ra ，fp-16 处保存调用者的 fp 。
+// 运行时没有符号表，因此只打印返回地址，可以离线借助 ELF 文件（如 addr2line）将它们解析为函数名
+/// Walk the saved frame pointers on the user stack and print return addresses
+fn backtrace() {
+    let mut fp: usize;
+    unsafe {
+        asm!("mv {}, fp", out(reg) fp);
+    }
+    let base = unsafe { STACK_BASE };
+    println!("backtrace:");
+    for depth in 0..MAX_BACKTRACE_DEPTH {
+        // 调用者的 fp 只会越来越大，到达 _start 的栈帧时停止，之上已经不是应用的栈帧了
+        if fp % core::mem::size_of::<usize>() != 0 || fp < 16 || fp >= base {
+            break;
+        }
+        let ra = unsafe { *((fp - 8) as *const usize) };
+        let prev_fp = unsafe { *((fp - 16) as *const usize) };
+        if ra == 0 {
+            break;
+        }
+        println!("  #{:<2} ra = {:#x}", depth, ra);
+        if prev_fp <= fp {
+            break;
+        }
+        fp = prev_fp;
     }
-    kill(getpid() as usize, SignalFlags::SIGABRT.bits());
-    unreachable!()
 }
@@ -5,6 +5,7 @@
 
 #[macro_use]
 pub mod console;
+mod heap;
 mod lang_items;
 mod syscall;
 
@@ -12,17 +13,30 @@ extern crate alloc;
 #[macro_use]
 extern crate bitflags;
 
+use alloc::string::String;
+use alloc::vec;
 use alloc::vec::Vec;
-use buddy_system_allocator::LockedHeap;
 use syscall::*;
-
-// 在 Rust 中可变长字符串类型 String 是基于动态内存分配的。因此本章我们还要在用户库 user_lib 中支持动态内存分配
-const USER_HEAP_SIZE: usize = 32768;
-
-static mut HEAP_SPACE: [u8; USER_HEAP_SIZE] = [0; USER_HEAP_SIZE];
-
-#[global_allocator]
-static HEAP: LockedHeap = LockedHeap::empty();
+pub use console::{flush_stdout, set_stdout_buffered, stdout_write_count, ASSERT_FAILED_EXIT_CODE};
+pub use syscall::{
+    SYSCALL_CHDIR, SYSCALL_CHMOD, SYSCALL_CLOSE, SYSCALL_COPY_FILE_RANGE, SYSCALL_CRASH,
+    SYSCALL_DUMP_MAPS, SYSCALL_DUP, SYSCALL_DUP2, SYSCALL_DUP3, SYSCALL_EXEC, SYSCALL_EXIT,
+    SYSCALL_FADVISE, SYSCALL_FCHDIR, SYSCALL_FCHMOD, SYSCALL_FCNTL, SYSCALL_FLOCK, SYSCALL_FORK,
+    SYSCALL_FSTAT, SYSCALL_FSTATAT, SYSCALL_FTRUNCATE, SYSCALL_GETCPU, SYSCALL_GETCWD,
+    SYSCALL_GETDENTS, SYSCALL_GETPGID, SYSCALL_GETPID, SYSCALL_GETRUSAGE, SYSCALL_GETUID,
+    SYSCALL_GET_ROBUST_LIST, SYSCALL_GET_TIME, SYSCALL_HWCAP, SYSCALL_IOCTL, SYSCALL_IO_SETUP,
+    SYSCALL_IO_SUBMIT, SYSCALL_IRQ_STATS, SYSCALL_KILL, SYSCALL_LOADAVG, SYSCALL_MINCORE,
+    SYSCALL_MKDIR, SYSCALL_MKNOD, SYSCALL_MMAP, SYSCALL_MUNMAP, SYSCALL_NICE, SYSCALL_OPEN,
+    SYSCALL_OPENAT, SYSCALL_PAUSE, SYSCALL_PIPE, SYSCALL_POLL, SYSCALL_PRCTL, SYSCALL_PREADV,
+    SYSCALL_PROFILE, SYSCALL_PWRITEV, SYSCALL_READ, SYSCALL_READ_TIMEOUT, SYSCALL_REGISTER_LOWMEM,
+    SYSCALL_RENAME, SYSCALL_RMDIR, SYSCALL_SBRK, SYSCALL_SCHED_GETAFFINITY,
+    SYSCALL_SCHED_SETAFFINITY, SYSCALL_SECCOMP, SYSCALL_SETPGID, SYSCALL_SETSID, SYSCALL_SETTLS,
+    SYSCALL_SETUID, SYSCALL_SET_ROBUST_LIST, SYSCALL_SIGACTION, SYSCALL_SIGNALFD,
+    SYSCALL_SIGPROCMASK, SYSCALL_SIGRETURN, SYSCALL_STATFS, SYSCALL_STATX, SYSCALL_SYNC,
+    SYSCALL_SYNCFS, SYSCALL_TGKILL, SYSCALL_TIMERFD_CREATE, SYSCALL_TIMERFD_SETTIME,
+    SYSCALL_TRUNCATE, SYSCALL_UNLINK, SYSCALL_VMSPLICE, SYSCALL_WAITPID, SYSCALL_WRITE,
+    SYSCALL_YIELD,
+};
 
 #[alloc_error_handler]
 pub fn handle_alloc_error(layout: core::alloc::Layout) -> ! {
@@ -33,31 +47,48 @@ pub fn handle_alloc_error(layout: core::alloc::Layout) -> ! {
 #[no_mangle]
 // 使用 Rust 的宏将 _start 这段代码编译后的汇编代码中放在一个名为 .text.entry 的代码段中，方便我们在后续链接的时候调整它的位置使得它能够作为用户库的入口。
 #[link_section = ".text.entry"]
-pub extern "C" fn _start(argc: usize, argv: usize) -> ! {
+pub extern "C" fn _start(argc: usize, argv: usize, envp: usize) -> ! {
+    lang_items::record_stack_base();
+    heap::init_heap();
     unsafe {
-        HEAP.lock()
-            .init(HEAP_SPACE.as_ptr() as usize, USER_HEAP_SIZE);
+        ENVP = envp;
     }
     // 在应用第一次进入用户态的时候，我们放在 Trap 上下文 a0/a1 两个寄存器中的内容可以被用户库中的入口函数以参数的形式接收：
-    let mut v: Vec<&'static str> = Vec::new();
-    for i in 0..argc {
-        let str_start =
-            unsafe { ((argv + i * core::mem::size_of::<usize>()) as *const usize).read_volatile() };
-        let len = (0usize..)
-            .find(|i| unsafe { ((str_start + *i) as *const u8).read_volatile() == 0 })
-            .unwrap();
-        v.push(
-            core::str::from_utf8(unsafe {
-                core::slice::from_raw_parts(str_start as *const u8, len)
-            })
-            .unwrap(),
-        );
-    }
+    let v: Vec<&'static str> = (0..argc).map(|i| str_at(argv, i)).collect();
     exit(main(argc, v.as_slice()));
     // exit(main());
     // panic!("unreachable after sys_exit!");
 }
 
+// 内核把环境变量压在用户栈上，与命令行参数一样是以 0 结尾的字符串指针数组，a2 寄存器中是数组的起始地址
+static mut ENVP: usize = 0;
+
+// 字符串指针数组 array 中第 i 个字符串
+fn str_at(array: usize, i: usize) -> &'static str {
+    let str_start =
+        unsafe { ((array + i * core::mem::size_of::<usize>()) as *const usize).read_volatile() };
+    let len = (0usize..)
+        .find(|i| unsafe { ((str_start + *i) as *const u8).read_volatile() == 0 })
+        .unwrap();
+    core::str::from_utf8(unsafe { core::slice::from_raw_parts(str_start as *const u8, len) })
+        .unwrap()
+}
+
+/// 当前进程的所有环境变量，每一项的形式为 NAME=value
+pub fn environ() -> impl Iterator<Item = &'static str> {
+    let envp = unsafe { ENVP };
+    (0..)
+        .take_while(move |i| unsafe {
+            ((envp + i * core::mem::size_of::<usize>()) as *const usize).read_volatile() != 0
+        })
+        .map(move |i| str_at(envp, i))
+}
+
+/// 环境变量 name 的值，不存在时返回 None
+pub fn getenv(name: &str) -> Option<&'static str> {
+    environ().find_map(|var| var.strip_prefix(name)?.strip_prefix('='))
+}
+
 // 使用 Rust 的宏将其函数符号 main 标志为弱链接
 // 这样在最后链接的时候，虽然在 lib.rs 和 bin 目录下的某个应用程序都有 main 符号，但由于 lib.rs 中的 main 符号是弱链接，链接器会使用 bin 目录下的应用主逻辑作为 main 。这里我们主要是进行某种程度上的保护，如果在 bin 目录下找不到任何 main ，那么编译也能够通过，但会在运行时报错。
 // 为了支持上述这些链接操作，我们需要在 lib.rs 的开头加入：#![feature(linkage)]
@@ -74,14 +105,390 @@ bitflags! {
         const RDWR = 1 << 1;
         const CREATE = 1 << 9;
         const TRUNC = 1 << 10;
+        const NONBLOCK = 1 << 11;
+        const SYNC = 1 << 12;
+        const CLOEXEC = 1 << 19;
     }
 }
 pub fn dup(fd: usize) -> isize {
     sys_dup(fd)
 }
+// 文件锁的操作
+pub const LOCK_SH: usize = 1;
+pub const LOCK_EX: usize = 2;
+pub const LOCK_NB: usize = 4;
+pub const LOCK_UN: usize = 8;
+pub fn flock(fd: usize, op: usize) -> isize {
+    sys_flock(fd, op)
+}
+pub fn ftruncate(fd: usize, len: usize) -> isize {
+    sys_ftruncate(fd, len)
+}
+pub fn truncate(path: &str, len: usize) -> isize {
+    sys_truncate(path, len)
+}
+pub fn rename(old: &str, new: &str) -> isize {
+    sys_rename(old, new)
+}
+/// 文件的状态
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Stat {
+    /// 索引节点编号
+    pub ino: usize,
+    /// 文件类型和权限位
+    pub mode: u32,
+    /// 文件大小（字节）
+    pub size: usize,
+}
+// Stat::mode 中的文件类型
+pub const S_IFREG: u32 = 0o100000;
+pub const S_IFDIR: u32 = 0o040000;
+pub const S_IFCHR: u32 = 0o020000;
+/// 文件的扩展状态，mask 给出内核实际填写了的字段
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Statx {
+    pub mask: u32,
+    /// 适合的 I/O 大小（字节），总是有效
+    pub blksize: u32,
+    pub ino: u64,
+    /// 文件类型和权限位
+    pub mode: u32,
+    /// 文件大小（字节）
+    pub size: u64,
+    /// 占用的 512 字节块数，包括索引块
+    pub blocks: u64,
+    pub atime_ms: u64,
+    pub mtime_ms: u64,
+    pub ctime_ms: u64,
+    /// 内核启动或文件创建以来读写的字节数，所有打开实例一起累计
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+}
+// statx 的 mask 中的各位
+pub const STATX_TYPE: u32 = 0x1;
+pub const STATX_MODE: u32 = 0x2;
+pub const STATX_ATIME: u32 = 0x20;
+pub const STATX_MTIME: u32 = 0x40;
+pub const STATX_CTIME: u32 = 0x80;
+pub const STATX_INO: u32 = 0x100;
+pub const STATX_SIZE: u32 = 0x200;
+pub const STATX_BLOCKS: u32 = 0x400;
+// 非标准的位，请求 bytes_read 和 bytes_written
+pub const STATX_IO: u32 = 0x1000_0000;
+/// 获取已打开文件 fd 的扩展状态中 mask 指定的字段
+pub fn statx(fd: usize, mask: u32, buf: &mut Statx) -> isize {
+    sys_statx(fd, mask, buf as *mut Statx)
+}
+// 设备号：高 8 位是主设备号，低 8 位是次设备号
+pub const fn makedev(major: u32, minor: u32) -> u32 {
+    (major << 8) | minor
+}
+pub fn mknod(path: &str, mode: u32, dev: u32) -> isize {
+    sys_mknod(path, mode, dev)
+}
+/// 路径中的某一级不存在
+pub const ENOENT: isize = 2;
+/// 要创建的路径已经存在
+pub const EEXIST: isize = 17;
+/// 路径中作为目录的某一级不是目录
+pub const ENOTDIR: isize = 20;
+/// 要删除的文件是一个目录
+pub const EISDIR: isize = 21;
+/// 没有空闲的索引节点
+pub const ENOSPC: isize = 28;
+/// 要删除的目录不是空的
+pub const ENOTEMPTY: isize = 39;
+pub fn mkdir(path: &str, mode: u32) -> isize {
+    sys_mkdir(path, mode)
+}
+/// 依次创建 path 中每一级不存在的目录，已经存在的目录被跳过；
+/// 某一级已经存在但不是目录时返回 -ENOTDIR
+pub fn mkdir_p(path: &str, mode: u32) -> isize {
+    let path = path.trim_end_matches('\0');
+    let mut prefix = String::new();
+    for component in path.split('/').filter(|c| !c.is_empty()) {
+        if path.starts_with('/') || !prefix.is_empty() {
+            prefix.push('/');
+        }
+        prefix.push_str(component);
+        let mut cpath = prefix.clone();
+        cpath.push('\0');
+        match mkdir(cpath.as_str(), mode) {
+            0 => {}
+            ret if ret == -EEXIST => {
+                let fd = open(cpath.as_str(), OpenFlags::RDONLY);
+                if fd < 0 {
+                    return -ENOTDIR;
+                }
+                let mut stat = Stat::default();
+                let ret = fstat(fd as usize, &mut stat);
+                close(fd as usize);
+                if ret != 0 || stat.mode & S_IFDIR == 0 {
+                    return -ENOTDIR;
+                }
+            }
+            ret => return ret,
+        }
+    }
+    0
+}
+pub fn fstat(fd: usize, buf: &mut Stat) -> isize {
+    sys_fstat(fd, buf as *mut Stat)
+}
+pub fn fstatat(dirfd: isize, path: &str, buf: &mut Stat) -> isize {
+    sys_fstatat(dirfd, path, buf as *mut Stat)
+}
+pub fn chmod(path: &str, mode: u32) -> isize {
+    sys_chmod(path, mode)
+}
+pub fn fchmod(fd: usize, mode: u32) -> isize {
+    sys_fchmod(fd, mode)
+}
+/// 目录项的索引节点读不出来，类型未知
+pub const DT_UNKNOWN: u8 = 0;
+/// 目录项是字符设备
+pub const DT_CHR: u8 = 2;
+/// 目录项是目录
+pub const DT_DIR: u8 = 4;
+/// 目录项是普通文件
+pub const DT_REG: u8 = 8;
+/// getdents 读到的目录项
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Dirent {
+    /// 索引节点编号
+    pub ino: u64,
+    /// 文件类型，为 DT_* 之一
+    pub d_type: u8,
+    /// 以 \0 结尾的文件名
+    pub name: [u8; 28],
+}
+impl Dirent {
+    /// 文件名
+    pub fn name(&self) -> &str {
+        let len = self
+            .name
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(self.name.len());
+        core::str::from_utf8(&self.name[..len]).unwrap()
+    }
+}
+pub fn getdents(fd: usize, dirents: &mut [Dirent]) -> isize {
+    sys_getdents(fd, dirents)
+}
+// 每次 getdents 最多读入的目录项数，readdir 先从缓冲区中取，取完了才再次陷入内核
+const DIR_BATCH: usize = 16;
+/// 用 opendir 打开的目录
+pub struct Dir {
+    fd: usize,
+    dirents: Vec<Dirent>,
+    // 缓冲区中下一个要返回的目录项和有效的目录项数
+    pos: usize,
+    len: usize,
+}
+/// readdir 读到的目录项
+#[derive(Debug, Clone)]
+pub struct DirEntry {
+    /// 文件名
+    pub name: String,
+    /// 索引节点编号
+    pub ino: u64,
+    /// 文件类型，为 DT_* 之一
+    pub d_type: u8,
+}
+/// 打开一个目录，路径不存在或者不是目录时返回 None
+pub fn opendir(path: &str) -> Option<Dir> {
+    let fd = open(path, OpenFlags::RDONLY);
+    if fd < 0 {
+        return None;
+    }
+    let fd = fd as usize;
+    let mut stat = Stat::default();
+    if fstat(fd, &mut stat) != 0 || stat.mode & S_IFDIR == 0 {
+        close(fd);
+        return None;
+    }
+    Some(Dir {
+        fd,
+        dirents: vec![Dirent::default(); DIR_BATCH],
+        pos: 0,
+        len: 0,
+    })
+}
+/// 读出目录中的下一个目录项，读到目录末尾时返回 None
+pub fn readdir(dir: &mut Dir) -> Option<DirEntry> {
+    if dir.pos == dir.len {
+        let n = getdents(dir.fd, &mut dir.dirents);
+        if n <= 0 {
+            return None;
+        }
+        dir.pos = 0;
+        dir.len = n as usize;
+    }
+    let dirent = &dir.dirents[dir.pos];
+    dir.pos += 1;
+    Some(DirEntry {
+        name: String::from(dirent.name()),
+        ino: dirent.ino,
+        d_type: dirent.d_type,
+    })
+}
+/// 关闭目录并释放缓冲区
+pub fn closedir(dir: Dir) -> isize {
+    close(dir.fd)
+}
+pub fn unlink(path: &str) -> isize {
+    sys_unlink(path)
+}
+pub fn rmdir(path: &str) -> isize {
+    sys_rmdir(path)
+}
+/// 删除目录 path 以及其中的所有内容。目录项在删除之前全部读出，边删除边读取可能会漏掉目录项；
+/// 不是目录的目录项一律直接 unlink ，不会跟随它去删除别处的内容。path 不是目录时返回 -ENOTDIR
+pub fn remove_dir_all(path: &str) -> isize {
+    let path = path.trim_end_matches('\0');
+    let mut cpath = String::from(path);
+    cpath.push('\0');
+    let Some(mut dir) = opendir(cpath.as_str()) else {
+        // 由 rmdir 区分路径不存在和不是目录这两种情况
+        return rmdir(cpath.as_str());
+    };
+    let mut entries = Vec::new();
+    while let Some(entry) = readdir(&mut dir) {
+        entries.push(entry);
+    }
+    closedir(dir);
+    for entry in entries {
+        let mut child = String::from(path.trim_end_matches('/'));
+        child.push('/');
+        child.push_str(entry.name.as_str());
+        let ret = if entry.d_type == DT_DIR {
+            remove_dir_all(child.as_str())
+        } else {
+            child.push('\0');
+            unlink(child.as_str())
+        };
+        if ret != 0 {
+            return ret;
+        }
+    }
+    rmdir(cpath.as_str())
+}
+pub fn getcwd(buf: &mut [u8]) -> isize {
+    sys_getcwd(buf)
+}
+pub fn chdir(path: &str) -> isize {
+    sys_chdir(path)
+}
+pub fn fchdir(fd: usize) -> isize {
+    sys_fchdir(fd)
+}
+// fadvise 的访问模式
+pub const POSIX_FADV_NORMAL: usize = 0;
+pub const POSIX_FADV_RANDOM: usize = 1;
+pub const POSIX_FADV_SEQUENTIAL: usize = 2;
+pub const POSIX_FADV_DONTNEED: usize = 4;
+pub fn fadvise(fd: usize, offset: usize, len: usize, advice: usize) -> isize {
+    sys_fadvise(fd, offset, len, advice)
+}
+/// 文件系统的使用情况
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FsStat {
+    /// 块的大小（字节）
+    pub block_size: usize,
+    /// 数据区域的总块数
+    pub total_blocks: usize,
+    /// 数据区域中空闲的块数
+    pub free_blocks: usize,
+    /// 索引节点的总数
+    pub total_inodes: usize,
+    /// 空闲的索引节点数
+    pub free_inodes: usize,
+}
+pub fn statfs(path: &str, buf: &mut FsStat) -> isize {
+    sys_statfs(path, buf as *mut FsStat)
+}
+/// 将所有文件系统中被修改过的块写回块设备
+pub fn sync() -> isize {
+    sys_sync()
+}
+/// 只写回 fd 所在的文件系统
+pub fn syncfs(fd: usize) -> isize {
+    sys_syncfs(fd)
+}
+pub fn copy_file_range(
+    in_fd: usize,
+    in_off: Option<&mut usize>,
+    out_fd: usize,
+    out_off: Option<&mut usize>,
+    len: usize,
+) -> isize {
+    sys_copy_file_range(in_fd, in_off, out_fd, out_off, len)
+}
+/// 模拟断电，还没有写回的修改全部丢失，需要以 crash_test 特性编译的内核
+pub fn crash() -> ! {
+    flush_stdout();
+    sys_crash();
+}
+pub fn dup2(old_fd: usize, new_fd: usize) -> isize {
+    sys_dup2(old_fd, new_fd)
+}
+pub fn dup3(old_fd: usize, new_fd: usize, flags: OpenFlags) -> isize {
+    sys_dup3(old_fd, new_fd, flags.bits)
+}
+pub const F_GETFL: usize = 3;
+pub const F_SETFL: usize = 4;
+/// 设置了 NONBLOCK 的文件描述符上没有数据可读时 read 返回 -EAGAIN 。这个标志不会被 dup 出的文件描述符继承
+pub fn fcntl(fd: usize, cmd: usize, arg: usize) -> isize {
+    sys_fcntl(fd, cmd, arg)
+}
+// 终端相关的 ioctl 命令
+pub const TIOCGPGRP: usize = 0x540F;
+pub const TIOCSPGRP: usize = 0x5410;
+pub const TIOCSTI: usize = 0x5412;
+pub const TCGETS: usize = 0x5401;
+pub const TCSETS: usize = 0x5402;
+/// 终端 lflag 中的 ISIG 位：置位时（cooked 模式）Ctrl-C/Ctrl-\/Ctrl-Z 会被转换为 SIGINT/SIGQUIT/SIGTSTP
+pub const ISIG: u32 = 0o1;
+/// 终端属性
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Termios {
+    pub lflag: u32,
+}
+pub fn ioctl(fd: usize, cmd: usize, arg: usize) -> isize {
+    sys_ioctl(fd, cmd, arg)
+}
+/// 获取终端 fd 的前台进程组，出错时返回 -1
+pub fn tcgetpgrp(fd: usize) -> isize {
+    let mut pgid: usize = 0;
+    match ioctl(fd, TIOCGPGRP, &mut pgid as *mut usize as usize) {
+        0 => pgid as isize,
+        err => err,
+    }
+}
+/// 将进程组 pgid 设置为终端 fd 的前台进程组
+pub fn tcsetpgrp(fd: usize, pgid: usize) -> isize {
+    ioctl(fd, TIOCSPGRP, &pgid as *const usize as usize)
+}
+pub fn tcgetattr(fd: usize, termios: &mut Termios) -> isize {
+    ioctl(fd, TCGETS, termios as *mut Termios as usize)
+}
+pub fn tcsetattr(fd: usize, termios: &Termios) -> isize {
+    ioctl(fd, TCSETS, termios as *const Termios as usize)
+}
 pub fn open(path: &str, flags: OpenFlags) -> isize {
     sys_open(path, flags.bits)
 }
+/// openat 和 fstatat 的 dirfd 取这个值时，相对路径基于当前的工作目录解析
+pub const AT_FDCWD: isize = -100;
+pub fn openat(dirfd: isize, path: &str, flags: OpenFlags) -> isize {
+    sys_openat(dirfd, path, flags.bits)
+}
 pub fn close(fd: usize) -> isize {
     sys_close(fd)
 }
@@ -91,34 +498,495 @@ pub fn pipe(pipe_fd: &mut [usize]) -> isize {
 pub fn read(fd: usize, buf: &mut [u8]) -> isize {
     sys_read(fd, buf)
 }
+/// 系统调用被信号打断时返回 -EINTR
+pub const EINTR: isize = 4;
+/// 非阻塞的 read 没有数据可读时返回 -EAGAIN
+pub const EAGAIN: isize = 11;
+pub fn read_timeout(fd: usize, buf: &mut [u8], timeout_ms: usize) -> isize {
+    sys_read_timeout(fd, buf, timeout_ms)
+}
+/// preadv/pwritev 的一个缓冲区
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct IoVec {
+    pub base: usize,
+    pub len: usize,
+}
+pub fn preadv(fd: usize, bufs: &mut [&mut [u8]], offset: usize) -> isize {
+    let iov: Vec<IoVec> = bufs
+        .iter_mut()
+        .map(|buf| IoVec {
+            base: buf.as_mut_ptr() as usize,
+            len: buf.len(),
+        })
+        .collect();
+    sys_preadv(fd, &iov, offset)
+}
+pub fn pwritev(fd: usize, bufs: &[&[u8]], offset: usize) -> isize {
+    let iov: Vec<IoVec> = bufs
+        .iter()
+        .map(|buf| IoVec {
+            base: buf.as_ptr() as usize,
+            len: buf.len(),
+        })
+        .collect();
+    sys_pwritev(fd, &iov, offset)
+}
+/// 有数据可读
+pub const POLLIN: u16 = 0x1;
+/// 写入不会阻塞
+pub const POLLOUT: u16 = 0x4;
+/// 出现错误，总是报告
+pub const POLLERR: u16 = 0x8;
+/// 所有写端都已关闭，总是报告
+pub const POLLHUP: u16 = 0x10;
+/// fd 没有打开，总是报告
+pub const POLLNVAL: u16 = 0x20;
+/// poll 等待的文件描述符，fd 为负数时被忽略
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PollFd {
+    pub fd: i32,
+    pub events: u16,
+    pub revents: u16,
+}
+pub fn poll(fds: &mut [PollFd], timeout_ms: isize) -> isize {
+    sys_poll(fds, timeout_ms)
+}
+pub fn timerfd_create() -> isize {
+    sys_timerfd_create()
+}
+/// 定时器在 value_ms 毫秒之后第一次到期，之后每 interval_ms 毫秒到期一次，interval_ms 为 0 时只到期一次
+pub fn timerfd_settime(fd: usize, interval_ms: usize, value_ms: usize) -> isize {
+    sys_timerfd_settime(fd, interval_ms, value_ms)
+}
+/// 完成队列已满时 io_submit 返回 -EBUSY ，要删除的目录是根目录或者某个进程的工作目录时 rmdir 也返回 -EBUSY
+pub const EBUSY: isize = 16;
+pub const IORING_OP_READ: u32 = 22;
+pub const IORING_OP_WRITE: u32 = 23;
+/// 提交队列中的读写请求，与单独调用 read/write 时的参数相同
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IoSqe {
+    pub opcode: u32,
+    pub fd: u32,
+    pub buf: usize,
+    pub len: usize,
+    /// 原样复制到请求的结果中，用来区分不同的请求
+    pub user_data: usize,
+}
+/// 完成队列中的请求结果，result 与单独调用 read/write 时的返回值相同
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IoCqe {
+    pub user_data: usize,
+    pub result: isize,
+}
+// head 和 tail 是只增不减的计数，表项下标为计数对 N 取模。内核在 io_submit 中会修改提交队列的 head
+// 和完成队列的 tail ，因此读取它们时使用 volatile 读
+/// 批量读写的环形队列，登记之后不能移动
+#[repr(C)]
+pub struct IoRing<T: Copy + Default, const N: usize> {
+    head: usize,
+    tail: usize,
+    entries: [T; N],
+}
+impl<T: Copy + Default, const N: usize> IoRing<T, N> {
+    pub fn new() -> Self {
+        Self {
+            head: 0,
+            tail: 0,
+            entries: [T::default(); N],
+        }
+    }
+    pub fn len(&self) -> usize {
+        unsafe {
+            let head = core::ptr::read_volatile(&self.head);
+            let tail = core::ptr::read_volatile(&self.tail);
+            tail.wrapping_sub(head)
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// 在队尾放入一项，队列已满时返回 false
+    pub fn push(&mut self, entry: T) -> bool {
+        if self.len() == N {
+            return false;
+        }
+        let tail = self.tail;
+        self.entries[tail % N] = entry;
+        unsafe { core::ptr::write_volatile(&mut self.tail, tail.wrapping_add(1)) };
+        true
+    }
+    /// 从队头取出一项，队列为空时返回 None
+    pub fn pop(&mut self) -> Option<T> {
+        if self.is_empty() {
+            return None;
+        }
+        let head = self.head;
+        let entry = unsafe { core::ptr::read_volatile(&self.entries[head % N]) };
+        unsafe { core::ptr::write_volatile(&mut self.head, head.wrapping_add(1)) };
+        Some(entry)
+    }
+}
+impl<T: Copy + Default, const N: usize> Default for IoRing<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+pub fn io_setup<const N: usize>(sq: &mut IoRing<IoSqe, N>, cq: &mut IoRing<IoCqe, N>) -> isize {
+    sys_io_setup(
+        sq as *mut IoRing<IoSqe, N> as usize,
+        cq as *mut IoRing<IoCqe, N> as usize,
+        N,
+    )
+}
+pub fn io_submit() -> isize {
+    sys_io_submit()
+}
 // 将syscall中的系统调用在用户库 user_lib 中进一步封装，从而更加接近在 Linux 等平台的实际系统调用接口：
 pub fn write(fd: usize, buf: &[u8]) -> isize {
     sys_write(fd, buf)
 }
+pub fn vmsplice(fd: usize, buf: &mut [u8]) -> isize {
+    sys_vmsplice(fd, buf)
+}
 pub fn exit(exit_code: i32) -> ! {
+    flush_stdout();
     sys_exit(exit_code);
 }
 // yield 是 Rust 的关键字，因此我们只能将应用直接调用的接口命名为 yield_
 pub fn yield_() -> isize {
     sys_yield()
 }
+/// 把自己的 nice 值增加 delta（限制在 -20 到 19 之间），返回新的 nice 值。fork 出的子进程继承 nice 值
+pub fn nice(delta: isize) -> isize {
+    sys_nice(delta)
+}
+/// 限制进程 pid（0 表示自己）只在 mask 中的 hart 上运行，fork 出的子进程继承这个掩码
+pub fn sched_setaffinity(pid: usize, mask: usize) -> isize {
+    sys_sched_setaffinity(pid, mask)
+}
+pub fn sched_getaffinity(pid: usize) -> isize {
+    sys_sched_getaffinity(pid)
+}
+/// robust futex 链表头，布局与 Linux 的 struct robust_list_head 相同
+#[repr(C)]
+#[derive(Debug, Default)]
+pub struct RobustListHead {
+    /// 第一个持有的锁的链表项，链表为空时指向链表头自己
+    pub list: usize,
+    /// 锁的 futex 字相对于链表项的偏移
+    pub futex_offset: isize,
+    /// 正在加锁或者解锁的链表项
+    pub list_op_pending: usize,
+}
+/// 登记 robust futex 链表头。内核还没有 futex ，进程退出时无法处理其中的锁，因此目前总是返回 -1
+pub fn set_robust_list(head: &RobustListHead) -> isize {
+    sys_set_robust_list(
+        head as *const RobustListHead as usize,
+        core::mem::size_of::<RobustListHead>(),
+    )
+}
+/// 获取进程 pid（0 表示自己）登记的 robust futex 链表头的地址和大小
+pub fn get_robust_list(pid: usize, head: &mut usize, len: &mut usize) -> isize {
+    sys_get_robust_list(pid, head as *mut usize, len as *mut usize)
+}
+/// 获取当前运行的 hart 编号和 NUMA 节点编号，不需要的一项传 None
+pub fn getcpu(cpu: Option<&mut u32>, node: Option<&mut u32>) -> isize {
+    let cpu = cpu.map_or(core::ptr::null_mut(), |cpu| cpu as *mut u32);
+    let node = node.map_or(core::ptr::null_mut(), |node| node as *mut u32);
+    sys_getcpu(cpu, node)
+}
 
 pub fn get_time() -> isize {
     sys_get_time()
 }
 
-// pub fn sbrk(size: i32) -> isize {
-//     sys_sbrk(size)
-// }
+/// xorshift64* pseudo-random number generator for tests.
+///
+/// The same seed always yields the same sequence, so failures can be reproduced.
+/// It is NOT cryptographically secure.
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // 用 splitmix64 打散种子，使相近的种子也得到无关的序列；xorshift 的状态不能为 0
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        Self { state: z.max(1) }
+    }
+    /// Seeded with the current time, so every run differs
+    pub fn from_time() -> Self {
+        Self::new(get_time() as u64)
+    }
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state = x;
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+    /// Uniformly distributed in `[lo, hi)`, `lo` must be less than `hi`
+    pub fn next_range(&mut self, lo: u64, hi: u64) -> u64 {
+        assert!(lo < hi);
+        let span = hi - lo;
+        // 2^64 mod span 个最小的值会让取模的结果不均匀，丢弃它们重新生成
+        let threshold = span.wrapping_neg() % span;
+        loop {
+            let x = self.next_u64();
+            if x >= threshold {
+                return lo + x % span;
+            }
+        }
+    }
+}
+
+// hwcap 返回的位图与 Linux 的 AT_HWCAP 相同：扩展字母 x 可用时第 x - 'a' 位为 1 。
+// 内核不保存用户的浮点寄存器，所以即使硬件有 FPU ， F 和 D 两位也不会被置上
+pub const HWCAP_I: usize = 1 << (b'i' - b'a');
+pub const HWCAP_M: usize = 1 << (b'm' - b'a');
+pub const HWCAP_A: usize = 1 << (b'a' - b'a');
+pub const HWCAP_F: usize = 1 << (b'f' - b'a');
+pub const HWCAP_D: usize = 1 << (b'd' - b'a');
+pub const HWCAP_C: usize = 1 << (b'c' - b'a');
+pub fn hwcap() -> usize {
+    sys_hwcap() as usize
+}
+
+pub fn sbrk(size: i32) -> isize {
+    sys_sbrk(size)
+}
+
+pub const PROT_READ: usize = 1 << 0;
+pub const PROT_WRITE: usize = 1 << 1;
+pub const PROT_EXEC: usize = 1 << 2;
+/// start 为 0 时由内核选择地址，成功时返回这个地址；否则成功时返回 0
+pub fn mmap(start: usize, len: usize, prot: usize) -> isize {
+    sys_mmap(start, len, prot, None)
+}
+/// 与 mmap 相同，但这段映射在 dump_maps 中显示为 [mmap:name] ，name 需要以 \0 结尾
+pub fn mmap_named(start: usize, len: usize, prot: usize, name: &str) -> isize {
+    sys_mmap(start, len, prot, Some(name))
+}
+pub fn munmap(start: usize, len: usize) -> isize {
+    sys_munmap(start, len)
+}
+
+const PAGE_SIZE: usize = 4096;
+/// vec 中的每个字节对应一个页面，驻留在内存中时为 1 ，vec 的长度不能少于页面数
+pub fn mincore(start: usize, len: usize, vec: &mut [u8]) -> isize {
+    if vec.len() < len.div_ceil(PAGE_SIZE) {
+        return -1;
+    }
+    sys_mincore(start, len, vec)
+}
+
+pub const RUSAGE_SELF: isize = 0;
+pub const RUSAGE_CHILDREN: isize = -1;
+/// 资源使用统计
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Rusage {
+    /// 最大驻留集大小（KiB）
+    pub maxrss: usize,
+    /// 不需要 I/O 的缺页次数
+    pub minflt: usize,
+    /// 需要 I/O 的缺页次数
+    pub majflt: usize,
+    /// 主动上下文切换次数
+    pub nvcsw: usize,
+    /// 被动上下文切换次数
+    pub nivcsw: usize,
+}
+pub fn getrusage(who: isize, usage: &mut Rusage) -> isize {
+    sys_getrusage(who, usage as *mut Rusage)
+}
+
+/// LoadAvg::loads 中定点数的小数位数
+pub const LOAD_FSHIFT: usize = 16;
+/// 系统负载
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LoadAvg {
+    /// 最近 1 秒、5 秒和 15 秒内就绪和正在运行的任务数的平均值，是有 LOAD_FSHIFT 位小数的定点数
+    pub loads: [usize; 3],
+    /// 所有 hart 运行任务的总时间（毫秒）
+    pub busy_ms: usize,
+    /// 所有 hart 空闲的总时间（毫秒）
+    pub idle_ms: usize,
+}
+pub fn loadavg(load: &mut LoadAvg) -> isize {
+    sys_loadavg(load as *mut LoadAvg)
+}
+/// 时钟中断的延迟统计
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IrqStats {
+    /// 处理过的时钟中断数
+    pub count: usize,
+    /// 最大延迟（微秒）
+    pub max_us: usize,
+    /// 平均延迟（微秒）
+    pub avg_us: usize,
+}
+pub fn irq_stats(stats: &mut IrqStats, reset: bool) -> isize {
+    sys_irq_stats(stats as *mut IrqStats, reset)
+}
+
+/// 内核代码段被平均分成的区间数，与内核中的 PROFILE_BUCKETS 相同
+pub const PROFILE_BUCKETS: usize = 256;
+/// 内核性能剖析的结果
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ProfileStats {
+    /// 内核代码段的起始地址，即第 0 个区间的起始地址
+    pub text_start: usize,
+    /// 每个区间的字节数
+    pub bucket_size: usize,
+    /// 在用户态采到的样本数
+    pub user_samples: usize,
+    /// 在内核态采到的样本数
+    pub kernel_samples: usize,
+    /// 每个区间中的内核态样本数
+    pub buckets: [usize; PROFILE_BUCKETS],
+}
+impl Default for ProfileStats {
+    fn default() -> Self {
+        Self {
+            text_start: 0,
+            bucket_size: 0,
+            user_samples: 0,
+            kernel_samples: 0,
+            buckets: [0; PROFILE_BUCKETS],
+        }
+    }
+}
+impl ProfileStats {
+    /// 第 i 个区间覆盖的内核地址范围
+    pub fn bucket_range(&self, i: usize) -> (usize, usize) {
+        let start = self.text_start + i * self.bucket_size;
+        (start, start + self.bucket_size)
+    }
+}
+/// 清零统计并开始剖析内核
+pub fn profile_start() -> isize {
+    sys_profile(true, core::ptr::null_mut())
+}
+/// 停止剖析内核，把采集到的统计结果写到 stats 中
+pub fn profile_stop(stats: &mut ProfileStats) -> isize {
+    sys_profile(false, stats as *mut ProfileStats)
+}
+
+pub const PR_SET_NO_NEW_PRIVS: usize = 38;
+pub const PR_GET_NO_NEW_PRIVS: usize = 39;
+pub fn prctl(option: usize, arg2: usize) -> isize {
+    sys_prctl(option, arg2)
+}
+/// 允许使用的系统调用号的位图，覆盖 0~2047 号系统调用
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SyscallFilter {
+    bits: [u64; 32],
+}
+impl SyscallFilter {
+    /// 禁止所有系统调用（exit 和 sigreturn 除外）
+    pub fn deny_all() -> Self {
+        Self { bits: [0; 32] }
+    }
+    /// 允许所有系统调用
+    pub fn allow_all() -> Self {
+        Self {
+            bits: [u64::MAX; 32],
+        }
+    }
+    pub fn allow(&mut self, id: usize) -> &mut Self {
+        self.bits[id / 64] |= 1 << (id % 64);
+        self
+    }
+    pub fn deny(&mut self, id: usize) -> &mut Self {
+        self.bits[id / 64] &= !(1 << (id % 64));
+        self
+    }
+}
+/// 需要先通过 prctl(PR_SET_NO_NEW_PRIVS, 1) 设置 no_new_privs
+pub fn seccomp(filter: &SyscallFilter) -> isize {
+    sys_seccomp(filter)
+}
+/// 登记之后进程在内存紧张时会收到 SIGMEMPRESSURE ，可以在处理例程中释放缓存
+pub fn register_lowmem() -> isize {
+    sys_register_lowmem()
+}
+/// 把当前进程的内存映射写入 buffer ，返回写入的字节数
+pub fn dump_maps(buffer: &mut [u8]) -> isize {
+    sys_dump_maps(buffer)
+}
+/// 把 tp 设为 TLS 块的起始地址 ptr 。程序有 TLS 段时内核在 exec 时已经为主线程建立了 TLS 块，否则 tp 初始为 0
+pub fn settls(ptr: usize) -> isize {
+    sys_settls(ptr)
+}
+/// 当前 tp 的值
+pub fn tls_pointer() -> usize {
+    let tp: usize;
+    unsafe {
+        core::arch::asm!("mv {}, tp", out(reg) tp);
+    }
+    tp
+}
 
 pub fn getpid() -> isize {
     sys_getpid()
 }
+pub fn setpgid(pid: usize, pgid: usize) -> isize {
+    sys_setpgid(pid, pgid)
+}
+pub fn getpgid(pid: usize) -> isize {
+    sys_getpgid(pid)
+}
+pub fn setsid() -> isize {
+    sys_setsid()
+}
+pub fn setuid(uid: u32) -> isize {
+    sys_setuid(uid)
+}
+pub fn getuid() -> isize {
+    sys_getuid()
+}
+// 先写出缓冲的输出，否则子进程会把它再输出一遍
 pub fn fork() -> isize {
+    flush_stdout();
     sys_fork()
 }
 pub fn exec(path: &str, args: &[*const u8]) -> isize {
-    sys_exec(path, args)
+    flush_stdout();
+    sys_exec(path, args, None)
+}
+/// 与 exec 相同，但新程序的环境变量为 envp 而不是继承当前进程的环境变量
+pub fn execve(path: &str, args: &[*const u8], envp: &[*const u8]) -> isize {
+    flush_stdout();
+    sys_exec(path, args, Some(envp))
+}
+/// 与 exec 相同，但 name 中不含 / 时依次在 PATH 中的每个目录下查找它，找不到时返回 -1
+pub fn execvp(name: &str, args: &[*const u8]) -> isize {
+    if name.contains('/') {
+        return exec(name, args);
+    }
+    let dirs = getenv("PATH").unwrap_or("/");
+    for dir in dirs.split(':').filter(|dir| !dir.is_empty()) {
+        let path = alloc::format!("{}/{}", dir.trim_end_matches('/'), name);
+        let fd = open(path.as_str(), OpenFlags::RDONLY);
+        if fd >= 0 {
+            close(fd as usize);
+            return exec(path.as_str(), args);
+        }
+    }
+    -1
 }
 // sys_waitpid 被封装成两个不同的 API:wait 和 waitpid
 // wait 表示等待任意一个子进程结束，根据 sys_waitpid 的约定它需要传的 pid 参数为 -1 
@@ -126,7 +994,7 @@ pub fn wait(exit_code: &mut i32) -> isize {
     loop {
         // 当 sys_waitpid 返回值为 -2 ，即要等待的子进程存在但它却尚未退出的时候，我们调用 yield_ 主动交出 CPU 使用权，
         // 待下次 CPU 使用权被内核交还给它的时候再次调用 sys_waitpid 查看要等待的子进程是否退出。这样做可以减小 CPU 资源的浪费。
-        match sys_waitpid(-1, exit_code as *mut _) {
+        match sys_waitpid(-1, exit_code as *mut _, 0) {
             -2 => {
                 yield_();
             }
@@ -139,7 +1007,7 @@ pub fn wait(exit_code: &mut i32) -> isize {
 // waitpid 则等待一个进程标识符的值为pid 的子进程结束
 pub fn waitpid(pid: usize, exit_code: &mut i32) -> isize {
     loop {
-        match sys_waitpid(pid as isize, exit_code as *mut _) {
+        match sys_waitpid(pid as isize, exit_code as *mut _, 0) {
             -2 => {
                 yield_();
             }
@@ -150,7 +1018,69 @@ pub fn waitpid(pid: usize, exit_code: &mut i32) -> isize {
 }
 
 pub fn waitpid_nb(pid: usize, exit_code: &mut i32) -> isize {
-    sys_waitpid(pid as isize, exit_code as *mut _)
+    sys_waitpid(pid as isize, exit_code as *mut _, 0)
+}
+
+// wait/waitpid 得到的是原始的退出码：被信号终止时为信号编号的相反数，与以负数正常退出无法区分。
+// waitpid_status 得到的则是与 Linux 编码相同的状态，用 WIFEXITED 等函数解析
+const WSTATUS: usize = 1 << 30;
+/// 与 waitpid 相同，但 pid 为 -1 时等待任意一个子进程，并把编码后的状态写入 status
+pub fn waitpid_status(pid: isize, status: &mut i32) -> isize {
+    loop {
+        match sys_waitpid(pid, status as *mut _, WSTATUS) {
+            -2 => {
+                yield_();
+            }
+            exit_pid => return exit_pid,
+        }
+    }
+}
+/// 子进程是否调用 exit 正常退出
+#[allow(non_snake_case)]
+pub fn WIFEXITED(status: i32) -> bool {
+    status & 0x7f == 0
+}
+/// 正常退出的子进程的退出码的低 8 位
+#[allow(non_snake_case)]
+pub fn WEXITSTATUS(status: i32) -> i32 {
+    (status >> 8) & 0xff
+}
+/// 子进程是否被信号终止
+#[allow(non_snake_case)]
+pub fn WIFSIGNALED(status: i32) -> bool {
+    status & 0x7f != 0
+}
+/// 终止子进程的信号
+#[allow(non_snake_case)]
+pub fn WTERMSIG(status: i32) -> i32 {
+    status & 0x7f
+}
+
+/// Exit code of a child created by `spawn`/`spawn_nowait` whose exec failed
+pub const SPAWN_EXEC_FAILED: i32 = 127;
+
+/// Run `path` with `args` (same format as `exec`) in a child process and return its pid,
+/// or -1 if fork fails
+pub fn spawn_nowait(path: &str, args: &[*const u8]) -> isize {
+    let pid = fork();
+    if pid == 0 {
+        exec(path, args);
+        // exec 失败时子进程必须退出，不能返回到调用者那里与父进程一起继续运行
+        exit(SPAWN_EXEC_FAILED);
+    }
+    pid
+}
+
+/// Run `path` with `args` in a child process and wait for it.
+/// Return the child's exit code, `SPAWN_EXEC_FAILED` if exec fails, or -1 if fork fails
+pub fn spawn(path: &str, args: &[*const u8]) -> i32 {
+    let pid = spawn_nowait(path, args);
+    if pid < 0 {
+        return -1;
+    }
+    let mut exit_code = 0;
+    waitpid(pid as usize, &mut exit_code);
+    exit_code
 }
 pub fn sleep(period_ms: usize) {
     let start = sys_get_time();
@@ -213,6 +1143,8 @@ pub const SIGWINCH: i32 = 28;
 pub const SIGIO: i32 = 29;
 pub const SIGPWR: i32 = 30;
 pub const SIGSYS: i32 = 31;
+/// 内存压力通知，与 SIGPWR 使用同一个编号
+pub const SIGMEMPRESSURE: i32 = SIGPWR;
 
 bitflags! {
     pub struct SignalFlags: i32 {
@@ -259,6 +1191,20 @@ pub fn kill(pid: usize, signum: i32) -> isize {
     sys_kill(pid, signum)
 }
 
+/// 功能：向当前进程自身发送一个信号，信号在系统调用返回之前就会被处理。
+/// 返回值：如果信号类型不存在则返回 -1 ，否则返回 0 。
+/// syscall ID: 131
+pub fn raise(signum: i32) -> isize {
+    let pid = getpid() as usize;
+    sys_tgkill(pid, pid, signum)
+}
+
+/// 以 SIGABRT 异常终止当前进程。即使进程为 SIGABRT 设置了处理例程，处理例程返回后进程仍然会退出
+pub fn abort() -> ! {
+    raise(SIGABRT);
+    exit(-SIGABRT);
+}
+
 /// 功能：为当前进程设置某种信号的处理函数，同时保存设置之前的处理函数。
 /// 进程可以通过 sigaction 系统调用捕获某种信号，即：当接收到某种信号的时候，暂停进程当前的执行，调用进程为该种信号提供的函数对信号进行处理，处理完成之后再恢复进程原先的执行
 /// 参数：signum 表示信号的编号，action 表示要设置成的处理函数的指针
@@ -278,6 +1224,12 @@ pub fn sigaction(
         old_action.map_or(core::ptr::null_mut(), |a| a),
     )
 }
+/// 功能：阻塞当前进程直到收到一个信号。
+/// 返回值：信号处理例程执行完毕后返回 -1 。
+/// syscall ID: 34
+pub fn pause() -> isize {
+    sys_pause()
+}
 /// 功能：设置当前进程的全局信号掩码。
 /// 参数：mask 表示当前进程要设置成的全局信号掩码，代表一个信号集合，
 /// 在集合中的信号始终被该进程屏蔽。
@@ -292,3 +1244,27 @@ pub fn sigprocmask(mask: u32) -> isize {
 pub fn sigreturn() -> isize {
     sys_sigreturn()
 }
+/// 从 signalfd 中读到的一个信号
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SignalfdSiginfo {
+    pub signo: u32,
+}
+/// 功能：创建一个 signalfd ，之后 mask 中的信号不再调用处理例程或者按照默认方式处理，而是通过 read_signalfd 读取。
+/// 有这样的信号时文件可读，可以和其他文件一起 poll 。
+/// 返回值：成功返回文件描述符，否则返回 -1 。
+/// syscall ID: 74
+pub fn signalfd(mask: SignalFlags) -> isize {
+    sys_signalfd(mask.bits() as u32)
+}
+/// 从 signalfd 中取走至多 infos.len() 个信号，没有信号时阻塞。返回读到的信号个数，等待时被其他信号打断返回 0
+pub fn read_signalfd(fd: usize, infos: &mut [SignalfdSiginfo]) -> isize {
+    let size = core::mem::size_of::<SignalfdSiginfo>();
+    let buf = unsafe {
+        core::slice::from_raw_parts_mut(infos.as_mut_ptr() as *mut u8, infos.len() * size)
+    };
+    match sys_read(fd, buf) {
+        len if len < 0 => len,
+        len => len / size as isize,
+    }
+}
@@ -1,10 +1,23 @@
 use core::arch::asm;
-use crate::SignalAction;
+use crate::{
+    Dirent, FsStat, IoVec, IrqStats, LoadAvg, PollFd, ProfileStats, Rusage, SignalAction, Stat,
+    Statx, SyscallFilter,
+};
 
 // 寄存器 a0~a6 保存系统调用的参数， a0 保存系统调用的返回值， a7 用来传递 syscall ID，这是因为所有的 syscall 都是通过 ecall 指令触发的，除了各输入参数之外我们还额外需要一个寄存器来保存要请求哪个系统调用。
 // 由于这超出了 Rust 语言的表达能力，我们需要在代码中使用内嵌汇编来完成参数/返回值绑定和 ecall 指令的插入：
 // 我们将所有的系统调用都封装成 syscall 函数，可以看到它支持传入 syscall ID 和 3 个参数（a0~a2寄存器中）。
 fn syscall(id: usize, args: [usize; 3]) -> isize {
+    syscall4(id, [args[0], args[1], args[2], 0])
+}
+
+// 少数系统调用需要第 4 个参数，通过 a3 寄存器传递
+fn syscall4(id: usize, args: [usize; 4]) -> isize {
+    syscall5(id, [args[0], args[1], args[2], args[3], 0])
+}
+
+// 第 5 个参数通过 a4 寄存器传递
+fn syscall5(id: usize, args: [usize; 5]) -> isize {
     let mut ret: isize;
     unsafe {
         // 我们曾经使用 global_asm! 宏来嵌入全局汇编代码，而这里的 asm! 宏可以将汇编代码嵌入到局部的函数上下文中。
@@ -15,6 +28,8 @@ fn syscall(id: usize, args: [usize; 3]) -> isize {
             inlateout("x10") args[0] => ret,
             in("x11") args[1],
             in("x12") args[2],
+            in("x13") args[3],
+            in("x14") args[4],
             in("x17") id
         );
     }
@@ -22,24 +37,89 @@ fn syscall(id: usize, args: [usize; 3]) -> isize {
 }
 
 // 于是 sys_write 和 sys_exit 只需将 syscall 进行包装：
-const SYSCALL_DUP: usize = 24;
-const SYSCALL_OPEN: usize = 56;
-const SYSCALL_CLOSE: usize = 57;
-const SYSCALL_PIPE: usize = 59;
-const SYSCALL_READ: usize = 63;
-const SYSCALL_WRITE: usize = 64;
-const SYSCALL_EXIT: usize = 93;
-const SYSCALL_YIELD: usize = 124;
-const SYSCALL_KILL: usize = 129;
-const SYSCALL_SIGACTION: usize = 134;
-const SYSCALL_SIGPROCMASK: usize = 135;
-const SYSCALL_SIGRETURN: usize = 139;
-const SYSCALL_GET_TIME: usize = 169;
-const SYSCALL_GETPID: usize = 172;
-const SYSCALL_FORK: usize = 220;
-const SYSCALL_EXEC: usize = 221;
-const SYSCALL_WAITPID: usize = 260;
-// const SYSCALL_SBRK: usize = 214;
+// 系统调用号也被导出给应用，用于构造 seccomp 过滤器
+pub const SYSCALL_GETCWD: usize = 17;
+pub const SYSCALL_DUP2: usize = 23;
+pub const SYSCALL_DUP: usize = 24;
+pub const SYSCALL_FCNTL: usize = 25;
+pub const SYSCALL_DUP3: usize = 26;
+pub const SYSCALL_IOCTL: usize = 29;
+pub const SYSCALL_FLOCK: usize = 32;
+pub const SYSCALL_MKNOD: usize = 33;
+pub const SYSCALL_UNLINK: usize = 35;
+pub const SYSCALL_RENAME: usize = 38;
+pub const SYSCALL_STATFS: usize = 43;
+pub const SYSCALL_TRUNCATE: usize = 45;
+pub const SYSCALL_FTRUNCATE: usize = 46;
+pub const SYSCALL_CHDIR: usize = 49;
+pub const SYSCALL_FCHDIR: usize = 50;
+pub const SYSCALL_FCHMOD: usize = 52;
+pub const SYSCALL_CHMOD: usize = 53;
+pub const SYSCALL_PAUSE: usize = 34;
+pub const SYSCALL_OPEN: usize = 56;
+pub const SYSCALL_CLOSE: usize = 57;
+pub const SYSCALL_PIPE: usize = 59;
+pub const SYSCALL_GETDENTS: usize = 61;
+pub const SYSCALL_READ: usize = 63;
+pub const SYSCALL_WRITE: usize = 64;
+pub const SYSCALL_PREADV: usize = 69;
+pub const SYSCALL_PWRITEV: usize = 70;
+pub const SYSCALL_SIGNALFD: usize = 74;
+pub const SYSCALL_VMSPLICE: usize = 75;
+pub const SYSCALL_FSTATAT: usize = 79;
+pub const SYSCALL_FSTAT: usize = 80;
+pub const SYSCALL_SYNC: usize = 81;
+pub const SYSCALL_TIMERFD_CREATE: usize = 85;
+pub const SYSCALL_TIMERFD_SETTIME: usize = 86;
+pub const SYSCALL_EXIT: usize = 93;
+pub const SYSCALL_SET_ROBUST_LIST: usize = 99;
+pub const SYSCALL_GET_ROBUST_LIST: usize = 100;
+pub const SYSCALL_SCHED_SETAFFINITY: usize = 122;
+pub const SYSCALL_SCHED_GETAFFINITY: usize = 123;
+pub const SYSCALL_YIELD: usize = 124;
+pub const SYSCALL_KILL: usize = 129;
+pub const SYSCALL_TGKILL: usize = 131;
+pub const SYSCALL_SIGACTION: usize = 134;
+pub const SYSCALL_SIGPROCMASK: usize = 135;
+pub const SYSCALL_SIGRETURN: usize = 139;
+pub const SYSCALL_SETUID: usize = 146;
+pub const SYSCALL_SETPGID: usize = 154;
+pub const SYSCALL_GETPGID: usize = 155;
+pub const SYSCALL_SETSID: usize = 157;
+pub const SYSCALL_GETRUSAGE: usize = 165;
+pub const SYSCALL_GETCPU: usize = 168;
+pub const SYSCALL_PRCTL: usize = 167;
+pub const SYSCALL_GET_TIME: usize = 169;
+pub const SYSCALL_GETPID: usize = 172;
+pub const SYSCALL_GETUID: usize = 174;
+pub const SYSCALL_SBRK: usize = 214;
+pub const SYSCALL_MUNMAP: usize = 215;
+pub const SYSCALL_FORK: usize = 220;
+pub const SYSCALL_EXEC: usize = 221;
+pub const SYSCALL_MMAP: usize = 222;
+pub const SYSCALL_FADVISE: usize = 223;
+pub const SYSCALL_MINCORE: usize = 232;
+pub const SYSCALL_WAITPID: usize = 260;
+pub const SYSCALL_SYNCFS: usize = 267;
+pub const SYSCALL_COPY_FILE_RANGE: usize = 285;
+pub const SYSCALL_STATX: usize = 291;
+pub const SYSCALL_READ_TIMEOUT: usize = 2000;
+pub const SYSCALL_HWCAP: usize = 2001;
+pub const SYSCALL_POLL: usize = 2002;
+pub const SYSCALL_IO_SETUP: usize = 2003;
+pub const SYSCALL_IO_SUBMIT: usize = 2004;
+pub const SYSCALL_SECCOMP: usize = 2005;
+pub const SYSCALL_REGISTER_LOWMEM: usize = 2006;
+pub const SYSCALL_DUMP_MAPS: usize = 2007;
+pub const SYSCALL_SETTLS: usize = 2008;
+pub const SYSCALL_LOADAVG: usize = 2009;
+pub const SYSCALL_NICE: usize = 2010;
+pub const SYSCALL_MKDIR: usize = 2011;
+pub const SYSCALL_RMDIR: usize = 2012;
+pub const SYSCALL_OPENAT: usize = 2013;
+pub const SYSCALL_CRASH: usize = 2014;
+pub const SYSCALL_IRQ_STATS: usize = 2015;
+pub const SYSCALL_PROFILE: usize = 2016;
 
 /// 功能：将进程中一个已经打开的文件复制一份并分配到一个新的文件描述符中。
 /// 参数：fd 表示进程中一个已经打开的文件的文件描述符。
@@ -50,10 +130,249 @@ pub fn sys_dup(fd: usize) -> isize {
     syscall(SYSCALL_DUP, [fd, 0, 0])
 }
 
+/// 功能：将已打开的文件 old_fd 复制到文件描述符 new_fd 上，new_fd 此前已打开时会先被关闭。
+/// 返回值：如果出现了错误则返回 -1，否则返回 new_fd 。old_fd 与 new_fd 相同时直接返回 new_fd 。
+/// syscall ID：23
+pub fn sys_dup2(old_fd: usize, new_fd: usize) -> isize {
+    syscall(SYSCALL_DUP2, [old_fd, new_fd, 0])
+}
+
+/// 功能：与 sys_dup2 相同，但可以通过 flags 为 new_fd 设置 close-on-exec 标志。
+/// 返回值：如果出现了错误则返回 -1，否则返回 new_fd 。old_fd 与 new_fd 相同时返回 -1 。
+/// syscall ID：26
+pub fn sys_dup3(old_fd: usize, new_fd: usize, flags: u32) -> isize {
+    syscall(SYSCALL_DUP3, [old_fd, new_fd, flags as usize])
+}
+
+/// 功能：读取（F_GETFL）或修改（F_SETFL）文件描述符的状态标志，目前只支持 O_NONBLOCK 。
+/// 返回值：F_GETFL 返回状态标志，F_SETFL 成功时返回 0 ；出现错误时返回 -1 。
+/// syscall ID：25
+pub fn sys_fcntl(fd: usize, cmd: usize, arg: usize) -> isize {
+    syscall(SYSCALL_FCNTL, [fd, cmd, arg])
+}
+
+/// 功能：对一个已打开的文件加上或解除建议性的文件锁。
+/// 参数：op 为 LOCK_SH/LOCK_EX/LOCK_UN 之一，加锁时可以或上 LOCK_NB 表示不阻塞。
+/// 返回值：如果出现了错误则返回 -1，否则返回 0 。
+/// syscall ID：32
+pub fn sys_flock(fd: usize, op: usize) -> isize {
+    syscall(SYSCALL_FLOCK, [fd, op, 0])
+}
+
+/// 功能：将一个以可写方式打开的文件截断或扩展到 len 字节。
+/// 返回值：如果出现了错误则返回 -1，否则返回 0 。
+/// syscall ID：46
+pub fn sys_ftruncate(fd: usize, len: usize) -> isize {
+    syscall(SYSCALL_FTRUNCATE, [fd, len, 0])
+}
+
+/// 功能：创建设备文件 path ，打开它时得到的是设备号为 dev 的设备。
+/// 返回值：如果出现了错误则返回 -1，否则返回 0 。
+/// syscall ID：33
+pub fn sys_mknod(path: &str, mode: u32, dev: u32) -> isize {
+    syscall(
+        SYSCALL_MKNOD,
+        [path.as_ptr() as usize, mode as usize, dev as usize],
+    )
+}
+
+/// 功能：删除文件 path ，它不能是目录。
+/// 返回值：成功返回 0 ；路径中的某一级不存在返回 -ENOENT ，某一级不是目录返回 -ENOTDIR ，path 是目录返回 -EISDIR 。
+/// syscall ID：35
+pub fn sys_unlink(path: &str) -> isize {
+    syscall(SYSCALL_UNLINK, [path.as_ptr() as usize, 0, 0])
+}
+
+/// 功能：将文件 old 重命名为 new ，new 已经存在时原子地将其替换。
+/// 返回值：如果出现了错误则返回 -1，否则返回 0 。
+/// syscall ID：38
+pub fn sys_rename(old: &str, new: &str) -> isize {
+    syscall(
+        SYSCALL_RENAME,
+        [old.as_ptr() as usize, new.as_ptr() as usize, 0],
+    )
+}
+
+/// 功能：创建目录 path ，它的父目录必须已经存在。
+/// 返回值：成功返回 0 ；父目录不存在返回 -ENOENT ，path 已经存在返回 -EEXIST ，
+/// 路径中的某一级不是目录返回 -ENOTDIR ，没有空闲的索引节点返回 -ENOSPC 。
+/// syscall ID：2011
+pub fn sys_mkdir(path: &str, mode: u32) -> isize {
+    syscall(SYSCALL_MKDIR, [path.as_ptr() as usize, mode as usize, 0])
+}
+
+/// 功能：删除空目录 path 。
+/// 返回值：成功返回 0 ；路径中的某一级不存在返回 -ENOENT ，某一级或者 path 本身不是目录返回 -ENOTDIR ，
+/// 目录不是空的返回 -ENOTEMPTY ，path 是根目录或者某个进程的工作目录返回 -EBUSY 。
+/// syscall ID：2012
+pub fn sys_rmdir(path: &str) -> isize {
+    syscall(SYSCALL_RMDIR, [path.as_ptr() as usize, 0, 0])
+}
+
+/// 功能：将路径 path 指向的文件截断或扩展到 len 字节。
+/// 返回值：如果出现了错误则返回 -1，否则返回 0 。
+/// syscall ID：45
+pub fn sys_truncate(path: &str, len: usize) -> isize {
+    syscall(SYSCALL_TRUNCATE, [path.as_ptr() as usize, len, 0])
+}
+
+/// 功能：将已打开文件 fd 的权限位修改为 mode 的低 12 位。
+/// 返回值：如果出现了错误则返回 -1，否则返回 0 。
+/// syscall ID：52
+pub fn sys_fchmod(fd: usize, mode: u32) -> isize {
+    syscall(SYSCALL_FCHMOD, [fd, mode as usize, 0])
+}
+
+/// 功能：将路径 path 指向的文件的权限位修改为 mode 的低 12 位。
+/// 返回值：如果出现了错误则返回 -1，否则返回 0 。
+/// syscall ID：53
+pub fn sys_chmod(path: &str, mode: u32) -> isize {
+    syscall(SYSCALL_CHMOD, [path.as_ptr() as usize, mode as usize, 0])
+}
+
+/// 功能：获取已打开文件 fd 的状态并保存到 buf 中。
+/// 返回值：如果出现了错误则返回 -1，否则返回 0 。
+/// syscall ID：80
+pub fn sys_fstat(fd: usize, buf: *mut Stat) -> isize {
+    syscall(SYSCALL_FSTAT, [fd, buf as usize, 0])
+}
+
+/// 功能：获取 path 的状态并保存到 buf 中，相对路径基于已打开的目录 dirfd 解析，dirfd 为 AT_FDCWD 时基于当前的工作目录。
+/// 返回值：如果出现了错误则返回 -1，否则返回 0 。
+/// syscall ID：79
+pub fn sys_fstatat(dirfd: isize, path: &str, buf: *mut Stat) -> isize {
+    syscall(
+        SYSCALL_FSTATAT,
+        [dirfd as usize, path.as_ptr() as usize, buf as usize],
+    )
+}
+
+/// 功能：获取已打开文件 fd 的扩展状态中 mask 指定的字段并保存到 buf 中，buf.mask 给出实际填写了的字段。
+/// 返回值：如果出现了错误则返回 -1，否则返回 0 。
+/// syscall ID：291
+pub fn sys_statx(fd: usize, mask: u32, buf: *mut Statx) -> isize {
+    syscall(SYSCALL_STATX, [fd, mask as usize, buf as usize])
+}
+
+/// 功能：创建一个没有启动的定时器文件。
+/// 返回值：定时器文件的文件描述符。
+/// syscall ID：85
+pub fn sys_timerfd_create() -> isize {
+    syscall(SYSCALL_TIMERFD_CREATE, [0, 0, 0])
+}
+
+/// 功能：让定时器文件 fd 在 value_ms 毫秒之后到期，之后每 interval_ms 毫秒到期一次（为 0 时只到期一次）；
+/// value_ms 为 0 时停止定时器。读定时器文件得到上一次读之后到期的次数。
+/// 返回值：成功返回 0 ；fd 不是定时器文件时返回 -1 。
+/// syscall ID：86
+pub fn sys_timerfd_settime(fd: usize, interval_ms: usize, value_ms: usize) -> isize {
+    syscall(SYSCALL_TIMERFD_SETTIME, [fd, interval_ms, value_ms])
+}
+
+/// 功能：从已打开的目录 fd 中继续读取至多 dirents.len() 个目录项，每一项都带有文件类型 d_type 。
+/// 返回值：如果出现了错误则返回 -1，否则返回读到的目录项个数，0 表示目录已经读完。
+/// syscall ID：61
+pub fn sys_getdents(fd: usize, dirents: &mut [Dirent]) -> isize {
+    syscall(
+        SYSCALL_GETDENTS,
+        [fd, dirents.as_mut_ptr() as usize, dirents.len()],
+    )
+}
+
+/// 功能：获取当前的工作目录，以 \0 结尾保存到 buf 中。
+/// 返回值：如果出现了错误则返回 -1，否则返回路径的长度（包括结尾的 \0）。
+/// syscall ID：17
+pub fn sys_getcwd(buf: &mut [u8]) -> isize {
+    syscall(SYSCALL_GETCWD, [buf.as_mut_ptr() as usize, buf.len(), 0])
+}
+
+/// 功能：将工作目录修改为 path 指向的目录。
+/// 返回值：如果出现了错误则返回 -1，否则返回 0 。
+/// syscall ID：49
+pub fn sys_chdir(path: &str) -> isize {
+    syscall(SYSCALL_CHDIR, [path.as_ptr() as usize, 0, 0])
+}
+
+/// 功能：将工作目录修改为已打开的目录 fd 。
+/// 返回值：如果出现了错误则返回 -1，否则返回 0 。
+/// syscall ID：50
+pub fn sys_fchdir(fd: usize) -> isize {
+    syscall(SYSCALL_FCHDIR, [fd, 0, 0])
+}
+
+/// 功能：获取路径 path 所在的文件系统的使用情况并保存到 buf 中。
+/// 返回值：如果出现了错误则返回 -1，否则返回 0 。
+/// syscall ID：43
+pub fn sys_statfs(path: &str, buf: *mut FsStat) -> isize {
+    syscall(SYSCALL_STATFS, [path.as_ptr() as usize, buf as usize, 0])
+}
+
+/// 功能：将所有文件系统中被修改过的块写回块设备，正被其他操作使用的块会被跳过。
+/// 返回值：总是返回 0 。
+/// syscall ID：81
+pub fn sys_sync() -> isize {
+    syscall(SYSCALL_SYNC, [0, 0, 0])
+}
+
+/// 功能：与 sync 相同，但只写回已打开文件 fd 所在的文件系统。
+/// 参数：fd 为文件描述符。
+/// 返回值：如果出现了错误则返回 -1，否则返回 0 。可能的错误原因是：fd 不合法或者不是文件系统中的文件。
+/// syscall ID：267
+pub fn sys_syncfs(fd: usize) -> isize {
+    syscall(SYSCALL_SYNCFS, [fd, 0, 0])
+}
+
+/// 功能：在内核中把 in_fd 从 in_off 处开始的最多 len 字节复制到 out_fd 的 out_off 处。
+/// 参数：in_off 和 out_off 为 None 时使用并移动文件描述符自己的偏移量，否则使用并更新给出的偏移量，文件描述符的偏移量不变。
+/// 返回值：实际复制的字节数，源文件提前到达末尾时少于 len ；出现错误时返回 -1 。
+/// syscall ID：285
+pub fn sys_copy_file_range(
+    in_fd: usize,
+    in_off: Option<&mut usize>,
+    out_fd: usize,
+    out_off: Option<&mut usize>,
+    len: usize,
+) -> isize {
+    let offset_ptr = |off: Option<&mut usize>| off.map_or(0, |off| off as *mut usize as usize);
+    syscall5(
+        SYSCALL_COPY_FILE_RANGE,
+        [in_fd, offset_ptr(in_off), out_fd, offset_ptr(out_off), len],
+    )
+}
+
+/// 功能：模拟断电：内核丢弃块缓存中所有还没有写回的块，然后立即关机。只有以 crash_test 特性编译的内核才提供。
+/// 返回值：不会返回。
+/// syscall ID：2014
+pub fn sys_crash() -> ! {
+    syscall(SYSCALL_CRASH, [0, 0, 0]);
+    panic!("sys_crash never returns!");
+}
+
+/// 功能：声明将以何种模式访问文件 fd 中 [offset, offset + len) 范围内的内容，len 为 0 表示一直到文件末尾。
+/// 返回值：如果出现了错误则返回 -1，否则返回 0 。
+/// syscall ID：223
+pub fn sys_fadvise(fd: usize, offset: usize, len: usize, advice: usize) -> isize {
+    syscall4(SYSCALL_FADVISE, [fd, offset, len, advice])
+}
+
+pub fn sys_ioctl(fd: usize, cmd: usize, arg: usize) -> isize {
+    syscall(SYSCALL_IOCTL, [fd, cmd, arg])
+}
+
 pub fn sys_open(path: &str, flags: u32) -> isize {
     syscall(SYSCALL_OPEN, [path.as_ptr() as usize, flags as usize, 0])
 }
 
+/// 功能：与 open 相同，但相对路径基于已打开的目录 dirfd 解析，dirfd 为 AT_FDCWD 时基于当前的工作目录；绝对路径忽略 dirfd 。
+/// 返回值：如果出现了错误则返回 -1，否则返回新的文件描述符。
+/// syscall ID：2013
+pub fn sys_openat(dirfd: isize, path: &str, flags: u32) -> isize {
+    syscall(
+        SYSCALL_OPENAT,
+        [dirfd as usize, path.as_ptr() as usize, flags as usize],
+    )
+}
+
 pub fn sys_close(fd: usize) -> isize {
     syscall(SYSCALL_CLOSE, [fd, 0, 0])
 }
@@ -69,6 +388,66 @@ pub fn sys_read(fd: usize, buffer: &mut [u8]) -> isize {
     )
 }
 
+/// 功能：与 sys_read 相同，但没有数据可读时最多等待 timeout_ms 毫秒。
+/// 返回值：实际读到的字节数，超时仍没有数据时返回 0 ；出现错误时返回 -1 ；等待期间收到信号时返回 -EINTR 。
+/// syscall ID：2000
+pub fn sys_read_timeout(fd: usize, buffer: &mut [u8], timeout_ms: usize) -> isize {
+    syscall4(
+        SYSCALL_READ_TIMEOUT,
+        [fd, buffer.as_mut_ptr() as usize, buffer.len(), timeout_ms],
+    )
+}
+
+/// 功能：查询当前可以使用的 ISA 扩展。
+/// 返回值：扩展字母 x 可用时第 x - 'a' 位为 1 的位图。
+/// syscall ID：2001
+pub fn sys_hwcap() -> isize {
+    syscall(SYSCALL_HWCAP, [0, 0, 0])
+}
+
+/// 功能：等待一组文件描述符中的任意一个就绪，最多等待 timeout_ms 毫秒，为负数时一直等待。
+/// 返回值：revents 不为 0 的项数，超时时返回 0 ；等待期间收到信号时返回 -EINTR 。
+/// syscall ID：2002
+pub fn sys_poll(fds: &mut [PollFd], timeout_ms: isize) -> isize {
+    syscall(
+        SYSCALL_POLL,
+        [fds.as_mut_ptr() as usize, fds.len(), timeout_ms as usize],
+    )
+}
+
+/// 功能：登记用于批量读写的提交队列 sq 和完成队列 cq ，每个队列有 entries 个表项，entries 为 0 时取消登记。
+/// 返回值：成功返回 0 ；entries 超过上限时返回 -1 。
+/// syscall ID：2003
+pub fn sys_io_setup(sq: usize, cq: usize, entries: usize) -> isize {
+    syscall(SYSCALL_IO_SETUP, [sq, cq, entries])
+}
+
+/// 功能：依次执行提交队列中的所有读写请求，并把结果放入完成队列，完成队列已满时停止。
+/// 返回值：执行了的请求数；没有登记队列时返回 -1 ；完成队列已满、一个请求也没有执行时返回 -EBUSY 。
+/// syscall ID：2004
+pub fn sys_io_submit() -> isize {
+    syscall(SYSCALL_IO_SUBMIT, [0, 0, 0])
+}
+
+/// 功能：创建一个 signalfd 。文件打开期间 mask 中的信号（SIGKILL 和 SIGSTOP 除外）不再按照通常的方式处理，
+/// 而是留着等待从这个文件中读取，读到的每条记录是一个 u32 的信号编号。
+/// 返回值：成功返回文件描述符；mask 不合法时返回 -1 。
+/// syscall ID：74
+pub fn sys_signalfd(mask: u32) -> isize {
+    syscall(SYSCALL_SIGNALFD, [mask as usize, 0, 0])
+}
+
+/// 功能：在缓冲区和管道之间移动数据。fd 为写端时移入管道，为读端时从管道中读出。
+/// 缓冲区中位于 mmap 内存中的整页直接移交物理页帧，移入管道之后这些页面的内容变回全零。
+/// 返回值：实际移动的字节数；出现错误时返回 -1 。
+/// syscall ID：75
+pub fn sys_vmsplice(fd: usize, buffer: &mut [u8]) -> isize {
+    syscall(
+        SYSCALL_VMSPLICE,
+        [fd, buffer.as_mut_ptr() as usize, buffer.len()],
+    )
+}
+
 pub fn sys_pipe(pipe: &mut [usize]) -> isize {
     syscall(SYSCALL_PIPE, [pipe.as_mut_ptr() as usize, 0, 0])
 }
@@ -79,6 +458,26 @@ pub fn sys_write(fd: usize, buffer: &[u8]) -> isize {
     syscall(SYSCALL_WRITE, [fd, buffer.as_ptr() as usize, buffer.len()])
 }
 
+/// 功能：从文件的 offset 处开始依次读满 iov 中的各个缓冲区，不改变文件描述符的偏移量。
+/// 返回值：实际读到的字节数，到达文件末尾时可能少于缓冲区的总长度；出现错误时返回 -1 。
+/// syscall ID：69
+pub fn sys_preadv(fd: usize, iov: &[IoVec], offset: usize) -> isize {
+    syscall4(
+        SYSCALL_PREADV,
+        [fd, iov.as_ptr() as usize, iov.len(), offset],
+    )
+}
+
+/// 功能：把 iov 中的各个缓冲区依次写入文件的 offset 处，不改变文件描述符的偏移量。
+/// 返回值：实际写入的字节数；出现错误时返回 -1 。
+/// syscall ID：70
+pub fn sys_pwritev(fd: usize, iov: &[IoVec], offset: usize) -> isize {
+    syscall4(
+        SYSCALL_PWRITEV,
+        [fd, iov.as_ptr() as usize, iov.len(), offset],
+    )
+}
+
 pub fn sys_exit(exit_code: i32) -> ! {
     syscall(SYSCALL_EXIT, [exit_code as usize, 0, 0]);
     panic!("sys_exit never returns!");
@@ -88,13 +487,45 @@ pub fn sys_yield() -> isize {
     syscall(SYSCALL_YIELD, [0, 0, 0])
 }
 
+/// 功能：登记当前进程的 robust futex 链表头。内核还没有 futex ，在实现 futex 之前不支持登记。
+/// 参数：head 为链表头的地址，len 为链表头的大小。
+/// 返回值：总是返回 -1 。
+/// syscall ID：99
+pub fn sys_set_robust_list(head: usize, len: usize) -> isize {
+    syscall(SYSCALL_SET_ROBUST_LIST, [head, len, 0])
+}
+
+/// 功能：获取进程 pid 登记的 robust futex 链表头。目前不支持登记，链表头的地址总是 0 。
+/// 参数：pid 为 0 时表示当前进程；链表头的地址和大小分别写入 head 和 len 。
+/// 返回值：成功返回 0 ；进程不存在时返回 -1 。
+/// syscall ID：100
+pub fn sys_get_robust_list(pid: usize, head: *mut usize, len: *mut usize) -> isize {
+    syscall(SYSCALL_GET_ROBUST_LIST, [pid, head as usize, len as usize])
+}
+
+/// 功能：设置进程 pid 的 CPU 亲和性掩码，第 i 位为 1 表示允许它在 hart i 上运行。
+/// 参数：pid 为 0 时表示当前进程；mask 不能为空，也不能包含不存在的 hart 。
+/// 返回值：成功返回 0 ；进程不存在或者 mask 不合法时返回 -1 。
+/// syscall ID：122
+pub fn sys_sched_setaffinity(pid: usize, mask: usize) -> isize {
+    syscall(SYSCALL_SCHED_SETAFFINITY, [pid, mask, 0])
+}
+
+/// 功能：获取进程 pid 的 CPU 亲和性掩码。
+/// 参数：pid 为 0 时表示当前进程。
+/// 返回值：进程的 CPU 亲和性掩码；进程不存在时返回 -1 。
+/// syscall ID：123
+pub fn sys_sched_getaffinity(pid: usize) -> isize {
+    syscall(SYSCALL_SCHED_GETAFFINITY, [pid, 0, 0])
+}
+
 pub fn sys_get_time() -> isize {
     syscall(SYSCALL_GET_TIME, [0, 0, 0])
 }
 
-// pub fn sys_sbrk(size: i32) -> isize {
-//     syscall(SYSCALL_SBRK, [size as usize, 0, 0])
-// }
+pub fn sys_sbrk(size: i32) -> isize {
+    syscall(SYSCALL_SBRK, [size as usize, 0, 0])
+}
 
 pub fn sys_getpid() -> isize {
     syscall(SYSCALL_GETPID, [0, 0, 0])
@@ -106,21 +537,27 @@ pub fn sys_fork() -> isize {
 
 // 为了支持命令行参数， sys_exec 的系统调用接口需要发生变化：
 // 参数多出了一个 args 数组，数组中的每个元素都是一个命令行参数字符串的起始地址。由于我们是以引用的形式传递这个数组，实际传递给内核的是这个数组的起始地址
-pub fn sys_exec(path: &str, args: &[*const u8]) -> isize {
+// envp 的格式与 args 相同，为 None 时新程序继承当前进程的环境变量
+pub fn sys_exec(path: &str, args: &[*const u8], envp: Option<&[*const u8]>) -> isize {
+    let envp = envp.map_or(0, |envp| envp.as_ptr() as usize);
     syscall(
         SYSCALL_EXEC,
-        [path.as_ptr() as usize, args.as_ptr() as usize, 0],
+        [path.as_ptr() as usize, args.as_ptr() as usize, envp],
     )
 }
 
-pub fn sys_waitpid(pid: isize, exit_code: *mut i32) -> isize {
-    syscall(SYSCALL_WAITPID, [pid as usize, exit_code as usize, 0])
+pub fn sys_waitpid(pid: isize, exit_code: *mut i32, options: usize) -> isize {
+    syscall(SYSCALL_WAITPID, [pid as usize, exit_code as usize, options])
 }
 
 pub fn sys_kill(pid: usize, signal: i32) -> isize {
     syscall(SYSCALL_KILL, [pid, signal as usize, 0])
 }
 
+pub fn sys_tgkill(tgid: usize, tid: usize, signal: i32) -> isize {
+    syscall(SYSCALL_TGKILL, [tgid, tid, signal as usize])
+}
+
 pub fn sys_sigaction(
     signum: i32,
     action: *const SignalAction,
@@ -142,10 +579,132 @@ pub fn sys_sigaction(
     */
 }
 
+pub fn sys_pause() -> isize {
+    syscall(SYSCALL_PAUSE, [0, 0, 0])
+}
+
 pub fn sys_sigprocmask(mask: u32) -> isize {
     syscall(SYSCALL_SIGPROCMASK, [mask as usize, 0, 0])
 }
 
 pub fn sys_sigreturn() -> isize {
     syscall(SYSCALL_SIGRETURN, [0, 0, 0])
-}
\ No newline at end of file
+}
+
+pub fn sys_setpgid(pid: usize, pgid: usize) -> isize {
+    syscall(SYSCALL_SETPGID, [pid, pgid, 0])
+}
+
+pub fn sys_getpgid(pid: usize) -> isize {
+    syscall(SYSCALL_GETPGID, [pid, 0, 0])
+}
+
+pub fn sys_setsid() -> isize {
+    syscall(SYSCALL_SETSID, [0, 0, 0])
+}
+
+pub fn sys_setuid(uid: u32) -> isize {
+    syscall(SYSCALL_SETUID, [uid as usize, 0, 0])
+}
+
+pub fn sys_getuid() -> isize {
+    syscall(SYSCALL_GETUID, [0, 0, 0])
+}
+
+pub fn sys_getrusage(who: isize, usage: *mut Rusage) -> isize {
+    syscall(SYSCALL_GETRUSAGE, [who as usize, usage as usize, 0])
+}
+
+/// 功能：获取当前进程正在运行的 hart 编号和 NUMA 节点编号，节点编号总是 0 。
+/// 参数：cpu 和 node 为保存结果的地址，为空指针时不写入对应的值。
+/// 返回值：0 。
+/// syscall ID：168
+pub fn sys_getcpu(cpu: *mut u32, node: *mut u32) -> isize {
+    syscall(SYSCALL_GETCPU, [cpu as usize, node as usize, 0])
+}
+
+/// 功能：设置或查询进程的属性，目前只支持 no_new_privs 标志。
+/// 返回值：设置成功时返回 0 ，查询时返回标志的值；出现错误时返回 -1 。
+/// syscall ID：167
+pub fn sys_prctl(option: usize, arg2: usize) -> isize {
+    syscall(SYSCALL_PRCTL, [option, arg2, 0])
+}
+
+/// 功能：限制当前进程只能使用 filter 中允许的系统调用，与已有的过滤器取交集，调用被禁止的系统调用时收到 SIGSYS 。
+/// 返回值：成功返回 0 ；没有设置 no_new_privs 时返回 -1 。
+/// syscall ID：2005
+pub fn sys_seccomp(filter: &SyscallFilter) -> isize {
+    syscall(SYSCALL_SECCOMP, [filter as *const _ as usize, 0, 0])
+}
+
+/// 功能：登记当前进程，之后每当空闲的物理内存降到水位线以下时收到 SIGMEMPRESSURE 。
+/// 返回值：总是返回 0 。
+/// syscall ID：2006
+pub fn sys_register_lowmem() -> isize {
+    syscall(SYSCALL_REGISTER_LOWMEM, [0, 0, 0])
+}
+
+/// 功能：将当前进程地址空间中各个逻辑段的起止地址、权限和名字以文本形式写入 buffer ，每个逻辑段一行。
+/// 返回值：成功返回写入的字节数；buffer 放不下全部内容则返回 -1 。
+/// syscall ID：2007
+pub fn sys_dump_maps(buffer: &mut [u8]) -> isize {
+    syscall(
+        SYSCALL_DUMP_MAPS,
+        [buffer.as_mut_ptr() as usize, buffer.len(), 0],
+    )
+}
+
+/// 功能：将当前进程的线程指针 tp 设为 ptr ，此后 tp 始终指向 ptr 处的 TLS 块。
+/// 返回值：0 。
+/// syscall ID：2008
+pub fn sys_settls(ptr: usize) -> isize {
+    syscall(SYSCALL_SETTLS, [ptr, 0, 0])
+}
+
+/// 功能：获取系统在最近 1 秒、5 秒和 15 秒内的平均负载，以及所有 hart 运行任务和空闲的总时间。
+/// 参数：load 为保存结果的 LoadAvg 结构体的地址。
+/// 返回值：0 。
+/// syscall ID：2009
+pub fn sys_loadavg(load: *mut LoadAvg) -> isize {
+    syscall(SYSCALL_LOADAVG, [load as usize, 0, 0])
+}
+
+/// 功能：获取时钟中断的延迟统计，即从设定的触发时刻到内核开始处理这个中断所经过的时间。
+/// 参数：stats 为保存结果的 IrqStats 结构体的地址；reset 为 true 时在读出之后清零统计。
+/// 返回值：0 。
+/// syscall ID：2015
+pub fn sys_irq_stats(stats: *mut IrqStats, reset: bool) -> isize {
+    syscall(SYSCALL_IRQ_STATS, [stats as usize, reset as usize, 0])
+}
+
+/// 功能：控制内核性能剖析，采样期间每次时钟中断记录一次被打断的位置。
+/// 参数：enable 为 false 时停止采样，为 true 时清零统计并开始采样；stats 不为空时，
+/// 把这次调用之前采集到的统计结果写到 stats 指向的 ProfileStats 结构体中。
+/// 返回值：0 。
+/// syscall ID：2016
+pub fn sys_profile(enable: bool, stats: *mut ProfileStats) -> isize {
+    syscall(SYSCALL_PROFILE, [enable as usize, stats as usize, 0])
+}
+
+/// 功能：将当前进程的 nice 值增加 delta ，结果限制在 -20 到 19 之间。nice 值越大，进程分到的处理器时间越少。
+/// 返回值：调整之后的 nice 值。
+/// syscall ID：2010
+pub fn sys_nice(delta: isize) -> isize {
+    syscall(SYSCALL_NICE, [delta as usize, 0, 0])
+}
+
+pub fn sys_mmap(start: usize, len: usize, prot: usize, name: Option<&str>) -> isize {
+    let name = name.map_or(0, |name| name.as_ptr() as usize);
+    syscall4(SYSCALL_MMAP, [start, len, prot, name])
+}
+
+pub fn sys_munmap(start: usize, len: usize) -> isize {
+    syscall(SYSCALL_MUNMAP, [start, len, 0])
+}
+
+/// 功能：查询 [start, start + len) 中的每个页面是否驻留在内存中，结果写入 vec ，每个页面一个字节。
+/// 返回值：成功返回 0 ；start 没有按页对齐或者区间中有页面不属于任何映射时返回 -1 。
+/// syscall ID：232
+pub fn sys_mincore(start: usize, len: usize, vec: &mut [u8]) -> isize {
+    syscall(SYSCALL_MINCORE, [start, len, vec.as_mut_ptr() as usize])
+}